@@ -0,0 +1,2 @@
+//! Compile tests for `wire::tool` and `wire::get_tool!`, in a crate of their
+//! own so `wire` is the only dependency in scope. See `tests/ui.rs`.
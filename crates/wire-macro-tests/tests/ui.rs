@@ -0,0 +1,5 @@
+#[test]
+fn macros_expand_with_only_wire_as_a_dependency() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/*.rs");
+}
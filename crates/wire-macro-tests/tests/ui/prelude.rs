@@ -0,0 +1,11 @@
+use wire::prelude::*;
+
+#[tool(description = "Echoes its input")]
+fn echo(text: String) -> String {
+    text
+}
+
+fn main() {
+    let tool: Tool = get_tool!(echo);
+    assert_eq!(tool.parameters["properties"]["text"]["type"], "string");
+}
@@ -0,0 +1,25 @@
+use wire::{get_tool, tool};
+
+/// Adds two numbers.
+#[tool(description = "Adds two numbers", strict)]
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[tool(description = "Forgets everything")]
+fn reset() {}
+
+fn main() {
+    let tool = get_tool!(add);
+    assert_eq!(tool.name, "add");
+    assert_eq!(tool.description, "Adds two numbers");
+    assert!(tool.strict);
+    assert_eq!(tool.parameters["properties"]["b"]["type"], "integer");
+    let sum = tool.function.call(r#"{"a": 2, "b": 3}"#.parse().unwrap());
+    assert_eq!(sum.to_string(), "5");
+
+    let tool = get_tool!(reset);
+    assert!(!tool.strict);
+    let status = tool.function.call(r#"{}"#.parse().unwrap());
+    assert_eq!(status["status"], "success");
+}
@@ -1,9 +1,23 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use serde_json::json;
-use std::path::Path;
+use std::path::PathBuf;
 use syn::{FnArg, ItemFn, Lit, Meta, Pat, ReturnType, Type, parse_macro_input};
 
+/// Where `#[tool]` leaves a function's metadata for `get_tool!`: the
+/// crate's `OUT_DIR`, or a directory of its own under the system temp dir
+/// when it has no build script.
+fn metadata_dir() -> PathBuf {
+    if let Ok(out_dir) = std::env::var("OUT_DIR") {
+        return PathBuf::from(out_dir);
+    }
+
+    let package = std::env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".to_string());
+    let dir = std::env::temp_dir().join("wire-tools").join(package);
+    std::fs::create_dir_all(&dir).expect("Failed to create the tool metadata directory");
+    dir
+}
+
 #[proc_macro_attribute]
 pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(item as ItemFn);
@@ -11,49 +25,52 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let attrs = parse_macro_input!(attr as syn::AttributeArgs);
     let description = attrs.iter().find_map(|arg| {
-        if let syn::NestedMeta::Meta(Meta::NameValue(nv)) = arg {
-            if nv.path.is_ident("description") {
-                if let Lit::Str(lit_str) = &nv.lit {
-                    return Some(lit_str.value());
-                }
-            }
+        if let syn::NestedMeta::Meta(Meta::NameValue(nv)) = arg
+            && nv.path.is_ident("description")
+            && let Lit::Str(lit_str) = &nv.lit
+        {
+            return Some(lit_str.value());
         }
         None
     }).expect("The #[tool] attribute requires a `description` argument, e.g., #[tool(description = \"...\")]");
 
+    let strict = attrs.iter().any(
+        |arg| matches!(arg, syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("strict")),
+    );
+
     let mut properties = serde_json::Map::new();
     for arg in &input_fn.sig.inputs {
-        if let FnArg::Typed(pat_type) = arg {
-            if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                let arg_name = pat_ident.ident.to_string();
-                let arg_type_str = if let Type::Path(type_path) = &*pat_type.ty {
-                    let type_name = type_path.path.segments.last().unwrap().ident.to_string();
-                    match type_name.as_str() {
-                        "String" => "string",
-                        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" => "integer",
-                        "f32" | "f64" => "number",
-                        "bool" => "boolean",
-                        _ => "object", // Default for complex types
-                    }
-                } else {
-                    "object"
-                };
-                properties.insert(arg_name, json!({ "type": arg_type_str }));
-            }
+        if let FnArg::Typed(pat_type) = arg
+            && let Pat::Ident(pat_ident) = &*pat_type.pat
+        {
+            let arg_name = pat_ident.ident.to_string();
+            let arg_type_str = if let Type::Path(type_path) = &*pat_type.ty {
+                let type_name = type_path.path.segments.last().unwrap().ident.to_string();
+                match type_name.as_str() {
+                    "String" => "string",
+                    "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" => "integer",
+                    "f32" | "f64" => "number",
+                    "bool" => "boolean",
+                    _ => "object", // Default for complex types
+                }
+            } else {
+                "object"
+            };
+            properties.insert(arg_name, json!({ "type": arg_type_str }));
         }
     }
 
     let metadata = json!({
         "name": fn_name.to_string(),
         "description": description,
+        "strict": strict,
         "parameters": {
             "type": "object",
             "properties": properties,
         }
     });
 
-    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR environment variable not set");
-    let metadata_path = Path::new(&out_dir).join(format!("{}.json", fn_name));
+    let metadata_path = metadata_dir().join(format!("{}.json", fn_name));
     std::fs::write(
         metadata_path,
         serde_json::to_string_pretty(&metadata).unwrap(),
@@ -67,10 +84,10 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
         .inputs
         .iter()
         .filter_map(|arg| {
-            if let FnArg::Typed(pat_type) = arg {
-                if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                    return Some((pat_ident.ident.clone(), pat_type.ty.clone()));
-                }
+            if let FnArg::Typed(pat_type) = arg
+                && let Pat::Ident(pat_ident) = &*pat_type.pat
+            {
+                return Some((pat_ident.ident.clone(), pat_type.ty.clone()));
             }
             None
         })
@@ -80,10 +97,10 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
         let name_str = name.to_string();
         quote! {
             let #name: #ty = if args[#name_str].is_null() {
-                Default::default()
+                ::core::default::Default::default()
             } else {
-                serde_json::from_value(args[#name_str].clone())
-                    .unwrap_or_else(|e| panic!("Failed to deserialize argument '{}' for tool '{}': {}", #name_str, stringify!(#fn_name), e))
+                ::wire::__private::serde_json::from_value(args[#name_str].clone())
+                    .unwrap_or_else(|e| ::core::panic!("Failed to deserialize argument '{}' for tool '{}': {}", #name_str, stringify!(#fn_name), e))
             };
         }
     });
@@ -93,18 +110,20 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
     let return_handling = match &input_fn.sig.output {
         ReturnType::Default => quote! {
             #fn_name(#(#arg_names),*);
-            serde_json::json!({ "status": "success" })
+            ::wire::__private::serde_json::json!({ "status": "success" })
         },
         ReturnType::Type(_, ty) => quote! {
             let result: #ty = #fn_name(#(#arg_names),*);
-            serde_json::to_value(result).expect("Failed to serialize return value")
+            ::wire::__private::serde_json::to_value(result).expect("Failed to serialize return value")
         },
     };
 
     quote! {
         #input_fn
 
-        pub fn #wrapper_name(args: serde_json::Value) -> serde_json::Value {
+        pub fn #wrapper_name(
+            args: ::wire::__private::serde_json::Value,
+        ) -> ::wire::__private::serde_json::Value {
             #(#deserialization_lines)*
             #return_handling
         }
@@ -118,7 +137,7 @@ pub fn get_tool(input: TokenStream) -> TokenStream {
     let fn_name_str = fn_name_ident.to_string();
 
     // Construct the path to the metadata file generated by the #[tool] macro
-    let metadata_file_path = format!("{}/{}.json", std::env::var("OUT_DIR").unwrap(), fn_name_str);
+    let metadata_file_path = metadata_dir().join(format!("{}.json", fn_name_str));
 
     // Read the metadata at compile time. Panics if the file doesn't exist,
     // which is the desired behavior (e.g., you forgot to add #[tool]).
@@ -132,8 +151,9 @@ pub fn get_tool(input: TokenStream) -> TokenStream {
     // The `function` field points to the wrapper you would define in your main code.
     quote! {
         {
-            // Assume Tool and ToolWrapper structs are defined in the calling crate
-            Tool {
+            use ::wire::__private::serde_json;
+
+            ::wire::__private::Tool {
                 name: #fn_name_str.to_string(),
                 function_type: "function".to_string(),
                 description: {
@@ -144,7 +164,11 @@ pub fn get_tool(input: TokenStream) -> TokenStream {
                     let data: serde_json::Value = serde_json::from_str(#metadata_str).unwrap();
                     data["parameters"].clone()
                 },
-                function: Box::new(ToolWrapper(#wrapper_name)),
+                strict: {
+                    let data: serde_json::Value = serde_json::from_str(#metadata_str).unwrap();
+                    data["strict"].as_bool().unwrap_or(false)
+                },
+                function: ::std::boxed::Box::new(::wire::__private::ToolWrapper(#wrapper_name)),
             }
         }
     }
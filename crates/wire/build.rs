@@ -0,0 +1,15 @@
+//! Sets the `any_provider` cfg when at least one provider with its own wire
+//! format is enabled. The helpers those providers share are dead code
+//! without one, and the groq-style presets all enable `openai`.
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(any_provider)");
+
+    let providers = ["OPENAI", "ANTHROPIC", "GEMINI", "OLLAMA", "COHERE"];
+    if providers
+        .iter()
+        .any(|provider| std::env::var_os(format!("CARGO_FEATURE_{}", provider)).is_some())
+    {
+        println!("cargo::rustc-cfg=any_provider");
+    }
+}
@@ -0,0 +1,70 @@
+//! Flag parsing and client setup shared by the examples.
+//!
+//! Every example takes `--model <name>` (any name `wire::new_client`
+//! accepts), `--prompt <text>` and `--offline`. Without the model's API key in
+//! the environment, or with `--offline`, the client talks to a local mock
+//! server instead of the provider.
+
+use wire::mock::{doctest_client, DoctestCall, MockLLMServer};
+use wire::prelude::*;
+
+pub struct Args {
+    pub model: String,
+    pub prompt: String,
+    pub offline: bool,
+}
+
+impl Args {
+    /// Parse the command line, printing `usage` and exiting on `--help` or
+    /// anything unrecognised.
+    pub fn parse(usage: &str, default_prompt: &str) -> Self {
+        let mut args = Args {
+            model: "gpt-4o-mini".to_string(),
+            prompt: default_prompt.to_string(),
+            offline: false,
+        };
+
+        let mut argv = std::env::args().skip(1);
+        while let Some(flag) = argv.next() {
+            match flag.as_str() {
+                "-m" | "--model" => args.model = argv.next().unwrap_or_else(|| exit(usage)),
+                "-p" | "--prompt" => args.prompt = argv.next().unwrap_or_else(|| exit(usage)),
+                "--offline" => args.offline = true,
+                _ => exit(usage),
+            }
+        }
+
+        args
+    }
+}
+
+fn exit(usage: &str) -> ! {
+    eprintln!(
+        "{}\n\nOptions:\n  -m, --model <name>   model to use [default: gpt-4o-mini]\n  -p, --prompt <text>  what to ask\n      --offline        answer from a mock server even if an API key is set",
+        usage
+    );
+    std::process::exit(2)
+}
+
+/// A client for `args.model`, and the mock server answering `call` when
+/// running offline. Keep the server alive while the client is in use.
+pub async fn connect(
+    args: &Args,
+    call: DoctestCall,
+) -> Result<(Box<dyn PromptCore>, Option<MockLLMServer>), String> {
+    let api = API::from_model(&args.model)?;
+    let key_missing = api
+        .provider()
+        .key_var()
+        .is_some_and(|var| std::env::var(var).is_err());
+
+    // The built-in models need no server at all
+    if !(args.offline || key_missing) || api.provider() == Provider::Wire {
+        return Ok((new_client(&args.model).map_err(|e| e.to_string())?, None));
+    }
+
+    eprintln!("(offline: answering from a mock server)");
+    let (server, options) = doctest_client(&args.model, call).await;
+    let client = new_client_with_options(&args.model, options).map_err(|e| e.to_string())?;
+    Ok((client, Some(server)))
+}
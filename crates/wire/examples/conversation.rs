@@ -0,0 +1,47 @@
+//! Keep a conversation going: each reply joins the history sent with the
+//! next question.
+//!
+//! ```text
+//! cargo run --example conversation -- --prompt "Plan a day in Lisbon"
+//! ```
+
+mod common;
+
+use common::{connect, Args};
+use wire::mock::DoctestCall;
+use wire::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse(
+        "Usage: conversation [OPTIONS]",
+        "What is the weather in Paris?",
+    );
+    let (client, _server) = connect(&args, DoctestCall::Prompt).await?;
+
+    let questions = [
+        args.prompt.as_str(),
+        "And tomorrow?",
+        "Sum that up in three words.",
+    ];
+    let mut history: Vec<Message> = Vec::new();
+
+    for question in questions {
+        history.push(
+            client
+                .new_message(question.to_string())
+                .message_type(MessageType::User)
+                .build(),
+        );
+
+        let reply = client
+            .prompt("You are a concise assistant.".to_string(), history.clone())
+            .await?;
+        println!("> {}\n{}\n", question, reply.content);
+        eprintln!("({} messages sent)", history.len());
+
+        history.push(reply);
+    }
+
+    Ok(())
+}
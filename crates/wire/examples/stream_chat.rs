@@ -0,0 +1,51 @@
+//! Stream a reply to the terminal as it arrives.
+//!
+//! ```text
+//! cargo run --example stream_chat -- --model claude-3-5-haiku-20241022 --prompt "Tell me a joke"
+//! ```
+
+mod common;
+
+use std::io::Write;
+
+use common::{connect, Args};
+use wire::mock::DoctestCall;
+use wire::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse(
+        "Usage: stream_chat [OPTIONS]",
+        "What is the weather in Paris?",
+    );
+    let (client, _server) = connect(&args, DoctestCall::Stream).await?;
+
+    let question = client
+        .new_message(args.prompt.clone())
+        .message_type(MessageType::User)
+        .build();
+
+    // Deltas arrive on `rx` while `prompt_stream` runs, so drive both at once
+    let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+    let print = async {
+        while let Some(delta) = rx.recv().await {
+            print!("{}", delta);
+            std::io::stdout().flush().ok();
+        }
+        println!();
+    };
+    let (reply, ()) = tokio::join!(
+        client.prompt_stream(vec![question], "Answer briefly.".to_string(), tx),
+        print
+    );
+    let reply = reply?;
+
+    if let Some(latency) = reply.metadata.latency {
+        eprintln!(
+            "({} deltas, first after {:?}, {:?} in all)",
+            latency.deltas, latency.ttft, latency.total
+        );
+    }
+
+    Ok(())
+}
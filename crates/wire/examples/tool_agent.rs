@@ -0,0 +1,79 @@
+//! Give the model a tool and let it call it until it has an answer.
+//!
+//! ```text
+//! cargo run --example tool_agent -- --prompt "Should I bring an umbrella in Oslo?"
+//! ```
+
+mod common;
+
+use common::{connect, Args};
+use wire::mock::DoctestCall;
+use wire::prelude::*;
+
+/// A stand-in for a real weather lookup.
+fn get_weather(args: serde_json::Value) -> serde_json::Value {
+    let city = args["city"].as_str().unwrap_or("nowhere");
+    serde_json::json!({ "city": city, "forecast": "sunny", "celsius": 21 })
+}
+
+fn weather_tool() -> Tool {
+    let spec = ToolSpec {
+        name: "get_weather".to_string(),
+        description: "Current weather for a city.".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"],
+        }),
+        strict: false,
+    };
+
+    Tool::from_spec(spec, ToolWrapper(get_weather))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse(
+        "Usage: tool_agent [OPTIONS]",
+        "What is the weather in Paris?",
+    );
+    let (client, _server) = connect(&args, DoctestCall::Tools).await?;
+    let tools = client.tools().ok_or("this model can't call tools")?;
+
+    let question = client
+        .new_message(args.prompt.clone())
+        .message_type(MessageType::User)
+        .build();
+
+    // Status lines ("calling tool get_weather...") arrive while the loop runs
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    let status = async {
+        while let Some(line) = rx.recv().await {
+            eprintln!("[{}]", line);
+        }
+    };
+    let (history, ()) = tokio::join!(
+        tools.prompt_with_tools_with_status(
+            tx,
+            "Use the tools you have.",
+            vec![question],
+            vec![weather_tool()],
+        ),
+        status
+    );
+
+    for message in history? {
+        match message.message_type {
+            MessageType::FunctionCall => {
+                for call in message.tool_calls.unwrap_or_default() {
+                    println!("call   {}({})", call.function.name, call.function.arguments);
+                }
+            }
+            MessageType::FunctionCallOutput => println!("result {}", message.content),
+            MessageType::Assistant => println!("answer {}", message.content),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Put a client behind tower middleware: a few questions at once, at most two
+//! in flight, each given 30 seconds.
+//!
+//! ```text
+//! cargo run --example tower_stack --features tower -- --model gpt-4o-mini
+//! ```
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{connect, Args};
+use tower::{Service, ServiceBuilder, ServiceExt};
+use wire::mock::DoctestCall;
+use wire::prelude::*;
+use wire::service::PromptRequest;
+
+#[tokio::main]
+async fn main() -> Result<(), tower::BoxError> {
+    let args = Args::parse(
+        "Usage: tower_stack [OPTIONS]",
+        "What is the weather in Paris?",
+    );
+    let (client, _server) = connect(&args, DoctestCall::Prompt).await?;
+    let client: Arc<dyn PromptCore> = Arc::from(client);
+
+    let service = ServiceBuilder::new()
+        .concurrency_limit(2)
+        .timeout(Duration::from_secs(30))
+        .service(client.clone());
+
+    let questions = [
+        args.prompt.clone(),
+        "And in Lyon?".to_string(),
+        "And in Marseille?".to_string(),
+    ];
+    let mut calls = Vec::new();
+    for question in questions {
+        let history = vec![client
+            .new_message(question.clone())
+            .message_type(MessageType::User)
+            .build()];
+        // Clones share the concurrency limit
+        let mut service = service.clone();
+        calls.push(tokio::spawn(async move {
+            let response = service
+                .ready()
+                .await?
+                .call(PromptRequest::new("Answer briefly.", history))
+                .await?;
+            Ok::<_, tower::BoxError>((question, response))
+        }));
+    }
+
+    for call in calls {
+        let (question, response) = call.await??;
+        let answer = response.reply().map_or("", |reply| reply.content.as_str());
+        println!("{}\n  {}", question, answer);
+    }
+
+    Ok(())
+}
@@ -1,12 +1,41 @@
-use native_tls::TlsStream;
-use std::collections::HashMap;
-use std::io::{BufRead, Write};
-use std::net::TcpStream;
-
-use crate::api::{AnthropicModel, Prompt};
-use crate::config::{ClientOptions, Endpoint, Scheme};
-use crate::network_common::{connect_https, unescape};
-use crate::types::{FunctionCall, Message, MessageBuilder, MessageType, Tool};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::api::{
+    event_stream, role_for, AnthropicModel, EventStream, PromptCore, Provider, RawTransport,
+    ToolCapable,
+};
+use crate::clock::Clock;
+use crate::compression::ACCEPT_ENCODING;
+use crate::config::{
+    ClientOptions, Endpoint, MaxTokensBehavior, PromptOptions, Scheme, ToolChoice,
+};
+use crate::content_filter::filter_outbound;
+use crate::credentials::Credentials;
+use crate::error::WireError;
+use crate::event_log::{emit, prepare_stream, send_logged, EventLog, WireEvent};
+use crate::metrics::{report_metrics, warn_on_tool_schema_size, LatencyRecorder};
+use crate::moderation::check_incoming;
+use crate::network_common::{
+    cancellable, open_stream, parse_event, unescape, ByteStream, ParsedEvent,
+};
+use crate::payload::{self, json_body};
+use crate::rate_limit::estimate_tokens;
+use crate::retry::with_retries;
+use crate::sanitize::{check_outbound, sanitize_outbound};
+use crate::snapshot::RequestSnapshot;
+use crate::tool_loop::{
+    report_interim_text, resume_pending_calls, run_tool_calls, StatusLog, ToolLoop, ToolLoopResult,
+    ToolStatus,
+};
+use crate::tool_protocol::{prompt_with_text_tools, text_protocol_tool_choice, ToolTransport};
+use crate::types::{
+    ContentCap, DeltaSequencer, EventSlot, FinishReason, FunctionCall, Message, MessageBuilder,
+    MessageMetadata, MessageType, StreamEventKind, StreamedContent, Tool, ToolSpec,
+    TruncatedStream,
+};
+use crate::warning::{RequestWarnings, WireWarning};
 
 impl AnthropicModel {
     /// Turn a human-readable model identifier into the strongly typed variant
@@ -43,6 +72,19 @@ impl AnthropicModel {
 
         ("anthropic".to_string(), model.to_string())
     }
+
+    /// The most tokens the model writes in one reply; a larger `max_tokens`
+    /// is sent as this.
+    pub fn max_output_tokens(&self) -> usize {
+        match self {
+            AnthropicModel::ClaudeOpus41 | AnthropicModel::ClaudeOpus4 => 32_000,
+            AnthropicModel::ClaudeSonnet4 | AnthropicModel::Claude37Sonnet => 64_000,
+            AnthropicModel::Claude35SonnetNew
+            | AnthropicModel::Claude35Haiku
+            | AnthropicModel::Claude35SonnetOld => 8192,
+            AnthropicModel::Claude3Haiku | AnthropicModel::Claude3Opus => 4096,
+        }
+    }
 }
 
 impl std::str::FromStr for AnthropicModel {
@@ -53,15 +95,22 @@ impl std::str::FromStr for AnthropicModel {
     }
 }
 
-impl<'a> From<&'a str> for AnthropicModel {
-    fn from(model: &'a str) -> Self {
-        AnthropicModel::from_model_name(model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<&str> for AnthropicModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        AnthropicModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
     }
 }
 
-impl From<String> for AnthropicModel {
-    fn from(model: String) -> Self {
-        AnthropicModel::from_model_name(&model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<String> for AnthropicModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        AnthropicModel::try_from(model.as_str())
     }
 }
 
@@ -70,14 +119,125 @@ impl From<String> for AnthropicModel {
 /// The client knows how to construct HTTPS requests, perform streaming reads
 /// when requested, and translate between the crate's canonical `Message`
 /// representation and Anthropic's schema.
+///
+/// # Examples
+///
+/// These run against `wire::mock::doctest_client`, which points `options` at
+/// a local mock server; against the real API use `AnthropicClient::new` and set the API
+/// key variable.
+///
+/// A single prompt:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("claude-3-5-haiku-20241022", wire::mock::DoctestCall::Prompt).await;
+/// use wire::api::PromptCore;
+/// use wire::anthropic::AnthropicClient;
+/// use wire::types::MessageType;
+///
+/// let client = AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let reply = client
+///     .prompt("Answer briefly.".to_string(), vec![question])
+///     .await
+///     .unwrap();
+/// assert_eq!(reply.content, "It is sunny in Paris.");
+/// # });
+/// ```
+///
+/// Streaming, with each delta sent over a channel as it arrives:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("claude-3-5-haiku-20241022", wire::mock::DoctestCall::Stream).await;
+/// use wire::api::PromptCore;
+/// use wire::anthropic::AnthropicClient;
+/// use wire::types::MessageType;
+///
+/// let client = AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+/// let reply = client
+///     .prompt_stream(vec![question], "Answer briefly.".to_string(), tx)
+///     .await
+///     .unwrap();
+///
+/// let mut streamed = String::new();
+/// while let Some(delta) = rx.recv().await {
+///     streamed.push_str(&delta);
+/// }
+/// assert_eq!(streamed, reply.content);
+/// # });
+/// ```
+///
+/// A tool loop: the model's calls run until it answers in text, and the
+/// returned history holds every turn, the final answer last.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("claude-3-5-haiku-20241022", wire::mock::DoctestCall::Tools).await;
+/// use wire::api::{PromptCore, ToolCapable};
+/// use wire::anthropic::AnthropicClient;
+/// use wire::types::{MessageType, Tool, ToolWrapper};
+///
+/// let weather = Tool {
+///     function_type: "function".to_string(),
+///     name: "get_weather".to_string(),
+///     description: "Current weather for a city.".to_string(),
+///     parameters: serde_json::json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"],
+///     }),
+///     strict: false,
+///     function: Box::new(ToolWrapper(|args: serde_json::Value| {
+///         serde_json::json!(format!("sunny in {}", args["city"].as_str().unwrap()))
+///     })),
+/// };
+///
+/// let client = AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let history = client
+///     .prompt_with_tools("Use the tools you have.", vec![question], vec![weather])
+///     .await
+///     .unwrap();
+/// assert_eq!(history.last().unwrap().content, "It is sunny in Paris.");
+/// # });
+/// ```
 pub struct AnthropicClient {
     pub http_client: reqwest::Client,
+    pub stream_client: reqwest::Client,
     pub model: AnthropicModel,
     pub host: String,
     pub port: u16,
     pub path: String,
     pub max_tokens: usize,
     pub scheme: Scheme,
+    pub credentials: Credentials,
+    /// The options the client was built with. Requests read their settings
+    /// from here; the fields above are the ones resolved from them.
+    pub options: ClientOptions,
+    /// Options given at construction that requests leave out, reported with
+    /// every prompt.
+    pub(crate) ignored_options: Vec<WireWarning>,
 }
 
 impl AnthropicClient {
@@ -98,35 +258,99 @@ impl AnthropicClient {
         let model = model.into();
         let mut client = Self {
             http_client: reqwest::Client::new(),
+            stream_client: ClientOptions::default().stream_client(),
             model,
             host: "api.anthropic.com".to_string(),
             port: 443,
             path: "/v1/messages".to_string(),
             max_tokens: 4096,
             scheme: Scheme::Https,
+            credentials: Credentials::from_env("ANTHROPIC_API_KEY"),
+            options: ClientOptions::default(),
+            ignored_options: Vec::new(),
         };
 
         client.apply_options(options);
         client
     }
 
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when Anthropic has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<AnthropicModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<AnthropicModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+
+    /// Send requests to another path on the same origin, e.g. a versioned or
+    /// regional route.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
     /// Apply optional client configuration modifiers.
     fn apply_options(&mut self, options: ClientOptions) {
-        match options.endpoint {
+        self.http_client = options.http_client();
+        self.stream_client = options.stream_client();
+
+        match &options.endpoint {
             Endpoint::Default => {}
             Endpoint::BaseUrl(endpoint) => {
-                self.host = endpoint.host;
+                self.host = endpoint.host.clone();
                 self.port = endpoint.port;
                 self.scheme = endpoint.scheme;
             }
         }
 
-        if options.disable_proxy {
-            self.http_client = reqwest::Client::builder()
-                .no_proxy()
-                .build()
-                .expect("reqwest client without proxy");
+        if let Some(max_tokens) = options.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+
+        if options.thinking_level.is_some() {
+            self.ignored_options.push(WireWarning::option_ignored(
+                "thinking_level",
+                Provider::Anthropic,
+                "wire sends Anthropic no thinking settings",
+            ));
+        }
+
+        self.options = options;
+    }
+
+    /// `max_tokens` as sent: no more than the model writes.
+    fn sent_max_tokens(&self) -> usize {
+        self.max_tokens.min(self.model.max_output_tokens())
+    }
+
+    /// The warnings of a request made with `options`, starting with the
+    /// options it leaves out or changes.
+    fn request_warnings(&self, options: &PromptOptions) -> Result<RequestWarnings<'_>, WireError> {
+        let mut warnings =
+            RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+        warnings.extend(self.ignored_options.iter().cloned())?;
+        if self.sent_max_tokens() < self.max_tokens {
+            warnings.extend([WireWarning::FieldClamped {
+                field: "max_tokens".to_string(),
+                provider: Provider::Anthropic.as_str().to_string(),
+                requested: self.max_tokens as u64,
+                sent: self.sent_max_tokens() as u64,
+            }])?;
         }
+        warnings.extend(options.header_warnings(Provider::Anthropic))?;
+        Ok(warnings)
     }
 
     /// Render the scheme/host/port combination into an origin string suitable
@@ -182,10 +406,13 @@ impl AnthropicClient {
                 }
 
                 processed_messages.push(serde_json::json!({
-                    "role": "user",
+                    "role": role_for(Provider::Anthropic, MessageType::FunctionCallOutput),
                     "content": tool_results
                 }));
-            } else if current_message.message_type == MessageType::Assistant {
+            } else if matches!(
+                current_message.message_type,
+                MessageType::Assistant | MessageType::FunctionCall
+            ) {
                 let tool_uses: Vec<serde_json::Value> = if let Some(calls) =
                     &current_message.tool_calls
                 {
@@ -208,7 +435,8 @@ impl AnthropicClient {
                     Vec::new()
                 };
 
-                let mut content = if !current_message.content.is_empty() {
+                // Anthropic rejects text blocks that are empty or only whitespace
+                let mut content = if !current_message.content.trim().is_empty() {
                     vec![serde_json::json!({
                         "type": "text",
                         "text": current_message.content
@@ -219,13 +447,18 @@ impl AnthropicClient {
 
                 content.extend(tool_uses);
 
+                // ...and turns with an empty content array
+                if content.is_empty() {
+                    continue;
+                }
+
                 processed_messages.push(serde_json::json!({
-                    "role": current_message.message_type.to_string(),
+                    "role": role_for(Provider::Anthropic, current_message.message_type),
                     "content": content
                 }));
             } else {
                 processed_messages.push(serde_json::json!({
-                    "role": current_message.message_type.to_string(),
+                    "role": role_for(Provider::Anthropic, current_message.message_type),
                     "content": &current_message.content
                 }));
             }
@@ -234,6 +467,135 @@ impl AnthropicClient {
         processed_messages
     }
 
+    /// Concatenated text blocks of a Messages API `content` array.
+    fn text_content(content: &[serde_json::Value]) -> String {
+        content
+            .iter()
+            .filter(|item| item["type"] == "text")
+            .filter_map(|text| text["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// The `tool_use` blocks of a Messages API `content` array.
+    fn tool_calls(content: &[serde_json::Value]) -> Vec<FunctionCall> {
+        content
+            .iter()
+            .filter(|item| item["type"] == "tool_use")
+            .map(|tool_use| FunctionCall {
+                id: tool_use["id"].as_str().unwrap_or_default().to_string(),
+                call_type: "function".to_string(),
+                function: crate::types::Function {
+                    name: tool_use["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: tool_use["input"].to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// Send one tool-loop request, following `max_tokens_behavior` when the
+    /// reply is cut off. Returns the final response along with the text of
+    /// any earlier truncated replies, which the response continues, and the
+    /// final request's snapshot and id.
+    async fn send_tool_request(
+        &self,
+        system_prompt: &str,
+        pending: Vec<Message>,
+        specs: &[ToolSpec],
+        status: &mut StatusLog,
+    ) -> Result<
+        (
+            serde_json::Value,
+            String,
+            Option<Arc<RequestSnapshot>>,
+            Option<String>,
+        ),
+        WireError,
+    > {
+        let mut prefix = String::new();
+        let mut continuations = 0;
+
+        loop {
+            let mut messages = pending.clone();
+            if !prefix.is_empty() {
+                messages.push(
+                    self.new_message(prefix.clone())
+                        .message_type(MessageType::Assistant)
+                        .build(),
+                );
+            }
+
+            let api = crate::api::API::Anthropic(self.model.clone());
+            let estimated_tokens = estimate_tokens(system_prompt, &messages);
+            let (body, request_snapshot, request_id) = cancellable(
+                self.options.cancellation.as_ref(),
+                with_retries(
+                    self.options.retry_policy.as_ref(),
+                    self.options.rate_limiter.as_ref(),
+                    estimated_tokens,
+                    &self.options.clock,
+                    Some(&mut *status),
+                    || {
+                        send_logged(
+                            self.options.event_log.as_ref(),
+                            &api,
+                            self.build_request(
+                                system_prompt.to_string(),
+                                messages.clone(),
+                                Some(specs),
+                                false,
+                            ),
+                            self.options.request_snapshot,
+                            self.options.max_request_bytes,
+                        )
+                    },
+                ),
+            )
+            .await?;
+            let response_json: serde_json::Value = serde_json::from_str(&body)?;
+
+            if response_json.get("stop_reason").and_then(|v| v.as_str()) != Some("max_tokens") {
+                return Ok((response_json, prefix, request_snapshot, request_id));
+            }
+
+            let content = response_json
+                .get("content")
+                .and_then(|value| value.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            prefix.push_str(&Self::text_content(content));
+
+            let can_continue = match self.options.max_tokens_behavior {
+                MaxTokensBehavior::Continue { max_continuations } => {
+                    continuations < max_continuations
+                }
+                MaxTokensBehavior::Error => false,
+            };
+
+            // Only text can be prefilled, so a cut-off tool call is dropped
+            // and the model is left to issue it again in full.
+            if !can_continue || prefix.trim().is_empty() {
+                let tool_calls = Self::tool_calls(content);
+                let mut partial = self
+                    .new_message(prefix)
+                    .message_type(MessageType::Assistant)
+                    .build();
+                partial.system_prompt = system_prompt.to_string();
+                if !tool_calls.is_empty() {
+                    partial.tool_calls = Some(tool_calls);
+                }
+
+                return Err(WireError::TruncatedResponse {
+                    partial: Box::new(partial),
+                });
+            }
+
+            // The API rejects prefills that end in whitespace
+            prefix.truncate(prefix.trim_end().len());
+            continuations += 1;
+        }
+    }
+
     /// Execute prompts with tool support. This currently mirrors the legacy
     /// behaviour and emits a warning signalling the known instability.
     async fn prompt_with_tools_internal(
@@ -242,33 +604,93 @@ impl AnthropicClient {
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-        if let Some(tx) = tx.as_ref() {
-            let _ = tx
-                .send("warn: anthropic tool support is experimental".to_string())
-                .await;
-        } else {
-            eprintln!("warn: anthropic tool support is experimental and may fail");
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        self.credentials.require()?;
+        check_incoming(
+            self.options.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        if self.options.tool_transport == ToolTransport::TextProtocol {
+            let mut warnings =
+                RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+            warnings.extend(
+                self.options
+                    .tool_choice
+                    .map(|_| text_protocol_tool_choice(Provider::Anthropic)),
+            )?;
+            return prompt_with_text_tools(
+                self,
+                &self.options.tool_hooks,
+                self.options.max_tool_iterations,
+                self.options.cancellation.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                warnings.to_vec(),
+                status,
+                system_prompt,
+                chat_history,
+                tools,
+            )
+            .await;
         }
 
+        status
+            .report(ToolStatus::Warning(WireWarning::Experimental {
+                feature: "tool".to_string(),
+                provider: Provider::Anthropic.as_str().to_string(),
+            }))
+            .await;
+
         let mut chat_history = chat_history;
         let system_prompt = system_prompt.to_string();
         let api = crate::api::API::Anthropic(self.model.clone());
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let tool_map: HashMap<String, Tool> =
+            tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
+        resume_pending_calls(
+            &mut status,
+            self.options.event_log.as_ref(),
+            self.options.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            &system_prompt,
+        )
+        .await?;
         let mut calling_tools = true;
 
+        let mut tool_loop = ToolLoop::new(
+            &self.options.tool_hooks,
+            self.options.max_tool_iterations,
+            self.options.cancellation.as_ref(),
+        );
+
         while calling_tools {
-            let response = self
-                .build_request(
-                    system_prompt.clone(),
-                    chat_history.clone(),
-                    Some(tools.clone()),
-                    false,
-                )
-                .send()
-                .await?;
+            let pending = tool_loop.next_request(&chat_history)?;
+            let mut warnings = self.request_warnings(&PromptOptions::default())?;
+            // Tool outputs join the history inside the loop, so check every turn
+            let pending = warnings.normalize(&api, &pending, self.options.history_strictness)?;
+            check_outbound(self.options.sanitize_policy, &system_prompt, &pending)?;
+
+            if let Some(ratio) = self.options.tool_schema_warning {
+                let stats = self.stats(&system_prompt, &pending, Some(&specs));
+                warn_on_tool_schema_size(&stats, ratio, &mut warnings, &mut status).await?;
+            }
 
-            let body = response.text().await?;
-            let response_json: serde_json::Value = serde_json::from_str(&body)?;
+            let recorder = LatencyRecorder::start();
+            let (response_json, prefix, request_snapshot, request_id) = self
+                .send_tool_request(&system_prompt, pending, &specs, &mut status)
+                .await?;
+            let metadata = MessageMetadata {
+                latency: Some(recorder.finish()),
+                request_snapshot,
+                request_id,
+                service_tier: Self::service_tier(&response_json["usage"]),
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            };
 
             let stop_reason = response_json
                 .get("stop_reason")
@@ -284,8 +706,9 @@ impl AnthropicClient {
                 if content.starts_with('"') && content.ends_with('"') && content.len() >= 2 {
                     content = content[1..content.len() - 1].to_string();
                 }
+                content.insert_str(0, &prefix);
 
-                chat_history.push(Message {
+                let message = Message {
                     message_type: MessageType::Assistant,
                     content,
                     api: api.clone(),
@@ -295,37 +718,24 @@ impl AnthropicClient {
                     name: None,
                     input_tokens: 0,
                     output_tokens: 0,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
                 });
+                chat_history.push(message);
             } else {
-                let tool_map: HashMap<String, Tool> =
-                    tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
-
                 let content_array = response_json
                     .get("content")
                     .and_then(|value| value.as_array())
-                    .ok_or_else(|| "Missing both content and tool calls")?;
-
-                let text_content: String = content_array
-                    .iter()
-                    .filter(|item| item["type"] == "text")
-                    .filter_map(|text| text["text"].as_str())
-                    .collect::<Vec<_>>()
-                    .join("");
-
-                let tool_calls: Vec<FunctionCall> = content_array
-                    .iter()
-                    .filter(|item| item["type"] == "tool_use")
-                    .map(|tool_use| FunctionCall {
-                        id: tool_use["id"].as_str().unwrap_or_default().to_string(),
-                        call_type: "function".to_string(),
-                        function: crate::types::Function {
-                            name: tool_use["name"].as_str().unwrap_or_default().to_string(),
-                            arguments: tool_use["input"].to_string(),
-                        },
-                    })
-                    .collect();
+                    .ok_or(WireError::MissingField { field: "content" })?;
+
+                let text_content = format!("{}{}", prefix, Self::text_content(content_array));
+                let tool_calls = Self::tool_calls(content_array);
+                report_interim_text(&mut status, &text_content).await;
 
-                chat_history.push(Message {
+                let message = Message {
                     message_type: MessageType::Assistant,
                     content: text_content,
                     api: api.clone(),
@@ -335,81 +745,60 @@ impl AnthropicClient {
                     name: Some("?".to_string()),
                     input_tokens: 0,
                     output_tokens: 0,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
                 });
-
-                for call in tool_calls {
-                    if let Some(tx) = tx.as_ref() {
-                        let _ = tx
-                            .send(format!("calling tool {}...", call.function.name))
-                            .await;
-                    }
-
-                    let tool_name = call.function.name.clone();
-                    let call_id = call.id.clone();
-                    let arguments = call.function.arguments.clone();
-
-                    let tool = tool_map
-                        .get(&tool_name)
-                        .ok_or_else(|| format!("tool {} not found", tool_name))?
-                        .clone();
-
-                    let tool_args: serde_json::Value = serde_json::from_str(&arguments)?;
-
-                    let tool_name_for_message = tool.name.clone();
-
-                    let function_output = tokio::task::spawn_blocking(move || {
-                        tool.function.call(tool_args).to_string()
-                    })
-                    .await
-                    .map_err(|err| -> Box<dyn std::error::Error> { Box::new(err) })?;
-
-                    chat_history.push(Message {
-                        message_type: MessageType::FunctionCallOutput,
-                        content: function_output,
-                        api: api.clone(),
-                        system_prompt: system_prompt.clone(),
-                        tool_call_id: Some(call_id),
-                        tool_calls: None,
-                        name: Some(tool_name_for_message),
-                        input_tokens: 0,
-                        output_tokens: 0,
-                    });
-                }
+                chat_history.push(message);
+
+                let outputs = run_tool_calls(
+                    &mut status,
+                    self.options.event_log.as_ref(),
+                    self.options.tool_output_policy.as_ref(),
+                    &tool_map,
+                    tool_calls,
+                    &chat_history,
+                    tool_loop.iteration(),
+                    &api,
+                    &system_prompt,
+                )
+                .await?;
+                chat_history.extend(outputs);
             }
         }
 
-        Ok(chat_history)
-    }
-}
-
-#[async_trait::async_trait]
-impl Prompt for AnthropicClient {
-    /// Retrieve the API key from the environment.
-    fn get_auth_token(&self) -> String {
-        std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY environment variable not set")
-    }
-
-    /// Convenience helper that seeds a `MessageBuilder` scoped to the configured
-    /// Anthropic model.
-    fn new_message(&self, content: String) -> MessageBuilder {
-        MessageBuilder::new(crate::api::API::Anthropic(self.model.clone()), content)
+        Ok(status.finish(chat_history))
     }
 
     /// Build a Reqwest request for an Anthropic message completion.
     ///
+    /// * `options` – the prompt's path override, service tier, extra body fields and
+    ///   headers; `PromptOptions::default()` outside of a prompt.
+    /// * `warnings` – collects extra body fields that wire had already set.
     /// * `system_prompt` – framing instructions supplied as Anthropic's `system` field.
     /// * `chat_history` – prior turns the provider should consider; already
     ///   normalised to the crate's shared `Message` schema.
     /// * `tools` – optional tool definitions advertised to the model so it can
     ///   issue tool calls.
     /// * `stream` – toggles server-sent-events streaming when `true`.
-    fn build_request(
+    fn request_to(
         &self,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
         system_prompt: String,
         chat_history: Vec<Message>,
-        tools: Option<Vec<Tool>>,
+        tools: Option<&[ToolSpec]>,
         stream: bool,
-    ) -> reqwest::RequestBuilder {
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let (system_prompt, chat_history) = filter_outbound(
+            self.options.content_filter.as_ref(),
+            system_prompt,
+            chat_history,
+        );
+        let (system_prompt, chat_history) =
+            sanitize_outbound(self.options.sanitize_policy, system_prompt, chat_history);
         let (_, model) = self.model.to_strings();
         let processed_messages = Self::format_messages(&chat_history);
 
@@ -417,9 +806,13 @@ impl Prompt for AnthropicClient {
             "model": model,
             "messages": processed_messages,
             "stream": stream,
-            "max_tokens": self.max_tokens,
+            "max_tokens": self.sent_max_tokens(),
             "system": system_prompt,
         });
+        if let Some(service_tier) = options.service_tier {
+            body["service_tier"] = service_tier.as_str().into();
+        }
+        options.merge_extra_body(&mut body, Provider::Anthropic, warnings)?;
 
         if let Some(tools) = &tools {
             let tools_mapped = tools
@@ -434,58 +827,135 @@ impl Prompt for AnthropicClient {
                 .collect::<Vec<_>>();
 
             body["tools"] = serde_json::json!(tools_mapped);
+            if let Some(tool_choice) = self.options.tool_choice {
+                let choice = match tool_choice {
+                    ToolChoice::Auto => "auto",
+                    ToolChoice::Any => "any",
+                    ToolChoice::None => "none",
+                };
+                body["tool_choice"] = serde_json::json!({ "type": choice });
+            }
         }
 
-        let url = format!("{}{}", self.origin(), self.path);
+        let url = format!("{}{}", self.origin(), options.path(&self.path));
 
-        self.http_client
-            .post(url)
-            .json(&body)
-            .header("x-api-key", self.get_auth_token())
+        let mut request = json_body(self.http_client.post(url), &body, self.options.json_format)
+            .header("x-api-key", self.get_auth_token()?)
             .header("anthropic-version", "2023-06-01")
+            .header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING);
+        for (name, value) in options.extra_headers() {
+            request = request.header(name, value);
+        }
+
+        Ok(request)
     }
 
-    /// Build the raw HTTPS request payload used by the streaming transport
-    /// implementation. Keeping this separate avoids duplicating the
-    /// serialisation logic.
+    /// Build the raw HTTP/1.1 request for `build_request_raw`.
     ///
+    /// * `options` – the prompt's path override, service tier, extra body fields and
+    ///   headers; `PromptOptions::default()` outside of a prompt.
+    /// * `warnings` – collects extra body fields that wire had already set.
     /// * `system_prompt` – converted into the `system` field in the body.
     /// * `chat_history` – serialised into Anthropic's `messages` array.
     /// * `stream` – when true the request path stays the same but the SSE flag is set.
-    fn build_request_raw(
+    fn raw_request_to(
         &self,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
         system_prompt: String,
         chat_history: Vec<Message>,
         stream: bool,
-    ) -> String {
+    ) -> Result<String, WireError> {
+        let (system_prompt, chat_history) = filter_outbound(
+            self.options.content_filter.as_ref(),
+            system_prompt,
+            chat_history,
+        );
+        let (system_prompt, chat_history) =
+            sanitize_outbound(self.options.sanitize_policy, system_prompt, chat_history);
         let (_, model) = self.model.to_strings();
         let processed_messages = Self::format_messages(&chat_history);
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": model,
             "messages": processed_messages,
             "stream": stream,
-            "max_tokens": self.max_tokens,
+            "max_tokens": self.sent_max_tokens(),
             "system": system_prompt,
         });
+        if let Some(service_tier) = options.service_tier {
+            body["service_tier"] = service_tier.as_str().into();
+        }
+        options.merge_extra_body(&mut body, Provider::Anthropic, warnings)?;
 
-        let json_string = serde_json::to_string(&body).expect("Failed to serialize JSON");
-        let path = self.path.clone();
+        let json_string = payload::to_string(&body, self.options.json_format);
+        let path = options.path(&self.path);
 
-        format!(
+        Ok(format!(
             "POST {} HTTP/1.1\r\n\
         Host: {}\r\n\
         Content-Type: application/json\r\n\
         Content-Length: {}\r\n\
         Accept: */*\r\n\
+        Accept-Encoding: {}\r\n\
         x-api-key: {}\r\n\
-        anthropic-version: 2023-06-01\r\n\r\n\
+        anthropic-version: 2023-06-01\r\n\
+        {}\r\n\
         {}",
             path,
             self.host_header(),
             json_string.len(),
-            self.get_auth_token(),
+            ACCEPT_ENCODING,
+            self.get_auth_token()?,
+            options.raw_extra_headers(),
             json_string.trim()
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCore for AnthropicClient {
+    /// The API key, as read when the client was built or refreshed.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        self.credentials.token()
+    }
+
+    fn refresh_credentials(&self) {
+        self.credentials.refresh();
+    }
+
+    /// Convenience helper that seeds a `MessageBuilder` scoped to the configured
+    /// Anthropic model.
+    fn clock(&self) -> &dyn Clock {
+        &self.options.clock
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.options.event_log.as_ref()
+    }
+
+    fn deny_warnings(&self) -> bool {
+        self.options.deny_warnings
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(crate::api::API::Anthropic(self.model.clone()), content)
+    }
+
+    fn build_request(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        self.request_to(
+            &PromptOptions::default(),
+            &mut RequestWarnings::new(false, None),
+            system_prompt,
+            chat_history,
+            tools,
+            stream,
         )
     }
 
@@ -494,17 +964,53 @@ impl Prompt for AnthropicClient {
     ///
     /// * `system_prompt` – instructions for the assistant role.
     /// * `chat_history` – conversation context (excluding the new completion).
-    async fn prompt(
+    async fn prompt_with_options(
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        let response = self
-            .build_request(system_prompt.clone(), chat_history, None, false)
-            .send()
-            .await?;
-
-        let body = response.text().await?;
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history = warnings.normalize(
+            &crate::api::API::Anthropic(self.model.clone()),
+            &chat_history,
+            self.options.history_strictness,
+        )?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let recorder = LatencyRecorder::start();
+        let api = crate::api::API::Anthropic(self.model.clone());
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (body, request_snapshot, request_id) = cancellable(
+            options.cancellation_or(self.options.cancellation.as_ref()),
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    send_logged(
+                        self.options.event_log.as_ref(),
+                        &api,
+                        self.request_to(
+                            options,
+                            &mut warnings,
+                            system_prompt.clone(),
+                            chat_history.clone(),
+                            None,
+                            false,
+                        ),
+                        self.options.request_snapshot,
+                        self.options.max_request_bytes,
+                    )
+                },
+            ),
+        )
+        .await?;
+        let latency = recorder.finish();
         let response_json: serde_json::Value = serde_json::from_str(&body)?;
 
         let mut content = self.read_json_response(&response_json)?;
@@ -513,7 +1019,11 @@ impl Prompt for AnthropicClient {
             content = content[1..content.len() - 1].to_string();
         }
 
-        Ok(Message {
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(content);
+        let service_tier = Self::service_tier(&response_json["usage"]);
+
+        let message = Message {
             message_type: MessageType::Assistant,
             content,
             api: crate::api::API::Anthropic(self.model.clone()),
@@ -523,59 +1033,76 @@ impl Prompt for AnthropicClient {
             name: None,
             input_tokens: 0,
             output_tokens: 0,
-        })
+            metadata: MessageMetadata {
+                latency: Some(latency),
+                truncated: cap.truncation(),
+                sequence: None,
+                content_bytes: None,
+                route: None,
+                truncated_stream: None,
+                request_snapshot,
+                request_id,
+                service_tier,
+                tool_invocation: None,
+                synthesized_call_ids: Default::default(),
+                warnings: warnings.to_vec(),
+                citations: None,
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        Ok(options.post_process(message)?)
     }
 
-    /// Execute a streaming prompt request, forwarding partial tokens to the
-    /// caller while collecting the final response.
-    ///
-    /// * `chat_history` – existing conversation turns.
-    /// * `system_prompt` – instructions carried through the session.
-    /// * `tx` – channel the caller can read partial deltas from as SSE chunks arrive.
-    async fn prompt_stream(
+    fn prompt_stream_events_with_options(
         &self,
-        chat_history: Vec<Message>,
         system_prompt: String,
-        tx: tokio::sync::mpsc::Sender<String>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        if self.scheme != Scheme::Https {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "prompt_stream is not available with non-TLS endpoints",
-            )));
-        }
-
-        let request = self.build_request_raw(system_prompt.clone(), chat_history, true);
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
+    }
 
-        let mut stream = connect_https(&self.host, self.port);
-        stream
-            .write_all(request.as_bytes())
-            .expect("Failed to write to stream");
-        stream.flush().expect("Failed to flush stream");
+    /// Extract the assistant response from Anthropic's JSON payload.
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        response_json
+            .get("content")
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.get("text"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or(WireError::MissingField {
+                field: "content[0].text",
+            })
+    }
 
-        let response = self.process_stream(stream, &tx).await?;
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
 
-        Ok(Message {
-            message_type: MessageType::Assistant,
-            content: response,
-            api: crate::api::API::Anthropic(self.model.clone()),
-            system_prompt,
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-            input_tokens: 0,
-            output_tokens: 0,
-        })
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        Some(self)
     }
+}
 
+#[async_trait::async_trait]
+impl ToolCapable for AnthropicClient {
     async fn prompt_with_tools(
         &self,
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<Message>, WireError> {
         self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
             .await
+            .map(|result| result.messages)
     }
 
     async fn prompt_with_tools_with_status(
@@ -584,23 +1111,39 @@ impl Prompt for AnthropicClient {
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<Message>, WireError> {
         self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
             .await
+            .map(|result| result.messages)
     }
 
-    /// Extract the assistant response from Anthropic's JSON payload.
-    fn read_json_response(
+    async fn run_tool_loop(
         &self,
-        response_json: &serde_json::Value,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        response_json
-            .get("content")
-            .and_then(|v| v.get(0))
-            .and_then(|v| v.get("text"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .ok_or_else(|| "Missing 'content[0].text'".into())
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransport for AnthropicClient {
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError> {
+        self.raw_request_to(
+            &PromptOptions::default(),
+            &mut RequestWarnings::new(false, None),
+            system_prompt,
+            chat_history,
+            stream,
+        )
     }
 
     /// Consume the server-sent-event stream from Anthropic, forwarding deltas to
@@ -608,14 +1151,204 @@ impl Prompt for AnthropicClient {
     /// finished.
     async fn process_stream(
         &self,
-        stream: TlsStream<TcpStream>,
+        stream: Box<dyn Read + Send>,
         tx: &tokio::sync::mpsc::Sender<String>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let reader = std::io::BufReader::new(stream);
-        let mut full_message = String::new();
+    ) -> Result<String, WireError> {
+        let mut content = StreamedContent::new(tx);
+        self.read_stream(
+            ByteStream::read_raw(stream),
+            &mut LatencyRecorder::start(),
+            &mut ContentCap::new(None),
+            &mut DeltaSequencer::new(),
+            &mut content,
+            &mut None,
+            &mut (0, 0),
+            &mut BTreeMap::new(),
+            &mut None,
+            false,
+        )
+        .await?;
+
+        Ok(content.finish())
+    }
+}
+
+impl AnthropicClient {
+    /// The tier reported in a response's `usage`.
+    fn service_tier(usage: &serde_json::Value) -> Option<String> {
+        usage["service_tier"].as_str().map(str::to_string)
+    }
+
+    /// Execute a streaming prompt request, leaving each event in `slot` as
+    /// SSE chunks arrive while collecting the final response.
+    ///
+    /// * `chat_history` – existing conversation turns.
+    /// * `system_prompt` – instructions carried through the session.
+    /// * `slot` – where `prompt_stream_events_with_options` picks events up.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history = warnings.normalize(
+            &crate::api::API::Anthropic(self.model.clone()),
+            &chat_history,
+            self.options.history_strictness,
+        )?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (request, request_snapshot) = prepare_stream(
+            self.options.event_log.as_ref(),
+            &crate::api::API::Anthropic(self.model.clone()),
+            self.request_to(
+                options,
+                &mut warnings,
+                system_prompt.clone(),
+                chat_history,
+                None,
+                true,
+            ),
+            self.options.request_snapshot,
+            self.options.max_request_bytes,
+        )?;
+
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let mut service_tier = None;
+        let mut tokens = (0, 0);
+        let mut calls = BTreeMap::new();
+        let mut stop_reason = None;
+        let cancellation = options.cancellation_or(self.options.cancellation.as_ref());
+        let body = cancellable(
+            cancellation,
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    open_stream(
+                        &self.stream_client,
+                        &request,
+                        self.options.max_redirects,
+                        self.options.stream_idle_timeout,
+                    )
+                },
+            ),
+        )
+        .await?
+        .with_cancellation(cancellation);
+        let request_id = body.request_id().map(str::to_string);
+        let truncated_stream = self
+            .read_stream(
+                body,
+                &mut recorder,
+                &mut cap,
+                &mut sequencer,
+                &mut content,
+                &mut service_tier,
+                &mut tokens,
+                &mut calls,
+                &mut stop_reason,
+                options.strict_stream_end,
+            )
+            .await
+            .map_err(|err| {
+                err.with_partial(|| content.partial())
+                    .with_request_id(request_id.as_deref())
+            })?;
+        warnings.stream_truncated(Provider::Anthropic, truncated_stream.as_ref())?;
+        let content_bytes = content.bytes();
+        let (input_tokens, output_tokens) = tokens;
+        // As with `prompt_with_tools`, only a `tool_use` stop hands back calls
+        let (message_type, tool_calls) = match stop_reason.as_deref() {
+            Some("tool_use") if !calls.is_empty() => {
+                let calls = calls
+                    .into_values()
+                    .map(|mut call: FunctionCall| {
+                        if call.function.arguments.is_empty() {
+                            call.function.arguments = "{}".to_string();
+                        }
+                        call
+                    })
+                    .collect();
+                (MessageType::FunctionCall, Some(calls))
+            }
+            _ => (MessageType::Assistant, None),
+        };
+
+        let message = Message {
+            message_type,
+            content: content.finish(),
+            api: crate::api::API::Anthropic(self.model.clone()),
+            system_prompt,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(recorder.finish()),
+                truncated: cap.truncation(),
+                sequence: Some(sequencer.finish()),
+                content_bytes: Some(content_bytes),
+                route: None,
+                truncated_stream,
+                request_snapshot,
+                request_id,
+                service_tier,
+                tool_invocation: None,
+                synthesized_call_ids: Default::default(),
+                warnings: warnings.to_vec(),
+                citations: None,
+            },
+        };
 
-        for line in reader.lines() {
-            let line = line?;
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        options.post_process(message)
+    }
+
+    /// Parse Anthropic's server-sent events from `body`, forwarding each text
+    /// delta into `content` and noting its arrival on `recorder`. `tool_use`
+    /// blocks, thinking, usage and the stop reason are reported to `content`
+    /// as `StreamEvent`s. The tier from `message_start` goes to
+    /// `service_tier`, and the token counts to `tokens`. Each `tool_use`
+    /// block's input is pieced together in `calls`, keyed by block index,
+    /// and `message_delta`'s stop reason goes to `stop_reason`.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_stream(
+        &self,
+        mut body: ByteStream,
+        recorder: &mut LatencyRecorder,
+        cap: &mut ContentCap,
+        sequencer: &mut DeltaSequencer,
+        content: &mut StreamedContent,
+        service_tier: &mut Option<String>,
+        tokens: &mut (usize, usize),
+        calls: &mut BTreeMap<usize, FunctionCall>,
+        stop_reason: &mut Option<String>,
+        strict: bool,
+    ) -> Result<Option<TruncatedStream>, WireError> {
+        let mut line = String::new();
+
+        while body.read_line_into(&mut line).await? {
+            if line.starts_with("event: ") || line.starts_with("data: ") {
+                emit(self.options.event_log.as_ref(), || WireEvent::StreamData {
+                    data: line.clone(),
+                });
+            }
 
             if line.starts_with("event: message_stop") {
                 break;
@@ -630,30 +1363,111 @@ impl Prompt for AnthropicClient {
                 break;
             }
 
-            let response_json: serde_json::Value = match serde_json::from_str(&payload) {
-                Ok(json) => json,
-                Err(e) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        e.to_string(),
-                    )));
+            let response_json = match parse_event(&mut body, payload, strict).await? {
+                ParsedEvent::Json(json) => json,
+                ParsedEvent::Truncated(truncated) => return Ok(Some(truncated)),
+            };
+
+            if response_json["type"] == "message_start" {
+                *service_tier = Self::service_tier(&response_json["message"]["usage"]);
+            }
+
+            // `message_start` counts the input, `message_delta` the output
+            let usage = match response_json["type"].as_str() {
+                Some("message_start") => response_json["message"]["usage"].as_object(),
+                Some("message_delta") => response_json["usage"].as_object(),
+                _ => None,
+            };
+            if let Some(usage) = usage {
+                let count = |field: &str| usage.get(field).and_then(|v| v.as_u64()).unwrap_or(0);
+                let (input, output) = (
+                    count("input_tokens") as usize,
+                    count("output_tokens") as usize,
+                );
+                // Each count is a running total; one left out stays as it was
+                if input > 0 {
+                    tokens.0 = input;
                 }
+                if output > 0 {
+                    tokens.1 = output;
+                }
+                content
+                    .report(StreamEventKind::Usage { input, output })
+                    .await?;
+            }
+
+            let block_index = response_json["index"].as_u64().unwrap_or(0) as usize;
+            let block = &response_json["content_block"];
+            if response_json["type"] == "content_block_start" && block["type"] == "tool_use" {
+                let id = block["id"].as_str().unwrap_or_default().to_string();
+                let name = block["name"].as_str().unwrap_or_default().to_string();
+                calls.insert(
+                    block_index,
+                    FunctionCall {
+                        id: id.clone(),
+                        call_type: "function".to_string(),
+                        function: crate::types::Function {
+                            name: name.clone(),
+                            arguments: String::new(),
+                        },
+                    },
+                );
+                let start = StreamEventKind::ToolCallStart {
+                    index: block_index,
+                    id,
+                    name,
+                };
+                content.report(start).await?;
+            }
+
+            let delta = match response_json["type"] == "content_block_delta" {
+                true => &response_json["delta"],
+                false => &serde_json::Value::Null,
             };
+            if let Some(arguments) = delta["partial_json"].as_str() {
+                if let Some(call) = calls.get_mut(&block_index) {
+                    call.function.arguments.push_str(arguments);
+                }
+                let delta = StreamEventKind::ToolCallArgumentsDelta {
+                    index: block_index,
+                    delta: arguments.to_string(),
+                };
+                content.report(delta).await?;
+            }
+            if let Some(thinking) = delta["thinking"].as_str() {
+                content
+                    .report(StreamEventKind::Thinking(thinking.to_string()))
+                    .await?;
+            }
 
-            let mut delta = "null".to_string();
-            if response_json["type"] == "content_block_delta" {
-                delta = unescape(&response_json["delta"]["text"].to_string());
-                if delta.starts_with('"') && delta.ends_with('"') && delta.len() >= 2 {
-                    delta = delta[1..delta.len() - 1].to_string();
+            if response_json["type"] == "message_delta" {
+                if let Some(reason) = response_json["delta"]["stop_reason"].as_str() {
+                    *stop_reason = Some(reason.to_string());
+                    let done = StreamEventKind::Done {
+                        finish_reason: FinishReason::from_provider(reason),
+                    };
+                    content.report(done).await?;
                 }
             }
 
-            if delta != "null" {
-                tx.send(delta.clone()).await?;
-                full_message.push_str(&delta);
+            let delta = delta["text"].as_str();
+
+            if let Some(delta) = delta {
+                recorder.record_delta();
+
+                let kept = cap.truncate(delta.to_string());
+                if !kept.is_empty() {
+                    content.forward(kept).await?;
+                    sequencer.record(block_index);
+                }
+
+                // Dropping `body` on return closes the connection
+                if cap.exceeded() {
+                    break;
+                }
             }
         }
 
-        Ok(full_message)
+        Ok(None)
     }
 }
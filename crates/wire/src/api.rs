@@ -1,29 +1,108 @@
-use native_tls::TlsStream;
-use std::net::TcpStream;
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
-use crate::config::ClientOptions;
-use crate::types::{Message, MessageBuilder, Tool};
+use futures_core::Stream;
 
+use crate::clock::{Clock, TokioClock};
+use crate::config::{ClientOptions, PromptOptions};
+use crate::debug_log::DebugExchange;
+use crate::error::WireError;
+use crate::event_log::EventLog;
+use crate::metrics::RequestStats;
+use crate::tool_loop::ToolLoopResult;
+use crate::types::{
+    EventSlot, FinishReason, Forwarder, Message, MessageBuilder, MessageType, PartialMessage,
+    StreamEvent, StreamEventKind, Tool, ToolSpec,
+};
+use crate::warning::RequestWarnings;
+
+/// What every client does: answer a prompt, whole or streamed. Tool loops
+/// and raw HTTP access are separate capabilities, `ToolCapable` and
+/// `RawTransport`; `tools()` and `raw_transport()` say whether a client has
+/// them.
 #[async_trait::async_trait]
-pub trait Prompt: Send + Sync {
-    fn get_auth_token(&self) -> String;
+pub trait PromptCore: Send + Sync {
+    /// The key or token requests are sent with, or `WireError::AuthMissing`
+    /// when there is none.
+    fn get_auth_token(&self) -> Result<String, WireError>;
+
+    /// Read the API key from the environment again. Clients read it once,
+    /// when they are built; see `credentials`.
+    fn refresh_credentials(&self) {}
 
     fn new_message(&self, content: String) -> MessageBuilder;
 
+    /// The clock deadlines are measured against; see `ClientOptions::with_clock`.
+    fn clock(&self) -> &dyn Clock {
+        &TokioClock
+    }
+
+    /// Where this client reports `WireEvent`s; see `ClientOptions::with_event_log`.
+    fn event_log(&self) -> Option<&EventLog> {
+        None
+    }
+
+    /// Whether a warning fails the prompt; see `ClientOptions::deny_warnings`.
+    fn deny_warnings(&self) -> bool {
+        false
+    }
+
+    /// The last requests this client's event log kept in memory with their
+    /// responses, oldest first; see `debug_log::DebugLogDir`. Empty without
+    /// such a log.
+    fn debug_tail(&self) -> Vec<DebugExchange> {
+        self.event_log().map(EventLog::tail).unwrap_or_default()
+    }
+
     fn build_request(
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-        tools: Option<Vec<Tool>>,
+        tools: Option<&[ToolSpec]>,
         stream: bool,
-    ) -> reqwest::RequestBuilder;
+    ) -> Result<reqwest::RequestBuilder, WireError>;
 
-    fn build_request_raw(
+    /// Measure the request `build_request` would produce, splitting out the
+    /// bytes spent on tool definitions.
+    fn stats(
         &self,
-        system_prompt: String,
-        chat_history: Vec<Message>,
-        stream: bool,
-    ) -> String;
+        system_prompt: &str,
+        chat_history: &[Message],
+        tools: Option<&[ToolSpec]>,
+    ) -> RequestStats {
+        let body_len = |tools: Option<&[ToolSpec]>| {
+            self.build_request(
+                system_prompt.to_string(),
+                chat_history.to_vec(),
+                tools,
+                false,
+            )
+            .ok()
+            .and_then(|request| request.build().ok())
+            .and_then(|request| {
+                request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(<[u8]>::len)
+            })
+            .unwrap_or(0)
+        };
+
+        let message_bytes = body_len(None);
+        let total_bytes = match tools {
+            Some(tools) => body_len(Some(tools)).max(message_bytes),
+            None => message_bytes,
+        };
+
+        RequestStats {
+            message_bytes,
+            tool_schema_bytes: total_bytes - message_bytes,
+            estimated_tokens: total_bytes.div_ceil(4),
+            message_count: chat_history.len(),
+        }
+    }
 
     /// Ad-hoc prompting for an LLM
     /// Makes zero expectations about the state of the conversation
@@ -32,21 +111,292 @@ pub trait Prompt: Send + Sync {
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-    ) -> Result<Message, Box<dyn std::error::Error>>;
+    ) -> Result<Message, WireError> {
+        self.prompt_with_options(system_prompt, chat_history, &PromptOptions::default())
+            .await
+    }
+
+    /// `prompt` with per-call options such as a response size cap.
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError>;
 
+    /// Stream a response, sending each content delta over `tx`. By default a
+    /// full channel pauses the stream; size it with `StreamOptions::channel`
+    /// or pick another `OnFull` policy through `prompt_stream_with_options`.
     async fn prompt_stream(
         &self,
         chat_history: Vec<Message>,
         system_prompt: String,
         tx: tokio::sync::mpsc::Sender<String>,
-    ) -> Result<Message, Box<dyn std::error::Error>>;
+    ) -> Result<Message, WireError> {
+        self.prompt_stream_with_options(chat_history, system_prompt, tx, &PromptOptions::default())
+            .await
+    }
+
+    /// `prompt_stream` with per-call options such as a response size cap.
+    /// Forwards the events of `prompt_stream_events_with_options` to `tx`,
+    /// or to `options.events` when set, under `options.stream`, and warns on
+    /// the reply about any dropped or stalled on the way.
+    async fn prompt_stream_with_options(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        tx: tokio::sync::mpsc::Sender<String>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        let mut events =
+            self.prompt_stream_events_with_options(system_prompt, chat_history, options);
+        let mut forwarder = Forwarder::new(&tx, options);
+
+        loop {
+            let event = tokio::select! {
+                event = std::future::poll_fn(|cx| events.as_mut().poll_next(cx)) => event,
+                // Dropping the events abandons the request
+                _ = forwarder.closed() => return Err(WireError::StreamClosed),
+            };
+            match event {
+                Some(Ok(StreamEvent {
+                    kind: StreamEventKind::Reply(mut message),
+                    ..
+                })) => {
+                    let mut warnings = RequestWarnings::new(self.deny_warnings(), self.event_log());
+                    warnings.extend(forwarder.flush().await?)?;
+                    message.metadata.warnings.extend(warnings.to_vec());
+                    return Ok(message);
+                }
+                Some(Ok(event)) => forwarder.send(event).await?,
+                Some(Err(err)) => return Err(err),
+                None => {
+                    return Err(WireError::StreamProtocol {
+                        reason: "the events ended without a reply".to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// `prompt_stream` as a `Stream` of `StreamEvent`s: content, tool calls,
+    /// usage and the like as the provider sends them, then the reply as
+    /// `StreamEventKind::Reply`. Nothing is sent until the stream is polled, and
+    /// a caller that stops polling pauses it.
+    ///
+    /// ```
+    /// use wire::prelude::*;
+    /// use wire::types::StreamEventKind;
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = wire::EchoClient::new();
+    /// let history = vec![client
+    ///     .new_message("hello there".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build()];
+    /// let mut events = client.prompt_stream_events(String::new(), history);
+    ///
+    /// let mut streamed = String::new();
+    /// while let Some(event) = std::future::poll_fn(|cx| events.as_mut().poll_next(cx)).await {
+    ///     match event?.kind {
+    ///         StreamEventKind::ContentDelta(delta) => streamed.push_str(&delta),
+    ///         StreamEventKind::Reply(reply) => assert_eq!(reply.content, streamed),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok::<(), WireError>(())
+    /// # }).unwrap();
+    /// ```
+    fn prompt_stream_events(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+    ) -> EventStream<'_> {
+        self.prompt_stream_events_with_options(
+            system_prompt,
+            chat_history,
+            &PromptOptions::default(),
+        )
+    }
+
+    /// `prompt_stream_events` with per-call options. `options.stream` and
+    /// `options.events` don't apply: the stream goes as fast as it is polled.
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_>;
+
+    /// Stream a response, but stop waiting at `deadline` as measured by
+    /// `clock()`. If the model has not finished by then the request is dropped
+    /// and whatever content had arrived is returned with
+    /// `FinishReason::Deadline`.
+    async fn prompt_with_deadline(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        deadline: std::time::Instant,
+    ) -> Result<PartialMessage, WireError> {
+        self.prompt_with_deadline_with_options(
+            system_prompt,
+            chat_history,
+            deadline,
+            &PromptOptions::default(),
+        )
+        .await
+    }
+
+    /// `prompt_with_deadline` with per-call options.
+    async fn prompt_with_deadline_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        deadline: std::time::Instant,
+        options: &PromptOptions,
+    ) -> Result<PartialMessage, WireError> {
+        let mut events =
+            self.prompt_stream_events_with_options(system_prompt.clone(), chat_history, options);
+        let mut expired = self.clock().sleep_until(deadline);
+
+        let mut content = String::new();
+        loop {
+            tokio::select! {
+                biased;
+                event = std::future::poll_fn(|cx| events.as_mut().poll_next(cx)) => match event {
+                    Some(Ok(StreamEvent { kind: StreamEventKind::ContentDelta(delta), .. })) => {
+                        content.push_str(&delta)
+                    }
+                    Some(Ok(StreamEvent { kind: StreamEventKind::Reply(message), .. })) => {
+                        let finish_reason = message
+                            .metadata
+                            .finish_reason()
+                            .unwrap_or(FinishReason::Complete);
+                        return Ok(PartialMessage {
+                            message,
+                            finish_reason,
+                        });
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err),
+                    None => break,
+                },
+                _ = &mut expired => break,
+            }
+        }
 
+        // Abandon the request; its reader stops at the next chunk
+        drop(events);
+
+        let mut message = self
+            .new_message(content)
+            .message_type(MessageType::Assistant)
+            .build();
+        message.system_prompt = system_prompt;
+
+        Ok(PartialMessage {
+            message,
+            finish_reason: FinishReason::Deadline,
+        })
+    }
+
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError>;
+
+    /// This client's tool loop, if it has one.
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        None
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.tools().is_some()
+    }
+
+    /// This client's request-over-a-socket transport, if it has one.
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        None
+    }
+}
+
+/// The stream `PromptCore::prompt_stream_events` returns.
+pub type EventStream<'a> = Pin<Box<dyn Stream<Item = Result<StreamEvent, WireError>> + Send + 'a>>;
+
+type Reply<'a> = Pin<Box<dyn Future<Output = Result<Message, WireError>> + Send + 'a>>;
+
+/// The events stream of a client whose stream processor is `reply`: it is
+/// given the `EventSlot` to leave its events in, and returns the reply.
+pub(crate) fn event_stream<'a, F, Fut>(reply: F) -> EventStream<'a>
+where
+    F: FnOnce(EventSlot) -> Fut,
+    Fut: Future<Output = Result<Message, WireError>> + Send + 'a,
+{
+    let slot = EventSlot::default();
+    Box::pin(PromptEvents {
+        reply: Some(Box::pin(reply(slot.clone()))),
+        slot,
+        seq: 0,
+    })
+}
+
+/// The events of a client that has nothing to report before its reply: the
+/// `Reply` alone, once `reply` resolves. For `PromptCore` implementations
+/// written outside wire.
+pub fn reply_events<'a, F>(reply: F) -> EventStream<'a>
+where
+    F: Future<Output = Result<Message, WireError>> + Send + 'a,
+{
+    event_stream(|_| reply)
+}
+
+/// Runs a stream processor, handing out each event it leaves as soon as it
+/// does, then the reply.
+struct PromptEvents<'a> {
+    reply: Option<Reply<'a>>,
+    slot: EventSlot,
+    /// `seq` of the next event.
+    seq: u64,
+}
+
+impl Stream for PromptEvents<'_> {
+    type Item = Result<StreamEvent, WireError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(reply) = this.reply.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        // The processor waits on its slot until the event is taken
+        let polled = reply.as_mut().poll(cx);
+        if let Some(event) = this.slot.take() {
+            this.seq = event.seq + 1;
+            return Poll::Ready(Some(Ok(event)));
+        }
+
+        match polled {
+            Poll::Ready(reply) => {
+                this.reply = None;
+                Poll::Ready(Some(reply.map(|message| StreamEvent {
+                    seq: this.seq,
+                    kind: StreamEventKind::Reply(message),
+                })))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Clients that can run a tool loop.
+#[async_trait::async_trait]
+pub trait ToolCapable: PromptCore {
+    /// Run the tool loop until the model answers without calling a tool. If
+    /// `chat_history` ends in calls without outputs, those tools run first;
+    /// see `tool_loop::record_tool_outputs` for outputs computed elsewhere.
     async fn prompt_with_tools(
         &self,
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>>;
+    ) -> Result<Vec<Message>, WireError>;
 
     async fn prompt_with_tools_with_status(
         &self,
@@ -54,36 +404,238 @@ pub trait Prompt: Send + Sync {
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>>;
+    ) -> Result<Vec<Message>, WireError>;
 
-    fn read_json_response(
+    /// Run the tool loop, sending progress to `tx` if given, and return the
+    /// history with the `ToolStatus` events reported along the way. Events
+    /// are kept whether or not anyone was listening.
+    ///
+    /// The built-in clients record their events; the default implementation,
+    /// for clients written elsewhere, returns none.
+    async fn run_tool_loop(
         &self,
-        response_json: &serde_json::Value,
-    ) -> Result<String, Box<dyn std::error::Error>>;
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let messages = match tx {
+            Some(tx) => {
+                self.prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
+                    .await?
+            }
+            None => {
+                self.prompt_with_tools(system_prompt, chat_history, tools)
+                    .await?
+            }
+        };
+
+        Ok(ToolLoopResult {
+            messages,
+            events: Vec::new(),
+            dropped_events: 0,
+        })
+    }
+}
 
+/// Clients that can write a request as raw HTTP/1.1 and read the response
+/// off any byte stream, for callers that manage the connection themselves.
+/// The clients' own streams don't go through it.
+#[async_trait::async_trait]
+pub trait RawTransport: PromptCore {
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError>;
+
+    /// Read the response to a `build_request_raw` request off `stream`,
+    /// starting at the status line, and forward its deltas over `tx`.
+    /// `stream` is usually the connection the request was written to;
+    /// chunked and gzipped bodies are decoded.
     async fn process_stream(
         &self,
-        stream: TlsStream<TcpStream>,
+        stream: Box<dyn Read + Send>,
         tx: &tokio::sync::mpsc::Sender<String>,
-    ) -> Result<String, Box<dyn std::error::Error>>;
+    ) -> Result<String, WireError>;
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+/// Everything the built-in clients implement, under the one name code written
+/// before the split uses. Implemented for any type with all three traits.
+///
+/// Importing `Prompt` no longer brings `prompt` and friends into scope on a
+/// concrete client; see the migration note in `CHANGELOG.md`. In short:
+/// import `PromptCore`, plus `ToolCapable` or `RawTransport` for the methods
+/// you call (or `wire::prelude::*` for all three), and write
+/// `dyn PromptCore` where you stored a `dyn Prompt`.
+#[deprecated(
+    note = "import `PromptCore`, `ToolCapable` and `RawTransport` (or `wire::prelude::*`) and use `dyn PromptCore`; see CHANGELOG.md"
+)]
+pub trait Prompt: PromptCore + ToolCapable + RawTransport {}
+
+#[allow(deprecated)]
+impl<T: PromptCore + ToolCapable + RawTransport + ?Sized> Prompt for T {}
+
+/// Serialized as `{"provider": "...", "model": "..."}`. See
+/// `tests/fixtures/schema/README.md` for the stability policy covering this
+/// format; `Deserialize` is implemented by hand to keep reading older shapes.
+///
+/// Provider variants exist only when their cargo feature (`openai`,
+/// `anthropic`, `gemini`, `groq`, `ollama`, `openrouter`, `cohere`,
+/// `together`, `fireworks`, `perplexity`, `compatible`) is enabled, hence
+/// `non_exhaustive`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize)]
 #[serde(tag = "provider", content = "model")]
+#[non_exhaustive]
 pub enum API {
+    #[cfg(feature = "openai")]
     #[serde(rename = "openai")]
     OpenAI(OpenAIModel),
+    #[cfg(feature = "anthropic")]
     #[serde(rename = "anthropic")]
     Anthropic(AnthropicModel),
+    #[cfg(feature = "gemini")]
     #[serde(rename = "gemini")]
     Gemini(GeminiModel),
+    #[cfg(feature = "groq")]
+    #[serde(rename = "groq")]
+    Groq(GroqModel),
+    #[cfg(feature = "ollama")]
+    #[serde(rename = "ollama")]
+    Ollama(OllamaModel),
+    #[cfg(feature = "openrouter")]
+    #[serde(rename = "openrouter")]
+    OpenRouter(OpenRouterModel),
+    #[cfg(feature = "cohere")]
+    #[serde(rename = "cohere")]
+    Cohere(CohereModel),
+    #[cfg(feature = "together")]
+    #[serde(rename = "together")]
+    Together(TogetherModel),
+    #[cfg(feature = "fireworks")]
+    #[serde(rename = "fireworks")]
+    Fireworks(FireworksModel),
+    #[cfg(feature = "perplexity")]
+    #[serde(rename = "perplexity")]
+    Perplexity(PerplexityModel),
+    /// Any other server speaking OpenAI's chat completions API; see
+    /// `compatible::OpenAICompatibleClient`.
+    #[cfg(feature = "compatible")]
+    #[serde(rename = "custom")]
+    Compatible(CompatibleModel),
+    /// Offline models built into the crate; see `echo::EchoClient`.
+    #[serde(rename = "wire")]
+    Wire(WireModel),
+}
+
+/// The provider half of an `API`, for behaviour that doesn't depend on the
+/// model.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Provider {
+    OpenAI,
+    Anthropic,
+    Gemini,
+    Groq,
+    Ollama,
+    OpenRouter,
+    Cohere,
+    Together,
+    Fireworks,
+    Perplexity,
+    /// An `OpenAICompatibleClient`'s gateway, whichever it is. The gateway's
+    /// own label is in its `CompatibleModel`.
+    Compatible,
+    Wire,
+}
+
+impl Provider {
+    /// The name used for the provider in serialized `API` values.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Gemini => "gemini",
+            Provider::Groq => "groq",
+            Provider::Ollama => "ollama",
+            Provider::OpenRouter => "openrouter",
+            Provider::Cohere => "cohere",
+            Provider::Together => "together",
+            Provider::Fireworks => "fireworks",
+            Provider::Perplexity => "perplexity",
+            Provider::Compatible => "custom",
+            Provider::Wire => "wire",
+        }
+    }
+
+    /// The environment variable the provider's client reads its API key
+    /// from by default, or `None` for providers that take no key (a local
+    /// Ollama server, the built-in models).
+    pub fn key_var(&self) -> Option<&'static str> {
+        match self {
+            Provider::OpenAI => Some("OPENAI_API_KEY"),
+            Provider::Anthropic => Some("ANTHROPIC_API_KEY"),
+            Provider::Gemini => Some("GEMINI_API_KEY"),
+            Provider::Groq => Some("GROQ_API_KEY"),
+            Provider::OpenRouter => Some("OPENROUTER_API_KEY"),
+            Provider::Cohere => Some("COHERE_API_KEY"),
+            Provider::Together => Some("TOGETHER_API_KEY"),
+            Provider::Fireworks => Some("FIREWORKS_API_KEY"),
+            Provider::Perplexity => Some("PERPLEXITY_API_KEY"),
+            Provider::Compatible => Some("OPENAI_COMPATIBLE_API_KEY"),
+            Provider::Ollama | Provider::Wire => None,
+        }
+    }
+}
+
+/// The role `provider` expects for a message of `message_type`. Every request
+/// builder maps roles through this; `MessageType`'s `Display` is the crate's
+/// own naming and is not a wire format.
+///
+/// Tool traffic that a provider carries in content blocks (Anthropic's
+/// `tool_result`, Gemini's `functionResponse`) still needs a role for the turn
+/// that holds it, and providers without a system role in their history send
+/// system messages as user turns.
+pub fn role_for(provider: Provider, message_type: MessageType) -> &'static str {
+    match (provider, message_type) {
+        (
+            Provider::OpenAI
+            | Provider::Groq
+            | Provider::Ollama
+            | Provider::OpenRouter
+            | Provider::Cohere
+            | Provider::Together
+            | Provider::Fireworks
+            | Provider::Perplexity
+            | Provider::Compatible
+            | Provider::Wire,
+            MessageType::System,
+        ) => "system",
+        (
+            Provider::OpenAI
+            | Provider::Groq
+            | Provider::Ollama
+            | Provider::OpenRouter
+            | Provider::Cohere
+            | Provider::Together
+            | Provider::Fireworks
+            | Provider::Perplexity
+            | Provider::Compatible
+            | Provider::Wire,
+            MessageType::FunctionCallOutput,
+        ) => "tool",
+        (Provider::Gemini, MessageType::Assistant | MessageType::FunctionCall) => "model",
+        (_, MessageType::Assistant | MessageType::FunctionCall) => "assistant",
+        (_, MessageType::User | MessageType::System | MessageType::FunctionCallOutput) => "user",
+    }
 }
 
+#[cfg(feature = "openai")]
 #[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum OpenAIModel {
     #[serde(rename = "gpt-5")]
     GPT5,
-    #[serde(rename = "gpt-4.1")]
+    #[serde(rename = "gpt-4.1", alias = "gpt-4o")]
     GPT4o,
     #[serde(rename = "gpt-4o-mini")]
     GPT4oMini,
@@ -93,6 +645,7 @@ pub enum OpenAIModel {
     O1Mini,
 }
 
+#[cfg(feature = "anthropic")]
 #[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum AnthropicModel {
     #[serde(rename = "claude-opus-4-1-20250805")]
@@ -115,9 +668,13 @@ pub enum AnthropicModel {
     Claude3Opus,
 }
 
+#[cfg(feature = "gemini")]
 #[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum GeminiModel {
-    #[serde(rename = "gemini-2.5-flash-preview-04-17")]
+    #[serde(
+        rename = "gemini-2.5-flash-preview-04-17",
+        alias = "gemini-2.5-pro-exp-03-25"
+    )]
     Gemini25ProExp,
     #[serde(rename = "gemini-2.0-flash")]
     Gemini20Flash,
@@ -127,24 +684,298 @@ pub enum GeminiModel {
     GeminiEmbedding,
 }
 
+#[cfg(feature = "groq")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GroqModel {
+    #[serde(rename = "llama-3.3-70b-versatile")]
+    Llama3370bVersatile,
+    #[serde(rename = "llama-3.1-8b-instant")]
+    Llama318bInstant,
+    #[serde(rename = "llama3-70b-8192")]
+    Llama370b8192,
+}
+
+#[cfg(feature = "cohere")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum CohereModel {
+    #[serde(rename = "command-a-03-2025")]
+    CommandA,
+    #[serde(rename = "command-r-plus-08-2024")]
+    CommandRPlus,
+    #[serde(rename = "command-r-08-2024")]
+    CommandR,
+    #[serde(rename = "command-r7b-12-2024")]
+    CommandR7B,
+}
+
+/// Whatever model the Ollama server has pulled, named as Ollama names it
+/// (`llama3.1:8b`). The crate can't know which models a server has, so any
+/// name is accepted.
+#[cfg(feature = "ollama")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct OllamaModel(pub(crate) String);
+
+/// A model id as OpenRouter names it, upstream provider first
+/// (`anthropic/claude-sonnet-4`). OpenRouter's catalogue changes too often to
+/// list, so any id is accepted.
+#[cfg(feature = "openrouter")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct OpenRouterModel(pub(crate) String);
+
+/// A model id as Together names it, organisation first
+/// (`Qwen/Qwen2.5-72B-Instruct-Turbo`). Together adds and retires checkpoints
+/// too often to list them, so any id is accepted.
+#[cfg(feature = "together")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct TogetherModel(pub(crate) String);
+
+/// A model id as Fireworks names it, by path
+/// (`accounts/fireworks/models/llama-v3p1-70b-instruct`). Accounts can host
+/// their own fine-tunes, so any id is accepted.
+#[cfg(feature = "fireworks")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct FireworksModel(pub(crate) String);
+
+#[cfg(feature = "perplexity")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PerplexityModel {
+    #[serde(rename = "sonar")]
+    Sonar,
+    #[serde(rename = "sonar-pro")]
+    SonarPro,
+    #[serde(rename = "sonar-reasoning")]
+    SonarReasoning,
+    #[serde(rename = "sonar-reasoning-pro")]
+    SonarReasoningPro,
+    #[serde(rename = "sonar-deep-research")]
+    SonarDeepResearch,
+}
+
+/// A model on a gateway serving OpenAI's chat completions API, with the label
+/// that tells gateways apart. Stored as `label/model` (`litellm/my-model`);
+/// the label defaults to `custom`, so `custom/my-model` is both how the model
+/// is named to `new_client` and how it is stored.
+#[cfg(feature = "compatible")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct CompatibleModel {
+    pub(crate) label: String,
+    pub(crate) name: String,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum WireModel {
+    #[serde(rename = "wire:echo")]
+    Echo,
+}
+
+impl<'de> serde::Deserialize<'de> for API {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Current format: `{"provider": "openai", "model": "gpt-4.1"}`
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "provider", content = "model")]
+        enum Tagged {
+            #[cfg(feature = "openai")]
+            #[serde(rename = "openai")]
+            OpenAI(OpenAIModel),
+            #[cfg(feature = "anthropic")]
+            #[serde(rename = "anthropic")]
+            Anthropic(AnthropicModel),
+            #[cfg(feature = "gemini")]
+            #[serde(rename = "gemini")]
+            Gemini(GeminiModel),
+            #[cfg(feature = "groq")]
+            #[serde(rename = "groq")]
+            Groq(GroqModel),
+            #[cfg(feature = "ollama")]
+            #[serde(rename = "ollama")]
+            Ollama(OllamaModel),
+            #[cfg(feature = "openrouter")]
+            #[serde(rename = "openrouter")]
+            OpenRouter(OpenRouterModel),
+            #[cfg(feature = "cohere")]
+            #[serde(rename = "cohere")]
+            Cohere(CohereModel),
+            #[cfg(feature = "together")]
+            #[serde(rename = "together")]
+            Together(TogetherModel),
+            #[cfg(feature = "fireworks")]
+            #[serde(rename = "fireworks")]
+            Fireworks(FireworksModel),
+            #[cfg(feature = "perplexity")]
+            #[serde(rename = "perplexity")]
+            Perplexity(PerplexityModel),
+            #[cfg(feature = "compatible")]
+            #[serde(rename = "custom")]
+            Compatible(CompatibleModel),
+            #[serde(rename = "wire")]
+            Wire(WireModel),
+        }
+
+        // Pre-workspace format: `{"OpenAI": "gpt-4o"}`
+        #[derive(serde::Deserialize)]
+        enum Legacy {
+            #[cfg(feature = "openai")]
+            OpenAI(OpenAIModel),
+            #[cfg(feature = "anthropic")]
+            Anthropic(AnthropicModel),
+            #[cfg(feature = "gemini")]
+            Gemini(GeminiModel),
+            #[cfg(feature = "groq")]
+            Groq(GroqModel),
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Tagged(Tagged),
+            Legacy(Legacy),
+        }
+
+        match Repr::deserialize(deserializer) {
+            Ok(Repr::Tagged(tagged)) => Ok(match tagged {
+                #[cfg(feature = "openai")]
+                Tagged::OpenAI(model) => API::OpenAI(model),
+                #[cfg(feature = "anthropic")]
+                Tagged::Anthropic(model) => API::Anthropic(model),
+                #[cfg(feature = "gemini")]
+                Tagged::Gemini(model) => API::Gemini(model),
+                #[cfg(feature = "groq")]
+                Tagged::Groq(model) => API::Groq(model),
+                #[cfg(feature = "ollama")]
+                Tagged::Ollama(model) => API::Ollama(model),
+                #[cfg(feature = "openrouter")]
+                Tagged::OpenRouter(model) => API::OpenRouter(model),
+                #[cfg(feature = "cohere")]
+                Tagged::Cohere(model) => API::Cohere(model),
+                #[cfg(feature = "together")]
+                Tagged::Together(model) => API::Together(model),
+                #[cfg(feature = "fireworks")]
+                Tagged::Fireworks(model) => API::Fireworks(model),
+                #[cfg(feature = "perplexity")]
+                Tagged::Perplexity(model) => API::Perplexity(model),
+                #[cfg(feature = "compatible")]
+                Tagged::Compatible(model) => API::Compatible(model),
+                Tagged::Wire(model) => API::Wire(model),
+            }),
+            Ok(Repr::Legacy(legacy)) => match legacy {
+                #[cfg(feature = "openai")]
+                Legacy::OpenAI(model) => Ok(API::OpenAI(model)),
+                #[cfg(feature = "anthropic")]
+                Legacy::Anthropic(model) => Ok(API::Anthropic(model)),
+                #[cfg(feature = "gemini")]
+                Legacy::Gemini(model) => Ok(API::Gemini(model)),
+                #[cfg(feature = "groq")]
+                Legacy::Groq(model) => Ok(API::Groq(model)),
+            },
+            Err(_) => Err(serde::de::Error::custom(
+                "expected an API as {\"provider\": ..., \"model\": ...} with a known model",
+            )),
+        }
+    }
+}
+
 impl API {
     pub fn from_model(model: &str) -> Result<Self, String> {
+        #[cfg(feature = "openai")]
         if let Ok(model) = OpenAIModel::from_model_name(model) {
             return Ok(API::OpenAI(model));
         }
 
+        #[cfg(feature = "anthropic")]
         if let Ok(model) = AnthropicModel::from_model_name(model) {
             return Ok(API::Anthropic(model));
         }
 
+        #[cfg(feature = "gemini")]
         if let Ok(model) = GeminiModel::from_model_name(model) {
             return Ok(API::Gemini(model));
         }
 
+        #[cfg(feature = "groq")]
+        if let Ok(model) = GroqModel::from_model_name(model) {
+            return Ok(API::Groq(model));
+        }
+
+        #[cfg(feature = "ollama")]
+        if let Ok(model) = OllamaModel::from_model_name(model) {
+            return Ok(API::Ollama(model));
+        }
+
+        #[cfg(feature = "openrouter")]
+        if let Ok(model) = OpenRouterModel::from_model_name(model) {
+            return Ok(API::OpenRouter(model));
+        }
+
+        #[cfg(feature = "cohere")]
+        if let Ok(model) = CohereModel::from_model_name(model) {
+            return Ok(API::Cohere(model));
+        }
+
+        #[cfg(feature = "together")]
+        if let Ok(model) = TogetherModel::from_model_name(model) {
+            return Ok(API::Together(model));
+        }
+
+        #[cfg(feature = "fireworks")]
+        if let Ok(model) = FireworksModel::from_model_name(model) {
+            return Ok(API::Fireworks(model));
+        }
+
+        #[cfg(feature = "perplexity")]
+        if let Ok(model) = PerplexityModel::from_model_name(model) {
+            return Ok(API::Perplexity(model));
+        }
+
+        #[cfg(feature = "compatible")]
+        if let Ok(model) = CompatibleModel::from_model_name(model) {
+            return Ok(API::Compatible(model));
+        }
+
+        if let Ok(model) = WireModel::from_model_name(model) {
+            return Ok(API::Wire(model));
+        }
+
         Err(format!("Unknown model: {}", model))
     }
 
+    /// The inverse of `to_strings`. Ollama, OpenRouter, Together and
+    /// Fireworks models are stored without their `ollama/`, `openrouter/`,
+    /// `together/` or `fireworks/` prefix, so those providers take any name,
+    /// slashes and all; `custom` takes any `label/model`.
     pub fn from_strings(provider: &str, model: &str) -> Result<Self, String> {
+        #[cfg(feature = "ollama")]
+        if provider == "ollama" {
+            return Ok(API::Ollama(OllamaModel::from(model)));
+        }
+
+        #[cfg(feature = "openrouter")]
+        if provider == "openrouter" {
+            return Ok(API::OpenRouter(OpenRouterModel::from(model)));
+        }
+
+        #[cfg(feature = "together")]
+        if provider == "together" {
+            return Ok(API::Together(TogetherModel::from(model)));
+        }
+
+        #[cfg(feature = "fireworks")]
+        if provider == "fireworks" {
+            return Ok(API::Fireworks(FireworksModel::from(model)));
+        }
+
+        #[cfg(feature = "compatible")]
+        if provider == "custom" {
+            return CompatibleModel::try_from(model.to_string()).map(API::Compatible);
+        }
+
         let api = Self::from_model(model)?;
         let (expected_provider, _) = api.to_strings();
 
@@ -158,49 +989,186 @@ impl API {
         }
     }
 
+    pub fn provider(&self) -> Provider {
+        match self {
+            #[cfg(feature = "openai")]
+            API::OpenAI(_) => Provider::OpenAI,
+            #[cfg(feature = "anthropic")]
+            API::Anthropic(_) => Provider::Anthropic,
+            #[cfg(feature = "gemini")]
+            API::Gemini(_) => Provider::Gemini,
+            #[cfg(feature = "groq")]
+            API::Groq(_) => Provider::Groq,
+            #[cfg(feature = "ollama")]
+            API::Ollama(_) => Provider::Ollama,
+            #[cfg(feature = "openrouter")]
+            API::OpenRouter(_) => Provider::OpenRouter,
+            #[cfg(feature = "cohere")]
+            API::Cohere(_) => Provider::Cohere,
+            #[cfg(feature = "together")]
+            API::Together(_) => Provider::Together,
+            #[cfg(feature = "fireworks")]
+            API::Fireworks(_) => Provider::Fireworks,
+            #[cfg(feature = "perplexity")]
+            API::Perplexity(_) => Provider::Perplexity,
+            #[cfg(feature = "compatible")]
+            API::Compatible(_) => Provider::Compatible,
+            API::Wire(_) => Provider::Wire,
+        }
+    }
+
     pub fn to_strings(&self) -> (String, String) {
         match self {
+            #[cfg(feature = "openai")]
             API::OpenAI(model) => model.to_strings(),
+            #[cfg(feature = "anthropic")]
             API::Anthropic(model) => model.to_strings(),
+            #[cfg(feature = "gemini")]
             API::Gemini(model) => model.to_strings(),
+            #[cfg(feature = "groq")]
+            API::Groq(model) => model.to_strings(),
+            #[cfg(feature = "ollama")]
+            API::Ollama(model) => model.to_strings(),
+            #[cfg(feature = "openrouter")]
+            API::OpenRouter(model) => model.to_strings(),
+            #[cfg(feature = "cohere")]
+            API::Cohere(model) => model.to_strings(),
+            #[cfg(feature = "together")]
+            API::Together(model) => model.to_strings(),
+            #[cfg(feature = "fireworks")]
+            API::Fireworks(model) => model.to_strings(),
+            #[cfg(feature = "perplexity")]
+            API::Perplexity(model) => model.to_strings(),
+            #[cfg(feature = "compatible")]
+            API::Compatible(model) => model.to_strings(),
+            API::Wire(model) => model.to_strings(),
         }
     }
 
-    pub fn to_client(&self) -> Box<dyn Prompt> {
+    pub fn to_client(&self) -> Box<dyn PromptCore> {
         match self {
+            #[cfg(feature = "openai")]
             API::OpenAI(model) => Box::new(crate::openai::OpenAIClient::new(model.clone())),
+            #[cfg(feature = "anthropic")]
             API::Anthropic(model) => {
                 Box::new(crate::anthropic::AnthropicClient::new(model.clone()))
             }
+            #[cfg(feature = "gemini")]
             API::Gemini(model) => Box::new(crate::gemini::GeminiClient::new(model.clone())),
+            #[cfg(feature = "groq")]
+            API::Groq(model) => Box::new(crate::groq::GroqClient::new(model.clone())),
+            #[cfg(feature = "ollama")]
+            API::Ollama(model) => Box::new(crate::ollama::OllamaClient::new(model.clone())),
+            #[cfg(feature = "openrouter")]
+            API::OpenRouter(model) => {
+                Box::new(crate::openrouter::OpenRouterClient::new(model.clone()))
+            }
+            #[cfg(feature = "cohere")]
+            API::Cohere(model) => Box::new(crate::cohere::CohereClient::new(model.clone())),
+            #[cfg(feature = "together")]
+            API::Together(model) => Box::new(crate::together::TogetherClient::new(model.clone())),
+            #[cfg(feature = "fireworks")]
+            API::Fireworks(model) => {
+                Box::new(crate::fireworks::FireworksClient::new(model.clone()))
+            }
+            #[cfg(feature = "perplexity")]
+            API::Perplexity(model) => {
+                Box::new(crate::perplexity::PerplexityClient::new(model.clone()))
+            }
+            #[cfg(feature = "compatible")]
+            API::Compatible(model) => Box::new(crate::compatible::OpenAICompatibleClient::new(
+                model.clone(),
+            )),
+            API::Wire(WireModel::Echo) => Box::new(crate::echo::EchoClient::new()),
         }
     }
 
-    pub fn to_client_with_options(&self, options: ClientOptions) -> Box<dyn Prompt> {
+    pub fn to_client_with_options(&self, options: ClientOptions) -> Box<dyn PromptCore> {
         match self {
+            #[cfg(feature = "openai")]
             API::OpenAI(model) => Box::new(crate::openai::OpenAIClient::with_options(
                 model.clone(),
                 options.clone(),
             )),
+            #[cfg(feature = "anthropic")]
             API::Anthropic(model) => Box::new(crate::anthropic::AnthropicClient::with_options(
                 model.clone(),
                 options.clone(),
             )),
+            #[cfg(feature = "gemini")]
             API::Gemini(model) => Box::new(crate::gemini::GeminiClient::with_options(
                 model.clone(),
                 options.clone(),
             )),
+            #[cfg(feature = "groq")]
+            API::Groq(model) => Box::new(crate::groq::GroqClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "ollama")]
+            API::Ollama(model) => Box::new(crate::ollama::OllamaClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "openrouter")]
+            API::OpenRouter(model) => Box::new(crate::openrouter::OpenRouterClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "cohere")]
+            API::Cohere(model) => Box::new(crate::cohere::CohereClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "together")]
+            API::Together(model) => Box::new(crate::together::TogetherClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "fireworks")]
+            API::Fireworks(model) => Box::new(crate::fireworks::FireworksClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "perplexity")]
+            API::Perplexity(model) => Box::new(crate::perplexity::PerplexityClient::with_options(
+                model.clone(),
+                options.clone(),
+            )),
+            #[cfg(feature = "compatible")]
+            API::Compatible(model) => {
+                Box::new(crate::compatible::OpenAICompatibleClient::with_options(
+                    model.clone(),
+                    options.clone(),
+                ))
+            }
+            API::Wire(WireModel::Echo) => {
+                Box::new(crate::echo::EchoClient::with_options(options.clone()))
+            }
         }
     }
 }
 
+/// Every provider model the crate can talk to with the enabled features.
+/// Built-in offline models such as `wire:echo` are not listed, nor are
+/// Ollama's, which depend on what the local server has pulled, or
+/// OpenRouter's, Together's and Fireworks', which change too often, or those behind
+/// OpenAI-compatible gateways.
 pub fn get_available_models() -> Vec<API> {
-    vec![
+    #[allow(unused_mut)]
+    let mut models = Vec::new();
+
+    #[cfg(feature = "openai")]
+    models.extend([
         API::OpenAI(OpenAIModel::GPT5),
         API::OpenAI(OpenAIModel::GPT4o),
         API::OpenAI(OpenAIModel::GPT4oMini),
         API::OpenAI(OpenAIModel::O1Preview),
         API::OpenAI(OpenAIModel::O1Mini),
+    ]);
+
+    #[cfg(feature = "anthropic")]
+    models.extend([
         API::Anthropic(AnthropicModel::ClaudeOpus41),
         API::Anthropic(AnthropicModel::ClaudeOpus4),
         API::Anthropic(AnthropicModel::ClaudeSonnet4),
@@ -210,9 +1178,39 @@ pub fn get_available_models() -> Vec<API> {
         API::Anthropic(AnthropicModel::Claude35SonnetOld),
         API::Anthropic(AnthropicModel::Claude3Haiku),
         API::Anthropic(AnthropicModel::Claude3Opus),
+    ]);
+
+    #[cfg(feature = "gemini")]
+    models.extend([
         API::Gemini(GeminiModel::Gemini25ProExp),
         API::Gemini(GeminiModel::Gemini20Flash),
         API::Gemini(GeminiModel::Gemini20FlashLite),
         API::Gemini(GeminiModel::GeminiEmbedding),
-    ]
+    ]);
+
+    #[cfg(feature = "groq")]
+    models.extend([
+        API::Groq(GroqModel::Llama3370bVersatile),
+        API::Groq(GroqModel::Llama318bInstant),
+        API::Groq(GroqModel::Llama370b8192),
+    ]);
+
+    #[cfg(feature = "cohere")]
+    models.extend([
+        API::Cohere(CohereModel::CommandA),
+        API::Cohere(CohereModel::CommandRPlus),
+        API::Cohere(CohereModel::CommandR),
+        API::Cohere(CohereModel::CommandR7B),
+    ]);
+
+    #[cfg(feature = "perplexity")]
+    models.extend([
+        API::Perplexity(PerplexityModel::Sonar),
+        API::Perplexity(PerplexityModel::SonarPro),
+        API::Perplexity(PerplexityModel::SonarReasoning),
+        API::Perplexity(PerplexityModel::SonarReasoningPro),
+        API::Perplexity(PerplexityModel::SonarDeepResearch),
+    ]);
+
+    models
 }
@@ -0,0 +1,79 @@
+//! Time as seen by the clients.
+//!
+//! Anything that waits (currently `Prompt::prompt_with_deadline`) asks the
+//! client's `Clock` for the time and for sleeps instead of calling tokio
+//! directly, so tests can swap in `mock::TestClock` and move time forward by
+//! hand. The default, `TokioClock`, is real time.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[async_trait::async_trait]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    async fn sleep(&self, duration: Duration);
+
+    /// Sleep until `deadline`, returning at once if it has passed.
+    async fn sleep_until(&self, deadline: Instant) {
+        let remaining = deadline.saturating_duration_since(self.now());
+        if !remaining.is_zero() {
+            self.sleep(remaining).await;
+        }
+    }
+}
+
+/// Real time, through `tokio::time`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioClock;
+
+#[async_trait::async_trait]
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// A `Clock` shared between clients, as stored in `ClientOptions`.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new<C>(clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        Self(Arc::new(clock))
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(TokioClock)
+    }
+}
+
+impl std::fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedClock")
+    }
+}
+
+#[async_trait::async_trait]
+impl Clock for SharedClock {
+    fn now(&self) -> Instant {
+        self.0.now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.0.sleep(duration).await;
+    }
+
+    async fn sleep_until(&self, deadline: Instant) {
+        self.0.sleep_until(deadline).await;
+    }
+}
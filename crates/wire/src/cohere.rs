@@ -0,0 +1,1080 @@
+//! Cohere's v2 chat API, for the Command models.
+//!
+//! `CohereClient` talks to `/v2/chat` on `api.cohere.com`. The request looks
+//! like OpenAI's at a glance, but the details differ: a reply's text comes
+//! as a list of content blocks, the text that accompanies tool calls is a
+//! `tool_plan`, and tool outputs go back as `document` blocks. Streams are
+//! server-sent events typed `content-delta`, `tool-call-delta` and so on,
+//! ending with `message-end`. Token counts are taken from
+//! `usage.billed_units`, what Cohere charges for.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::api::{
+    event_stream, role_for, CohereModel, EventStream, PromptCore, Provider, RawTransport,
+    ToolCapable, API,
+};
+use crate::clock::Clock;
+use crate::compression::ACCEPT_ENCODING;
+use crate::config::{ClientOptions, Endpoint, PromptOptions, Scheme, ToolChoice};
+use crate::credentials::Credentials;
+use crate::error::WireError;
+use crate::event_log::{emit, prepare_stream, send_logged, EventLog, WireEvent};
+use crate::metrics::{report_metrics, warn_on_tool_schema_size, LatencyRecorder};
+use crate::moderation::check_incoming;
+use crate::network_common::{cancellable, open_stream, parse_event, ByteStream, ParsedEvent};
+use crate::payload::{self, json_body};
+use crate::rate_limit::estimate_tokens;
+use crate::retry::with_retries;
+use crate::sanitize::{check_outbound, outbound};
+use crate::tool_loop::{
+    report_interim_text, resume_pending_calls, run_tool_calls, synthesize_call_ids, StatusLog,
+    ToolLoop, ToolLoopResult,
+};
+use crate::tool_protocol::{prompt_with_text_tools, text_protocol_tool_choice, ToolTransport};
+use crate::types::{
+    ContentCap, DeltaSequencer, EventSlot, Function, FunctionCall, Message, MessageBuilder,
+    MessageMetadata, MessageType, StreamEventKind, StreamedContent, Tool, ToolSpec,
+    TruncatedStream,
+};
+use crate::warning::{RequestWarnings, WireWarning};
+
+const CHAT_PATH: &str = "/v2/chat";
+
+impl CohereModel {
+    /// Turn a Cohere model id into its variant.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model {
+            "command-a-03-2025" => Ok(CohereModel::CommandA),
+            "command-r-plus-08-2024" => Ok(CohereModel::CommandRPlus),
+            "command-r-08-2024" => Ok(CohereModel::CommandR),
+            "command-r7b-12-2024" => Ok(CohereModel::CommandR7B),
+            _ => Err(format!("Unknown Cohere model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple.
+    pub fn to_strings(&self) -> (String, String) {
+        let model = match self {
+            CohereModel::CommandA => "command-a-03-2025",
+            CohereModel::CommandRPlus => "command-r-plus-08-2024",
+            CohereModel::CommandR => "command-r-08-2024",
+            CohereModel::CommandR7B => "command-r7b-12-2024",
+        };
+
+        ("cohere".to_string(), model.to_string())
+    }
+}
+
+impl std::str::FromStr for CohereModel {
+    type Err = String;
+
+    fn from_str(model: &str) -> Result<Self, Self::Err> {
+        CohereModel::from_model_name(model)
+    }
+}
+
+impl TryFrom<&str> for CohereModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        CohereModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
+    }
+}
+
+impl TryFrom<String> for CohereModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        CohereModel::try_from(model.as_str())
+    }
+}
+
+/// Client for Cohere's Command models, authenticated with `COHERE_API_KEY`.
+///
+/// Tools are sent natively. Cohere has no explicit `auto` tool choice, so
+/// `ToolChoice::Auto` leaves `tool_choice` out of the request; `Any` and
+/// `None` are sent as `REQUIRED` and `NONE`.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("command-r-08-2024", wire::mock::DoctestCall::Tools).await;
+/// use wire::api::{PromptCore, ToolCapable};
+/// use wire::cohere::CohereClient;
+/// use wire::types::{MessageType, Tool, ToolWrapper};
+///
+/// let weather = Tool {
+///     function_type: "function".to_string(),
+///     name: "get_weather".to_string(),
+///     description: "Current weather for a city.".to_string(),
+///     parameters: serde_json::json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"],
+///     }),
+///     strict: false,
+///     function: Box::new(ToolWrapper(|args: serde_json::Value| {
+///         serde_json::json!(format!("sunny in {}", args["city"].as_str().unwrap()))
+///     })),
+/// };
+///
+/// let client = CohereClient::try_with_options("command-r-08-2024", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let history = client
+///     .prompt_with_tools("Use the tools you have.", vec![question], vec![weather])
+///     .await
+///     .unwrap();
+/// assert_eq!(history.last().unwrap().content, "It is sunny in Paris.");
+/// # });
+/// ```
+pub struct CohereClient {
+    pub http_client: reqwest::Client,
+    pub stream_client: reqwest::Client,
+    pub model: CohereModel,
+    pub host: String,
+    pub port: u16,
+    pub scheme: Scheme,
+    pub credentials: Credentials,
+    /// The options the client was built with. Requests read their settings
+    /// from here; the fields above are the ones resolved from them.
+    pub options: ClientOptions,
+    /// Options given at construction that requests leave out, reported with
+    /// every prompt.
+    pub(crate) ignored_options: Vec<WireWarning>,
+}
+
+impl CohereClient {
+    /// Construct a client with default options against `api.cohere.com`.
+    pub fn new<M>(model: M) -> Self
+    where
+        M: Into<CohereModel>,
+    {
+        Self::with_options(model, ClientOptions::default())
+    }
+
+    /// Construct a client with custom transport options such as the base URL
+    /// or proxy behaviour.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<CohereModel>,
+    {
+        let mut client = Self {
+            http_client: reqwest::Client::new(),
+            stream_client: ClientOptions::default().stream_client(),
+            model: model.into(),
+            host: "api.cohere.com".to_string(),
+            port: 443,
+            scheme: Scheme::Https,
+            credentials: Credentials::from_env("COHERE_API_KEY"),
+            options: ClientOptions::default(),
+            ignored_options: Vec::new(),
+        };
+
+        client.apply_options(options);
+        client
+    }
+
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when Cohere has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<CohereModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<CohereModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+
+    /// Apply caller-supplied configuration overlays.
+    fn apply_options(&mut self, options: ClientOptions) {
+        self.http_client = options.http_client();
+        self.stream_client = options.stream_client();
+
+        match &options.endpoint {
+            Endpoint::Default => {}
+            Endpoint::BaseUrl(endpoint) => {
+                self.host = endpoint.host.clone();
+                self.port = endpoint.port;
+                self.scheme = endpoint.scheme;
+            }
+        }
+
+        if options.thinking_level.is_some() {
+            self.ignored_options.push(WireWarning::option_ignored(
+                "thinking_level",
+                Provider::Cohere,
+                "wire sends Cohere no thinking settings",
+            ));
+        }
+
+        self.options = options;
+    }
+
+    /// The warnings of a request made with `options`, starting with the
+    /// options it leaves out.
+    fn request_warnings(&self, options: &PromptOptions) -> Result<RequestWarnings<'_>, WireError> {
+        let mut warnings =
+            RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+        warnings.extend(self.ignored_options.iter().cloned())?;
+        warnings.extend(options.header_warnings(Provider::Cohere))?;
+        Ok(warnings)
+    }
+
+    fn api(&self) -> API {
+        API::Cohere(self.model.clone())
+    }
+
+    /// Render the scheme/host/port tuple into a base URL.
+    fn origin(&self) -> String {
+        match (self.scheme, self.port) {
+            (Scheme::Https, 443) => format!("https://{}", self.host),
+            (Scheme::Http, 80) => format!("http://{}", self.host),
+            _ => format!("{}://{}:{}", self.scheme.as_str(), self.host, self.port),
+        }
+    }
+
+    /// Produce the correct `Host` header, including the port when required.
+    fn host_header(&self) -> String {
+        match (self.scheme, self.port) {
+            (Scheme::Https, 443) | (Scheme::Http, 80) => self.host.clone(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    /// The `/v2/chat` body. Assistant turns that called tools carry their
+    /// text as the `tool_plan`, and tool outputs go back as a single
+    /// `document` block answering the call's id.
+    fn request_body(
+        &self,
+        system_prompt: &str,
+        chat_history: &[Message],
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
+    ) -> Result<serde_json::Value, WireError> {
+        let filter = self.options.content_filter.as_ref();
+        let text = |message_type: MessageType, content: &str| match filter {
+            Some(filter) => outbound(
+                &filter.apply(&message_type, content),
+                self.options.sanitize_policy,
+            ),
+            None => outbound(content, self.options.sanitize_policy),
+        };
+
+        let mut messages = vec![serde_json::json!({
+            "role": role_for(Provider::Cohere, MessageType::System),
+            "content": text(MessageType::System, system_prompt),
+        })];
+        for m in chat_history {
+            let role = role_for(Provider::Cohere, m.message_type);
+            let content = text(m.message_type, &m.content);
+
+            let calls = m.tool_calls.as_ref().filter(|calls| !calls.is_empty());
+            let message = if let Some(calls) = calls {
+                let mut message = serde_json::json!({
+                    "role": role,
+                    "tool_calls": calls
+                        .iter()
+                        .map(|call| {
+                            serde_json::json!({
+                                "id": call.id,
+                                "type": "function",
+                                "function": {
+                                    "name": call.function.name,
+                                    "arguments": call.function.arguments,
+                                }
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                });
+                if !content.is_empty() {
+                    message["tool_plan"] = content.into();
+                }
+                message
+            } else if m.message_type == MessageType::FunctionCallOutput {
+                serde_json::json!({
+                    "role": role,
+                    "tool_call_id": m.tool_call_id,
+                    "content": [{ "type": "document", "document": { "data": content } }],
+                })
+            } else {
+                serde_json::json!({ "role": role, "content": content })
+            };
+
+            messages.push(message);
+        }
+
+        let (_, model) = self.model.to_strings();
+        let mut body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": stream,
+        });
+
+        if let Some(tools) = tools {
+            body["tools"] = tools
+                .iter()
+                .map(|t| {
+                    serde_json::json!({
+                        "type": "function",
+                        "function": {
+                            "name": t.name.clone(),
+                            "description": t.description.clone(),
+                            "parameters": t.parameters.clone(),
+                        }
+                    })
+                })
+                .collect();
+            match self.options.tool_choice {
+                Some(ToolChoice::Any) => body["tool_choice"] = "REQUIRED".into(),
+                Some(ToolChoice::None) => body["tool_choice"] = "NONE".into(),
+                Some(ToolChoice::Auto) | None => {}
+            }
+        }
+        if let Some(max_tokens) = self.options.max_tokens {
+            body["max_tokens"] = max_tokens.into();
+        }
+        options.merge_extra_body(&mut body, Provider::Cohere, warnings)?;
+
+        Ok(body)
+    }
+
+    fn http_request(
+        &self,
+        body: &serde_json::Value,
+        options: &PromptOptions,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let url = format!("{}{}", self.origin(), options.path(CHAT_PATH));
+
+        let mut request = json_body(self.http_client.post(url), body, self.options.json_format)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.get_auth_token()?),
+            )
+            .header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING);
+        for (name, value) in options.extra_headers() {
+            request = request.header(name, value);
+        }
+
+        Ok(request)
+    }
+
+    /// The streaming request as raw HTTP/1.1, for `build_request_raw`.
+    fn raw_request(
+        &self,
+        body: &serde_json::Value,
+        options: &PromptOptions,
+    ) -> Result<String, WireError> {
+        let json_string = payload::to_string(body, self.options.json_format);
+
+        Ok(format!(
+            "POST {} HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Accept: */*\r\n\
+        Accept-Encoding: {}\r\n\
+        Authorization: Bearer {}\r\n\
+        {}\r\n\
+        {}",
+            options.path(CHAT_PATH),
+            self.host_header(),
+            json_string.len(),
+            ACCEPT_ENCODING,
+            self.get_auth_token()?,
+            options.raw_extra_headers(),
+            json_string.trim()
+        ))
+    }
+
+    /// `(input, output)` tokens from the `billed_units` of a reply's or
+    /// `message-end` event's `usage`.
+    fn token_counts(usage: &serde_json::Value) -> (usize, usize) {
+        let count = |field: &str| usage["billed_units"][field].as_u64().unwrap_or(0) as usize;
+        (count("input_tokens"), count("output_tokens"))
+    }
+
+    /// The text blocks of a reply's `message.content`, joined, or `None`
+    /// when it has no content.
+    fn text_content(response_json: &serde_json::Value) -> Option<String> {
+        let blocks = response_json["message"]["content"].as_array()?;
+        Some(
+            blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect(),
+        )
+    }
+
+    /// The `message.tool_calls` of a reply, arguments left as the JSON
+    /// string Cohere sends.
+    fn tool_calls(response_json: &serde_json::Value) -> Vec<FunctionCall> {
+        response_json["message"]["tool_calls"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|call| FunctionCall {
+                id: call["id"].as_str().unwrap_or_default().to_string(),
+                call_type: "function".to_string(),
+                function: Function {
+                    name: call["function"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: call["function"]["arguments"]
+                        .as_str()
+                        .unwrap_or("{}")
+                        .to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Cohere reports failures as `{"message": "..."}`, where a reply has a
+/// message object.
+fn check_error(response_json: &serde_json::Value) -> Result<(), WireError> {
+    match response_json["message"].as_str() {
+        Some(error) => Err(WireError::Provider {
+            code: None,
+            message: error.to_string(),
+            request_id: None,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCore for CohereClient {
+    /// The API key, as read when the client was built or refreshed.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        self.credentials.token()
+    }
+
+    fn refresh_credentials(&self) {
+        self.credentials.refresh();
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &self.options.clock
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.options.event_log.as_ref()
+    }
+
+    fn deny_warnings(&self) -> bool {
+        self.options.deny_warnings
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(self.api(), content)
+    }
+
+    /// Build the `/v2/chat` request.
+    ///
+    /// * `tools` – sent in Cohere's `tools` array.
+    /// * `stream` – sets the body's `stream` flag; the path is the same
+    ///   either way.
+    fn build_request(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let options = PromptOptions::default();
+        self.http_request(
+            &self.request_body(
+                &system_prompt,
+                &chat_history,
+                tools,
+                stream,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            &options,
+        )
+    }
+
+    /// Execute a non-streaming prompt and return the reply with its billed
+    /// token counts.
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Cohere)?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api(), &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let request_body = self.request_body(
+            &system_prompt,
+            &chat_history,
+            None,
+            false,
+            options,
+            &mut warnings,
+        )?;
+        let recorder = LatencyRecorder::start();
+        let api = self.api();
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (body, request_snapshot, request_id) = cancellable(
+            options.cancellation_or(self.options.cancellation.as_ref()),
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    send_logged(
+                        self.options.event_log.as_ref(),
+                        &api,
+                        self.http_request(&request_body, options),
+                        self.options.request_snapshot,
+                        self.options.max_request_bytes,
+                    )
+                },
+            ),
+        )
+        .await?;
+        let latency = recorder.finish();
+        let response_json: serde_json::Value = serde_json::from_str(&body)?;
+        check_error(&response_json).map_err(|err| err.with_request_id(request_id.as_deref()))?;
+
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(self.read_json_response(&response_json)?);
+        let (input_tokens, output_tokens) = Self::token_counts(&response_json["usage"]);
+
+        let message = Message {
+            message_type: MessageType::Assistant,
+            content,
+            api: self.api(),
+            system_prompt,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(latency),
+                truncated: cap.truncation(),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        Ok(options.post_process(message)?)
+    }
+
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
+    }
+
+    /// Join the text blocks of `message.content` in a non-streaming reply.
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        Self::text_content(response_json).ok_or(WireError::MissingField {
+            field: "message.content",
+        })
+    }
+
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
+
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        Some(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolCapable for CohereClient {
+    async fn prompt_with_tools(
+        &self,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn prompt_with_tools_with_status(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn run_tool_loop(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransport for CohereClient {
+    /// Build the raw HTTP request used by the streaming implementation.
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError> {
+        let options = PromptOptions::default();
+        self.raw_request(
+            &self.request_body(
+                &system_prompt,
+                &chat_history,
+                None,
+                stream,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            &options,
+        )
+    }
+
+    /// Read Cohere's server-sent events, forwarding each text delta to the
+    /// provided channel.
+    async fn process_stream(
+        &self,
+        stream: Box<dyn Read + Send>,
+        tx: &tokio::sync::mpsc::Sender<String>,
+    ) -> Result<String, WireError> {
+        let mut content = StreamedContent::new(tx);
+        self.read_stream(
+            ByteStream::read_raw(stream),
+            &mut LatencyRecorder::start(),
+            &mut ContentCap::new(None),
+            &mut DeltaSequencer::new(),
+            &mut content,
+            &mut (0, 0),
+            false,
+        )
+        .await?;
+
+        Ok(content.finish())
+    }
+}
+
+impl CohereClient {
+    async fn prompt_with_tools_internal(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        self.credentials.require()?;
+        check_incoming(
+            self.options.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        if self.options.tool_transport == ToolTransport::TextProtocol {
+            let mut warnings =
+                RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+            warnings.extend(
+                self.options
+                    .tool_choice
+                    .map(|_| text_protocol_tool_choice(Provider::Cohere)),
+            )?;
+            return prompt_with_text_tools(
+                self,
+                &self.options.tool_hooks,
+                self.options.max_tool_iterations,
+                self.options.cancellation.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                warnings.to_vec(),
+                status,
+                system_prompt,
+                chat_history,
+                tools,
+            )
+            .await;
+        }
+
+        let mut chat_history = chat_history;
+        let system_prompt = system_prompt.to_string();
+        let api = self.api();
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let tool_map: HashMap<String, Tool> =
+            tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
+        resume_pending_calls(
+            &mut status,
+            self.options.event_log.as_ref(),
+            self.options.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            &system_prompt,
+        )
+        .await?;
+
+        let mut tool_loop = ToolLoop::new(
+            &self.options.tool_hooks,
+            self.options.max_tool_iterations,
+            self.options.cancellation.as_ref(),
+        );
+
+        loop {
+            let pending = tool_loop.next_request(&chat_history)?;
+            let mut warnings = self.request_warnings(&PromptOptions::default())?;
+            // Tool outputs join the history inside the loop, so check every turn
+            let pending = warnings.normalize(&api, &pending, self.options.history_strictness)?;
+            check_outbound(self.options.sanitize_policy, &system_prompt, &pending)?;
+
+            if let Some(ratio) = self.options.tool_schema_warning {
+                let stats = self.stats(&system_prompt, &pending, Some(&specs));
+                warn_on_tool_schema_size(&stats, ratio, &mut warnings, &mut status).await?;
+            }
+
+            let recorder = LatencyRecorder::start();
+            let estimated_tokens = estimate_tokens(&system_prompt, &pending);
+            let (body, request_snapshot, request_id) = cancellable(
+                self.options.cancellation.as_ref(),
+                with_retries(
+                    self.options.retry_policy.as_ref(),
+                    self.options.rate_limiter.as_ref(),
+                    estimated_tokens,
+                    &self.options.clock,
+                    Some(&mut status),
+                    || {
+                        send_logged(
+                            self.options.event_log.as_ref(),
+                            &api,
+                            self.build_request(
+                                system_prompt.clone(),
+                                pending.clone(),
+                                Some(&specs),
+                                false,
+                            ),
+                            self.options.request_snapshot,
+                            self.options.max_request_bytes,
+                        )
+                    },
+                ),
+            )
+            .await?;
+            let response_json: serde_json::Value = serde_json::from_str(&body)?;
+            check_error(&response_json)
+                .map_err(|err| err.with_request_id(request_id.as_deref()))?;
+
+            let mut tool_calls = Self::tool_calls(&response_json);
+            // A reply that calls tools explains itself in `tool_plan`
+            let content = match Self::text_content(&response_json) {
+                Some(text) => text,
+                None => response_json["message"]["tool_plan"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            let (input_tokens, output_tokens) = Self::token_counts(&response_json["usage"]);
+            let metadata = MessageMetadata {
+                latency: Some(recorder.finish()),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                synthesized_call_ids: synthesize_call_ids(&mut tool_calls, tool_loop.iteration()),
+                ..Default::default()
+            };
+
+            if tool_calls.is_empty() {
+                let message = Message {
+                    message_type: MessageType::Assistant,
+                    content,
+                    api: api.clone(),
+                    system_prompt: system_prompt.clone(),
+                    tool_call_id: None,
+                    tool_calls: None,
+                    name: None,
+                    input_tokens,
+                    output_tokens,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
+                });
+                chat_history.push(message);
+
+                return Ok(status.finish(chat_history));
+            }
+
+            report_interim_text(&mut status, &content).await;
+
+            let message = Message {
+                message_type: MessageType::FunctionCall,
+                content,
+                api: api.clone(),
+                system_prompt: String::new(),
+                tool_call_id: None,
+                tool_calls: Some(tool_calls.clone()),
+                name: None,
+                input_tokens,
+                output_tokens,
+                metadata,
+            };
+            report_metrics(&self.options.metrics_callback, &message);
+            emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                message: message.clone(),
+            });
+            chat_history.push(message);
+
+            let outputs = run_tool_calls(
+                &mut status,
+                self.options.event_log.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                &tool_map,
+                tool_calls,
+                &chat_history,
+                tool_loop.iteration(),
+                &api,
+                &system_prompt,
+            )
+            .await?;
+            chat_history.extend(outputs);
+        }
+    }
+
+    /// Stream a reply, leaving the text of each `content-delta` event in
+    /// `slot` as it arrives.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Cohere)?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api(), &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let body = self.request_body(
+            &system_prompt,
+            &chat_history,
+            None,
+            true,
+            options,
+            &mut warnings,
+        )?;
+        let (request, request_snapshot) = prepare_stream(
+            self.options.event_log.as_ref(),
+            &self.api(),
+            self.http_request(&body, options),
+            self.options.request_snapshot,
+            self.options.max_request_bytes,
+        )?;
+
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let mut tokens = (0, 0);
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let cancellation = options.cancellation_or(self.options.cancellation.as_ref());
+        let body = cancellable(
+            cancellation,
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    open_stream(
+                        &self.stream_client,
+                        &request,
+                        self.options.max_redirects,
+                        self.options.stream_idle_timeout,
+                    )
+                },
+            ),
+        )
+        .await?
+        .with_cancellation(cancellation);
+        let request_id = body.request_id().map(str::to_string);
+        let truncated_stream = self
+            .read_stream(
+                body,
+                &mut recorder,
+                &mut cap,
+                &mut sequencer,
+                &mut content,
+                &mut tokens,
+                options.strict_stream_end,
+            )
+            .await
+            .map_err(|err| {
+                err.with_partial(|| content.partial())
+                    .with_request_id(request_id.as_deref())
+            })?;
+        warnings.stream_truncated(Provider::Cohere, truncated_stream.as_ref())?;
+        let content_bytes = content.bytes();
+        let (input_tokens, output_tokens) = tokens;
+
+        let message = Message {
+            message_type: MessageType::Assistant,
+            content: content.finish(),
+            api: self.api(),
+            system_prompt,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(recorder.finish()),
+                truncated: cap.truncation(),
+                sequence: Some(sequencer.finish()),
+                content_bytes: Some(content_bytes),
+                truncated_stream,
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        options.post_process(message)
+    }
+
+    /// Parse Cohere's server-sent events from `body`, forwarding the text of
+    /// each `content-delta` into `content` and noting its arrival on `recorder`.
+    /// The billed tokens of `message-end` land in `tokens`. Tool call and
+    /// tool plan deltas carry no reply text and are skipped.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_stream(
+        &self,
+        mut body: ByteStream,
+        recorder: &mut LatencyRecorder,
+        cap: &mut ContentCap,
+        sequencer: &mut DeltaSequencer,
+        content: &mut StreamedContent,
+        tokens: &mut (usize, usize),
+        strict: bool,
+    ) -> Result<Option<TruncatedStream>, WireError> {
+        let mut line = String::new();
+
+        while body.read_line_into(&mut line).await? {
+            if line.starts_with("event: ") || line.starts_with("data: ") {
+                emit(self.options.event_log.as_ref(), || WireEvent::StreamData {
+                    data: line.clone(),
+                });
+            }
+
+            if !line.starts_with("data: ") {
+                continue;
+            }
+
+            let payload = line[6..].trim();
+            if payload.is_empty() || payload == "[DONE]" {
+                break;
+            }
+
+            let response_json = match parse_event(&mut body, payload, strict).await? {
+                ParsedEvent::Json(json) => json,
+                ParsedEvent::Truncated(truncated) => return Ok(Some(truncated)),
+            };
+            check_error(&response_json)?;
+
+            if response_json["type"] == "message-end" {
+                *tokens = Self::token_counts(&response_json["delta"]["usage"]);
+                let usage = StreamEventKind::Usage {
+                    input: tokens.0,
+                    output: tokens.1,
+                };
+                content.report(usage).await?;
+                break;
+            }
+
+            let delta = match response_json["type"] == "content-delta" {
+                true => response_json["delta"]["message"]["content"]["text"].as_str(),
+                false => None,
+            };
+
+            if let Some(delta) = delta {
+                recorder.record_delta();
+
+                let kept = cap.truncate(delta.to_string());
+                if !kept.is_empty() {
+                    content.forward(kept).await?;
+                    sequencer.record(response_json["index"].as_u64().unwrap_or(0) as usize);
+                }
+
+                // Dropping `body` on return closes the connection
+                if cap.exceeded() {
+                    break;
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,171 @@
+//! Any other server speaking OpenAI's chat completions API: gateways such as
+//! LiteLLM, inference servers such as vLLM and llama.cpp's, and hosted
+//! providers the crate has no client of its own for.
+//!
+//! `OpenAICompatibleClient` is an `OpenAIClient` pointed at the options'
+//! endpoint (`ClientOptions::from_base_url`) and `/v1/chat/completions`,
+//! whose requests name any model and whose replies carry `API::Compatible`.
+//! Models are named `custom/<model>`, so
+//! `new_client_with_options("custom/my-model", options)` builds one.
+//!
+//! `ClientOptions::compatible` configures the rest:
+//!
+//! - the label, which stands in for `custom` in the `API` of replies and
+//!   metrics (`litellm/my-model`), so traffic to different gateways stays
+//!   apart once stored;
+//! - the key, read from `OPENAI_COMPATIBLE_API_KEY`, another variable, or
+//!   given directly. `ClientOptions::without_api_key` sends none, for
+//!   servers that take none;
+//! - the path, for servers that mount the API elsewhere.
+//!
+//! For a server on this machine, `ClientOptions::local(port)` and the
+//! presets for its usual tenants (`lm_studio`, `llama_cpp`, `vllm`) set all
+//! of it: the loopback address without a proxy, no key, a label, and model
+//! names taken as they are, so `new_client_with_options("qwen2.5-7b-instruct",
+//! ClientOptions::lm_studio())` needs neither `custom/` nor an environment
+//! variable.
+//!
+//! Streams and tool calls work to the extent the server implements them.
+
+use crate::api::{CompatibleModel, API};
+use crate::config::{check_compatible_label, ClientOptions};
+use crate::credentials::Credentials;
+use crate::openai::{openai_compatible_client, OpenAIClient};
+
+/// The label of models not given one.
+const DEFAULT_LABEL: &str = "custom";
+
+impl CompatibleModel {
+    /// A model as the server names it, labelled `custom`. Any name is
+    /// accepted; the server decides whether it serves the model.
+    pub fn new(name: impl Into<String>) -> Self {
+        CompatibleModel {
+            label: DEFAULT_LABEL.to_string(),
+            name: name.into(),
+        }
+    }
+
+    /// The same model under `label`.
+    ///
+    /// # Errors
+    /// When `label` is empty or contains `/`, which separates it from the
+    /// model once stored.
+    pub fn with_label(self, label: impl Into<String>) -> Result<Self, String> {
+        let label = label.into();
+        check_compatible_label(&label).map_err(|err| err.to_string())?;
+
+        Ok(CompatibleModel { label, ..self })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Resolve a `custom/<model>` identifier, labelled `custom`.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model.strip_prefix("custom/") {
+            Some(name) if !name.is_empty() => Ok(CompatibleModel::new(name)),
+            _ => Err(format!("Unknown custom model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model as `label/model`.
+    pub fn to_strings(&self) -> (String, String) {
+        ("custom".to_string(), String::from(self.clone()))
+    }
+}
+
+/// Takes the model with or without a `custom/` prefix, labelled `custom`.
+impl<'a> From<&'a str> for CompatibleModel {
+    fn from(model: &'a str) -> Self {
+        CompatibleModel::new(model.strip_prefix("custom/").unwrap_or(model))
+    }
+}
+
+impl From<CompatibleModel> for String {
+    fn from(model: CompatibleModel) -> Self {
+        format!("{}/{}", model.label, model.name)
+    }
+}
+
+/// Parses the stored `label/model` form.
+impl TryFrom<String> for CompatibleModel {
+    type Error = String;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        match model.split_once('/') {
+            Some((label, name)) if !name.is_empty() => CompatibleModel::new(name).with_label(label),
+            _ => Err(format!("Unknown custom model: {}", model)),
+        }
+    }
+}
+
+openai_compatible_client! {
+    /// Client for a server speaking OpenAI's chat completions API. `new` sends
+    /// to OpenAI's own host; give a base URL with `with_options`.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "custom/my-model",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::compatible::OpenAICompatibleClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let options = options.with_compatible_label("litellm").unwrap();
+    /// let client = OpenAICompatibleClient::with_options("my-model", options);
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// assert_eq!(reply.api.to_strings().1, "litellm/my-model");
+    /// # });
+    /// ```
+    OpenAICompatibleClient(CompatibleModel),
+    key: "The key, as given or as read when the client was built or refreshed."
+}
+
+impl OpenAICompatibleClient {
+    /// Construct a client with custom transport settings, taking the label,
+    /// key and path from `options.compatible`.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<CompatibleModel>,
+    {
+        let mut model = model.into();
+        let compatible = options.compatible.clone();
+        // `with_compatible_label` checked it
+        if let Some(label) = compatible.label {
+            model.label = label;
+        }
+
+        let credentials = match (compatible.api_key, compatible.key_var) {
+            (Some(key), _) => Credentials::from_secret(key),
+            (None, Some(var)) => Credentials::from_env(var),
+            (None, None) => Credentials::from_env("OPENAI_COMPATIBLE_API_KEY"),
+        };
+        let openai = OpenAIClient::build(
+            API::Compatible(model.clone()),
+            "api.openai.com",
+            "/v1/chat/completions",
+            credentials,
+            options,
+        );
+
+        Self { model, openai }
+    }
+}
@@ -0,0 +1,226 @@
+//! Gzip and brotli decoding for provider responses.
+//!
+//! Requests advertise `Accept-Encoding: gzip, br`, so large responses may
+//! come back compressed. Responses read through `reqwest`, streams included,
+//! are decoded by it as they arrive. A raw response handed to
+//! `RawTransport::process_stream` is wrapped in a `GzipDecoder` or a
+//! `BrotliDecoder`, which decode the body as it is read, so server-sent
+//! events that the server flushes one at a time reach the caller as they
+//! arrive. `gunzip` and `unbrotli` decode a whole body at once.
+//!
+//! One-shot bodies are capped at `MAX_DECODED_BYTES`: a few kilobytes of
+//! gzip can inflate to gigabytes, and `PromptOptions::max_response_bytes`
+//! only limits the content taken from a body, not the body itself. Past
+//! the cap, reading fails with `std::io::ErrorKind::InvalidData`. Streams
+//! are not capped as a whole, since a long generation may rightly run past
+//! it; instead each event, the bytes up to a line break, is held to the
+//! same cap.
+//!
+//! Request bodies are sent uncompressed. None of the supported providers
+//! document accepting `Content-Encoding: gzip` on requests, and a provider
+//! that ignores the header would read the body as invalid JSON; prompts are
+//! also small next to the responses they produce.
+//!
+//! ```
+//! // "hi\n", gzipped
+//! let body = [
+//!     0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0xc8, 0xe4, 0x02,
+//!     0x00, 0x7a, 0x7a, 0x6f, 0xed, 0x03, 0x00, 0x00, 0x00,
+//! ];
+//!
+//! assert_eq!(wire::compression::gunzip(&body).unwrap(), b"hi\n");
+//! ```
+
+use std::io::Read;
+#[cfg(feature = "mock")]
+use std::io::Write;
+
+use crate::error::WireError;
+
+/// Value sent in `Accept-Encoding`; the codings wire can decode.
+pub const ACCEPT_ENCODING: &str = "gzip, br";
+
+/// Most bytes a one-shot response body, or one event of a stream, may
+/// decode to.
+pub const MAX_DECODED_BYTES: u64 = 64 * 1024 * 1024;
+
+fn too_large(limit: u64) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("response body decodes to more than {} bytes", limit),
+    )
+}
+
+/// Decode a complete gzip body of at most `MAX_DECODED_BYTES`.
+pub fn gunzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzipDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// Decode a complete brotli body of at most `MAX_DECODED_BYTES`.
+pub fn unbrotli(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    BrotliDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// Whether a `Content-Encoding` value names gzip.
+pub fn is_gzip(content_encoding: &str) -> bool {
+    matches!(
+        content_encoding.trim().to_ascii_lowercase().as_str(),
+        "gzip" | "x-gzip"
+    )
+}
+
+/// Whether a `Content-Encoding` value names brotli.
+pub fn is_brotli(content_encoding: &str) -> bool {
+    content_encoding.trim().eq_ignore_ascii_case("br")
+}
+
+/// The body of `response` as text. `reqwest` has already undone any gzip
+/// or brotli encoding; reading stops once the decoded body passes `MAX_DECODED_BYTES`.
+pub(crate) async fn response_text(mut response: reqwest::Response) -> Result<String, WireError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > MAX_DECODED_BYTES {
+            return Err(too_large(MAX_DECODED_BYTES).into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(body)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err).into())
+}
+
+/// Inflates a gzip stream read from `R`, failing once the output passes a
+/// limit.
+///
+/// Reads return as soon as the input read so far decodes to something, so
+/// output flushed by the server reaches the caller without waiting for more.
+pub struct GzipDecoder<R> {
+    inner: flate2::read::GzDecoder<R>,
+    limit: Limit,
+}
+
+impl<R: Read> GzipDecoder<R> {
+    /// A decoder limited to `MAX_DECODED_BYTES`.
+    pub fn new(inner: R) -> Self {
+        Self::with_limit(inner, MAX_DECODED_BYTES)
+    }
+
+    /// A decoder that fails once its output passes `limit` bytes.
+    pub fn with_limit(inner: R, limit: u64) -> Self {
+        Self {
+            inner: flate2::read::GzDecoder::new(inner),
+            limit: Limit::new(limit),
+        }
+    }
+}
+
+impl<R: Read> Read for GzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.limit.take(read)
+    }
+}
+
+/// Decodes a brotli stream read from `R`, failing once the output passes a
+/// limit.
+///
+/// Like `GzipDecoder`, reads return as soon as the input read so far
+/// decodes to something.
+pub struct BrotliDecoder<R: Read> {
+    inner: brotli::Decompressor<R>,
+    limit: Limit,
+}
+
+impl<R: Read> BrotliDecoder<R> {
+    /// A decoder limited to `MAX_DECODED_BYTES`.
+    pub fn new(inner: R) -> Self {
+        Self::with_limit(inner, MAX_DECODED_BYTES)
+    }
+
+    /// A decoder that fails once its output passes `limit` bytes.
+    pub fn with_limit(inner: R, limit: u64) -> Self {
+        Self {
+            inner: brotli::Decompressor::new(inner, 4096),
+            limit: Limit::new(limit),
+        }
+    }
+}
+
+impl<R: Read> Read for BrotliDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.limit.take(read)
+    }
+}
+
+/// Bytes a decoder may still produce.
+struct Limit {
+    limit: u64,
+    remaining: u64,
+}
+
+impl Limit {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            remaining: limit,
+        }
+    }
+
+    /// Count `read` more decoded bytes, failing once they pass the limit.
+    fn take(&mut self, read: usize) -> std::io::Result<usize> {
+        if read as u64 > self.remaining {
+            return Err(too_large(self.limit));
+        }
+
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Gzip writer for the mock server. Each write is flushed, so the server
+/// can send it as one chunk and the client can decode it without waiting
+/// for the next.
+#[cfg(feature = "mock")]
+pub(crate) struct GzipWriter(flate2::write::GzEncoder<Vec<u8>>);
+
+#[cfg(feature = "mock")]
+impl GzipWriter {
+    pub(crate) fn new() -> Self {
+        Self(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ))
+    }
+
+    /// `data` compressed and flushed; the first write also carries the header.
+    pub(crate) fn write(&mut self, data: &[u8]) -> Vec<u8> {
+        self.0
+            .write_all(data)
+            .and_then(|()| self.0.flush())
+            .expect("writing to a Vec cannot fail");
+        std::mem::take(self.0.get_mut())
+    }
+
+    /// Whatever is still buffered, the final block and the trailer.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.0.finish().expect("writing to a Vec cannot fail")
+    }
+}
+
+/// `data` brotli-compressed, for the mock server.
+#[cfg(feature = "mock")]
+pub(crate) fn brotli_compress(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        writer
+            .write_all(data)
+            .expect("writing to a Vec cannot fail");
+    }
+    compressed
+}
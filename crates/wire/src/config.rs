@@ -1,6 +1,30 @@
 use std::fmt;
+use std::sync::Arc;
 
+use crate::api::Provider;
+use crate::clock::{Clock, SharedClock};
+use crate::content_filter::ContentFilter;
+use crate::credentials::{Secret, TokenSource};
+use crate::error::WireError;
+use crate::event_log::{EventLog, EventSink};
+use crate::metrics::{MetricsCallback, PromptMetrics};
+#[cfg(feature = "mock")]
 use crate::mock::MockLLMServer;
+use crate::moderation::{Moderator, SharedModerator};
+use crate::normalize::HistoryStrictness;
+use crate::payload::JsonFormat;
+use crate::post::{self, Post};
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::sanitize::SanitizePolicy;
+use crate::tool_loop::{ToolHooks, ToolLoopHooks, ToolOutputPolicy};
+
+use crate::tool_protocol::ToolTransport;
+use crate::types::{Message, StreamEvent};
+use crate::warning::{RequestWarnings, WireWarning};
+/// What `ClientOptions::cancellation` and `PromptOptions::cancellation`
+/// take, re-exported so callers need no `tokio-util` of their own.
+pub use tokio_util::sync::CancellationToken;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Scheme {
@@ -30,6 +54,174 @@ pub enum Endpoint {
     BaseUrl(EndpointUrl),
 }
 
+/// An Azure OpenAI deployment, which `OpenAIClient` sends to in place of
+/// `/v1/chat/completions`; see `ClientOptions::with_azure_deployment`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AzureDeployment {
+    /// The name given to the model deployment in the Azure resource.
+    pub deployment: String,
+    /// Sent as the `api-version` query parameter, e.g. `2024-10-21`.
+    pub api_version: String,
+}
+
+impl AzureDeployment {
+    /// `/openai/deployments/{deployment}/chat/completions?api-version=...`
+    pub fn path(&self) -> String {
+        format!(
+            "/openai/deployments/{}/chat/completions?api-version={}",
+            self.deployment, self.api_version
+        )
+    }
+}
+
+/// A Vertex AI project and region, which `GeminiClient` sends to in place of
+/// the Generative Language API; see `ClientOptions::with_vertex`.
+#[derive(Clone, Debug)]
+pub struct VertexOptions {
+    pub project: String,
+    /// The region serving the model, e.g. `us-central1`, or `global`.
+    pub location: String,
+    pub token: VertexToken,
+}
+
+impl VertexOptions {
+    /// `{location}-aiplatform.googleapis.com`, or
+    /// `aiplatform.googleapis.com` for the `global` location.
+    pub fn host(&self) -> String {
+        if self.location == "global" {
+            "aiplatform.googleapis.com".to_string()
+        } else {
+            format!("{}-aiplatform.googleapis.com", self.location)
+        }
+    }
+
+    /// `/projects/{project}/locations/{location}/publishers/google/models/{model}`
+    pub fn model_path(&self, model: &str) -> String {
+        format!(
+            "/projects/{}/locations/{}/publishers/google/models/{}",
+            self.project, self.location, model
+        )
+    }
+}
+
+/// The OAuth2 access token Vertex AI requests carry as
+/// `Authorization: Bearer`.
+#[derive(Clone, Debug)]
+pub enum VertexToken {
+    /// A token fetched beforehand, e.g. with
+    /// `gcloud auth print-access-token`. It expires after an hour or so.
+    Static(Secret),
+    /// Asked for a token before every request.
+    Source(TokenSource),
+}
+
+impl VertexToken {
+    pub fn fixed(token: impl Into<String>) -> Self {
+        VertexToken::Static(Secret::new(token))
+    }
+
+    pub fn from_fn<F>(source: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        VertexToken::Source(TokenSource::new(source))
+    }
+
+    #[cfg(feature = "gemini")]
+    pub(crate) fn credentials(&self) -> crate::credentials::Credentials {
+        use crate::credentials::Credentials;
+
+        match self {
+            VertexToken::Static(token) => Credentials::from_secret(token.clone()),
+            VertexToken::Source(source) => Credentials::from_source(source.clone()),
+        }
+    }
+}
+
+/// What `openrouter::OpenRouterClient` adds to every request; other clients
+/// ignore it. See `ClientOptions::with_openrouter_attribution` and
+/// `with_openrouter_provider`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenRouterOptions {
+    /// Sent as `HTTP-Referer`: the app's URL, which OpenRouter uses to
+    /// attribute its usage.
+    pub referer: Option<String>,
+    /// Sent as `X-Title`: the app's name as OpenRouter shows it.
+    pub title: Option<String>,
+    /// Sent as the body's `provider` object.
+    pub provider: Option<ProviderPreferences>,
+}
+
+/// How `compatible::OpenAICompatibleClient` reaches a gateway serving
+/// OpenAI's chat completions API; other clients ignore it. The gateway's
+/// address is the options' endpoint. See
+/// `ClientOptions::with_compatible_label` and its neighbours.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompatibleOptions {
+    /// Names the gateway in the `API` of replies and metrics, in place of
+    /// `custom`. Set through `with_compatible_label`, which checks it.
+    pub(crate) label: Option<String>,
+    /// The environment variable holding the key, in place of
+    /// `OPENAI_COMPATIBLE_API_KEY`.
+    pub key_var: Option<String>,
+    /// The key itself, which wins over `key_var`.
+    pub api_key: Option<Secret>,
+    /// The route of the chat completions endpoint, in place of
+    /// `/v1/chat/completions`.
+    pub path: Option<String>,
+    /// Take every model name given to `new_client_with_options` as one the
+    /// server serves, with or without `custom/`, rather than resolving it to
+    /// a provider. The local presets (`ClientOptions::local`) set it.
+    pub any_model: bool,
+}
+
+impl CompatibleOptions {
+    /// The gateway's label, if one was given.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+/// Check that `label` can stand in for `custom` in a stored
+/// `custom/<model>` id: it may be neither empty nor contain `/`.
+pub(crate) fn check_compatible_label(label: &str) -> Result<(), ClientOptionsError> {
+    if label.is_empty() || label.contains('/') {
+        return Err(ClientOptionsError::InvalidLabel(label.to_string()));
+    }
+    Ok(())
+}
+
+/// Which upstream providers OpenRouter may route a request to, and in what
+/// order. Fields left empty are left out of the body, so OpenRouter's
+/// defaults apply.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ProviderPreferences {
+    /// Providers to try first, in order, by OpenRouter's slug
+    /// (`"anthropic"`, `"amazon-bedrock"`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Whether other providers may serve the request when those in `order`
+    /// fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Route only to providers that support every parameter in the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// `"allow"` or `"deny"` providers that may store or train on prompts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<String>,
+    /// Route to these providers and no others.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+    /// Never route to these providers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// `"price"`, `"throughput"` or `"latency"`: how to rank providers when
+    /// `order` doesn't decide.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ThinkingLevel {
     Minimal,
@@ -59,11 +251,137 @@ impl ThinkingLevel {
     }
 }
 
+/// Which capacity Anthropic may serve a request from. The tier that
+/// actually served it comes back in `MessageMetadata::service_tier`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceTier {
+    /// Priority capacity when the account has it, standard otherwise.
+    Auto,
+    /// Standard capacity only.
+    StandardOnly,
+}
+
+impl ServiceTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceTier::Auto => "auto",
+            ServiceTier::StandardOnly => "standard_only",
+        }
+    }
+}
+
+/// Whether the model may, must or must not call the tools it is offered in a
+/// native tool loop. The text protocol has no way to enforce it and ignores
+/// it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model decides (`AUTO` for Gemini, left unset for Cohere, `auto`
+    /// elsewhere).
+    Auto,
+    /// The model must call at least one tool (`ANY` for Gemini, `required`
+    /// for OpenAI, `any` for Anthropic, `REQUIRED` for Cohere).
+    Any,
+    /// The model must answer in text (`NONE` for Gemini and Cohere, `none`
+    /// elsewhere).
+    None,
+}
+
+/// What the Anthropic tool loop does when a reply stops at `max_tokens`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaxTokensBehavior {
+    /// Send the partial text back as an assistant prefill and let the model
+    /// pick up where it stopped, up to `max_continuations` times.
+    Continue { max_continuations: usize },
+    /// Fail with `WireError::TruncatedResponse`, which carries the partial
+    /// message.
+    #[default]
+    Error,
+}
+
+/// Redirects followed per request unless `ClientOptions::with_max_redirects`
+/// says otherwise; the same as reqwest's default.
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// How long a stream may go quiet unless
+/// `ClientOptions::with_stream_idle_timeout` says otherwise.
+pub const DEFAULT_STREAM_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
+/// The largest request body sent unless
+/// `ClientOptions::with_max_request_bytes` says otherwise: 20 MiB, above
+/// what any provider accepts for one request.
+pub const DEFAULT_MAX_REQUEST_BYTES: usize = 20 * 1024 * 1024;
+
 #[derive(Clone, Debug)]
 pub struct ClientOptions {
     pub endpoint: Endpoint,
     pub disable_proxy: bool,
     pub thinking_level: Option<ThinkingLevel>,
+    /// Cap on the tokens of each reply. `None` leaves OpenAI and Gemini to
+    /// their own limits and Anthropic at 4096.
+    pub max_tokens: Option<usize>,
+    pub metrics_callback: Option<MetricsCallback>,
+    pub tool_transport: ToolTransport,
+    pub tool_schema_warning: Option<f64>,
+    pub tool_hooks: ToolHooks,
+    pub max_tool_iterations: Option<usize>,
+    pub tool_output_policy: Option<ToolOutputPolicy>,
+    /// Sent with every native tool-loop request when set; the provider's own
+    /// default applies otherwise.
+    pub tool_choice: Option<ToolChoice>,
+    pub max_tokens_behavior: MaxTokensBehavior,
+    pub moderator: Option<SharedModerator>,
+    pub clock: SharedClock,
+    pub content_filter: Option<ContentFilter>,
+    pub max_redirects: usize,
+    /// The longest a connection to the provider may take. `None` leaves it
+    /// to the operating system.
+    pub connect_timeout: Option<std::time::Duration>,
+    /// The longest a request may take, response included. Streams would
+    /// outlive any such limit, so for them it is the longest wait for the
+    /// next bytes. `None` waits for ever.
+    pub request_timeout: Option<std::time::Duration>,
+    /// The longest a stream may go without sending anything before it
+    /// fails with `WireError::StreamStalled`. `None` waits for ever.
+    pub stream_idle_timeout: Option<std::time::Duration>,
+    /// Requests whose body is larger fail with `WireError::RequestTooLarge`
+    /// before anything is sent. `None` sends bodies of any size.
+    pub max_request_bytes: Option<usize>,
+    /// Send a request again after a transient failure; see `retry`. `None`
+    /// sends each request once.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Hold requests back to stay under a requests- or tokens-per-minute
+    /// budget; see `rate_limit`. `None` sends them at once.
+    pub rate_limiter: Option<RateLimiter>,
+    pub event_log: Option<EventLog>,
+    pub json_format: JsonFormat,
+    pub sanitize_policy: SanitizePolicy,
+    pub history_strictness: HistoryStrictness,
+    /// Fail prompts with `WireError::Warning` instead of sending a request
+    /// that raised a warning; see `warning`.
+    pub deny_warnings: bool,
+    /// Attach the request behind each reply to its metadata; see
+    /// `snapshot`.
+    pub request_snapshot: bool,
+    /// Send OpenAI requests to this Azure deployment. Other clients ignore
+    /// it.
+    pub azure: Option<AzureDeployment>,
+    /// Send Gemini requests to this Vertex AI project. Other clients ignore
+    /// it.
+    pub vertex: Option<VertexOptions>,
+    /// Attribution and routing for OpenRouter requests. Other clients ignore
+    /// it.
+    pub openrouter: OpenRouterOptions,
+    /// Label, key and path for `compatible::OpenAICompatibleClient`. Other
+    /// clients ignore it.
+    pub compatible: CompatibleOptions,
+    /// The key OpenAI and OpenAI-compatible clients send in place of the one
+    /// they read from the environment; an empty one sends no auth header.
+    /// Other clients ignore it.
+    pub api_key: Option<Secret>,
+    /// Once it fires, every prompt, stream and tool loop of the client
+    /// fails with `WireError::Cancelled`, those in flight included. A
+    /// prompt's own `PromptOptions::cancellation` takes its place.
+    pub cancellation: Option<CancellationToken>,
 }
 
 impl Default for ClientOptions {
@@ -72,16 +390,428 @@ impl Default for ClientOptions {
             endpoint: Endpoint::Default,
             disable_proxy: false,
             thinking_level: None,
+            max_tokens: None,
+            metrics_callback: None,
+            tool_transport: ToolTransport::Native,
+            tool_schema_warning: None,
+            tool_hooks: ToolHooks::default(),
+            max_tool_iterations: None,
+            tool_output_policy: None,
+            tool_choice: None,
+            max_tokens_behavior: MaxTokensBehavior::default(),
+            moderator: None,
+            clock: SharedClock::default(),
+            content_filter: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            connect_timeout: None,
+            request_timeout: None,
+            stream_idle_timeout: Some(DEFAULT_STREAM_IDLE_TIMEOUT),
+            max_request_bytes: Some(DEFAULT_MAX_REQUEST_BYTES),
+            retry_policy: None,
+            rate_limiter: None,
+            event_log: None,
+            json_format: JsonFormat::default(),
+            sanitize_policy: SanitizePolicy::default(),
+            history_strictness: HistoryStrictness::default(),
+            deny_warnings: false,
+            request_snapshot: false,
+            azure: None,
+            vertex: None,
+            openrouter: OpenRouterOptions::default(),
+            compatible: CompatibleOptions::default(),
+            api_key: None,
+            cancellation: None,
+        }
+    }
+}
+
+/// Headers the clients set themselves, which `PromptOptions::extra_headers`
+/// cannot replace.
+pub const MANAGED_HEADERS: &[&str] = &[
+    "host",
+    "content-type",
+    "content-length",
+    "accept",
+    "accept-encoding",
+    "authorization",
+    "x-api-key",
+    "api-key",
+    "anthropic-version",
+];
+
+/// Options that apply to a single prompt rather than to the client.
+#[derive(Clone, Debug, Default)]
+pub struct PromptOptions {
+    /// Cap on the response content in bytes. Streams stop reading once it is
+    /// exceeded; non-streaming responses are truncated. Either way the returned
+    /// message records a `Truncation` in its metadata.
+    pub max_response_bytes: Option<usize>,
+    /// Skip the client's `Moderator`, if any, for this call.
+    pub bypass_moderation: bool,
+    /// When non-empty, sent in place of the `system_prompt` argument. Gemini
+    /// gets one `system_instruction` part per fragment; the other providers
+    /// get the fragments joined with blank lines.
+    pub system_fragments: Vec<String>,
+    /// Streaming prompts only: send deltas over the channel without also
+    /// building the full response. The returned message has empty content;
+    /// its metadata (latency, truncation, sequence, `content_bytes`) is
+    /// still filled in.
+    pub discard_streamed_content: bool,
+    /// How streaming prompts deliver deltas to their channel.
+    pub stream: StreamOptions,
+    /// Request path used in place of the client's, e.g. a versioned or
+    /// regional route. Appended to the client's origin as is.
+    pub path_override: Option<String>,
+    /// Streaming prompts only: fail when the final event does not parse,
+    /// instead of returning the content before it with a
+    /// `TruncatedStream` in the metadata.
+    pub strict_stream_end: bool,
+    /// Anthropic only: sent as the request's `service_tier`. Other
+    /// providers fail the call with `WireError::UnsupportedOption`.
+    pub service_tier: Option<ServiceTier>,
+    /// Merged into the request body last, for provider fields wire has no
+    /// option for yet. Values wire sets itself win; see `payload::merge`.
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+    /// Sent after wire's own headers. Those wire manages (`MANAGED_HEADERS`)
+    /// are skipped.
+    pub extra_headers: Vec<(String, String)>,
+    /// Run in order on the final content of the reply; see `post`.
+    pub post: Vec<Post>,
+    /// Streaming prompts only: send every `StreamEvent` here, content
+    /// included, instead of content deltas over the prompt's channel. The
+    /// channel then gets nothing.
+    pub events: Option<tokio::sync::mpsc::Sender<StreamEvent>>,
+    /// Fail the call with `WireError::Cancelled` once it fires, dropping
+    /// the request; a stream's error keeps the content sent so far. Used in
+    /// place of the client's `ClientOptions::cancellation`.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl PromptOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    pub fn with_bypass_moderation(mut self, bypass_moderation: bool) -> Self {
+        self.bypass_moderation = bypass_moderation;
+        self
+    }
+
+    pub fn with_discard_streamed_content(mut self, discard_streamed_content: bool) -> Self {
+        self.discard_streamed_content = discard_streamed_content;
+        self
+    }
+
+    pub fn with_stream_options(mut self, stream: StreamOptions) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn with_path_override(mut self, path: impl Into<String>) -> Self {
+        self.path_override = Some(path.into());
+        self
+    }
+
+    pub fn with_strict_stream_end(mut self, strict_stream_end: bool) -> Self {
+        self.strict_stream_end = strict_stream_end;
+        self
+    }
+
+    pub fn with_service_tier(mut self, service_tier: ServiceTier) -> Self {
+        self.service_tier = Some(service_tier);
+        self
+    }
+
+    /// Add fields to the request body, e.g. OpenAI's `prediction`. Nested
+    /// objects are merged into the ones wire builds, so
+    /// `{"generationConfig": {"seed": 7}}` keeps Gemini's other settings.
+    pub fn with_extra_body(
+        mut self,
+        extra_body: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        self.extra_body = extra_body;
+        self
+    }
+
+    /// Add request headers, e.g. `anthropic-beta`.
+    pub fn with_extra_headers<I, K, V>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.extra_headers = headers
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self
+    }
+
+    /// Keep instruction fragments (persona, rules, the current date, ...)
+    /// separate instead of concatenating them into one system prompt.
+    pub fn with_system_fragments<I, S>(mut self, fragments: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.system_fragments = fragments.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Reshape the reply's content, e.g. down to its first code block.
+    /// Streamed deltas are sent unchanged.
+    pub fn with_post(mut self, post: Vec<Post>) -> Self {
+        self.post = post;
+        self
+    }
+
+    /// Receive tool calls, usage and the like as the response streams in,
+    /// not just its content; see `StreamEvent`.
+    pub fn with_events(mut self, events: tokio::sync::mpsc::Sender<StreamEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Stop the call when `cancellation` fires; see `cancellation`.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// The token that stops this call: its own, else the client's.
+    pub(crate) fn cancellation_or<'a>(
+        &'a self,
+        client: Option<&'a CancellationToken>,
+    ) -> Option<&'a CancellationToken> {
+        self.cancellation.as_ref().or(client)
+    }
+
+    /// `message` with its content run through `post`, failing with
+    /// `WireError::PostProcess` when a step finds nothing to work on. A
+    /// stream whose content was discarded has nothing to run them on.
+    pub(crate) fn post_process(&self, mut message: Message) -> Result<Message, WireError> {
+        if self.post.is_empty() || (self.discard_streamed_content && message.content.is_empty()) {
+            return Ok(message);
+        }
+
+        match post::apply(&self.post, &message.content) {
+            Ok(content) => {
+                message.content = content;
+                Ok(message)
+            }
+            Err(error) => Err(WireError::PostProcess {
+                error,
+                content: message.content,
+            }),
+        }
+    }
+
+    /// The system prompt as one string: `system_prompt`, or the fragments
+    /// joined with blank lines.
+    pub(crate) fn system_prompt(&self, system_prompt: String) -> String {
+        if self.system_fragments.is_empty() {
+            system_prompt
+        } else {
+            self.system_fragments.join("\n\n")
+        }
+    }
+
+    /// The request path: the override if set, else the client's `default`.
+    pub(crate) fn path<'a>(&'a self, default: &'a str) -> &'a str {
+        self.path_override.as_deref().unwrap_or(default)
+    }
+
+    /// Fail with `WireError::UnsupportedOption` if `service_tier` is set,
+    /// for a client of a `provider` that takes none.
+    #[cfg(any(
+        feature = "openai",
+        feature = "gemini",
+        feature = "ollama",
+        feature = "cohere"
+    ))]
+    pub(crate) fn check_service_tier(&self, provider: Provider) -> Result<(), WireError> {
+        match self.service_tier {
+            Some(_) => Err(WireError::UnsupportedOption {
+                option: "service_tier",
+                provider: provider.as_str().to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Merge `extra_body` into `body`. A field wire had already set keeps
+    /// wire's value, with a `WireWarning::OptionIgnored`.
+    pub(crate) fn merge_extra_body(
+        &self,
+        body: &mut serde_json::Value,
+        provider: Provider,
+        warnings: &mut RequestWarnings<'_>,
+    ) -> Result<(), WireError> {
+        let conflicts = crate::payload::merge(body, &self.extra_body);
+        warnings.extend(conflicts.into_iter().map(|path| {
+            WireWarning::option_ignored(
+                &format!("extra body field `{}`", path),
+                provider,
+                "wire sets this field; its own value was sent",
+            )
+        }))
+    }
+
+    /// `extra_headers` without the ones wire manages or that could not be
+    /// sent as is; `header_warnings` reports those.
+    pub(crate) fn extra_headers(&self) -> Vec<(&str, &str)> {
+        self.extra_headers
+            .iter()
+            .filter(|(name, value)| skipped_header(name, value).is_none())
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// A warning for each of `extra_headers` that a `provider` client leaves
+    /// out of its requests.
+    pub(crate) fn header_warnings(&self, provider: Provider) -> Vec<WireWarning> {
+        self.extra_headers
+            .iter()
+            .filter_map(|(name, value)| {
+                skipped_header(name, value).map(|reason| {
+                    WireWarning::option_ignored(
+                        &format!("extra header `{}`", name),
+                        provider,
+                        reason,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// `extra_headers()` as raw header lines, each ending in CRLF.
+    pub(crate) fn raw_extra_headers(&self) -> String {
+        self.extra_headers()
+            .into_iter()
+            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+            .collect()
+    }
+
+    /// The system prompt as separate parts, for providers that take several.
+    #[cfg(feature = "gemini")]
+    pub(crate) fn system_parts(&self, system_prompt: String) -> Vec<String> {
+        if self.system_fragments.is_empty() {
+            vec![system_prompt]
+        } else {
+            self.system_fragments.clone()
+        }
+    }
+}
+
+/// Why an extra header can't be sent, if it can't.
+fn skipped_header(name: &str, value: &str) -> Option<&'static str> {
+    if MANAGED_HEADERS
+        .iter()
+        .any(|managed| managed.eq_ignore_ascii_case(name))
+    {
+        Some("wire sets it itself")
+    } else if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+        || reqwest::header::HeaderValue::from_str(value).is_err()
+    {
+        Some("it is not a valid header")
+    } else {
+        None
+    }
+}
+
+/// Capacity of the channels the crate creates for deltas, and the default for
+/// `StreamOptions::channel`. Enough to absorb a burst of small deltas without
+/// holding much text; a consumer that does slow work per delta (rendering,
+/// speech) should still keep up on average or pick an `OnFull` policy.
+pub const DEFAULT_STREAM_CHANNEL_CAPACITY: usize = 64;
+
+/// A stream that waits this long on a full channel is reported as stalled.
+pub const DEFAULT_STALL_WARNING: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// What a streaming prompt does with a delta when the consumer's channel is
+/// full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnFull {
+    /// Wait for room. Reading from the provider pauses too, holding its
+    /// connection open while the consumer catches up.
+    #[default]
+    Block,
+    /// Keep reading and hold back up to `channel_capacity` deltas (or
+    /// events, for `PromptOptions::events`); when that backlog is full, the
+    /// oldest is discarded. Held deltas are delivered
+    /// as the channel frees up and before the prompt returns. The returned
+    /// message still has the full content, and a
+    /// `WireWarning::StreamEventsDropped` if anything was discarded.
+    DropOldest,
+    /// Fail the prompt.
+    Fail,
+}
+
+/// Delivery settings for streaming prompts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Capacity of the channel made by `channel`, and of the backlog kept by
+    /// `OnFull::DropOldest`.
+    pub channel_capacity: usize,
+    pub on_full: OnFull,
+    /// Warn with `WireWarning::ChannelStalled` when a delta has waited this
+    /// long for room in the channel. `None` disables the warning.
+    pub stall_warning: Option<std::time::Duration>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            channel_capacity: DEFAULT_STREAM_CHANNEL_CAPACITY,
+            on_full: OnFull::default(),
+            stall_warning: Some(DEFAULT_STALL_WARNING),
         }
     }
 }
 
+impl StreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
+    }
+
+    pub fn with_on_full(mut self, on_full: OnFull) -> Self {
+        self.on_full = on_full;
+        self
+    }
+
+    pub fn with_stall_warning(mut self, stall_warning: Option<std::time::Duration>) -> Self {
+        self.stall_warning = stall_warning;
+        self
+    }
+
+    /// A channel of `channel_capacity` to pass to a streaming prompt.
+    pub fn channel(
+        &self,
+    ) -> (
+        tokio::sync::mpsc::Sender<String>,
+        tokio::sync::mpsc::Receiver<String>,
+    ) {
+        tokio::sync::mpsc::channel(self.channel_capacity.max(1))
+    }
+}
+
 #[derive(Debug)]
 pub enum ClientOptionsError {
     InvalidUrl(url::ParseError),
     MissingHost,
     MissingPort,
     UnsupportedScheme(String),
+    InvalidLabel(String),
 }
 
 impl fmt::Display for ClientOptionsError {
@@ -93,6 +823,9 @@ impl fmt::Display for ClientOptionsError {
             ClientOptionsError::UnsupportedScheme(scheme) => {
                 write!(f, "unsupported url scheme: {}", scheme)
             }
+            ClientOptionsError::InvalidLabel(label) => {
+                write!(f, "invalid compatible label: {:?}", label)
+            }
         }
     }
 }
@@ -131,17 +864,442 @@ impl ClientOptions {
             }),
             disable_proxy: matches!(host.as_str(), "localhost" | "127.0.0.1"),
             thinking_level: None,
+            max_tokens: None,
+            metrics_callback: None,
+            tool_transport: ToolTransport::Native,
+            tool_schema_warning: None,
+            tool_hooks: ToolHooks::default(),
+            max_tool_iterations: None,
+            tool_output_policy: None,
+            tool_choice: None,
+            max_tokens_behavior: MaxTokensBehavior::default(),
+            moderator: None,
+            clock: SharedClock::default(),
+            content_filter: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            connect_timeout: None,
+            request_timeout: None,
+            stream_idle_timeout: Some(DEFAULT_STREAM_IDLE_TIMEOUT),
+            max_request_bytes: Some(DEFAULT_MAX_REQUEST_BYTES),
+            retry_policy: None,
+            rate_limiter: None,
+            event_log: None,
+            json_format: JsonFormat::default(),
+            sanitize_policy: SanitizePolicy::default(),
+            history_strictness: HistoryStrictness::default(),
+            deny_warnings: false,
+            request_snapshot: false,
+            azure: None,
+            vertex: None,
+            openrouter: OpenRouterOptions::default(),
+            compatible: CompatibleOptions::default(),
+            api_key: None,
+            cancellation: None,
         })
     }
 
+    /// Options for an OpenAI-compatible server on this machine at `port`:
+    /// `http://127.0.0.1:{port}`, with no proxy and no key. Any model name
+    /// resolves, labelled `local`, so
+    /// `new_client_with_options("qwen2.5-7b-instruct", ClientOptions::local(8080))`
+    /// builds a client without an environment variable in sight.
+    pub fn local(port: u16) -> Self {
+        Self::local_preset(port, "local")
+    }
+
+    /// `local` on LM Studio's port, 1234, labelled `lm-studio`.
+    pub fn lm_studio() -> Self {
+        Self::local_preset(1234, "lm-studio")
+    }
+
+    /// `local` on llama.cpp's `llama-server` port, 8080, labelled
+    /// `llama-cpp`.
+    pub fn llama_cpp() -> Self {
+        Self::local_preset(8080, "llama-cpp")
+    }
+
+    /// `local` on vLLM's port, 8000, labelled `vllm`.
+    pub fn vllm() -> Self {
+        Self::local_preset(8000, "vllm")
+    }
+
+    fn local_preset(port: u16, label: &str) -> Self {
+        let mut options = Self::from_base_url(format!("http://127.0.0.1:{}", port))
+            .expect("a loopback url with a port is valid");
+        options.compatible.label = Some(label.to_string());
+        options.compatible.any_model = true;
+        options.without_api_key()
+    }
+
+    #[cfg(feature = "mock")]
     pub fn for_mock_server(server: &MockLLMServer) -> Result<Self, ClientOptionsError> {
-        let mut options = Self::from_base_url(&server.base_url())?;
+        let mut options = Self::from_base_url(server.base_url())?;
         options.disable_proxy = true;
         Ok(options)
     }
 
+    /// Options for the Azure OpenAI resource `resource`
+    /// (`https://{resource}.openai.azure.com`), sending to `deployment`.
+    pub fn for_azure(
+        resource: &str,
+        deployment: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> Result<Self, ClientOptionsError> {
+        Ok(
+            Self::from_base_url(format!("https://{}.openai.azure.com", resource))?
+                .with_azure_deployment(deployment, api_version),
+        )
+    }
+
+    /// Make `OpenAIClient` speak Azure OpenAI: requests go to the
+    /// deployment's path with `api-version` in the query, and authenticate
+    /// with an `api-key` header holding `AZURE_OPENAI_API_KEY` in place of
+    /// the bearer token. Point the endpoint at the resource with `for_azure`
+    /// or `from_base_url`.
+    pub fn with_azure_deployment(
+        mut self,
+        deployment: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> Self {
+        self.azure = Some(AzureDeployment {
+            deployment: deployment.into(),
+            api_version: api_version.into(),
+        });
+        self
+    }
+
+    /// Make `GeminiClient` speak Vertex AI: requests go to
+    /// `/v1/projects/{project}/locations/{location}/publishers/google/models/{model}:generateContent`
+    /// on the location's host, and authenticate with `token` as a bearer
+    /// token in place of `GEMINI_API_KEY`. An endpoint set with
+    /// `from_base_url` wins over the location's host.
+    pub fn with_vertex(
+        mut self,
+        project: impl Into<String>,
+        location: impl Into<String>,
+        token: VertexToken,
+    ) -> Self {
+        self.vertex = Some(VertexOptions {
+            project: project.into(),
+            location: location.into(),
+            token,
+        });
+        self
+    }
+
+    /// Tell OpenRouter which app is making the requests, through its
+    /// `HTTP-Referer` and `X-Title` headers.
+    pub fn with_openrouter_attribution(
+        mut self,
+        referer: impl Into<String>,
+        title: impl Into<String>,
+    ) -> Self {
+        self.openrouter.referer = Some(referer.into());
+        self.openrouter.title = Some(title.into());
+        self
+    }
+
+    /// Send `preferences` as the `provider` object of every OpenRouter
+    /// request.
+    pub fn with_openrouter_provider(mut self, preferences: ProviderPreferences) -> Self {
+        self.openrouter.provider = Some(preferences);
+        self
+    }
+
+    /// Name the gateway an `OpenAICompatibleClient` talks to, so its replies
+    /// carry `API::Compatible` models like `litellm/my-model` rather than
+    /// `custom/my-model`.
+    ///
+    /// # Errors
+    /// `ClientOptionsError::InvalidLabel` when `label` is empty or contains
+    /// `/`, which separates it from the model once stored.
+    pub fn with_compatible_label(
+        mut self,
+        label: impl Into<String>,
+    ) -> Result<Self, ClientOptionsError> {
+        let label = label.into();
+        check_compatible_label(&label)?;
+        self.compatible.label = Some(label);
+        Ok(self)
+    }
+
+    /// Read an `OpenAICompatibleClient`'s key from `var` instead of
+    /// `OPENAI_COMPATIBLE_API_KEY`.
+    pub fn with_compatible_key_var(mut self, var: impl Into<String>) -> Self {
+        self.compatible.key_var = Some(var.into());
+        self
+    }
+
+    /// Give an `OpenAICompatibleClient` its key directly.
+    pub fn with_compatible_api_key(mut self, key: impl Into<String>) -> Self {
+        self.compatible.api_key = Some(Secret::new(key));
+        self
+    }
+
+    /// Send `key` in place of the one OpenAI and OpenAI-compatible clients
+    /// read from the environment, so no variable needs to be set.
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(Secret::new(key));
+        self
+    }
+
+    /// Send no key at all, for servers that take none. OpenAI and
+    /// OpenAI-compatible clients then read no variable and leave the auth
+    /// header out.
+    pub fn without_api_key(self) -> Self {
+        self.with_api_key("")
+    }
+
+    /// Send an `OpenAICompatibleClient`'s requests to `path` instead of
+    /// `/v1/chat/completions`.
+    pub fn with_compatible_path(mut self, path: impl Into<String>) -> Self {
+        self.compatible.path = Some(path.into());
+        self
+    }
+
     pub fn with_thinking_level(mut self, thinking_level: ThinkingLevel) -> Self {
         self.thinking_level = Some(thinking_level);
         self
     }
+
+    /// Cap the length of every reply at `max_tokens` tokens.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Receive usage and latency for every request the client completes.
+    pub fn with_metrics_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&PromptMetrics) + Send + Sync + 'static,
+    {
+        self.metrics_callback = Some(MetricsCallback::new(callback));
+        self
+    }
+
+    /// Choose how tools are offered to the model in `prompt_with_tools`.
+    pub fn with_tool_transport(mut self, tool_transport: ToolTransport) -> Self {
+        self.tool_transport = tool_transport;
+        self
+    }
+
+    /// Warn with `WireWarning::ToolSchemasLarge`, on the reply and the
+    /// status channel, whenever tool definitions exceed `ratio` of a
+    /// tool-loop request. See
+    /// `metrics::DEFAULT_TOOL_SCHEMA_WARNING_RATIO` for a sensible value.
+    pub fn with_tool_schema_warning(mut self, ratio: f64) -> Self {
+        self.tool_schema_warning = Some(ratio);
+        self
+    }
+
+    /// Run `hook` before every tool loop iteration. Hooks run in the order
+    /// they were added.
+    pub fn with_tool_loop_hook<H>(mut self, hook: H) -> Self
+    where
+        H: ToolLoopHooks + 'static,
+    {
+        self.tool_hooks.push(hook);
+        self
+    }
+
+    /// Fail the client's prompts, streams and tool loops with
+    /// `WireError::Cancelled` once `cancellation` fires. A fired token stays
+    /// fired; cancel a single call with `PromptOptions::with_cancellation`,
+    /// passing a `child_token()` of this one to keep both.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Fail `prompt_with_tools` once the model has been asked this many times
+    /// without producing a final answer.
+    pub fn with_max_tool_iterations(mut self, max_tool_iterations: usize) -> Self {
+        self.max_tool_iterations = Some(max_tool_iterations);
+        self
+    }
+
+    /// Cut tool outputs down to `policy` before they reach the model; the
+    /// full output stays in the output message's `metadata.tool_invocation`.
+    pub fn with_tool_output_policy(mut self, policy: ToolOutputPolicy) -> Self {
+        self.tool_output_policy = Some(policy);
+        self
+    }
+
+    /// Tell the model whether it may, must or must not call tools in
+    /// `prompt_with_tools`.
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Choose how a tool-loop reply cut off by `max_tokens` is handled.
+    pub fn with_max_tokens_behavior(mut self, max_tokens_behavior: MaxTokensBehavior) -> Self {
+        self.max_tokens_behavior = max_tokens_behavior;
+        self
+    }
+
+    /// Check the user messages of every `prompt`, `prompt_stream` and
+    /// `prompt_with_tools` call with `moderator` before anything is sent.
+    /// Flagged input fails with `error::WireError::ContentFlagged`.
+    pub fn with_moderator<M>(mut self, moderator: M) -> Self
+    where
+        M: Moderator + 'static,
+    {
+        self.moderator = Some(SharedModerator::new(moderator));
+        self
+    }
+
+    /// Use `clock` instead of real time for deadlines; see `clock`.
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Rewrite the system prompt and message text in every request body with
+    /// `filter`; see `content_filter`. `PiiScrubber::into_filter` is a
+    /// ready-made one.
+    pub fn with_content_filter(mut self, filter: ContentFilter) -> Self {
+        self.content_filter = Some(filter);
+        self
+    }
+
+    /// Follow at most `max_redirects` redirects per request; with 0 any
+    /// redirect is an error. Streaming requests only follow 307 and 308,
+    /// which keep the method and body.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Fail with `WireError::Timeout` when connecting takes longer than
+    /// `connect_timeout`.
+    pub fn with_connect_timeout(mut self, connect_timeout: std::time::Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Fail with `WireError::Timeout` when a request takes longer than
+    /// `request_timeout`, or a stream goes that long without sending
+    /// anything.
+    pub fn with_request_timeout(mut self, request_timeout: std::time::Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Fail a stream with `WireError::StreamStalled`, keeping what arrived,
+    /// once it goes `stream_idle_timeout` without sending anything; `None`
+    /// waits for ever.
+    pub fn with_stream_idle_timeout(
+        mut self,
+        stream_idle_timeout: Option<std::time::Duration>,
+    ) -> Self {
+        self.stream_idle_timeout = stream_idle_timeout;
+        self
+    }
+
+    /// Retry requests that fail with a transient error, as `policy` says.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Queue requests until `limiter` has room for them. Options cloned
+    /// from these share its budget.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Refuse to send request bodies over `max_request_bytes`, or lift the
+    /// limit with `None`. The error lists the largest parts of the body.
+    pub fn with_max_request_bytes(mut self, max_request_bytes: Option<usize>) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Report every request, response, stream event, tool run and message
+    /// to `sink`; see `event_log`.
+    pub fn with_event_log(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.event_log = Some(EventLog::new(sink));
+        self
+    }
+
+    /// Lay request bodies out as `json_format`. Keys are sorted either way;
+    /// see `payload`.
+    pub fn with_json_format(mut self, json_format: JsonFormat) -> Self {
+        self.json_format = json_format;
+        self
+    }
+
+    /// Choose what happens to control characters in outbound message text;
+    /// see `sanitize`. The default strips them.
+    pub fn with_sanitize_policy(mut self, sanitize_policy: SanitizePolicy) -> Self {
+        self.sanitize_policy = sanitize_policy;
+        self
+    }
+
+    /// Choose what happens when a history written by another provider holds
+    /// something this one can't take; see `normalize`. The default rewrites
+    /// it and warns.
+    pub fn with_history_strictness(mut self, history_strictness: HistoryStrictness) -> Self {
+        self.history_strictness = history_strictness;
+        self
+    }
+
+    /// Fail a prompt rather than send a request with an option left out or
+    /// changed, or a history rewritten; see `warning`. By default such
+    /// requests are sent and the reply's metadata lists the warnings.
+    pub fn deny_warnings(mut self) -> Self {
+        self.deny_warnings = true;
+        self
+    }
+
+    /// Keep a copy of each request body in the metadata of the reply it
+    /// produced, for `snapshot::replay`. Off by default, when nothing is kept.
+    pub fn with_request_snapshot(mut self, request_snapshot: bool) -> Self {
+        self.request_snapshot = request_snapshot;
+        self
+    }
+
+    /// A reqwest client honouring the proxy, redirect and timeout settings.
+    pub(crate) fn http_client(&self) -> reqwest::Client {
+        // `limited(0)` fails on the first redirect rather than handing back
+        // the 3xx response as if it were the answer
+        let mut builder = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::limited(self.max_redirects));
+        if self.disable_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        builder.build().expect("reqwest client")
+    }
+
+    /// The reqwest client streams are sent with. It differs from
+    /// `http_client` in two ways: `request_timeout` bounds each wait for
+    /// the next bytes rather than the whole response, which a stream would
+    /// outlive, and redirects are left to `network_common::open_stream`,
+    /// which only follows those that keep the method and body.
+    pub(crate) fn stream_client(&self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        if self.disable_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+
+        builder.build().expect("reqwest client")
+    }
 }
@@ -0,0 +1,120 @@
+//! Rewriting message text on its way out.
+//!
+//! A `ContentFilter` set with `ClientOptions::with_content_filter` is applied
+//! by the request builders to the system prompt and to the text content of
+//! every message, with the message's type. Tool definitions and tool call
+//! arguments are sent as they are. Filtering happens on the request body
+//! only: the caller's history, and the messages the clients return, keep
+//! the original text.
+//!
+//! `PiiScrubber` is a ready-made filter for emails, E.164 phone numbers and
+//! card-like digit runs:
+//!
+//! ```
+//! use wire::content_filter::PiiScrubber;
+//!
+//! let scrubber = PiiScrubber::new();
+//! assert_eq!(
+//!     scrubber.scrub("Mail ada@example.com or call +442071838750"),
+//!     "Mail [EMAIL] or call [PHONE]"
+//! );
+//! ```
+
+use std::sync::Arc;
+
+use fancy_regex::Regex;
+
+use crate::types::MessageType;
+
+type FilterFn = dyn Fn(&MessageType, &str) -> String + Send + Sync;
+
+#[derive(Clone)]
+pub struct ContentFilter(Arc<FilterFn>);
+
+impl ContentFilter {
+    pub fn new<F>(filter: F) -> Self
+    where
+        F: Fn(&MessageType, &str) -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(filter))
+    }
+
+    pub fn apply(&self, message_type: &MessageType, content: &str) -> String {
+        (self.0)(message_type, content)
+    }
+}
+
+impl std::fmt::Debug for ContentFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContentFilter")
+    }
+}
+
+/// Run `filter`, if configured, over what a request builder is about to send.
+#[cfg(any(feature = "openai", feature = "anthropic"))]
+pub(crate) fn filter_outbound(
+    filter: Option<&ContentFilter>,
+    system_prompt: String,
+    mut chat_history: Vec<crate::types::Message>,
+) -> (String, Vec<crate::types::Message>) {
+    let Some(filter) = filter else {
+        return (system_prompt, chat_history);
+    };
+
+    for message in chat_history.iter_mut() {
+        message.content = filter.apply(&message.message_type, &message.content);
+    }
+
+    (
+        filter.apply(&MessageType::System, &system_prompt),
+        chat_history,
+    )
+}
+
+/// Replaces emails, E.164 phone numbers and 13 to 19 digit card-like numbers
+/// with `[EMAIL]`, `[PHONE]` and `[CARD]`.
+#[derive(Clone, Debug)]
+pub struct PiiScrubber {
+    patterns: Vec<(Regex, &'static str)>,
+}
+
+impl PiiScrubber {
+    pub fn new() -> Self {
+        let patterns = [
+            (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]"),
+            (r"\+[1-9]\d{7,14}\b", "[PHONE]"),
+            (r"\b\d(?:[ -]?\d){12,18}\b", "[CARD]"),
+        ];
+
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|(pattern, replacement)| {
+                    (
+                        Regex::new(pattern).expect("built-in PII pattern compiles"),
+                        replacement,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn scrub(&self, text: &str) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, (pattern, replacement)| {
+                pattern.replace_all(&text, *replacement).into_owned()
+            })
+    }
+
+    /// This scrubber as a filter for every message type.
+    pub fn into_filter(self) -> ContentFilter {
+        ContentFilter::new(move |_, text| self.scrub(text))
+    }
+}
+
+impl Default for PiiScrubber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
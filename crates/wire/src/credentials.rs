@@ -0,0 +1,176 @@
+//! API keys, read once per client.
+//!
+//! Each provider client reads its key variable (`OPENAI_API_KEY`,
+//! `ANTHROPIC_API_KEY`, `GEMINI_API_KEY`) when it is constructed and keeps
+//! it in `Credentials`, rather than reading the environment on every
+//! request. Changing the variable later does not reach clients that already
+//! exist, so clients built under different values (say, in tests that scope
+//! the variable) don't see each other's key. After rotating a key, call
+//! `PromptCore::refresh_credentials` on the clients that should pick it up.
+//! A key given directly, as `compatible::OpenAICompatibleClient` allows,
+//! never changes. A `TokenSource`, such as a Vertex AI token callback, is
+//! asked again on every request.
+//!
+//! ```
+//! use wire::credentials::Secret;
+//!
+//! let secret = Secret::new("sk-live-1234");
+//! assert_eq!(format!("{:?}", secret), "Secret(<redacted>)");
+//! assert_eq!(secret.expose(), "sk-live-1234");
+//! ```
+
+use std::borrow::Cow;
+use std::sync::{Arc, RwLock};
+
+use crate::error::WireError;
+
+/// A credential that keeps itself out of `Debug` output and logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// The secret itself, for the request that needs it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+/// Callback producing a short-lived token, such as an OAuth2 access token,
+/// for each request. It owns any caching and renewal.
+#[derive(Clone)]
+pub struct TokenSource(Arc<dyn Fn() -> String + Send + Sync>);
+
+impl TokenSource {
+    pub fn new<F>(source: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(source))
+    }
+
+    pub fn token(&self) -> Secret {
+        Secret((self.0)())
+    }
+}
+
+// A source that panics is asked again on the next request, with no state
+// of ours left half-updated, so clients stay usable across `catch_unwind`.
+impl std::panic::UnwindSafe for TokenSource {}
+impl std::panic::RefUnwindSafe for TokenSource {}
+
+impl std::fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TokenSource")
+    }
+}
+
+/// The value of an environment variable as of construction or the last
+/// `refresh`, a secret given directly, or a `TokenSource` asked each time.
+pub struct Credentials {
+    var: Option<Cow<'static, str>>,
+    secret: RwLock<Option<Secret>>,
+    source: Option<TokenSource>,
+}
+
+impl Credentials {
+    /// Read `var` now. A missing variable is only an error once the secret
+    /// is needed.
+    pub fn from_env(var: impl Into<Cow<'static, str>>) -> Self {
+        let var = var.into();
+        Self {
+            secret: RwLock::new(read_env(&var)),
+            var: Some(var),
+            source: None,
+        }
+    }
+
+    /// Use `secret` itself, which `refresh` leaves alone.
+    pub fn from_secret(secret: Secret) -> Self {
+        Self {
+            var: None,
+            secret: RwLock::new(Some(secret)),
+            source: None,
+        }
+    }
+
+    /// Ask `source` for the secret whenever it is needed.
+    pub fn from_source(source: TokenSource) -> Self {
+        Self {
+            var: None,
+            secret: RwLock::new(None),
+            source: Some(source),
+        }
+    }
+
+    /// The variable the secret is read from, or `None` for one given
+    /// directly.
+    pub fn var(&self) -> Option<&str> {
+        self.var.as_deref()
+    }
+
+    /// Read the variable again, replacing the stored secret. A variable
+    /// that has since been unset clears it.
+    pub fn refresh(&self) {
+        if let Some(var) = &self.var {
+            *self.secret.write().unwrap() = read_env(var);
+        }
+    }
+
+    /// The stored secret, if the variable was set, or the source's current
+    /// one.
+    pub fn secret(&self) -> Option<Secret> {
+        match &self.source {
+            Some(source) => Some(source.token()),
+            None => self.secret.read().unwrap().clone(),
+        }
+    }
+
+    /// `WireError::AuthMissing`, naming the variable, when no secret is
+    /// stored. Clients check this before anything else, so a missing key
+    /// fails the prompt before the history is checked or moderated. A
+    /// source is always there and is not asked.
+    pub fn require(&self) -> Result<(), WireError> {
+        if self.source.is_some() || self.secret.read().unwrap().is_some() {
+            return Ok(());
+        }
+        Err(self.missing())
+    }
+
+    /// The stored secret as a string, or `WireError::AuthMissing` when the
+    /// variable was not set.
+    pub fn token(&self) -> Result<String, WireError> {
+        self.secret()
+            .map(|secret| secret.0)
+            .ok_or_else(|| self.missing())
+    }
+
+    fn missing(&self) -> WireError {
+        WireError::AuthMissing {
+            var: self.var().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("var", &self.var())
+            .field("secret", &self.secret.read().unwrap())
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+fn read_env(var: &str) -> Option<Secret> {
+    std::env::var(var).ok().map(Secret)
+}
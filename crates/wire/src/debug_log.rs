@@ -0,0 +1,332 @@
+//! A directory of per-request event logs that cleans up after itself.
+//!
+//! `DebugLogDir` is an `EventSink` that writes each request's events to its
+//! own JSON-lines file, `request-000001.jsonl` and up, readable with
+//! `event_log::read_event_log`. A file starts with the first event after the
+//! previous reply (usually the request's warnings, then the request) and
+//! ends with the `Message` built from the response.
+//!
+//! `LogRotation` bounds the directory by file count, total bytes and age.
+//! Pruning runs after every event and removes the oldest files first, in
+//! request order, until every limit holds; the file of a request still in
+//! flight is never removed, so it may briefly hold the directory over its
+//! limits. Ages are measured with the sink's `Clock` from when a file was
+//! started; files already in the directory when the sink opens count as
+//! started then.
+//!
+//! The sink also keeps the last few request/response pairs in memory, which
+//! `PromptCore::debug_tail` returns for whichever client logs to it.
+//!
+//! Events are filed in the order they arrive, so a sink shared by prompts
+//! running at the same time interleaves them; give each concurrent client
+//! its own directory.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use wire::config::ClientOptions;
+//! use wire::debug_log::{DebugLogDir, LogRotation};
+//!
+//! let rotation = LogRotation::default()
+//!     .with_max_files(500)
+//!     .with_max_bytes(256 * 1024 * 1024)
+//!     .with_max_age(Duration::from_secs(6 * 60 * 60));
+//! let sink = DebugLogDir::open("wire-debug", rotation).unwrap();
+//! let options = ClientOptions::default().with_event_log(Arc::new(sink));
+//! let client = wire::new_client_with_options("gpt-4o-mini", options).unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SharedClock};
+use crate::event_log::{EventSink, WireEvent};
+
+const FILE_PREFIX: &str = "request-";
+const FILE_SUFFIX: &str = ".jsonl";
+
+/// Limits on a `DebugLogDir`. `None` leaves that dimension unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogRotation {
+    pub max_files: Option<usize>,
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+impl LogRotation {
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = Some(max_files);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+}
+
+/// One request and what came back for it, as kept in memory by
+/// `DebugLogDir`.
+#[derive(Clone, Debug)]
+pub struct DebugExchange {
+    /// The `WireEvent::Request` itself.
+    pub request: WireEvent,
+    /// The `Response`, or the `StreamData` events, read for the request so
+    /// far.
+    pub response: Vec<WireEvent>,
+    /// The file the exchange is logged in, which rotation may have removed
+    /// since.
+    pub path: PathBuf,
+}
+
+struct LogFile {
+    path: PathBuf,
+    bytes: u64,
+    started: Instant,
+}
+
+struct Current {
+    file: Option<std::fs::File>,
+    has_request: bool,
+    replied: bool,
+}
+
+struct DirState {
+    /// Oldest first; the last one is `current`'s.
+    files: VecDeque<LogFile>,
+    current: Option<Current>,
+    next_index: u64,
+    tail: VecDeque<DebugExchange>,
+}
+
+/// An `EventSink` writing one file per request into a directory; see the
+/// module docs. Write errors are ignored so a full disk never fails a
+/// prompt.
+pub struct DebugLogDir {
+    dir: PathBuf,
+    rotation: LogRotation,
+    tail_len: usize,
+    clock: SharedClock,
+    state: Mutex<DirState>,
+}
+
+impl DebugLogDir {
+    /// Log into `dir`, creating it if needed. Files a previous sink left
+    /// there are kept, subject to `rotation`, and numbering carries on after
+    /// them.
+    pub fn open(dir: impl AsRef<Path>, rotation: LogRotation) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut existing = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(index) = name.to_str().and_then(file_index) else {
+                continue;
+            };
+            existing.push((index, entry.path(), entry.metadata()?.len()));
+        }
+        existing.sort();
+
+        let sink = Self {
+            dir,
+            rotation,
+            tail_len: 8,
+            clock: SharedClock::default(),
+            state: Mutex::new(DirState {
+                files: VecDeque::new(),
+                current: None,
+                next_index: existing.last().map_or(1, |(index, _, _)| index + 1),
+                tail: VecDeque::new(),
+            }),
+        };
+
+        {
+            let now = sink.clock.now();
+            let mut state = sink.lock();
+            state
+                .files
+                .extend(existing.into_iter().map(|(_, path, bytes)| LogFile {
+                    path,
+                    bytes,
+                    started: now,
+                }));
+            sink.prune(&mut state);
+        }
+
+        Ok(sink)
+    }
+
+    /// Keep the last `len` request/response pairs in memory instead of 8.
+    pub fn with_tail(mut self, len: usize) -> Self {
+        self.tail_len = len;
+        self
+    }
+
+    /// Measure file ages with `clock` instead of real time. Files already
+    /// in the directory count as started at `clock`'s current time.
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = SharedClock::new(clock);
+        let now = self.clock.now();
+        for file in self.lock().files.iter_mut() {
+            file.started = now;
+        }
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The files currently in the directory, oldest first.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.lock()
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// The last request/response pairs, oldest first.
+    pub fn tail(&self) -> Vec<DebugExchange> {
+        self.lock().tail.iter().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DirState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn start_file(&self, state: &mut DirState) {
+        let path = self.dir.join(format!(
+            "{}{:06}{}",
+            FILE_PREFIX, state.next_index, FILE_SUFFIX
+        ));
+        state.next_index += 1;
+
+        state.current = Some(Current {
+            file: std::fs::File::create(&path).ok(),
+            has_request: false,
+            replied: false,
+        });
+        state.files.push_back(LogFile {
+            path,
+            bytes: 0,
+            started: self.clock.now(),
+        });
+    }
+
+    /// Remove the oldest files until `rotation` holds or only the one in
+    /// flight is left.
+    fn prune(&self, state: &mut DirState) {
+        let now = self.clock.now();
+        let in_flight = state
+            .current
+            .as_ref()
+            .is_some_and(|current| !current.replied);
+        let protected = usize::from(in_flight);
+
+        let mut total: u64 = state.files.iter().map(|file| file.bytes).sum();
+        while state.files.len() > protected {
+            let oldest = &state.files[0];
+            let expired = self
+                .rotation
+                .max_age
+                .is_some_and(|max_age| now.saturating_duration_since(oldest.started) > max_age);
+            let too_many = self
+                .rotation
+                .max_files
+                .is_some_and(|max_files| state.files.len() > max_files);
+            let too_big = self
+                .rotation
+                .max_bytes
+                .is_some_and(|max_bytes| total > max_bytes);
+            if !(expired || too_many || too_big) {
+                break;
+            }
+
+            let oldest = state.files.pop_front().expect("checked above");
+            total -= oldest.bytes;
+            let _ = std::fs::remove_file(&oldest.path);
+            if state.files.is_empty() {
+                state.current = None;
+            }
+        }
+    }
+}
+
+impl EventSink for DebugLogDir {
+    fn record(&self, event: &WireEvent) {
+        let Ok(mut line) = serde_json::to_string(event) else {
+            return;
+        };
+        line.push('\n');
+
+        let mut state = self.lock();
+        let is_request = matches!(event, WireEvent::Request { .. });
+        let start = match &state.current {
+            None => true,
+            Some(current) => current.replied || (is_request && current.has_request),
+        };
+        if start {
+            self.start_file(&mut state);
+        }
+
+        let state = &mut *state;
+        let current = state.current.as_mut().expect("a file was just started");
+        if let Some(file) = current.file.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                let logged = state.files.back_mut().expect("current file is listed");
+                logged.bytes += line.len() as u64;
+            }
+        }
+
+        match event {
+            WireEvent::Request { .. } => {
+                current.has_request = true;
+                if self.tail_len > 0 {
+                    if state.tail.len() == self.tail_len {
+                        state.tail.pop_front();
+                    }
+                    state.tail.push_back(DebugExchange {
+                        request: event.clone(),
+                        response: Vec::new(),
+                        path: state.files.back().expect("current file").path.clone(),
+                    });
+                }
+            }
+            WireEvent::Response { .. } | WireEvent::StreamData { .. } => {
+                if let Some(exchange) = state.tail.back_mut() {
+                    exchange.response.push(event.clone());
+                }
+            }
+            WireEvent::Message { .. } => current.replied = true,
+            _ => {}
+        }
+
+        self.prune(state);
+    }
+
+    fn tail(&self) -> Vec<DebugExchange> {
+        DebugLogDir::tail(self)
+    }
+}
+
+/// The number in a `request-000001.jsonl` file name.
+fn file_index(name: &str) -> Option<u64> {
+    name.strip_prefix(FILE_PREFIX)?
+        .strip_suffix(FILE_SUFFIX)?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,573 @@
+//! An offline client for examples, doctests and CI.
+//!
+//! `EchoClient` (model name `wire:echo`) implements `PromptCore` and
+//! `ToolCapable` without touching the network or reading API keys. Having no
+//! socket, it has no `RawTransport`. It replies with the last user message,
+//! streams that reply word by word, and in `prompt_with_tools` turns lines of
+//! the form `CALL:<tool name>:<json arguments>` into tool calls:
+//!
+//! ```
+//! use wire::api::PromptCore;
+//! use wire::types::{MessageBuilder, MessageType};
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let client = wire::new_client("wire:echo").unwrap();
+//! let history = vec![client
+//!     .new_message("hello there".to_string())
+//!     .message_type(MessageType::User)
+//!     .build()];
+//!
+//! let reply = client.prompt("Be brief.".to_string(), history).await.unwrap();
+//! assert_eq!(reply.content, "hello there");
+//! # });
+//! ```
+//!
+//! Once a tool has run, the echoed reply is the tool output, which ends the
+//! loop.
+//!
+//! `EchoClient::with_config` shapes the stream for testing consumers: the
+//! size of each delta, a pause between deltas and a failure partway through.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use wire::api::PromptCore;
+//! use wire::echo::{EchoClient, EchoClientConfig, InjectedStreamError};
+//! use wire::error::WireError;
+//! use wire::types::MessageType;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let client = EchoClient::new().with_config(EchoClientConfig {
+//!     chunk_chars: Some(4),
+//!     delay: Duration::from_millis(1),
+//!     fail_after: Some(2),
+//! });
+//! let history = vec![client
+//!     .new_message("hello there".to_string())
+//!     .message_type(MessageType::User)
+//!     .build()];
+//!
+//! let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+//! let err = client
+//!     .prompt_stream(history, String::new(), tx)
+//!     .await
+//!     .unwrap_err();
+//! let WireError::Io(err) = err else {
+//!     panic!("expected a broken stream, got {}", err);
+//! };
+//! assert_eq!(
+//!     err.get_ref()
+//!         .and_then(|err| err.downcast_ref::<InjectedStreamError>()),
+//!     Some(&InjectedStreamError { deltas_sent: 2 })
+//! );
+//! assert_eq!(rx.recv().await.as_deref(), Some("hell"));
+//! assert_eq!(rx.recv().await.as_deref(), Some("o th"));
+//! assert_eq!(rx.recv().await, None);
+//! # });
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::api::{event_stream, EventStream, PromptCore, ToolCapable, WireModel, API};
+use crate::clock::{Clock, SharedClock};
+use crate::config::{CancellationToken, ClientOptions, PromptOptions};
+use crate::error::WireError;
+use crate::event_log::{emit, EventLog, WireEvent};
+use crate::metrics::{report_metrics, LatencyRecorder, MetricsCallback};
+use crate::moderation::{check_incoming, SharedModerator};
+use crate::tool_loop::{
+    resume_pending_calls, run_tool_calls, StatusLog, ToolHooks, ToolLoop, ToolLoopResult,
+    ToolOutputPolicy,
+};
+use crate::types::{
+    ContentCap, DeltaSequencer, EventSlot, Function, FunctionCall, Message, MessageBuilder,
+    MessageMetadata, MessageType, StreamedContent, Tool, ToolSpec,
+};
+
+/// Prefix that marks a line of a user message as a scripted tool call.
+pub const ECHO_CALL_PREFIX: &str = "CALL:";
+
+impl WireModel {
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model {
+            "wire:echo" => Ok(WireModel::Echo),
+            _ => Err(format!("Unknown wire model: {}", model)),
+        }
+    }
+
+    pub fn to_strings(&self) -> (String, String) {
+        let model = match self {
+            WireModel::Echo => "wire:echo",
+        };
+
+        ("wire".to_string(), model.to_string())
+    }
+}
+
+/// How `EchoClient` streams its reply. The default streams it word by
+/// word, at once, to the end.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EchoClientConfig {
+    /// Characters per delta, in place of one word per delta.
+    pub chunk_chars: Option<usize>,
+    /// Pause before each delta after the first, on the client's `Clock`.
+    pub delay: Duration,
+    /// Fail the stream once this many deltas have been sent, with a
+    /// `WireError::Io` wrapping an `InjectedStreamError`, as a connection
+    /// that broke off would. Content sent until then stays sent.
+    pub fail_after: Option<usize>,
+}
+
+/// The failure `EchoClientConfig::fail_after` injects into a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InjectedStreamError {
+    pub deltas_sent: usize,
+}
+
+impl fmt::Display for InjectedStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "injected echo stream failure after {} deltas",
+            self.deltas_sent
+        )
+    }
+}
+
+impl std::error::Error for InjectedStreamError {}
+
+/// Deterministic, offline stand-in for a provider client.
+pub struct EchoClient {
+    pub metrics_callback: Option<MetricsCallback>,
+    pub tool_hooks: ToolHooks,
+    pub max_tool_iterations: Option<usize>,
+    pub tool_output_policy: Option<ToolOutputPolicy>,
+    pub moderator: Option<SharedModerator>,
+    pub clock: SharedClock,
+    pub event_log: Option<EventLog>,
+    pub cancellation: Option<CancellationToken>,
+    pub config: EchoClientConfig,
+}
+
+impl EchoClient {
+    pub fn new() -> Self {
+        Self::with_options(ClientOptions::default())
+    }
+
+    /// Only the metrics callback, moderator, clock, event log, cancellation
+    /// and tool loop settings apply; transport options are ignored.
+    pub fn with_options(options: ClientOptions) -> Self {
+        Self {
+            metrics_callback: options.metrics_callback,
+            tool_hooks: options.tool_hooks,
+            max_tool_iterations: options.max_tool_iterations,
+            tool_output_policy: options.tool_output_policy,
+            moderator: options.moderator,
+            clock: options.clock,
+            event_log: options.event_log,
+            cancellation: options.cancellation,
+            config: EchoClientConfig::default(),
+        }
+    }
+
+    /// Stream replies as `config` says.
+    pub fn with_config(mut self, config: EchoClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    fn api(&self) -> API {
+        API::Wire(WireModel::Echo)
+    }
+
+    /// The text the echo model answers `chat_history` with: the trailing tool
+    /// outputs if the conversation ends with them, otherwise the last user
+    /// message.
+    fn echo(chat_history: &[Message]) -> String {
+        let outputs: Vec<&str> = chat_history
+            .iter()
+            .rev()
+            .take_while(|message| message.message_type == MessageType::FunctionCallOutput)
+            .map(|message| message.content.as_str())
+            .collect();
+
+        if !outputs.is_empty() {
+            return outputs.into_iter().rev().collect::<Vec<_>>().join("\n");
+        }
+
+        chat_history
+            .iter()
+            .rev()
+            .find(|message| message.message_type == MessageType::User)
+            .map(|message| message.content.clone())
+            .unwrap_or_default()
+    }
+
+    /// `echo` cut into the deltas `config` asks for.
+    fn deltas(&self, echo: &str) -> Vec<String> {
+        match self.config.chunk_chars {
+            Some(size) => {
+                let chars: Vec<char> = echo.chars().collect();
+                chars
+                    .chunks(size.max(1))
+                    .map(|chunk| chunk.iter().collect())
+                    .collect()
+            }
+            None => echo
+                .split_inclusive(char::is_whitespace)
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Parse the `CALL:<tool name>:<json>` lines in `content`.
+    fn scripted_calls(content: &str, turn: usize) -> Result<Vec<FunctionCall>, WireError> {
+        let mut calls = Vec::new();
+
+        for line in content.lines() {
+            let Some(call) = line.trim().strip_prefix(ECHO_CALL_PREFIX) else {
+                continue;
+            };
+
+            let (name, arguments) = call.split_once(':').unwrap_or((call, "{}"));
+            let arguments: serde_json::Value =
+                serde_json::from_str(arguments).map_err(|err| WireError::ToolExecution {
+                    name: name.trim().to_string(),
+                    reason: format!("invalid arguments: {}", err),
+                })?;
+
+            calls.push(FunctionCall {
+                id: format!("echo_{}_{}", turn, calls.len()),
+                call_type: "function".to_string(),
+                function: Function {
+                    name: name.trim().to_string(),
+                    arguments: arguments.to_string(),
+                },
+            });
+        }
+
+        Ok(calls)
+    }
+
+    fn reply(&self, system_prompt: String, content: String, metadata: MessageMetadata) -> Message {
+        let mut message = self
+            .new_message(content)
+            .message_type(MessageType::Assistant)
+            .build();
+        message.system_prompt = system_prompt;
+        message.metadata = metadata;
+
+        report_metrics(&self.metrics_callback, &message);
+        emit(self.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        message
+    }
+
+    async fn prompt_with_tools_internal(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        check_incoming(
+            self.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        let tool_map: HashMap<String, Tool> =
+            tools.into_iter().map(|t| (t.name.clone(), t)).collect();
+        let mut chat_history = chat_history;
+        resume_pending_calls(
+            &mut status,
+            self.event_log.as_ref(),
+            self.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            system_prompt,
+        )
+        .await?;
+        let mut tool_loop = ToolLoop::new(
+            &self.tool_hooks,
+            self.max_tool_iterations,
+            self.cancellation.as_ref(),
+        );
+        let mut turn = 0;
+
+        loop {
+            let pending = tool_loop.next_request(&chat_history)?;
+            let content = Self::echo(&pending);
+
+            let is_user_turn = pending
+                .last()
+                .is_some_and(|message| message.message_type != MessageType::FunctionCallOutput);
+            let tool_calls = if is_user_turn {
+                Self::scripted_calls(&content, turn)?
+            } else {
+                Vec::new()
+            };
+
+            if tool_calls.is_empty() {
+                let metadata = MessageMetadata::default();
+                chat_history.push(self.reply(system_prompt.to_string(), content, metadata));
+                break;
+            }
+            turn += 1;
+
+            let mut message = self.new_message(String::new()).build();
+            message.message_type = MessageType::FunctionCall;
+            message.tool_calls = Some(tool_calls.clone());
+            report_metrics(&self.metrics_callback, &message);
+            emit(self.event_log.as_ref(), || WireEvent::Message {
+                message: message.clone(),
+            });
+            chat_history.push(message);
+
+            let outputs = run_tool_calls(
+                &mut status,
+                self.event_log.as_ref(),
+                self.tool_output_policy.as_ref(),
+                &tool_map,
+                tool_calls,
+                &chat_history,
+                tool_loop.iteration(),
+                &self.api(),
+                system_prompt,
+            )
+            .await?;
+            chat_history.extend(outputs);
+        }
+
+        Ok(status.finish(chat_history))
+    }
+
+    /// The echo as `prompt_stream_events_with_options` streams it, each
+    /// event left in `slot`.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        check_incoming(self.moderator.as_ref(), &chat_history, options).await?;
+        let system_prompt = options.system_prompt(system_prompt);
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let echo = Self::echo(&chat_history);
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let cancellation = options.cancellation_or(self.cancellation.as_ref());
+
+        for (index, delta) in self.deltas(&echo).into_iter().enumerate() {
+            if self.config.fail_after == Some(index) {
+                return Err(
+                    std::io::Error::other(InjectedStreamError { deltas_sent: index }).into(),
+                );
+            }
+            if index > 0 && !self.config.delay.is_zero() {
+                tokio::select! {
+                    _ = self.clock.sleep(self.config.delay) => {}
+                    _ = cancelled(cancellation) => {
+                        return Err(WireError::Cancelled {
+                            partial: content.partial(),
+                        });
+                    }
+                }
+            }
+            recorder.record_delta();
+
+            let kept = cap.truncate(delta);
+            if !kept.is_empty() {
+                content.forward(kept).await?;
+                sequencer.record(0);
+            }
+
+            if cap.exceeded() {
+                break;
+            }
+        }
+
+        let metadata = MessageMetadata {
+            latency: Some(recorder.finish()),
+            truncated: cap.truncation(),
+            sequence: Some(sequencer.finish()),
+            content_bytes: Some(content.bytes()),
+            route: None,
+            truncated_stream: None,
+            request_snapshot: None,
+            request_id: None,
+            service_tier: None,
+            tool_invocation: None,
+            synthesized_call_ids: Default::default(),
+            warnings: Vec::new(),
+            citations: None,
+        };
+
+        options.post_process(self.reply(system_prompt, content.finish(), metadata))
+    }
+}
+
+impl Default for EchoClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wait for `cancellation` to fire; for ever without one.
+async fn cancelled(cancellation: Option<&CancellationToken>) {
+    match cancellation {
+        Some(cancellation) => cancellation.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCore for EchoClient {
+    /// The echo model needs no credentials.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        Ok(String::new())
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &self.clock
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(self.api(), content)
+    }
+
+    /// A request that is never sent; it exists so `stats` and
+    /// request-inspecting code behave as they do for real clients.
+    fn build_request(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let mut body = serde_json::json!({
+            "model": "wire:echo",
+            "system": system_prompt,
+            "messages": chat_history,
+            "stream": stream,
+        });
+
+        if let Some(tools) = tools {
+            body["tools"] = serde_json::json!(tools);
+        }
+
+        Ok(reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .expect("reqwest client without proxy")
+            .post("http://localhost/wire/echo")
+            .json(&body))
+    }
+
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        check_incoming(self.moderator.as_ref(), &chat_history, options).await?;
+        let system_prompt = options.system_prompt(system_prompt);
+        let recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(Self::echo(&chat_history));
+
+        let metadata = MessageMetadata {
+            latency: Some(recorder.finish()),
+            truncated: cap.truncation(),
+            sequence: None,
+            content_bytes: None,
+            route: None,
+            truncated_stream: None,
+            request_snapshot: None,
+            request_id: None,
+            service_tier: None,
+            tool_invocation: None,
+            synthesized_call_ids: Default::default(),
+            warnings: Vec::new(),
+            citations: None,
+        };
+
+        Ok(options.post_process(self.reply(system_prompt, content, metadata))?)
+    }
+
+    /// Streams the echo one word (with its trailing whitespace) per chunk,
+    /// or as `config` says. Stops as soon as the stream is dropped or the
+    /// call is cancelled, even mid-pause.
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
+    }
+
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        response_json
+            .get("content")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or(WireError::MissingField { field: "content" })
+    }
+
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolCapable for EchoClient {
+    async fn prompt_with_tools(
+        &self,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn prompt_with_tools_with_status(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn run_tool_loop(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
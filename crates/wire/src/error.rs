@@ -0,0 +1,545 @@
+//! The one error type of the crate. Clients, the free functions in the crate
+//! root and the tool loop all fail with a `WireError`, so callers can tell a
+//! missing key from a network failure from a reply that didn't parse by
+//! matching on it:
+//!
+//! ```
+//! use wire::error::WireError;
+//!
+//! fn describe(error: &WireError) -> String {
+//!     match error {
+//!         WireError::AuthMissing { var } => format!("set {}", var),
+//!         WireError::Http { status, .. } => format!("the provider answered {}", status),
+//!         _ => error.to_string(),
+//!     }
+//! }
+//!
+//! assert_eq!(
+//!     describe(&WireError::AuthMissing { var: "OPENAI_API_KEY".to_string() }),
+//!     "set OPENAI_API_KEY"
+//! );
+//! ```
+//!
+//! `is_retryable` says whether sending the same request again could work,
+//! which is what the retry policy goes by:
+//!
+//! ```
+//! use wire::error::WireError;
+//!
+//! assert!(WireError::http(529, "/v1/messages", String::new()).is_retryable());
+//! assert!(!WireError::http(401, "/v1/messages", String::new()).is_retryable());
+//! ```
+//!
+//! Code that still deals in `Box<dyn std::error::Error>` can keep using `?`:
+//! `WireError` converts into one, and `downcast_ref::<WireError>()` gets it
+//! back.
+//!
+//! `WireError` is `Send + Sync`, as is every prompt future, so a prompt can
+//! run inside `tokio::spawn` and its error can go into a
+//! `Box<dyn std::error::Error + Send + Sync>`. A variant whose payload isn't
+//! breaks `tests/spawn_tests.rs`.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::types::Message;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WireError {
+    /// The variable holding the client's key is not set, so nothing was
+    /// sent.
+    AuthMissing { var: String },
+    /// The provider answered `path` with a status other than success.
+    /// `message` and `code` come from the provider's error object when the
+    /// body has one (see `WireError::http`); `body` is the response as it
+    /// came. `retry_after` is how long the provider asked the caller to
+    /// wait, from its `Retry-After` or rate-limit reset headers.
+    /// `request_id` is the provider's id for the request, for support.
+    Http {
+        status: u16,
+        path: String,
+        code: Option<String>,
+        message: Option<String>,
+        body: String,
+        retry_after: Option<Duration>,
+        request_id: Option<String>,
+    },
+    /// The request could not be sent or its response not read: DNS,
+    /// connection or TLS.
+    Transport(reqwest::Error),
+    /// The same for streams, as the io error behind the failure so its kind
+    /// (refused, reset, ...) can be matched, and for a stream that broke
+    /// off.
+    Io(std::io::Error),
+    /// `ClientOptions::connect_timeout` or `request_timeout` ran out.
+    Timeout { phase: TimeoutPhase },
+    /// The provider reported an error in a response that otherwise arrived
+    /// fine. `code` is its own code or type for the error, when it gives one.
+    Provider {
+        code: Option<String>,
+        message: String,
+        request_id: Option<String>,
+    },
+    /// A request or response body was not the JSON expected.
+    Serialization(serde_json::Error),
+    /// The response parsed, but without `field`, which the client reads
+    /// the reply from.
+    MissingField { field: &'static str },
+    /// A stream event was not JSON and the stream went on after it, or
+    /// `StreamOptions::strict_stream_end` is set.
+    StreamProtocol { reason: String },
+    /// A stream sent nothing for `idle`
+    /// (`ClientOptions::stream_idle_timeout`) and was abandoned. `partial`
+    /// is the content that arrived before it stalled, unless the prompt
+    /// discards streamed content.
+    StreamStalled {
+        idle: Duration,
+        partial: String,
+        request_id: Option<String>,
+    },
+    /// The receiver of a stream's deltas was dropped.
+    StreamClosed,
+    /// The call's `CancellationToken` fired (`PromptOptions::cancellation`
+    /// or `ClientOptions::cancellation`) and its request was dropped.
+    /// `partial` is the content a stream had sent by then, unless the
+    /// prompt discards streamed content.
+    Cancelled { partial: String },
+    /// `OnFull::Fail` is set and the stream's channel, of `capacity`, was
+    /// full.
+    ChannelFull { capacity: usize },
+    /// The model called `name`, which is not among the tools given.
+    ToolNotFound { name: String },
+    /// `tool_loop::record_tool_outputs` was given an output for `id`, which
+    /// is not a pending tool call.
+    UnknownToolCall { id: String },
+    /// Tool `name` could not run: its arguments were not JSON, or it
+    /// panicked.
+    ToolExecution { name: String, reason: String },
+    /// The tool loop ran `max` iterations without a final answer; see
+    /// `ClientOptions::with_max_tool_iterations`.
+    ToolLoopLimit { max: usize },
+    /// A tool call the model wrote into its reply under
+    /// `ToolTransport::TextProtocol` could not be read, even after repair.
+    MalformedToolCall { reason: String },
+    /// `client` has no tool loop, but tools were given.
+    ToolsUnsupported { client: String },
+    /// A reply in the tool loop hit `max_tokens` and `MaxTokensBehavior`
+    /// allowed no (further) continuation. `partial` is the text and any
+    /// tool calls received before the cut-off; call arguments may be
+    /// incomplete JSON.
+    TruncatedResponse { partial: Box<Message> },
+    /// `new_client` was given a model no enabled client serves.
+    UnknownModel { model: String, reason: String },
+    /// The `scheduler::PromptScheduler` was dropped before the prompt
+    /// started.
+    SchedulerDropped,
+    /// The configured `Moderator` flagged the incoming user content, so
+    /// nothing was sent to the model.
+    ContentFlagged { categories: Vec<String> },
+    /// `SanitizePolicy::Reject` found a control character in outbound text:
+    /// in message `message_index` of the history, or in the system prompt
+    /// when that is `None`. `offset` is in bytes.
+    ControlCharacter {
+        message_index: Option<usize>,
+        offset: usize,
+        character: char,
+    },
+    /// A `PromptOptions` field was set that `provider`'s API has no
+    /// equivalent for, so nothing was sent.
+    UnsupportedOption {
+        option: &'static str,
+        provider: String,
+    },
+    /// `HistoryStrictness::Strict` found message `message_index` of the
+    /// history in a form `provider` can't take; see `normalize`.
+    UntranslatableHistory {
+        message_index: usize,
+        provider: String,
+        reason: String,
+    },
+    /// The request body came to `bytes`, over `ClientOptions::max_request_bytes`,
+    /// so nothing was sent. `largest` lists the biggest parts of the body,
+    /// biggest first; see `request_size`.
+    RequestTooLarge {
+        bytes: usize,
+        limit: usize,
+        largest: Vec<crate::request_size::BodyPart>,
+    },
+    /// `ClientOptions::deny_warnings` is set and building the request raised
+    /// `warning`, so nothing was sent; see `warning`.
+    Warning {
+        warning: crate::warning::WireWarning,
+    },
+    /// A `PromptOptions::post` step failed on the reply; see `post`.
+    /// `content` is the reply as the model wrote it.
+    PostProcess {
+        error: crate::post::PostError,
+        content: String,
+    },
+}
+
+/// What a `WireError::Timeout` was waiting for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeoutPhase {
+    /// A connection to the provider (`connect_timeout`).
+    Connect,
+    /// The whole of a request and its response (`request_timeout`).
+    Request,
+    /// The next bytes of a stream (`request_timeout`, per read).
+    Read,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutPhase::Connect => write!(f, "connecting"),
+            TimeoutPhase::Request => write!(f, "request"),
+            TimeoutPhase::Read => write!(f, "stream read"),
+        }
+    }
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_message(f)?;
+        match self.request_id() {
+            Some(request_id) => write!(f, "; request-id: {}", request_id),
+            None => Ok(()),
+        }
+    }
+}
+
+impl WireError {
+    fn fmt_message(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::AuthMissing { var } => write!(f, "{} environment variable not set", var),
+            WireError::Http {
+                status,
+                path,
+                code,
+                message,
+                body,
+                ..
+            } => {
+                write!(f, "HTTP {} from {}", status, path)?;
+                if let Some(code) = code {
+                    write!(f, " ({})", code)?;
+                }
+                match message {
+                    Some(message) => write!(f, ": {}", message),
+                    None if body.is_empty() => Ok(()),
+                    None => write!(f, ": {}", body),
+                }
+            }
+            WireError::Transport(err) => write!(f, "request failed: {}", err),
+            WireError::Io(err) => write!(f, "stream failed: {}", err),
+            WireError::Timeout { phase } => write!(f, "{} timed out", phase),
+            WireError::Provider {
+                code: Some(code),
+                message,
+                ..
+            } => write!(f, "provider error {}: {}", code, message),
+            WireError::Provider {
+                code: None,
+                message,
+                ..
+            } => write!(f, "provider error: {}", message),
+            WireError::Serialization(err) => write!(f, "invalid JSON: {}", err),
+            WireError::MissingField { field } => write!(f, "Missing '{}'", field),
+            WireError::StreamProtocol { reason } => write!(f, "invalid stream: {}", reason),
+            WireError::StreamStalled { idle, partial, .. } => write!(
+                f,
+                "stream stalled: nothing received for {:.1}s after {} bytes",
+                idle.as_secs_f64(),
+                partial.len()
+            ),
+            WireError::StreamClosed => write!(f, "the stream's receiver was dropped"),
+            WireError::Cancelled { partial } => {
+                write!(f, "cancelled after {} bytes", partial.len())
+            }
+            WireError::ChannelFull { capacity } => write!(
+                f,
+                "stream consumer fell behind: channel of capacity {} is full",
+                capacity
+            ),
+            WireError::ToolNotFound { name } => write!(f, "tool {} not found", name),
+            WireError::UnknownToolCall { id } => write!(f, "no pending tool call with id {}", id),
+            WireError::ToolExecution { name, reason } => {
+                write!(f, "tool {} failed: {}", name, reason)
+            }
+            WireError::ToolLoopLimit { max } => {
+                write!(f, "tool loop exceeded {} iterations", max)
+            }
+            WireError::MalformedToolCall { reason } => write!(f, "{}", reason),
+            WireError::ToolsUnsupported { client } => write!(f, "{} cannot run tools", client),
+            WireError::TruncatedResponse { partial } => write!(
+                f,
+                "response stopped at max_tokens after {} bytes",
+                partial.content.len()
+            ),
+            WireError::UnknownModel { reason, .. } => write!(f, "{}", reason),
+            WireError::SchedulerDropped => {
+                write!(f, "the scheduler was dropped before the prompt started")
+            }
+            WireError::ContentFlagged { categories } if categories.is_empty() => {
+                write!(f, "content flagged by moderation")
+            }
+            WireError::ContentFlagged { categories } => {
+                write!(
+                    f,
+                    "content flagged by moderation: {}",
+                    categories.join(", ")
+                )
+            }
+            WireError::ControlCharacter {
+                message_index,
+                offset,
+                character,
+            } => {
+                let found = crate::sanitize::ControlCharacter {
+                    offset: *offset,
+                    character: *character,
+                };
+                match message_index {
+                    Some(index) => write!(f, "message {} contains {}", index, found),
+                    None => write!(f, "system prompt contains {}", found),
+                }
+            }
+            WireError::UnsupportedOption { option, provider } => {
+                write!(f, "{} is not supported by {}", option, provider)
+            }
+            WireError::UntranslatableHistory {
+                message_index,
+                provider,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "message {} can't be sent to {}: {}",
+                    message_index, provider, reason
+                )
+            }
+            WireError::RequestTooLarge {
+                bytes,
+                limit,
+                largest,
+            } => {
+                write!(
+                    f,
+                    "request body of {} bytes is over the {} byte limit",
+                    bytes, limit
+                )?;
+                for (index, part) in largest.iter().enumerate() {
+                    let lead = if index == 0 { "; largest: " } else { ", " };
+                    write!(f, "{}{} ({} bytes)", lead, part.path, part.bytes)?;
+                }
+                Ok(())
+            }
+            WireError::Warning { warning } => write!(f, "{} (warnings are denied)", warning),
+            WireError::PostProcess { error, .. } => write!(f, "post-processing failed: {}", error),
+        }
+    }
+}
+
+impl WireError {
+    /// A `Http` error for a `status` reply to `path`, with the provider's
+    /// message and code read from `body`. The providers put them in
+    /// different places:
+    ///
+    /// - OpenAI and compatible gateways: `error.message`, `error.type`
+    /// - Anthropic: `error.message`, `error.type`
+    /// - Gemini: `error.message`, `error.status`
+    /// - Ollama: `error`, a string
+    /// - Cohere: `message`
+    ///
+    /// ```
+    /// use wire::error::WireError;
+    ///
+    /// let body = r#"{"error":{"message":"Rate limit reached","type":"rate_limit_error"}}"#;
+    /// let err = WireError::http(429, "/v1/chat/completions", body.to_string());
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "HTTP 429 from /v1/chat/completions (rate_limit_error): Rate limit reached"
+    /// );
+    /// ```
+    pub fn http(status: u16, path: impl Into<String>, body: String) -> Self {
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+        let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+
+        let error = &json["error"];
+        let (code, message) = match error {
+            serde_json::Value::Object(_) => (
+                text(&error["type"])
+                    .or_else(|| text(&error["status"]))
+                    .or_else(|| text(&error["code"])),
+                text(&error["message"]),
+            ),
+            serde_json::Value::String(message) => (None, Some(message.clone())),
+            _ => (None, text(&json["message"])),
+        };
+
+        WireError::Http {
+            status,
+            path: path.into(),
+            code,
+            message,
+            body,
+            retry_after: None,
+            request_id: None,
+        }
+    }
+
+    /// The provider's id for the request that failed, from its
+    /// `x-request-id` or `request-id` response header, when it sent one.
+    /// Only errors about a response the provider sent carry it: `Http`,
+    /// `Provider` and `StreamStalled`.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            WireError::Http { request_id, .. }
+            | WireError::Provider { request_id, .. }
+            | WireError::StreamStalled { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request could succeed if sent again: timeouts
+    /// (408), rate limits (429), server errors (500, 502, 503, 504),
+    /// Anthropic's overload (529), the same reported inside a Gemini stream,
+    /// and requests that never got an answer: a refused or reset connection,
+    /// a timeout or a stalled stream. Everything else, a bad request (400),
+    /// a refused key (401, 403), an unknown model (404) or tool arguments
+    /// that don't parse among them, would fail the same way again.
+    ///
+    /// `RetryPolicy` retries these unless given its own `retry_on`.
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+
+        match self {
+            WireError::Http { status, .. } => {
+                matches!(status, 408 | 429 | 500 | 502 | 503 | 504 | 529)
+            }
+            WireError::Provider {
+                code: Some(code), ..
+            } => matches!(
+                code.as_str(),
+                "RESOURCE_EXHAUSTED" | "INTERNAL" | "UNAVAILABLE" | "DEADLINE_EXCEEDED"
+            ),
+            WireError::Timeout { .. } | WireError::StreamStalled { .. } => true,
+            WireError::Transport(err) => err.is_connect(),
+            WireError::Io(err) => matches!(
+                err.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+
+    /// Note the provider's id for the request on errors that carry one;
+    /// other errors are returned as they are.
+    pub(crate) fn with_request_id(mut self, id: Option<&str>) -> Self {
+        if let WireError::Http { request_id, .. }
+        | WireError::Provider { request_id, .. }
+        | WireError::StreamStalled { request_id, .. } = &mut self
+        {
+            if request_id.is_none() {
+                *request_id = id.map(str::to_string);
+            }
+        }
+        self
+    }
+
+    /// Fill in the content a `StreamStalled` or `Cancelled` stream had sent;
+    /// other errors are returned as they are.
+    pub(crate) fn with_partial(mut self, content: impl FnOnce() -> String) -> Self {
+        if let WireError::StreamStalled { partial, .. } | WireError::Cancelled { partial } =
+            &mut self
+        {
+            *partial = content();
+        }
+        self
+    }
+
+    /// Set the wait the provider asked for on an `Http` error; other
+    /// errors are returned as they are.
+    pub(crate) fn with_retry_after(mut self, wait: Option<Duration>) -> Self {
+        if let WireError::Http { retry_after, .. } = &mut self {
+            *retry_after = wait;
+        }
+        self
+    }
+}
+
+impl std::error::Error for WireError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WireError::Transport(err) => Some(err),
+            WireError::Io(err) => Some(err),
+            WireError::Serialization(err) => Some(err),
+            WireError::PostProcess { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for WireError {
+    fn from(err: reqwest::Error) -> Self {
+        match (err.is_timeout(), err.is_connect()) {
+            (true, true) => WireError::Timeout {
+                phase: TimeoutPhase::Connect,
+            },
+            (true, false) => WireError::Timeout {
+                phase: TimeoutPhase::Request,
+            },
+            _ => WireError::Transport(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for WireError {
+    /// An io error wrapping a `WireError`, as the stream reader's timeouts
+    /// are, gives back the `WireError`.
+    fn from(err: std::io::Error) -> Self {
+        if !err.get_ref().is_some_and(|inner| inner.is::<WireError>()) {
+            return WireError::Io(err);
+        }
+        let inner = err.into_inner().and_then(|inner| inner.downcast().ok());
+        *inner.expect("checked to be a WireError")
+    }
+}
+
+/// The provider's id for a request, from the response headers: OpenAI and
+/// most gateways send `x-request-id`, Anthropic `request-id`.
+pub(crate) fn parse_request_id<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<String> {
+    headers
+        .into_iter()
+        .find(|(name, _)| {
+            name.eq_ignore_ascii_case("x-request-id") || name.eq_ignore_ascii_case("request-id")
+        })
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Lets `try_new` take a model enum as well as a name.
+impl From<std::convert::Infallible> for WireError {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl From<serde_json::Error> for WireError {
+    fn from(err: serde_json::Error) -> Self {
+        WireError::Serialization(err)
+    }
+}
+
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for WireError {
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        WireError::StreamClosed
+    }
+}
@@ -0,0 +1,303 @@
+//! A machine-readable record of everything a client does for a prompt.
+//!
+//! With an `EventSink` set through `ClientOptions::with_event_log`, every
+//! client reports each request it sends, the responses and stream data it
+//! reads, the tools it runs and the messages it produces as `WireEvent`s, in
+//! order. `FileEventSink` writes them as JSON lines, a file that can be
+//! attached to a bug report and replayed with `MockLLMServer::from_event_log`.
+//!
+//! Credentials are never logged: request headers are left out and the `key`
+//! query parameter is dropped from request paths.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use wire::config::ClientOptions;
+//! use wire::event_log::FileEventSink;
+//!
+//! let sink = FileEventSink::create("prompt.jsonl").unwrap();
+//! let options = ClientOptions::default().with_event_log(Arc::new(sink));
+//! let client = wire::new_client_with_options("gpt-4o-mini", options).unwrap();
+//! ```
+
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::api::API;
+use crate::debug_log::DebugExchange;
+use crate::error::WireError;
+use crate::snapshot::RequestSnapshot;
+use crate::types::Message;
+use crate::warning::WireWarning;
+
+/// One step of a prompt, as written to the event log. Serialized with an
+/// `event` field naming the variant, e.g. `{"event": "tool_call", ...}`.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+// Events are built one at a time and dropped once recorded, so the size of
+// the `Message` variant costs nothing worth boxing it for
+#[allow(clippy::large_enum_variant)]
+pub enum WireEvent {
+    /// A request about to be sent. `path` includes the query string, minus
+    /// any `key` parameter.
+    Request {
+        provider: String,
+        model: String,
+        path: String,
+        stream: bool,
+        body: serde_json::Value,
+    },
+    /// The body of a non-streaming response, as a string if it was not JSON.
+    Response {
+        status: u16,
+        body: serde_json::Value,
+    },
+    /// One piece of a streamed response: an SSE line (`event: ...` or
+    /// `data: ...`) for OpenAI and Anthropic, one element of the streamed
+    /// JSON array for Gemini.
+    StreamData { data: String },
+    /// A tool is about to run.
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    /// A tool finished with `output`.
+    ToolResult {
+        id: String,
+        name: String,
+        output: String,
+    },
+    /// A message the model produced: a reply or a request for tool calls.
+    Message { message: Message },
+    /// Something the client changed or left out of the request about to be
+    /// sent; see `warning`.
+    Warning { warning: WireWarning },
+}
+
+/// Destination for `WireEvent`s.
+pub trait EventSink: Send + Sync {
+    fn record(&self, event: &WireEvent);
+
+    /// The last request/response pairs the sink kept in memory, oldest
+    /// first. Sinks that keep none, the default, return nothing.
+    fn tail(&self) -> Vec<DebugExchange> {
+        Vec::new()
+    }
+}
+
+/// An `EventSink` shared between clients, as stored in `ClientOptions`.
+#[derive(Clone)]
+pub struct EventLog(Arc<dyn EventSink>);
+
+impl EventLog {
+    pub fn new(sink: Arc<dyn EventSink>) -> Self {
+        Self(sink)
+    }
+
+    pub fn record(&self, event: &WireEvent) {
+        self.0.record(event)
+    }
+
+    pub fn tail(&self) -> Vec<DebugExchange> {
+        self.0.tail()
+    }
+}
+
+impl std::fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventLog")
+    }
+}
+
+/// Writes each event as a line of JSON. Write errors are ignored so a full
+/// disk never fails a prompt.
+pub struct FileEventSink {
+    file: Mutex<std::fs::File>,
+}
+
+impl FileEventSink {
+    /// Log to `path`, replacing anything already there.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(std::fs::File::create(path)?),
+        })
+    }
+
+    /// Log to the end of `path`, creating it if needed.
+    pub fn append(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl EventSink for FileEventSink {
+    fn record(&self, event: &WireEvent) {
+        let Ok(mut line) = serde_json::to_string(event) else {
+            return;
+        };
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// Read back a log written by `FileEventSink`.
+pub fn read_event_log(path: impl AsRef<Path>) -> std::io::Result<Vec<WireEvent>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut events = Vec::new();
+
+    for (index, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let event = serde_json::from_str(&line).map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("event log line {}: {}", index + 1, err),
+            )
+        })?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Record the event built by `event` if a log is configured.
+pub(crate) fn emit<F>(log: Option<&EventLog>, event: F)
+where
+    F: FnOnce() -> WireEvent,
+{
+    if let Some(log) = log {
+        log.record(&event());
+    }
+}
+
+/// `path` (with its query) without the `key` parameter.
+pub(crate) fn redact_path(path: &str) -> String {
+    let Some((path, query)) = path.split_once('?') else {
+        return path.to_string();
+    };
+
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some("key"))
+        .collect();
+
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query.join("&"))
+    }
+}
+
+fn request_event(api: &API, path: &str, stream: bool, body: &[u8]) -> WireEvent {
+    let (provider, model) = api.to_strings();
+
+    WireEvent::Request {
+        provider,
+        model,
+        path: redact_path(path),
+        stream,
+        body: serde_json::from_slice(body)
+            .unwrap_or_else(|_| String::from_utf8_lossy(body).into_owned().into()),
+    }
+}
+
+/// Check `request`'s body against `max_request_bytes` and record it,
+/// returning its snapshot when `snapshot` is set.
+fn record_request(
+    log: Option<&EventLog>,
+    api: &API,
+    request: &reqwest::Request,
+    stream: bool,
+    snapshot: bool,
+    max_request_bytes: Option<usize>,
+) -> Result<Option<Arc<RequestSnapshot>>, WireError> {
+    let path = || {
+        let url = request.url();
+        match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        }
+    };
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .unwrap_or_default();
+    crate::request_size::check_body(max_request_bytes, body)?;
+    emit(log, || request_event(api, &path(), stream, body));
+
+    Ok(snapshot.then(|| Arc::new(RequestSnapshot::new(api, &path(), stream, body))))
+}
+
+/// Build a streaming `request` and check and record it as `send_logged`
+/// does, for `network_common::open_stream` to send. With `snapshot` set,
+/// also returns a snapshot of it.
+pub(crate) fn prepare_stream(
+    log: Option<&EventLog>,
+    api: &API,
+    request: Result<reqwest::RequestBuilder, WireError>,
+    snapshot: bool,
+    max_request_bytes: Option<usize>,
+) -> Result<(reqwest::Request, Option<Arc<RequestSnapshot>>), WireError> {
+    let request = request?.build()?;
+    let snapshot = record_request(log, api, &request, true, snapshot, max_request_bytes)?;
+
+    Ok((request, snapshot))
+}
+
+/// Send a non-streaming `request` and read its body, recording both. A
+/// request the client could not build fails with the error it gave. With
+/// `snapshot` set, also returns a snapshot of the request, then the
+/// provider's id for it, when it sent one. A body over
+/// `max_request_bytes` fails with `WireError::RequestTooLarge` unsent, and
+/// a response with a status other than success with `WireError::Http`.
+pub(crate) async fn send_logged(
+    log: Option<&EventLog>,
+    api: &API,
+    request: Result<reqwest::RequestBuilder, WireError>,
+    snapshot: bool,
+    max_request_bytes: Option<usize>,
+) -> Result<(String, Option<Arc<RequestSnapshot>>, Option<String>), WireError> {
+    let (client, request) = request?.build_split();
+    let request = request?;
+    let snapshot = record_request(log, api, &request, false, snapshot, max_request_bytes)?;
+
+    // Without the query, which can hold a key (Gemini's `?key=`)
+    let error_path = request.url().path().to_string();
+    let response = client.execute(request).await?;
+    let status = response.status().as_u16();
+    let headers = || {
+        response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+    };
+    let retry_after = crate::retry::parse_retry_after(headers());
+    let request_id = crate::error::parse_request_id(headers());
+    let body = crate::compression::response_text(response).await?;
+
+    emit(log, || WireEvent::Response {
+        status,
+        body: serde_json::from_str(&body).unwrap_or_else(|_| body.clone().into()),
+    });
+
+    if !(200..300).contains(&status) {
+        return Err(WireError::http(status, error_path, body)
+            .with_retry_after(retry_after)
+            .with_request_id(request_id.as_deref()));
+    }
+
+    Ok((body, snapshot, request_id))
+}
@@ -0,0 +1,119 @@
+//! Fireworks AI, which serves open-weight and fine-tuned models behind an
+//! OpenAI-style chat completions API.
+//!
+//! `FireworksClient` is an `OpenAIClient` pointed at
+//! `api.fireworks.ai/inference/v1/chat/completions`, keyed by
+//! `FIREWORKS_API_KEY`, whose requests name a `FireworksModel` and whose
+//! replies carry `API::Fireworks`.
+//!
+//! Fireworks names models by path, `accounts/<account>/models/<model>`, so
+//! ids hold slashes of their own. Only the leading `fireworks/` is ever
+//! taken off; the rest goes to Fireworks as given.
+
+use crate::api::{FireworksModel, Provider, API};
+use crate::config::ClientOptions;
+use crate::openai::{openai_compatible_client, OpenAICompatible};
+
+impl FireworksModel {
+    /// A model as Fireworks names it, e.g.
+    /// `accounts/fireworks/models/llama-v3p1-70b-instruct`. Any id is
+    /// accepted; Fireworks decides whether it serves the model.
+    pub fn new(name: impl Into<String>) -> Self {
+        FireworksModel(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolve a `fireworks/<model>` identifier, or a bare
+    /// `accounts/<account>/models/<model>` one.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        let name = model.strip_prefix("fireworks/").unwrap_or(model);
+        match name.strip_prefix("accounts/") {
+            Some(path) if is_model_path(path) => Ok(FireworksModel::new(name)),
+            _ => Err(format!("Unknown Fireworks model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model without its prefix.
+    pub fn to_strings(&self) -> (String, String) {
+        ("fireworks".to_string(), self.0.clone())
+    }
+}
+
+/// `<account>/models/<model>`, neither part empty.
+fn is_model_path(path: &str) -> bool {
+    match path.split_once("/models/") {
+        Some((account, model)) => {
+            !account.is_empty() && !account.contains('/') && !model.is_empty()
+        }
+        None => false,
+    }
+}
+
+/// Takes the model with or without its `fireworks/` prefix.
+impl<'a> From<&'a str> for FireworksModel {
+    fn from(model: &'a str) -> Self {
+        FireworksModel::new(model.strip_prefix("fireworks/").unwrap_or(model))
+    }
+}
+
+impl From<String> for FireworksModel {
+    fn from(model: String) -> Self {
+        FireworksModel::from(model.as_str())
+    }
+}
+
+/// Where Fireworks serves chat completions.
+const FIREWORKS: OpenAICompatible = OpenAICompatible {
+    host: "api.fireworks.ai",
+    path: "/inference/v1/chat/completions",
+    provider: Provider::Fireworks,
+};
+
+openai_compatible_client! {
+    /// Client for Fireworks' OpenAI-compatible chat completions endpoint.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "accounts/fireworks/models/llama-v3p1-70b-instruct",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::fireworks::FireworksClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let client =
+    ///     FireworksClient::with_options("accounts/fireworks/models/llama-v3p1-70b-instruct", options);
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// # });
+    /// ```
+    FireworksClient(FireworksModel),
+    key: "The Fireworks API key, as read when the client was built or refreshed."
+}
+
+impl FireworksClient {
+    /// Construct a client with custom transport settings.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<FireworksModel>,
+    {
+        let model = model.into();
+        let openai = FIREWORKS.client(API::Fireworks(model.clone()), options);
+
+        Self { model, openai }
+    }
+}
@@ -1,11 +1,45 @@
-use native_tls::TlsStream;
-use std::io::{BufRead, Read, Write};
-use std::net::TcpStream;
+use std::collections::HashMap;
+use std::io::Read;
 
-use crate::api::{GeminiModel, Prompt};
-use crate::config::{ClientOptions, Endpoint, Scheme};
-use crate::network_common::{connect_https, unescape};
-use crate::types::{Message, MessageBuilder, MessageType, Tool};
+use crate::api::{
+    event_stream, role_for, EventStream, GeminiModel, PromptCore, Provider, RawTransport,
+    ToolCapable,
+};
+use crate::clock::Clock;
+use crate::compression::ACCEPT_ENCODING;
+use crate::config::{ClientOptions, Endpoint, PromptOptions, Scheme, ToolChoice};
+use crate::credentials::Credentials;
+use crate::error::WireError;
+use crate::event_log::{emit, prepare_stream, send_logged, EventLog, WireEvent};
+use crate::metrics::{report_metrics, warn_on_tool_schema_size, LatencyRecorder};
+use crate::moderation::check_incoming;
+use crate::network_common::{cancellable, open_stream, truncated_stream, unescape, ByteStream};
+use crate::payload::{self, json_body};
+use crate::rate_limit::estimate_tokens;
+use crate::retry::with_retries;
+use crate::sanitize::{check_outbound, outbound};
+use crate::tool_loop::{
+    report_interim_text, resume_pending_calls, run_tool_calls, synthesize_call_ids, StatusLog,
+    ToolLoop, ToolLoopResult,
+};
+use crate::tool_protocol::{prompt_with_text_tools, text_protocol_tool_choice, ToolTransport};
+use crate::types::{
+    ContentCap, DeltaSequencer, EventSlot, FinishReason, Function, FunctionCall, Message,
+    MessageBuilder, MessageMetadata, MessageType, StreamEventKind, StreamedContent, Tool, ToolSpec,
+    TruncatedStream,
+};
+use crate::warning::{RequestWarnings, WireWarning};
+
+/// Gemini only accepts a JSON object as a `functionResponse`, so any other
+/// tool output is sent as `{"result": <output>}`. Output that isn't JSON at
+/// all goes in as a string.
+fn function_response(output: &str) -> serde_json::Value {
+    match serde_json::from_str(output) {
+        Ok(object @ serde_json::Value::Object(_)) => object,
+        Ok(value) => serde_json::json!({ "result": value }),
+        Err(_) => serde_json::json!({ "result": output }),
+    }
+}
 
 impl GeminiModel {
     /// Resolve a model identifier string into the strongly typed enum variant.
@@ -41,29 +75,162 @@ impl std::str::FromStr for GeminiModel {
     }
 }
 
-impl<'a> From<&'a str> for GeminiModel {
-    fn from(model: &'a str) -> Self {
-        GeminiModel::from_model_name(model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<&str> for GeminiModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        GeminiModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
     }
 }
 
-impl From<String> for GeminiModel {
-    fn from(model: String) -> Self {
-        GeminiModel::from_model_name(&model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<String> for GeminiModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        GeminiModel::try_from(model.as_str())
     }
 }
 
+/// API version in request paths unless `GeminiClient::with_api_version`
+/// picks another.
+pub const DEFAULT_API_VERSION: &str = "v1beta";
+
+/// API version in Vertex AI request paths unless
+/// `GeminiClient::with_api_version` picks another.
+pub const VERTEX_API_VERSION: &str = "v1";
+
 /// Client adapter for Google's Gemini Generative Language API.
 ///
 /// The implementation mirrors the behaviour of the other provider clients but
 /// adapts to Gemini's specific JSON layout and chunked transfer streaming
 /// format.
+///
+/// # Examples
+///
+/// These run against `wire::mock::doctest_client`, which points `options` at
+/// a local mock server; against the real API use `GeminiClient::new` and set the API
+/// key variable.
+///
+/// A single prompt:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gemini-2.0-flash", wire::mock::DoctestCall::Prompt).await;
+/// use wire::api::PromptCore;
+/// use wire::gemini::GeminiClient;
+/// use wire::types::MessageType;
+///
+/// let client = GeminiClient::try_with_options("gemini-2.0-flash", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let reply = client
+///     .prompt("Answer briefly.".to_string(), vec![question])
+///     .await
+///     .unwrap();
+/// assert_eq!(reply.content, "It is sunny in Paris.");
+/// # });
+/// ```
+///
+/// Streaming, with each delta sent over a channel as it arrives:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gemini-2.0-flash", wire::mock::DoctestCall::Stream).await;
+/// use wire::api::PromptCore;
+/// use wire::gemini::GeminiClient;
+/// use wire::types::MessageType;
+///
+/// let client = GeminiClient::try_with_options("gemini-2.0-flash", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+/// let reply = client
+///     .prompt_stream(vec![question], "Answer briefly.".to_string(), tx)
+///     .await
+///     .unwrap();
+///
+/// let mut streamed = String::new();
+/// while let Some(delta) = rx.recv().await {
+///     streamed.push_str(&delta);
+/// }
+/// assert_eq!(streamed, reply.content);
+/// # });
+/// ```
+///
+/// A tool loop: the model's calls run until it answers in text, and the
+/// returned history holds every turn, the final answer last. Gemini only
+/// takes JSON objects as function responses, so a tool returning anything
+/// else (like the string here) has its output sent as `{"result": ...}`.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gemini-2.0-flash", wire::mock::DoctestCall::Tools).await;
+/// use wire::api::{PromptCore, ToolCapable};
+/// use wire::gemini::GeminiClient;
+/// use wire::types::{MessageType, Tool, ToolWrapper};
+///
+/// let weather = Tool {
+///     function_type: "function".to_string(),
+///     name: "get_weather".to_string(),
+///     description: "Current weather for a city.".to_string(),
+///     parameters: serde_json::json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"],
+///     }),
+///     strict: false,
+///     function: Box::new(ToolWrapper(|args: serde_json::Value| {
+///         serde_json::json!(format!("sunny in {}", args["city"].as_str().unwrap()))
+///     })),
+/// };
+///
+/// let client = GeminiClient::try_with_options("gemini-2.0-flash", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let history = client
+///     .prompt_with_tools("Use the tools you have.", vec![question], vec![weather])
+///     .await
+///     .unwrap();
+/// assert_eq!(history.last().unwrap().content, "It is sunny in Paris.");
+/// # });
+/// ```
+///
+/// The same client talks to Gemini models on Vertex AI, whose payloads
+/// match; set the project with `ClientOptions::with_vertex`.
 pub struct GeminiClient {
     pub http_client: reqwest::Client,
+    pub stream_client: reqwest::Client,
     pub model: GeminiModel,
     pub host: String,
     pub port: u16,
     pub scheme: Scheme,
+    /// API version segment of the request path, e.g. `v1beta` or `v1`.
+    pub api_version: String,
+    pub credentials: Credentials,
+    /// The options the client was built with. Requests read their settings
+    /// from here; the fields above are the ones resolved from them.
+    pub options: ClientOptions,
+    /// Options given at construction that requests leave out, reported with
+    /// every prompt.
+    pub(crate) ignored_options: Vec<WireWarning>,
 }
 
 impl GeminiClient {
@@ -84,33 +251,86 @@ impl GeminiClient {
         let model = model.into();
         let mut client = Self {
             http_client: reqwest::Client::new(),
+            stream_client: ClientOptions::default().stream_client(),
             model,
             host: "generativelanguage.googleapis.com".to_string(),
             port: 443,
             scheme: Scheme::Https,
+            api_version: DEFAULT_API_VERSION.to_string(),
+            credentials: Credentials::from_env("GEMINI_API_KEY"),
+            options: ClientOptions::default(),
+            ignored_options: Vec::new(),
         };
 
         client.apply_options(options);
         client
     }
 
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when Gemini has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<GeminiModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<GeminiModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+
+    /// Send requests to another API version, e.g. `v1` for the stable API.
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
     /// Apply caller-supplied configuration overlays.
     fn apply_options(&mut self, options: ClientOptions) {
-        match options.endpoint {
+        self.http_client = options.http_client();
+        self.stream_client = options.stream_client();
+
+        if let Some(vertex) = &options.vertex {
+            self.host = vertex.host();
+            self.api_version = VERTEX_API_VERSION.to_string();
+            self.credentials = vertex.token.credentials();
+        }
+
+        match &options.endpoint {
             Endpoint::Default => {}
             Endpoint::BaseUrl(endpoint) => {
-                self.host = endpoint.host;
+                self.host = endpoint.host.clone();
                 self.port = endpoint.port;
                 self.scheme = endpoint.scheme;
             }
         }
 
-        if options.disable_proxy {
-            self.http_client = reqwest::Client::builder()
-                .no_proxy()
-                .build()
-                .expect("reqwest client without proxy");
+        if options.thinking_level.is_some() {
+            self.ignored_options.push(WireWarning::option_ignored(
+                "thinking_level",
+                Provider::Gemini,
+                "wire sends Gemini no thinking settings",
+            ));
         }
+
+        self.options = options;
+    }
+
+    /// The warnings of a request made with `options`, starting with the
+    /// options it leaves out.
+    fn request_warnings(&self, options: &PromptOptions) -> Result<RequestWarnings<'_>, WireError> {
+        let mut warnings =
+            RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+        warnings.extend(self.ignored_options.iter().cloned())?;
+        warnings.extend(options.header_warnings(Provider::Gemini))?;
+        Ok(warnings)
     }
 
     /// Render the scheme/host/port tuple into a base URL.
@@ -130,11 +350,173 @@ impl GeminiClient {
         }
     }
 
+    /// The `generateContent` body, with one `system_instruction` part per
+    /// entry of `system_parts`. Tool calls in the history become
+    /// `functionCall` parts and their outputs `functionResponse` parts.
+    fn request_body(
+        &self,
+        system_parts: &[String],
+        chat_history: &[Message],
+        tools: Option<&[ToolSpec]>,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
+    ) -> Result<serde_json::Value, WireError> {
+        let filter = self.options.content_filter.as_ref();
+        let text = |message_type: MessageType, content: &str| match filter {
+            Some(filter) => outbound(
+                &filter.apply(&message_type, content),
+                self.options.sanitize_policy,
+            ),
+            None => outbound(content, self.options.sanitize_policy),
+        };
+
+        let contents = chat_history
+            .iter()
+            .filter_map(|m| {
+                let mut parts = Vec::new();
+                if m.message_type == MessageType::FunctionCallOutput {
+                    parts.push(serde_json::json!({
+                        "functionResponse": {
+                            "name": m.name.clone().unwrap_or_default(),
+                            "response": function_response(&text(m.message_type, &m.content)),
+                        }
+                    }));
+                // Gemini rejects parts with empty text
+                } else if !m.content.trim().is_empty() {
+                    parts.push(serde_json::json!({ "text": text(m.message_type, &m.content) }));
+                }
+
+                for call in m.tool_calls.iter().flatten() {
+                    let args: serde_json::Value = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| serde_json::json!({}));
+                    parts.push(serde_json::json!({
+                        "functionCall": { "name": call.function.name, "args": args }
+                    }));
+                }
+
+                (!parts.is_empty()).then(|| {
+                    serde_json::json!({
+                        "parts": parts,
+                        "role": role_for(Provider::Gemini, m.message_type),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut body = serde_json::json!({
+            "contents": contents,
+            "system_instruction": {
+                "parts": system_parts.iter().map(|part| {
+                    serde_json::json!({ "text": text(MessageType::System, part) })
+                }).collect::<Vec<_>>()
+            }
+        });
+
+        if let Some(max_tokens) = self.options.max_tokens {
+            body["generationConfig"] = serde_json::json!({ "maxOutputTokens": max_tokens });
+        }
+
+        if let Some(tools) = tools {
+            let declarations = tools
+                .iter()
+                .map(|t| {
+                    serde_json::json!({
+                        "name": t.name.clone(),
+                        "description": t.description.clone(),
+                        "parameters": t.parameters.clone(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            body["tools"] = serde_json::json!([{ "functionDeclarations": declarations }]);
+            if let Some(tool_choice) = self.options.tool_choice {
+                let mode = match tool_choice {
+                    ToolChoice::Auto => "AUTO",
+                    ToolChoice::Any => "ANY",
+                    ToolChoice::None => "NONE",
+                };
+                body["toolConfig"] = serde_json::json!({
+                    "functionCallingConfig": { "mode": mode }
+                });
+            }
+        }
+        options.merge_extra_body(&mut body, Provider::Gemini, warnings)?;
+
+        Ok(body)
+    }
+
+    fn http_request(
+        &self,
+        body: &serde_json::Value,
+        stream: bool,
+        options: &PromptOptions,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let url = format!("{}{}", self.origin(), options.path(&self.path(stream)));
+
+        let mut request = match self.options.vertex {
+            Some(_) => self.http_client.post(url).header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.get_auth_token()?),
+            ),
+            None => self
+                .http_client
+                .post(format!("{}?key={}", url, self.get_auth_token()?)),
+        }
+        .header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING);
+        for (name, value) in options.extra_headers() {
+            request = request.header(name, value);
+        }
+
+        Ok(json_body(request, body, self.options.json_format))
+    }
+
+    /// The streaming request as raw HTTP/1.1, for `build_request_raw`.
+    fn raw_request(
+        &self,
+        body: &serde_json::Value,
+        stream: bool,
+        options: &PromptOptions,
+    ) -> Result<String, WireError> {
+        let json_string = payload::to_string(body, self.options.json_format);
+        let path = options.path(&self.path(stream)).to_string();
+        let (path, auth) = match self.options.vertex {
+            Some(_) => (
+                path,
+                format!("Authorization: Bearer {}\r\n", self.get_auth_token()?),
+            ),
+            None => (
+                format!("{}?key={}", path, self.get_auth_token()?),
+                String::new(),
+            ),
+        };
+
+        Ok(format!(
+            "POST {} HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Accept: */*\r\n\
+        {}{}\r\n\
+        {}",
+            path,
+            self.host_header(),
+            json_string.len(),
+            auth,
+            options.raw_extra_headers(),
+            json_string.trim()
+        ))
+    }
+
     /// Compute the REST path for either synchronous or streaming requests.
     fn path(&self, stream: bool) -> String {
         let (_, model) = self.model.to_strings();
+        let model = match &self.options.vertex {
+            Some(vertex) => vertex.model_path(&model),
+            None => format!("/models/{}", model),
+        };
         format!(
-            "/v1beta/models/{}:{}",
+            "/{}{}:{}",
+            self.api_version,
             model,
             if stream {
                 "streamGenerateContent"
@@ -146,13 +528,30 @@ impl GeminiClient {
 }
 
 #[async_trait::async_trait]
-impl Prompt for GeminiClient {
-    /// Retrieve the API key from the environment.
-    fn get_auth_token(&self) -> String {
-        std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY environment variable not set")
+impl PromptCore for GeminiClient {
+    /// The API key, as read when the client was built or refreshed, or the
+    /// Vertex AI access token.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        self.credentials.token()
+    }
+
+    fn refresh_credentials(&self) {
+        self.credentials.refresh();
     }
 
     /// Helper that seeds a `MessageBuilder` configured for this Gemini model.
+    fn clock(&self) -> &dyn Clock {
+        &self.options.clock
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.options.event_log.as_ref()
+    }
+
+    fn deny_warnings(&self) -> bool {
+        self.options.deny_warnings
+    }
+
     fn new_message(&self, content: String) -> MessageBuilder {
         MessageBuilder::new(crate::api::API::Gemini(self.model.clone()), content)
     }
@@ -162,89 +561,28 @@ impl Prompt for GeminiClient {
     /// * `system_prompt` – Gemini's `system_instruction` value.
     /// * `chat_history` – prior user/model turns expressed as shared `Message`
     ///   records.
-    /// * `_tools` – placeholder for tool support (Gemini streaming currently
-    ///   ignores it).
+    /// * `tools` – sent as `functionDeclarations`, along with the client's
+    ///   `tool_choice` as `functionCallingConfig.mode`.
     /// * `stream` – selects between the `generateContent` and
     ///   `streamGenerateContent` endpoints.
     fn build_request(
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-        _tools: Option<Vec<Tool>>,
+        tools: Option<&[ToolSpec]>,
         stream: bool,
-    ) -> reqwest::RequestBuilder {
-        let body = serde_json::json!({
-            "contents": chat_history.iter().map(|m| {
-                serde_json::json!({
-                    "parts": [{
-                        "text": m.content
-                    }],
-                    "role": match m.message_type {
-                        MessageType::User => "user",
-                        MessageType::Assistant => "model",
-                        _ => panic!("Unsupported message type for Gemini"),
-                    }
-                })
-            }).collect::<Vec<_>>(),
-            "system_instruction": {
-                "parts": [{
-                    "text": system_prompt,
-                }]
-            }
-        });
-
-        let url = format!("{}{}", self.origin(), self.path(stream));
-
-        self.http_client
-            .post(format!("{}?key={}", url, self.get_auth_token()))
-            .json(&body)
-    }
-
-    /// Build the raw HTTPS request used by the streaming implementation.
-    ///
-    /// * `system_prompt` – embedded within the `system_instruction` field.
-    /// * `chat_history` – serialised into Gemini's `contents` array.
-    /// * `stream` – flips the path between streaming and non-streaming endpoints.
-    fn build_request_raw(
-        &self,
-        system_prompt: String,
-        chat_history: Vec<Message>,
-        stream: bool,
-    ) -> String {
-        let body = serde_json::json!({
-            "contents": chat_history.iter().map(|m| {
-                serde_json::json!({
-                    "parts": [{
-                        "text": m.content
-                    }],
-                    "role": match m.message_type {
-                        MessageType::User => "user",
-                        MessageType::Assistant => "model",
-                        _ => panic!("Unsupported message type for Gemini"),
-                    }
-                })
-            }).collect::<Vec<_>>(),
-            "system_instruction": {
-                "parts": [{
-                    "text": system_prompt,
-                }]
-            }
-        });
-
-        let json_string = serde_json::to_string(&body).expect("Failed to serialize JSON");
-        let path = format!("{}?key={}", self.path(stream), self.get_auth_token());
-
-        format!(
-            "POST {} HTTP/1.1\r\n\
-        Host: {}\r\n\
-        Content-Type: application/json\r\n\
-        Content-Length: {}\r\n\
-        Accept: */*\r\n\r\n\r\n\
-        {}",
-            path,
-            self.host_header(),
-            json_string.len(),
-            json_string.trim()
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let options = PromptOptions::default();
+        self.http_request(
+            &self.request_body(
+                &[system_prompt],
+                &chat_history,
+                tools,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            stream,
+            &options,
         )
     }
 
@@ -253,17 +591,54 @@ impl Prompt for GeminiClient {
     ///
     /// * `system_prompt` – instructions pinned to the returned `Message`.
     /// * `chat_history` – prior conversation turns supplied to Gemini.
-    async fn prompt(
+    async fn prompt_with_options(
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        let response = self
-            .build_request(system_prompt.clone(), chat_history, None, false)
-            .send()
-            .await?;
-
-        let body = response.text().await?;
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Gemini)?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history = warnings.normalize(
+            &crate::api::API::Gemini(self.model.clone()),
+            &chat_history,
+            self.options.history_strictness,
+        )?;
+        let system_parts = options.system_parts(system_prompt.clone());
+        check_outbound(
+            self.options.sanitize_policy,
+            &system_parts.join("\n\n"),
+            &chat_history,
+        )?;
+        let request_body =
+            self.request_body(&system_parts, &chat_history, None, options, &mut warnings)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        let recorder = LatencyRecorder::start();
+        let api = crate::api::API::Gemini(self.model.clone());
+        let estimated_tokens = estimate_tokens(&system_parts.concat(), &chat_history);
+        let (body, request_snapshot, request_id) = cancellable(
+            options.cancellation_or(self.options.cancellation.as_ref()),
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    send_logged(
+                        self.options.event_log.as_ref(),
+                        &api,
+                        self.http_request(&request_body, false, options),
+                        self.options.request_snapshot,
+                        self.options.max_request_bytes,
+                    )
+                },
+            ),
+        )
+        .await?;
+        let latency = recorder.finish();
         let response_json: serde_json::Value = serde_json::from_str(&body)?;
 
         let mut content = self.read_json_response(&response_json)?;
@@ -272,7 +647,10 @@ impl Prompt for GeminiClient {
             content = content[1..content.len() - 1].to_string();
         }
 
-        Ok(Message {
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(content);
+
+        let message = Message {
             message_type: MessageType::Assistant,
             content,
             api: crate::api::API::Gemini(self.model.clone()),
@@ -282,59 +660,79 @@ impl Prompt for GeminiClient {
             name: None,
             input_tokens: 0,
             output_tokens: 0,
-        })
+            metadata: MessageMetadata {
+                latency: Some(latency),
+                truncated: cap.truncation(),
+                sequence: None,
+                content_bytes: None,
+                route: None,
+                truncated_stream: None,
+                request_snapshot,
+                request_id,
+                service_tier: None,
+                tool_invocation: None,
+                synthesized_call_ids: Default::default(),
+                warnings: warnings.to_vec(),
+                citations: None,
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        Ok(options.post_process(message)?)
     }
 
-    /// Execute a streaming prompt request, forwarding token deltas as they
-    /// arrive.
-    ///
-    /// * `chat_history` – context sent to Gemini before streaming begins.
-    /// * `system_prompt` – baseline instruction string.
-    /// * `tx` – channel the caller reads streaming text chunks from.
-    async fn prompt_stream(
+    fn prompt_stream_events_with_options(
         &self,
-        chat_history: Vec<Message>,
         system_prompt: String,
-        tx: tokio::sync::mpsc::Sender<String>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        if self.scheme != Scheme::Https {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "prompt_stream is not available with non-TLS endpoints",
-            )));
-        }
-
-        let request = self.build_request_raw(system_prompt.clone(), chat_history, true);
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
+    }
 
-        let mut stream = connect_https(&self.host, self.port);
-        stream
-            .write_all(request.as_bytes())
-            .expect("Failed to write to stream");
-        stream.flush().expect("Failed to flush stream");
+    /// Extract the assistant payload from Gemini's JSON response body.
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        response_json
+            .get("candidates")
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.get("content"))
+            .and_then(|v| v.get("parts"))
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.get("text"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or(WireError::MissingField {
+                field: "candidates[0].content.parts[0].text",
+            })
+    }
 
-        let response = self.process_stream(stream, &tx).await?;
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
 
-        Ok(Message {
-            message_type: MessageType::Assistant,
-            content: response,
-            api: crate::api::API::Gemini(self.model.clone()),
-            system_prompt,
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-            input_tokens: 0,
-            output_tokens: 0,
-        })
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        Some(self)
     }
+}
 
+#[async_trait::async_trait]
+impl ToolCapable for GeminiClient {
     async fn prompt_with_tools(
         &self,
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-        let _ = (system_prompt, chat_history, tools);
-        Err("prompt_with_tools is not yet implemented for Gemini".into())
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
     }
 
     async fn prompt_with_tools_with_status(
@@ -343,27 +741,49 @@ impl Prompt for GeminiClient {
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-        let _ = tx;
-        self.prompt_with_tools(system_prompt, chat_history, tools)
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
             .await
+            .map(|result| result.messages)
     }
 
-    /// Extract the assistant payload from Gemini's JSON response body.
-    fn read_json_response(
+    async fn run_tool_loop(
         &self,
-        response_json: &serde_json::Value,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        response_json
-            .get("candidates")
-            .and_then(|v| v.get(0))
-            .and_then(|v| v.get("content"))
-            .and_then(|v| v.get("parts"))
-            .and_then(|v| v.get(0))
-            .and_then(|v| v.get("text"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .ok_or_else(|| "Missing 'candidates[0].content.parts[0].text'".into())
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransport for GeminiClient {
+    /// Build the raw HTTPS request used by the streaming implementation.
+    ///
+    /// * `system_prompt` – embedded within the `system_instruction` field.
+    /// * `chat_history` – serialised into Gemini's `contents` array.
+    /// * `stream` – flips the path between streaming and non-streaming endpoints.
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError> {
+        let options = PromptOptions::default();
+        self.raw_request(
+            &self.request_body(
+                &[system_prompt],
+                &chat_history,
+                None,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            stream,
+            &options,
+        )
     }
 
     /// Process Gemini's chunked transfer stream, which interleaves hex length
@@ -371,68 +791,568 @@ impl Prompt for GeminiClient {
     /// channel.
     async fn process_stream(
         &self,
-        stream: TlsStream<TcpStream>,
+        stream: Box<dyn Read + Send>,
         tx: &tokio::sync::mpsc::Sender<String>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let mut reader = std::io::BufReader::new(stream);
-        let mut accumulated_text = String::new();
-        let mut line = String::new();
+    ) -> Result<String, WireError> {
+        let mut content = StreamedContent::new(tx);
+        self.read_stream(
+            ByteStream::read_raw(stream),
+            &mut LatencyRecorder::start(),
+            &mut ContentCap::new(None),
+            &mut DeltaSequencer::new(),
+            &mut content,
+            &mut (0, 0),
+            &mut Vec::new(),
+            false,
+        )
+        .await?;
+
+        Ok(content.finish())
+    }
+}
+
+impl GeminiClient {
+    /// The text parts of a `candidates[0].content.parts` array.
+    fn text_content(parts: &[serde_json::Value]) -> String {
+        parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// The `functionCall` parts of a `candidates[0].content.parts` array.
+    /// Gemini only sometimes gives calls an id, so the rest are left empty
+    /// for the tool loop to fill in; its replies are matched to calls by
+    /// name.
+    fn tool_calls(parts: &[serde_json::Value]) -> Vec<FunctionCall> {
+        parts
+            .iter()
+            .filter_map(|part| part.get("functionCall"))
+            .map(Self::function_call)
+            .collect()
+    }
+
+    /// One `functionCall` object as a `FunctionCall`.
+    fn function_call(call: &serde_json::Value) -> FunctionCall {
+        FunctionCall {
+            id: call["id"].as_str().unwrap_or_default().to_string(),
+            call_type: "function".to_string(),
+            function: Function {
+                name: call["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call
+                    .get("args")
+                    .cloned()
+                    .unwrap_or_else(|| serde_json::json!({}))
+                    .to_string(),
+            },
+        }
+    }
+
+    async fn prompt_with_tools_internal(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        self.credentials.require()?;
+        check_incoming(
+            self.options.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        if self.options.tool_transport == ToolTransport::TextProtocol {
+            let mut warnings =
+                RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+            warnings.extend(
+                self.options
+                    .tool_choice
+                    .map(|_| text_protocol_tool_choice(Provider::Gemini)),
+            )?;
+            return prompt_with_text_tools(
+                self,
+                &self.options.tool_hooks,
+                self.options.max_tool_iterations,
+                self.options.cancellation.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                warnings.to_vec(),
+                status,
+                system_prompt,
+                chat_history,
+                tools,
+            )
+            .await;
+        }
+
+        let mut chat_history = chat_history;
+        let system_prompt = system_prompt.to_string();
+        let api = crate::api::API::Gemini(self.model.clone());
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let tool_map: HashMap<String, Tool> =
+            tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
+        resume_pending_calls(
+            &mut status,
+            self.options.event_log.as_ref(),
+            self.options.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            &system_prompt,
+        )
+        .await?;
+
+        let mut tool_loop = ToolLoop::new(
+            &self.options.tool_hooks,
+            self.options.max_tool_iterations,
+            self.options.cancellation.as_ref(),
+        );
 
         loop {
-            line.clear();
-            if reader.read_line(&mut line)? == 0 {
-                break;
+            let pending = tool_loop.next_request(&chat_history)?;
+            let mut warnings = self.request_warnings(&PromptOptions::default())?;
+            // Tool outputs join the history inside the loop, so check every turn
+            let pending = warnings.normalize(&api, &pending, self.options.history_strictness)?;
+            check_outbound(self.options.sanitize_policy, &system_prompt, &pending)?;
+
+            if let Some(ratio) = self.options.tool_schema_warning {
+                let stats = self.stats(&system_prompt, &pending, Some(&specs));
+                warn_on_tool_schema_size(&stats, ratio, &mut warnings, &mut status).await?;
             }
 
-            let line = line.trim();
-            if line.is_empty() || line == "," {
-                continue;
+            let recorder = LatencyRecorder::start();
+            let estimated_tokens = estimate_tokens(&system_prompt, &pending);
+            let (body, request_snapshot, request_id) = cancellable(
+                self.options.cancellation.as_ref(),
+                with_retries(
+                    self.options.retry_policy.as_ref(),
+                    self.options.rate_limiter.as_ref(),
+                    estimated_tokens,
+                    &self.options.clock,
+                    Some(&mut status),
+                    || {
+                        send_logged(
+                            self.options.event_log.as_ref(),
+                            &api,
+                            self.build_request(
+                                system_prompt.clone(),
+                                pending.clone(),
+                                Some(&specs),
+                                false,
+                            ),
+                            self.options.request_snapshot,
+                            self.options.max_request_bytes,
+                        )
+                    },
+                ),
+            )
+            .await?;
+            let response_json: serde_json::Value = serde_json::from_str(&body)?;
+            let metadata = MessageMetadata {
+                latency: Some(recorder.finish()),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            };
+
+            let parts = response_json["candidates"][0]["content"]["parts"]
+                .as_array()
+                .ok_or(WireError::MissingField {
+                    field: "candidates[0].content.parts",
+                })?;
+            let content = Self::text_content(parts);
+            let mut tool_calls = Self::tool_calls(parts);
+
+            if tool_calls.is_empty() {
+                let message = Message {
+                    message_type: MessageType::Assistant,
+                    content,
+                    api: api.clone(),
+                    system_prompt: system_prompt.clone(),
+                    tool_call_id: None,
+                    tool_calls: None,
+                    name: None,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
+                });
+                chat_history.push(message);
+
+                return Ok(status.finish(chat_history));
             }
 
-            let size = match i64::from_str_radix(line, 16) {
-                Ok(size) => size,
-                Err(_) => {
-                    continue;
+            report_interim_text(&mut status, &content).await;
+
+            let synthesized_call_ids = synthesize_call_ids(&mut tool_calls, tool_loop.iteration());
+            let message = Message {
+                message_type: MessageType::FunctionCall,
+                content,
+                api: api.clone(),
+                system_prompt: String::new(),
+                tool_call_id: None,
+                tool_calls: Some(tool_calls.clone()),
+                name: None,
+                input_tokens: 0,
+                output_tokens: 0,
+                metadata: MessageMetadata {
+                    synthesized_call_ids,
+                    ..metadata
+                },
+            };
+            report_metrics(&self.options.metrics_callback, &message);
+            emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                message: message.clone(),
+            });
+            chat_history.push(message);
+
+            let outputs = run_tool_calls(
+                &mut status,
+                self.options.event_log.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                &tool_map,
+                tool_calls,
+                &chat_history,
+                tool_loop.iteration(),
+                &api,
+                &system_prompt,
+            )
+            .await?;
+            chat_history.extend(outputs);
+        }
+    }
+
+    /// Execute a streaming prompt request, leaving each event in `slot` as
+    /// it arrives.
+    ///
+    /// * `chat_history` – context sent to Gemini before streaming begins.
+    /// * `system_prompt` – baseline instruction string.
+    /// * `slot` – where `prompt_stream_events_with_options` picks events up.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Gemini)?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history = warnings.normalize(
+            &crate::api::API::Gemini(self.model.clone()),
+            &chat_history,
+            self.options.history_strictness,
+        )?;
+        let system_parts = options.system_parts(system_prompt.clone());
+        check_outbound(
+            self.options.sanitize_policy,
+            &system_parts.join("\n\n"),
+            &chat_history,
+        )?;
+        let body = self.request_body(&system_parts, &chat_history, None, options, &mut warnings)?;
+        let (request, request_snapshot) = prepare_stream(
+            self.options.event_log.as_ref(),
+            &crate::api::API::Gemini(self.model.clone()),
+            self.http_request(&body, true, options),
+            self.options.request_snapshot,
+            self.options.max_request_bytes,
+        )?;
+        let system_prompt = options.system_prompt(system_prompt);
+
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let mut tokens = (0, 0);
+        let mut tool_calls = Vec::new();
+        let estimated_tokens = estimate_tokens(&system_parts.concat(), &chat_history);
+        let cancellation = options.cancellation_or(self.options.cancellation.as_ref());
+        let body = cancellable(
+            cancellation,
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    open_stream(
+                        &self.stream_client,
+                        &request,
+                        self.options.max_redirects,
+                        self.options.stream_idle_timeout,
+                    )
+                },
+            ),
+        )
+        .await?
+        .with_cancellation(cancellation);
+        let request_id = body.request_id().map(str::to_string);
+        let truncated_stream = self
+            .read_stream(
+                body,
+                &mut recorder,
+                &mut cap,
+                &mut sequencer,
+                &mut content,
+                &mut tokens,
+                &mut tool_calls,
+                options.strict_stream_end,
+            )
+            .await
+            .map_err(|err| {
+                err.with_partial(|| content.partial())
+                    .with_request_id(request_id.as_deref())
+            })?;
+        warnings.stream_truncated(Provider::Gemini, truncated_stream.as_ref())?;
+        let content_bytes = content.bytes();
+        let (input_tokens, output_tokens) = tokens;
+        let synthesized_call_ids = synthesize_call_ids(&mut tool_calls, 0);
+        let (message_type, tool_calls) = if tool_calls.is_empty() {
+            (MessageType::Assistant, None)
+        } else {
+            (MessageType::FunctionCall, Some(tool_calls))
+        };
+
+        let message = Message {
+            message_type,
+            content: content.finish(),
+            api: crate::api::API::Gemini(self.model.clone()),
+            system_prompt,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(recorder.finish()),
+                truncated: cap.truncation(),
+                sequence: Some(sequencer.finish()),
+                content_bytes: Some(content_bytes),
+                route: None,
+                truncated_stream,
+                request_snapshot,
+                request_id,
+                service_tier: None,
+                tool_invocation: None,
+                synthesized_call_ids,
+                warnings: warnings.to_vec(),
+                citations: None,
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        options.post_process(message)
+    }
+
+    /// Parse Gemini's streamed JSON array from `body`, forwarding each text
+    /// delta into `content` and noting its arrival on `recorder`. Function calls,
+    /// thoughts, usage and the finish reason are reported to `content` as
+    /// `StreamEvent`s; a call arrives whole, as its start and one arguments
+    /// delta, and is added to `calls`. The last `usageMetadata` goes to
+    /// `tokens`. Elements may
+    /// span chunks and be separated by any whitespace; an `error` object in
+    /// the array fails the stream with `WireError::Provider`.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_stream(
+        &self,
+        mut body: ByteStream,
+        recorder: &mut LatencyRecorder,
+        cap: &mut ContentCap,
+        sequencer: &mut DeltaSequencer,
+        content: &mut StreamedContent,
+        tokens: &mut (usize, usize),
+        calls: &mut Vec<FunctionCall>,
+        strict: bool,
+    ) -> Result<Option<TruncatedStream>, WireError> {
+        let mut decoder = Utf8Decoder::default();
+        // Decoded text not yet parsed; an element can span several chunks
+        let mut pending = String::new();
+
+        loop {
+            let buffer = match body.next_chunk().await {
+                Ok(Some(buffer)) => buffer,
+                Ok(None) => break,
+                // The connection closed partway through the last chunk
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof && !strict => {
+                    return Ok(Some(truncated_stream(&pending)));
                 }
+                Err(err) => return Err(err.into()),
             };
+            pending.push_str(&decoder.decode(&buffer)?);
 
-            let mut buffer = vec![0; size as usize];
-            reader.read_exact(&mut buffer)?;
+            while let Some(item) = next_element(&mut pending)? {
+                let ArrayItem::Element { raw, json } = item else {
+                    return Ok(None);
+                };
+                emit(self.options.event_log.as_ref(), || WireEvent::StreamData {
+                    data: raw,
+                });
 
-            let chunk = match String::from_utf8(buffer) {
-                Ok(c) => c,
-                Err(e) => {
-                    panic!("Error: non-UTF8 in Gemini response! {}", e);
+                if let Some(error) = json.get("error") {
+                    return Err(WireError::Provider {
+                        code: error["status"].as_str().map(str::to_string),
+                        message: error["message"].as_str().unwrap_or_default().to_string(),
+                        request_id: None,
+                    });
                 }
-            }
-            .trim()
-            .to_string();
 
-            if chunk == "]" {
-                break;
-            }
+                let candidate = &json["candidates"][0];
+                for part in candidate["content"]["parts"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Some(call) = part.get("functionCall") {
+                        let call = Self::function_call(call);
+                        let start = StreamEventKind::ToolCallStart {
+                            index: calls.len(),
+                            id: call.id.clone(),
+                            name: call.function.name.clone(),
+                        };
+                        content.report(start).await?;
+                        let arguments = StreamEventKind::ToolCallArgumentsDelta {
+                            index: calls.len(),
+                            delta: call.function.arguments.clone(),
+                        };
+                        content.report(arguments).await?;
+                        calls.push(call);
+                        continue;
+                    }
+
+                    let Some(text) = part["text"].as_str() else {
+                        continue;
+                    };
+                    if part["thought"] == true {
+                        content
+                            .report(StreamEventKind::Thinking(text.to_string()))
+                            .await?;
+                        continue;
+                    }
 
-            let chunk_ref = {
-                if chunk.starts_with('[') {
-                    &chunk[1..]
-                } else if chunk.starts_with(",\r\n") {
-                    &chunk[3..]
-                } else {
-                    panic!("Error: unexpected chunk format: {}", chunk);
+                    recorder.record_delta();
+
+                    let kept = cap.truncate(text.to_string());
+                    if !kept.is_empty() {
+                        content.forward(kept).await?;
+                        sequencer.record(0);
+                    }
+
+                    // Dropping `body` on return closes the connection
+                    if cap.exceeded() {
+                        return Ok(None);
+                    }
                 }
-            };
 
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(chunk_ref) {
-                if let Some(text) = json["candidates"][0]["content"]["parts"][0]["text"].as_str() {
-                    accumulated_text.push_str(text);
-                    tx.send(text.to_string()).await?;
+                if let Some(usage) = json["usageMetadata"].as_object() {
+                    let count =
+                        |field: &str| usage.get(field).and_then(|v| v.as_u64()).unwrap_or(0);
+                    *tokens = (
+                        count("promptTokenCount") as usize,
+                        count("candidatesTokenCount") as usize,
+                    );
+                    let usage = StreamEventKind::Usage {
+                        input: tokens.0,
+                        output: tokens.1,
+                    };
+                    content.report(usage).await?;
+                }
+
+                if let Some(reason) = candidate["finishReason"].as_str() {
+                    let done = StreamEventKind::Done {
+                        finish_reason: FinishReason::from_provider(reason),
+                    };
+                    content.report(done).await?;
                 }
             }
+        }
 
-            let mut newline = String::new();
-            reader.read_line(&mut newline)?;
+        // The stream ended without closing the array
+        let tail = pending.trim_matches(is_separator);
+        if tail.is_empty() {
+            return Ok(None);
+        }
+        if strict {
+            return Err(WireError::StreamProtocol {
+                reason: format!("Gemini stream ended mid-element: {}", tail),
+            });
         }
+        Ok(Some(truncated_stream(tail)))
+    }
+}
+
+/// Decodes UTF-8 arriving in pieces, holding back a character split across
+/// two pieces until the rest of it comes.
+#[derive(Default)]
+struct Utf8Decoder {
+    partial: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<String, WireError> {
+        self.partial.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            // Only the end is cut short; the rest is still to come
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => {
+                return Err(WireError::StreamProtocol {
+                    reason: format!("non-UTF-8 in Gemini response: {}", err),
+                })
+            }
+        };
+
+        let rest = self.partial.split_off(valid);
+        let text = std::mem::replace(&mut self.partial, rest);
+        Ok(String::from_utf8(text).expect("checked as UTF-8 above"))
+    }
+}
 
-        Ok(accumulated_text)
+/// What comes between the elements of Gemini's streamed JSON array.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '[' || c == ','
+}
+
+/// What `next_element` found at the front of the stream.
+enum ArrayItem {
+    Element {
+        raw: String,
+        json: serde_json::Value,
+    },
+    End,
+}
+
+/// Take the next element of Gemini's streamed JSON array off the front of
+/// `pending`, or `None` while it is still incomplete.
+fn next_element(pending: &mut String) -> Result<Option<ArrayItem>, WireError> {
+    let rest = pending.trim_start_matches(is_separator);
+    if rest.starts_with(']') {
+        pending.clear();
+        return Ok(Some(ArrayItem::End));
+    }
+
+    let mut values = serde_json::Deserializer::from_str(rest).into_iter::<serde_json::Value>();
+    match values.next() {
+        Some(Ok(json)) => {
+            let raw = rest[..values.byte_offset()].to_string();
+            let skipped = pending.len() - rest.len();
+            pending.drain(..skipped + raw.len());
+            Ok(Some(ArrayItem::Element { raw, json }))
+        }
+        Some(Err(err)) if err.is_eof() => Ok(None),
+        None => Ok(None),
+        Some(Err(err)) => Err(WireError::StreamProtocol {
+            reason: format!("unexpected Gemini stream element: {}", err),
+        }),
     }
 }
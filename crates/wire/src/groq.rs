@@ -0,0 +1,132 @@
+//! Groq, which serves OpenAI's chat completions API for its own models.
+//!
+//! `GroqClient` is an `OpenAIClient` pointed at
+//! `api.groq.com/openai/v1/chat/completions`, keyed by `GROQ_API_KEY`, whose
+//! requests name a `GroqModel` and whose replies carry `API::Groq`.
+
+use crate::api::{GroqModel, Provider, API};
+use crate::config::ClientOptions;
+use crate::error::WireError;
+use crate::openai::{openai_compatible_client, OpenAICompatible};
+
+impl GroqModel {
+    /// Resolve a model identifier string into the strongly typed enum variant.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model {
+            "llama-3.3-70b-versatile" => Ok(GroqModel::Llama3370bVersatile),
+            "llama-3.1-8b-instant" => Ok(GroqModel::Llama318bInstant),
+            "llama3-70b-8192" => Ok(GroqModel::Llama370b8192),
+            _ => Err(format!("Unknown Groq model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model as Groq names it.
+    pub fn to_strings(&self) -> (String, String) {
+        let model = match self {
+            GroqModel::Llama3370bVersatile => "llama-3.3-70b-versatile",
+            GroqModel::Llama318bInstant => "llama-3.1-8b-instant",
+            GroqModel::Llama370b8192 => "llama3-70b-8192",
+        };
+
+        ("groq".to_string(), model.to_string())
+    }
+}
+
+impl std::str::FromStr for GroqModel {
+    type Err = String;
+
+    fn from_str(model: &str) -> Result<Self, Self::Err> {
+        GroqModel::from_model_name(model)
+    }
+}
+
+impl TryFrom<&str> for GroqModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        GroqModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
+    }
+}
+
+impl TryFrom<String> for GroqModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        GroqModel::try_from(model.as_str())
+    }
+}
+
+/// Where Groq serves chat completions.
+const GROQ: OpenAICompatible = OpenAICompatible {
+    host: "api.groq.com",
+    path: "/openai/v1/chat/completions",
+    provider: Provider::Groq,
+};
+
+openai_compatible_client! {
+    /// Client for Groq's OpenAI-compatible chat completions endpoint.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "llama-3.3-70b-versatile",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::groq::GroqClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let client = GroqClient::try_with_options("llama-3.3-70b-versatile", options).unwrap();
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// # });
+    /// ```
+    GroqClient(GroqModel),
+    key: "The Groq API key, as read when the client was built or refreshed."
+}
+
+impl GroqClient {
+    /// Construct a client with custom transport settings.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<GroqModel>,
+    {
+        let model = model.into();
+        let openai = GROQ.client(API::Groq(model.clone()), options);
+
+        Self { model, openai }
+    }
+
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when Groq has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<GroqModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<GroqModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+}
@@ -0,0 +1,92 @@
+//! Canonical fingerprints of requests.
+//!
+//! `request_fingerprint` hashes what a request asks the model for, so two
+//! requests with the same fingerprint can share a cached response, an
+//! in-flight call or a recorded cassette. Credentials, endpoints, timeouts and
+//! the other transport settings in `ClientOptions` are not part of it, nor is
+//! per-message bookkeeping such as token counts, metadata or the `API` tag
+//! (`provider` and `model` cover that).
+//!
+//! The fingerprint is stable within a minor version of this crate. It may
+//! change between minor versions, so don't persist it across upgrades
+//! without expecting misses.
+
+use sha2::{Digest, Sha256};
+
+use crate::api::Provider;
+use crate::config::PromptOptions;
+use crate::payload;
+use crate::types::{FunctionCall, Message, ToolSpec};
+
+/// Bumped whenever the canonical form changes.
+const FINGERPRINT_VERSION: &str = "wire-request-fingerprint-v1";
+
+/// SHA-256 of the canonical JSON form of a request.
+pub fn request_fingerprint(
+    provider: Provider,
+    model: &str,
+    system_prompt: &str,
+    chat_history: &[Message],
+    tools: Option<&[ToolSpec]>,
+    options: &PromptOptions,
+) -> [u8; 32] {
+    let messages: Vec<serde_json::Value> = chat_history
+        .iter()
+        .map(|message| {
+            serde_json::json!({
+                "type": message.message_type,
+                "content": message.content,
+                "tool_calls": message.tool_calls.as_ref().map(|calls| {
+                    calls.iter().map(canonical_call).collect::<Vec<_>>()
+                }),
+                "tool_call_id": message.tool_call_id,
+                "name": message.name,
+            })
+        })
+        .collect();
+
+    // Fragments stay separate: Gemini sends them as distinct parts
+    let system = if options.system_fragments.is_empty() {
+        serde_json::json!(system_prompt)
+    } else {
+        serde_json::json!(options.system_fragments)
+    };
+
+    let request = serde_json::json!({
+        "provider": provider.as_str(),
+        "model": model,
+        "system": system,
+        "messages": messages,
+        "tools": tools,
+        "options": {
+            "max_response_bytes": options.max_response_bytes,
+        },
+    });
+
+    let canonical = payload::to_canonical_string(&request);
+
+    let mut hasher = Sha256::new();
+    hasher.update(FINGERPRINT_VERSION.as_bytes());
+    hasher.update([0]);
+    hasher.update(canonical.as_bytes());
+    hasher.finalize().into()
+}
+
+/// A tool call with its arguments parsed, so that argument key order doesn't
+/// matter. Arguments that aren't valid JSON are kept as the raw string.
+fn canonical_call(call: &FunctionCall) -> serde_json::Value {
+    let arguments = serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+        .unwrap_or_else(|_| call.function.arguments.clone().into());
+
+    serde_json::json!({
+        "id": call.id,
+        "type": call.call_type,
+        "name": call.function.name,
+        "arguments": arguments,
+    })
+}
+
+/// Hex encoding of a fingerprint, for logs and file names.
+pub fn to_hex(fingerprint: &[u8; 32]) -> String {
+    fingerprint.iter().map(|b| format!("{:02x}", b)).collect()
+}
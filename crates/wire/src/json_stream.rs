@@ -0,0 +1,256 @@
+//! Check streamed JSON-mode output as it arrives.
+//!
+//! `JsonStreamValidator` follows brackets, strings and escapes across deltas,
+//! so deltas may split anywhere, including inside a string or right after a
+//! backslash. While the text is still
+//! a prefix of some JSON value it reports the text so far, for progressive
+//! rendering; once the top-level object or array closes it parses and reports
+//! the value. Text before the value (a preamble such as "Sure! Here's the
+//! JSON:"), text after it, and mismatched brackets fail on the delta that
+//! contains them rather than when the stream ends.
+//!
+//! ```
+//! use wire::json_stream::{JsonStreamEvent, JsonStreamValidator};
+//!
+//! let mut validator = JsonStreamValidator::new();
+//! validator.push(r#"{"name": "Ad"#).unwrap();
+//! let events = validator.push(r#"a", "tags": ["x"]}"#).unwrap();
+//!
+//! assert_eq!(
+//!     events.last(),
+//!     Some(&JsonStreamEvent::Complete(serde_json::json!({
+//!         "name": "Ada",
+//!         "tags": ["x"],
+//!     })))
+//! );
+//! ```
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonStreamEvent {
+    /// Everything received so far, starting at the value's opening bracket.
+    /// It is not valid JSON on its own, but nothing in it rules out a valid
+    /// value yet.
+    Prefix(String),
+    /// The top-level value closed and parsed.
+    Complete(serde_json::Value),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonStreamError {
+    /// Text outside the JSON value, such as a preamble before it or prose
+    /// after it. `offset` is in bytes from the start of the stream.
+    UnexpectedText { offset: usize, text: String },
+    /// A closing bracket that doesn't match the innermost open one.
+    MismatchedBracket { offset: usize, found: char },
+    /// The brackets balanced but the value doesn't parse, e.g. a bare word
+    /// where a value should be.
+    Invalid(String),
+    /// The stream ended before the value closed.
+    Incomplete,
+}
+
+impl fmt::Display for JsonStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonStreamError::UnexpectedText { offset, text } => {
+                write!(
+                    f,
+                    "unexpected text outside JSON at byte {}: {:?}",
+                    offset, text
+                )
+            }
+            JsonStreamError::MismatchedBracket { offset, found } => {
+                write!(f, "mismatched '{}' at byte {}", found, offset)
+            }
+            JsonStreamError::Invalid(err) => write!(f, "invalid JSON: {}", err),
+            JsonStreamError::Incomplete => write!(f, "stream ended inside a JSON value"),
+        }
+    }
+}
+
+impl std::error::Error for JsonStreamError {}
+
+type ValueCallback = Box<dyn FnMut(serde_json::Value) + Send>;
+
+pub struct JsonStreamValidator {
+    /// The value's text, from its opening bracket.
+    text: String,
+    /// Bytes seen so far, for error offsets.
+    offset: usize,
+    closers: Vec<char>,
+    in_string: bool,
+    escaped: bool,
+    value: Option<serde_json::Value>,
+    failed: Option<JsonStreamError>,
+    on_value_complete: Option<ValueCallback>,
+}
+
+impl JsonStreamValidator {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            offset: 0,
+            closers: Vec::new(),
+            in_string: false,
+            escaped: false,
+            value: None,
+            failed: None,
+            on_value_complete: None,
+        }
+    }
+
+    /// Call `callback` with the value when the top-level object or array
+    /// closes.
+    pub fn on_value_complete<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(serde_json::Value) + Send + 'static,
+    {
+        self.on_value_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Feed the next delta. Once this returns an error every later call
+    /// returns it too.
+    pub fn push(&mut self, delta: &str) -> Result<Vec<JsonStreamEvent>, JsonStreamError> {
+        if let Some(err) = &self.failed {
+            return Err(err.clone());
+        }
+
+        let result = self.consume(delta);
+        self.offset += delta.len();
+        if let Err(err) = &result {
+            self.failed = Some(err.clone());
+        }
+
+        result
+    }
+
+    /// The completed value. Call once the stream has ended.
+    pub fn finish(&self) -> Result<serde_json::Value, JsonStreamError> {
+        if let Some(err) = &self.failed {
+            return Err(err.clone());
+        }
+
+        self.value.clone().ok_or(JsonStreamError::Incomplete)
+    }
+
+    /// Whether the top-level value has closed.
+    pub fn is_complete(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Return a sender to hand to `prompt_stream` in place of `tx`. Deltas are
+    /// validated and forwarded to `tx`; the task resolves to the value, or to
+    /// the first error. On an error the returned sender is closed, so the
+    /// client's next send fails and the request stops early.
+    pub fn wrap_sender(
+        mut self,
+        tx: tokio::sync::mpsc::Sender<String>,
+    ) -> (
+        tokio::sync::mpsc::Sender<String>,
+        tokio::task::JoinHandle<Result<serde_json::Value, JsonStreamError>>,
+    ) {
+        let (deltas_tx, mut deltas_rx) = tokio::sync::mpsc::channel::<String>(tx.max_capacity());
+
+        let validated = tokio::spawn(async move {
+            while let Some(delta) = deltas_rx.recv().await {
+                self.push(&delta)?;
+                let _ = tx.send(delta).await;
+            }
+
+            self.finish()
+        });
+
+        (deltas_tx, validated)
+    }
+
+    fn consume(&mut self, delta: &str) -> Result<Vec<JsonStreamEvent>, JsonStreamError> {
+        let mut events = Vec::new();
+        let mut grew = false;
+
+        for (i, c) in delta.char_indices() {
+            let offset = self.offset + i;
+
+            if self.value.is_some() || self.text.is_empty() {
+                if c.is_whitespace() {
+                    continue;
+                }
+                if self.value.is_some() || !matches!(c, '{' | '[') {
+                    return Err(JsonStreamError::UnexpectedText {
+                        offset,
+                        text: delta[i..].to_string(),
+                    });
+                }
+            }
+
+            self.text.push(c);
+            grew = true;
+
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if c == '\\' {
+                    self.escaped = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+
+            match c {
+                '"' => self.in_string = true,
+                '{' => self.closers.push('}'),
+                '[' => self.closers.push(']'),
+                '}' | ']' => {
+                    if self.closers.pop() != Some(c) {
+                        return Err(JsonStreamError::MismatchedBracket { offset, found: c });
+                    }
+                    if self.closers.is_empty() {
+                        events.push(JsonStreamEvent::Prefix(self.text.clone()));
+                        grew = false;
+                        events.push(self.complete()?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if grew {
+            events.push(JsonStreamEvent::Prefix(self.text.clone()));
+        }
+
+        Ok(events)
+    }
+
+    fn complete(&mut self) -> Result<JsonStreamEvent, JsonStreamError> {
+        let value: serde_json::Value = serde_json::from_str(&self.text)
+            .map_err(|err| JsonStreamError::Invalid(err.to_string()))?;
+
+        if let Some(callback) = self.on_value_complete.as_mut() {
+            callback(value.clone());
+        }
+        self.value = Some(value.clone());
+
+        Ok(JsonStreamEvent::Complete(value))
+    }
+}
+
+impl Default for JsonStreamValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for JsonStreamValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonStreamValidator")
+            .field("text", &self.text)
+            .field("closers", &self.closers)
+            .field("in_string", &self.in_string)
+            .field("value", &self.value)
+            .field("failed", &self.failed)
+            .finish()
+    }
+}
@@ -1,44 +1,162 @@
+// `WireError::Http` keeps the provider's whole error response and request id,
+// so it is larger than clippy would like; errors are rare enough not to box it.
+#![allow(clippy::result_large_err)]
+// The request plumbing the providers share (retries, event logs, option
+// checks) has no callers in a build without one; `any_provider` is set by
+// build.rs.
+#![cfg_attr(not(any_provider), allow(dead_code))]
+
+#[cfg(any_provider)]
 mod network_common;
 
 pub mod types;
 
+#[cfg(feature = "anthropic")]
 pub mod anthropic;
 pub mod api;
+pub mod clock;
+#[cfg(feature = "cohere")]
+pub mod cohere;
+#[cfg(feature = "compatible")]
+pub mod compatible;
+pub mod compression;
 pub mod config;
+pub mod content_filter;
+pub mod credentials;
+pub mod debug_log;
+pub mod echo;
+pub mod error;
+pub mod event_log;
+#[cfg(feature = "fireworks")]
+pub mod fireworks;
+#[cfg(feature = "gemini")]
 pub mod gemini;
+#[cfg(feature = "groq")]
+pub mod groq;
+pub mod hash;
+pub mod json_stream;
+pub mod metrics;
+#[cfg(feature = "mock")]
 pub mod mock;
+pub mod moderation;
+pub mod normalize;
+#[cfg(feature = "ollama")]
+pub mod ollama;
+#[cfg(feature = "openai")]
 pub mod openai;
+#[cfg(feature = "openrouter")]
+pub mod openrouter;
+pub mod orchestrate;
+pub mod payload;
+#[cfg(feature = "perplexity")]
+pub mod perplexity;
+pub mod post;
+pub mod rate_limit;
+pub mod request_size;
+pub mod retry;
+pub mod router;
+pub mod sanitize;
+pub mod scheduler;
+pub mod sentence;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod snapshot;
+#[cfg(feature = "together")]
+pub mod together;
+pub mod tool_loop;
+pub mod tool_protocol;
+pub mod tools;
+pub mod warning;
 
 pub use api::get_available_models;
 
-use crate::config::ClientOptions;
-use api::{Prompt, API};
-use types::{Message, Tool};
+// Shorter paths for the most used items; see `prelude` for what is stable
+#[cfg(feature = "anthropic")]
+pub use anthropic::AnthropicClient;
+pub use api::{PromptCore, RawTransport, ToolCapable, API};
+#[cfg(feature = "cohere")]
+pub use cohere::CohereClient;
+#[cfg(feature = "compatible")]
+pub use compatible::OpenAICompatibleClient;
+pub use config::{ClientOptions, PromptOptions};
+pub use echo::EchoClient;
+pub use error::WireError;
+#[cfg(feature = "fireworks")]
+pub use fireworks::FireworksClient;
+#[cfg(feature = "gemini")]
+pub use gemini::GeminiClient;
+#[cfg(feature = "groq")]
+pub use groq::GroqClient;
+#[cfg(feature = "ollama")]
+pub use ollama::OllamaClient;
+#[cfg(feature = "openai")]
+pub use openai::OpenAIClient;
+#[cfg(feature = "openrouter")]
+pub use openrouter::OpenRouterClient;
+#[cfg(feature = "perplexity")]
+pub use perplexity::PerplexityClient;
+pub use router::RouterClient;
+#[cfg(feature = "together")]
+pub use together::TogetherClient;
+pub use types::{Message, MessageType, Tool};
+/// `#[tool(description = "...")]` on a function, then `get_tool!(function)`
+/// for its `Tool`. Arguments become the JSON schema's properties. Depending
+/// on `wire` is enough; the expansion only names `::wire` paths.
+#[cfg(feature = "macros")]
+pub use wire_macros::{get_tool, tool};
+
+/// What the code `#[tool]` and `get_tool!` expand to refers to, so callers
+/// need no dependencies of their own for it. Not a public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::types::{Tool, ToolWrapper};
+    pub use serde_json;
+}
 
 /// Create a client using a model identifier with default options.
 ///
 /// # Errors
-/// Returns an error when the model is unknown.
-pub fn new_client(model: &str) -> Result<Box<dyn Prompt>, String> {
+/// `WireError::UnknownModel` when no enabled client serves the model.
+pub fn new_client(model: &str) -> Result<Box<dyn PromptCore>, WireError> {
     new_client_internal(model, None)
 }
 
 /// Create a client using a model identifier and custom transport options.
 ///
+/// Options for a local server (`ClientOptions::local` and its presets) take
+/// any model name as the server's own and build an
+/// `OpenAICompatibleClient`.
+///
 /// # Errors
-/// Returns an error when the model is unknown.
+/// `WireError::UnknownModel` when no enabled client serves the model.
 pub fn new_client_with_options(
     model: &str,
     options: ClientOptions,
-) -> Result<Box<dyn Prompt>, String> {
+) -> Result<Box<dyn PromptCore>, WireError> {
     new_client_internal(model, Some(options))
 }
 
 fn new_client_internal(
     model: &str,
     options: Option<ClientOptions>,
-) -> Result<Box<dyn Prompt>, String> {
-    let api = API::from_model(model)?;
+) -> Result<Box<dyn PromptCore>, WireError> {
+    let unknown = |reason: String| WireError::UnknownModel {
+        model: model.to_string(),
+        reason,
+    };
+
+    #[cfg(feature = "compatible")]
+    if let Some(opts) = options.as_ref().filter(|opts| opts.compatible.any_model) {
+        let name = model.strip_prefix("custom/").unwrap_or(model);
+        if name.is_empty() {
+            return Err(unknown(format!("Unknown custom model: {}", model)));
+        }
+        return Ok(
+            API::Compatible(api::CompatibleModel::new(name)).to_client_with_options(opts.clone())
+        );
+    }
+
+    let api = API::from_model(model).map_err(unknown)?;
 
     Ok(match options {
         Some(opts) => api.to_client_with_options(opts),
@@ -46,9 +164,52 @@ fn new_client_internal(
     })
 }
 
+/// Everything a typical caller needs, in one glob import: the client traits,
+/// `new_client`, the `API` and model types, options, messages, tools and
+/// `WireError`.
+///
+/// The prelude and the crate-root re-exports are the stable surface: a name
+/// there is only removed or renamed in a breaking release, with a
+/// `CHANGELOG.md` entry. New names may be added at any time, so a glob import
+/// of the prelude next to another glob import can become ambiguous; name the
+/// item explicitly if that happens. The full module paths (`wire::api::API`)
+/// stay valid alongside the shorter ones.
 pub mod prelude {
-    pub use crate::types::{MessageBuilder, MessageWithTools, Tool, ToolWrapper};
-    pub use wire_macros::{get_tool, tool};
+    #[cfg(feature = "anthropic")]
+    pub use crate::api::AnthropicModel;
+    #[cfg(feature = "cohere")]
+    pub use crate::api::CohereModel;
+    #[cfg(feature = "compatible")]
+    pub use crate::api::CompatibleModel;
+    #[cfg(feature = "fireworks")]
+    pub use crate::api::FireworksModel;
+    #[cfg(feature = "gemini")]
+    pub use crate::api::GeminiModel;
+    #[cfg(feature = "groq")]
+    pub use crate::api::GroqModel;
+    #[cfg(feature = "ollama")]
+    pub use crate::api::OllamaModel;
+    #[cfg(feature = "openai")]
+    pub use crate::api::OpenAIModel;
+    #[cfg(feature = "openrouter")]
+    pub use crate::api::OpenRouterModel;
+    #[cfg(feature = "perplexity")]
+    pub use crate::api::PerplexityModel;
+    #[allow(deprecated)]
+    pub use crate::api::Prompt;
+    #[cfg(feature = "together")]
+    pub use crate::api::TogetherModel;
+    pub use crate::api::{PromptCore, Provider, RawTransport, ToolCapable, WireModel, API};
+    pub use crate::config::{ClientOptions, PromptOptions};
+    pub use crate::error::WireError;
+    pub use crate::tools::ToolRegistry;
+    pub use crate::types::{
+        ContextualToolWrapper, Message, MessageBuilder, MessageType, MessageWithTools, Tool,
+        ToolContext, ToolSpec, ToolWrapper,
+    };
+    #[cfg(feature = "macros")]
+    pub use crate::{get_tool, tool};
+    pub use crate::{new_client, new_client_with_options};
 }
 
 // TODO: These need deprecated in favor of the traits
@@ -56,12 +217,12 @@ pub mod prelude {
 pub async fn prompt_stream(
     api: API,
     system_prompt: &str,
-    chat_history: &Vec<Message>,
+    chat_history: &[Message],
     tx: tokio::sync::mpsc::Sender<String>,
-) -> Result<Message, Box<dyn std::error::Error>> {
+) -> Result<Message, WireError> {
     let client = api.to_client();
     client
-        .prompt_stream(chat_history.clone(), system_prompt.to_string(), tx)
+        .prompt_stream(chat_history.to_vec(), system_prompt.to_string(), tx)
         .await
 }
 
@@ -71,35 +232,11 @@ pub async fn prompt_with_tools(
     system_prompt: &str,
     chat_history: Vec<Message>,
     tools: Vec<Tool>,
-) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-    let response = match (api, chat_history, tools) {
-        (API::OpenAI(model), chat_history, tools) => {
-            let client = openai::OpenAIClient::new(model.clone());
-            client
-                .prompt_with_tools(system_prompt, chat_history, tools)
-                .await
-        }
-        (API::Anthropic(model), chat_history, tools) => {
-            let client = anthropic::AnthropicClient::new(model.clone());
-            client
-                .prompt_with_tools(system_prompt, chat_history, tools)
-                .await
-        }
-        (API::Gemini(model), chat_history, tools) => {
-            let client = gemini::GeminiClient::new(model.clone());
-            client
-                .prompt_with_tools(system_prompt, chat_history, tools)
-                .await
-        }
-    };
-
-    match response {
-        Ok(r) => Ok(r),
-        Err(e) => {
-            println!("error prompting LLM: {}", e);
-            Err(e)
-        }
-    }
+) -> Result<Vec<Message>, WireError> {
+    let client = api.to_client();
+    tool_client(&api, client.as_ref())?
+        .prompt_with_tools(system_prompt, chat_history, tools)
+        .await
 }
 
 pub async fn prompt_with_tools_and_status(
@@ -108,33 +245,19 @@ pub async fn prompt_with_tools_and_status(
     system_prompt: &str,
     chat_history: Vec<Message>,
     tools: Vec<Tool>,
-) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-    let response = match (api, chat_history, tools, tx) {
-        (API::OpenAI(model), chat_history, tools, tx) => {
-            let client = openai::OpenAIClient::new(model.clone());
-            client
-                .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
-                .await
-        }
-        (API::Anthropic(model), chat_history, tools, tx) => {
-            let client = anthropic::AnthropicClient::new(model.clone());
-            client
-                .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
-                .await
-        }
-        (API::Gemini(model), chat_history, tools, tx) => {
-            let client = gemini::GeminiClient::new(model.clone());
-            client
-                .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
-                .await
-        }
-    };
+) -> Result<Vec<Message>, WireError> {
+    let client = api.to_client();
+    tool_client(&api, client.as_ref())?
+        .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
+        .await
+}
 
-    match response {
-        Ok(r) => Ok(r),
-        Err(e) => {
-            println!("error prompting LLM: {}", e);
-            Err(e)
-        }
-    }
+/// `client`'s tool loop, or an error naming the provider of `api`.
+fn tool_client<'a>(
+    api: &API,
+    client: &'a dyn PromptCore,
+) -> Result<&'a dyn ToolCapable, WireError> {
+    client.tools().ok_or_else(|| WireError::ToolsUnsupported {
+        client: format!("the {} client", api.to_strings().0),
+    })
 }
@@ -0,0 +1,169 @@
+//! Timing and usage measurements collected while talking to a provider.
+//!
+//! Every client records when a request started, when the first content delta
+//! arrived and when the response finished. The numbers are attached to the
+//! returned `Message` and forwarded to an optional `MetricsCallback` configured
+//! through `ClientOptions`. `RequestStats` covers the other direction: how big
+//! a request is before it is sent.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::api::API;
+use crate::types::Message;
+
+/// Latency measurements for a single provider exchange.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    /// Time from request start until the first content delta arrived. Always
+    /// `None` for non-streaming requests and for streams that produced no
+    /// content.
+    pub ttft: Option<Duration>,
+    /// Time from request start until the response was fully consumed.
+    pub total: Duration,
+    /// Number of content deltas observed while streaming.
+    pub deltas: usize,
+}
+
+/// Usage and timing reported to the `MetricsCallback` after each request.
+#[derive(Clone, Debug)]
+pub struct PromptMetrics {
+    pub api: API,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub latency: LatencyStats,
+    /// The `RouterClient` route that handled the request, for attributing
+    /// cost. Only set on metrics reported by a router.
+    pub route: Option<String>,
+}
+
+/// Callback invoked with the `PromptMetrics` of every completed request.
+#[derive(Clone)]
+pub struct MetricsCallback(Arc<dyn Fn(&PromptMetrics) + Send + Sync>);
+
+impl MetricsCallback {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&PromptMetrics) + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+
+    pub fn call(&self, metrics: &PromptMetrics) {
+        (self.0)(metrics)
+    }
+}
+
+/// Invoke `callback`, if configured, with the usage and latency recorded on
+/// `message`.
+pub(crate) fn report_metrics(callback: &Option<MetricsCallback>, message: &Message) {
+    if let Some(callback) = callback {
+        callback.call(&PromptMetrics {
+            api: message.api.clone(),
+            input_tokens: message.input_tokens,
+            output_tokens: message.output_tokens,
+            latency: message.metadata.latency.clone().unwrap_or_default(),
+            route: message.metadata.route.clone(),
+        });
+    }
+}
+
+impl std::fmt::Debug for MetricsCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MetricsCallback")
+    }
+}
+
+/// Stopwatch used by the clients and stream processors to build `LatencyStats`.
+#[derive(Debug)]
+pub(crate) struct LatencyRecorder {
+    started: Instant,
+    first_delta: Option<Instant>,
+    deltas: usize,
+}
+
+impl LatencyRecorder {
+    /// Start timing a request.
+    pub(crate) fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            first_delta: None,
+            deltas: 0,
+        }
+    }
+
+    /// Note the arrival of a content delta.
+    pub(crate) fn record_delta(&mut self) {
+        if self.first_delta.is_none() {
+            self.first_delta = Some(Instant::now());
+        }
+
+        self.deltas += 1;
+    }
+
+    /// Stop timing and produce the collected statistics.
+    pub(crate) fn finish(&self) -> LatencyStats {
+        LatencyStats {
+            ttft: self
+                .first_delta
+                .map(|first| first.duration_since(self.started)),
+            total: self.started.elapsed(),
+            deltas: self.deltas,
+        }
+    }
+}
+
+/// Share of a request above which tool schemas trigger a warning when
+/// `ClientOptions::with_tool_schema_warning` is used without a custom ratio.
+pub const DEFAULT_TOOL_SCHEMA_WARNING_RATIO: f64 = 0.3;
+
+/// Size breakdown of a request body, as produced by `PromptCore::stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestStats {
+    /// Bytes of the body without any tool definitions.
+    pub message_bytes: usize,
+    /// Bytes added to the body by the tool definitions.
+    pub tool_schema_bytes: usize,
+    /// Rough token count for the whole body (4 bytes per token).
+    pub estimated_tokens: usize,
+    /// Messages in the chat history, not counting the system prompt.
+    pub message_count: usize,
+}
+
+impl RequestStats {
+    pub fn total_bytes(&self) -> usize {
+        self.message_bytes + self.tool_schema_bytes
+    }
+
+    /// Fraction of the body taken up by tool definitions.
+    pub fn tool_schema_ratio(&self) -> f64 {
+        match self.total_bytes() {
+            0 => 0.0,
+            total => self.tool_schema_bytes as f64 / total as f64,
+        }
+    }
+}
+
+/// Warn, in `warnings` and on `status`, when tool definitions take up more
+/// than `ratio` of the request.
+#[cfg(any_provider)]
+pub(crate) async fn warn_on_tool_schema_size(
+    stats: &RequestStats,
+    ratio: f64,
+    warnings: &mut crate::warning::RequestWarnings<'_>,
+    status: &mut crate::tool_loop::StatusLog,
+) -> Result<(), crate::error::WireError> {
+    if stats.tool_schema_ratio() <= ratio {
+        return Ok(());
+    }
+
+    let warning = crate::warning::WireWarning::ToolSchemasLarge {
+        tool_schema_bytes: stats.tool_schema_bytes,
+        total_bytes: stats.total_bytes(),
+    };
+    warnings.extend([warning.clone()])?;
+    status
+        .report(crate::tool_loop::ToolStatus::Warning(warning))
+        .await;
+    Ok(())
+}
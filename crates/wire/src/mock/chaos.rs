@@ -0,0 +1,271 @@
+//! Seeded fault injection for the mock server.
+//!
+//! A server configured with `MockLLMServer::with_chaos` picks a fault for
+//! every incoming request from a deterministic pseudo-random sequence, so a
+//! failing run can be replayed exactly by reusing its seed.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+/// Environment variable consulted by `ChaosConfig::from_env` to replay a
+/// specific seed.
+pub const CHAOS_SEED_ENV: &str = "WIRE_CHAOS_SEED";
+
+/// The kinds of misbehaviour a chaos server can inject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChaosFault {
+    /// Interleave extra `:` comment lines between SSE events.
+    ExtraComments,
+    /// Write the response in small pieces split at arbitrary byte offsets.
+    SplitWrites,
+    /// Append junk after the end of a stream (`[DONE]`, `message_stop`, `]`).
+    TrailingGarbage,
+    /// Answer with a 503 once; the next request to the same route succeeds.
+    ServiceUnavailable,
+    /// Wait a short, random time before responding.
+    Delay,
+}
+
+impl ChaosFault {
+    pub fn all() -> Vec<ChaosFault> {
+        vec![
+            ChaosFault::ExtraComments,
+            ChaosFault::SplitWrites,
+            ChaosFault::TrailingGarbage,
+            ChaosFault::ServiceUnavailable,
+            ChaosFault::Delay,
+        ]
+    }
+}
+
+/// Configuration for `MockLLMServer::with_chaos`.
+#[derive(Clone, Debug)]
+pub struct ChaosConfig {
+    seed: u64,
+    probability: f64,
+    faults: Vec<ChaosFault>,
+    max_delay: Duration,
+}
+
+impl ChaosConfig {
+    /// Inject every fault kind into every request, driven by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            probability: 1.0,
+            faults: ChaosFault::all(),
+            max_delay: Duration::from_millis(20),
+        }
+    }
+
+    /// Use the seed from `WIRE_CHAOS_SEED` when set, otherwise derive one from
+    /// the current time. Print `seed()` in test output so failures can be
+    /// replayed.
+    pub fn from_env() -> Self {
+        let seed = std::env::var(CHAOS_SEED_ENV)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+
+        Self::new(seed)
+    }
+
+    /// Restrict injection to the given fault kinds.
+    pub fn with_faults(mut self, faults: Vec<ChaosFault>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Chance (0.0 to 1.0) that any given request receives a fault.
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Upper bound for `ChaosFault::Delay` pauses.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// A fault injected into a specific request, as reported by
+/// `MockLLMServer::injected_faults`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub path: String,
+    pub fault: ChaosFault,
+}
+
+/// Small splitmix64 generator; good enough for fault selection and fully
+/// reproducible from its seed.
+#[derive(Clone, Debug)]
+pub(super) struct ChaosRng(u64);
+
+impl ChaosRng {
+    pub(super) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(super) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`.
+    pub(super) fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next_u64() % bound
+        }
+    }
+
+    pub(super) fn chance(&mut self, probability: f64) -> bool {
+        let sample = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        sample < probability
+    }
+}
+
+/// Per-server chaos bookkeeping, guarded by the server state's mutex.
+#[derive(Debug)]
+pub(super) struct ChaosState {
+    config: ChaosConfig,
+    rng: ChaosRng,
+    recovering: HashSet<String>,
+    injected: Vec<InjectedFault>,
+}
+
+impl ChaosState {
+    pub(super) fn new(config: ChaosConfig) -> Self {
+        Self {
+            rng: ChaosRng::new(config.seed),
+            config,
+            recovering: HashSet::new(),
+            injected: Vec::new(),
+        }
+    }
+
+    /// Decide what happens to the next request for `path`.
+    pub(super) fn plan(&mut self, path: &str) -> ChaosPlan {
+        let rng = ChaosRng::new(self.rng.next_u64());
+        let recovering = self.recovering.remove(path);
+
+        if self.config.faults.is_empty() || !self.rng.chance(self.config.probability) {
+            return ChaosPlan::none(rng);
+        }
+
+        let idx = self.rng.below(self.config.faults.len() as u64) as usize;
+        let fault = self.config.faults[idx];
+
+        // A 503 is never followed by another 503 on the same route, so a
+        // single retry is always enough to get through.
+        if fault == ChaosFault::ServiceUnavailable {
+            if recovering {
+                return ChaosPlan::none(rng);
+            }
+            self.recovering.insert(path.to_string());
+        }
+
+        self.injected.push(InjectedFault {
+            path: path.to_string(),
+            fault,
+        });
+
+        ChaosPlan {
+            fault: Some(fault),
+            max_delay: self.config.max_delay,
+            rng,
+        }
+    }
+
+    pub(super) fn injected(&self) -> Vec<InjectedFault> {
+        self.injected.clone()
+    }
+}
+
+/// The fault (if any) chosen for a single request, plus the generator used to
+/// make that fault's own random choices.
+#[derive(Debug)]
+pub(super) struct ChaosPlan {
+    pub(super) fault: Option<ChaosFault>,
+    max_delay: Duration,
+    rng: ChaosRng,
+}
+
+impl ChaosPlan {
+    pub(super) fn none(rng: ChaosRng) -> Self {
+        Self {
+            fault: None,
+            max_delay: Duration::ZERO,
+            rng,
+        }
+    }
+
+    pub(super) fn is(&self, fault: ChaosFault) -> bool {
+        self.fault == Some(fault)
+    }
+
+    pub(super) fn delay(&mut self) -> Option<Duration> {
+        if !self.is(ChaosFault::Delay) {
+            return None;
+        }
+
+        let max_millis = self.max_delay.as_millis().max(1) as u64;
+        Some(Duration::from_millis(1 + self.rng.below(max_millis)))
+    }
+
+    /// Whether to place an extra comment before the next SSE event.
+    pub(super) fn extra_comment(&mut self) -> bool {
+        self.is(ChaosFault::ExtraComments) && self.rng.chance(0.5)
+    }
+
+    pub(super) fn trailing_garbage(&self) -> bool {
+        self.is(ChaosFault::TrailingGarbage)
+    }
+}
+
+/// Writes a response to the socket, fragmenting every write when the plan
+/// calls for `ChaosFault::SplitWrites`.
+pub(super) struct ResponseWriter<'a> {
+    stream: &'a mut TcpStream,
+    pub(super) plan: ChaosPlan,
+}
+
+impl<'a> ResponseWriter<'a> {
+    pub(super) fn new(stream: &'a mut TcpStream, plan: ChaosPlan) -> Self {
+        Self { stream, plan }
+    }
+
+    pub(super) async fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        if !self.plan.is(ChaosFault::SplitWrites) {
+            return self.stream.write_all(bytes).await;
+        }
+
+        let mut remaining = bytes;
+        while !remaining.is_empty() {
+            let piece = (1 + self.plan.rng.below(32) as usize).min(remaining.len());
+            self.stream.write_all(&remaining[..piece]).await?;
+            self.stream.flush().await?;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            remaining = &remaining[piece..];
+        }
+
+        Ok(())
+    }
+}
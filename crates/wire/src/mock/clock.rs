@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+/// A `Clock` that only moves when told to. Clones share the same time, so
+/// keep one in the test and hand another to the client with
+/// `ClientOptions::with_clock`.
+#[derive(Clone, Debug)]
+pub struct TestClock {
+    state: Arc<Mutex<TestClockState>>,
+}
+
+#[derive(Debug)]
+struct TestClockState {
+    now: Instant,
+    sleepers: Vec<(Instant, tokio::sync::oneshot::Sender<()>)>,
+}
+
+impl TestClock {
+    /// Start at the current real time.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TestClockState {
+                now: Instant::now(),
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Move time forward by `duration`, waking every sleep that is now due.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.now += duration;
+
+        let now = state.now;
+        let (due, pending) = std::mem::take(&mut state.sleepers)
+            .into_iter()
+            .partition(|(wake_at, _)| *wake_at <= now);
+        state.sleepers = pending;
+        drop(state);
+
+        for (_, waker) in due {
+            let _ = waker.send(());
+        }
+    }
+
+    /// Sleeps that have started and not yet been woken.
+    pub fn pending_sleeps(&self) -> usize {
+        self.state.lock().unwrap().sleepers.len()
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.state.lock().unwrap().now
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        if duration.is_zero() {
+            return;
+        }
+
+        let woken = {
+            let mut state = self.state.lock().unwrap();
+            let (waker, woken) = tokio::sync::oneshot::channel();
+            let wake_at = state.now + duration;
+            state.sleepers.push((wake_at, waker));
+            woken
+        };
+
+        let _ = woken.await;
+    }
+}
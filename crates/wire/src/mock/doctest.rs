@@ -0,0 +1,230 @@
+//! Canned servers for the runnable examples in the client docs and in
+//! `examples/`, which fall back to them when no API key is set.
+
+use super::server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use crate::api::{Provider, API};
+use crate::config::ClientOptions;
+
+/// What every doctest server answers in the end.
+pub const DOCTEST_REPLY: &str = "It is sunny in Paris.";
+
+/// Placeholder API key the doctest servers expect.
+pub const DOCTEST_KEY: &str = "doctest-key";
+
+/// Which call a doc example makes, so the server can answer it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DoctestCall {
+    /// `prompt`: one JSON reply of `DOCTEST_REPLY`.
+    Prompt,
+    /// `prompt_stream`: `DOCTEST_REPLY` streamed word by word.
+    Stream,
+    /// `prompt_with_tools`: a call to `get_weather` with
+    /// `{"city": "Paris"}`, then `DOCTEST_REPLY`.
+    Tools,
+}
+
+/// Start a mock server answering `call` the way `model`'s provider would,
+/// and return it with options pointing a client at it. Sets the provider's
+/// API key variable to `DOCTEST_KEY`, since clients read their keys from
+/// the environment when they are built; build the client after calling this.
+/// The server stops when dropped.
+///
+/// # Panics
+/// If `model` is unknown, belongs to no provider with an API, or the server
+/// cannot start.
+pub async fn doctest_client(model: &str, call: DoctestCall) -> (MockLLMServer, ClientOptions) {
+    let api = API::from_model(model).expect("doctest model is known");
+    if let Some(var) = api.provider().key_var() {
+        std::env::set_var(var, DOCTEST_KEY);
+    }
+
+    let (path, responses) = match api.provider() {
+        #[cfg(feature = "openai")]
+        Provider::OpenAI => ("/v1/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "anthropic")]
+        Provider::Anthropic => ("/v1/messages".to_string(), anthropic(call)),
+        #[cfg(feature = "gemini")]
+        Provider::Gemini => {
+            let method = match call {
+                DoctestCall::Stream => "streamGenerateContent",
+                DoctestCall::Prompt | DoctestCall::Tools => "generateContent",
+            };
+            let (_, model) = api.to_strings();
+            let path = format!("/v1beta/models/{}:{}?key={}", model, method, DOCTEST_KEY);
+            (path, gemini(call))
+        }
+        #[cfg(feature = "groq")]
+        Provider::Groq => ("/openai/v1/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "ollama")]
+        Provider::Ollama => ("/api/chat".to_string(), ollama(call)),
+        #[cfg(feature = "openrouter")]
+        Provider::OpenRouter => ("/api/v1/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "cohere")]
+        Provider::Cohere => ("/v2/chat".to_string(), cohere(call)),
+        #[cfg(feature = "together")]
+        Provider::Together => ("/v1/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "fireworks")]
+        Provider::Fireworks => ("/inference/v1/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "perplexity")]
+        Provider::Perplexity => ("/chat/completions".to_string(), openai(call)),
+        #[cfg(feature = "compatible")]
+        Provider::Compatible => ("/v1/chat/completions".to_string(), openai(call)),
+        other => panic!("{:?} has no API to mock", other),
+    };
+
+    let server = MockLLMServer::start(vec![MockRoute::new(path, responses)])
+        .await
+        .expect("doctest server starts");
+    let options = ClientOptions::for_mock_server(&server).expect("options for doctest server");
+
+    (server, options)
+}
+
+fn words() -> Vec<String> {
+    DOCTEST_REPLY
+        .split_inclusive(' ')
+        .map(str::to_string)
+        .collect()
+}
+
+fn json(body: serde_json::Value) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(body))
+}
+
+#[cfg(feature = "openai")]
+fn openai(call: DoctestCall) -> Vec<MockResponse> {
+    let reply = json(serde_json::json!({
+        "choices": [{ "message": { "content": DOCTEST_REPLY } }]
+    }));
+
+    match call {
+        DoctestCall::Prompt => vec![reply],
+        DoctestCall::Stream => vec![MockResponse::openai_text_stream(words())],
+        DoctestCall::Tools => vec![
+            json(serde_json::json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call-1",
+                            "type": "function",
+                            "function": {
+                                "name": "get_weather",
+                                "arguments": r#"{"city":"Paris"}"#
+                            }
+                        }]
+                    }
+                }]
+            })),
+            reply,
+        ],
+    }
+}
+
+#[cfg(feature = "anthropic")]
+fn anthropic(call: DoctestCall) -> Vec<MockResponse> {
+    let reply = json(serde_json::json!({
+        "stop_reason": "end_turn",
+        "content": [{ "type": "text", "text": DOCTEST_REPLY }]
+    }));
+
+    match call {
+        DoctestCall::Prompt => vec![reply],
+        DoctestCall::Stream => vec![MockResponse::anthropic_text_stream(words())],
+        DoctestCall::Tools => vec![
+            json(serde_json::json!({
+                "stop_reason": "tool_use",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "call-1",
+                    "name": "get_weather",
+                    "input": { "city": "Paris" }
+                }]
+            })),
+            reply,
+        ],
+    }
+}
+
+#[cfg(feature = "gemini")]
+fn gemini(call: DoctestCall) -> Vec<MockResponse> {
+    let text = |text: &str| {
+        json(serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": text }] } }]
+        }))
+    };
+
+    match call {
+        DoctestCall::Prompt => vec![text(DOCTEST_REPLY)],
+        DoctestCall::Stream => vec![MockResponse::gemini_text_stream(words())],
+        DoctestCall::Tools => vec![
+            json(serde_json::json!({
+                "candidates": [{
+                    "content": {
+                        "parts": [{
+                            "functionCall": { "name": "get_weather", "args": { "city": "Paris" } }
+                        }]
+                    }
+                }]
+            })),
+            text(DOCTEST_REPLY),
+        ],
+    }
+}
+
+#[cfg(feature = "ollama")]
+fn ollama(call: DoctestCall) -> Vec<MockResponse> {
+    let reply = json(serde_json::json!({
+        "message": { "role": "assistant", "content": DOCTEST_REPLY },
+        "done": true
+    }));
+
+    match call {
+        DoctestCall::Prompt => vec![reply],
+        DoctestCall::Stream => vec![MockResponse::ollama_text_stream(words())],
+        DoctestCall::Tools => vec![
+            json(serde_json::json!({
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "function": { "name": "get_weather", "arguments": { "city": "Paris" } }
+                    }]
+                },
+                "done": true
+            })),
+            reply,
+        ],
+    }
+}
+
+#[cfg(feature = "cohere")]
+fn cohere(call: DoctestCall) -> Vec<MockResponse> {
+    let reply = json(serde_json::json!({
+        "finish_reason": "COMPLETE",
+        "message": {
+            "role": "assistant",
+            "content": [{ "type": "text", "text": DOCTEST_REPLY }]
+        }
+    }));
+
+    match call {
+        DoctestCall::Prompt => vec![reply],
+        DoctestCall::Stream => vec![MockResponse::cohere_text_stream(words())],
+        DoctestCall::Tools => vec![
+            json(serde_json::json!({
+                "finish_reason": "TOOL_CALL",
+                "message": {
+                    "role": "assistant",
+                    "tool_plan": "I will look up the weather in Paris.",
+                    "tool_calls": [{
+                        "id": "get_weather_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                    }]
+                }
+            })),
+            reply,
+        ],
+    }
+}
@@ -3,6 +3,14 @@
 //! applications that want to exercise clients without contacting real
 //! services.
 
+mod chaos;
+mod clock;
+#[cfg(any_provider)]
+mod doctest;
 mod server;
 
+pub use chaos::{ChaosConfig, ChaosFault, InjectedFault, CHAOS_SEED_ENV};
+pub use clock::TestClock;
+#[cfg(any_provider)]
+pub use doctest::{doctest_client, DoctestCall, DOCTEST_KEY, DOCTEST_REPLY};
 pub use server::*;
@@ -1,10 +1,18 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncReadExt;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{oneshot, Mutex, Notify};
+use tokio::task::JoinSet;
+
+use super::chaos::ResponseWriter;
+use super::chaos::{ChaosConfig, ChaosFault, ChaosPlan, ChaosRng, ChaosState, InjectedFault};
+use crate::compression::{brotli_compress, GzipWriter};
+use crate::event_log::{read_event_log, WireEvent};
 
 #[derive(Clone, Debug)]
 pub struct RecordedRequest {
@@ -43,16 +51,21 @@ impl MockRoute {
 pub enum MockResponse {
     Sse(MockSseResponse),
     Chunked(MockChunkedResponse),
+    JsonLines(MockJsonLinesResponse),
     Json(MockJsonResponse),
+    Redirect(MockRedirectResponse),
 }
 
 impl MockResponse {
+    /// OpenAI's chat completions stream: a chunk per text chunk, then the
+    /// usage chunk `include_usage` asks for, reporting 3 prompt tokens and
+    /// one completion token per chunk, then `[DONE]`.
     pub fn openai_text_stream<D>(chunks: D) -> Self
     where
         D: IntoIterator,
         D::Item: Into<String>,
     {
-        let events = chunks
+        let mut events: Vec<MockSseEvent> = chunks
             .into_iter()
             .map(|text| {
                 MockSseEvent::data_json(serde_json::json!({
@@ -66,20 +79,95 @@ impl MockResponse {
                 }))
             })
             .collect();
+        let completion_tokens = events.len();
+        events.push(MockSseEvent::data_json(serde_json::json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 3,
+                "completion_tokens": completion_tokens,
+                "total_tokens": 3 + completion_tokens
+            }
+        })));
 
-        MockResponse::Sse(MockSseResponse {
-            events,
-            send_done: true,
-        })
+        MockResponse::Sse(MockSseResponse::new(events).with_done())
     }
 
+    /// OpenAI's chat completions stream for a single tool call: a chunk
+    /// naming the call, a chunk per piece of its arguments, a `tool_calls`
+    /// finish reason and the usage chunk, then `[DONE]`.
+    pub fn openai_tool_call_stream<A>(id: &str, name: &str, arguments: A) -> Self
+    where
+        A: IntoIterator,
+        A::Item: Into<String>,
+    {
+        let chunk = |delta: serde_json::Value, finish_reason: Option<&str>| {
+            MockSseEvent::data_json(serde_json::json!({
+                "choices": [
+                    {
+                        "delta": delta,
+                        "finish_reason": finish_reason,
+                    }
+                ]
+            }))
+        };
+
+        let mut events = vec![chunk(
+            serde_json::json!({
+                "tool_calls": [{
+                    "index": 0,
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": "" }
+                }]
+            }),
+            None,
+        )];
+        events.extend(arguments.into_iter().map(|piece| {
+            chunk(
+                serde_json::json!({
+                    "tool_calls": [{
+                        "index": 0,
+                        "function": { "arguments": piece.into() }
+                    }]
+                }),
+                None,
+            )
+        }));
+        let completion_tokens = events.len();
+        events.push(chunk(serde_json::json!({}), Some("tool_calls")));
+        events.push(MockSseEvent::data_json(serde_json::json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 3,
+                "completion_tokens": completion_tokens,
+                "total_tokens": 3 + completion_tokens
+            }
+        })));
+
+        MockResponse::Sse(MockSseResponse::new(events).with_done())
+    }
+
+    /// Anthropic's messages stream: `message_start` counting 3 input
+    /// tokens, a `content_block_delta` per text chunk, then `message_delta`
+    /// counting one output token per chunk and `message_stop`.
     pub fn anthropic_text_stream<D>(chunks: D) -> Self
     where
         D: IntoIterator,
         D::Item: Into<String>,
     {
-        let mut events = Vec::new();
-        events.push(MockSseEvent::event("message_start"));
+        let typed = |kind: &str, data: serde_json::Value| MockSseEvent {
+            event: Some(kind.to_string()),
+            data: Some(data.to_string()),
+            comment: None,
+        };
+
+        let mut events = vec![typed(
+            "message_start",
+            serde_json::json!({
+                "type": "message_start",
+                "message": { "usage": { "input_tokens": 3, "output_tokens": 1 } }
+            }),
+        )];
         events.extend(chunks.into_iter().map(|text| {
             MockSseEvent::data_json(serde_json::json!({
                 "type": "content_block_delta",
@@ -88,20 +176,111 @@ impl MockResponse {
                 }
             }))
         }));
+        let output_tokens = events.len() - 1;
+        events.push(typed(
+            "message_delta",
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": "end_turn" },
+                "usage": { "output_tokens": output_tokens }
+            }),
+        ));
         events.push(MockSseEvent::event("message_stop"));
 
-        MockResponse::Sse(MockSseResponse {
-            events,
-            send_done: false,
-        })
+        MockResponse::Sse(MockSseResponse::new(events))
+    }
+
+    /// Anthropic's messages stream for a reply that calls a tool: a text
+    /// block with `text`, then a `tool_use` block whose input arrives as an
+    /// `input_json_delta` per piece of `arguments`, ending with the
+    /// `tool_use` stop reason. Usage is 3 input tokens and one output token
+    /// per delta.
+    pub fn anthropic_tool_use_stream<A>(text: &str, id: &str, name: &str, arguments: A) -> Self
+    where
+        A: IntoIterator,
+        A::Item: Into<String>,
+    {
+        let typed = |kind: &str, data: serde_json::Value| MockSseEvent {
+            event: Some(kind.to_string()),
+            data: Some(data.to_string()),
+            comment: None,
+        };
+        let block_stop = |index: usize| {
+            typed(
+                "content_block_stop",
+                serde_json::json!({ "type": "content_block_stop", "index": index }),
+            )
+        };
+
+        let mut events = vec![
+            typed(
+                "message_start",
+                serde_json::json!({
+                    "type": "message_start",
+                    "message": { "usage": { "input_tokens": 3, "output_tokens": 1 } }
+                }),
+            ),
+            typed(
+                "content_block_start",
+                serde_json::json!({
+                    "type": "content_block_start",
+                    "index": 0,
+                    "content_block": { "type": "text", "text": "" }
+                }),
+            ),
+            typed(
+                "content_block_delta",
+                serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": { "type": "text_delta", "text": text }
+                }),
+            ),
+            block_stop(0),
+            typed(
+                "content_block_start",
+                serde_json::json!({
+                    "type": "content_block_start",
+                    "index": 1,
+                    "content_block": { "type": "tool_use", "id": id, "name": name, "input": {} }
+                }),
+            ),
+        ];
+        let mut output_tokens = 1;
+        for piece in arguments {
+            events.push(typed(
+                "content_block_delta",
+                serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": 1,
+                    "delta": { "type": "input_json_delta", "partial_json": piece.into() }
+                }),
+            ));
+            output_tokens += 1;
+        }
+        events.push(block_stop(1));
+        events.push(typed(
+            "message_delta",
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": "tool_use" },
+                "usage": { "output_tokens": output_tokens }
+            }),
+        ));
+        events.push(MockSseEvent::event("message_stop"));
+
+        MockResponse::Sse(MockSseResponse::new(events))
     }
 
+    /// Gemini's streamed array: an element per text chunk, the last also
+    /// carrying `usageMetadata` for 3 prompt tokens and one candidate token
+    /// per chunk.
     pub fn gemini_text_stream<D>(chunks: D) -> Self
     where
         D: IntoIterator,
         D::Item: Into<String>,
     {
-        let objects = chunks
+        let mut objects: Vec<serde_json::Value> = chunks
             .into_iter()
             .map(|text| {
                 serde_json::json!({
@@ -119,8 +298,174 @@ impl MockResponse {
                 })
             })
             .collect();
+        let candidates_tokens = objects.len();
+        if let Some(last) = objects.last_mut() {
+            last["usageMetadata"] = serde_json::json!({
+                "promptTokenCount": 3,
+                "candidatesTokenCount": candidates_tokens,
+                "totalTokenCount": 3 + candidates_tokens
+            });
+        }
+
+        MockResponse::Chunked(MockChunkedResponse::new(objects))
+    }
+
+    /// Ollama's `/api/chat` stream: one object per text chunk, then a `done`
+    /// object reporting 3 prompt tokens and one output token per chunk.
+    pub fn ollama_text_stream<D>(chunks: D) -> Self
+    where
+        D: IntoIterator,
+        D::Item: Into<String>,
+    {
+        let mut objects: Vec<serde_json::Value> = chunks
+            .into_iter()
+            .map(|text| {
+                serde_json::json!({
+                    "message": { "role": "assistant", "content": text.into() },
+                    "done": false
+                })
+            })
+            .collect();
+        let eval_count = objects.len();
+        objects.push(serde_json::json!({
+            "message": { "role": "assistant", "content": "" },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 3,
+            "eval_count": eval_count
+        }));
+
+        MockResponse::JsonLines(MockJsonLinesResponse::new(objects))
+    }
+
+    /// Cohere's `/v2/chat` stream: a `content-delta` event per text chunk,
+    /// then `message-end` billing 3 input tokens and one output token per
+    /// chunk.
+    pub fn cohere_text_stream<D>(chunks: D) -> Self
+    where
+        D: IntoIterator,
+        D::Item: Into<String>,
+    {
+        let typed = |kind: &str, data: serde_json::Value| MockSseEvent {
+            event: Some(kind.to_string()),
+            data: Some(data.to_string()),
+            comment: None,
+        };
+
+        let mut events = vec![typed(
+            "message-start",
+            serde_json::json!({ "type": "message-start", "delta": { "message": { "role": "assistant" } } }),
+        )];
+        events.extend(chunks.into_iter().map(|text| {
+            typed(
+                "content-delta",
+                serde_json::json!({
+                    "type": "content-delta",
+                    "index": 0,
+                    "delta": { "message": { "content": { "text": text.into() } } }
+                }),
+            )
+        }));
+        let output_tokens = events.len() - 1;
+        events.push(typed(
+            "message-end",
+            serde_json::json!({
+                "type": "message-end",
+                "delta": {
+                    "finish_reason": "COMPLETE",
+                    "usage": {
+                        "billed_units": { "input_tokens": 3, "output_tokens": output_tokens }
+                    }
+                }
+            }),
+        ));
 
-        MockResponse::Chunked(MockChunkedResponse { objects })
+        MockResponse::Sse(MockSseResponse::new(events))
+    }
+
+    /// Pause for `delay` before writing each SSE event or chunk. Has no effect
+    /// on JSON responses or redirects.
+    pub fn with_chunk_delay(self, delay: Duration) -> Self {
+        match self {
+            MockResponse::Sse(sse) => MockResponse::Sse(sse.with_chunk_delay(delay)),
+            MockResponse::Chunked(chunked) => {
+                MockResponse::Chunked(chunked.with_chunk_delay(delay))
+            }
+            MockResponse::JsonLines(lines) => {
+                MockResponse::JsonLines(lines.with_chunk_delay(delay))
+            }
+            other => other,
+        }
+    }
+
+    /// Send a chunked response as chunks of `bytes` bytes instead of one
+    /// chunk per object, splitting objects and multi-byte characters
+    /// wherever they fall. `cut_short` no longer applies. Has no effect on
+    /// other responses.
+    pub fn with_chunk_size(self, bytes: usize) -> Self {
+        match self {
+            MockResponse::Chunked(chunked) => MockResponse::Chunked(chunked.with_chunk_size(bytes)),
+            other => other,
+        }
+    }
+
+    /// Send nothing for `stall` after the first `events` SSE events, keeping
+    /// the connection open, as a provider whose stream stalls would; then
+    /// send the rest. Has no effect on other responses.
+    pub fn stall_after(self, events: usize, stall: Duration) -> Self {
+        match self {
+            MockResponse::Sse(sse) => MockResponse::Sse(sse.stall_after(events, stall)),
+            other => other,
+        }
+    }
+
+    /// Send `name: value` with the head of a JSON or SSE response. Has no
+    /// effect on other responses.
+    pub fn with_header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        match self {
+            MockResponse::Json(json) => MockResponse::Json(json.with_header(name, value)),
+            MockResponse::Sse(sse) => MockResponse::Sse(sse.with_header(name, value)),
+            other => other,
+        }
+    }
+
+    /// Close the connection `keep` bytes into the last SSE event or chunk,
+    /// as a proxy timing out mid-event would. Nothing is sent after it, not
+    /// even `[DONE]` or the end of the array. Has no effect on JSON responses
+    /// or redirects.
+    pub fn cut_short(self, keep: usize) -> Self {
+        match self {
+            MockResponse::Sse(sse) => MockResponse::Sse(sse.cut_short(keep)),
+            MockResponse::Chunked(chunked) => MockResponse::Chunked(chunked.cut_short(keep)),
+            MockResponse::JsonLines(lines) => MockResponse::JsonLines(lines.cut_short(keep)),
+            other => other,
+        }
+    }
+
+    /// Send the body gzipped, as stored deflate blocks with one block per
+    /// SSE event. Has no effect on chunked responses or redirects.
+    pub fn gzipped(self) -> Self {
+        match self {
+            MockResponse::Sse(sse) => MockResponse::Sse(sse.gzipped()),
+            MockResponse::Json(json) => MockResponse::Json(json.gzipped()),
+            other => other,
+        }
+    }
+
+    /// Send the body brotli-compressed. Only JSON responses support it.
+    pub fn brotli(self) -> Self {
+        match self {
+            MockResponse::Json(json) => MockResponse::Json(json.brotli()),
+            other => other,
+        }
+    }
+
+    /// An empty response with `status` (e.g. 307) pointing at `location`.
+    pub fn redirect(status: u16, location: impl Into<String>) -> Self {
+        MockResponse::Redirect(MockRedirectResponse {
+            status,
+            location: location.into(),
+        })
     }
 }
 
@@ -142,33 +487,55 @@ impl RouteState {
     }
 }
 
+/// How long `shutdown` waits for in-flight connections before aborting them.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
 struct MockServerState {
     routes: Mutex<HashMap<String, RouteState>>,
     recordings: Mutex<Vec<RecordedRequest>>,
+    recorded: Notify,
+    chaos: std::sync::Mutex<Option<ChaosState>>,
 }
 
 impl MockServerState {
+    /// Routes match on the full path first, then on the path without its
+    /// query string.
     async fn next_response(&self, path: &str) -> Option<MockResponse> {
         let mut routes = self.routes.lock().await;
+        let path = if routes.contains_key(path) {
+            path
+        } else {
+            path.split('?').next().unwrap_or(path)
+        };
+
         routes.get_mut(path).and_then(|route| route.next())
     }
 
     async fn record_request(&self, record: RecordedRequest) {
         let mut recordings = self.recordings.lock().await;
         recordings.push(record);
+        self.recorded.notify_waiters();
     }
 
     async fn recordings(&self) -> Vec<RecordedRequest> {
         let recordings = self.recordings.lock().await;
         recordings.clone()
     }
+
+    fn chaos_plan(&self, path: &str) -> ChaosPlan {
+        let mut chaos = self.chaos.lock().unwrap_or_else(|err| err.into_inner());
+        match chaos.as_mut() {
+            Some(chaos) => chaos.plan(path),
+            None => ChaosPlan::none(ChaosRng::new(0)),
+        }
+    }
 }
 
 pub struct MockLLMServer {
     addr: SocketAddr,
     state: Arc<MockServerState>,
     shutdown_tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-    join_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    join_handle: Arc<Mutex<Option<tokio::task::JoinHandle<usize>>>>,
 }
 
 impl MockLLMServer {
@@ -179,6 +546,8 @@ impl MockLLMServer {
         let state = Arc::new(MockServerState {
             routes: Mutex::new(HashMap::new()),
             recordings: Mutex::new(Vec::new()),
+            recorded: Notify::new(),
+            chaos: std::sync::Mutex::new(None),
         });
 
         {
@@ -199,9 +568,8 @@ impl MockLLMServer {
         let join_handle_slot = Arc::new(Mutex::new(None));
 
         let state_clone = state.clone();
-        let join_handle = tokio::spawn(async move {
-            run_server(listener, state_clone, shutdown_rx).await;
-        });
+        let join_handle =
+            tokio::spawn(async move { run_server(listener, state_clone, shutdown_rx).await });
 
         {
             let mut handle_slot = join_handle_slot.lock().await;
@@ -216,6 +584,42 @@ impl MockLLMServer {
         })
     }
 
+    /// Serve the responses captured in an event log written through
+    /// `ClientOptions::with_event_log`. Each logged request path answers with
+    /// the responses logged after its requests, in order, so replaying the
+    /// same prompts gives the same results.
+    pub async fn from_event_log(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let events = read_event_log(path)?;
+        Self::start(replay_routes(events)).await
+    }
+
+    /// Inject seeded, reproducible faults into every route served from now on.
+    pub fn with_chaos(self, config: ChaosConfig) -> Self {
+        {
+            let mut chaos = self
+                .state
+                .chaos
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            *chaos = Some(ChaosState::new(config));
+        }
+
+        self
+    }
+
+    /// Faults injected so far, in request order.
+    pub fn injected_faults(&self) -> Vec<InjectedFault> {
+        let chaos = self
+            .state
+            .chaos
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        chaos
+            .as_ref()
+            .map(|chaos| chaos.injected())
+            .unwrap_or_default()
+    }
+
     pub fn address(&self) -> SocketAddr {
         self.addr
     }
@@ -224,13 +628,18 @@ impl MockLLMServer {
         format!("http://{}", self.addr)
     }
 
-    pub async fn shutdown(&self) {
+    /// Stop accepting connections and wait (up to a few seconds) for the ones
+    /// in flight to finish, so every request the server saw is recorded by
+    /// the time this returns. Returns how many were still open after that
+    /// and aborted.
+    pub async fn shutdown(&self) -> usize {
         if let Some(tx) = self.shutdown_tx.lock().await.take() {
             let _ = tx.send(());
         }
 
-        if let Some(handle) = self.join_handle.lock().await.take() {
-            let _ = handle.await;
+        match self.join_handle.lock().await.take() {
+            Some(handle) => handle.await.unwrap_or_default(),
+            None => 0,
         }
     }
 
@@ -246,6 +655,42 @@ impl MockLLMServer {
             .filter(|record| record.path == path)
             .collect()
     }
+
+    /// Wait until at least `count` requests to `path` have been recorded and
+    /// return them, or fail with `TimedOut` after `timeout`.
+    pub async fn wait_for_requests(
+        &self,
+        path: &str,
+        count: usize,
+        timeout: Duration,
+    ) -> std::io::Result<Vec<RecordedRequest>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            // Register before checking so a recording in between isn't missed
+            let recorded = self.state.recorded.notified();
+            tokio::pin!(recorded);
+            recorded.as_mut().enable();
+
+            let requests = self.requests_for(path).await;
+            if requests.len() >= count {
+                return Ok(requests);
+            }
+
+            if tokio::time::timeout_at(deadline, recorded).await.is_err() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "expected {} requests to {} within {:?}, got {}",
+                        count,
+                        path,
+                        timeout,
+                        requests.len()
+                    ),
+                ));
+            }
+        }
+    }
 }
 
 impl Drop for MockLLMServer {
@@ -268,6 +713,11 @@ impl Drop for MockLLMServer {
 pub struct MockSseResponse {
     events: Vec<MockSseEvent>,
     send_done: bool,
+    chunk_delay: Option<Duration>,
+    gzip: bool,
+    cut_at: Option<usize>,
+    stall: Option<(usize, Duration)>,
+    headers: Vec<(String, String)>,
 }
 
 impl MockSseResponse {
@@ -275,13 +725,48 @@ impl MockSseResponse {
         Self {
             events,
             send_done: false,
+            chunk_delay: None,
+            gzip: false,
+            cut_at: None,
+            stall: None,
+            headers: Vec::new(),
         }
     }
 
+    /// Send `name: value` with the response head.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Go quiet for `stall` after `events` events; see
+    /// `MockResponse::stall_after`.
+    pub fn stall_after(mut self, events: usize, stall: Duration) -> Self {
+        self.stall = Some((events, stall));
+        self
+    }
+
+    /// Stop `keep` bytes into the last event; see `MockResponse::cut_short`.
+    pub fn cut_short(mut self, keep: usize) -> Self {
+        self.cut_at = Some(keep);
+        self
+    }
+
     pub fn with_done(mut self) -> Self {
         self.send_done = true;
         self
     }
+
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+
+    /// Send the stream gzipped and chunked, one chunk per event.
+    pub fn gzipped(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -328,41 +813,266 @@ impl MockSseEvent {
 #[derive(Clone, Debug)]
 pub struct MockChunkedResponse {
     objects: Vec<serde_json::Value>,
+    chunk_delay: Option<Duration>,
+    cut_at: Option<usize>,
+    chunk_size: Option<usize>,
+    raw_chunks: Option<Vec<String>>,
+}
+
+impl MockChunkedResponse {
+    pub fn new(objects: Vec<serde_json::Value>) -> Self {
+        Self {
+            objects,
+            chunk_delay: None,
+            cut_at: None,
+            chunk_size: None,
+            raw_chunks: None,
+        }
+    }
+
+    /// Send each of `chunks` as it is, one HTTP chunk apiece, for framing
+    /// the other constructors don't produce: other separators, whitespace,
+    /// keep-alive chunks. `cut_short` and `with_chunk_size` don't apply.
+    pub fn raw<D>(chunks: D) -> Self
+    where
+        D: IntoIterator,
+        D::Item: Into<String>,
+    {
+        Self {
+            raw_chunks: Some(chunks.into_iter().map(Into::into).collect()),
+            ..Self::new(Vec::new())
+        }
+    }
+
+    /// See `MockResponse::with_chunk_size`.
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = Some(bytes.max(1));
+        self
+    }
+
+    /// Stop `keep` bytes into the last chunk; see `MockResponse::cut_short`.
+    pub fn cut_short(mut self, keep: usize) -> Self {
+        self.cut_at = Some(keep);
+        self
+    }
+
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+}
+
+/// Newline-delimited JSON, one object per chunk, as Ollama streams.
+#[derive(Clone, Debug)]
+pub struct MockJsonLinesResponse {
+    objects: Vec<serde_json::Value>,
+    chunk_delay: Option<Duration>,
+    cut_at: Option<usize>,
+}
+
+impl MockJsonLinesResponse {
+    pub fn new(objects: Vec<serde_json::Value>) -> Self {
+        Self {
+            objects,
+            chunk_delay: None,
+            cut_at: None,
+        }
+    }
+
+    /// Stop `keep` bytes into the last line; see `MockResponse::cut_short`.
+    pub fn cut_short(mut self, keep: usize) -> Self {
+        self.cut_at = Some(keep);
+        self
+    }
+
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MockJsonResponse {
     body: serde_json::Value,
     status: u16,
+    gzip: bool,
+    brotli: bool,
+    headers: Vec<(String, String)>,
+    delay: Option<Duration>,
 }
 
 impl MockJsonResponse {
     pub fn new(body: serde_json::Value) -> Self {
-        Self { body, status: 200 }
+        Self {
+            body,
+            status: 200,
+            gzip: false,
+            brotli: false,
+            headers: Vec::new(),
+            delay: None,
+        }
     }
 
     pub fn with_status(mut self, status: u16) -> Self {
         self.status = status;
         self
     }
+
+    /// Send the body with `Content-Encoding: gzip`.
+    pub fn gzipped(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    /// Send the body with `Content-Encoding: br`.
+    pub fn brotli(mut self) -> Self {
+        self.brotli = true;
+        self
+    }
+
+    /// Wait `delay` before answering at all, like a provider that hangs.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Add a header, such as `Retry-After`, to the response.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockRedirectResponse {
+    status: u16,
+    location: String,
+}
+
+/// Rebuild the routes that produced `events`.
+fn replay_routes(events: Vec<WireEvent>) -> Vec<MockRoute> {
+    let mut routes = Vec::new();
+    let mut current: Option<ReplayedRequest> = None;
+
+    for event in events {
+        match event {
+            WireEvent::Request {
+                provider,
+                path,
+                stream,
+                ..
+            } => {
+                if let Some(request) = current.take() {
+                    request.finish(&mut routes);
+                }
+                current = Some(ReplayedRequest {
+                    path,
+                    provider,
+                    stream: stream.then(Vec::new),
+                });
+            }
+            WireEvent::Response { status, body } => {
+                if let Some(request) = current.as_ref().filter(|r| r.stream.is_none()) {
+                    let response = MockJsonResponse::new(body).with_status(status);
+                    add_response(&mut routes, &request.path, MockResponse::Json(response));
+                }
+            }
+            WireEvent::StreamData { data } => {
+                if let Some(stream) = current.as_mut().and_then(|r| r.stream.as_mut()) {
+                    stream.push(data);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(request) = current {
+        request.finish(&mut routes);
+    }
+
+    routes
+}
+
+/// A logged request whose response is being rebuilt.
+struct ReplayedRequest {
+    path: String,
+    provider: String,
+    /// Stream data logged so far, if the request streams.
+    stream: Option<Vec<String>>,
+}
+
+impl ReplayedRequest {
+    fn finish(self, routes: &mut Vec<MockRoute>) {
+        if let Some(data) = self.stream {
+            add_response(routes, &self.path, replay_stream(&self.provider, data));
+        }
+    }
+}
+
+fn add_response(routes: &mut Vec<MockRoute>, path: &str, response: MockResponse) {
+    match routes.iter_mut().find(|route| route.path == path) {
+        Some(route) => route.responders.push(response),
+        None => routes.push(MockRoute::single(path, response)),
+    }
 }
 
+/// A streamed response carrying `data` as logged: Gemini array elements,
+/// Ollama's JSON lines, or SSE lines for the other providers.
+fn replay_stream(provider: &str, data: Vec<String>) -> MockResponse {
+    let objects = || {
+        data.iter()
+            .map(|element| {
+                serde_json::from_str(element).unwrap_or_else(|_| element.as_str().into())
+            })
+            .collect()
+    };
+    match provider {
+        "gemini" => return MockResponse::Chunked(MockChunkedResponse::new(objects())),
+        "ollama" => return MockResponse::JsonLines(MockJsonLinesResponse::new(objects())),
+        _ => {}
+    }
+
+    let mut events: Vec<MockSseEvent> = Vec::new();
+    for line in data {
+        if let Some(name) = line.strip_prefix("event: ") {
+            events.push(MockSseEvent::event(name));
+        } else if let Some(payload) = line.strip_prefix("data: ") {
+            match events.last_mut() {
+                // `event:` and its `data:` belong to the same SSE event
+                Some(event) if event.event.is_some() && event.data.is_none() => {
+                    event.data = Some(payload.to_string());
+                }
+                _ => events.push(MockSseEvent::data_text(payload)),
+            }
+        }
+    }
+
+    MockResponse::Sse(MockSseResponse::new(events))
+}
+
+/// Serve until shutdown, returning how many connections were aborted for
+/// outliving `SHUTDOWN_GRACE`.
 async fn run_server(
     listener: TcpListener,
     state: Arc<MockServerState>,
     mut shutdown_rx: oneshot::Receiver<()>,
-) {
+) -> usize {
+    // Dropping the set (e.g. when the server is dropped and this task
+    // aborted) aborts every connection task with it
+    let mut connections = JoinSet::new();
+
     loop {
         tokio::select! {
             biased;
             _ = &mut shutdown_rx => {
                 break;
             }
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
             accept_result = listener.accept() => {
                 match accept_result {
                     Ok((stream, _)) => {
                         let state_clone = state.clone();
-                        tokio::spawn(async move {
+                        connections.spawn(async move {
                             let _ = handle_connection(stream, state_clone).await;
                         });
                     }
@@ -374,6 +1084,16 @@ async fn run_server(
             }
         }
     }
+
+    drop(listener);
+
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+
+    // Dropping the set aborts whatever is left
+    connections.len()
 }
 
 async fn handle_connection(
@@ -433,10 +1153,21 @@ async fn handle_connection(
         })
         .await;
 
+    let mut plan = state.chaos_plan(&path);
+    if let Some(delay) = plan.delay() {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut out = ResponseWriter::new(&mut stream, plan);
+
+    if out.plan.is(ChaosFault::ServiceUnavailable) {
+        return send_service_unavailable(&mut out).await;
+    }
+
     if let Some(response) = state.next_response(&path).await {
-        send_response(response, &mut stream).await
+        send_response(response, &mut out).await
     } else {
-        send_not_found(&mut stream).await
+        send_not_found(&mut out).await
     }
 }
 
@@ -487,15 +1218,20 @@ fn parse_request_head(buffer: &[u8]) -> std::io::Result<ParsedHead> {
     })
 }
 
-async fn send_response(response: MockResponse, stream: &mut TcpStream) -> std::io::Result<()> {
+async fn send_response(
+    response: MockResponse,
+    stream: &mut ResponseWriter<'_>,
+) -> std::io::Result<()> {
     match response {
         MockResponse::Sse(sse) => send_sse_response(sse, stream).await,
         MockResponse::Chunked(chunked) => send_chunked_response(chunked, stream).await,
+        MockResponse::JsonLines(lines) => send_json_lines_response(lines, stream).await,
         MockResponse::Json(json) => send_json_response(json, stream).await,
+        MockResponse::Redirect(redirect) => send_redirect_response(redirect, stream).await,
     }
 }
 
-async fn send_not_found(stream: &mut TcpStream) -> std::io::Result<()> {
+async fn send_not_found(stream: &mut ResponseWriter<'_>) -> std::io::Result<()> {
     let body = b"Not Found";
     let response = format!(
         "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
@@ -505,47 +1241,160 @@ async fn send_not_found(stream: &mut TcpStream) -> std::io::Result<()> {
     stream.write_all(body).await
 }
 
+async fn send_service_unavailable(stream: &mut ResponseWriter<'_>) -> std::io::Result<()> {
+    let body = serde_json::json!({
+        "error": {
+            "type": "overloaded_error",
+            "message": "chaos: service unavailable",
+        }
+    })
+    .to_string();
+    let response = format!(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await
+}
+
 async fn send_sse_response(
     response: MockSseResponse,
-    stream: &mut TcpStream,
+    stream: &mut ResponseWriter<'_>,
 ) -> std::io::Result<()> {
-    let header = b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
-    stream.write_all(header).await?;
+    let mut body = SseBody::new(response.gzip);
+    let mut header = if response.gzip {
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n"
+    } else {
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n"
+    }
+    .to_string();
+    for (name, value) in &response.headers {
+        header.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    header.push_str("\r\n");
+    stream.write_all(header.as_bytes()).await?;
+
+    let last = response.events.len().saturating_sub(1);
+    for (idx, event) in response.events.into_iter().enumerate() {
+        if let Some(delay) = response.chunk_delay {
+            tokio::time::sleep(delay).await;
+        }
+        if let Some((_, stall)) = response.stall.filter(|(events, _)| *events == idx) {
+            tokio::time::sleep(stall).await;
+        }
+
+        let mut bytes = Vec::new();
+        if stream.plan.extra_comment() {
+            bytes.extend_from_slice(b": chaos keep-alive\r\n\r\n");
+        }
 
-    for event in response.events {
         if let Some(comment) = &event.comment {
-            stream
-                .write_all(format!(":{}\r\n", comment).as_bytes())
-                .await?;
+            bytes.extend_from_slice(format!(":{}\r\n", comment).as_bytes());
         }
         if let Some(name) = &event.event {
-            stream
-                .write_all(format!("event: {}\r\n", name).as_bytes())
-                .await?;
+            bytes.extend_from_slice(format!("event: {}\r\n", name).as_bytes());
         }
         if let Some(data) = &event.data {
-            stream
-                .write_all(format!("data: {}\r\n", data).as_bytes())
-                .await?;
+            bytes.extend_from_slice(format!("data: {}\r\n", data).as_bytes());
         }
-        stream.write_all(b"\r\n").await?;
+        bytes.extend_from_slice(b"\r\n");
+        if let Some(keep) = response.cut_at.filter(|_| idx == last) {
+            bytes.truncate(keep);
+            return body.write(stream, &bytes).await;
+        }
+        body.write(stream, &bytes).await?;
     }
 
     if response.send_done {
-        stream.write_all(b"data: [DONE]\r\n\r\n").await?;
+        body.write(stream, b"data: [DONE]\r\n\r\n").await?;
+    }
+
+    if stream.plan.trailing_garbage() {
+        body.write(
+            stream,
+            b"data: {\"choices\": [{\"delta\r\n\r\n\x07chaos garbage",
+        )
+        .await?;
     }
 
-    Ok(())
+    body.finish(stream).await
+}
+
+/// SSE body writer; gzipped bodies go out as one chunk per write.
+struct SseBody {
+    gzip: Option<GzipWriter>,
+}
+
+impl SseBody {
+    fn new(gzip: bool) -> Self {
+        Self {
+            gzip: gzip.then(GzipWriter::new),
+        }
+    }
+
+    async fn write(
+        &mut self,
+        stream: &mut ResponseWriter<'_>,
+        bytes: &[u8],
+    ) -> std::io::Result<()> {
+        match self.gzip.as_mut() {
+            Some(encoder) => write_chunk(stream, &encoder.write(bytes)).await,
+            None => stream.write_all(bytes).await,
+        }
+    }
+
+    async fn finish(self, stream: &mut ResponseWriter<'_>) -> std::io::Result<()> {
+        let Some(encoder) = self.gzip else {
+            return Ok(());
+        };
+
+        write_chunk(stream, &encoder.finish()).await?;
+        stream.write_all(b"0\r\n\r\n").await
+    }
+}
+
+async fn write_chunk(stream: &mut ResponseWriter<'_>, chunk: &[u8]) -> std::io::Result<()> {
+    stream
+        .write_all(format!("{:X}\r\n", chunk.len()).as_bytes())
+        .await?;
+    stream.write_all(chunk).await?;
+    stream.write_all(b"\r\n").await
 }
 
 async fn send_chunked_response(
     response: MockChunkedResponse,
-    stream: &mut TcpStream,
+    stream: &mut ResponseWriter<'_>,
 ) -> std::io::Result<()> {
     let header = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\nConnection: keep-alive\r\n\r\n";
     stream.write_all(header).await?;
 
+    if let Some(chunks) = &response.raw_chunks {
+        for chunk in chunks {
+            if let Some(delay) = response.chunk_delay {
+                tokio::time::sleep(delay).await;
+            }
+            write_chunk(stream, chunk.as_bytes()).await?;
+        }
+        return stream.write_all(b"0\r\n\r\n").await;
+    }
+
+    if let Some(size) = response.chunk_size {
+        let objects: Vec<_> = response.objects.iter().map(|o| o.to_string()).collect();
+        let body = format!("[{}]", objects.join(",\r\n"));
+        for chunk in body.as_bytes().chunks(size) {
+            if let Some(delay) = response.chunk_delay {
+                tokio::time::sleep(delay).await;
+            }
+            write_chunk(stream, chunk).await?;
+        }
+        return stream.write_all(b"0\r\n\r\n").await;
+    }
+
     for (idx, object) in response.objects.iter().enumerate() {
+        if let Some(delay) = response.chunk_delay {
+            tokio::time::sleep(delay).await;
+        }
+
         let mut chunk_body = String::new();
         if idx == 0 {
             chunk_body.push('[');
@@ -555,33 +1404,111 @@ async fn send_chunked_response(
             chunk_body.push_str(&object.to_string());
         }
 
-        let size_line = format!("{:X}\r\n", chunk_body.as_bytes().len());
+        let size_line = format!("{:X}\r\n", chunk_body.len());
         stream.write_all(size_line.as_bytes()).await?;
+        if let Some(keep) = response
+            .cut_at
+            .filter(|_| idx + 1 == response.objects.len())
+        {
+            return stream
+                .write_all(&chunk_body.as_bytes()[..keep.min(chunk_body.len())])
+                .await;
+        }
         stream.write_all(chunk_body.as_bytes()).await?;
         stream.write_all(b"\r\n").await?;
     }
 
     stream.write_all(b"1\r\n]\r\n").await?;
+
+    if stream.plan.trailing_garbage() {
+        stream.write_all(b"5\r\n,{\"ca\r\n").await?;
+    }
+
+    stream.write_all(b"0\r\n\r\n").await
+}
+
+async fn send_json_lines_response(
+    response: MockJsonLinesResponse,
+    stream: &mut ResponseWriter<'_>,
+) -> std::io::Result<()> {
+    let header = b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\nConnection: keep-alive\r\n\r\n";
+    stream.write_all(header).await?;
+
+    for (idx, object) in response.objects.iter().enumerate() {
+        if let Some(delay) = response.chunk_delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let line = format!("{}\n", object);
+        let size_line = format!("{:X}\r\n", line.len());
+        stream.write_all(size_line.as_bytes()).await?;
+        if let Some(keep) = response
+            .cut_at
+            .filter(|_| idx + 1 == response.objects.len())
+        {
+            return stream
+                .write_all(&line.as_bytes()[..keep.min(line.len())])
+                .await;
+        }
+        stream.write_all(line.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+    }
+
     stream.write_all(b"0\r\n\r\n").await
 }
 
 async fn send_json_response(
     response: MockJsonResponse,
-    stream: &mut TcpStream,
+    stream: &mut ResponseWriter<'_>,
 ) -> std::io::Result<()> {
-    let body_string = response.body.to_string();
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut body = response.body.to_string().into_bytes();
+    let mut encoding = "";
+    if response.gzip {
+        let mut encoder = GzipWriter::new();
+        let mut gzipped = encoder.write(&body);
+        gzipped.extend(encoder.finish());
+        body = gzipped;
+        encoding = "Content-Encoding: gzip\r\n";
+    } else if response.brotli {
+        body = brotli_compress(&body);
+        encoding = "Content-Encoding: br\r\n";
+    }
+
+    let extra: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
     let header = format!(
-        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}{}Content-Length: {}\r\nConnection: close\r\n\r\n",
         response.status,
-        body_string.as_bytes().len()
+        encoding,
+        extra,
+        body.len()
     );
     stream.write_all(header.as_bytes()).await?;
-    stream.write_all(body_string.as_bytes()).await
+    stream.write_all(&body).await
+}
+
+async fn send_redirect_response(
+    response: MockRedirectResponse,
+    stream: &mut ResponseWriter<'_>,
+) -> std::io::Result<()> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        response.status, response.location
+    );
+    stream.write_all(header.as_bytes()).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::AsyncWriteExt;
 
     #[tokio::test]
     async fn openai_stream_records_requests() {
@@ -623,4 +1550,65 @@ mod tests {
 
         server.shutdown().await;
     }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_flight_requests() {
+        if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+            eprintln!("skipping mock server shutdown test");
+            return;
+        }
+
+        let events = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|delta| MockSseEvent::data_json(serde_json::json!({ "delta": delta })))
+            .collect();
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/v1/slow",
+            MockResponse::Sse(
+                MockSseResponse::new(events).with_chunk_delay(Duration::from_millis(50)),
+            ),
+        )])
+        .await
+        .expect("server starts");
+
+        let addr = server.address();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.expect("connects");
+            stream
+                .write_all(
+                    b"POST /v1/slow HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi",
+                )
+                .await
+                .expect("writes request");
+
+            let mut response = String::new();
+            stream
+                .read_to_string(&mut response)
+                .await
+                .expect("reads response");
+            response
+        });
+
+        // The handler is now mid-response, with all four delays ahead of it
+        server
+            .wait_for_requests("/v1/slow", 1, Duration::from_secs(5))
+            .await
+            .expect("request recorded");
+        let started = std::time::Instant::now();
+        assert_eq!(server.shutdown().await, 0, "the handler finished in time");
+
+        assert!(
+            started.elapsed() >= Duration::from_millis(150),
+            "shutdown returned before the handler finished"
+        );
+        let response = client.await.expect("client task");
+        assert!(response.contains(r#"{"delta":"d"}"#));
+        assert_eq!(server.recorded_requests().await.len(), 1);
+
+        let err = server
+            .wait_for_requests("/v1/slow", 2, Duration::from_millis(20))
+            .await
+            .expect_err("no second request");
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
 }
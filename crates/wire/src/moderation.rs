@@ -0,0 +1,205 @@
+//! Screening user input before it reaches a model.
+//!
+//! A `Moderator` set through `ClientOptions::with_moderator` checks the user
+//! messages at the end of the history (the turn being sent) before `prompt`,
+//! `prompt_stream` or `prompt_with_tools` make any request. Flagged input fails
+//! with `WireError::ContentFlagged`. Tool outputs and earlier turns are not
+//! re-checked, and `PromptOptions::with_bypass_moderation` skips the check for
+//! a single call.
+//!
+//! `OpenAIModerator` uses OpenAI's `/v1/moderations` endpoint; implement the
+//! trait to plug in another classifier.
+
+use std::sync::Arc;
+
+use crate::config::PromptOptions;
+use crate::error::WireError;
+use crate::types::{Message, MessageType};
+
+/// Outcome of a moderation check.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// Names of the categories that tripped, in the classifier's own terms.
+    pub categories: Vec<String>,
+}
+
+#[async_trait::async_trait]
+pub trait Moderator: Send + Sync {
+    async fn moderate(&self, input: &str) -> Result<ModerationResult, WireError>;
+}
+
+/// A `Moderator` shared between clients, as stored in `ClientOptions`.
+#[derive(Clone)]
+pub struct SharedModerator(Arc<dyn Moderator>);
+
+impl SharedModerator {
+    pub fn new<M>(moderator: M) -> Self
+    where
+        M: Moderator + 'static,
+    {
+        Self(Arc::new(moderator))
+    }
+}
+
+impl std::fmt::Debug for SharedModerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedModerator")
+    }
+}
+
+/// Run `moderator` over the trailing user messages of `chat_history` unless
+/// `options` bypasses moderation.
+pub(crate) async fn check_incoming(
+    moderator: Option<&SharedModerator>,
+    chat_history: &[Message],
+    options: &PromptOptions,
+) -> Result<(), WireError> {
+    let Some(moderator) = moderator else {
+        return Ok(());
+    };
+    if options.bypass_moderation {
+        return Ok(());
+    }
+
+    let mut incoming: Vec<&str> = chat_history
+        .iter()
+        .rev()
+        .take_while(|message| message.message_type == MessageType::User)
+        .map(|message| message.content.as_str())
+        .collect();
+    if incoming.is_empty() {
+        return Ok(());
+    }
+    incoming.reverse();
+
+    let result = moderator.0.moderate(&incoming.join("\n\n")).await?;
+    if result.flagged {
+        return Err(WireError::ContentFlagged {
+            categories: result.categories,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "openai")]
+pub use openai::OpenAIModerator;
+
+#[cfg(feature = "openai")]
+mod openai {
+    use super::{ModerationResult, Moderator};
+    use crate::compression::{response_text, ACCEPT_ENCODING};
+    use crate::config::{ClientOptions, Endpoint, Scheme};
+    use crate::credentials::Credentials;
+    use crate::error::WireError;
+
+    /// Moderation through OpenAI's `/v1/moderations`, authenticated with
+    /// `OPENAI_API_KEY` as read when the moderator is built.
+    pub struct OpenAIModerator {
+        pub http_client: reqwest::Client,
+        pub model: String,
+        pub host: String,
+        pub port: u16,
+        pub scheme: Scheme,
+        pub credentials: Credentials,
+    }
+
+    impl OpenAIModerator {
+        pub fn new() -> Self {
+            Self::with_options(ClientOptions::default())
+        }
+
+        /// Only the endpoint, proxy and redirect settings of `options` apply.
+        pub fn with_options(options: ClientOptions) -> Self {
+            let mut moderator = Self {
+                http_client: reqwest::Client::new(),
+                model: "omni-moderation-latest".to_string(),
+                host: "api.openai.com".to_string(),
+                port: 443,
+                scheme: Scheme::Https,
+                credentials: Credentials::from_env("OPENAI_API_KEY"),
+            };
+
+            moderator.http_client = options.http_client();
+            if let Endpoint::BaseUrl(endpoint) = options.endpoint {
+                moderator.host = endpoint.host;
+                moderator.port = endpoint.port;
+                moderator.scheme = endpoint.scheme;
+            }
+
+            moderator
+        }
+
+        pub fn with_model<S>(mut self, model: S) -> Self
+        where
+            S: Into<String>,
+        {
+            self.model = model.into();
+            self
+        }
+
+        fn origin(&self) -> String {
+            match (self.scheme, self.port) {
+                (Scheme::Https, 443) | (Scheme::Http, 80) => {
+                    format!("{}://{}", self.scheme.as_str(), self.host)
+                }
+                _ => format!("{}://{}:{}", self.scheme.as_str(), self.host, self.port),
+            }
+        }
+    }
+
+    impl Default for OpenAIModerator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Moderator for OpenAIModerator {
+        async fn moderate(&self, input: &str) -> Result<ModerationResult, WireError> {
+            let response = self
+                .http_client
+                .post(format!("{}/v1/moderations", self.origin()))
+                .bearer_auth(self.credentials.token()?)
+                .header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING)
+                .json(&serde_json::json!({
+                    "model": self.model,
+                    "input": input,
+                }))
+                .send()
+                .await?;
+
+            let status = response.status();
+            let body = response_text(response).await?;
+            if !status.is_success() {
+                return Err(WireError::http(status.as_u16(), "/v1/moderations", body));
+            }
+            let body: serde_json::Value = serde_json::from_str(&body)?;
+
+            let result = body
+                .get("results")
+                .and_then(|results| results.get(0))
+                .ok_or(WireError::MissingField {
+                    field: "results[0]",
+                })?;
+
+            let categories = result
+                .get("categories")
+                .and_then(|categories| categories.as_object())
+                .map(|categories| {
+                    categories
+                        .iter()
+                        .filter(|(_, tripped)| tripped.as_bool() == Some(true))
+                        .map(|(name, _)| name.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(ModerationResult {
+                flagged: result["flagged"].as_bool().unwrap_or(false),
+                categories,
+            })
+        }
+    }
+}
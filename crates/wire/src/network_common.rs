@@ -1,5 +1,13 @@
-use std::net::{TcpStream, ToSocketAddrs};
+use std::io::{BufRead, BufReader, Read};
+use std::time::Duration;
 
+use tokio_util::sync::CancellationToken;
+
+use crate::compression::{is_brotli, is_gzip, BrotliDecoder, GzipDecoder, MAX_DECODED_BYTES};
+use crate::error::{TimeoutPhase, WireError};
+use crate::types::TruncatedStream;
+
+#[cfg(any(feature = "openai", feature = "anthropic", feature = "gemini"))]
 pub fn unescape(content: &str) -> String {
     content
         .replace("\\n", "\n")
@@ -9,16 +17,577 @@ pub fn unescape(content: &str) -> String {
         .replace("\\\\", "\\")
 }
 
-pub fn connect_https(host: &str, port: u16) -> native_tls::TlsStream<std::net::TcpStream> {
-    let addr = (host, port)
-        .to_socket_addrs()
-        .unwrap()
-        .find(|addr| addr.is_ipv4())
-        .expect("No IPv4 address found");
+/// An io error carrying `WireError::StreamStalled`, with no partial content
+/// or request id yet; the client adds them.
+fn stalled(idle: Duration) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        WireError::StreamStalled {
+            idle,
+            partial: String::new(),
+            request_id: None,
+        },
+    )
+}
+
+/// An io error carrying `WireError::Cancelled`, with no partial content
+/// yet; the client adds it.
+fn cancelled() -> std::io::Error {
+    std::io::Error::other(WireError::Cancelled {
+        partial: String::new(),
+    })
+}
+
+/// `request`, dropped as soon as `cancellation` fires, which fails it with
+/// `WireError::Cancelled`. Nothing is sent once it has fired.
+pub(crate) async fn cancellable<T>(
+    cancellation: Option<&CancellationToken>,
+    request: impl std::future::Future<Output = Result<T, WireError>>,
+) -> Result<T, WireError> {
+    let Some(cancellation) = cancellation else {
+        return request.await;
+    };
+
+    tokio::select! {
+        biased;
+        _ = cancellation.cancelled() => Err(cancelled().into()),
+        result = request => result,
+    }
+}
+
+/// An io error carrying `WireError::Timeout`, which `?` turns back into it.
+fn timed_out(phase: TimeoutPhase) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, WireError::Timeout { phase })
+}
+
+/// `err` as the io error a socket would have given: a timeout as
+/// `WireError::Timeout`, anything else with the kind of the io error behind
+/// it, so a refused or reset connection still reads as one.
+fn io_error(err: reqwest::Error) -> std::io::Error {
+    if err.is_timeout() {
+        return timed_out(match err.is_connect() {
+            true => TimeoutPhase::Connect,
+            // Stream clients bound each read, not the whole request
+            false => TimeoutPhase::Read,
+        });
+    }
+
+    let mut source = std::error::Error::source(&err);
+    let mut kind = std::io::ErrorKind::Other;
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            kind = io.kind();
+            break;
+        }
+        source = err.source();
+    }
+
+    std::io::Error::new(kind, err)
+}
+
+/// Send a streaming `request` with `client` and hand back the response body
+/// as a `ByteStream`. `client` should be a `ClientOptions::stream_client`,
+/// which leaves redirects to this function and bounds each read rather than
+/// the whole response.
+///
+/// 307 and 308 responses are followed up to `max_redirects` times by sending
+/// the same request, body included, to their `Location`; credentials are
+/// dropped when it is another origin. Other redirects would change the
+/// method, so they fail instead, as do redirects past the limit; either
+/// error names the `Location`. Any other status outside 2xx fails with
+/// `WireError::Http` and the provider's error body.
+///
+/// A stream that sends nothing for `idle_timeout` fails with
+/// `WireError::StreamStalled`.
+pub(crate) async fn open_stream(
+    client: &reqwest::Client,
+    request: &reqwest::Request,
+    max_redirects: usize,
+    idle_timeout: Option<Duration>,
+) -> Result<ByteStream, WireError> {
+    let mut request = clone_request(request)?;
+    let mut redirects = 0;
+
+    loop {
+        let response = client
+            .execute(clone_request(&request)?)
+            .await
+            .map_err(io_error)?;
+        let status = response.status().as_u16();
+        let headers = || {
+            response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+        };
+        let request_id = crate::error::parse_request_id(headers());
+
+        if response.status().is_success() {
+            return Ok(ByteStream {
+                source: Source::Response(response),
+                buffer: Vec::new(),
+                idle_timeout,
+                cancellation: None,
+                request_id,
+            });
+        }
+
+        if !matches!(status, 301 | 302 | 303 | 307 | 308) {
+            let retry_after = crate::retry::parse_retry_after(headers());
+            // Without the query, which can hold a key (Gemini's `?key=`)
+            let path = request.url().path().to_string();
+            return Err(WireError::http(status, path, error_body(response).await?)
+                .with_retry_after(retry_after)
+                .with_request_id(request_id.as_deref()));
+        }
+
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("HTTP {} response without a Location header", status),
+                )
+            })?;
+        if !matches!(status, 307 | 308) {
+            return Err(std::io::Error::other(format!(
+                "HTTP {} redirect to {} not followed: it would turn the POST into a GET",
+                status, location
+            ))
+            .into());
+        }
+        if redirects == max_redirects {
+            return Err(std::io::Error::other(format!(
+                "too many redirects (limit {}); last Location: {}",
+                max_redirects, location
+            ))
+            .into());
+        }
+        redirects += 1;
+
+        let next = request
+            .url()
+            .join(&location)
+            .map_err(|err| invalid_location(&location, err))?;
+        if !matches!(next.scheme(), "http" | "https") {
+            return Err(invalid_location(&location, next.scheme()).into());
+        }
+
+        // Like reqwest, keep credentials on the origin they were meant for
+        if next.origin() != request.url().origin() {
+            for name in CREDENTIAL_HEADERS {
+                request.headers_mut().remove(*name);
+            }
+        }
+        *request.url_mut() = next;
+    }
+}
+
+/// A copy of `request` to send. Every body the clients build is in memory,
+/// so only a streamed one could fail.
+fn clone_request(request: &reqwest::Request) -> std::io::Result<reqwest::Request> {
+    request
+        .try_clone()
+        .ok_or_else(|| std::io::Error::other("the request body can't be sent twice"))
+}
+
+/// How much of an error response's body is kept.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// The start of an error response's body, as it is all the caller will get.
+async fn error_body(mut response: reqwest::Response) -> Result<String, WireError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(io_error)? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_ERROR_BODY {
+            body.truncate(MAX_ERROR_BODY);
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Headers that carry provider credentials.
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "x-api-key", "x-goog-api-key"];
+
+fn invalid_location(location: &str, reason: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("invalid redirect Location {}: {}", location, reason),
+    )
+}
+
+/// The body of a raw HTTP/1.1 response read off `reader`: the head is
+/// skipped, and the chunked framing and gzip or brotli it names are
+/// undone. The decoded body is not capped, as `ByteStream` caps each event.
+fn raw_body<R>(mut reader: BufReader<R>) -> std::io::Result<Box<dyn Read + Send>>
+where
+    R: Read + Send + 'static,
+{
+    let mut gzipped = false;
+    let mut brotli = false;
+    let mut chunked = false;
+    let mut line = String::new();
+    // The status line, then headers up to the blank line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let (name, value) = line.split_once(':').unwrap_or((line, ""));
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-encoding" => {
+                gzipped = is_gzip(value);
+                brotli = is_brotli(value);
+            }
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+            _ => {}
+        }
+    }
+
+    let body: Box<dyn Read + Send> = match chunked {
+        true => Box::new(ChunkedReader::new(reader)),
+        false => Box::new(reader),
+    };
+    Ok(if gzipped {
+        Box::new(GzipDecoder::with_limit(body, u64::MAX))
+    } else if brotli {
+        Box::new(BrotliDecoder::with_limit(body, u64::MAX))
+    } else {
+        body
+    })
+}
+
+/// The body of a `Transfer-Encoding: chunked` response, without the framing.
+///
+/// The next chunk header is only read once the current chunk is used up, so
+/// a read never waits on a chunk the server has not sent yet.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: usize,
+    started: bool,
+    done: bool,
+}
 
-    let stream = TcpStream::connect(&addr).unwrap();
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            started: false,
+            done: false,
+        }
+    }
 
-    let connector = native_tls::TlsConnector::new().expect("TLS connector failed to create");
+    fn read_size(&mut self) -> std::io::Result<usize> {
+        let mut line = String::new();
+        if self.started {
+            // The CRLF that ends the previous chunk
+            self.inner.read_line(&mut line)?;
+            line.clear();
+        }
+        self.started = true;
+
+        self.inner.read_line(&mut line)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        usize::from_str_radix(size, 16).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid chunk size {:?}", size),
+            )
+        })
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.remaining = self.read_size()?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "stream ended mid-chunk",
+            ));
+        }
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+/// Where a `ByteStream`'s bytes come from.
+enum Source {
+    /// A response reqwest is still receiving.
+    Response(reqwest::Response),
+    /// Chunks read off a blocking reader on tokio's blocking pool, so the
+    /// reads don't stall the executor.
+    Reader(tokio::sync::mpsc::Receiver<std::io::Result<Vec<u8>>>),
+}
+
+/// A response body consumed asynchronously, a line or a chunk at a time.
+/// Dropping it closes the connection.
+///
+/// The body as a whole may be any length, but what has been received and
+/// not read yet, at most one event for a reader going line by line, is
+/// capped at `MAX_DECODED_BYTES`.
+pub struct ByteStream {
+    source: Source,
+    buffer: Vec<u8>,
+    idle_timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    request_id: Option<String>,
+}
+
+impl ByteStream {
+    /// The body of a raw HTTP/1.1 response read off `reader`, starting at
+    /// the status line, as `RawTransport::process_stream` is given. Readers
+    /// see the same bytes as from a response sent by `open_stream`.
+    pub fn read_raw<R>(reader: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        tokio::task::spawn_blocking(move || {
+            let mut body = match raw_body(BufReader::new(reader)) {
+                Ok(body) => body,
+                Err(err) => {
+                    let _ = tx.blocking_send(Err(err));
+                    return;
+                }
+            };
+
+            let mut chunk = [0u8; 8192];
+            loop {
+                match body.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if tx.blocking_send(Ok(chunk[..n].to_vec())).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = tx.blocking_send(Err(err));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            source: Source::Reader(rx),
+            buffer: Vec::new(),
+            idle_timeout: None,
+            cancellation: None,
+            request_id: None,
+        }
+    }
+
+    /// Fail the next read with `WireError::Cancelled` once `cancellation`
+    /// fires, even one already waiting.
+    pub(crate) fn with_cancellation(mut self, cancellation: Option<&CancellationToken>) -> Self {
+        self.cancellation = cancellation.cloned();
+        self
+    }
+
+    /// The provider's id for the request, from the response headers.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Pull the next chunk into the buffer. Returns `false` at end of stream.
+    async fn fill(&mut self) -> std::io::Result<bool> {
+        let idle = self.idle_timeout;
+        let cancellation = self.cancellation.as_ref();
+        let chunk = match &mut self.source {
+            Source::Response(response) => within(idle, cancellation, response.chunk())
+                .await?
+                .map_err(io_error)?
+                .map(|chunk| chunk.to_vec()),
+            Source::Reader(rx) => within(idle, cancellation, rx.recv()).await?.transpose()?,
+        };
+        let Some(chunk) = chunk else {
+            return Ok(false);
+        };
+
+        // A few kilobytes of gzip can decode to gigabytes with no line break
+        if (self.buffer.len() + chunk.len()) as u64 > MAX_DECODED_BYTES {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "stream event decodes to more than {} bytes",
+                    MAX_DECODED_BYTES
+                ),
+            ));
+        }
+
+        self.buffer.extend_from_slice(&chunk);
+        Ok(true)
+    }
+
+    /// Read the next line into `line`, replacing its contents, so one buffer
+    /// can be reused for a whole stream. Returns `false` once the stream is
+    /// exhausted.
+    #[cfg(any(
+        feature = "openai",
+        feature = "anthropic",
+        feature = "ollama",
+        feature = "cohere"
+    ))]
+    pub async fn read_line_into(&mut self, line: &mut String) -> std::io::Result<bool> {
+        line.clear();
+
+        loop {
+            let (end, consumed) = match self.buffer.iter().position(|b| *b == b'\n') {
+                Some(idx) => (idx, idx + 1),
+                None => {
+                    if self.fill().await? {
+                        continue;
+                    }
+                    if self.buffer.is_empty() {
+                        return Ok(false);
+                    }
+                    (self.buffer.len(), self.buffer.len())
+                }
+            };
+
+            let mut bytes = &self.buffer[..end];
+            if consumed > end {
+                bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+            }
+            let text = std::str::from_utf8(bytes).map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+            })?;
+            line.push_str(text);
+            self.buffer.drain(..consumed);
+
+            return Ok(true);
+        }
+    }
+
+    /// Take whatever has been received but not read yet, waiting for more
+    /// when there is nothing. Returns `None` once the stream is exhausted.
+    #[cfg(feature = "gemini")]
+    pub async fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        if self.buffer.is_empty() && !self.fill().await? {
+            return Ok(None);
+        }
+
+        Ok(Some(std::mem::take(&mut self.buffer)))
+    }
+
+    /// Whether nothing but whitespace is left before the end of the stream.
+    /// Reads ahead until it can tell.
+    #[cfg(any(
+        feature = "openai",
+        feature = "anthropic",
+        feature = "ollama",
+        feature = "cohere"
+    ))]
+    pub async fn at_end(&mut self) -> std::io::Result<bool> {
+        loop {
+            if self.buffer.iter().any(|b| !b.is_ascii_whitespace()) {
+                return Ok(false);
+            }
+            if !self.fill().await? {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// `next`, failing with `WireError::StreamStalled` if it takes longer than
+/// `idle`, or with `WireError::Cancelled` once `cancellation` fires.
+async fn within<F: std::future::Future>(
+    idle: Option<Duration>,
+    cancellation: Option<&CancellationToken>,
+    next: F,
+) -> std::io::Result<F::Output> {
+    let next = async {
+        match idle {
+            None => Ok(next.await),
+            Some(idle) => tokio::time::timeout(idle, next)
+                .await
+                .map_err(|_| stalled(idle)),
+        }
+    };
+
+    match cancellation {
+        None => next.await,
+        Some(cancellation) => tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => Err(cancelled()),
+            next = next => next,
+        },
+    }
+}
+
+/// One parsed event of a server-sent event stream.
+#[cfg(any(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "ollama",
+    feature = "cohere"
+))]
+pub(crate) enum ParsedEvent {
+    Json(serde_json::Value),
+    /// The final event, cut off partway through.
+    Truncated(TruncatedStream),
+}
+
+/// Parse the JSON `payload` of a stream event. One that does not parse fails
+/// the stream, unless nothing follows it and `strict` is off: a connection
+/// closed partway through the last event (usually a proxy timing out) should
+/// not cost the content that arrived before it.
+#[cfg(any(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "ollama",
+    feature = "cohere"
+))]
+pub(crate) async fn parse_event(
+    body: &mut ByteStream,
+    payload: &str,
+    strict: bool,
+) -> Result<ParsedEvent, WireError> {
+    let err = match serde_json::from_str(payload) {
+        Ok(json) => return Ok(ParsedEvent::Json(json)),
+        Err(err) => err,
+    };
+
+    if strict || !body.at_end().await? {
+        return Err(WireError::StreamProtocol {
+            reason: err.to_string(),
+        });
+    }
+
+    Ok(ParsedEvent::Truncated(truncated_stream(payload)))
+}
 
-    connector.connect(host, stream).unwrap()
+/// Describe a stream cut off at `tail` for the message metadata. The client
+/// reports it as a `WireWarning::StreamTruncated`.
+pub(crate) fn truncated_stream(tail: &str) -> TruncatedStream {
+    TruncatedStream {
+        tail: tail.to_string(),
+    }
 }
@@ -0,0 +1,233 @@
+//! Replaying a history through a provider other than the one that wrote it.
+//!
+//! Every message records the `API` that produced it, and nothing stops a
+//! conversation from switching providers halfway: an agent started on OpenAI
+//! can hand its history, tool calls and all, to Anthropic. The providers
+//! disagree on the details, though. Anthropic only takes tool-use ids made of
+//! letters, digits, `_` and `-`; OpenAI caps them at 40 characters, and
+//! OpenRouter, which may hand a request to either, gets both rules; the ids
+//! the tool loop makes up for calls that came without one restart at
+//! `call_0_0` on every run, so ids repeat within one history; and a tool
+//! output that answers no call is refused outright by the providers that
+//! pair them by id.
+//!
+//! The native clients run each history through `normalize_history_with`,
+//! under the client's `HistoryStrictness`, before building a request, and
+//! report each lenient rewrite as a `warning::WireWarning`. As with
+//! `sanitize`, only the request changes: the caller's history keeps its
+//! original tags and ids.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::api::{Provider, API};
+use crate::error::WireError;
+use crate::types::{Message, MessageType};
+use crate::warning::WireWarning;
+
+/// The longest tool-call id OpenAI accepts.
+const OPENAI_MAX_ID_LEN: usize = 40;
+
+/// What `normalize_history_with` does with a message it can't translate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistoryStrictness {
+    /// Rewrite it into something the provider takes, with a
+    /// `WireWarning::HistoryNormalized`: a tool output nothing asked for
+    /// becomes a user message, and arguments that aren't a JSON object
+    /// become `{}`.
+    #[default]
+    Lenient,
+    /// Fail the prompt with `error::WireError::UntranslatableHistory` before
+    /// anything is sent.
+    Strict,
+}
+
+/// `history` as `api`'s provider will take it, rewriting leniently.
+pub fn normalize_history_for(
+    api: &API,
+    history: &[Message],
+) -> Result<(Vec<Message>, Vec<WireWarning>), WireError> {
+    normalize_history_with(api, history, HistoryStrictness::Lenient)
+}
+
+/// `history` as `api`'s provider will take it: every message tagged with
+/// `api`, tool-call ids valid and unique for the provider, and each tool
+/// output pointing at the rewritten id of the call it answers.
+///
+/// Comes with a `WireWarning::HistoryNormalized` for each lenient rewrite.
+pub fn normalize_history_with(
+    api: &API,
+    history: &[Message],
+    strictness: HistoryStrictness,
+) -> Result<(Vec<Message>, Vec<WireWarning>), WireError> {
+    let mut warnings = Vec::new();
+    let normalized = normalize_history_reporting(api, history, strictness, &mut warnings)?;
+
+    Ok((normalized, warnings))
+}
+
+/// `normalize_history_with`, adding a warning to `warnings` for each lenient
+/// rewrite.
+pub(crate) fn normalize_history_reporting(
+    api: &API,
+    history: &[Message],
+    strictness: HistoryStrictness,
+    warnings: &mut Vec<WireWarning>,
+) -> Result<Vec<Message>, WireError> {
+    let provider = api.provider();
+    // Original id -> (new id, tool name), for the most recent call with it
+    let mut calls: HashMap<String, (String, String)> = HashMap::new();
+    let mut used = HashSet::new();
+    let mut normalized = Vec::with_capacity(history.len());
+
+    for (index, message) in history.iter().enumerate() {
+        let mut message = message.clone();
+        message.api = api.clone();
+
+        for (position, call) in message.tool_calls.iter_mut().flatten().enumerate() {
+            if !is_object(&call.function.arguments) {
+                let reason = format!(
+                    "arguments of tool call `{}` are not a JSON object",
+                    call.function.name
+                );
+                untranslatable(strictness, provider, index, reason, warnings)?;
+                call.function.arguments = "{}".to_string();
+            }
+
+            let id = unique_id(provider, &call.id, index, position, &mut used);
+            calls.insert(call.id.clone(), (id.clone(), call.function.name.clone()));
+            call.id = id;
+        }
+
+        if message.message_type == MessageType::FunctionCallOutput {
+            let answered = message
+                .tool_call_id
+                .as_ref()
+                .and_then(|id| calls.get(id))
+                .cloned();
+            match answered {
+                Some((id, name)) => {
+                    message.tool_call_id = Some(id);
+                    message.name.get_or_insert(name);
+                }
+                // Gemini and Ollama pair outputs with calls by name
+                None if message.name.is_some() && !pairs_by_id(provider) => {}
+                None => {
+                    let reason = "tool output answers no earlier tool call".to_string();
+                    untranslatable(strictness, provider, index, reason, warnings)?;
+                    message = as_user_message(message);
+                }
+            }
+        }
+
+        normalized.push(message);
+    }
+
+    Ok(normalized)
+}
+
+fn untranslatable(
+    strictness: HistoryStrictness,
+    provider: Provider,
+    message_index: usize,
+    reason: String,
+    warnings: &mut Vec<WireWarning>,
+) -> Result<(), WireError> {
+    let provider = provider.as_str().to_string();
+    match strictness {
+        HistoryStrictness::Strict => Err(WireError::UntranslatableHistory {
+            message_index,
+            provider,
+            reason,
+        }),
+        HistoryStrictness::Lenient => {
+            warnings.push(WireWarning::HistoryNormalized {
+                message_index,
+                provider,
+                reason,
+            });
+            Ok(())
+        }
+    }
+}
+
+fn pairs_by_id(provider: Provider) -> bool {
+    matches!(
+        provider,
+        Provider::OpenAI
+            | Provider::Groq
+            | Provider::Anthropic
+            | Provider::OpenRouter
+            | Provider::Cohere
+            | Provider::Together
+            | Provider::Fireworks
+            | Provider::Perplexity
+            | Provider::Compatible
+    )
+}
+
+/// Empty arguments are how some providers send a call without parameters.
+fn is_object(arguments: &str) -> bool {
+    arguments.trim().is_empty()
+        || serde_json::from_str::<serde_json::Value>(arguments)
+            .map(|value| value.is_object())
+            .unwrap_or(false)
+}
+
+fn as_user_message(mut message: Message) -> Message {
+    let tool = message.name.take().unwrap_or_else(|| "a tool".to_string());
+    message.content = format!("Output of {}: {}", tool, message.content);
+    message.message_type = MessageType::User;
+    message.tool_call_id = None;
+    message
+}
+
+/// `id` in the form `provider` accepts, made distinct from every id in
+/// `used`.
+fn unique_id(
+    provider: Provider,
+    id: &str,
+    index: usize,
+    position: usize,
+    used: &mut HashSet<String>,
+) -> String {
+    let mut base: String = match provider {
+        Provider::Anthropic | Provider::OpenRouter => id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+        _ => id.to_string(),
+    };
+    if base.is_empty() {
+        base = format!("call_{}_{}", index, position);
+    }
+
+    let limit = match provider {
+        Provider::OpenAI | Provider::Groq | Provider::OpenRouter => Some(OPENAI_MAX_ID_LEN),
+        _ => None,
+    };
+    let fit = |text: &str, suffix: &str| -> String {
+        let keep = limit.map_or(text.len(), |limit| {
+            text.len().min(limit.saturating_sub(suffix.len()))
+        });
+        let mut end = keep;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}{}", &text[..end], suffix)
+    };
+
+    let mut candidate = fit(&base, "");
+    let mut attempt = 2;
+    while used.contains(&candidate) {
+        candidate = fit(&base, &format!("_{}", attempt));
+        attempt += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
@@ -0,0 +1,994 @@
+//! Ollama's native chat API, for models served from the local machine.
+//!
+//! `OllamaClient` talks to `/api/chat` on `http://localhost:11434` (moved with
+//! `ClientOptions::from_base_url`). Streams arrive as one JSON object per
+//! line rather than server-sent events, and the last one, marked `"done"`,
+//! carries the token counts. Ollama needs no API key.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::api::{
+    event_stream, role_for, EventStream, OllamaModel, PromptCore, Provider, RawTransport,
+    ToolCapable, API,
+};
+use crate::clock::Clock;
+use crate::config::{ClientOptions, Endpoint, PromptOptions, Scheme};
+use crate::error::WireError;
+use crate::event_log::{emit, prepare_stream, send_logged, EventLog, WireEvent};
+use crate::metrics::{report_metrics, warn_on_tool_schema_size, LatencyRecorder};
+use crate::moderation::check_incoming;
+use crate::network_common::{cancellable, open_stream, parse_event, ByteStream, ParsedEvent};
+use crate::payload::{self, json_body};
+use crate::rate_limit::estimate_tokens;
+use crate::retry::with_retries;
+use crate::sanitize::{check_outbound, outbound};
+use crate::tool_loop::{
+    report_interim_text, resume_pending_calls, run_tool_calls, synthesize_call_ids, StatusLog,
+    ToolLoop, ToolLoopResult,
+};
+use crate::tool_protocol::{prompt_with_text_tools, ToolTransport};
+use crate::types::{
+    ContentCap, DeltaSequencer, EventSlot, Function, FunctionCall, Message, MessageBuilder,
+    MessageMetadata, MessageType, StreamEventKind, StreamedContent, Tool, ToolSpec,
+    TruncatedStream,
+};
+use crate::warning::{RequestWarnings, WireWarning};
+
+const CHAT_PATH: &str = "/api/chat";
+
+impl OllamaModel {
+    /// A model as the Ollama server names it, e.g. `llama3.1:8b`. Any name
+    /// is accepted; the server decides whether it has the model.
+    pub fn new(name: impl Into<String>) -> Self {
+        OllamaModel(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolve an `ollama/<model>` identifier. The prefix is required here,
+    /// since a bare name could belong to any provider.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model.strip_prefix("ollama/") {
+            Some(name) if !name.is_empty() => Ok(OllamaModel::new(name)),
+            _ => Err(format!("Unknown Ollama model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model without its prefix.
+    pub fn to_strings(&self) -> (String, String) {
+        ("ollama".to_string(), self.0.clone())
+    }
+}
+
+/// Takes the model with or without its `ollama/` prefix.
+impl<'a> From<&'a str> for OllamaModel {
+    fn from(model: &'a str) -> Self {
+        OllamaModel::new(model.strip_prefix("ollama/").unwrap_or(model))
+    }
+}
+
+impl From<String> for OllamaModel {
+    fn from(model: String) -> Self {
+        OllamaModel::from(model.as_str())
+    }
+}
+
+/// Client for a local Ollama server.
+///
+/// `ClientOptions::with_max_tokens` is sent as `options.num_predict`; other
+/// entries of Ollama's `options` block (temperature, context size, ...) can
+/// go through `PromptOptions::with_extra_body`. Tools are sent natively, but
+/// Ollama has no way to force or forbid a call, so
+/// `ClientOptions::with_tool_choice` has no effect beyond a warning on each
+/// tool-loop reply.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("ollama/llama3.1:8b", wire::mock::DoctestCall::Stream).await;
+/// use wire::api::PromptCore;
+/// use wire::ollama::OllamaClient;
+/// use wire::types::MessageType;
+///
+/// let client = OllamaClient::with_options("llama3.1:8b", options).with_keep_alive("10m");
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+/// let reply = client
+///     .prompt_stream(vec![question], "Answer briefly.".to_string(), tx)
+///     .await
+///     .unwrap();
+///
+/// let mut streamed = String::new();
+/// while let Some(delta) = rx.recv().await {
+///     streamed.push_str(&delta);
+/// }
+/// assert_eq!(streamed, reply.content);
+/// assert_eq!(reply.content, "It is sunny in Paris.");
+/// # });
+/// ```
+pub struct OllamaClient {
+    pub http_client: reqwest::Client,
+    pub stream_client: reqwest::Client,
+    pub model: OllamaModel,
+    pub host: String,
+    pub port: u16,
+    pub scheme: Scheme,
+    /// How long the server keeps the model loaded after a request, in
+    /// Ollama's duration format (`"5m"`, `"1h"`, `"0"` to unload at once).
+    /// `None` leaves it to the server.
+    pub keep_alive: Option<String>,
+    /// The options the client was built with. Requests read their settings
+    /// from here; the fields above are the ones resolved from them.
+    pub options: ClientOptions,
+    /// Options given at construction that requests leave out, reported with
+    /// every prompt.
+    pub(crate) ignored_options: Vec<WireWarning>,
+    /// Reported with every tool-loop request when `tool_choice` was set.
+    pub(crate) ignored_tool_choice: Option<WireWarning>,
+}
+
+impl OllamaClient {
+    /// Construct a client pointed at `localhost:11434`.
+    pub fn new<M>(model: M) -> Self
+    where
+        M: Into<OllamaModel>,
+    {
+        Self::with_options(model, ClientOptions::default())
+    }
+
+    /// Construct a client with custom transport options, e.g. an Ollama
+    /// server on another machine.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<OllamaModel>,
+    {
+        let mut client = Self {
+            http_client: reqwest::Client::new(),
+            stream_client: ClientOptions::default().stream_client(),
+            model: model.into(),
+            host: "localhost".to_string(),
+            port: 11434,
+            scheme: Scheme::Http,
+            keep_alive: None,
+            options: ClientOptions::default(),
+            ignored_options: Vec::new(),
+            ignored_tool_choice: None,
+        };
+
+        client.apply_options(options);
+        client
+    }
+
+    /// Keep the model loaded for `keep_alive` after each request.
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Apply caller-supplied configuration overlays.
+    fn apply_options(&mut self, options: ClientOptions) {
+        self.http_client = options.http_client();
+        self.stream_client = options.stream_client();
+
+        match &options.endpoint {
+            Endpoint::Default => {}
+            Endpoint::BaseUrl(endpoint) => {
+                self.host = endpoint.host.clone();
+                self.port = endpoint.port;
+                self.scheme = endpoint.scheme;
+            }
+        }
+
+        if options.thinking_level.is_some() {
+            self.ignored_options.push(WireWarning::option_ignored(
+                "thinking_level",
+                Provider::Ollama,
+                "wire sends Ollama no thinking settings",
+            ));
+        }
+        self.ignored_tool_choice = options.tool_choice.map(|_| {
+            WireWarning::option_ignored(
+                "tool_choice",
+                Provider::Ollama,
+                "Ollama can't force or forbid a tool call",
+            )
+        });
+
+        self.options = options;
+    }
+
+    /// The warnings of a request made with `options`, starting with the
+    /// options it leaves out.
+    fn request_warnings(&self, options: &PromptOptions) -> Result<RequestWarnings<'_>, WireError> {
+        let mut warnings =
+            RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+        warnings.extend(self.ignored_options.iter().cloned())?;
+        warnings.extend(options.header_warnings(Provider::Ollama))?;
+        Ok(warnings)
+    }
+
+    fn api(&self) -> API {
+        API::Ollama(self.model.clone())
+    }
+
+    /// Render the scheme/host/port tuple into a base URL.
+    fn origin(&self) -> String {
+        match (self.scheme, self.port) {
+            (Scheme::Https, 443) => format!("https://{}", self.host),
+            (Scheme::Http, 80) => format!("http://{}", self.host),
+            _ => format!("{}://{}:{}", self.scheme.as_str(), self.host, self.port),
+        }
+    }
+
+    /// Produce the correct `Host` header, including the port when required.
+    fn host_header(&self) -> String {
+        match (self.scheme, self.port) {
+            (Scheme::Https, 443) | (Scheme::Http, 80) => self.host.clone(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    /// The `/api/chat` body. Tool calls go out with their arguments as JSON
+    /// objects, and tool outputs name the tool they came from.
+    fn request_body(
+        &self,
+        system_prompt: &str,
+        chat_history: &[Message],
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
+    ) -> Result<serde_json::Value, WireError> {
+        let filter = self.options.content_filter.as_ref();
+        let text = |message_type: MessageType, content: &str| match filter {
+            Some(filter) => outbound(
+                &filter.apply(&message_type, content),
+                self.options.sanitize_policy,
+            ),
+            None => outbound(content, self.options.sanitize_policy),
+        };
+
+        let mut messages = vec![serde_json::json!({
+            "role": role_for(Provider::Ollama, MessageType::System),
+            "content": text(MessageType::System, system_prompt),
+        })];
+        for m in chat_history {
+            let mut message = serde_json::json!({
+                "role": role_for(Provider::Ollama, m.message_type),
+                "content": text(m.message_type, &m.content),
+            });
+
+            if let Some(calls) = m.tool_calls.as_ref().filter(|calls| !calls.is_empty()) {
+                message["tool_calls"] = calls
+                    .iter()
+                    .map(|call| {
+                        let arguments: serde_json::Value =
+                            serde_json::from_str(&call.function.arguments)
+                                .unwrap_or_else(|_| serde_json::json!({}));
+                        serde_json::json!({
+                            "function": { "name": call.function.name, "arguments": arguments }
+                        })
+                    })
+                    .collect();
+            }
+            if m.message_type == MessageType::FunctionCallOutput {
+                if let Some(name) = &m.name {
+                    message["tool_name"] = name.clone().into();
+                }
+            }
+
+            messages.push(message);
+        }
+
+        let (_, model) = self.model.to_strings();
+        let mut body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": stream,
+        });
+
+        if let Some(tools) = tools {
+            body["tools"] = tools
+                .iter()
+                .map(|t| {
+                    serde_json::json!({
+                        "type": "function",
+                        "function": {
+                            "name": t.name.clone(),
+                            "description": t.description.clone(),
+                            "parameters": t.parameters.clone(),
+                        }
+                    })
+                })
+                .collect();
+        }
+        if let Some(max_tokens) = self.options.max_tokens {
+            body["options"] = serde_json::json!({ "num_predict": max_tokens });
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = keep_alive.clone().into();
+        }
+        options.merge_extra_body(&mut body, Provider::Ollama, warnings)?;
+
+        Ok(body)
+    }
+
+    fn http_request(
+        &self,
+        body: &serde_json::Value,
+        options: &PromptOptions,
+    ) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.origin(), options.path(CHAT_PATH));
+
+        let mut request = self.http_client.post(url);
+        for (name, value) in options.extra_headers() {
+            request = request.header(name, value);
+        }
+
+        json_body(request, body, self.options.json_format)
+    }
+
+    /// The streaming request as raw HTTP/1.1, for `build_request_raw`.
+    /// Ollama streams uncompressed, so no `Accept-Encoding`.
+    fn raw_request(&self, body: &serde_json::Value, options: &PromptOptions) -> String {
+        let json_string = payload::to_string(body, self.options.json_format);
+
+        format!(
+            "POST {} HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Accept: */*\r\n\
+        {}\r\n\
+        {}",
+            options.path(CHAT_PATH),
+            self.host_header(),
+            json_string.len(),
+            options.raw_extra_headers(),
+            json_string.trim()
+        )
+    }
+
+    /// `(input, output)` tokens from the final `"done"` object of a reply.
+    fn token_counts(response_json: &serde_json::Value) -> (usize, usize) {
+        let count = |field: &str| response_json[field].as_u64().unwrap_or(0) as usize;
+        (count("prompt_eval_count"), count("eval_count"))
+    }
+
+    /// The `message.tool_calls` of a reply. Ollama doesn't always give calls
+    /// an id, so the rest are left empty for the tool loop to fill in;
+    /// outputs are matched to calls by tool name.
+    fn tool_calls(response_json: &serde_json::Value) -> Vec<FunctionCall> {
+        response_json["message"]["tool_calls"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|call| FunctionCall {
+                id: call["id"].as_str().unwrap_or_default().to_string(),
+                call_type: "function".to_string(),
+                function: Function {
+                    name: call["function"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: call["function"]
+                        .get("arguments")
+                        .cloned()
+                        .unwrap_or_else(|| serde_json::json!({}))
+                        .to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Ollama reports failures as `{"error": "..."}`, in place of a reply or as
+/// a line of a stream.
+fn check_error(response_json: &serde_json::Value) -> Result<(), WireError> {
+    match response_json["error"].as_str() {
+        Some(error) => Err(WireError::Provider {
+            code: None,
+            message: error.to_string(),
+            request_id: None,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCore for OllamaClient {
+    /// Ollama takes no API key.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        Ok(String::new())
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &self.options.clock
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.options.event_log.as_ref()
+    }
+
+    fn deny_warnings(&self) -> bool {
+        self.options.deny_warnings
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(self.api(), content)
+    }
+
+    /// Build the `/api/chat` request.
+    ///
+    /// * `tools` – sent in Ollama's `tools` array.
+    /// * `stream` – sets the body's `stream` flag; the path is the same
+    ///   either way.
+    fn build_request(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let options = PromptOptions::default();
+        Ok(self.http_request(
+            &self.request_body(
+                &system_prompt,
+                &chat_history,
+                tools,
+                stream,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            &options,
+        ))
+    }
+
+    /// Execute a non-streaming prompt and return the reply with Ollama's
+    /// token counts.
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Ollama)?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api(), &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let request_body = self.request_body(
+            &system_prompt,
+            &chat_history,
+            None,
+            false,
+            options,
+            &mut warnings,
+        )?;
+        let recorder = LatencyRecorder::start();
+        let api = self.api();
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (body, request_snapshot, request_id) = cancellable(
+            options.cancellation_or(self.options.cancellation.as_ref()),
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    send_logged(
+                        self.options.event_log.as_ref(),
+                        &api,
+                        Ok(self.http_request(&request_body, options)),
+                        self.options.request_snapshot,
+                        self.options.max_request_bytes,
+                    )
+                },
+            ),
+        )
+        .await?;
+        let latency = recorder.finish();
+        let response_json: serde_json::Value = serde_json::from_str(&body)?;
+        check_error(&response_json).map_err(|err| err.with_request_id(request_id.as_deref()))?;
+
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(self.read_json_response(&response_json)?);
+        let (input_tokens, output_tokens) = Self::token_counts(&response_json);
+
+        let message = Message {
+            message_type: MessageType::Assistant,
+            content,
+            api: self.api(),
+            system_prompt,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(latency),
+                truncated: cap.truncation(),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        Ok(options.post_process(message)?)
+    }
+
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
+    }
+
+    /// Extract `message.content` from a non-streaming reply.
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        response_json["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or(WireError::MissingField {
+                field: "message.content",
+            })
+    }
+
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
+
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        Some(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolCapable for OllamaClient {
+    async fn prompt_with_tools(
+        &self,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn prompt_with_tools_with_status(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn run_tool_loop(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransport for OllamaClient {
+    /// Build the raw HTTP request used by the streaming implementation.
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError> {
+        let options = PromptOptions::default();
+        Ok(self.raw_request(
+            &self.request_body(
+                &system_prompt,
+                &chat_history,
+                None,
+                stream,
+                &options,
+                &mut RequestWarnings::new(false, None),
+            )?,
+            &options,
+        ))
+    }
+
+    /// Read Ollama's newline-delimited JSON stream, forwarding each text
+    /// delta to the provided channel.
+    async fn process_stream(
+        &self,
+        stream: Box<dyn Read + Send>,
+        tx: &tokio::sync::mpsc::Sender<String>,
+    ) -> Result<String, WireError> {
+        let mut content = StreamedContent::new(tx);
+        self.read_stream(
+            ByteStream::read_raw(stream),
+            &mut LatencyRecorder::start(),
+            &mut ContentCap::new(None),
+            &mut DeltaSequencer::new(),
+            &mut content,
+            &mut (0, 0),
+            false,
+        )
+        .await?;
+
+        Ok(content.finish())
+    }
+}
+
+impl OllamaClient {
+    async fn prompt_with_tools_internal(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        check_incoming(
+            self.options.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        if self.options.tool_transport == ToolTransport::TextProtocol {
+            let mut warnings =
+                RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+            warnings.extend(self.ignored_tool_choice.clone())?;
+            return prompt_with_text_tools(
+                self,
+                &self.options.tool_hooks,
+                self.options.max_tool_iterations,
+                self.options.cancellation.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                warnings.to_vec(),
+                status,
+                system_prompt,
+                chat_history,
+                tools,
+            )
+            .await;
+        }
+
+        let mut chat_history = chat_history;
+        let system_prompt = system_prompt.to_string();
+        let api = self.api();
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let tool_map: HashMap<String, Tool> =
+            tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
+        resume_pending_calls(
+            &mut status,
+            self.options.event_log.as_ref(),
+            self.options.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            &system_prompt,
+        )
+        .await?;
+
+        let mut tool_loop = ToolLoop::new(
+            &self.options.tool_hooks,
+            self.options.max_tool_iterations,
+            self.options.cancellation.as_ref(),
+        );
+
+        loop {
+            let pending = tool_loop.next_request(&chat_history)?;
+            let mut warnings = self.request_warnings(&PromptOptions::default())?;
+            warnings.extend(self.ignored_tool_choice.clone())?;
+            // Tool outputs join the history inside the loop, so check every turn
+            let pending = warnings.normalize(&api, &pending, self.options.history_strictness)?;
+            check_outbound(self.options.sanitize_policy, &system_prompt, &pending)?;
+
+            if let Some(ratio) = self.options.tool_schema_warning {
+                let stats = self.stats(&system_prompt, &pending, Some(&specs));
+                warn_on_tool_schema_size(&stats, ratio, &mut warnings, &mut status).await?;
+            }
+
+            let recorder = LatencyRecorder::start();
+            let estimated_tokens = estimate_tokens(&system_prompt, &pending);
+            let (body, request_snapshot, request_id) = cancellable(
+                self.options.cancellation.as_ref(),
+                with_retries(
+                    self.options.retry_policy.as_ref(),
+                    self.options.rate_limiter.as_ref(),
+                    estimated_tokens,
+                    &self.options.clock,
+                    Some(&mut status),
+                    || {
+                        send_logged(
+                            self.options.event_log.as_ref(),
+                            &api,
+                            self.build_request(
+                                system_prompt.clone(),
+                                pending.clone(),
+                                Some(&specs),
+                                false,
+                            ),
+                            self.options.request_snapshot,
+                            self.options.max_request_bytes,
+                        )
+                    },
+                ),
+            )
+            .await?;
+            let response_json: serde_json::Value = serde_json::from_str(&body)?;
+            check_error(&response_json)
+                .map_err(|err| err.with_request_id(request_id.as_deref()))?;
+
+            let content = self.read_json_response(&response_json)?;
+            let mut tool_calls = Self::tool_calls(&response_json);
+            let (input_tokens, output_tokens) = Self::token_counts(&response_json);
+            let metadata = MessageMetadata {
+                latency: Some(recorder.finish()),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                synthesized_call_ids: synthesize_call_ids(&mut tool_calls, tool_loop.iteration()),
+                ..Default::default()
+            };
+
+            if tool_calls.is_empty() {
+                let message = Message {
+                    message_type: MessageType::Assistant,
+                    content,
+                    api: api.clone(),
+                    system_prompt: system_prompt.clone(),
+                    tool_call_id: None,
+                    tool_calls: None,
+                    name: None,
+                    input_tokens,
+                    output_tokens,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
+                });
+                chat_history.push(message);
+
+                return Ok(status.finish(chat_history));
+            }
+
+            report_interim_text(&mut status, &content).await;
+
+            let message = Message {
+                message_type: MessageType::FunctionCall,
+                content,
+                api: api.clone(),
+                system_prompt: String::new(),
+                tool_call_id: None,
+                tool_calls: Some(tool_calls.clone()),
+                name: None,
+                input_tokens,
+                output_tokens,
+                metadata,
+            };
+            report_metrics(&self.options.metrics_callback, &message);
+            emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                message: message.clone(),
+            });
+            chat_history.push(message);
+
+            let outputs = run_tool_calls(
+                &mut status,
+                self.options.event_log.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                &tool_map,
+                tool_calls,
+                &chat_history,
+                tool_loop.iteration(),
+                &api,
+                &system_prompt,
+            )
+            .await?;
+            chat_history.extend(outputs);
+        }
+    }
+
+    /// Stream a reply, leaving each line's `message.content` in `slot` as it
+    /// arrives.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(Provider::Ollama)?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api(), &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let body = self.request_body(
+            &system_prompt,
+            &chat_history,
+            None,
+            true,
+            options,
+            &mut warnings,
+        )?;
+        let (request, request_snapshot) = prepare_stream(
+            self.options.event_log.as_ref(),
+            &self.api(),
+            Ok(self.http_request(&body, options)),
+            self.options.request_snapshot,
+            self.options.max_request_bytes,
+        )?;
+
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let mut tokens = (0, 0);
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let cancellation = options.cancellation_or(self.options.cancellation.as_ref());
+        let body = cancellable(
+            cancellation,
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    open_stream(
+                        &self.stream_client,
+                        &request,
+                        self.options.max_redirects,
+                        self.options.stream_idle_timeout,
+                    )
+                },
+            ),
+        )
+        .await?
+        .with_cancellation(cancellation);
+        let request_id = body.request_id().map(str::to_string);
+        let truncated_stream = self
+            .read_stream(
+                body,
+                &mut recorder,
+                &mut cap,
+                &mut sequencer,
+                &mut content,
+                &mut tokens,
+                options.strict_stream_end,
+            )
+            .await
+            .map_err(|err| {
+                err.with_partial(|| content.partial())
+                    .with_request_id(request_id.as_deref())
+            })?;
+        warnings.stream_truncated(Provider::Ollama, truncated_stream.as_ref())?;
+        let content_bytes = content.bytes();
+        let (input_tokens, output_tokens) = tokens;
+
+        let message = Message {
+            message_type: MessageType::Assistant,
+            content: content.finish(),
+            api: self.api(),
+            system_prompt,
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(recorder.finish()),
+                truncated: cap.truncation(),
+                sequence: Some(sequencer.finish()),
+                content_bytes: Some(content_bytes),
+                truncated_stream,
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        options.post_process(message)
+    }
+
+    /// Parse the JSON objects of Ollama's stream from `body`, one per line,
+    /// forwarding each text delta into `content` and noting its arrival on
+    /// `recorder`. The final object's token counts land in `tokens`. Lines
+    /// that aren't objects (the response head, chunk sizes) are skipped.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_stream(
+        &self,
+        mut body: ByteStream,
+        recorder: &mut LatencyRecorder,
+        cap: &mut ContentCap,
+        sequencer: &mut DeltaSequencer,
+        content: &mut StreamedContent,
+        tokens: &mut (usize, usize),
+        strict: bool,
+    ) -> Result<Option<TruncatedStream>, WireError> {
+        let mut line = String::new();
+
+        while body.read_line_into(&mut line).await? {
+            let payload = line.trim();
+            if !payload.starts_with('{') {
+                continue;
+            }
+            emit(self.options.event_log.as_ref(), || WireEvent::StreamData {
+                data: payload.to_string(),
+            });
+
+            let response_json = match parse_event(&mut body, payload, strict).await? {
+                ParsedEvent::Json(json) => json,
+                ParsedEvent::Truncated(truncated) => return Ok(Some(truncated)),
+            };
+            check_error(&response_json)?;
+
+            if let Some(delta) = response_json["message"]["content"].as_str() {
+                if !delta.is_empty() {
+                    recorder.record_delta();
+
+                    let kept = cap.truncate(delta.to_string());
+                    if !kept.is_empty() {
+                        content.forward(kept).await?;
+                        sequencer.record(0);
+                    }
+
+                    // Dropping `body` on return closes the connection
+                    if cap.exceeded() {
+                        break;
+                    }
+                }
+            }
+
+            if response_json["done"].as_bool() == Some(true) {
+                *tokens = Self::token_counts(&response_json);
+                let usage = StreamEventKind::Usage {
+                    input: tokens.0,
+                    output: tokens.1,
+                };
+                content.report(usage).await?;
+                break;
+            }
+        }
+
+        Ok(None)
+    }
+}
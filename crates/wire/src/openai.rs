@@ -1,12 +1,38 @@
-use native_tls::TlsStream;
-use std::collections::HashMap;
-use std::io::{BufRead, Write};
-use std::net::TcpStream;
-
-use crate::api::{OpenAIModel, Prompt};
-use crate::config::{ClientOptions, Endpoint, Scheme, ThinkingLevel};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+
+use crate::api::{
+    event_stream, role_for, EventStream, OpenAIModel, PromptCore, Provider, RawTransport,
+    ToolCapable, API,
+};
+use crate::clock::Clock;
+use crate::compression::ACCEPT_ENCODING;
+use crate::config::{
+    AzureDeployment, ClientOptions, Endpoint, EndpointUrl, PromptOptions, Scheme, ThinkingLevel,
+    ToolChoice,
+};
+use crate::content_filter::filter_outbound;
+use crate::credentials::Credentials;
+use crate::error::WireError;
+use crate::event_log::{emit, prepare_stream, send_logged, EventLog, WireEvent};
+use crate::metrics::{report_metrics, warn_on_tool_schema_size, LatencyRecorder};
+use crate::moderation::check_incoming;
 use crate::network_common::*;
-use crate::types::{FunctionCall, Message, MessageBuilder, MessageType, Tool};
+use crate::payload::{self, json_body};
+use crate::rate_limit::estimate_tokens;
+use crate::retry::with_retries;
+use crate::sanitize::{check_outbound, sanitize_outbound};
+use crate::tool_loop::{
+    report_interim_text, resume_pending_calls, run_tool_calls, synthesize_call_ids, StatusLog,
+    ToolLoop, ToolLoopResult,
+};
+use crate::tool_protocol::{prompt_with_text_tools, text_protocol_tool_choice, ToolTransport};
+use crate::types::{
+    Citations, ContentCap, DeltaSequencer, EventSlot, FinishReason, Function, FunctionCall,
+    Message, MessageBuilder, MessageMetadata, MessageType, StreamEventKind, StreamedContent, Tool,
+    ToolSpec, TruncatedStream,
+};
+use crate::warning::{RequestWarnings, WireWarning};
 
 impl OpenAIModel {
     /// Resolve a user supplied model string into the strongly typed enum
@@ -45,15 +71,22 @@ impl std::str::FromStr for OpenAIModel {
     }
 }
 
-impl<'a> From<&'a str> for OpenAIModel {
-    fn from(model: &'a str) -> Self {
-        OpenAIModel::from_model_name(model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<&str> for OpenAIModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        OpenAIModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
     }
 }
 
-impl From<String> for OpenAIModel {
-    fn from(model: String) -> Self {
-        OpenAIModel::from_model_name(&model).unwrap_or_else(|err| panic!("{err}"))
+impl TryFrom<String> for OpenAIModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        OpenAIModel::try_from(model.as_str())
     }
 }
 
@@ -62,14 +95,164 @@ impl From<String> for OpenAIModel {
 /// The struct stores connection metadata (host, path, scheme) alongside the
 /// selected model and an underlying `reqwest::Client`. Helper methods translate
 /// the crate's provider-agnostic message format into OpenAI-specific JSON.
+///
+/// # Examples
+///
+/// These run against `wire::mock::doctest_client`, which points `options` at
+/// a local mock server; against the real API use `OpenAIClient::new` and set the API
+/// key variable.
+///
+/// A single prompt:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gpt-4o-mini", wire::mock::DoctestCall::Prompt).await;
+/// use wire::api::PromptCore;
+/// use wire::openai::OpenAIClient;
+/// use wire::types::MessageType;
+///
+/// let client = OpenAIClient::try_with_options("gpt-4o-mini", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let reply = client
+///     .prompt("Answer briefly.".to_string(), vec![question])
+///     .await
+///     .unwrap();
+/// assert_eq!(reply.content, "It is sunny in Paris.");
+/// # });
+/// ```
+///
+/// Streaming, with each delta sent over a channel as it arrives:
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gpt-4o-mini", wire::mock::DoctestCall::Stream).await;
+/// use wire::api::PromptCore;
+/// use wire::openai::OpenAIClient;
+/// use wire::types::MessageType;
+///
+/// let client = OpenAIClient::try_with_options("gpt-4o-mini", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+/// let reply = client
+///     .prompt_stream(vec![question], "Answer briefly.".to_string(), tx)
+///     .await
+///     .unwrap();
+///
+/// let mut streamed = String::new();
+/// while let Some(delta) = rx.recv().await {
+///     streamed.push_str(&delta);
+/// }
+/// assert_eq!(streamed, reply.content);
+/// # });
+/// ```
+///
+/// A tool loop: the model's calls run until it answers in text, and the
+/// returned history holds every turn, the final answer last.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// # let (_server, options) =
+/// #     wire::mock::doctest_client("gpt-4o-mini", wire::mock::DoctestCall::Tools).await;
+/// use wire::api::{PromptCore, ToolCapable};
+/// use wire::openai::OpenAIClient;
+/// use wire::types::{MessageType, Tool, ToolWrapper};
+///
+/// let weather = Tool {
+///     function_type: "function".to_string(),
+///     name: "get_weather".to_string(),
+///     description: "Current weather for a city.".to_string(),
+///     parameters: serde_json::json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"],
+///     }),
+///     strict: false,
+///     function: Box::new(ToolWrapper(|args: serde_json::Value| {
+///         serde_json::json!(format!("sunny in {}", args["city"].as_str().unwrap()))
+///     })),
+/// };
+///
+/// let client = OpenAIClient::try_with_options("gpt-4o-mini", options).unwrap();
+/// let question = client
+///     .new_message("What is the weather in Paris?".to_string())
+///     .message_type(MessageType::User)
+///     .build();
+///
+/// let history = client
+///     .prompt_with_tools("Use the tools you have.", vec![question], vec![weather])
+///     .await
+///     .unwrap();
+/// assert_eq!(history.last().unwrap().content, "It is sunny in Paris.");
+/// # });
+/// ```
+///
+/// The same client talks to Azure OpenAI deployments, whose payloads match
+/// OpenAI's; build it with `ClientOptions::for_azure`.
 pub struct OpenAIClient {
     pub http_client: reqwest::Client,
-    pub model: OpenAIModel,
-    pub host: String,
-    pub port: u16,
-    pub path: String,
-    pub scheme: Scheme,
-    pub thinking_level: Option<ThinkingLevel>,
+    pub stream_client: reqwest::Client,
+    /// The API requests name and replies carry: `API::OpenAI` with the
+    /// model, or another provider's for the clients built on this one (see
+    /// `groq`).
+    pub api: API,
+    pub credentials: Credentials,
+    /// Where requests go when the options give no base URL.
+    pub(crate) default_host: &'static str,
+    /// Where on the host requests go when neither `with_path` nor the options
+    /// say otherwise.
+    pub(crate) default_path: &'static str,
+    /// The path `with_path` gave, in place of every other.
+    pub(crate) path: Option<String>,
+    /// Headers that provider wants on every request, such as OpenRouter's
+    /// attribution. A prompt's extra header of the same name replaces one.
+    pub(crate) compatible_headers: Vec<(&'static str, String)>,
+    /// Fields that provider wants in every body, such as OpenRouter's
+    /// `provider`, merged in ahead of the prompt's extra body.
+    pub(crate) compatible_body: serde_json::Map<String, serde_json::Value>,
+    /// The options the client was built with. Requests read their settings
+    /// from here.
+    pub options: ClientOptions,
+    /// Options given at construction that requests leave out, reported with
+    /// every prompt.
+    pub(crate) ignored_options: Vec<WireWarning>,
+}
+
+/// How requests carry the API key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AuthHeader {
+    /// `Authorization: Bearer <key>`
+    Bearer,
+    /// `api-key: <key>`
+    ApiKey,
+}
+
+impl AuthHeader {
+    fn name(&self) -> &'static str {
+        match self {
+            AuthHeader::Bearer => "Authorization",
+            AuthHeader::ApiKey => "api-key",
+        }
+    }
+
+    fn value(&self, token: String) -> String {
+        match self {
+            AuthHeader::Bearer => format!("Bearer {}", token),
+            AuthHeader::ApiKey => token,
+        }
+    }
 }
 
 impl OpenAIClient {
@@ -87,73 +270,200 @@ impl OpenAIClient {
     where
         M: Into<OpenAIModel>,
     {
-        let model = model.into();
-        let default_thinking_level = Self::default_thinking_level(&model);
+        let var = match options.azure {
+            Some(_) => "AZURE_OPENAI_API_KEY",
+            None => "OPENAI_API_KEY",
+        };
+        Self::build(
+            API::OpenAI(model.into()),
+            "api.openai.com",
+            "/v1/chat/completions",
+            Credentials::from_env(var),
+            options,
+        )
+    }
+
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when OpenAI has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<OpenAIModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<OpenAIModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+
+    /// A client naming `api`'s model, sending to `host` and `path` unless the
+    /// options say otherwise, with the key in `credentials` unless the options
+    /// give one. Providers other than OpenAI serving the same API build
+    /// theirs here.
+    pub(crate) fn build(
+        api: API,
+        host: &'static str,
+        path: &'static str,
+        credentials: Credentials,
+        options: ClientOptions,
+    ) -> Self {
+        let credentials = match &options.api_key {
+            Some(key) => Credentials::from_secret(key.clone()),
+            None => credentials,
+        };
 
         let mut client = Self {
-            http_client: reqwest::Client::new(),
-            model,
-            host: "api.openai.com".to_string(),
-            port: 443,
-            path: "/v1/chat/completions".to_string(),
-            scheme: Scheme::Https,
-            thinking_level: default_thinking_level,
+            http_client: options.http_client(),
+            stream_client: options.stream_client(),
+            api,
+            credentials,
+            default_host: host,
+            default_path: path,
+            path: None,
+            compatible_headers: Vec::new(),
+            compatible_body: serde_json::Map::new(),
+            options,
+            ignored_options: Vec::new(),
         };
 
-        client.apply_options(options);
+        if client.options.thinking_level.is_some() && client.reasoning_effort_value().is_none() {
+            client.ignored_options.push(WireWarning::option_ignored(
+                "thinking_level",
+                client.api.provider(),
+                "only GPT-5 takes a reasoning effort",
+            ));
+        }
+
         client
     }
 
-    /// Apply optional configuration overrides.
-    fn apply_options(&mut self, options: ClientOptions) {
-        match options.endpoint {
-            Endpoint::Default => {}
-            Endpoint::BaseUrl(endpoint) => {
-                self.host = endpoint.host;
-                self.port = endpoint.port;
-                self.scheme = endpoint.scheme;
-            }
-        }
+    /// The auth header's value, or `None` for an empty key, which leaves the
+    /// header out.
+    fn auth_value(&self) -> Result<Option<String>, WireError> {
+        let token = self.get_auth_token()?;
+        Ok((!token.is_empty()).then(|| self.auth_header().value(token)))
+    }
 
-        if options.disable_proxy {
-            self.http_client = reqwest::Client::builder()
-                .no_proxy()
-                .build()
-                .expect("reqwest client without proxy");
+    /// Send requests to another path on the same origin, e.g. a versioned or
+    /// regional route.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The Azure deployment requests go to, which only OpenAI's own models
+    /// have.
+    fn azure(&self) -> Option<&AzureDeployment> {
+        match self.api {
+            API::OpenAI(_) => self.options.azure.as_ref(),
+            _ => None,
         }
+    }
 
-        if let Some(thinking_level) = options.thinking_level {
-            self.thinking_level = Some(thinking_level);
+    /// Where the key goes; Azure deployments take it as `api-key`.
+    fn auth_header(&self) -> AuthHeader {
+        match self.azure() {
+            Some(_) => AuthHeader::ApiKey,
+            None => AuthHeader::Bearer,
         }
     }
 
-    fn default_thinking_level(model: &OpenAIModel) -> Option<ThinkingLevel> {
-        match model {
-            OpenAIModel::GPT5 => Some(ThinkingLevel::Minimal),
-            _ => None,
+    /// The options' base URL, or `default_host` over HTTPS.
+    fn endpoint(&self) -> EndpointUrl {
+        match &self.options.endpoint {
+            Endpoint::BaseUrl(endpoint) => endpoint.clone(),
+            Endpoint::Default => EndpointUrl {
+                scheme: Scheme::Https,
+                host: self.default_host.to_string(),
+                port: 443,
+            },
         }
     }
 
     /// Compose the scheme/host/port triple into an origin string.
     fn origin(&self) -> String {
-        match (self.scheme, self.port) {
-            (Scheme::Https, 443) => format!("https://{}", self.host),
-            (Scheme::Http, 80) => format!("http://{}", self.host),
-            _ => format!("{}://{}:{}", self.scheme.as_str(), self.host, self.port),
+        let EndpointUrl { scheme, host, port } = self.endpoint();
+        match (scheme, port) {
+            (Scheme::Https, 443) => format!("https://{}", host),
+            (Scheme::Http, 80) => format!("http://{}", host),
+            _ => format!("{}://{}:{}", scheme.as_str(), host, port),
         }
     }
 
     /// Determine the correct `Host` header value for the current endpoint.
     fn host_header(&self) -> String {
-        match (self.scheme, self.port) {
-            (Scheme::Https, 443) | (Scheme::Http, 80) => self.host.clone(),
-            _ => format!("{}:{}", self.host, self.port),
+        let EndpointUrl { scheme, host, port } = self.endpoint();
+        match (scheme, port) {
+            (Scheme::Https, 443) | (Scheme::Http, 80) => host,
+            _ => format!("{}:{}", host, port),
+        }
+    }
+
+    /// The path requests go to: `with_path`'s, else an Azure deployment's or
+    /// the gateway's from the options, else `default_path`.
+    fn path(&self) -> String {
+        if let Some(path) = &self.path {
+            return path.clone();
+        }
+        if let Some(azure) = self.azure() {
+            return azure.path();
         }
+        #[cfg(feature = "compatible")]
+        if let (API::Compatible(_), Some(path)) = (&self.api, &self.options.compatible.path) {
+            return path.clone();
+        }
+        self.default_path.to_string()
+    }
+
+    /// The model as requests name it.
+    fn model_name(&self) -> String {
+        match &self.api {
+            #[cfg(feature = "compatible")]
+            API::Compatible(model) => model.name().to_string(),
+            api => api.to_strings().1,
+        }
+    }
+
+    /// `compatible_headers` less those the prompt's extra headers replace.
+    fn compatible_headers(&self, options: &PromptOptions) -> Vec<(&'static str, &str)> {
+        self.compatible_headers
+            .iter()
+            .filter(|(name, _)| {
+                !options
+                    .extra_headers
+                    .iter()
+                    .any(|(other, _)| other.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect()
+    }
+
+    /// The warnings of a request made with `options`, starting with the
+    /// options it leaves out.
+    fn request_warnings(&self, options: &PromptOptions) -> Result<RequestWarnings<'_>, WireError> {
+        let mut warnings =
+            RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+        warnings.extend(self.ignored_options.iter().cloned())?;
+        warnings.extend(options.header_warnings(self.api.provider()))?;
+        Ok(warnings)
     }
 
+    /// GPT-5's reasoning effort: the options' thinking level, else minimal.
     fn reasoning_effort_value(&self) -> Option<&'static str> {
-        match self.model {
-            OpenAIModel::GPT5 => self.thinking_level.map(|level| level.as_reasoning_effort()),
+        match self.api {
+            API::OpenAI(OpenAIModel::GPT5) => Some(
+                self.options
+                    .thinking_level
+                    .unwrap_or(ThinkingLevel::Minimal)
+                    .as_reasoning_effort(),
+            ),
             _ => None,
         }
     }
@@ -166,24 +476,108 @@ impl OpenAIClient {
         system_prompt: &str,
         chat_history: Vec<Message>,
         tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    ) -> Result<ToolLoopResult, WireError> {
+        let mut status = StatusLog::new(tx);
+        self.credentials.require()?;
+        check_incoming(
+            self.options.moderator.as_ref(),
+            &chat_history,
+            &PromptOptions::default(),
+        )
+        .await?;
+
+        if self.options.tool_transport == ToolTransport::TextProtocol {
+            let mut warnings =
+                RequestWarnings::new(self.options.deny_warnings, self.options.event_log.as_ref());
+            warnings.extend(
+                self.options
+                    .tool_choice
+                    .map(|_| text_protocol_tool_choice(self.api.provider())),
+            )?;
+            return prompt_with_text_tools(
+                self,
+                &self.options.tool_hooks,
+                self.options.max_tool_iterations,
+                self.options.cancellation.as_ref(),
+                self.options.tool_output_policy.as_ref(),
+                warnings.to_vec(),
+                status,
+                system_prompt,
+                chat_history,
+                tools,
+            )
+            .await;
+        }
+
         let mut chat_history = chat_history;
         let system_prompt = system_prompt.to_string();
-        let api = crate::api::API::OpenAI(self.model.clone());
+        let api = self.api.clone();
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let tool_map: HashMap<String, Tool> =
+            tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
+        resume_pending_calls(
+            &mut status,
+            self.options.event_log.as_ref(),
+            self.options.tool_output_policy.as_ref(),
+            &tool_map,
+            &mut chat_history,
+            &system_prompt,
+        )
+        .await?;
         let mut calling_tools = true;
 
+        let mut tool_loop = ToolLoop::new(
+            &self.options.tool_hooks,
+            self.options.max_tool_iterations,
+            self.options.cancellation.as_ref(),
+        );
+
         while calling_tools {
-            let response = self
-                .build_request(
-                    system_prompt.clone(),
-                    chat_history.clone(),
-                    Some(tools.clone()),
-                    false,
-                )
-                .send()
-                .await?;
+            let pending = tool_loop.next_request(&chat_history)?;
+            let mut warnings = self.request_warnings(&PromptOptions::default())?;
+            // Tool outputs join the history inside the loop, so check every turn
+            let pending = warnings.normalize(&api, &pending, self.options.history_strictness)?;
+            check_outbound(self.options.sanitize_policy, &system_prompt, &pending)?;
+
+            if let Some(ratio) = self.options.tool_schema_warning {
+                let stats = self.stats(&system_prompt, &pending, Some(&specs));
+                warn_on_tool_schema_size(&stats, ratio, &mut warnings, &mut status).await?;
+            }
 
-            let body = response.text().await?;
+            let recorder = LatencyRecorder::start();
+            let estimated_tokens = estimate_tokens(&system_prompt, &pending);
+            let (body, request_snapshot, request_id) = cancellable(
+                self.options.cancellation.as_ref(),
+                with_retries(
+                    self.options.retry_policy.as_ref(),
+                    self.options.rate_limiter.as_ref(),
+                    estimated_tokens,
+                    &self.options.clock,
+                    Some(&mut status),
+                    || {
+                        send_logged(
+                            self.options.event_log.as_ref(),
+                            &api,
+                            self.build_request(
+                                system_prompt.clone(),
+                                pending.clone(),
+                                Some(&specs),
+                                false,
+                            ),
+                            self.options.request_snapshot,
+                            self.options.max_request_bytes,
+                        )
+                    },
+                ),
+            )
+            .await?;
+            let metadata = MessageMetadata {
+                latency: Some(recorder.finish()),
+                request_snapshot,
+                request_id,
+                warnings: warnings.to_vec(),
+                ..Default::default()
+            };
             let response_json: serde_json::Value = serde_json::from_str(&body)?;
 
             let usage = response_json
@@ -194,12 +588,25 @@ impl OpenAIClient {
                     "completion_tokens": 0
                 }));
 
-            if let Some(mut content) = response_json
+            let reply = response_json
                 .get("choices")
                 .and_then(|v| v.get(0))
-                .and_then(|v| v.get("message"))
+                .and_then(|v| v.get("message"));
+            let reply_text = reply
                 .and_then(|v| v.get("content"))
                 .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let requests_tools = reply
+                .and_then(|v| v.get("tool_calls"))
+                .and_then(|v| v.as_array())
+                .is_some_and(|calls| !calls.is_empty());
+
+            // Text that comes with tool calls is interim, not the answer
+            if let Some(mut content) = reply
+                .and_then(|v| v.get("content"))
+                .and_then(|v| v.as_str())
+                .filter(|_| !requests_tools)
                 .map(|s| s.to_string())
             {
                 calling_tools = false;
@@ -208,7 +615,7 @@ impl OpenAIClient {
                     content = content[1..content.len() - 1].to_string();
                 }
 
-                chat_history.push(Message {
+                let message = Message {
                     message_type: MessageType::Assistant,
                     content,
                     api: api.clone(),
@@ -218,23 +625,32 @@ impl OpenAIClient {
                     name: None,
                     input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
                     output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
+                    metadata,
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
                 });
+                chat_history.push(message);
             } else {
-                let tool_map: HashMap<String, Tool> =
-                    tools.iter().map(|t| (t.name.clone(), t.clone())).collect();
-
                 let content = response_json
                     .get("choices")
                     .and_then(|v| v.get(0))
                     .and_then(|v| v.get("message"))
                     .and_then(|v| v.get("tool_calls"))
-                    .ok_or_else(|| "Missing both content and tool calls")?;
+                    .ok_or(WireError::MissingField {
+                        field: "choices[0].message.content",
+                    })?;
 
-                let tool_calls: Vec<FunctionCall> = serde_json::from_value(content.clone())?;
+                let mut tool_calls: Vec<FunctionCall> = serde_json::from_value(content.clone())?;
+                let synthesized_call_ids =
+                    synthesize_call_ids(&mut tool_calls, tool_loop.iteration());
 
-                chat_history.push(Message {
+                report_interim_text(&mut status, &reply_text).await;
+
+                let message = Message {
                     message_type: MessageType::FunctionCall,
-                    content: String::new(),
+                    content: reply_text,
                     api: api.clone(),
                     system_prompt: String::new(),
                     tool_call_id: None,
@@ -242,94 +658,78 @@ impl OpenAIClient {
                     name: None,
                     input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
                     output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
+                    metadata: MessageMetadata {
+                        synthesized_call_ids,
+                        ..metadata
+                    },
+                };
+                report_metrics(&self.options.metrics_callback, &message);
+                emit(self.options.event_log.as_ref(), || WireEvent::Message {
+                    message: message.clone(),
                 });
-
-                for call in tool_calls {
-                    if let Some(tx) = tx.as_ref() {
-                        let _ = tx
-                            .send(format!("calling tool {}...", call.function.name))
-                            .await;
-                    }
-
-                    let tool_name = call.function.name.clone();
-                    let call_id = call.id.clone();
-                    let arguments = call.function.arguments.clone();
-
-                    let tool = tool_map
-                        .get(&tool_name)
-                        .ok_or_else(|| format!("tool {} not found", tool_name))?
-                        .clone();
-
-                    let tool_args: serde_json::Value = serde_json::from_str(&arguments)?;
-
-                    let tool_name_for_message = tool.name.clone();
-
-                    let function_output = tokio::task::spawn_blocking(move || {
-                        tool.function.call(tool_args).to_string()
-                    })
-                    .await
-                    .map_err(|err| -> Box<dyn std::error::Error> { Box::new(err) })?;
-
-                    chat_history.push(Message {
-                        message_type: MessageType::FunctionCallOutput,
-                        content: function_output,
-                        api: api.clone(),
-                        system_prompt: system_prompt.clone(),
-                        tool_call_id: Some(call_id),
-                        tool_calls: None,
-                        name: Some(tool_name_for_message),
-                        input_tokens: 0,
-                        output_tokens: 0,
-                    });
-                }
+                chat_history.push(message);
+
+                let outputs = run_tool_calls(
+                    &mut status,
+                    self.options.event_log.as_ref(),
+                    self.options.tool_output_policy.as_ref(),
+                    &tool_map,
+                    tool_calls,
+                    &chat_history,
+                    tool_loop.iteration(),
+                    &api,
+                    &system_prompt,
+                )
+                .await?;
+                chat_history.extend(outputs);
             }
         }
 
-        Ok(chat_history)
-    }
-}
-
-#[async_trait::async_trait]
-impl Prompt for OpenAIClient {
-    /// Fetch the OpenAI API key from the environment.
-    fn get_auth_token(&self) -> String {
-        std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY environment variable not set")
-    }
-
-    /// Helper that returns a `MessageBuilder` pinned to the selected OpenAI model.
-    fn new_message(&self, content: String) -> MessageBuilder {
-        MessageBuilder::new(crate::api::API::OpenAI(self.model.clone()), content)
+        Ok(status.finish(chat_history))
     }
 
     /// Build a `reqwest` request tailored to OpenAI's chat completions endpoint,
     /// translating the shared `Message` model plus optional tool metadata into
     /// the JSON payload OpenAI expects.
     ///
+    /// * `options` – the prompt's path override and extra body fields and
+    ///   headers; `PromptOptions::default()` outside of a prompt.
+    /// * `warnings` – collects extra body fields that wire had already set.
     /// * `system_prompt` – inserted as the leading system role message.
     /// * `chat_history` – prior conversation messages emitted by users, tools,
     ///   or previous assistant responses.
     /// * `tools` – optional function definitions surfaced through OpenAI's
     ///   `tools` array.
     /// * `stream` – toggles server streaming when `true`.
-    fn build_request(
+    fn request_to(
         &self,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
         system_prompt: String,
-        mut chat_history: Vec<Message>,
-        tools: Option<Vec<Tool>>,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
         stream: bool,
-    ) -> reqwest::RequestBuilder {
-        let (_, model) = self.model.to_strings();
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let (system_prompt, chat_history) = filter_outbound(
+            self.options.content_filter.as_ref(),
+            system_prompt,
+            chat_history,
+        );
+        let (system_prompt, mut chat_history) =
+            sanitize_outbound(self.options.sanitize_policy, system_prompt, chat_history);
+        let model = self.model_name();
         let messages = {
             let mut msgs = vec![Message {
                 message_type: MessageType::System,
                 content: system_prompt.clone(),
-                api: crate::api::API::OpenAI(self.model.clone()),
+                api: self.api.clone(),
                 system_prompt,
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
                 input_tokens: 0,
                 output_tokens: 0,
+                metadata: MessageMetadata::default(),
             }];
 
             msgs.append(&mut chat_history);
@@ -344,14 +744,17 @@ impl Prompt for OpenAIClient {
             "messages": messages.iter()
                 .map(|message| {
                     let mut m = serde_json::json!({
-                        "role": message.message_type.to_string(),
+                        "role": role_for(Provider::OpenAI, message.message_type),
                         "content": message.content,
                     });
 
                     if message.message_type == MessageType::FunctionCall {
-                        m["role"] = serde_json::Value::String("assistant".to_string());
-                        m["name"] = serde_json::Value::String("idk".to_string());
                         m["tool_calls"] = serde_json::json!(message.tool_calls);
+
+                        // Tool-call turns without text need an explicit null
+                        if message.content.trim().is_empty() {
+                            m["content"] = serde_json::Value::Null;
+                        }
                     }
 
                     if message.message_type == MessageType::FunctionCallOutput {
@@ -363,61 +766,111 @@ impl Prompt for OpenAIClient {
             "stream": stream,
         });
 
+        // Without it a stream never says how many tokens it used
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         if let Some(reasoning_effort) = self.reasoning_effort_value() {
             body["reasoning_effort"] = reasoning_effort.into();
         }
 
+        if let Some(max_tokens) = self.options.max_tokens {
+            body["max_completion_tokens"] = max_tokens.into();
+        }
+
         if let Some(tools) = &tools {
             let tools_mapped = tools
                 .iter()
                 .map(|t| {
+                    let mut function = serde_json::json!({
+                        "name": t.name.clone(),
+                        "description": t.description.clone(),
+                        "parameters": t.parameters.clone(),
+                    });
+
+                    if t.strict {
+                        function["parameters"] = strict_schema(&t.parameters);
+                        function["strict"] = serde_json::json!(true);
+                    }
+
                     serde_json::json!({
                         "type": "function",
-                        "function": {
-                            "name": t.name.clone(),
-                            "description": t.description.clone(),
-                            "parameters": t.parameters.clone(),
-                        }
+                        "function": function,
                     })
                 })
                 .collect::<Vec<_>>();
 
             body["tools"] = serde_json::json!(tools_mapped);
+            if let Some(tool_choice) = self.options.tool_choice {
+                body["tool_choice"] = serde_json::json!(match tool_choice {
+                    ToolChoice::Auto => "auto",
+                    ToolChoice::Any => "required",
+                    ToolChoice::None => "none",
+                });
+            }
         }
+        payload::merge(&mut body, &self.compatible_body);
+        options.merge_extra_body(&mut body, self.api.provider(), warnings)?;
 
-        let url = format!("{}{}", self.origin(), self.path);
+        let url = format!("{}{}", self.origin(), options.path(&self.path()));
 
-        let mut request = self.http_client.post(url.clone()).json(&body);
+        let mut request = json_body(
+            self.http_client.post(url.clone()),
+            &body,
+            self.options.json_format,
+        );
 
-        request = request.header("Authorization", format!("Bearer {}", self.get_auth_token()));
+        if let Some(value) = self.auth_value()? {
+            request = request.header(self.auth_header().name(), value);
+        }
+        request = request.header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING);
+        for (name, value) in self.compatible_headers(options) {
+            request = request.header(name, value);
+        }
+        for (name, value) in options.extra_headers() {
+            request = request.header(name, value);
+        }
 
-        request
+        Ok(request)
     }
 
-    /// Build the raw HTTPS request string used by the manual TLS streaming
-    /// implementation.
+    /// Build the raw HTTP/1.1 request string for `build_request_raw`.
     ///
+    /// * `options` – the prompt's path override and extra body fields and
+    ///   headers; `PromptOptions::default()` outside of a prompt.
+    /// * `warnings` – collects extra body fields that wire had already set.
     /// * `system_prompt` – written into the first `messages` entry.
     /// * `chat_history` – appended sequentially after the system message.
     /// * `stream` – mirrors the `stream` flag in the JSON payload.
-    fn build_request_raw(
+    fn raw_request_to(
         &self,
+        options: &PromptOptions,
+        warnings: &mut RequestWarnings<'_>,
         system_prompt: String,
-        mut chat_history: Vec<Message>,
+        chat_history: Vec<Message>,
         stream: bool,
-    ) -> String {
-        let (_, model) = self.model.to_strings();
+    ) -> Result<String, WireError> {
+        let (system_prompt, chat_history) = filter_outbound(
+            self.options.content_filter.as_ref(),
+            system_prompt,
+            chat_history,
+        );
+        let (system_prompt, mut chat_history) =
+            sanitize_outbound(self.options.sanitize_policy, system_prompt, chat_history);
+        let model = self.model_name();
         let messages = {
             let mut msgs = vec![Message {
                 message_type: MessageType::System,
                 content: system_prompt.clone(),
-                api: crate::api::API::OpenAI(self.model.clone()),
+                api: self.api.clone(),
                 system_prompt,
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
                 input_tokens: 0,
                 output_tokens: 0,
+                metadata: MessageMetadata::default(),
             }];
 
             msgs.append(&mut chat_history);
@@ -430,24 +883,45 @@ impl Prompt for OpenAIClient {
             "messages": messages.iter()
                 .map(|message| {
                     serde_json::json!({
-                        "role": message.message_type.to_string(),
+                        "role": role_for(Provider::OpenAI, message.message_type),
                         "content": message.content
                     })
                 }).collect::<Vec<serde_json::Value>>(),
             "stream": stream,
         });
 
+        // Without it a stream never says how many tokens it used
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         if let Some(reasoning_effort) = self.reasoning_effort_value() {
             body["reasoning_effort"] = reasoning_effort.into();
         }
 
-        let json = serde_json::json!(body);
-        let json_string = serde_json::to_string(&json).expect("Failed to serialize JSON");
+        if let Some(max_tokens) = self.options.max_tokens {
+            body["max_completion_tokens"] = max_tokens.into();
+        }
+        payload::merge(&mut body, &self.compatible_body);
+        options.merge_extra_body(&mut body, self.api.provider(), warnings)?;
+
+        let json_string = payload::to_string(&body, self.options.json_format);
 
+        let default_path = self.path();
         let (auth_string, api_version, path) = (
-            format!("Authorization: Bearer {}\r\n", self.get_auth_token()),
+            format!(
+                "{}{}{}",
+                self.auth_value()?
+                    .map(|value| format!("{}: {}\r\n", self.auth_header().name(), value))
+                    .unwrap_or_default(),
+                self.compatible_headers(options)
+                    .into_iter()
+                    .map(|(name, value)| format!("{}: {}\r\n", name, value))
+                    .collect::<String>(),
+                options.raw_extra_headers()
+            ),
             "\r\n".to_string(),
-            self.path.clone(),
+            options.path(&default_path),
         );
 
         let request = format!(
@@ -456,12 +930,14 @@ impl Prompt for OpenAIClient {
         Content-Type: application/json\r\n\
         Content-Length: {}\r\n\
         Accept: */*\r\n\
+        Accept-Encoding: {}\r\n\
         {}\
         {}\
         {}",
             path,
             self.host_header(),
             json_string.len(),
+            ACCEPT_ENCODING,
             auth_string,
             if api_version == "\r\n" && auth_string == "\r\n" {
                 String::new()
@@ -471,73 +947,67 @@ impl Prompt for OpenAIClient {
             json_string.trim()
         );
 
-        request
+        Ok(request)
     }
+}
 
-    /// Execute a streaming request against OpenAI, yielding deltas over the
-    /// provided channel as they arrive.
-    ///
-    /// * `chat_history` – context messages that precede the new completion.
-    /// * `system_prompt` – system role text included at the start of the request.
-    /// * `tx` – channel to deliver streaming content chunks to the caller.
-    async fn prompt_stream(
-        &self,
-        chat_history: Vec<Message>,
-        system_prompt: String,
-        tx: tokio::sync::mpsc::Sender<String>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        if self.scheme != Scheme::Https {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "prompt_stream is not available with non-TLS endpoints",
-            )));
-        }
+#[async_trait::async_trait]
+impl PromptCore for OpenAIClient {
+    /// The OpenAI API key (`AZURE_OPENAI_API_KEY` for an Azure deployment),
+    /// as read when the client was built or refreshed.
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        self.credentials.token()
+    }
 
-        let request = self.build_request_raw(system_prompt.clone(), chat_history, true);
+    fn refresh_credentials(&self) {
+        self.credentials.refresh();
+    }
 
-        let mut stream = connect_https(&self.host, self.port);
-        stream
-            .write_all(request.as_bytes())
-            .expect("Failed to write to stream");
-        stream.flush().expect("Failed to flush stream");
+    /// Helper that returns a `MessageBuilder` pinned to the selected OpenAI model.
+    fn clock(&self) -> &dyn Clock {
+        &self.options.clock
+    }
 
-        let response = self.process_stream(stream, &tx).await;
+    fn event_log(&self) -> Option<&EventLog> {
+        self.options.event_log.as_ref()
+    }
 
-        let content = response?;
+    fn deny_warnings(&self) -> bool {
+        self.options.deny_warnings
+    }
 
-        Ok(Message {
-            message_type: MessageType::Assistant,
-            content,
-            api: crate::api::API::OpenAI(self.model.clone()),
-            system_prompt: system_prompt.to_string(),
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-            // TODO: implement
-            input_tokens: 0,
-            output_tokens: 0,
-        })
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(self.api.clone(), content)
     }
 
-    async fn prompt_with_tools(
+    fn build_request(
         &self,
-        system_prompt: &str,
+        system_prompt: String,
         chat_history: Vec<Message>,
-        tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
-            .await
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        self.request_to(
+            &PromptOptions::default(),
+            &mut RequestWarnings::new(false, None),
+            system_prompt,
+            chat_history,
+            tools,
+            stream,
+        )
     }
 
-    async fn prompt_with_tools_with_status(
+    fn prompt_stream_events_with_options(
         &self,
-        tx: tokio::sync::mpsc::Sender<String>,
-        system_prompt: &str,
+        system_prompt: String,
         chat_history: Vec<Message>,
-        tools: Vec<Tool>,
-    ) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
-        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
-            .await
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        event_stream(move |slot| async move {
+            self.stream_reply(chat_history, system_prompt, &options, slot)
+                .await
+        })
     }
 
     /// Execute a non-streaming request and return the assistant response once
@@ -545,47 +1015,106 @@ impl Prompt for OpenAIClient {
     ///
     /// * `system_prompt` – system role content captured alongside the message.
     /// * `chat_history` – prior conversation turns to include in the request.
-    async fn prompt(
+    async fn prompt_with_options(
         &self,
         system_prompt: String,
         chat_history: Vec<Message>,
-    ) -> Result<Message, Box<dyn std::error::Error>> {
-        let response = self
-            .build_request(system_prompt.clone(), chat_history, None, false)
-            .send()
-            .await?;
-
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(self.api.provider())?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api, &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let recorder = LatencyRecorder::start();
         // NOTE: I guess anthropic's response doesn't work with `.json()`?
-        let body = response.text().await?;
+        let api = self.api.clone();
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (body, request_snapshot, request_id) = cancellable(
+            options.cancellation_or(self.options.cancellation.as_ref()),
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    send_logged(
+                        self.options.event_log.as_ref(),
+                        &api,
+                        self.request_to(
+                            options,
+                            &mut warnings,
+                            system_prompt.clone(),
+                            chat_history.clone(),
+                            None,
+                            false,
+                        ),
+                        self.options.request_snapshot,
+                        self.options.max_request_bytes,
+                    )
+                },
+            ),
+        )
+        .await?;
+        let latency = recorder.finish();
 
         let response_json: serde_json::Value = serde_json::from_str(&body)?;
 
         let mut content = self.read_json_response(&response_json)?;
+        let citations = Citations::from_response(&response_json);
 
         content = unescape(&content);
         if content.starts_with("\"") && content.ends_with("\"") {
             content = content[1..content.len() - 1].to_string();
         }
 
-        Ok(Message {
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let content = cap.truncate(content);
+
+        let message = Message {
             message_type: MessageType::Assistant,
             content,
-            api: crate::api::API::OpenAI(self.model.clone()),
-            system_prompt: system_prompt,
+            api: self.api.clone(),
+            system_prompt,
             tool_calls: None,
             tool_call_id: None,
             name: None,
-            // TODO: Implement
-            input_tokens: 0,
-            output_tokens: 0,
-        })
+            input_tokens: response_json["usage"]["prompt_tokens"]
+                .as_u64()
+                .unwrap_or(0) as usize,
+            output_tokens: response_json["usage"]["completion_tokens"]
+                .as_u64()
+                .unwrap_or(0) as usize,
+            metadata: MessageMetadata {
+                latency: Some(latency),
+                truncated: cap.truncation(),
+                sequence: None,
+                content_bytes: None,
+                route: None,
+                truncated_stream: None,
+                request_snapshot,
+                request_id,
+                service_tier: None,
+                tool_invocation: None,
+                synthesized_call_ids: Default::default(),
+                warnings: warnings.to_vec(),
+                citations,
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        Ok(options.post_process(message)?)
     }
 
     /// Extract the assistant message content from OpenAI's JSON response body.
-    fn read_json_response(
-        &self,
-        response_json: &serde_json::Value,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
         response_json
             .get("choices")
             .and_then(|v| v.get(0))
@@ -593,51 +1122,645 @@ impl Prompt for OpenAIClient {
             .and_then(|v| v.get("content"))
             .and_then(|v| v.as_str())
             .map(|s| s.to_string())
-            .ok_or_else(|| "Missing 'choices[0].message.content'".into())
+            .ok_or(WireError::MissingField {
+                field: "choices[0].message.content",
+            })
+    }
+
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        Some(self)
+    }
+
+    fn raw_transport(&self) -> Option<&dyn RawTransport> {
+        Some(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolCapable for OpenAIClient {
+    async fn prompt_with_tools(
+        &self,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(None, system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn prompt_with_tools_with_status(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        self.prompt_with_tools_internal(Some(tx), system_prompt, chat_history, tools)
+            .await
+            .map(|result| result.messages)
+    }
+
+    async fn run_tool_loop(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        self.prompt_with_tools_internal(tx, system_prompt, chat_history, tools)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransport for OpenAIClient {
+    fn build_request_raw(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        stream: bool,
+    ) -> Result<String, WireError> {
+        self.raw_request_to(
+            &PromptOptions::default(),
+            &mut RequestWarnings::new(false, None),
+            system_prompt,
+            chat_history,
+            stream,
+        )
     }
 
     /// Process the chunked transfer stream returned by OpenAI's API, forwarding
     /// partial deltas while reconstructing the final assistant response.
     async fn process_stream(
         &self,
-        stream: TlsStream<TcpStream>,
+        stream: Box<dyn Read + Send>,
         tx: &tokio::sync::mpsc::Sender<String>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let reader = std::io::BufReader::new(stream);
-        let mut full_message = String::new();
+    ) -> Result<String, WireError> {
+        let mut content = StreamedContent::new(tx);
+        self.read_stream(
+            ByteStream::read_raw(stream),
+            &mut LatencyRecorder::start(),
+            &mut ContentCap::new(None),
+            &mut DeltaSequencer::new(),
+            &mut content,
+            &mut None,
+            &mut (0, 0),
+            &mut BTreeMap::new(),
+            false,
+        )
+        .await?;
+
+        Ok(content.finish())
+    }
+}
+
+/// Where a provider serving OpenAI's API takes requests: the host and path
+/// its client sends to, and the provider whose `key_var` holds the key.
+#[cfg(any(
+    feature = "groq",
+    feature = "openrouter",
+    feature = "together",
+    feature = "fireworks",
+    feature = "perplexity"
+))]
+pub(crate) struct OpenAICompatible {
+    pub host: &'static str,
+    pub path: &'static str,
+    pub provider: Provider,
+}
+
+#[cfg(any(
+    feature = "groq",
+    feature = "openrouter",
+    feature = "together",
+    feature = "fireworks",
+    feature = "perplexity"
+))]
+impl OpenAICompatible {
+    /// An `OpenAIClient` sending `api`'s model to this endpoint.
+    pub(crate) fn client(&self, api: API, options: ClientOptions) -> OpenAIClient {
+        let var = self
+            .provider
+            .key_var()
+            .expect("OpenAI-compatible providers take a key");
+        OpenAIClient::build(
+            api,
+            self.host,
+            self.path,
+            Credentials::from_env(var),
+            options,
+        )
+    }
+}
+
+/// Declare `$client`, a client for `$model` that hands all of its work to the
+/// `OpenAIClient` in its `openai` field, with `new` and `with_path`. The
+/// module declaring it writes `with_options`, which builds that client.
+///
+/// Prompts, streams, tool loops and the raw transport of every such client
+/// behave as they do for OpenAI, so the module docs only say what differs.
+/// `ClientOptions::with_thinking_level` has no effect on any of them; only
+/// GPT-5 takes a reasoning effort, and the option is reported as ignored.
+#[cfg(any(
+    feature = "groq",
+    feature = "openrouter",
+    feature = "together",
+    feature = "fireworks",
+    feature = "perplexity",
+    feature = "compatible"
+))]
+macro_rules! openai_compatible_client {
+    ($(#[$attr:meta])* $client:ident($model:ty), key: $key_doc:literal) => {
+        $(#[$attr])*
+        pub struct $client {
+            pub model: $model,
+            /// The client doing the work. Its public fields configure this one
+            /// as they would an OpenAI client.
+            pub openai: $crate::openai::OpenAIClient,
+        }
+
+        impl $client {
+            /// Construct a new client using default transport settings.
+            pub fn new<M>(model: M) -> Self
+            where
+                M: Into<$model>,
+            {
+                Self::with_options(model, $crate::config::ClientOptions::default())
+            }
+
+            /// Send requests to another path on the same origin.
+            pub fn with_path(mut self, path: impl Into<String>) -> Self {
+                self.openai = self.openai.with_path(path);
+                self
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::api::PromptCore for $client {
+            #[doc = $key_doc]
+            fn get_auth_token(&self) -> Result<String, $crate::error::WireError> {
+                self.openai.get_auth_token()
+            }
+
+            fn refresh_credentials(&self) {
+                self.openai.refresh_credentials();
+            }
+
+            fn clock(&self) -> &dyn $crate::clock::Clock {
+                self.openai.clock()
+            }
+
+            fn event_log(&self) -> Option<&$crate::event_log::EventLog> {
+                self.openai.event_log()
+            }
+
+            fn deny_warnings(&self) -> bool {
+                self.openai.deny_warnings()
+            }
+
+            fn new_message(&self, content: String) -> $crate::types::MessageBuilder {
+                self.openai.new_message(content)
+            }
+
+            fn build_request(
+                &self,
+                system_prompt: String,
+                chat_history: Vec<$crate::types::Message>,
+                tools: Option<&[$crate::types::ToolSpec]>,
+                stream: bool,
+            ) -> Result<reqwest::RequestBuilder, $crate::error::WireError> {
+                self.openai
+                    .build_request(system_prompt, chat_history, tools, stream)
+            }
+
+            async fn prompt_with_options(
+                &self,
+                system_prompt: String,
+                chat_history: Vec<$crate::types::Message>,
+                options: &$crate::config::PromptOptions,
+            ) -> Result<$crate::types::Message, $crate::error::WireError> {
+                self.openai
+                    .prompt_with_options(system_prompt, chat_history, options)
+                    .await
+            }
 
-        for line in reader.lines() {
-            let line = line?;
+            fn prompt_stream_events_with_options(
+                &self,
+                system_prompt: String,
+                chat_history: Vec<$crate::types::Message>,
+                options: &$crate::config::PromptOptions,
+            ) -> $crate::api::EventStream<'_> {
+                self.openai
+                    .prompt_stream_events_with_options(system_prompt, chat_history, options)
+            }
+
+            fn read_json_response(
+                &self,
+                response_json: &serde_json::Value,
+            ) -> Result<String, $crate::error::WireError> {
+                self.openai.read_json_response(response_json)
+            }
+
+            fn tools(&self) -> Option<&dyn $crate::api::ToolCapable> {
+                Some(self)
+            }
+
+            fn raw_transport(&self) -> Option<&dyn $crate::api::RawTransport> {
+                Some(self)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::api::ToolCapable for $client {
+            async fn prompt_with_tools(
+                &self,
+                system_prompt: &str,
+                chat_history: Vec<$crate::types::Message>,
+                tools: Vec<$crate::types::Tool>,
+            ) -> Result<Vec<$crate::types::Message>, $crate::error::WireError> {
+                self.openai
+                    .prompt_with_tools(system_prompt, chat_history, tools)
+                    .await
+            }
+
+            async fn prompt_with_tools_with_status(
+                &self,
+                tx: tokio::sync::mpsc::Sender<String>,
+                system_prompt: &str,
+                chat_history: Vec<$crate::types::Message>,
+                tools: Vec<$crate::types::Tool>,
+            ) -> Result<Vec<$crate::types::Message>, $crate::error::WireError> {
+                self.openai
+                    .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
+                    .await
+            }
+
+            async fn run_tool_loop(
+                &self,
+                tx: Option<tokio::sync::mpsc::Sender<String>>,
+                system_prompt: &str,
+                chat_history: Vec<$crate::types::Message>,
+                tools: Vec<$crate::types::Tool>,
+            ) -> Result<$crate::tool_loop::ToolLoopResult, $crate::error::WireError> {
+                self.openai
+                    .run_tool_loop(tx, system_prompt, chat_history, tools)
+                    .await
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::api::RawTransport for $client {
+            fn build_request_raw(
+                &self,
+                system_prompt: String,
+                chat_history: Vec<$crate::types::Message>,
+                stream: bool,
+            ) -> Result<String, $crate::error::WireError> {
+                self.openai
+                    .build_request_raw(system_prompt, chat_history, stream)
+            }
+
+            async fn process_stream(
+                &self,
+                stream: Box<dyn std::io::Read + Send>,
+                tx: &tokio::sync::mpsc::Sender<String>,
+            ) -> Result<String, $crate::error::WireError> {
+                self.openai.process_stream(stream, tx).await
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "groq",
+    feature = "openrouter",
+    feature = "together",
+    feature = "fireworks",
+    feature = "perplexity",
+    feature = "compatible"
+))]
+pub(crate) use openai_compatible_client;
+
+impl OpenAIClient {
+    /// Execute a streaming request against OpenAI, leaving each event in
+    /// `slot` as it arrives, and return the assembled reply.
+    ///
+    /// * `chat_history` – context messages that precede the new completion.
+    /// * `system_prompt` – system role text included at the start of the request.
+    /// * `slot` – where `prompt_stream_events_with_options` picks events up.
+    async fn stream_reply(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        options: &PromptOptions,
+        slot: EventSlot,
+    ) -> Result<Message, WireError> {
+        options.check_service_tier(self.api.provider())?;
+        self.credentials.require()?;
+        check_incoming(self.options.moderator.as_ref(), &chat_history, options).await?;
+        let mut warnings = self.request_warnings(options)?;
+        let chat_history =
+            warnings.normalize(&self.api, &chat_history, self.options.history_strictness)?;
+        let system_prompt = options.system_prompt(system_prompt);
+        check_outbound(self.options.sanitize_policy, &system_prompt, &chat_history)?;
+        let estimated_tokens = estimate_tokens(&system_prompt, &chat_history);
+        let (request, request_snapshot) = prepare_stream(
+            self.options.event_log.as_ref(),
+            &self.api,
+            self.request_to(
+                options,
+                &mut warnings,
+                system_prompt.clone(),
+                chat_history,
+                None,
+                true,
+            ),
+            self.options.request_snapshot,
+            self.options.max_request_bytes,
+        )?;
+
+        let mut recorder = LatencyRecorder::start();
+        let mut cap = ContentCap::new(options.max_response_bytes);
+        let mut sequencer = DeltaSequencer::new();
+        let mut content = StreamedContent::for_prompt(options, slot);
+        let mut citations = None;
+        let mut tokens = (0, 0);
+        let mut calls = BTreeMap::new();
+        let cancellation = options.cancellation_or(self.options.cancellation.as_ref());
+        let body = cancellable(
+            cancellation,
+            with_retries(
+                self.options.retry_policy.as_ref(),
+                self.options.rate_limiter.as_ref(),
+                estimated_tokens,
+                &self.options.clock,
+                None,
+                || {
+                    open_stream(
+                        &self.stream_client,
+                        &request,
+                        self.options.max_redirects,
+                        self.options.stream_idle_timeout,
+                    )
+                },
+            ),
+        )
+        .await?
+        .with_cancellation(cancellation);
+        let request_id = body.request_id().map(str::to_string);
+        let truncated_stream = self
+            .read_stream(
+                body,
+                &mut recorder,
+                &mut cap,
+                &mut sequencer,
+                &mut content,
+                &mut citations,
+                &mut tokens,
+                &mut calls,
+                options.strict_stream_end,
+            )
+            .await
+            .map_err(|err| {
+                err.with_partial(|| content.partial())
+                    .with_request_id(request_id.as_deref())
+            })?;
+        warnings.stream_truncated(self.api.provider(), truncated_stream.as_ref())?;
+        let content_bytes = content.bytes();
+        let (input_tokens, output_tokens) = tokens;
+        let mut tool_calls: Vec<FunctionCall> = calls.into_values().collect();
+        let synthesized_call_ids = synthesize_call_ids(&mut tool_calls, 0);
+        let (message_type, tool_calls) = if tool_calls.is_empty() {
+            (MessageType::Assistant, None)
+        } else {
+            (MessageType::FunctionCall, Some(tool_calls))
+        };
+
+        let message = Message {
+            message_type,
+            content: content.finish(),
+            api: self.api.clone(),
+            system_prompt: system_prompt.to_string(),
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+            input_tokens,
+            output_tokens,
+            metadata: MessageMetadata {
+                latency: Some(recorder.finish()),
+                truncated: cap.truncation(),
+                sequence: Some(sequencer.finish()),
+                content_bytes: Some(content_bytes),
+                route: None,
+                truncated_stream,
+                request_snapshot,
+                request_id,
+                service_tier: None,
+                tool_invocation: None,
+                synthesized_call_ids,
+                warnings: warnings.to_vec(),
+                citations,
+            },
+        };
+
+        report_metrics(&self.options.metrics_callback, &message);
+        emit(self.options.event_log.as_ref(), || WireEvent::Message {
+            message: message.clone(),
+        });
+        options.post_process(message)
+    }
+
+    /// Parse OpenAI's server-sent events from `body`, forwarding each content
+    /// delta into `content` and noting its arrival on `recorder`.
+    /// Tool calls, reasoning, usage and the finish reason are reported to
+    /// `content` as `StreamEvent`s. The last chunk's `citations`, if any
+    /// carries them, go to `citations`, and the usage chunk's counts to
+    /// `tokens`. Tool call fragments are pieced together in `calls`, keyed by
+    /// their index.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_stream(
+        &self,
+        mut body: ByteStream,
+        recorder: &mut LatencyRecorder,
+        cap: &mut ContentCap,
+        sequencer: &mut DeltaSequencer,
+        content: &mut StreamedContent,
+        citations: &mut Option<Citations>,
+        tokens: &mut (usize, usize),
+        calls: &mut BTreeMap<usize, FunctionCall>,
+        strict: bool,
+    ) -> Result<Option<TruncatedStream>, WireError> {
+        let mut line = String::new();
+
+        while body.read_line_into(&mut line).await? {
             if !line.starts_with("data: ") {
                 continue;
             }
-
-            println!("{}", line);
+            emit(self.options.event_log.as_ref(), || WireEvent::StreamData {
+                data: line.clone(),
+            });
 
             let payload = line[6..].trim();
             if payload.is_empty() || payload == "[DONE]" {
                 break;
             }
 
-            let response_json: serde_json::Value = match serde_json::from_str(&payload) {
-                Ok(json) => json,
-                Err(e) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        e.to_string(),
-                    )));
-                }
+            let response_json = match parse_event(&mut body, payload, strict).await? {
+                ParsedEvent::Json(json) => json,
+                ParsedEvent::Truncated(truncated) => return Ok(Some(truncated)),
             };
 
-            let mut delta = unescape(&response_json["choices"][0]["delta"]["content"].to_string());
-            if delta != "null" {
-                delta = delta[1..delta.len() - 1].to_string();
-                tx.send(delta.clone()).await?;
+            if let Some(found) = Citations::from_response(&response_json) {
+                *citations = Some(found);
+            }
+
+            let choice = &response_json["choices"][0];
+            for call in choice["delta"]["tool_calls"]
+                .as_array()
+                .into_iter()
+                .flatten()
+            {
+                let index = call["index"].as_u64().unwrap_or(0) as usize;
+                let entry = calls.entry(index).or_insert_with(|| FunctionCall {
+                    id: String::new(),
+                    call_type: "function".to_string(),
+                    function: Function {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+                if let Some(id) = call["id"].as_str() {
+                    entry.id = id.to_string();
+                }
+                if let Some(name) = call["function"]["name"].as_str() {
+                    entry.function.name = name.to_string();
+                }
+
+                // Only a call's first chunk names it
+                if let (Some(id), Some(name)) =
+                    (call["id"].as_str(), call["function"]["name"].as_str())
+                {
+                    let start = StreamEventKind::ToolCallStart {
+                        index,
+                        id: id.to_string(),
+                        name: name.to_string(),
+                    };
+                    content.report(start).await?;
+                }
+                if let Some(arguments) = call["function"]["arguments"].as_str() {
+                    entry.function.arguments.push_str(arguments);
+                    if !arguments.is_empty() {
+                        let delta = StreamEventKind::ToolCallArgumentsDelta {
+                            index,
+                            delta: arguments.to_string(),
+                        };
+                        content.report(delta).await?;
+                    }
+                }
+            }
+
+            // What compatible servers running reasoning models call it
+            if let Some(thinking) = choice["delta"]["reasoning_content"].as_str() {
+                if !thinking.is_empty() {
+                    content
+                        .report(StreamEventKind::Thinking(thinking.to_string()))
+                        .await?;
+                }
+            }
+
+            if let Some(delta) = choice["delta"]["content"].as_str() {
+                recorder.record_delta();
 
-                full_message.push_str(&delta);
+                let kept = cap.truncate(delta.to_string());
+                if !kept.is_empty() {
+                    content.forward(kept).await?;
+                    sequencer.record(0);
+                }
+
+                // Dropping `body` on return closes the connection
+                if cap.exceeded() {
+                    break;
+                }
+            }
+
+            if let Some(usage) = response_json["usage"].as_object() {
+                let count = |field: &str| usage.get(field).and_then(|v| v.as_u64()).unwrap_or(0);
+                *tokens = (
+                    count("prompt_tokens") as usize,
+                    count("completion_tokens") as usize,
+                );
+                let usage = StreamEventKind::Usage {
+                    input: tokens.0,
+                    output: tokens.1,
+                };
+                content.report(usage).await?;
+            }
+
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                let done = StreamEventKind::Done {
+                    finish_reason: FinishReason::from_provider(reason),
+                };
+                content.report(done).await?;
             }
         }
 
-        Ok(full_message)
+        Ok(None)
     }
 }
+
+/// Rewrite a JSON schema for OpenAI's strict function calling: every object
+/// gets `additionalProperties: false` and lists all of its properties as
+/// required, at any depth.
+fn strict_schema(schema: &serde_json::Value) -> serde_json::Value {
+    let serde_json::Value::Object(schema) = schema else {
+        return schema.clone();
+    };
+
+    let mut strict = serde_json::Map::new();
+    for (key, value) in schema {
+        let value = match key.as_str() {
+            "properties" | "$defs" | "definitions" => match value {
+                serde_json::Value::Object(entries) => serde_json::Value::Object(
+                    entries
+                        .iter()
+                        .map(|(name, entry)| (name.clone(), strict_schema(entry)))
+                        .collect(),
+                ),
+                other => other.clone(),
+            },
+            "items" => strict_schema(value),
+            "anyOf" | "oneOf" | "allOf" => match value {
+                serde_json::Value::Array(variants) => {
+                    serde_json::Value::Array(variants.iter().map(strict_schema).collect())
+                }
+                other => other.clone(),
+            },
+            _ => value.clone(),
+        };
+        strict.insert(key.clone(), value);
+    }
+
+    let is_object = strict.get("type").and_then(|t| t.as_str()) == Some("object")
+        || strict.contains_key("properties");
+    if is_object {
+        // Keep the schema's own `required` order and append the rest
+        let mut required: Vec<serde_json::Value> = strict
+            .get("required")
+            .and_then(|required| required.as_array())
+            .cloned()
+            .unwrap_or_default();
+        if let Some(properties) = strict.get("properties").and_then(|p| p.as_object()) {
+            for name in properties.keys() {
+                if !required.iter().any(|r| r == name) {
+                    required.push(name.clone().into());
+                }
+            }
+        }
+
+        strict.insert("required".to_string(), required.into());
+        strict.insert("additionalProperties".to_string(), false.into());
+    }
+
+    serde_json::Value::Object(strict)
+}
@@ -0,0 +1,126 @@
+//! OpenRouter, which routes OpenAI-style chat completions to models from
+//! many providers.
+//!
+//! `OpenRouterClient` is an `OpenAIClient` pointed at
+//! `openrouter.ai/api/v1/chat/completions`, keyed by `OPENROUTER_API_KEY`,
+//! whose requests name an `OpenRouterModel` and whose replies carry
+//! `API::OpenRouter`. `ClientOptions::with_openrouter_attribution` and
+//! `with_openrouter_provider` add OpenRouter's own headers and routing
+//! preferences.
+
+use crate::api::{OpenRouterModel, Provider, API};
+use crate::config::ClientOptions;
+use crate::openai::{openai_compatible_client, OpenAICompatible};
+
+impl OpenRouterModel {
+    /// A model as OpenRouter names it, e.g. `anthropic/claude-sonnet-4`.
+    /// Any id is accepted; OpenRouter decides whether it serves the model.
+    pub fn new(name: impl Into<String>) -> Self {
+        OpenRouterModel(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolve an `openrouter/<model>` identifier. The prefix is required
+    /// here, since ids such as `openai/gpt-4o` would otherwise be ambiguous.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model.strip_prefix("openrouter/") {
+            Some(name) if !name.is_empty() => Ok(OpenRouterModel::new(name)),
+            _ => Err(format!("Unknown OpenRouter model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model without its prefix.
+    pub fn to_strings(&self) -> (String, String) {
+        ("openrouter".to_string(), self.0.clone())
+    }
+}
+
+/// Takes the model with or without its `openrouter/` prefix.
+impl<'a> From<&'a str> for OpenRouterModel {
+    fn from(model: &'a str) -> Self {
+        OpenRouterModel::new(model.strip_prefix("openrouter/").unwrap_or(model))
+    }
+}
+
+impl From<String> for OpenRouterModel {
+    fn from(model: String) -> Self {
+        OpenRouterModel::from(model.as_str())
+    }
+}
+
+/// Where OpenRouter serves chat completions.
+const OPENROUTER: OpenAICompatible = OpenAICompatible {
+    host: "openrouter.ai",
+    path: "/api/v1/chat/completions",
+    provider: Provider::OpenRouter,
+};
+
+openai_compatible_client! {
+    /// Client for OpenRouter's OpenAI-compatible chat completions endpoint.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "openrouter/anthropic/claude-sonnet-4",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::config::ProviderPreferences;
+    /// use wire::openrouter::OpenRouterClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let options = options
+    ///     .with_openrouter_attribution("https://example.com", "Example App")
+    ///     .with_openrouter_provider(ProviderPreferences {
+    ///         order: vec!["anthropic".to_string()],
+    ///         allow_fallbacks: Some(false),
+    ///         ..Default::default()
+    ///     });
+    /// let client = OpenRouterClient::with_options("anthropic/claude-sonnet-4", options);
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// # });
+    /// ```
+    OpenRouterClient(OpenRouterModel),
+    key: "The OpenRouter API key, as read when the client was built or refreshed."
+}
+
+impl OpenRouterClient {
+    /// Construct a client with custom transport settings.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<OpenRouterModel>,
+    {
+        let model = model.into();
+        let openrouter = options.openrouter.clone();
+        let mut openai = OPENROUTER.client(API::OpenRouter(model.clone()), options);
+
+        if let Some(referer) = openrouter.referer {
+            openai.compatible_headers.push(("HTTP-Referer", referer));
+        }
+        if let Some(title) = openrouter.title {
+            openai.compatible_headers.push(("X-Title", title));
+        }
+        if let Some(provider) = openrouter.provider {
+            openai.compatible_body.insert(
+                "provider".to_string(),
+                serde_json::to_value(provider).expect("provider preferences serialize"),
+            );
+        }
+
+        Self { model, openai }
+    }
+}
@@ -0,0 +1,214 @@
+//! Patterns that combine several clients into one answer.
+//!
+//! `cross_check` asks two clients the same question at the same time, then
+//! hands both answers to a third client to adjudicate. The clients can be any
+//! mix of providers:
+//!
+//! ```no_run
+//! use wire::orchestrate::cross_check;
+//! use wire::types::MessageType;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let primary = wire::new_client("gpt-4o").unwrap();
+//! let secondary = wire::new_client("claude-3-5-sonnet-20241022").unwrap();
+//! let judge = wire::new_client("gemini-2.0-flash").unwrap();
+//!
+//! let question = primary
+//!     .new_message("When did the Berlin Wall fall?".to_string())
+//!     .message_type(MessageType::User)
+//!     .build();
+//! let result = cross_check(
+//!     primary.as_ref(),
+//!     secondary.as_ref(),
+//!     judge.as_ref(),
+//!     "Answer concisely.".to_string(),
+//!     vec![question],
+//! )
+//! .await
+//! .unwrap();
+//!
+//! println!("{}", result.verdict.content);
+//! # });
+//! ```
+
+use crate::api::PromptCore;
+use crate::error::WireError;
+use crate::types::{Message, MessageType};
+
+/// System prompt given to the judge by default.
+pub const DEFAULT_JUDGE_SYSTEM_PROMPT: &str =
+    "You review answers written by other assistants and decide which one is correct.";
+
+/// Judging prompt used by default. See `CrossCheckOptions::judge_template`
+/// for the placeholders.
+pub const DEFAULT_JUDGE_TEMPLATE: &str = "Two assistants were asked the same question.
+
+Question:
+{question}
+
+Answer A:
+{first_answer}
+
+Answer B:
+{second_answer}
+
+Say which answer is more accurate and why, and point out any claim the two disagree on.";
+
+/// How `cross_check_with_options` prompts the judge.
+#[derive(Clone, Debug)]
+pub struct CrossCheckOptions {
+    pub judge_system_prompt: String,
+    /// The judge's only message. `{question}` is replaced with the last user
+    /// message of the history, `{first_answer}` and `{second_answer}` with
+    /// the two replies.
+    pub judge_template: String,
+}
+
+impl Default for CrossCheckOptions {
+    fn default() -> Self {
+        Self {
+            judge_system_prompt: DEFAULT_JUDGE_SYSTEM_PROMPT.to_string(),
+            judge_template: DEFAULT_JUDGE_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl CrossCheckOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_judge_system_prompt(mut self, judge_system_prompt: impl Into<String>) -> Self {
+        self.judge_system_prompt = judge_system_prompt.into();
+        self
+    }
+
+    pub fn with_judge_template(mut self, judge_template: impl Into<String>) -> Self {
+        self.judge_template = judge_template.into();
+        self
+    }
+}
+
+/// Token usage summed over several messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl Usage {
+    pub fn of<'a>(messages: impl IntoIterator<Item = &'a Message>) -> Self {
+        messages
+            .into_iter()
+            .fold(Self::default(), |usage, message| Self {
+                input_tokens: usage.input_tokens + message.input_tokens,
+                output_tokens: usage.output_tokens + message.output_tokens,
+            })
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Everything `cross_check` produced.
+#[derive(Clone, Debug)]
+pub struct CrossCheckResult {
+    /// The primary's and the secondary's replies, in that order.
+    pub answers: [Message; 2],
+    pub verdict: Message,
+    /// Usage of all three calls.
+    pub usage: Usage,
+}
+
+/// Ask `primary` and `secondary` concurrently, then have `judge` compare
+/// their answers using the default judging prompt.
+pub async fn cross_check(
+    primary: &dyn PromptCore,
+    secondary: &dyn PromptCore,
+    judge: &dyn PromptCore,
+    system_prompt: String,
+    chat_history: Vec<Message>,
+) -> Result<CrossCheckResult, WireError> {
+    cross_check_with_options(
+        primary,
+        secondary,
+        judge,
+        system_prompt,
+        chat_history,
+        &CrossCheckOptions::default(),
+    )
+    .await
+}
+
+/// `cross_check` with a custom judging prompt. Fails if any of the three
+/// calls fails.
+pub async fn cross_check_with_options(
+    primary: &dyn PromptCore,
+    secondary: &dyn PromptCore,
+    judge: &dyn PromptCore,
+    system_prompt: String,
+    chat_history: Vec<Message>,
+    options: &CrossCheckOptions,
+) -> Result<CrossCheckResult, WireError> {
+    let question = chat_history
+        .iter()
+        .rev()
+        .find(|message| message.message_type == MessageType::User)
+        .map(|message| message.content.clone())
+        .unwrap_or_default();
+
+    let (first, second) = tokio::join!(
+        primary.prompt(system_prompt.clone(), chat_history.clone()),
+        secondary.prompt(system_prompt, chat_history),
+    );
+    let answers = [first?, second?];
+
+    let judging = render(
+        &options.judge_template,
+        &[
+            ("{question}", &question),
+            ("{first_answer}", &answers[0].content),
+            ("{second_answer}", &answers[1].content),
+        ],
+    );
+    let request = judge
+        .new_message(judging)
+        .message_type(MessageType::User)
+        .build();
+    let verdict = judge
+        .prompt(options.judge_system_prompt.clone(), vec![request])
+        .await?;
+
+    Ok(CrossCheckResult {
+        usage: Usage::of(answers.iter().chain([&verdict])),
+        answers,
+        verdict,
+    })
+}
+
+/// Substitute `values` into `template` in one pass, so an answer that happens
+/// to contain a placeholder is left as written.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match values.iter().find(|(name, _)| rest.starts_with(name)) {
+            Some((name, value)) => {
+                rendered.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
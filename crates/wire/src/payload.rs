@@ -0,0 +1,105 @@
+//! How request bodies become bytes.
+//!
+//! The clients build bodies as `serde_json::Value`s and serialize them here.
+//! Object keys come out sorted at every level, whatever order the builder
+//! inserted them in and whether or not `serde_json`'s `preserve_order`
+//! feature is enabled, so the same request always produces the same bytes.
+//! Proxies that sign request bodies and `hash::request_fingerprint` both rely
+//! on that.
+//!
+//! `JsonFormat::Compact` (the default) is the canonical form: no whitespace.
+//! `JsonFormat::Pretty` keeps the key order but indents, for reading bodies in
+//! proxy or debug logs.
+
+/// Layout of serialized request bodies; see `ClientOptions::with_json_format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    #[default]
+    Compact,
+    Pretty,
+}
+
+/// A copy of `value` with the keys of every object in sorted order.
+pub fn canonicalize(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonicalize(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(canonicalize).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+/// `value` with sorted keys, laid out as `format` says.
+pub fn to_string(value: &serde_json::Value, format: JsonFormat) -> String {
+    let value = canonicalize(value);
+    let json = match format {
+        JsonFormat::Compact => serde_json::to_string(&value),
+        JsonFormat::Pretty => serde_json::to_string_pretty(&value),
+    };
+
+    json.expect("a JSON value always serializes")
+}
+
+/// The canonical form: compact, with sorted keys.
+pub fn to_canonical_string(value: &serde_json::Value) -> String {
+    to_string(value, JsonFormat::Compact)
+}
+
+/// Merge `extra` into `body`, as for `PromptOptions::with_extra_body`.
+/// Objects merge key by key at every level. Any other value already in
+/// `body` is kept, and its path (e.g. `generationConfig.maxOutputTokens`)
+/// is returned.
+pub fn merge(
+    body: &mut serde_json::Value,
+    extra: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    merge_at(body, extra, "", &mut conflicts);
+    conflicts
+}
+
+fn merge_at(
+    body: &mut serde_json::Value,
+    extra: &serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    conflicts: &mut Vec<String>,
+) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+
+    for (key, value) in extra {
+        let path = format!("{}{}", prefix, key);
+        match (body.get_mut(key), value) {
+            (None, value) => {
+                body.insert(key.clone(), value.clone());
+            }
+            (Some(existing @ serde_json::Value::Object(_)), serde_json::Value::Object(value)) => {
+                merge_at(existing, value, &format!("{}.", path), conflicts);
+            }
+            (Some(_), _) => conflicts.push(path),
+        }
+    }
+}
+
+/// Attach `body` to `request` as JSON serialized in `format`.
+pub(crate) fn json_body(
+    request: reqwest::RequestBuilder,
+    body: &serde_json::Value,
+    format: JsonFormat,
+) -> reqwest::RequestBuilder {
+    request
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(to_string(body, format))
+}
@@ -0,0 +1,142 @@
+//! Perplexity, whose Sonar models answer from a web search and cite their
+//! sources.
+//!
+//! `PerplexityClient` is an `OpenAIClient` pointed at
+//! `api.perplexity.ai/chat/completions`, keyed by `PERPLEXITY_API_KEY`, whose
+//! requests name a `PerplexityModel` and whose replies carry
+//! `API::Perplexity`. Perplexity sends the `citations` and `search_results`
+//! behind a reply next to its `choices`; they end up in
+//! `MessageMetadata::citations`, for streams once the last chunk has
+//! arrived. Perplexity does not call tools, so a tool loop ends with its
+//! first reply.
+
+use crate::api::{PerplexityModel, Provider, API};
+use crate::config::ClientOptions;
+use crate::error::WireError;
+use crate::openai::{openai_compatible_client, OpenAICompatible};
+
+impl PerplexityModel {
+    /// Resolve a model identifier string into the strongly typed enum variant.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model {
+            "sonar" => Ok(PerplexityModel::Sonar),
+            "sonar-pro" => Ok(PerplexityModel::SonarPro),
+            "sonar-reasoning" => Ok(PerplexityModel::SonarReasoning),
+            "sonar-reasoning-pro" => Ok(PerplexityModel::SonarReasoningPro),
+            "sonar-deep-research" => Ok(PerplexityModel::SonarDeepResearch),
+            _ => Err(format!("Unknown Perplexity model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model as Perplexity names it.
+    pub fn to_strings(&self) -> (String, String) {
+        let model = match self {
+            PerplexityModel::Sonar => "sonar",
+            PerplexityModel::SonarPro => "sonar-pro",
+            PerplexityModel::SonarReasoning => "sonar-reasoning",
+            PerplexityModel::SonarReasoningPro => "sonar-reasoning-pro",
+            PerplexityModel::SonarDeepResearch => "sonar-deep-research",
+        };
+
+        ("perplexity".to_string(), model.to_string())
+    }
+}
+
+impl std::str::FromStr for PerplexityModel {
+    type Err = String;
+
+    fn from_str(model: &str) -> Result<Self, Self::Err> {
+        PerplexityModel::from_model_name(model)
+    }
+}
+
+impl TryFrom<&str> for PerplexityModel {
+    type Error = WireError;
+
+    fn try_from(model: &str) -> Result<Self, Self::Error> {
+        PerplexityModel::from_model_name(model).map_err(|reason| WireError::UnknownModel {
+            model: model.to_string(),
+            reason,
+        })
+    }
+}
+
+impl TryFrom<String> for PerplexityModel {
+    type Error = WireError;
+
+    fn try_from(model: String) -> Result<Self, Self::Error> {
+        PerplexityModel::try_from(model.as_str())
+    }
+}
+
+/// Where Perplexity serves chat completions.
+const PERPLEXITY: OpenAICompatible = OpenAICompatible {
+    host: "api.perplexity.ai",
+    path: "/chat/completions",
+    provider: Provider::Perplexity,
+};
+
+openai_compatible_client! {
+    /// Client for Perplexity's chat completions endpoint.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "sonar",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::perplexity::PerplexityClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let client = PerplexityClient::try_with_options("sonar", options).unwrap();
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// # });
+    /// ```
+    PerplexityClient(PerplexityModel),
+    key: "The Perplexity API key, as read when the client was built or refreshed."
+}
+
+impl PerplexityClient {
+    /// Construct a client with custom transport settings.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<PerplexityModel>,
+    {
+        let model = model.into();
+        let openai = PERPLEXITY.client(API::Perplexity(model.clone()), options);
+
+        Self { model, openai }
+    }
+
+    /// `new` for a model named by a string, failing with
+    /// `WireError::UnknownModel` when Perplexity has no such model.
+    pub fn try_new<M>(model: M) -> Result<Self, WireError>
+    where
+        M: TryInto<PerplexityModel>,
+        M::Error: Into<WireError>,
+    {
+        Self::try_with_options(model, ClientOptions::default())
+    }
+
+    /// `with_options` for a model named by a string.
+    pub fn try_with_options<M>(model: M, options: ClientOptions) -> Result<Self, WireError>
+    where
+        M: TryInto<PerplexityModel>,
+        M::Error: Into<WireError>,
+    {
+        let model = model.try_into().map_err(Into::into)?;
+        Ok(Self::with_options(model, options))
+    }
+}
@@ -0,0 +1,366 @@
+//! Reshaping a reply's content before it is returned.
+//!
+//! Callers often want part of a reply rather than all of it: the code in it,
+//! the text without its markdown, one value a pattern picks out. List the
+//! steps with `PromptOptions::with_post` and the client runs them, in order,
+//! on the final content of every prompt made with those options. Streamed
+//! deltas go out as they arrived; only the returned message changes.
+//!
+//! A step that can't find what it looks for fails the prompt with
+//! `error::WireError::PostProcess`, which carries the `PostError` and the
+//! content as the model wrote it, so the caller can retry with a nudge.
+//!
+//! ```
+//! use wire::post::{apply, Post, PostError};
+//!
+//! let reply = "Here you go:\n\n```rust\nfn main() {}\n```\n";
+//! let steps = [Post::ExtractFirstCodeBlock { lang: Some("rust".to_string()) }, Post::Trim];
+//! assert_eq!(apply(&steps, reply).unwrap(), "fn main() {}");
+//!
+//! let steps = [Post::ExtractFirstCodeBlock { lang: Some("python".to_string()) }];
+//! assert_eq!(
+//!     apply(&steps, reply),
+//!     Err(PostError::NoCodeBlock { lang: Some("python".to_string()) })
+//! );
+//! ```
+
+use std::fmt;
+
+/// One step of post-processing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Post {
+    /// The body of the first fenced code block, or of the first whose info
+    /// string starts with `lang` (compared case-insensitively) when given.
+    /// A block left open runs to the end of the content.
+    ExtractFirstCodeBlock { lang: Option<String> },
+    /// Markdown down to its text: heading, quote and list markers, emphasis,
+    /// inline code ticks, fences and rules go; links and images keep their
+    /// text.
+    MarkdownToText,
+    /// What capture group `group` of the first match of `pattern` matched;
+    /// group 0 is the whole match.
+    RegexCapture { pattern: String, group: usize },
+    /// Leading and trailing whitespace removed.
+    Trim,
+}
+
+/// Why a step could not produce its output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PostError {
+    /// No fenced code block, or none tagged `lang`.
+    NoCodeBlock { lang: Option<String> },
+    /// `pattern` did not match, or matched without `group` taking part.
+    NoMatch { pattern: String, group: usize },
+    /// `pattern` is not a valid regular expression, or could not be run.
+    InvalidPattern { pattern: String, reason: String },
+}
+
+impl fmt::Display for PostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostError::NoCodeBlock { lang: None } => write!(f, "no code block found"),
+            PostError::NoCodeBlock { lang: Some(lang) } => {
+                write!(f, "no {} code block found", lang)
+            }
+            PostError::NoMatch { pattern, group: 0 } => {
+                write!(f, "no match for /{}/", pattern)
+            }
+            PostError::NoMatch { pattern, group } => {
+                write!(f, "no match for group {} of /{}/", group, pattern)
+            }
+            PostError::InvalidPattern { pattern, reason } => {
+                write!(f, "invalid pattern /{}/: {}", pattern, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PostError {}
+
+/// `content` run through `steps` in order.
+pub fn apply(steps: &[Post], content: &str) -> Result<String, PostError> {
+    let mut content = content.to_string();
+    for step in steps {
+        content = match step {
+            Post::ExtractFirstCodeBlock { lang } => {
+                extract_code_block(&content, lang.as_deref())
+                    .ok_or_else(|| PostError::NoCodeBlock { lang: lang.clone() })?
+            }
+            Post::MarkdownToText => markdown_to_text(&content),
+            Post::RegexCapture { pattern, group } => capture(&content, pattern, *group)?,
+            Post::Trim => content.trim().to_string(),
+        };
+    }
+
+    Ok(content)
+}
+
+/// An opening or closing fence: the fence character, its run length and
+/// the info string after it.
+struct Fence<'a> {
+    marker: char,
+    length: usize,
+    info: &'a str,
+}
+
+/// `line` as a fence, if it is one: three or more backticks or tildes after
+/// at most three spaces. Backtick fences can't have backticks in their info
+/// string.
+fn fence(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = rest.len() - rest.trim_start_matches(marker).len();
+    let info = rest[length..].trim();
+    if length < 3 || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+
+    Some(Fence {
+        marker,
+        length,
+        info,
+    })
+}
+
+/// Whether `line` closes a block opened by `open`: the same character, at
+/// least as many of it, and nothing after.
+fn closes(line: &str, open: &Fence<'_>) -> bool {
+    fence(line).is_some_and(|close| {
+        close.marker == open.marker && close.length >= open.length && close.info.is_empty()
+    })
+}
+
+fn extract_code_block(content: &str, lang: Option<&str>) -> Option<String> {
+    let mut lines = content.split_inclusive('\n');
+    while let Some(line) = lines.next() {
+        let Some(open) = fence(line) else {
+            continue;
+        };
+
+        let mut body = String::new();
+        for line in lines.by_ref() {
+            if closes(line, &open) {
+                break;
+            }
+            body.push_str(line);
+        }
+
+        let tag = open.info.split_whitespace().next().unwrap_or("");
+        if lang.is_none_or(|lang| tag.eq_ignore_ascii_case(lang)) {
+            // The line break before the closing fence isn't part of the code
+            if body.ends_with('\n') {
+                body.pop();
+                if body.ends_with('\r') {
+                    body.pop();
+                }
+            }
+            return Some(body);
+        }
+    }
+
+    None
+}
+
+fn markdown_to_text(content: &str) -> String {
+    let mut text = Vec::new();
+    let mut open: Option<Fence<'_>> = None;
+
+    for line in content.lines() {
+        if let Some(fence) = &open {
+            if closes(line, fence) {
+                open = None;
+            } else {
+                text.push(line.to_string());
+            }
+            continue;
+        }
+        if let Some(fence) = fence(line) {
+            open = Some(fence);
+            continue;
+        }
+
+        let line = line.trim_start();
+        if is_rule(line) {
+            continue;
+        }
+        text.push(inline_text(strip_block_markers(line)));
+    }
+
+    text.join("\n").trim().to_string()
+}
+
+/// `***`, `---` or `___`, spaces allowed between.
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['*', '-', '_'].iter().any(|m| marks.iter().all(|c| c == m))
+}
+
+/// `line` without its quote, heading and list markers.
+fn strip_block_markers(mut line: &str) -> &str {
+    while let Some(rest) = line.strip_prefix('>') {
+        line = rest.trim_start();
+    }
+
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes)
+        && line[hashes..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+    {
+        return line[hashes..].trim().trim_end_matches('#').trim_end();
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return rest;
+        }
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        if let Some(rest) = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+        {
+            return rest;
+        }
+    }
+
+    line
+}
+
+/// `line` without emphasis and code markers, links and images reduced to
+/// their text.
+fn inline_text(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut text = String::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() => {
+                text.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                // Code spans keep their content as written
+                let ticks = chars[i..].iter().take_while(|c| **c == '`').count();
+                let close = (i + ticks..chars.len()).find(|&j| {
+                    chars[j..].iter().take_while(|c| **c == '`').count() == ticks
+                        && chars.get(j.wrapping_sub(1)) != Some(&'`')
+                });
+                match close {
+                    Some(close) => {
+                        text.extend(&chars[i + ticks..close]);
+                        i = close + ticks;
+                    }
+                    None => {
+                        text.extend(&chars[i..i + ticks]);
+                        i += ticks;
+                    }
+                }
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => match link(&chars, i + 1) {
+                Some((label, end)) => {
+                    text.push_str(&inline_text(&label));
+                    i = end;
+                }
+                None => {
+                    text.push('!');
+                    i += 1;
+                }
+            },
+            '[' => match link(&chars, i) {
+                Some((label, end)) => {
+                    text.push_str(&inline_text(&label));
+                    i = end;
+                }
+                None => {
+                    text.push('[');
+                    i += 1;
+                }
+            },
+            // A lone `*` between spaces is arithmetic, not emphasis
+            '*' if !(is_space(chars.get(i.wrapping_sub(1))) && is_space(chars.get(i + 1))) => {
+                i += 1
+            }
+            // Underscores inside words are part of them: snake_case
+            '_' if !is_word(chars.get(i.wrapping_sub(1))) || !is_word(chars.get(i + 1)) => i += 1,
+            '~' if chars.get(i + 1) == Some(&'~') => i += 2,
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    text
+}
+
+fn is_word(c: Option<&char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric())
+}
+
+fn is_space(c: Option<&char>) -> bool {
+    c.is_none_or(|c| c.is_whitespace())
+}
+
+/// A `[label](target)` link starting at `start`: its label and the index
+/// just past it.
+fn link(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    for (j, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(j);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let close = close?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = (close + 2..chars.len()).find(|&j| chars[j] == ')')?;
+
+    Some((chars[start + 1..close].iter().collect(), end + 1))
+}
+
+fn capture(content: &str, pattern: &str, group: usize) -> Result<String, PostError> {
+    let invalid = |err: fancy_regex::Error| PostError::InvalidPattern {
+        pattern: pattern.to_string(),
+        reason: err.to_string(),
+    };
+
+    let regex = fancy_regex::Regex::new(pattern).map_err(invalid)?;
+    if group >= regex.captures_len() {
+        return Err(PostError::InvalidPattern {
+            pattern: pattern.to_string(),
+            reason: format!("no group {}", group),
+        });
+    }
+
+    regex
+        .captures(content)
+        .map_err(invalid)?
+        .and_then(|captures| captures.get(group))
+        .map(|found| found.as_str().to_string())
+        .ok_or_else(|| PostError::NoMatch {
+            pattern: pattern.to_string(),
+            group,
+        })
+}
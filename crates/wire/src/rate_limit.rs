@@ -0,0 +1,179 @@
+//! Keeping a client under the provider's rate limits.
+//!
+//! A client given a `RateLimiter` (with `ClientOptions::with_rate_limiter`)
+//! waits for room under its requests-per-minute and tokens-per-minute
+//! budgets before each request, so a burst of prompts queues up instead of
+//! failing with 429s. That covers prompts, the connection of a stream, each
+//! model turn of a tool loop and each retry. In a tool loop each wait is
+//! reported on the status channel as a `ToolStatus::RateLimited`.
+//!
+//! The budgets are token buckets: a full minute's allowance is available at
+//! once, and it refills evenly over the minute. Clones of a limiter share
+//! their buckets, so every client built from the same `ClientOptions` draws
+//! on one budget. A request's tokens are estimated from its text, at four
+//! bytes a token, as in `RequestStats`; tool definitions are not counted.
+//!
+//! Waits go through the client's `Clock`, so a `mock::TestClock` controls
+//! them in tests.
+//!
+//! ```
+//! use wire::config::ClientOptions;
+//! use wire::rate_limit::RateLimiter;
+//!
+//! let limiter = RateLimiter::new()
+//!     .with_requests_per_minute(500)
+//!     .with_tokens_per_minute(30_000);
+//! let options = ClientOptions::default().with_rate_limiter(limiter);
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+use crate::tool_loop::{StatusLog, ToolStatus};
+use crate::types::Message;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Requests and tokens per minute shared by every clone.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    requests_per_minute: Option<usize>,
+    tokens_per_minute: Option<usize>,
+    buckets: Arc<Mutex<Option<Buckets>>>,
+}
+
+impl RateLimiter {
+    /// A limiter with no budgets yet; on its own it never waits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_requests_per_minute(mut self, requests: usize) -> Self {
+        self.requests_per_minute = Some(requests);
+        self
+    }
+
+    pub fn with_tokens_per_minute(mut self, tokens: usize) -> Self {
+        self.tokens_per_minute = Some(tokens);
+        self
+    }
+
+    pub fn requests_per_minute(&self) -> Option<usize> {
+        self.requests_per_minute
+    }
+
+    pub fn tokens_per_minute(&self) -> Option<usize> {
+        self.tokens_per_minute
+    }
+
+    /// Take one request and `tokens` from the budgets at `now`, returning
+    /// how long to wait before sending it. The request is counted at once,
+    /// so callers that ask later wait behind it.
+    pub fn reserve(&self, now: Instant, tokens: usize) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap();
+        let buckets = buckets.get_or_insert_with(|| Buckets {
+            updated: now,
+            requests: self.requests_per_minute.map(Bucket::full),
+            tokens: self.tokens_per_minute.map(Bucket::full),
+        });
+
+        let elapsed = now.saturating_duration_since(buckets.updated);
+        buckets.updated = buckets.updated.max(now);
+
+        let mut wait = Duration::ZERO;
+        if let Some(requests) = &mut buckets.requests {
+            wait = wait.max(requests.take(elapsed, 1));
+        }
+        if let Some(bucket) = &mut buckets.tokens {
+            wait = wait.max(bucket.take(elapsed, tokens));
+        }
+        wait
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("tokens_per_minute", &self.tokens_per_minute)
+            .finish()
+    }
+}
+
+struct Buckets {
+    updated: Instant,
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+/// A minute's allowance, refilled evenly. `level` goes below zero when
+/// requests are waiting on it.
+struct Bucket {
+    capacity: f64,
+    level: f64,
+}
+
+impl Bucket {
+    fn full(per_minute: usize) -> Self {
+        let capacity = per_minute.max(1) as f64;
+        Self {
+            capacity,
+            level: capacity,
+        }
+    }
+
+    /// Refill for `elapsed`, take `amount` and return the wait until it is
+    /// paid back. More than a minute's allowance costs a minute's, since it
+    /// would never fit.
+    fn take(&mut self, elapsed: Duration, amount: usize) -> Duration {
+        let refill = self.capacity * elapsed.as_secs_f64() / MINUTE.as_secs_f64();
+        self.level = (self.level + refill).min(self.capacity);
+        self.level -= (amount as f64).min(self.capacity);
+
+        if self.level >= 0.0 {
+            Duration::ZERO
+        } else {
+            MINUTE.mul_f64(-self.level / self.capacity)
+        }
+    }
+}
+
+/// The tokens a request for `system_prompt` and `chat_history` is charged.
+pub(crate) fn estimate_tokens(system_prompt: &str, chat_history: &[Message]) -> usize {
+    let bytes: usize = chat_history
+        .iter()
+        .map(|message| {
+            let arguments: usize = message
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| call.function.arguments.len())
+                .sum();
+            message.content.len() + arguments
+        })
+        .sum();
+    (system_prompt.len() + bytes).div_ceil(4)
+}
+
+/// Wait on `clock` until `limiter` has room for a request of `tokens`,
+/// reporting the wait to `status`. Without a limiter it returns at once.
+pub(crate) async fn wait_for_room(
+    limiter: Option<&RateLimiter>,
+    tokens: usize,
+    clock: &dyn Clock,
+    status: Option<&mut StatusLog>,
+) {
+    let Some(limiter) = limiter else {
+        return;
+    };
+
+    let wait = limiter.reserve(clock.now(), tokens);
+    if wait.is_zero() {
+        return;
+    }
+    if let Some(status) = status {
+        status.report(ToolStatus::RateLimited { wait }).await;
+    }
+    clock.sleep(wait).await;
+}
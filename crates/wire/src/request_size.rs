@@ -0,0 +1,79 @@
+//! A ceiling on request bodies, checked once the body is built and before
+//! anything is sent.
+//!
+//! A history gone wrong (the same base64 image appended on every turn, a
+//! tool output pasted back in a loop) can grow a body to tens of megabytes,
+//! which providers refuse only after it has been uploaded, if the upload
+//! finishes at all. Every client instead fails such a prompt with
+//! `WireError::RequestTooLarge`, naming the largest parts of the body so the
+//! culprit is easy to find. The limit is `ClientOptions::max_request_bytes`,
+//! `config::DEFAULT_MAX_REQUEST_BYTES` unless set.
+
+use crate::error::WireError;
+
+/// How many of the largest parts `WireError::RequestTooLarge` lists.
+const LARGEST_PARTS: usize = 5;
+
+/// A piece of a request body and its size once serialized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BodyPart {
+    /// Where the part sits in the body as the provider receives it, e.g.
+    /// `messages[3]` or `messages[3].content[1]`. Indices count the
+    /// provider's own entries, which can include a system message.
+    pub path: String,
+    pub bytes: usize,
+}
+
+/// Fail with `WireError::RequestTooLarge` when `body` is over `limit`.
+pub(crate) fn check_body(limit: Option<usize>, body: &[u8]) -> Result<(), WireError> {
+    match limit {
+        Some(limit) if body.len() > limit => Err(WireError::RequestTooLarge {
+            bytes: body.len(),
+            limit,
+            largest: largest_parts(body),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The largest entries of the body's top-level fields, and of the `content`
+/// or `parts` blocks inside them, biggest first. Empty when the body isn't
+/// JSON.
+fn largest_parts(body: &[u8]) -> Vec<BodyPart> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice(body) else {
+        return Vec::new();
+    };
+
+    let size = |value: &serde_json::Value| serde_json::to_vec(value).map_or(0, |json| json.len());
+    let mut parts = Vec::new();
+    for (name, value) in &fields {
+        let Some(entries) = value.as_array() else {
+            parts.push(BodyPart {
+                path: name.clone(),
+                bytes: size(value),
+            });
+            continue;
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
+            let path = format!("{}[{}]", name, index);
+            parts.push(BodyPart {
+                path: path.clone(),
+                bytes: size(entry),
+            });
+            for key in ["content", "parts"] {
+                let blocks = entry.get(key).and_then(|blocks| blocks.as_array());
+                for (block_index, block) in blocks.into_iter().flatten().enumerate() {
+                    parts.push(BodyPart {
+                        path: format!("{}.{}[{}]", path, key, block_index),
+                        bytes: size(block),
+                    });
+                }
+            }
+        }
+    }
+
+    parts.sort_by_key(|part| std::cmp::Reverse(part.bytes));
+    parts.truncate(LARGEST_PARTS);
+    parts
+}
@@ -0,0 +1,338 @@
+//! Sending a request again after a transient failure.
+//!
+//! A client given a `RetryPolicy` (with `ClientOptions::with_retry_policy`)
+//! repeats a request that failed with an error the policy's predicate
+//! accepts, waiting longer before each attempt. Only the request to the
+//! model is repeated: the send and read of a prompt, each model turn of a
+//! tool loop, and the connection of a stream. Tools that already ran in a
+//! tool loop are not run again, and a stream that broke off after its first
+//! byte is not restarted, as its deltas already went out.
+//!
+//! A provider that says how long to wait is listened to: an `Http` error's
+//! `retry_after`, read from the `Retry-After` header or OpenAI's
+//! `x-ratelimit-reset-*`, replaces the backoff for that retry, up to the
+//! policy's `max_retry_after`. In a tool loop each wait is reported on the
+//! status channel as a `ToolStatus::Retrying`.
+//!
+//! Waits go through the client's `Clock`, so a `mock::TestClock` controls
+//! them in tests.
+//!
+//! ```
+//! use std::time::Duration;
+//! use wire::retry::RetryPolicy;
+//!
+//! let policy = RetryPolicy::new(4)
+//!     .with_base_delay(Duration::from_millis(200))
+//!     .with_max_delay(Duration::from_secs(1))
+//!     .with_jitter(0.0);
+//! let delays: Vec<_> = (1..4).map(|retry| policy.backoff(retry)).collect();
+//! assert_eq!(
+//!     delays,
+//!     [Duration::from_millis(200), Duration::from_millis(400), Duration::from_millis(800)]
+//! );
+//! assert_eq!(policy.backoff(10), Duration::from_secs(1));
+//! ```
+
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::error::WireError;
+use crate::rate_limit::{wait_for_room, RateLimiter};
+use crate::tool_loop::{StatusLog, ToolStatus};
+
+/// Whether a failed attempt is worth repeating.
+pub type RetryPredicate = Arc<dyn Fn(&WireError) -> bool + Send + Sync>;
+
+/// How often, and after how long, a failed request is sent again.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first included; 1 never retries.
+    pub max_attempts: usize,
+    /// The wait before the first retry, doubled for each one after it.
+    pub base_delay: Duration,
+    /// The longest wait between two attempts.
+    pub max_delay: Duration,
+    /// The fraction of each wait that is random: 0.0 waits the full
+    /// backoff, 1.0 anywhere from nothing to it. Spreads out clients that
+    /// failed together.
+    pub jitter: f64,
+    /// The longest a provider's `Retry-After` is waited for; a longer one
+    /// is cut to this.
+    pub max_retry_after: Duration,
+    /// Which errors are retried; `is_transient` when `None`.
+    pub retry_on: Option<RetryPredicate>,
+}
+
+impl RetryPolicy {
+    /// Up to `max_attempts` attempts with the default waits: 500ms doubling
+    /// up to 30s, half of each random.
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            max_retry_after: Duration::from_secs(60),
+            retry_on: None,
+        }
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Clamped to 0.0..=1.0.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Retry the errors `retry_on` accepts instead of the transient ones.
+    pub fn with_retry_on<F>(mut self, retry_on: F) -> Self
+    where
+        F: Fn(&WireError) -> bool + Send + Sync + 'static,
+    {
+        self.retry_on = Some(Arc::new(retry_on));
+        self
+    }
+
+    /// The errors retried without a `retry_on`; the same as
+    /// `WireError::is_retryable`.
+    pub fn is_transient(error: &WireError) -> bool {
+        error.is_retryable()
+    }
+
+    /// Whether the policy repeats a request that failed with `error`.
+    pub fn should_retry(&self, error: &WireError) -> bool {
+        match &self.retry_on {
+            Some(retry_on) => retry_on(error),
+            None => Self::is_transient(error),
+        }
+    }
+
+    /// The wait before retry number `retry` (from 1), before jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// `backoff` with up to `jitter` of it taken off at random.
+    fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        let fraction = (random >> 11) as f64 / (1u64 << 53) as f64;
+        backoff.mul_f64(1.0 - self.jitter * fraction)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("retry_on", &self.retry_on.as_ref().map(|_| "custom"))
+            .finish()
+    }
+}
+
+/// Run `attempt` until it succeeds, fails with an error `policy` doesn't
+/// retry, or runs out of attempts, sleeping on `clock` in between and
+/// reporting each wait to `status`. Without a policy it runs once. Every
+/// attempt first waits for room under `limiter` for a request of `tokens`.
+pub(crate) async fn with_retries<T, F, Fut>(
+    policy: Option<&RetryPolicy>,
+    limiter: Option<&RateLimiter>,
+    tokens: usize,
+    clock: &dyn Clock,
+    mut status: Option<&mut StatusLog>,
+    mut attempt: F,
+) -> Result<T, WireError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, WireError>>,
+{
+    let Some(policy) = policy else {
+        wait_for_room(limiter, tokens, clock, status).await;
+        return attempt().await;
+    };
+
+    let mut retry = 0;
+    loop {
+        wait_for_room(limiter, tokens, clock, status.as_deref_mut()).await;
+        match attempt().await {
+            Err(error) if retry + 1 < policy.max_attempts && policy.should_retry(&error) => {
+                retry += 1;
+                let wait = match &error {
+                    WireError::Http {
+                        retry_after: Some(wait),
+                        ..
+                    } => (*wait).min(policy.max_retry_after),
+                    _ => policy.delay(retry as u32),
+                };
+                if let Some(status) = status.as_deref_mut() {
+                    status
+                        .report(ToolStatus::Retrying {
+                            retry,
+                            wait,
+                            reason: error.to_string(),
+                        })
+                        .await;
+                }
+                clock.sleep(wait).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The wait a failed response's headers ask for, from `(name, value)`
+/// pairs with names in any case. The first of these that is present wins:
+///
+/// - `retry-after-ms`: milliseconds (OpenAI)
+/// - `retry-after`: seconds, or an HTTP date
+/// - `x-ratelimit-reset-requests` and `x-ratelimit-reset-tokens`: a
+///   duration like `1m30s` or `250ms` (OpenAI); the longer of the two
+///
+/// ```
+/// use std::time::Duration;
+/// use wire::retry::parse_retry_after;
+///
+/// let wait = parse_retry_after([("Retry-After", "2"), ("x-ratelimit-reset-tokens", "6m0s")]);
+/// assert_eq!(wait, Some(Duration::from_secs(2)));
+///
+/// let wait = parse_retry_after([
+///     ("x-ratelimit-reset-requests", "1.5s"),
+///     ("x-ratelimit-reset-tokens", "250ms"),
+/// ]);
+/// assert_eq!(wait, Some(Duration::from_millis(1500)));
+/// ```
+pub fn parse_retry_after<'a, I>(headers: I) -> Option<Duration>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut millis = None;
+    let mut seconds = None;
+    let mut reset: Option<Duration> = None;
+    for (name, value) in headers {
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "retry-after-ms" => {
+                millis = value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok());
+            }
+            "retry-after" => seconds = parse_seconds(value).or_else(|| parse_http_date(value)),
+            "x-ratelimit-reset-requests" | "x-ratelimit-reset-tokens" => {
+                if let Some(wait) = parse_go_duration(value) {
+                    reset = Some(reset.map_or(wait, |reset| reset.max(wait)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    millis.or(seconds).or(reset)
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.parse().ok()?).ok()
+}
+
+/// A duration in Go's notation, as OpenAI's reset headers have it: numbers
+/// each followed by `h`, `m`, `s`, `ms`, `us` or `ns`.
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let mut rest = value;
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let seconds = match &rest[..unit] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        rest = &rest[unit..];
+        total += Duration::try_from_secs_f64(number * seconds).ok()?;
+    }
+
+    Some(total)
+}
+
+/// The time from now until an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`);
+/// zero once it has passed.
+fn parse_http_date(value: &str) -> Option<Duration> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts[..] else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let mut clock = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let at = days * 86_400 + hour * 3600 + minute * 60 + second;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs() as i64;
+    Some(Duration::from_secs(at.saturating_sub(now).max(0) as u64))
+}
@@ -0,0 +1,398 @@
+//! Pick a client per request.
+//!
+//! `RouterClient` implements `PromptCore` over several inner clients. Each
+//! call is described by a `RouteRequest` and checked against the routes in the order
+//! they were added; the first whose predicate matches handles it, and the
+//! fallback route handles the rest. The chosen route's name is recorded in
+//! `MessageMetadata::route` of the reply and passed to the router's
+//! `MetricsCallback`, so usage can be attributed per route.
+//!
+//! The tool loop is routed too, and fails if it lands on a client without
+//! `tools()`. Raw transport is not: pick a client with `route` and use its
+//! `raw_transport()`.
+//!
+//! `RouterClient::tiered` is a ready-made rule set: requests with tools or
+//! more than `DEFAULT_LONG_REQUEST_TOKENS` go to the expensive client,
+//! everything else to the cheap one.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use wire::router::RouterClient;
+//!
+//! let cheap = Arc::from(wire::new_client("gpt-4o-mini").unwrap());
+//! let expensive = Arc::from(wire::new_client("gpt-4o").unwrap());
+//!
+//! let router = RouterClient::new("cheap", cheap)
+//!     .with_route("long history", expensive, |request| {
+//!         request.chat_history.len() > 20
+//!     });
+//! ```
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::api::{EventStream, PromptCore, ToolCapable};
+use crate::clock::Clock;
+use crate::config::PromptOptions;
+use crate::error::WireError;
+use crate::event_log::EventLog;
+use crate::metrics::{report_metrics, MetricsCallback};
+use crate::tool_loop::ToolLoopResult;
+use crate::types::{Message, MessageBuilder, StreamEvent, StreamEventKind, Tool, ToolSpec};
+
+/// Requests estimated above this many tokens count as long for
+/// `RouterClient::tiered`.
+pub const DEFAULT_LONG_REQUEST_TOKENS: usize = 2000;
+
+/// What a route predicate gets to look at.
+#[derive(Clone, Copy, Debug)]
+pub struct RouteRequest<'a> {
+    pub system_prompt: &'a str,
+    pub chat_history: &'a [Message],
+    /// Tools offered to the model; empty outside the tool loop.
+    pub tools: &'a [ToolSpec],
+    /// Rough size of the request: the system prompt, message contents and
+    /// tool definitions at 4 bytes per token.
+    pub estimated_tokens: usize,
+}
+
+impl<'a> RouteRequest<'a> {
+    pub fn new(system_prompt: &'a str, chat_history: &'a [Message], tools: &'a [ToolSpec]) -> Self {
+        let bytes = system_prompt.len()
+            + chat_history
+                .iter()
+                .map(|message| message.content.len())
+                .sum::<usize>()
+            + tools
+                .iter()
+                .map(|tool| serde_json::to_string(tool).map_or(0, |json| json.len()))
+                .sum::<usize>();
+
+        Self {
+            system_prompt,
+            chat_history,
+            tools,
+            estimated_tokens: bytes.div_ceil(4),
+        }
+    }
+
+    pub fn has_tools(&self) -> bool {
+        !self.tools.is_empty()
+    }
+}
+
+type RoutePredicate = Box<dyn Fn(&RouteRequest) -> bool + Send + Sync>;
+
+struct Route {
+    name: String,
+    client: Arc<dyn PromptCore>,
+    predicate: RoutePredicate,
+}
+
+/// A client that hands each call to one of several others.
+pub struct RouterClient {
+    routes: Vec<Route>,
+    fallback: (String, Arc<dyn PromptCore>),
+    pub metrics_callback: Option<MetricsCallback>,
+}
+
+impl RouterClient {
+    /// A router that sends everything to `client` until routes are added.
+    pub fn new(name: impl Into<String>, client: Arc<dyn PromptCore>) -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback: (name.into(), client),
+            metrics_callback: None,
+        }
+    }
+
+    /// Routes named `"tools"` and `"long"` send requests with tools or over
+    /// `DEFAULT_LONG_REQUEST_TOKENS` to `expensive`; the rest go to `cheap`
+    /// under the name `"cheap"`.
+    pub fn tiered(cheap: Arc<dyn PromptCore>, expensive: Arc<dyn PromptCore>) -> Self {
+        Self::new("cheap", cheap)
+            .with_route("tools", expensive.clone(), |request| request.has_tools())
+            .with_route("long", expensive, |request| {
+                request.estimated_tokens > DEFAULT_LONG_REQUEST_TOKENS
+            })
+    }
+
+    /// Send requests matching `predicate` to `client`, unless an earlier
+    /// route matched first.
+    pub fn with_route<F>(
+        mut self,
+        name: impl Into<String>,
+        client: Arc<dyn PromptCore>,
+        predicate: F,
+    ) -> Self
+    where
+        F: Fn(&RouteRequest) -> bool + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            name: name.into(),
+            client,
+            predicate: Box::new(predicate),
+        });
+        self
+    }
+
+    /// Report every reply, tagged with its route, to `callback`. Inner
+    /// clients keep reporting to their own callbacks, untagged.
+    pub fn with_metrics_callback(mut self, callback: MetricsCallback) -> Self {
+        self.metrics_callback = Some(callback);
+        self
+    }
+
+    /// The name and client of the route that would handle `request`.
+    pub fn route(&self, request: &RouteRequest) -> (&str, &dyn PromptCore) {
+        let (name, client) = self
+            .routes
+            .iter()
+            .find(|route| (route.predicate)(request))
+            .map(|route| (&route.name, &route.client))
+            .unwrap_or((&self.fallback.0, &self.fallback.1));
+
+        (name, client.as_ref())
+    }
+
+    fn route_for(
+        &self,
+        system_prompt: &str,
+        chat_history: &[Message],
+        tools: &[ToolSpec],
+    ) -> (String, &dyn PromptCore) {
+        let (name, client) = self.route(&RouteRequest::new(system_prompt, chat_history, tools));
+        (name.to_string(), client)
+    }
+
+    /// Tag `message` with `route` and report it.
+    fn record(&self, route: &str, mut message: Message) -> Message {
+        message.metadata.route = Some(route.to_string());
+        report_metrics(&self.metrics_callback, &message);
+        message
+    }
+
+    /// Tag and report the messages a tool loop added after the
+    /// `history_len` it started with.
+    fn record_all(&self, route: &str, history_len: usize, messages: Vec<Message>) -> Vec<Message> {
+        messages
+            .into_iter()
+            .enumerate()
+            .map(|(index, message)| {
+                if index < history_len {
+                    message
+                } else {
+                    self.record(route, message)
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCore for RouterClient {
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        self.fallback.1.get_auth_token()
+    }
+
+    /// Refreshes every route's client.
+    fn refresh_credentials(&self) {
+        for route in &self.routes {
+            route.client.refresh_credentials();
+        }
+        self.fallback.1.refresh_credentials();
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        self.fallback.1.new_message(content)
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.fallback.1.clock()
+    }
+
+    fn event_log(&self) -> Option<&EventLog> {
+        self.fallback.1.event_log()
+    }
+
+    fn deny_warnings(&self) -> bool {
+        self.fallback.1.deny_warnings()
+    }
+
+    fn build_request(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        tools: Option<&[ToolSpec]>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        let (_, client) = self.route_for(&system_prompt, &chat_history, tools.unwrap_or(&[]));
+        client.build_request(system_prompt, chat_history, tools, stream)
+    }
+
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        let (route, client) = self.route_for(&system_prompt, &chat_history, &[]);
+        let message = client
+            .prompt_with_options(system_prompt, chat_history, options)
+            .await?;
+
+        Ok(self.record(&route, message))
+    }
+
+    async fn prompt_stream_with_options(
+        &self,
+        chat_history: Vec<Message>,
+        system_prompt: String,
+        tx: tokio::sync::mpsc::Sender<String>,
+        options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        let (route, client) = self.route_for(&system_prompt, &chat_history, &[]);
+        let message = client
+            .prompt_stream_with_options(chat_history, system_prompt, tx, options)
+            .await?;
+
+        Ok(self.record(&route, message))
+    }
+
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let (route, client) = self.route_for(&system_prompt, &chat_history, &[]);
+        let events = client.prompt_stream_events_with_options(system_prompt, chat_history, options);
+
+        Box::pin(RoutedEvents {
+            router: self,
+            route,
+            events,
+        })
+    }
+
+    /// Parsed by the fallback client; a raw response does not say which
+    /// route produced it.
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        self.fallback.1.read_json_response(response_json)
+    }
+
+    /// Some when any route's client can run tools. A tool loop routed to
+    /// one that cannot fails.
+    fn tools(&self) -> Option<&dyn ToolCapable> {
+        let mut clients = self
+            .routes
+            .iter()
+            .map(|route| &route.client)
+            .chain(std::iter::once(&self.fallback.1));
+
+        if clients.any(|client| client.supports_tools()) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+/// A route's events, its reply tagged and reported as the route's.
+struct RoutedEvents<'a> {
+    router: &'a RouterClient,
+    route: String,
+    events: EventStream<'a>,
+}
+
+impl Stream for RoutedEvents<'_> {
+    type Item = Result<StreamEvent, WireError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        this.events.as_mut().poll_next(cx).map(|event| {
+            event.map(|event| {
+                event.map(|event| match event {
+                    StreamEvent {
+                        seq,
+                        kind: StreamEventKind::Reply(message),
+                    } => StreamEvent {
+                        seq,
+                        kind: StreamEventKind::Reply(this.router.record(&this.route, message)),
+                    },
+                    event => event,
+                })
+            })
+        })
+    }
+}
+
+/// `client`'s tool loop, or an error naming the `route` that lacks one.
+fn tools_for<'a>(
+    route: &str,
+    client: &'a dyn PromptCore,
+) -> Result<&'a dyn ToolCapable, WireError> {
+    client.tools().ok_or_else(|| WireError::ToolsUnsupported {
+        client: format!("the client for route {:?}", route),
+    })
+}
+
+#[async_trait::async_trait]
+impl ToolCapable for RouterClient {
+    async fn prompt_with_tools(
+        &self,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let (route, client) = self.route_for(system_prompt, &chat_history, &specs);
+        let history_len = chat_history.len();
+        let client = tools_for(&route, client)?;
+        let messages = client
+            .prompt_with_tools(system_prompt, chat_history, tools)
+            .await?;
+
+        Ok(self.record_all(&route, history_len, messages))
+    }
+
+    async fn prompt_with_tools_with_status(
+        &self,
+        tx: tokio::sync::mpsc::Sender<String>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Vec<Message>, WireError> {
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let (route, client) = self.route_for(system_prompt, &chat_history, &specs);
+        let history_len = chat_history.len();
+        let client = tools_for(&route, client)?;
+        let messages = client
+            .prompt_with_tools_with_status(tx, system_prompt, chat_history, tools)
+            .await?;
+
+        Ok(self.record_all(&route, history_len, messages))
+    }
+
+    async fn run_tool_loop(
+        &self,
+        tx: Option<tokio::sync::mpsc::Sender<String>>,
+        system_prompt: &str,
+        chat_history: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ToolLoopResult, WireError> {
+        let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+        let (route, client) = self.route_for(system_prompt, &chat_history, &specs);
+        let history_len = chat_history.len();
+        let client = tools_for(&route, client)?;
+        let mut result = client
+            .run_tool_loop(tx, system_prompt, chat_history, tools)
+            .await?;
+
+        result.messages = self.record_all(&route, history_len, result.messages);
+        Ok(result)
+    }
+}
@@ -0,0 +1,222 @@
+//! Cleaning message text on its way out.
+//!
+//! Raw control characters pasted into a prompt (a stray NUL, the ESC of a
+//! terminal colour code) are rejected by some providers with an opaque 400
+//! and quietly accepted by others. The request builders run the system prompt
+//! and the content of every message, tool outputs included, through the
+//! client's `SanitizePolicy` (set with `ClientOptions::with_sanitize_policy`)
+//! after any `ContentFilter`. As with filters, only the request body changes:
+//! the caller's history keeps the original text.
+//!
+//! The pass covers the C0 controls, U+0000 to U+001F, except `\n` and `\t`.
+//! A `\r\n` pair is a line break and is kept as it is under every policy, so
+//! text without controls goes out byte for byte; a lone `\r` is a control
+//! like any other.
+//!
+//! ```
+//! use wire::sanitize::{sanitize, SanitizePolicy};
+//!
+//! let pasted = "\u{1b}[31mred\u{1b}[0m\r\nnext\u{0}";
+//! assert_eq!(sanitize(pasted, SanitizePolicy::Strip).unwrap(), "[31mred[0m\r\nnext");
+//! assert_eq!(
+//!     sanitize(pasted, SanitizePolicy::Escape).unwrap(),
+//!     "\\u001b[31mred\\u001b[0m\r\nnext\\u0000"
+//! );
+//! assert!(sanitize(pasted, SanitizePolicy::Reject).is_err());
+//! ```
+//!
+//! A Rust string is always well-formed UTF-8, so the pass has nothing to
+//! check there. The one place ill-formed text gets in is a JSON escape for
+//! half a surrogate pair, which `serde_json` refuses to parse; run exported
+//! histories through `repair_surrogates` before deserializing them.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::WireError;
+use crate::types::Message;
+
+/// What the request builders do with control characters in outbound text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SanitizePolicy {
+    /// Drop them.
+    #[default]
+    Strip,
+    /// Replace each with its JSON escape, as text: ESC becomes `\u001b`.
+    Escape,
+    /// Fail the prompt with `error::WireError::ControlCharacter` before
+    /// anything is sent. Requests built directly with `build_request` or
+    /// `build_request_raw` carry the text unchanged.
+    Reject,
+}
+
+/// The first control character in text that `SanitizePolicy::Reject` refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlCharacter {
+    /// Byte offset of the character in the text.
+    pub offset: usize,
+    pub character: char,
+}
+
+impl fmt::Display for ControlCharacter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "control character U+{:04X} at byte {}",
+            self.character as u32, self.offset
+        )
+    }
+}
+
+impl std::error::Error for ControlCharacter {}
+
+/// Whether the character at `offset` in `text` is one the pass acts on.
+fn is_control(text: &str, offset: usize, character: char) -> bool {
+    match character {
+        '\n' | '\t' => false,
+        '\r' => text.as_bytes().get(offset + 1) != Some(&b'\n'),
+        _ => character < ' ',
+    }
+}
+
+/// `text` cleaned as `policy` says, borrowed when there was nothing to do.
+pub fn sanitize(text: &str, policy: SanitizePolicy) -> Result<Cow<'_, str>, ControlCharacter> {
+    if !text
+        .char_indices()
+        .any(|(offset, character)| is_control(text, offset, character))
+    {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let mut clean = String::with_capacity(text.len());
+    for (offset, character) in text.char_indices() {
+        if !is_control(text, offset, character) {
+            clean.push(character);
+            continue;
+        }
+
+        match policy {
+            SanitizePolicy::Strip => {}
+            SanitizePolicy::Escape => clean.push_str(&format!("\\u{:04x}", character as u32)),
+            SanitizePolicy::Reject => return Err(ControlCharacter { offset, character }),
+        }
+    }
+
+    Ok(Cow::Owned(clean))
+}
+
+/// Fail with `WireError::ControlCharacter` if `policy` is `Reject` and the
+/// system prompt or any message holds a control character.
+pub(crate) fn check_outbound(
+    policy: SanitizePolicy,
+    system_prompt: &str,
+    chat_history: &[Message],
+) -> Result<(), WireError> {
+    if policy != SanitizePolicy::Reject {
+        return Ok(());
+    }
+
+    let texts = std::iter::once((None, system_prompt)).chain(
+        chat_history
+            .iter()
+            .enumerate()
+            .map(|(index, message)| (Some(index), message.content.as_str())),
+    );
+    for (message_index, text) in texts {
+        if let Err(found) = sanitize(text, policy) {
+            return Err(WireError::ControlCharacter {
+                message_index,
+                offset: found.offset,
+                character: found.character,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// `text` as a request builder should send it. Text that `Reject` would
+/// refuse goes out unchanged; the prompt methods have already checked it.
+pub(crate) fn outbound(text: &str, policy: SanitizePolicy) -> String {
+    match sanitize(text, policy) {
+        Ok(clean) => clean.into_owned(),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Run the system prompt and every message through `outbound`.
+#[cfg(any(feature = "openai", feature = "anthropic"))]
+pub(crate) fn sanitize_outbound(
+    policy: SanitizePolicy,
+    system_prompt: String,
+    mut chat_history: Vec<Message>,
+) -> (String, Vec<Message>) {
+    for message in chat_history.iter_mut() {
+        if let Ok(Cow::Owned(clean)) = sanitize(&message.content, policy) {
+            message.content = clean;
+        }
+    }
+
+    (outbound(&system_prompt, policy), chat_history)
+}
+
+/// `json` with every `\u` escape for an unpaired surrogate replaced by
+/// `\ufffd`, the replacement character, so that `serde_json` will parse it.
+/// JavaScript and Python happily write such escapes when a string was cut in
+/// the middle of an emoji.
+pub fn repair_surrogates(json: &str) -> Cow<'_, str> {
+    if !json.contains("\\u") {
+        return Cow::Borrowed(json);
+    }
+
+    let bytes = json.as_bytes();
+    let escape_at = |at: usize| -> Option<u32> {
+        if bytes.get(at) != Some(&b'\\') || bytes.get(at + 1) != Some(&b'u') {
+            return None;
+        }
+        let hex = json.get(at + 2..at + 6)?;
+        u32::from_str_radix(hex, 16).ok()
+    };
+
+    let mut repaired = String::with_capacity(json.len());
+    let mut copied = 0;
+    let mut at = 0;
+    while at < bytes.len() {
+        if bytes[at] != b'\\' {
+            at += 1;
+            continue;
+        }
+
+        let unit = match escape_at(at) {
+            Some(unit) => unit,
+            // Some other escape, `\\` included: skip both bytes
+            None => {
+                at += 2;
+                continue;
+            }
+        };
+        let keep = match unit {
+            0xD800..=0xDBFF => {
+                if matches!(escape_at(at + 6), Some(0xDC00..=0xDFFF)) {
+                    at += 12;
+                    continue;
+                }
+                false
+            }
+            0xDC00..=0xDFFF => false,
+            _ => true,
+        };
+        if !keep {
+            repaired.push_str(&json[copied..at]);
+            repaired.push_str("\\ufffd");
+            copied = at + 6;
+        }
+        at += 6;
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(json);
+    }
+    repaired.push_str(&json[copied..]);
+    Cow::Owned(repaired)
+}
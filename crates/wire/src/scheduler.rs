@@ -0,0 +1,378 @@
+//! Share one client between callers of different urgency.
+//!
+//! `PromptScheduler` queues prompts for a client and starts them no faster
+//! than its `RateLimit` allows. Each submission carries a `Priority`; when a
+//! slot opens, the most urgent waiting prompt takes it, oldest first among
+//! equals, so an interactive prompt overtakes any amount of queued
+//! background work. `SchedulerOptions` can also cap how many prompts of one
+//! priority run at once, and age waiting prompts so low-priority work still
+//! gets through while urgent traffic keeps the limit saturated.
+//!
+//! Waiting is measured against the client's `clock()`, so tests can drive
+//! the scheduler with `mock::TestClock`.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use wire::scheduler::{Priority, PromptScheduler, RateLimit};
+//! use wire::types::MessageType;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let client = Arc::from(wire::new_client("gpt-4o-mini").unwrap());
+//! let scheduler = PromptScheduler::new(client, RateLimit::per_minute(60));
+//!
+//! let question = scheduler
+//!     .client()
+//!     .new_message("Summarize our chat so far.".to_string())
+//!     .message_type(MessageType::User)
+//!     .build();
+//! let summary = scheduler.submit(
+//!     Priority::Background,
+//!     "Be brief.".to_string(),
+//!     vec![question],
+//! );
+//!
+//! println!("{}", summary.await.unwrap().content);
+//! # });
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{oneshot, Notify};
+
+use crate::api::PromptCore;
+use crate::config::PromptOptions;
+use crate::error::WireError;
+use crate::types::Message;
+
+/// How urgently a submission should run, most urgent first.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Someone is waiting on the answer.
+    Interactive,
+    Normal,
+    /// Work nobody is watching, such as summarizing old history.
+    Background,
+}
+
+impl Priority {
+    /// Every priority, most urgent first.
+    pub const ALL: [Priority; 3] = [
+        Priority::Interactive,
+        Priority::Normal,
+        Priority::Background,
+    ];
+
+    fn rank(self) -> usize {
+        self as usize
+    }
+}
+
+/// At most `requests` prompts started in any window of `per`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub requests: usize,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    pub fn new(requests: usize, per: Duration) -> Self {
+        Self { requests, per }
+    }
+
+    pub fn per_second(requests: usize) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: usize) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+}
+
+/// How a `PromptScheduler` picks between waiting prompts.
+#[derive(Clone, Debug, Default)]
+pub struct SchedulerOptions {
+    /// Most prompts of each priority running at once, indexed in
+    /// `Priority::ALL` order. `None` is no cap.
+    concurrency_limits: [Option<usize>; 3],
+    /// A waiting prompt is treated as one priority more urgent for every
+    /// `aging` it has waited. `None` keeps priorities strict, which lets a
+    /// steady stream of urgent prompts hold back the rest indefinitely.
+    pub aging: Option<Duration>,
+}
+
+impl SchedulerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run at most `limit` prompts submitted at `priority` at once. Aging
+    /// does not lift the cap: it counts prompts by the priority they were
+    /// submitted with.
+    pub fn with_concurrency_limit(mut self, priority: Priority, limit: usize) -> Self {
+        self.concurrency_limits[priority.rank()] = Some(limit);
+        self
+    }
+
+    pub fn with_aging(mut self, aging: Duration) -> Self {
+        self.aging = Some(aging);
+        self
+    }
+
+    pub fn concurrency_limit(&self, priority: Priority) -> Option<usize> {
+        self.concurrency_limits[priority.rank()]
+    }
+}
+
+struct Waiting {
+    priority: Priority,
+    submitted: Instant,
+    start: oneshot::Sender<Running>,
+}
+
+#[derive(Default)]
+struct Queue {
+    /// In submission order.
+    waiting: VecDeque<Waiting>,
+    running: [usize; 3],
+    /// When each prompt in the current rate limit window started.
+    started: VecDeque<Instant>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Signalled on every submission and every prompt that finishes.
+    changed: Notify,
+}
+
+/// Held by a prompt while it runs; its slot frees up when this drops.
+struct Running {
+    shared: Arc<Shared>,
+    priority: Priority,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().running[self.priority.rank()] -= 1;
+        self.shared.changed.notify_one();
+    }
+}
+
+impl Shared {
+    /// Start every prompt that may start at `now`. Returns when the rate
+    /// limit next frees a slot, if a prompt is waiting on it.
+    fn dispatch(
+        self: &Arc<Self>,
+        now: Instant,
+        rate_limit: RateLimit,
+        options: &SchedulerOptions,
+    ) -> Option<Instant> {
+        // Prompts whose caller gave up are dropped here, and a prompt that
+        // is abandoned just as it starts hands its slot back. Either way the
+        // `Running` must drop after the lock is released
+        let mut abandoned = Vec::new();
+        let mut queue = self.queue.lock().unwrap();
+        queue.waiting.retain(|waiting| !waiting.start.is_closed());
+
+        let wake_at = loop {
+            while queue
+                .started
+                .front()
+                .is_some_and(|started| *started + rate_limit.per <= now)
+            {
+                queue.started.pop_front();
+            }
+            if queue.started.len() >= rate_limit.requests {
+                break queue
+                    .waiting
+                    .front()
+                    .and(queue.started.front())
+                    .map(|started| *started + rate_limit.per);
+            }
+
+            let next = queue
+                .waiting
+                .iter()
+                .enumerate()
+                .filter(|(_, waiting)| {
+                    options
+                        .concurrency_limit(waiting.priority)
+                        .is_none_or(|limit| queue.running[waiting.priority.rank()] < limit)
+                })
+                .min_by_key(|(index, waiting)| {
+                    (effective_rank(waiting, now, options.aging), *index)
+                })
+                .map(|(index, _)| index);
+            let Some(index) = next else {
+                break None;
+            };
+
+            let waiting = queue.waiting.remove(index).expect("index is in the queue");
+            queue.running[waiting.priority.rank()] += 1;
+            queue.started.push_back(now);
+
+            let running = Running {
+                shared: self.clone(),
+                priority: waiting.priority,
+            };
+            if let Err(running) = waiting.start.send(running) {
+                queue.started.pop_back();
+                abandoned.push(running);
+            }
+        };
+
+        drop(queue);
+        drop(abandoned);
+        wake_at
+    }
+}
+
+/// `waiting`'s priority rank after aging; lower runs first.
+fn effective_rank(waiting: &Waiting, now: Instant, aging: Option<Duration>) -> usize {
+    let rank = waiting.priority.rank();
+    match aging {
+        Some(aging) if !aging.is_zero() => {
+            let waited = now.saturating_duration_since(waiting.submitted);
+            let steps = waited.as_nanos() / aging.as_nanos();
+            rank.saturating_sub(steps.min(rank as u128) as usize)
+        }
+        _ => rank,
+    }
+}
+
+/// Queues prompts for one client, starting them by priority within a rate
+/// limit.
+///
+/// Dispatch happens on a task spawned by `new`, so the scheduler must be
+/// created inside a tokio runtime. Dropping the scheduler stops it; prompts
+/// still queued then fail, while those already running finish.
+pub struct PromptScheduler {
+    client: Arc<dyn PromptCore>,
+    shared: Arc<Shared>,
+    dispatcher: tokio::task::JoinHandle<()>,
+}
+
+impl PromptScheduler {
+    pub fn new(client: Arc<dyn PromptCore>, rate_limit: RateLimit) -> Self {
+        Self::with_options(client, rate_limit, SchedulerOptions::default())
+    }
+
+    pub fn with_options(
+        client: Arc<dyn PromptCore>,
+        rate_limit: RateLimit,
+        options: SchedulerOptions,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            changed: Notify::new(),
+        });
+
+        let dispatcher = tokio::spawn(dispatch_loop(
+            client.clone(),
+            shared.clone(),
+            rate_limit,
+            options,
+        ));
+
+        Self {
+            client,
+            shared,
+            dispatcher,
+        }
+    }
+
+    /// The client prompts are sent through.
+    pub fn client(&self) -> &dyn PromptCore {
+        self.client.as_ref()
+    }
+
+    /// Prompts submitted and not yet started.
+    pub fn waiting(&self) -> usize {
+        let queue = self.shared.queue.lock().unwrap();
+        queue
+            .waiting
+            .iter()
+            .filter(|waiting| !waiting.start.is_closed())
+            .count()
+    }
+
+    /// Queue a prompt at `priority`. Its place in the queue is taken now;
+    /// the returned future resolves to the reply once the prompt has been
+    /// started and answered. Dropping the future withdraws the prompt, or
+    /// frees its slot once it has started.
+    pub fn submit(
+        &self,
+        priority: Priority,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+    ) -> impl Future<Output = Result<Message, WireError>> + Send + 'static {
+        self.submit_with_options(
+            priority,
+            system_prompt,
+            chat_history,
+            PromptOptions::default(),
+        )
+    }
+
+    /// `submit` with per-call options.
+    pub fn submit_with_options(
+        &self,
+        priority: Priority,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: PromptOptions,
+    ) -> impl Future<Output = Result<Message, WireError>> + Send + 'static {
+        let (start, started) = oneshot::channel();
+        self.shared
+            .queue
+            .lock()
+            .unwrap()
+            .waiting
+            .push_back(Waiting {
+                priority,
+                submitted: self.client.clock().now(),
+                start,
+            });
+        self.shared.changed.notify_one();
+
+        let client = self.client.clone();
+        async move {
+            let _running = started.await.map_err(|_| WireError::SchedulerDropped)?;
+
+            client
+                .prompt_with_options(system_prompt, chat_history, &options)
+                .await
+        }
+    }
+}
+
+impl Drop for PromptScheduler {
+    fn drop(&mut self) {
+        self.dispatcher.abort();
+
+        let waiting = std::mem::take(&mut self.shared.queue.lock().unwrap().waiting);
+        drop(waiting);
+    }
+}
+
+async fn dispatch_loop(
+    client: Arc<dyn PromptCore>,
+    shared: Arc<Shared>,
+    rate_limit: RateLimit,
+    options: SchedulerOptions,
+) {
+    loop {
+        let changed = shared.changed.notified();
+        match shared.dispatch(client.clock().now(), rate_limit, &options) {
+            Some(wake_at) => {
+                tokio::select! {
+                    _ = changed => {}
+                    _ = client.clock().sleep_until(wake_at) => {}
+                }
+            }
+            None => changed.await,
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Regroup streamed deltas into sentences or lines.
+//!
+//! Providers stream arbitrary fragments of text. `SentenceAggregator` buffers
+//! them and releases text only at sentence ends (`.`, `!` or `?` followed by
+//! whitespace), newlines, or matches of an extra boundary pattern, flushing
+//! whatever is left when the stream ends. Joining the emitted pieces always
+//! gives back the original text.
+//!
+//! It works on plain strings, so it can sit in front of any client:
+//!
+//! ```
+//! use wire::sentence::SentenceAggregator;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+//! let deltas = SentenceAggregator::wrap(tx);
+//!
+//! for delta in ["Hello th", "ere. How a", "re you?"] {
+//!     deltas.send(delta.to_string()).await.unwrap();
+//! }
+//! drop(deltas);
+//!
+//! assert_eq!(rx.recv().await.as_deref(), Some("Hello there. "));
+//! assert_eq!(rx.recv().await.as_deref(), Some("How are you?"));
+//! # });
+//! ```
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use fancy_regex::Regex;
+use futures_core::Stream;
+
+/// Words ending in a period that do not end a sentence. Matched
+/// case-insensitively against the word before the period.
+pub const DEFAULT_ABBREVIATIONS: &[&str] = &[
+    "e.g.", "i.e.", "etc.", "vs.", "cf.", "approx.", "mr.", "mrs.", "ms.", "dr.", "prof.", "sr.",
+    "jr.", "st.", "no.", "fig.",
+];
+
+#[derive(Clone, Debug)]
+pub struct SentenceAggregator {
+    buffer: String,
+    abbreviations: Vec<String>,
+    boundary: Option<Regex>,
+}
+
+impl SentenceAggregator {
+    pub fn new() -> Self {
+        Self {
+            buffer: String::new(),
+            abbreviations: DEFAULT_ABBREVIATIONS
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            boundary: None,
+        }
+    }
+
+    /// Also emit after every match of `pattern`, e.g. `";\s"` or `"\|"`.
+    pub fn with_boundary(mut self, pattern: &str) -> Result<Self, String> {
+        let boundary = Regex::new(pattern)
+            .map_err(|err| format!("Invalid boundary pattern {}: {}", pattern, err))?;
+        self.boundary = Some(boundary);
+        Ok(self)
+    }
+
+    /// Treat `abbreviation` (including its trailing period) as not ending a
+    /// sentence.
+    pub fn with_abbreviation<S>(mut self, abbreviation: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.abbreviations.push(abbreviation.into().to_lowercase());
+        self
+    }
+
+    /// Add `delta` to the buffer and return every piece that is now complete.
+    pub fn push(&mut self, delta: &str) -> Vec<String> {
+        self.buffer.push_str(delta);
+
+        let mut pieces = Vec::new();
+        while let Some(end) = self.next_boundary() {
+            let rest = self.buffer.split_off(end);
+            pieces.push(std::mem::replace(&mut self.buffer, rest));
+        }
+
+        pieces
+    }
+
+    /// Take whatever is still buffered. Call once the stream has ended.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.buffer))
+        }
+    }
+
+    /// Return a sender to hand to `prompt_stream` in place of `tx`. Deltas
+    /// sent to it reach `tx` as sentences; the remainder is flushed when the
+    /// returned sender is dropped.
+    pub fn wrap(tx: tokio::sync::mpsc::Sender<String>) -> tokio::sync::mpsc::Sender<String> {
+        Self::new().wrap_sender(tx)
+    }
+
+    /// `wrap` with this aggregator's settings.
+    pub fn wrap_sender(
+        mut self,
+        tx: tokio::sync::mpsc::Sender<String>,
+    ) -> tokio::sync::mpsc::Sender<String> {
+        let (deltas_tx, mut deltas_rx) = tokio::sync::mpsc::channel::<String>(tx.max_capacity());
+
+        tokio::spawn(async move {
+            while let Some(delta) = deltas_rx.recv().await {
+                for piece in self.push(&delta) {
+                    if tx.send(piece).await.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if let Some(rest) = self.finish() {
+                let _ = tx.send(rest).await;
+            }
+        });
+
+        deltas_tx
+    }
+
+    /// Regroup a stream of deltas the same way.
+    pub fn stream<S>(self, deltas: S) -> SentenceStream<S>
+    where
+        S: Stream<Item = String> + Unpin,
+    {
+        SentenceStream {
+            deltas,
+            aggregator: self,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Byte offset just past the first complete piece in the buffer.
+    fn next_boundary(&self) -> Option<usize> {
+        let text = self.buffer.as_str();
+        let mut end = None;
+
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        for (i, &(offset, c)) in chars.iter().enumerate() {
+            if c == '\n' {
+                end = Some(offset + 1);
+                break;
+            }
+
+            if !matches!(c, '.' | '!' | '?') {
+                continue;
+            }
+
+            // Swallow runs like `?!` or `."` before looking for whitespace
+            let mut j = i + 1;
+            while j < chars.len() && matches!(chars[j].1, '.' | '!' | '?' | '"' | '\'' | ')') {
+                j += 1;
+            }
+
+            // Can't tell yet whether this ends the sentence
+            let Some(&(next_offset, next)) = chars.get(j) else {
+                break;
+            };
+            if !next.is_whitespace() {
+                continue;
+            }
+
+            if c == '.' && self.is_abbreviation(&text[..offset + 1]) {
+                continue;
+            }
+
+            end = Some(next_offset + next.len_utf8());
+            break;
+        }
+
+        if let Some(boundary) = self.boundary.as_ref() {
+            let matched = boundary
+                .find_iter(text)
+                .filter_map(Result::ok)
+                .map(|m| m.end())
+                .find(|&match_end| match_end > 0);
+
+            end = match (end, matched) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        end
+    }
+
+    /// Whether the word ending at the end of `text` is a known abbreviation.
+    fn is_abbreviation(&self, text: &str) -> bool {
+        let word = text
+            .rsplit(|c: char| c.is_whitespace() || c == '(')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        self.abbreviations.contains(&word)
+    }
+}
+
+impl Default for SentenceAggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stream adapter returned by `SentenceAggregator::stream`.
+pub struct SentenceStream<S> {
+    deltas: S,
+    aggregator: SentenceAggregator,
+    ready: VecDeque<String>,
+    done: bool,
+}
+
+impl<S> Stream for SentenceStream<S>
+where
+    S: Stream<Item = String> + Unpin,
+{
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(piece) = this.ready.pop_front() {
+                return Poll::Ready(Some(piece));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match Pin::new(&mut this.deltas).poll_next(cx) {
+                Poll::Ready(Some(delta)) => this.ready.extend(this.aggregator.push(&delta)),
+                Poll::Ready(None) => {
+                    this.done = true;
+                    this.ready.extend(this.aggregator.finish());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
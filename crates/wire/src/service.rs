@@ -0,0 +1,149 @@
+//! Clients as `tower::Service`s, so tower middleware can wrap them.
+//!
+//! Timeouts, retries, rate and concurrency limits are what tower layers are
+//! for, and wire doesn't redo them. A `PromptRequest` describes one call:
+//! without tools it is a plain prompt and the response is the reply; with
+//! tools it runs the client's tool loop and the response is the history the
+//! loop returns.
+//!
+//! A service is cloned for each call and its futures may outlive `&self`, so
+//! the impl is on `Arc`s of clients: `Arc<OpenAIClient>`, `Arc<RouterClient>`
+//! or `Arc<dyn PromptCore>`. Turn the `Box` from `new_client` into one with
+//! `Arc::from`. Services are always ready; limits come from the layers.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//! use wire::prelude::*;
+//! use wire::service::PromptRequest;
+//!
+//! # async fn run(history: Vec<Message>) -> Result<(), tower::BoxError> {
+//! let client: Arc<dyn PromptCore> = Arc::from(wire::new_client("gpt-4o-mini")?);
+//! let mut service = ServiceBuilder::new()
+//!     .concurrency_limit(4)
+//!     .timeout(Duration::from_secs(30))
+//!     .service(client);
+//!
+//! let response = service
+//!     .ready()
+//!     .await?
+//!     .call(PromptRequest::new("Answer briefly.", history))
+//!     .await?;
+//! println!("{}", response.reply().map_or("", |reply| &reply.content));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::api::PromptCore;
+use crate::config::PromptOptions;
+use crate::error::WireError;
+use crate::types::{Message, Tool};
+
+/// The error of a prompt service, as tower middleware boxes them: the
+/// client's `WireError`, which `downcast_ref` gets back, or the
+/// middleware's own, such as a timeout.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// One call to a prompt service.
+#[derive(Clone, Debug)]
+pub struct PromptRequest {
+    pub system_prompt: String,
+    pub history: Vec<Message>,
+    /// When non-empty, the request runs the client's tool loop with these
+    /// tools.
+    pub tools: Vec<Tool>,
+    /// Plain prompts only: the tool loop runs with the client's own options.
+    pub options: PromptOptions,
+}
+
+impl PromptRequest {
+    pub fn new(system_prompt: impl Into<String>, history: Vec<Message>) -> Self {
+        Self {
+            system_prompt: system_prompt.into(),
+            history,
+            tools: Vec::new(),
+            options: PromptOptions::default(),
+        }
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_options(mut self, options: PromptOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// What a prompt service answers with.
+#[derive(Clone, Debug)]
+// Most responses are replies; boxing them to shrink the rarer tool loop
+// would cost an allocation on every call
+#[allow(clippy::large_enum_variant)]
+pub enum PromptResponse {
+    /// The reply to a request without tools.
+    Reply(Message),
+    /// The history returned by the tool loop, its final answer last.
+    ToolLoop(Vec<Message>),
+}
+
+impl PromptResponse {
+    /// The model's final answer.
+    pub fn reply(&self) -> Option<&Message> {
+        match self {
+            PromptResponse::Reply(message) => Some(message),
+            PromptResponse::ToolLoop(history) => history.last(),
+        }
+    }
+
+    /// Every message of the response: the reply alone, or the tool loop's
+    /// history.
+    pub fn into_messages(self) -> Vec<Message> {
+        match self {
+            PromptResponse::Reply(message) => vec![message],
+            PromptResponse::ToolLoop(history) => history,
+        }
+    }
+}
+
+impl<C> tower_service::Service<PromptRequest> for Arc<C>
+where
+    C: PromptCore + ?Sized + 'static,
+{
+    type Response = PromptResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<PromptResponse, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: PromptRequest) -> Self::Future {
+        let client = Arc::clone(self);
+
+        Box::pin(async move {
+            if request.tools.is_empty() {
+                let reply = client
+                    .prompt_with_options(request.system_prompt, request.history, &request.options)
+                    .await?;
+                return Ok(PromptResponse::Reply(reply));
+            }
+
+            let tools = client.tools().ok_or(WireError::ToolsUnsupported {
+                client: "the client".to_string(),
+            })?;
+            let history = tools
+                .prompt_with_tools(&request.system_prompt, request.history, request.tools)
+                .await?;
+            Ok(PromptResponse::ToolLoop(history))
+        })
+    }
+}
@@ -0,0 +1,104 @@
+//! The request behind a reply, kept so the reply can be reproduced.
+//!
+//! With `ClientOptions::with_request_snapshot(true)`, every reply from a
+//! provider client carries the request that produced it in
+//! `MessageMetadata::request_snapshot`: the body exactly as it was sent, and
+//! the path it went to. The history it was built from may change afterwards;
+//! the snapshot does not. As in the event log, credentials are left out:
+//! headers are not kept and the `key` query parameter is dropped.
+//!
+//! `replay` sends a snapshot again through a client of the same provider,
+//! which supplies the endpoint and the credentials.
+//!
+//! ```no_run
+//! use wire::config::ClientOptions;
+//! use wire::snapshot::replay;
+//! use wire::types::MessageType;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let options = ClientOptions::default().with_request_snapshot(true);
+//! let client = wire::new_client_with_options("gpt-4o-mini", options).unwrap();
+//!
+//! let question = client
+//!     .new_message("Name a prime.".to_string())
+//!     .message_type(MessageType::User)
+//!     .build();
+//! let reply = client
+//!     .prompt("Be brief.".to_string(), vec![question])
+//!     .await
+//!     .unwrap();
+//!
+//! let snapshot = reply.metadata.request_snapshot.unwrap();
+//! let again = replay(&snapshot, client.as_ref()).await.unwrap();
+//! # });
+//! ```
+
+use crate::api::{PromptCore, API};
+use crate::error::WireError;
+
+/// A request as sent; see the module docs.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RequestSnapshot {
+    pub provider: String,
+    pub model: String,
+    /// Path and query, minus any `key` parameter.
+    pub path: String,
+    pub stream: bool,
+    /// The body byte for byte, laid out in the client's `JsonFormat`.
+    pub body: String,
+}
+
+impl RequestSnapshot {
+    pub(crate) fn new(api: &API, path: &str, stream: bool, body: &[u8]) -> Self {
+        let (provider, model) = api.to_strings();
+
+        Self {
+            provider,
+            model,
+            path: crate::event_log::redact_path(path),
+            stream,
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+
+    pub fn body_json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+/// Send `snapshot`'s body to its path again through `client`, which should
+/// be for the same provider, and return the response body as text. A
+/// streamed request's response comes back whole, unparsed.
+///
+/// Only the endpoint and credentials come from `client`; its options, such
+/// as moderation or a content filter, do not apply to the replayed body.
+pub async fn replay(
+    snapshot: &RequestSnapshot,
+    client: &dyn PromptCore,
+) -> Result<String, WireError> {
+    let (http_client, request) = client
+        .build_request(String::new(), Vec::new(), None, snapshot.stream)?
+        .build_split();
+    let mut request = request?;
+
+    let key = request
+        .url()
+        .query_pairs()
+        .find(|(name, _)| name == "key")
+        .map(|(_, key)| key.into_owned());
+    let (path, query) = match snapshot.path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (snapshot.path.as_str(), None),
+    };
+
+    let url = request.url_mut();
+    url.set_path(path);
+    url.set_query(query);
+    if let Some(key) = key {
+        url.query_pairs_mut().append_pair("key", &key);
+    }
+    *request.body_mut() = Some(snapshot.body.clone().into());
+
+    let response = http_client.execute(request).await?;
+    crate::compression::response_text(response).await
+}
@@ -0,0 +1,104 @@
+//! Together AI, which hosts open-weight models (Llama, Qwen, Mixtral, ...)
+//! behind an OpenAI-style chat completions API.
+//!
+//! `TogetherClient` is an `OpenAIClient` pointed at
+//! `api.together.xyz/v1/chat/completions`, keyed by `TOGETHER_API_KEY`,
+//! whose requests name a `TogetherModel` and whose replies carry
+//! `API::Together`. Together takes OpenAI's tool schema for the models it
+//! supports function calling on.
+
+use crate::api::{Provider, TogetherModel, API};
+use crate::config::ClientOptions;
+use crate::openai::{openai_compatible_client, OpenAICompatible};
+
+impl TogetherModel {
+    /// A model as Together names it, e.g.
+    /// `meta-llama/Llama-3.3-70B-Instruct-Turbo`. Any id is accepted;
+    /// Together decides whether it serves the model.
+    pub fn new(name: impl Into<String>) -> Self {
+        TogetherModel(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolve a `together/<model>` identifier. The prefix is required here,
+    /// since Together's ids look like any other hub's.
+    pub fn from_model_name(model: &str) -> Result<Self, String> {
+        match model.strip_prefix("together/") {
+            Some(name) if !name.is_empty() => Ok(TogetherModel::new(name)),
+            _ => Err(format!("Unknown Together model: {}", model)),
+        }
+    }
+
+    /// Return a `(provider, model)` tuple, the model without its prefix.
+    pub fn to_strings(&self) -> (String, String) {
+        ("together".to_string(), self.0.clone())
+    }
+}
+
+/// Takes the model with or without its `together/` prefix.
+impl<'a> From<&'a str> for TogetherModel {
+    fn from(model: &'a str) -> Self {
+        TogetherModel::new(model.strip_prefix("together/").unwrap_or(model))
+    }
+}
+
+impl From<String> for TogetherModel {
+    fn from(model: String) -> Self {
+        TogetherModel::from(model.as_str())
+    }
+}
+
+/// Where Together serves chat completions.
+const TOGETHER: OpenAICompatible = OpenAICompatible {
+    host: "api.together.xyz",
+    path: "/v1/chat/completions",
+    provider: Provider::Together,
+};
+
+openai_compatible_client! {
+    /// Client for Together's OpenAI-compatible chat completions endpoint.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// # let (_server, options) = wire::mock::doctest_client(
+    /// #     "together/meta-llama/Llama-3.3-70B-Instruct-Turbo",
+    /// #     wire::mock::DoctestCall::Prompt,
+    /// # )
+    /// # .await;
+    /// use wire::api::PromptCore;
+    /// use wire::together::TogetherClient;
+    /// use wire::types::MessageType;
+    ///
+    /// let client = TogetherClient::with_options("meta-llama/Llama-3.3-70B-Instruct-Turbo", options);
+    /// let question = client
+    ///     .new_message("What is the weather in Paris?".to_string())
+    ///     .message_type(MessageType::User)
+    ///     .build();
+    ///
+    /// let reply = client
+    ///     .prompt("Answer briefly.".to_string(), vec![question])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(reply.content, "It is sunny in Paris.");
+    /// # });
+    /// ```
+    TogetherClient(TogetherModel),
+    key: "The Together API key, as read when the client was built or refreshed."
+}
+
+impl TogetherClient {
+    /// Construct a client with custom transport settings.
+    pub fn with_options<M>(model: M, options: ClientOptions) -> Self
+    where
+        M: Into<TogetherModel>,
+    {
+        let model = model.into();
+        let openai = TOGETHER.client(API::Together(model.clone()), options);
+
+        Self { model, openai }
+    }
+}
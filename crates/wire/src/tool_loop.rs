@@ -0,0 +1,707 @@
+//! Iteration state and extension points shared by the tool loops.
+//!
+//! Every pass through `prompt_with_tools` is one iteration: a request to the
+//! model followed by any tool calls it asked for. `ClientOptions` can cap the
+//! number of iterations and register `ToolLoopHooks` that see (and may edit)
+//! the history about to be sent on each pass.
+//!
+//! A history that ends in tool calls without outputs (say, persisted by a
+//! process that stopped mid-loop) can be handed straight back to
+//! `prompt_with_tools`: the missing tools run before the first request. Use
+//! `record_tool_outputs` first for calls the application already executed.
+//!
+//! Tools that return more than the model should read (a database dump, a
+//! whole log file) can be reined in with
+//! `ClientOptions::with_tool_output_policy`. Outputs over
+//! `ToolOutputPolicy::max_bytes` reach the model cut down, with a marker
+//! saying how much was left out, while the output message's
+//! `metadata.tool_invocation` keeps everything the tool returned.
+//!
+//! Progress the loop reports (the model's interim text, each tool it is
+//! about to call, warnings) is recorded as `ToolStatus` events as well as
+//! sent to the status channel. `ToolCapable::run_tool_loop` returns them with
+//! the history, so they survive a receiver that was busy, dropped or never
+//! attached.
+//!
+//! Outputs are paired with calls by id, which not every provider hands out:
+//! Gemini and Ollama often leave it off, and some OpenAI-compatible servers
+//! send it empty or give every call in a reply the same one. The loop gives
+//! such calls `call_{iteration}_{index}` ids before running them and records
+//! what it replaced in the call message's `metadata.synthesized_call_ids`.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::API;
+use crate::config::CancellationToken;
+use crate::error::WireError;
+use crate::event_log::{emit, EventLog, WireEvent};
+use crate::types::{
+    FunctionCall, Message, MessageBuilder, MessageMetadata, MessageType, Tool, ToolContext,
+    ToolInvocation,
+};
+use crate::warning::WireWarning;
+
+/// Where the tool loop currently stands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToolIteration {
+    /// Zero-based index of the iteration about to run.
+    pub index: usize,
+    /// The configured `ClientOptions::max_tool_iterations`, if any.
+    pub max_iterations: Option<usize>,
+}
+
+impl ToolIteration {
+    /// Iterations left after this one, when a limit is set.
+    pub fn remaining(&self) -> Option<usize> {
+        self.max_iterations
+            .map(|max| max.saturating_sub(self.index + 1))
+    }
+
+    /// Whether this is the last iteration the budget allows.
+    pub fn is_final(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+}
+
+/// Callbacks invoked by the tool loop.
+pub trait ToolLoopHooks: Send + Sync {
+    /// Called before each request. `pending` is the history that will be sent
+    /// for this iteration only; changes are not carried into the returned
+    /// conversation or into later iterations.
+    fn before_iteration(&self, iteration: &ToolIteration, pending: &mut Vec<Message>) {
+        let _ = (iteration, pending);
+    }
+}
+
+/// Built-in hook that tells the model to wrap up on the final iteration.
+#[derive(Clone, Debug)]
+pub struct BudgetNudge {
+    message: String,
+}
+
+impl BudgetNudge {
+    pub fn new() -> Self {
+        Self {
+            message: "You have no tool calls remaining; answer now with what you have.".to_string(),
+        }
+    }
+
+    pub fn with_message<S>(mut self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.message = message.into();
+        self
+    }
+}
+
+impl Default for BudgetNudge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolLoopHooks for BudgetNudge {
+    fn before_iteration(&self, iteration: &ToolIteration, pending: &mut Vec<Message>) {
+        if !iteration.is_final() {
+            return;
+        }
+
+        // Borrow the API from the conversation; it only matters for display
+        let Some(api) = pending.last().map(|message| message.api.clone()) else {
+            return;
+        };
+
+        pending.push(
+            MessageBuilder::new(api, self.message.clone())
+                .message_type(MessageType::User)
+                .build(),
+        );
+    }
+}
+
+/// The hooks registered on a client, in registration order.
+#[derive(Clone, Default)]
+pub struct ToolHooks(Vec<Arc<dyn ToolLoopHooks>>);
+
+impl ToolHooks {
+    pub fn push<H>(&mut self, hook: H)
+    where
+        H: ToolLoopHooks + 'static,
+    {
+        self.0.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Debug for ToolHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ToolHooks({})", self.0.len())
+    }
+}
+
+/// Which part of an oversized tool output the model gets to see.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolOutputTruncation {
+    /// The first `max_bytes`.
+    #[default]
+    Head,
+    /// The last `max_bytes`, for logs and other output whose end matters most.
+    Tail,
+    /// For JSON, an outline of its structure: keys, array lengths and short
+    /// values. Output that isn't JSON, or whose outline is still too long,
+    /// falls back to `Head`.
+    Summary,
+}
+
+/// Limit on the tool output the loop sends to the model; see the module
+/// docs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToolOutputPolicy {
+    pub max_bytes: usize,
+    pub truncation: ToolOutputTruncation,
+}
+
+impl ToolOutputPolicy {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            truncation: ToolOutputTruncation::default(),
+        }
+    }
+
+    pub fn with_truncation(mut self, truncation: ToolOutputTruncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// `output` as the model should see it, borrowed when it fits.
+    pub fn apply<'a>(&self, output: &'a str) -> Cow<'a, str> {
+        if output.len() <= self.max_bytes {
+            return Cow::Borrowed(output);
+        }
+
+        let summary = match self.truncation {
+            ToolOutputTruncation::Summary => serde_json::from_str(output)
+                .ok()
+                .map(|value| outline(&value))
+                .filter(|outline| outline.len() <= self.max_bytes),
+            _ => None,
+        };
+        if let Some(summary) = summary {
+            return Cow::Owned(format!(
+                "{}...[truncated {}; outline shown]",
+                summary,
+                human_bytes(output.len())
+            ));
+        }
+
+        if self.truncation == ToolOutputTruncation::Tail {
+            let mut start = output.len() - self.max_bytes;
+            while !output.is_char_boundary(start) {
+                start += 1;
+            }
+            return Cow::Owned(format!(
+                "...[truncated {}; {} bytes shown]{}",
+                human_bytes(start),
+                output.len() - start,
+                &output[start..]
+            ));
+        }
+
+        let mut end = self.max_bytes;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        Cow::Owned(format!(
+            "{}...[truncated {}; {} bytes shown]",
+            &output[..end],
+            human_bytes(output.len() - end),
+            end
+        ))
+    }
+}
+
+/// `1.2MB`, `4.0KB`, `512 bytes`.
+fn human_bytes(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size >= KB * KB {
+        format!("{:.1}MB", size / (KB * KB))
+    } else if size >= KB {
+        format!("{:.1}KB", size / KB)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// The top level of `value`, with everything below it reduced to its shape.
+fn outline(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    let shape = |value: &Value| match value {
+        Value::Array(items) => format!("[{} items]", items.len()),
+        Value::Object(fields) => format!("{{{} keys}}", fields.len()),
+        Value::String(text) if text.len() > 32 => format!("<string, {} bytes>", text.len()),
+        other => other.to_string(),
+    };
+
+    match value {
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("{}: {}", Value::from(key.as_str()), shape(value)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Value::Array(items) => match items.first() {
+            Some(first) => format!("[{} items, first: {}]", items.len(), shape(first)),
+            None => "[]".to_string(),
+        },
+        other => shape(other),
+    }
+}
+
+/// How many `ToolStatus` events a tool loop keeps; older ones are dropped
+/// first.
+pub const STATUS_HISTORY: usize = 256;
+
+/// A progress update from the tool loop. `Display` gives the line sent to
+/// the status channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ToolStatus {
+    /// Text the model wrote alongside its tool calls.
+    Interim(String),
+    /// A tool about to run.
+    Calling { id: String, name: String },
+    /// Something the caller should know about the request, such as tool
+    /// schemas crowding out the conversation.
+    Warning(WireWarning),
+    /// A failed request about to be sent again after `wait`: retry number
+    /// `retry` (from 1) under the client's `RetryPolicy`.
+    Retrying {
+        retry: usize,
+        wait: Duration,
+        reason: String,
+    },
+    /// A request held back for `wait` by the client's `RateLimiter`.
+    RateLimited { wait: Duration },
+}
+
+impl std::fmt::Display for ToolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolStatus::Interim(text) => write!(f, "{}", text),
+            ToolStatus::Calling { name, .. } => write!(f, "calling tool {}...", name),
+            ToolStatus::Warning(warning) => write!(f, "warn: {}", warning),
+            ToolStatus::Retrying {
+                retry,
+                wait,
+                reason,
+            } => write!(
+                f,
+                "retry {} in {:.1}s: {}",
+                retry,
+                wait.as_secs_f64(),
+                reason
+            ),
+            ToolStatus::RateLimited { wait } => {
+                write!(f, "rate limited, waiting {:.1}s", wait.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// A finished tool loop: the history `prompt_with_tools` would return and
+/// the progress reported along the way.
+#[derive(Clone, Debug)]
+pub struct ToolLoopResult {
+    pub messages: Vec<Message>,
+    /// The last `STATUS_HISTORY` events, oldest first.
+    pub events: Vec<ToolStatus>,
+    /// Events that didn't fit in `events`.
+    pub dropped_events: usize,
+}
+
+/// The status events of one tool loop, forwarded live when the caller passed
+/// a channel.
+pub(crate) struct StatusLog {
+    tx: Option<tokio::sync::mpsc::Sender<String>>,
+    events: VecDeque<ToolStatus>,
+    dropped: usize,
+}
+
+impl StatusLog {
+    pub(crate) fn new(tx: Option<tokio::sync::mpsc::Sender<String>>) -> Self {
+        Self {
+            tx,
+            events: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Record `status` and send it on, if there is a channel.
+    pub(crate) async fn report(&mut self, status: ToolStatus) {
+        if let Some(tx) = &self.tx {
+            // A receiver that went away doesn't stop the loop; the event is
+            // still on the result
+            let _ = tx.send(status.to_string()).await;
+        }
+
+        if self.events.len() == STATUS_HISTORY {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(status);
+    }
+
+    pub(crate) fn finish(self, messages: Vec<Message>) -> ToolLoopResult {
+        ToolLoopResult {
+            messages,
+            events: self.events.into(),
+            dropped_events: self.dropped,
+        }
+    }
+}
+
+/// Per-call bookkeeping used by the clients' tool loops.
+pub(crate) struct ToolLoop<'a> {
+    hooks: &'a ToolHooks,
+    max_iterations: Option<usize>,
+    cancellation: Option<&'a CancellationToken>,
+    index: usize,
+}
+
+impl<'a> ToolLoop<'a> {
+    pub(crate) fn new(
+        hooks: &'a ToolHooks,
+        max_iterations: Option<usize>,
+        cancellation: Option<&'a CancellationToken>,
+    ) -> Self {
+        Self {
+            hooks,
+            max_iterations,
+            cancellation,
+            index: 0,
+        }
+    }
+
+    /// Index of the iteration started by the last `next_request`.
+    pub(crate) fn iteration(&self) -> usize {
+        self.index.saturating_sub(1)
+    }
+
+    /// Start the next iteration and return the history to send for it, or an
+    /// error once the iteration budget is spent or the loop is cancelled.
+    pub(crate) fn next_request(
+        &mut self,
+        chat_history: &[Message],
+    ) -> Result<Vec<Message>, WireError> {
+        if self.cancellation.is_some_and(|token| token.is_cancelled()) {
+            return Err(WireError::Cancelled {
+                partial: String::new(),
+            });
+        }
+        if let Some(max) = self.max_iterations {
+            if self.index >= max {
+                return Err(WireError::ToolLoopLimit { max });
+            }
+        }
+
+        let iteration = ToolIteration {
+            index: self.index,
+            max_iterations: self.max_iterations,
+        };
+        self.index += 1;
+
+        let mut pending = chat_history.to_vec();
+        for hook in self.hooks.0.iter() {
+            hook.before_iteration(&iteration, &mut pending);
+        }
+
+        Ok(pending)
+    }
+}
+
+/// Tool calls in the last call message of `chat_history` that have no
+/// `FunctionCallOutput` yet. Empty unless the history ends with that message
+/// and (some of) its outputs.
+pub fn pending_tool_calls(chat_history: &[Message]) -> Vec<FunctionCall> {
+    let Some(position) = last_call_position(chat_history) else {
+        return Vec::new();
+    };
+
+    let outputs = &chat_history[position + 1..];
+    if outputs
+        .iter()
+        .any(|message| message.message_type != MessageType::FunctionCallOutput)
+    {
+        return Vec::new();
+    }
+
+    let answered: HashSet<&str> = outputs
+        .iter()
+        .filter_map(|message| message.tool_call_id.as_deref())
+        .collect();
+
+    chat_history[position]
+        .tool_calls
+        .iter()
+        .flatten()
+        .filter(|call| !answered.contains(call.id.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Append outputs the application already computed, keyed by call id, for
+/// pending calls at the end of `chat_history`. Fails without touching the
+/// history if an id doesn't belong to a pending call.
+pub fn record_tool_outputs(
+    chat_history: &mut Vec<Message>,
+    outputs: HashMap<String, String>,
+) -> Result<(), WireError> {
+    let pending = pending_tool_calls(chat_history);
+
+    if let Some(id) = outputs
+        .keys()
+        .find(|id| !pending.iter().any(|call| &call.id == *id))
+    {
+        return Err(WireError::UnknownToolCall { id: id.clone() });
+    }
+
+    let Some(position) = last_call_position(chat_history) else {
+        return Ok(());
+    };
+    let api = chat_history[position].api.clone();
+    let system_prompt = chat_history[position].system_prompt.clone();
+
+    let mut outputs = outputs;
+    for call in pending {
+        if let Some(output) = outputs.remove(&call.id) {
+            chat_history.push(tool_output(
+                &api,
+                &system_prompt,
+                call.id,
+                call.function.name,
+                output,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Give each of one reply's `calls` an id of its own: a call without one, or
+/// with one an earlier call in the reply already has, becomes
+/// `call_{iteration}_{index}`. Returns the ids replaced (empty for a missing
+/// one), keyed by the id that replaced them.
+#[cfg(any(
+    feature = "openai",
+    feature = "gemini",
+    feature = "ollama",
+    feature = "cohere"
+))]
+pub(crate) fn synthesize_call_ids(
+    calls: &mut [FunctionCall],
+    iteration: usize,
+) -> std::collections::BTreeMap<String, String> {
+    let mut kept = HashSet::new();
+    let replace: Vec<bool> = calls
+        .iter()
+        .map(|call| call.id.is_empty() || !kept.insert(call.id.clone()))
+        .collect();
+
+    let mut synthesized = std::collections::BTreeMap::new();
+    for (index, call) in calls.iter_mut().enumerate() {
+        if !replace[index] {
+            continue;
+        }
+
+        let mut id = format!("call_{}_{}", iteration, index);
+        let mut attempt = 2;
+        while kept.contains(&id) {
+            id = format!("call_{}_{}_{}", iteration, index, attempt);
+            attempt += 1;
+        }
+        kept.insert(id.clone());
+        synthesized.insert(id.clone(), std::mem::replace(&mut call.id, id));
+    }
+
+    synthesized
+}
+
+/// Forward the text the model wrote alongside its tool calls ("Let me check
+/// the weather...") to the status log, ahead of the calls themselves.
+pub(crate) async fn report_interim_text(status: &mut StatusLog, content: &str) {
+    let content = content.trim();
+    if !content.is_empty() {
+        status
+            .report(ToolStatus::Interim(content.to_string()))
+            .await;
+    }
+}
+
+/// Run `calls`, requested in `iteration` by the last call message of
+/// `chat_history`, in order and return their `FunctionCallOutput` messages,
+/// cut down to `policy` when one is set.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run_tool_calls(
+    status: &mut StatusLog,
+    events: Option<&EventLog>,
+    policy: Option<&ToolOutputPolicy>,
+    tools: &HashMap<String, Tool>,
+    calls: Vec<FunctionCall>,
+    chat_history: &[Message],
+    iteration: usize,
+    api: &API,
+    system_prompt: &str,
+) -> Result<Vec<Message>, WireError> {
+    let mut outputs = Vec::with_capacity(calls.len());
+    let history = Arc::new(chat_history.to_vec());
+
+    for call in calls {
+        status
+            .report(ToolStatus::Calling {
+                id: call.id.clone(),
+                name: call.function.name.clone(),
+            })
+            .await;
+
+        let tool = tools
+            .get(&call.function.name)
+            .ok_or_else(|| WireError::ToolNotFound {
+                name: call.function.name.clone(),
+            })?
+            .clone();
+
+        emit(events, || WireEvent::ToolCall {
+            id: call.id.clone(),
+            name: call.function.name.clone(),
+            arguments: call.function.arguments.clone(),
+        });
+
+        let tool_args: serde_json::Value =
+            serde_json::from_str(&call.function.arguments).map_err(|err| {
+                WireError::ToolExecution {
+                    name: call.function.name.clone(),
+                    reason: format!("invalid arguments: {}", err),
+                }
+            })?;
+        let arguments = call.function.arguments.clone();
+        let tool_name = tool.name.clone();
+        let context = ToolContext::new(
+            call.id.clone(),
+            tool_name.clone(),
+            iteration,
+            history.clone(),
+        );
+
+        let function_output = tokio::task::spawn_blocking(move || {
+            tool.function
+                .call_with_context(tool_args, &context)
+                .to_string()
+        })
+        .await
+        .map_err(|err| WireError::ToolExecution {
+            name: tool_name.clone(),
+            reason: err.to_string(),
+        })?;
+
+        emit(events, || WireEvent::ToolResult {
+            id: call.id.clone(),
+            name: tool_name.clone(),
+            output: function_output.clone(),
+        });
+
+        let content = match policy {
+            Some(policy) => policy.apply(&function_output).into_owned(),
+            None => function_output.clone(),
+        };
+        let mut output = tool_output(
+            api,
+            system_prompt,
+            call.id.clone(),
+            tool_name.clone(),
+            content,
+        );
+        output.metadata.tool_invocation = Some(ToolInvocation {
+            call_id: call.id,
+            name: tool_name,
+            arguments,
+            truncated: output.content != function_output,
+            output: function_output,
+        });
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+/// Execute the calls `pending_tool_calls` finds in `chat_history` and append
+/// their outputs, so an interrupted loop picks up where it stopped.
+pub(crate) async fn resume_pending_calls(
+    status: &mut StatusLog,
+    events: Option<&EventLog>,
+    policy: Option<&ToolOutputPolicy>,
+    tools: &HashMap<String, Tool>,
+    chat_history: &mut Vec<Message>,
+    system_prompt: &str,
+) -> Result<(), WireError> {
+    let pending = pending_tool_calls(chat_history);
+    let Some(api) = chat_history.last().map(|message| message.api.clone()) else {
+        return Ok(());
+    };
+
+    let outputs = run_tool_calls(
+        status,
+        events,
+        policy,
+        tools,
+        pending,
+        chat_history,
+        0,
+        &api,
+        system_prompt,
+    )
+    .await?;
+    chat_history.extend(outputs);
+
+    Ok(())
+}
+
+fn last_call_position(chat_history: &[Message]) -> Option<usize> {
+    chat_history.iter().rposition(|message| {
+        message
+            .tool_calls
+            .as_ref()
+            .is_some_and(|calls| !calls.is_empty())
+    })
+}
+
+fn tool_output(
+    api: &API,
+    system_prompt: &str,
+    call_id: String,
+    name: String,
+    content: String,
+) -> Message {
+    Message {
+        message_type: MessageType::FunctionCallOutput,
+        content,
+        api: api.clone(),
+        system_prompt: system_prompt.to_string(),
+        tool_call_id: Some(call_id),
+        tool_calls: None,
+        name: Some(name),
+        input_tokens: 0,
+        output_tokens: 0,
+        metadata: MessageMetadata::default(),
+    }
+}
@@ -0,0 +1,393 @@
+//! Tool calling for models without a native function-calling API.
+//!
+//! `TextToolProtocol` describes the available tools in the system prompt and
+//! asks the model to answer with fenced blocks such as
+//!
+//! ````text
+//! ```tool_call
+//! {"name": "lookup_weather", "arguments": {"location": "NYC"}}
+//! ```
+//! ````
+//!
+//! Those blocks are parsed back into `FunctionCall`s, and tool results are
+//! rendered into the conversation as plain text, so the loop works with any
+//! provider that can return text. Clients pick it up through
+//! `ClientOptions::with_tool_transport(ToolTransport::TextProtocol)`.
+
+use std::collections::HashMap;
+
+use crate::api::PromptCore;
+use crate::config::{CancellationToken, PromptOptions};
+use crate::error::WireError;
+use crate::tool_loop::{
+    resume_pending_calls, run_tool_calls, StatusLog, ToolHooks, ToolLoop, ToolLoopResult,
+    ToolOutputPolicy,
+};
+use crate::types::{Function, FunctionCall, Message, MessageType, Tool, ToolSpec};
+use crate::warning::WireWarning;
+
+const TOOL_CALL_FENCE: &str = "```tool_call";
+const TOOL_RESULT_FENCE: &str = "```tool_result";
+
+/// How tool definitions and calls travel between the client and the model.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolTransport {
+    /// The provider's own function-calling API.
+    #[default]
+    Native,
+    /// Tools described in the system prompt, calls parsed from fenced JSON in
+    /// the reply. See `TextToolProtocol`.
+    TextProtocol,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextToolProtocol;
+
+impl TextToolProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Append the tool catalog and calling convention to `system_prompt`.
+    pub fn system_prompt(&self, system_prompt: &str, tools: &[ToolSpec]) -> String {
+        let mut prompt = system_prompt.trim_end().to_string();
+        if !prompt.is_empty() {
+            prompt.push_str("\n\n");
+        }
+
+        prompt.push_str("# Tools\n\n");
+        prompt.push_str("You can call the following tools:\n\n");
+        for tool in tools {
+            prompt.push_str(&format!(
+                "- `{}`: {}\n  Parameters (JSON Schema): {}\n",
+                tool.name, tool.description, tool.parameters
+            ));
+        }
+
+        prompt.push_str(
+            "\nTo call a tool, reply with one fenced block per call and nothing after them:\n\n\
+             ```tool_call\n\
+             {\"name\": \"<tool name>\", \"arguments\": { ... }}\n\
+             ```\n\n\
+             Results come back in ```tool_result blocks. Once you have everything you need, \
+             answer normally without any tool_call blocks.",
+        );
+
+        prompt
+    }
+
+    /// Extract every ```tool_call block from `content`. Calls are numbered
+    /// `call_0`, `call_1`, ... in the order they appear.
+    ///
+    /// Slightly malformed JSON (trailing commas, missing closing brackets, an
+    /// unterminated fence) is repaired; anything beyond that is an error.
+    pub fn parse_tool_calls(&self, content: &str) -> Result<Vec<FunctionCall>, String> {
+        let mut calls = Vec::new();
+
+        for block in fenced_blocks(content, TOOL_CALL_FENCE) {
+            let value = repair_json(block)
+                .ok_or_else(|| format!("Unparseable tool call: {}", block.trim()))?;
+
+            let entries = match value {
+                serde_json::Value::Array(entries) => entries,
+                other => vec![other],
+            };
+
+            for entry in entries {
+                let name = entry
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .ok_or_else(|| format!("Tool call without a name: {}", entry))?;
+
+                let arguments = match entry.get("arguments").or_else(|| entry.get("parameters")) {
+                    None | Some(serde_json::Value::Null) => serde_json::json!({}),
+                    // Some models mimic OpenAI and send the arguments as a string
+                    Some(serde_json::Value::String(arguments)) => repair_json(arguments)
+                        .ok_or_else(|| format!("Unparseable arguments for {}", name))?,
+                    Some(arguments) => arguments.clone(),
+                };
+
+                calls.push(FunctionCall {
+                    id: format!("call_{}", calls.len()),
+                    call_type: "function".to_string(),
+                    function: Function {
+                        name: name.to_string(),
+                        arguments: arguments.to_string(),
+                    },
+                });
+            }
+        }
+
+        Ok(calls)
+    }
+
+    /// `content` with all ```tool_call blocks removed.
+    pub fn strip_tool_calls(&self, content: &str) -> String {
+        let mut text = String::new();
+        let mut rest = content;
+
+        while let Some(start) = rest.find(TOOL_CALL_FENCE) {
+            text.push_str(&rest[..start]);
+            let body = &rest[start + TOOL_CALL_FENCE.len()..];
+            rest = match body.find("```") {
+                Some(end) => &body[end + 3..],
+                None => "",
+            };
+        }
+
+        text.push_str(rest);
+        text.trim().to_string()
+    }
+
+    /// Rewrite tool traffic in `chat_history` as plain text so it can be sent
+    /// to a model without native tool support. Other messages pass through.
+    pub fn render_history(&self, chat_history: &[Message]) -> Vec<Message> {
+        chat_history
+            .iter()
+            .map(|message| {
+                let mut rendered = message.clone();
+
+                if let Some(calls) = message.tool_calls.as_ref() {
+                    let mut content = message.content.trim().to_string();
+                    for call in calls {
+                        let arguments: serde_json::Value =
+                            serde_json::from_str(&call.function.arguments)
+                                .unwrap_or_else(|_| call.function.arguments.clone().into());
+                        let block = serde_json::json!({
+                            "name": call.function.name,
+                            "arguments": arguments,
+                        });
+
+                        if !content.is_empty() {
+                            content.push_str("\n\n");
+                        }
+                        content.push_str(&format!("{}\n{}\n```", TOOL_CALL_FENCE, block));
+                    }
+
+                    rendered.message_type = MessageType::Assistant;
+                    rendered.content = content;
+                    rendered.tool_calls = None;
+                } else if message.message_type == MessageType::FunctionCallOutput {
+                    let output: serde_json::Value = serde_json::from_str(&message.content)
+                        .unwrap_or_else(|_| message.content.clone().into());
+                    let block = serde_json::json!({
+                        "name": message.name,
+                        "output": output,
+                    });
+
+                    rendered.message_type = MessageType::User;
+                    rendered.content = format!("{}\n{}\n```", TOOL_RESULT_FENCE, block);
+                    rendered.tool_call_id = None;
+                    rendered.name = None;
+                }
+
+                rendered
+            })
+            .collect()
+    }
+}
+
+/// The body of every block opened with `fence`. An unterminated final block
+/// runs to the end of `content`.
+fn fenced_blocks<'a>(content: &'a str, fence: &str) -> Vec<&'a str> {
+    let mut blocks = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find(fence) {
+        let body = &rest[start + fence.len()..];
+        match body.find("```") {
+            Some(end) => {
+                blocks.push(&body[..end]);
+                rest = &body[end + 3..];
+            }
+            None => {
+                blocks.push(body);
+                break;
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Parse `input` as JSON, fixing the mistakes models commonly make: trailing
+/// commas, unclosed strings/objects/arrays and chatter after the value.
+pub(crate) fn repair_json(input: &str) -> Option<serde_json::Value> {
+    let input = input.trim();
+    if let Ok(value) = serde_json::from_str(input) {
+        return Some(value);
+    }
+
+    let start = input.find(['{', '['])?;
+    let chars: Vec<char> = input[start..].chars().collect();
+
+    let mut repaired = String::new();
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if in_string {
+            repaired.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                repaired.push(c);
+            }
+            '{' => {
+                closers.push('}');
+                repaired.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                repaired.push(c);
+            }
+            '}' | ']' => {
+                if closers.pop() != Some(c) {
+                    return None;
+                }
+                repaired.push(c);
+                if closers.is_empty() {
+                    break;
+                }
+            }
+            ',' => {
+                let next = chars[idx + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']') | None) {
+                    repaired.push(c);
+                }
+            }
+            _ => repaired.push(c),
+        }
+    }
+
+    if in_string {
+        repaired.push('"');
+    }
+    while let Some(closer) = closers.pop() {
+        let trimmed = repaired.trim_end().trim_end_matches(',').len();
+        repaired.truncate(trimmed);
+        repaired.push(closer);
+    }
+
+    serde_json::from_str(&repaired).ok()
+}
+
+/// The warning for a `ToolChoice` set on a `provider` client using the text
+/// protocol, which can't enforce one.
+#[cfg(any(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "cohere"
+))]
+pub(crate) fn text_protocol_tool_choice(provider: crate::api::Provider) -> WireWarning {
+    WireWarning::option_ignored(
+        "tool_choice",
+        provider,
+        "the text tool protocol can't enforce it",
+    )
+}
+
+/// Tool loop for `ToolTransport::TextProtocol`, built on plain `prompt` calls
+/// so it works with any client.
+///
+/// `warnings` are those the client raised before the loop, added to each
+/// reply's own.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn prompt_with_text_tools<P>(
+    client: &P,
+    tool_hooks: &ToolHooks,
+    max_iterations: Option<usize>,
+    cancellation: Option<&CancellationToken>,
+    tool_output_policy: Option<&ToolOutputPolicy>,
+    warnings: Vec<WireWarning>,
+    mut status: StatusLog,
+    system_prompt: &str,
+    chat_history: Vec<Message>,
+    tools: Vec<Tool>,
+) -> Result<ToolLoopResult, WireError>
+where
+    P: PromptCore + ?Sized,
+{
+    let protocol = TextToolProtocol::new();
+    let specs: Vec<ToolSpec> = tools.iter().map(Tool::spec).collect();
+    let tool_map: HashMap<String, Tool> = tools.into_iter().map(|t| (t.name.clone(), t)).collect();
+    let tool_system_prompt = protocol.system_prompt(system_prompt, &specs);
+
+    let mut chat_history = chat_history;
+    resume_pending_calls(
+        &mut status,
+        client.event_log(),
+        tool_output_policy,
+        &tool_map,
+        &mut chat_history,
+        system_prompt,
+    )
+    .await?;
+    let mut tool_loop = ToolLoop::new(tool_hooks, max_iterations, cancellation);
+    let mut turn = 0;
+
+    loop {
+        let pending = tool_loop.next_request(&chat_history)?;
+        // Tool results are rendered as user turns; the caller's input was
+        // already moderated before the loop started
+        let mut response = client
+            .prompt_with_options(
+                tool_system_prompt.clone(),
+                protocol.render_history(&pending),
+                &PromptOptions::new().with_bypass_moderation(true),
+            )
+            .await?;
+        response.system_prompt = system_prompt.to_string();
+        response
+            .metadata
+            .warnings
+            .splice(0..0, warnings.iter().cloned());
+
+        let mut tool_calls = protocol
+            .parse_tool_calls(&response.content)
+            .map_err(|reason| WireError::MalformedToolCall { reason })?;
+        if tool_calls.is_empty() {
+            chat_history.push(response);
+            break;
+        }
+
+        for call in tool_calls.iter_mut() {
+            call.id = format!("call_{}_{}", turn, call.id.trim_start_matches("call_"));
+        }
+        turn += 1;
+
+        let api = response.api.clone();
+        response.message_type = MessageType::FunctionCall;
+        response.content = protocol.strip_tool_calls(&response.content);
+        response.tool_calls = Some(tool_calls.clone());
+        chat_history.push(response);
+
+        let outputs = run_tool_calls(
+            &mut status,
+            client.event_log(),
+            tool_output_policy,
+            &tool_map,
+            tool_calls,
+            &chat_history,
+            tool_loop.iteration(),
+            &api,
+            system_prompt,
+        )
+        .await?;
+        chat_history.extend(outputs);
+    }
+
+    Ok(status.finish(chat_history))
+}
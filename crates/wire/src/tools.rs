@@ -0,0 +1,136 @@
+//! A catalog of tools whose definitions can live outside the binary.
+//!
+//! Specs are plain data (`ToolSpec`) and can be loaded from JSON; the
+//! implementations are bound by name at startup. `ToolRegistry::tools` refuses
+//! to hand out a catalog that still has unbound specs, so a typo in a config
+//! file fails early instead of when the model first calls the tool.
+
+use std::collections::HashMap;
+
+use crate::types::{Tool, ToolFunction, ToolSpec};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ToolRegistryError {
+    /// The JSON catalog could not be parsed.
+    Parse(String),
+    /// Two specs share the same name.
+    DuplicateTool(String),
+    /// An implementation was bound to a name with no matching spec.
+    UnknownTool(String),
+    /// These specs have no implementation bound to them.
+    Unbound(Vec<String>),
+}
+
+impl std::fmt::Display for ToolRegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolRegistryError::Parse(err) => write!(f, "Invalid tool catalog: {}", err),
+            ToolRegistryError::DuplicateTool(name) => write!(f, "Duplicate tool: {}", name),
+            ToolRegistryError::UnknownTool(name) => write!(f, "No spec for tool: {}", name),
+            ToolRegistryError::Unbound(names) => {
+                write!(f, "Tools without an implementation: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ToolRegistryError {}
+
+#[derive(Debug, Default)]
+pub struct ToolRegistry {
+    specs: Vec<ToolSpec>,
+    functions: HashMap<String, Box<dyn ToolFunction>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a catalog from a JSON array of specs. Nothing is bound yet.
+    pub fn from_json(json: &str) -> Result<Self, ToolRegistryError> {
+        let specs: Vec<ToolSpec> =
+            serde_json::from_str(json).map_err(|err| ToolRegistryError::Parse(err.to_string()))?;
+
+        let mut registry = Self::new();
+        for spec in specs {
+            registry.add_spec(spec)?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Serialize the specs (not the implementations) as a JSON array.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.specs).expect("tool specs always serialize")
+    }
+
+    pub fn add_spec(&mut self, spec: ToolSpec) -> Result<(), ToolRegistryError> {
+        if self.spec(&spec.name).is_some() {
+            return Err(ToolRegistryError::DuplicateTool(spec.name));
+        }
+
+        self.specs.push(spec);
+        Ok(())
+    }
+
+    /// Add a complete tool, spec and implementation together.
+    pub fn register(&mut self, tool: Tool) -> Result<(), ToolRegistryError> {
+        self.add_spec(tool.spec())?;
+        self.functions.insert(tool.name, tool.function);
+        Ok(())
+    }
+
+    /// Attach the implementation for the spec called `name`, replacing any
+    /// earlier binding.
+    pub fn bind<F>(&mut self, name: &str, function: F) -> Result<(), ToolRegistryError>
+    where
+        F: ToolFunction + 'static,
+    {
+        if self.spec(name).is_none() {
+            return Err(ToolRegistryError::UnknownTool(name.to_string()));
+        }
+
+        self.functions.insert(name.to_string(), Box::new(function));
+        Ok(())
+    }
+
+    pub fn spec(&self, name: &str) -> Option<&ToolSpec> {
+        self.specs.iter().find(|spec| spec.name == name)
+    }
+
+    pub fn specs(&self) -> &[ToolSpec] {
+        &self.specs
+    }
+
+    /// Names of specs that still lack an implementation, in catalog order.
+    pub fn unbound(&self) -> Vec<String> {
+        self.specs
+            .iter()
+            .filter(|spec| !self.functions.contains_key(&spec.name))
+            .map(|spec| spec.name.clone())
+            .collect()
+    }
+
+    /// The full tool list for `Prompt::prompt_with_tools`. Fails if any spec
+    /// is unbound.
+    pub fn tools(&self) -> Result<Vec<Tool>, ToolRegistryError> {
+        let unbound = self.unbound();
+        if !unbound.is_empty() {
+            return Err(ToolRegistryError::Unbound(unbound));
+        }
+
+        Ok(self
+            .specs
+            .iter()
+            .map(|spec| Tool {
+                function_type: "function".to_string(),
+                name: spec.name.clone(),
+                description: spec.description.clone(),
+                parameters: spec.parameters.clone(),
+                strict: spec.strict,
+                function: self.functions[&spec.name].clone(),
+            })
+            .collect())
+    }
+}
@@ -1,26 +1,94 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Poll;
+
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::sync::mpsc::Sender;
 
+use crate::config::{OnFull, PromptOptions, StreamOptions};
+use crate::error::WireError;
+use crate::metrics::LatencyStats;
+use crate::snapshot::RequestSnapshot;
+use crate::warning::WireWarning;
 use crate::API;
 
-#[derive(PartialEq, Clone, Debug, serde::Serialize, serde::Deserialize)]
+// Variant names are the serialized form; the lowercase role names are also
+// accepted when reading since some callers persisted `to_string()` output.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum MessageType {
+    #[serde(alias = "system")]
     System,
+    #[serde(alias = "user")]
     User,
+    #[serde(alias = "assistant")]
     Assistant,
+    #[serde(alias = "function")]
     FunctionCall,
+    #[serde(alias = "tool")]
     FunctionCallOutput,
 }
 
 // TODO: Refactor types for the Responses API instead of the completions API
 
+impl std::fmt::Display for MessageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let role = match self {
+            MessageType::System => "system",
+            MessageType::User => "user",
+            MessageType::Assistant => "assistant",
+            MessageType::FunctionCall => "function",
+            MessageType::FunctionCallOutput => "tool",
+        };
+
+        f.write_str(role)
+    }
+}
+
+/// Parses role strings, inverting `Display`:
+///
+/// | role                     | `MessageType`        |
+/// |--------------------------|----------------------|
+/// | `system`, `developer`    | `System`             |
+/// | `user`                   | `User`               |
+/// | `assistant`, `model`     | `Assistant`          |
+/// | `function`               | `FunctionCall`       |
+/// | `tool`                   | `FunctionCallOutput` |
+///
+/// `developer` is OpenAI's newer name for the system role and `model` is
+/// Gemini's assistant role. Providers mark tool calls on an assistant turn
+/// rather than with a role of their own; use `MessageType::from_role` for
+/// those.
+impl std::str::FromStr for MessageType {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "system" | "developer" => Ok(MessageType::System),
+            "user" => Ok(MessageType::User),
+            "assistant" | "model" => Ok(MessageType::Assistant),
+            "function" => Ok(MessageType::FunctionCall),
+            "tool" => Ok(MessageType::FunctionCallOutput),
+            _ => Err(format!("Unknown role: {}", role)),
+        }
+    }
+}
+
+impl TryFrom<&str> for MessageType {
+    type Error = String;
+
+    fn try_from(role: &str) -> Result<Self, Self::Error> {
+        role.parse()
+    }
+}
+
 impl MessageType {
-    pub fn to_string(&self) -> String {
-        match self {
-            MessageType::System => "system".to_string(),
-            MessageType::User => "user".to_string(),
-            MessageType::Assistant => "assistant".to_string(),
-            MessageType::FunctionCall => "function".to_string(),
-            MessageType::FunctionCallOutput => "tool".to_string(),
+    /// Parse `role` as in `FromStr`, treating an assistant turn that carries
+    /// tool calls as a `FunctionCall`.
+    pub fn from_role(role: &str, has_tool_calls: bool) -> Result<Self, String> {
+        match role.parse()? {
+            MessageType::Assistant if has_tool_calls => Ok(MessageType::FunctionCall),
+            message_type => Ok(message_type),
         }
     }
 }
@@ -37,12 +105,63 @@ pub struct Tool {
     pub name: String,
     pub description: String,
     pub parameters: serde_json::Value,
+    /// Ask for strict schema adherence where the provider supports it
+    /// (currently OpenAI). Off by default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
     #[serde(skip)]
     pub function: Box<dyn ToolFunction>,
 }
 
+impl Tool {
+    /// The serializable part of the tool: everything a provider needs to
+    /// advertise it.
+    pub fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: self.parameters.clone(),
+            strict: self.strict,
+        }
+    }
+
+    /// Enable or disable strict argument checking; see `Tool::strict`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Reattach an implementation to a spec, e.g. one loaded from a config
+    /// file.
+    pub fn from_spec<F>(spec: ToolSpec, function: F) -> Self
+    where
+        F: ToolFunction + 'static,
+    {
+        Tool {
+            function_type: "function".to_string(),
+            name: spec.name,
+            description: spec.description,
+            parameters: spec.parameters,
+            strict: spec.strict,
+            function: Box::new(function),
+        }
+    }
+}
+
+/// Name, description and JSON schema of a tool, without its implementation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
+    // Some OpenAI-compatible servers leave it out; see `tool_loop`
+    #[serde(default)]
     pub id: String,
     #[serde(rename = "type")]
     pub call_type: String,
@@ -56,16 +175,20 @@ pub struct Function {
 }
 
 // TODO: Hideous type. Move the tool stuff out of here.
+// NOTE: The serialized form is covered by the fixtures in `tests/fixtures/schema`;
+//       read the README there before changing any serde attribute
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Message {
     // TODO: This gets mapped to `role` in `build_request` and should be more clearly named
     pub message_type: MessageType,
 
-    #[serde(skip_serializing_if = "String::is_empty")]
+    // Omitted when empty, so it has to default when reading back
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub content: String,
     pub api: API,
 
     // TODO: Do we really need this with _every_ message?
+    #[serde(default)]
     pub system_prompt: String,
 
     // Tool calls made by the model
@@ -86,6 +209,675 @@ pub struct Message {
     pub input_tokens: usize,
     #[serde(skip)]
     pub output_tokens: usize,
+
+    // Details about the exchange that produced this message, not part of the
+    // conversation itself
+    #[serde(skip)]
+    pub metadata: MessageMetadata,
+}
+
+/// Response details attached to messages returned by the clients.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageMetadata {
+    pub latency: Option<LatencyStats>,
+    /// Set when the content was cut short by `PromptOptions::max_response_bytes`.
+    pub truncated: Option<Truncation>,
+    /// Ordering of the deltas sent by a streaming prompt.
+    pub sequence: Option<StreamSequence>,
+    /// Bytes of content a streaming prompt sent over its channel, counted
+    /// even when `PromptOptions::discard_streamed_content` left the message
+    /// empty.
+    pub content_bytes: Option<usize>,
+    /// Name of the `RouterClient` route that answered, when one did.
+    pub route: Option<String>,
+    /// Set when a stream's final event was cut off mid-JSON; the content is
+    /// what arrived before it.
+    pub truncated_stream: Option<TruncatedStream>,
+    /// The request that produced this message, when the client was built
+    /// with `ClientOptions::with_request_snapshot`.
+    pub request_snapshot: Option<Arc<RequestSnapshot>>,
+    /// The provider's id for the request, from its `x-request-id` or
+    /// `request-id` response header; quote it to the provider's support.
+    pub request_id: Option<String>,
+    /// The capacity that served the request, as Anthropic reports it in
+    /// `usage.service_tier`: `"standard"`, `"priority"`, ...
+    pub service_tier: Option<String>,
+    /// The call behind a tool output the tool loop ran, with everything the
+    /// tool returned.
+    pub tool_invocation: Option<ToolInvocation>,
+    /// Ids the tool loop gave this message's calls in place of the
+    /// provider's, each mapped to the id the provider sent: empty when it
+    /// sent none, or the one an earlier call in the reply already had.
+    pub synthesized_call_ids: BTreeMap<String, String>,
+    /// What the client changed or left out of the request behind this
+    /// message; see `warning`.
+    pub warnings: Vec<WireWarning>,
+    /// The sources a search-backed model drew on, as Perplexity reports
+    /// them alongside its reply.
+    pub citations: Option<Citations>,
+}
+
+impl MessageMetadata {
+    /// Why the response ended, when it ended early: `Other("truncated_stream")`
+    /// for a stream whose final event was cut off.
+    pub fn finish_reason(&self) -> Option<FinishReason> {
+        self.truncated_stream
+            .as_ref()
+            .map(|_| FinishReason::Other(TruncatedStream::FINISH_REASON.to_string()))
+    }
+}
+
+/// Why a response ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FinishReason {
+    /// The model finished before the deadline.
+    Complete,
+    /// The deadline passed first; the content is what had streamed in by then.
+    Deadline,
+    /// The response ended early for another reason, e.g. `"truncated_stream"`.
+    Other(String),
+}
+
+impl FinishReason {
+    /// A provider's stop reason: `Complete` for the model finishing on its
+    /// own, `Other` with the provider's word for anything else, e.g.
+    /// `"length"` or `"tool_use"`.
+    #[cfg(any(feature = "openai", feature = "anthropic", feature = "gemini"))]
+    pub(crate) fn from_provider(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" | "STOP" => FinishReason::Complete,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// A response that may have been cut off by a deadline.
+#[derive(Clone, Debug)]
+pub struct PartialMessage {
+    pub message: Message,
+    pub finish_reason: FinishReason,
+}
+
+impl PartialMessage {
+    pub fn is_partial(&self) -> bool {
+        self.finish_reason != FinishReason::Complete
+    }
+}
+
+/// What a streaming prompt reports as the response arrives: content and
+/// everything around it. `prompt_stream_events` yields these, ending with the
+/// assembled `Reply`; `PromptOptions::events` receives them from
+/// `prompt_stream_with_options`. The plain `String` channel only gets
+/// `ContentDelta`s.
+#[derive(Clone, Debug)]
+pub struct StreamEvent {
+    /// Counts the events of one stream from 0. A gap means events were
+    /// dropped, as `OnFull::DropOldest` does when the consumer falls behind.
+    pub seq: u64,
+    pub kind: StreamEventKind,
+}
+
+/// What a `StreamEvent` reports.
+#[derive(Clone, Debug)]
+// Only the last event is a reply, and it is moved out right away
+#[allow(clippy::large_enum_variant)]
+pub enum StreamEventKind {
+    /// The model began to answer. Sent once, just before the first
+    /// `ContentDelta`, `Thinking` or `ToolCallStart`, to time the first
+    /// token.
+    FirstToken,
+    /// Response text, as the `String` channel would have received it.
+    ContentDelta(String),
+    /// The model began a tool call. `index` tells its argument deltas apart
+    /// from those of other calls in the same response.
+    ToolCallStart {
+        index: usize,
+        id: String,
+        name: String,
+    },
+    /// The next piece of a tool call's JSON arguments.
+    ToolCallArgumentsDelta { index: usize, delta: String },
+    /// Reasoning the provider streams apart from the content.
+    Thinking(String),
+    /// Token counts so far, as the provider reports them; a later count
+    /// replaces an earlier one. A provider that reports input and output
+    /// apart sends the other as 0.
+    Usage { input: usize, output: usize },
+    /// The provider said why the response ended.
+    Done { finish_reason: FinishReason },
+    /// The assembled reply. Always the last item `prompt_stream_events`
+    /// yields for a stream that didn't fail; never sent to
+    /// `PromptOptions::events`.
+    Reply(Message),
+}
+
+impl StreamEventKind {
+    /// Whether this is something the model said, as opposed to an account
+    /// of the response.
+    fn is_output(&self) -> bool {
+        matches!(
+            self,
+            StreamEventKind::ContentDelta(_)
+                | StreamEventKind::Thinking(_)
+                | StreamEventKind::ToolCallStart { .. }
+        )
+    }
+}
+
+/// How the deltas of a streamed response were numbered.
+///
+/// Every string sent on the stream channel gets the next `seq`, starting at
+/// 0, so a subscriber that received `final_seq + 1` deltas received them all.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamSequence {
+    /// `seq` of the last delta sent, or `None` if nothing was sent.
+    pub final_seq: Option<u64>,
+    /// The content blocks the deltas belonged to, in stream order. Only
+    /// Anthropic streams have more than one block.
+    pub blocks: Vec<ContentBlockSpan>,
+}
+
+/// A run of deltas from one content block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentBlockSpan {
+    /// The provider's index for the block.
+    pub index: usize,
+    pub first_seq: u64,
+    pub last_seq: u64,
+}
+
+/// Numbers the deltas a stream processor sends.
+#[derive(Debug, Default)]
+pub(crate) struct DeltaSequencer {
+    sequence: StreamSequence,
+}
+
+impl DeltaSequencer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the next `seq` to a delta from content block `block_index`.
+    pub(crate) fn record(&mut self, block_index: usize) -> u64 {
+        let seq = self.sequence.final_seq.map_or(0, |last| last + 1);
+        self.sequence.final_seq = Some(seq);
+
+        match self.sequence.blocks.last_mut() {
+            Some(block) if block.index == block_index => block.last_seq = seq,
+            _ => self.sequence.blocks.push(ContentBlockSpan {
+                index: block_index,
+                first_seq: seq,
+                last_seq: seq,
+            }),
+        }
+
+        seq
+    }
+
+    pub(crate) fn finish(self) -> StreamSequence {
+        self.sequence
+    }
+}
+
+/// The end of a stream whose final event did not parse because the
+/// connection closed partway through it, typically a proxy timing out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruncatedStream {
+    /// The undecodable final event, as received.
+    pub tail: String,
+}
+
+impl TruncatedStream {
+    /// The `FinishReason::Other` reported for a truncated stream.
+    pub const FINISH_REASON: &'static str = "truncated_stream";
+}
+
+/// Sources behind a reply. The content's `[1]`, `[2]`, ... markers index
+/// into `urls`, counting from one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Citations {
+    pub urls: Vec<String>,
+    /// The search results the reply was written from, with titles and
+    /// dates when the provider gives them.
+    pub search_results: Vec<SearchResult>,
+}
+
+impl Citations {
+    /// The `citations` and `search_results` of a response body or stream
+    /// chunk, or `None` if it has neither.
+    #[cfg(feature = "openai")]
+    pub(crate) fn from_response(response_json: &serde_json::Value) -> Option<Self> {
+        let urls = response_json.get("citations").and_then(|v| v.as_array());
+        let search_results = response_json
+            .get("search_results")
+            .and_then(|v| v.as_array());
+        if urls.is_none() && search_results.is_none() {
+            return None;
+        }
+
+        Some(Citations {
+            urls: urls
+                .into_iter()
+                .flatten()
+                .filter_map(|url| url.as_str().map(str::to_string))
+                .collect(),
+            search_results: search_results
+                .into_iter()
+                .flatten()
+                .filter_map(|result| serde_json::from_value(result.clone()).ok())
+                .collect(),
+        })
+    }
+}
+
+/// One search result a reply cites.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    #[serde(default)]
+    pub title: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+/// A tool call run by a tool loop. `output` is what the tool returned, in
+/// full, even when `tool_loop::ToolOutputPolicy` cut down the message content
+/// the model saw.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolInvocation {
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    pub output: String,
+    /// Whether the message content is a cut-down form of `output`.
+    pub truncated: bool,
+}
+
+/// How a response was cut down to fit `PromptOptions::max_response_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Truncation {
+    /// The configured cap; the content is at most this many bytes.
+    pub limit: usize,
+    /// Size of the response content before truncation. For streams this is
+    /// what had arrived when reading stopped, so the real response may be
+    /// larger still.
+    pub original_bytes: usize,
+}
+
+/// Enforces `PromptOptions::max_response_bytes` on response content, whether
+/// it arrives all at once or delta by delta.
+#[derive(Debug)]
+pub(crate) struct ContentCap {
+    limit: Option<usize>,
+    kept: usize,
+    seen: usize,
+}
+
+impl ContentCap {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            kept: 0,
+            seen: 0,
+        }
+    }
+
+    /// Account for `delta` and return the prefix of it that still fits, cut on
+    /// a character boundary.
+    pub(crate) fn admit<'a>(&mut self, delta: &'a str) -> &'a str {
+        self.seen += delta.len();
+
+        let kept = match self.limit {
+            None => delta,
+            Some(limit) => {
+                let mut end = limit.saturating_sub(self.kept).min(delta.len());
+                while !delta.is_char_boundary(end) {
+                    end -= 1;
+                }
+                &delta[..end]
+            }
+        };
+
+        self.kept += kept.len();
+        kept
+    }
+
+    /// Apply the cap to a complete response.
+    pub(crate) fn truncate(&mut self, content: String) -> String {
+        let kept = self.admit(&content).len();
+        let mut content = content;
+        content.truncate(kept);
+        content
+    }
+
+    pub(crate) fn exceeded(&self) -> bool {
+        self.seen > self.kept
+    }
+
+    pub(crate) fn truncation(&self) -> Option<Truncation> {
+        match self.limit {
+            Some(limit) if self.exceeded() => Some(Truncation {
+                limit,
+                original_bytes: self.seen,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Where a stream processor puts what it reads: every event in the
+/// `EventSlot` of the prompt's event stream, or for a raw transport, content
+/// deltas into its channel. A copy of the content is kept only when the full
+/// response is wanted.
+#[derive(Debug)]
+pub(crate) struct StreamedContent {
+    content: Option<String>,
+    bytes: usize,
+    destination: Destination,
+    /// `seq` of the next event.
+    seq: u64,
+    first_token_sent: bool,
+}
+
+#[derive(Debug)]
+enum Destination {
+    /// A raw transport's channel, which only takes content.
+    Text(Sender<String>),
+    Events(EventSlot),
+}
+
+/// Where a client's stream processor leaves each event for its
+/// `prompt_stream_events` stream to hand out, one at a time.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EventSlot(Arc<Mutex<Option<StreamEvent>>>);
+
+impl EventSlot {
+    /// Leave `event` and wait until it has been taken. The stream taking it
+    /// is what polls the processor, so there is nobody to wake.
+    async fn put(&self, event: StreamEvent) {
+        *self.lock() = Some(event);
+        std::future::poll_fn(|_| {
+            if self.lock().is_some() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
+    /// The event left since the last call, if any.
+    pub(crate) fn take(&self) -> Option<StreamEvent> {
+        self.lock().take()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<StreamEvent>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl StreamedContent {
+    /// For `RawTransport::process_stream`: the content goes to `tx` and is
+    /// kept.
+    pub(crate) fn new(tx: &Sender<String>) -> Self {
+        Self {
+            content: Some(String::new()),
+            bytes: 0,
+            destination: Destination::Text(tx.clone()),
+            seq: 0,
+            first_token_sent: false,
+        }
+    }
+
+    /// For a streaming prompt: events go to `slot`, and the content is kept
+    /// unless `options` discard it.
+    pub(crate) fn for_prompt(options: &PromptOptions, slot: EventSlot) -> Self {
+        Self {
+            content: (!options.discard_streamed_content).then(String::new),
+            bytes: 0,
+            destination: Destination::Events(slot),
+            seq: 0,
+            first_token_sent: false,
+        }
+    }
+
+    /// Count `delta`, keep a copy if accumulating and send it on.
+    pub(crate) async fn forward(&mut self, delta: String) -> Result<(), WireError> {
+        self.bytes += delta.len();
+        if let Some(content) = &mut self.content {
+            content.push_str(&delta);
+        }
+
+        self.report(StreamEventKind::ContentDelta(delta)).await
+    }
+
+    /// Send an event on, if its destination takes it: a raw transport's
+    /// channel only takes content.
+    pub(crate) async fn report(&mut self, kind: StreamEventKind) -> Result<(), WireError> {
+        let slot = match &self.destination {
+            Destination::Events(slot) => slot.clone(),
+            Destination::Text(tx) => {
+                if let StreamEventKind::ContentDelta(delta) = kind {
+                    tx.send(delta).await?;
+                }
+                return Ok(());
+            }
+        };
+
+        if !self.first_token_sent && kind.is_output() {
+            self.first_token_sent = true;
+            slot.put(self.next(StreamEventKind::FirstToken)).await;
+        }
+        slot.put(self.next(kind)).await;
+        Ok(())
+    }
+
+    fn next(&mut self, kind: StreamEventKind) -> StreamEvent {
+        let event = StreamEvent {
+            seq: self.seq,
+            kind,
+        };
+        self.seq += 1;
+        event
+    }
+
+    /// The content kept so far, empty when not accumulating.
+    pub(crate) fn partial(&self) -> String {
+        self.content.clone().unwrap_or_default()
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// The accumulated content, empty when not accumulating.
+    pub(crate) fn finish(self) -> String {
+        self.content.unwrap_or_default()
+    }
+}
+
+/// Hands the events of a streaming prompt to its caller: each to
+/// `PromptOptions::events` if set, else the content into the prompt's
+/// channel, according to its `StreamOptions`.
+pub(crate) struct Forwarder<'a> {
+    sink: Sink<'a>,
+    options: &'a StreamOptions,
+    /// Events held back by `OnFull::DropOldest`, oldest first.
+    backlog: VecDeque<StreamEvent>,
+    dropped: usize,
+    /// Set once a send has waited longer than `stall_warning`.
+    stalled: Option<WireWarning>,
+}
+
+fn content_event(seq: u64, delta: String) -> StreamEvent {
+    StreamEvent {
+        seq,
+        kind: StreamEventKind::ContentDelta(delta),
+    }
+}
+
+/// The channel a `Forwarder` delivers to.
+#[derive(Clone, Copy)]
+enum Sink<'a> {
+    /// The prompt's own channel, which only takes content.
+    Text(&'a Sender<String>),
+    Events(&'a Sender<StreamEvent>),
+}
+
+impl Sink<'_> {
+    fn try_send(self, event: StreamEvent) -> Result<(), TrySendError<StreamEvent>> {
+        match (self, event) {
+            (Sink::Events(tx), event) => tx.try_send(event),
+            (
+                Sink::Text(tx),
+                StreamEvent {
+                    seq,
+                    kind: StreamEventKind::ContentDelta(delta),
+                },
+            ) => tx.try_send(delta).map_err(|err| match err {
+                TrySendError::Full(delta) => TrySendError::Full(content_event(seq, delta)),
+                TrySendError::Closed(delta) => TrySendError::Closed(content_event(seq, delta)),
+            }),
+            (Sink::Text(_), _) => Ok(()),
+        }
+    }
+
+    async fn send(self, event: StreamEvent) -> Result<(), SendError<StreamEvent>> {
+        match (self, event) {
+            (Sink::Events(tx), event) => tx.send(event).await,
+            (
+                Sink::Text(tx),
+                StreamEvent {
+                    seq,
+                    kind: StreamEventKind::ContentDelta(delta),
+                },
+            ) => tx
+                .send(delta)
+                .await
+                .map_err(|SendError(delta)| SendError(content_event(seq, delta))),
+            (Sink::Text(_), _) => Ok(()),
+        }
+    }
+
+    /// Whether `kind` goes down this channel at all.
+    fn takes(self, kind: &StreamEventKind) -> bool {
+        match self {
+            Sink::Text(_) => matches!(kind, StreamEventKind::ContentDelta(_)),
+            Sink::Events(_) => true,
+        }
+    }
+
+    async fn closed(self) {
+        match self {
+            Sink::Text(tx) => tx.closed().await,
+            Sink::Events(tx) => tx.closed().await,
+        }
+    }
+
+    fn max_capacity(self) -> usize {
+        match self {
+            Sink::Text(tx) => tx.max_capacity(),
+            Sink::Events(tx) => tx.max_capacity(),
+        }
+    }
+}
+
+impl<'a> Forwarder<'a> {
+    pub(crate) fn new(tx: &'a Sender<String>, options: &'a PromptOptions) -> Self {
+        Self {
+            sink: match options.events.as_ref() {
+                Some(events) => Sink::Events(events),
+                None => Sink::Text(tx),
+            },
+            options: &options.stream,
+            backlog: VecDeque::new(),
+            dropped: 0,
+            stalled: None,
+        }
+    }
+
+    /// Deliver `event` under the `OnFull` policy.
+    pub(crate) async fn send(&mut self, event: StreamEvent) -> Result<(), WireError> {
+        if !self.sink.takes(&event.kind) {
+            return Ok(());
+        }
+
+        match self.options.on_full {
+            OnFull::Block => Ok(self.wait_send(event).await?),
+            OnFull::Fail => match self.sink.try_send(event) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => Err(WireError::ChannelFull {
+                    capacity: self.sink.max_capacity(),
+                }),
+                Err(TrySendError::Closed(_)) => Err(WireError::StreamClosed),
+            },
+            OnFull::DropOldest => {
+                self.backlog.push_back(event);
+                self.drain_backlog()?;
+
+                if self.backlog.len() > self.options.channel_capacity {
+                    self.backlog.pop_front();
+                    self.dropped += 1;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Resolves once the caller has dropped the receiving end.
+    pub(crate) async fn closed(&self) {
+        self.sink.closed().await
+    }
+
+    /// Deliver anything `OnFull::DropOldest` held back, waiting for room now
+    /// that the provider's response has been read, and return warnings for
+    /// anything dropped or stalled on the way.
+    pub(crate) async fn flush(&mut self) -> Result<Vec<WireWarning>, WireError> {
+        while let Some(event) = self.backlog.pop_front() {
+            self.wait_send(event).await?;
+        }
+
+        let dropped = (self.dropped > 0).then_some(WireWarning::StreamEventsDropped {
+            dropped: self.dropped,
+        });
+        Ok(dropped.into_iter().chain(self.stalled.take()).collect())
+    }
+
+    /// Move as much of the backlog into the channel as fits right now.
+    fn drain_backlog(&mut self) -> Result<(), SendError<StreamEvent>> {
+        while let Some(event) = self.backlog.pop_front() {
+            match self.sink.try_send(event) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.backlog.push_front(event);
+                    break;
+                }
+                Err(TrySendError::Closed(event)) => return Err(SendError(event)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for room in the channel, noting it if that takes longer than
+    /// `stall_warning`.
+    async fn wait_send(&mut self, event: StreamEvent) -> Result<(), SendError<StreamEvent>> {
+        let Some(threshold) = self.options.stall_warning else {
+            return self.sink.send(event).await;
+        };
+
+        let send = self.sink.send(event);
+        tokio::pin!(send);
+        match tokio::time::timeout(threshold, &mut send).await {
+            Ok(sent) => sent,
+            Err(_) => {
+                self.stalled.get_or_insert(WireWarning::ChannelStalled {
+                    waited_ms: threshold.as_millis() as u64,
+                    capacity: self.sink.max_capacity(),
+                });
+                send.await
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -198,6 +990,7 @@ impl MessageBuilder {
             name: self.name,
             input_tokens: self.input_tokens,
             output_tokens: self.output_tokens,
+            metadata: MessageMetadata::default(),
         }
     }
 
@@ -237,6 +1030,16 @@ impl From<MessageWithTools> for (Message, Vec<Tool>) {
 
 pub trait ToolFunction: Send + Sync {
     fn call(&self, args: serde_json::Value) -> serde_json::Value;
+    /// What the tool loop invokes. Plain tools ignore `context`; see
+    /// `ContextualToolWrapper` for tools that read it.
+    fn call_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> serde_json::Value {
+        let _ = context;
+        self.call(args)
+    }
     fn clone_box(&self) -> Box<dyn ToolFunction>;
     fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
 }
@@ -272,6 +1075,83 @@ where
     }
 }
 
+/// What the tool loop knows about a call, handed to tools that ask for it.
+#[derive(Clone, Debug, Default)]
+pub struct ToolContext {
+    pub call_id: String,
+    pub tool_name: String,
+    /// Zero-based index of the loop iteration that requested the call. Calls
+    /// resumed from a persisted history report 0.
+    pub iteration: usize,
+    history: std::sync::Arc<Vec<Message>>,
+}
+
+impl ToolContext {
+    pub fn new(
+        call_id: String,
+        tool_name: String,
+        iteration: usize,
+        history: std::sync::Arc<Vec<Message>>,
+    ) -> Self {
+        Self {
+            call_id,
+            tool_name,
+            iteration,
+            history,
+        }
+    }
+
+    /// The conversation up to and including the message that made the call.
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+}
+
+/// A tool implementation that also receives the `ToolContext` of each call.
+/// Implemented for closures taking `(args, &ToolContext)`; wrap it in
+/// `ContextualToolWrapper` to build a `Tool`.
+pub trait ContextualToolFunction: Send + Sync {
+    fn call(&self, args: serde_json::Value, context: &ToolContext) -> serde_json::Value;
+}
+
+impl<F> ContextualToolFunction for F
+where
+    F: Fn(serde_json::Value, &ToolContext) -> serde_json::Value + Send + Sync,
+{
+    fn call(&self, args: serde_json::Value, context: &ToolContext) -> serde_json::Value {
+        self(args, context)
+    }
+}
+
+/// `ToolWrapper` for `ContextualToolFunction`s. Called outside the tool loop
+/// (through `ToolFunction::call`), the function sees an empty context.
+pub struct ContextualToolWrapper<F>(pub F);
+
+impl<F> ToolFunction for ContextualToolWrapper<F>
+where
+    F: ContextualToolFunction + Clone + 'static,
+{
+    fn call(&self, args: serde_json::Value) -> serde_json::Value {
+        ContextualToolFunction::call(&self.0, args, &ToolContext::default())
+    }
+
+    fn call_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> serde_json::Value {
+        ContextualToolFunction::call(&self.0, args, context)
+    }
+
+    fn clone_box(&self) -> Box<dyn ToolFunction> {
+        Box::new(Self(self.0.clone()))
+    }
+
+    fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContextualFnWrapper")
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RequestParams {
     pub provider: String,
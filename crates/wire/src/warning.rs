@@ -0,0 +1,219 @@
+//! Things a client changed or left out of a request without failing it.
+//!
+//! Not every option means something to every provider: Anthropic has no
+//! reasoning effort to send a `ThinkingLevel` as, the text tool protocol has
+//! no way to enforce a `ToolChoice`, and a history written by another
+//! provider may need rewriting before this one takes it. Rather than drop
+//! these silently, the clients collect a `WireWarning` for each while
+//! building a request. The warnings end up in `MessageMetadata::warnings` of
+//! the reply and, with an event log set, as `WireEvent::Warning`s.
+//!
+//! With `ClientOptions::deny_warnings`, the first warning fails the prompt
+//! with `error::WireError::Warning` before anything is sent.
+
+use std::fmt;
+
+use crate::api::{Provider, API};
+use crate::error::WireError;
+use crate::event_log::{emit, EventLog, WireEvent};
+use crate::normalize::{normalize_history_reporting, HistoryStrictness};
+use crate::types::{Message, TruncatedStream};
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum WireWarning {
+    /// `option` was set, but `provider` has no use for it, so it was left
+    /// out of the request.
+    OptionIgnored {
+        option: String,
+        provider: String,
+        reason: String,
+    },
+    /// `field` was sent as `sent`, the most `provider` takes, in place of
+    /// the `requested` value.
+    FieldClamped {
+        field: String,
+        provider: String,
+        requested: u64,
+        sent: u64,
+    },
+    /// Message `message_index` of the history was rewritten for `provider`;
+    /// see `normalize`.
+    HistoryNormalized {
+        message_index: usize,
+        provider: String,
+        reason: String,
+    },
+    /// `provider`'s stream closed partway through its final event. The
+    /// content before it was kept and the `tail_bytes` of the event were
+    /// dropped; see `MessageMetadata::truncated_stream`.
+    StreamTruncated { provider: String, tail_bytes: usize },
+    /// The stream's consumer fell behind and `OnFull::DropOldest` discarded
+    /// the `dropped` oldest deltas or events.
+    StreamEventsDropped { dropped: usize },
+    /// A delta waited `waited_ms` or more for room in a full channel of
+    /// `capacity`, longer than `StreamOptions::stall_warning`. Given once
+    /// per stream.
+    ChannelStalled { waited_ms: u64, capacity: usize },
+    /// Tool definitions took `tool_schema_bytes` of a `total_bytes`
+    /// tool-loop request, more than `ClientOptions::tool_schema_warning`.
+    ToolSchemasLarge {
+        tool_schema_bytes: usize,
+        total_bytes: usize,
+    },
+    /// `provider`'s support for `feature` is experimental and may fail.
+    Experimental { feature: String, provider: String },
+}
+
+impl WireWarning {
+    pub(crate) fn option_ignored(
+        option: &str,
+        provider: Provider,
+        reason: impl Into<String>,
+    ) -> Self {
+        WireWarning::OptionIgnored {
+            option: option.to_string(),
+            provider: provider.as_str().to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for WireWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireWarning::OptionIgnored {
+                option,
+                provider,
+                reason,
+            } => write!(f, "{} is ignored by {}: {}", option, provider, reason),
+            WireWarning::FieldClamped {
+                field,
+                provider,
+                requested,
+                sent,
+            } => write!(
+                f,
+                "{} of {} is more than {} takes; sent {}",
+                field, requested, provider, sent
+            ),
+            WireWarning::HistoryNormalized {
+                message_index,
+                provider,
+                reason,
+            } => write!(
+                f,
+                "message {} was rewritten for {}: {}",
+                message_index, provider, reason
+            ),
+            WireWarning::StreamTruncated {
+                provider,
+                tail_bytes,
+            } => write!(
+                f,
+                "{} stream ended partway through its final event; kept the content before it and dropped {} bytes",
+                provider, tail_bytes
+            ),
+            WireWarning::StreamEventsDropped { dropped } => write!(
+                f,
+                "stream consumer fell behind; dropped {} of the oldest deltas",
+                dropped
+            ),
+            WireWarning::ChannelStalled {
+                waited_ms,
+                capacity,
+            } => write!(
+                f,
+                "stream stalled for {}ms waiting on a full channel of capacity {}",
+                waited_ms, capacity
+            ),
+            WireWarning::ToolSchemasLarge {
+                tool_schema_bytes,
+                total_bytes,
+            } => write!(
+                f,
+                "tool schemas are {:.0}% of the request ({} of {} bytes)",
+                *tool_schema_bytes as f64 / (*total_bytes).max(1) as f64 * 100.0,
+                tool_schema_bytes,
+                total_bytes
+            ),
+            WireWarning::Experimental { feature, provider } => {
+                write!(f, "{} {} support is experimental", provider, feature)
+            }
+        }
+    }
+}
+
+/// The warnings raised while building one request, each reported as it is
+/// added.
+pub(crate) struct RequestWarnings<'a> {
+    deny: bool,
+    event_log: Option<&'a EventLog>,
+    warnings: Vec<WireWarning>,
+}
+
+impl<'a> RequestWarnings<'a> {
+    pub(crate) fn new(deny: bool, event_log: Option<&'a EventLog>) -> Self {
+        Self {
+            deny,
+            event_log,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Log each of `warnings` not already raised, or fail on the first one
+    /// when they are denied. A request built again for a retry raises the
+    /// same warnings, which are kept once.
+    pub(crate) fn extend<I>(&mut self, warnings: I) -> Result<(), WireError>
+    where
+        I: IntoIterator<Item = WireWarning>,
+    {
+        for warning in warnings {
+            if self.warnings.contains(&warning) {
+                continue;
+            }
+            if self.deny {
+                return Err(WireError::Warning { warning });
+            }
+
+            emit(self.event_log, || WireEvent::Warning {
+                warning: warning.clone(),
+            });
+            self.warnings.push(warning);
+        }
+
+        Ok(())
+    }
+
+    /// `history` normalized for `api` (see `normalize`), with a warning for
+    /// each message rewritten.
+    pub(crate) fn normalize(
+        &mut self,
+        api: &API,
+        history: &[Message],
+        strictness: HistoryStrictness,
+    ) -> Result<Vec<Message>, WireError> {
+        let mut rewrites = Vec::new();
+        let history = normalize_history_reporting(api, history, strictness, &mut rewrites)?;
+        self.extend(rewrites)?;
+        Ok(history)
+    }
+
+    /// Warn that `provider`'s stream was cut off, if it was.
+    pub(crate) fn stream_truncated(
+        &mut self,
+        provider: Provider,
+        truncated: Option<&TruncatedStream>,
+    ) -> Result<(), WireError> {
+        self.extend(truncated.map(|truncated| WireWarning::StreamTruncated {
+            provider: provider.as_str().to_string(),
+            tail_bytes: truncated.tail.len(),
+        }))
+    }
+
+    /// The warnings so far, for the reply's metadata.
+    pub(crate) fn to_vec(&self) -> Vec<WireWarning> {
+        self.warnings.clone()
+    }
+}
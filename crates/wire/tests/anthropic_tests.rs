@@ -1,125 +1,89 @@
+#![cfg(feature = "anthropic")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
 mod common;
 
-use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
-use common::{function_call, message, request_body_json, sample_tool};
-use std::panic;
+#[cfg(feature = "mock")]
+use common::mock_server::{
+    MockJsonResponse, MockLLMServer, MockResponse, MockRoute, MockSseEvent, MockSseResponse,
+};
+use common::{message, request_body_json, sample_tool};
+use std::time::Duration;
 use temp_env::with_var;
 use wire::anthropic::AnthropicClient;
-use wire::api::{AnthropicModel, Prompt};
-use wire::config::ClientOptions;
-use wire::types::MessageType;
+use wire::api::{AnthropicModel, PromptCore, ToolCapable};
+use wire::config::{ClientOptions, MaxTokensBehavior, PromptOptions, ServiceTier, ToolChoice};
+use wire::error::WireError;
+use wire::types::{ContentBlockSpan, MessageType};
+
+#[cfg(feature = "mock")]
+fn max_tokens_fixture(name: &str) -> MockResponse {
+    fixture("max_tokens", name)
+}
+
+#[cfg(feature = "mock")]
+fn fixture(dir: &str, name: &str) -> MockResponse {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/anthropic")
+        .join(dir)
+        .join(name);
+    let body = std::fs::read_to_string(&path).expect("fixture readable");
+    MockResponse::Json(MockJsonResponse::new(
+        serde_json::from_str(&body).expect("fixture is json"),
+    ))
+}
 
-fn build_client<M>(model: M) -> Option<AnthropicClient>
+fn build_client<M>(model: M) -> AnthropicClient
 where
-    M: Into<AnthropicModel>,
+    M: TryInto<AnthropicModel>,
+    M::Error: Into<WireError>,
 {
-    let model = model.into();
-    panic::catch_unwind(|| AnthropicClient::new(model.clone())).ok()
+    AnthropicClient::try_new(model).expect("known model")
 }
 
 #[test]
 fn anthropic_client_new_accepts_model_str() {
-    let client = match build_client("claude-3-5-haiku-20241022") {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client("claude-3-5-haiku-20241022");
 
     assert_eq!(client.model, AnthropicModel::Claude35Haiku);
 }
 
 #[test]
-fn anthropic_build_request_formats_messages_and_tools() {
-    std::env::set_var("ANTHROPIC_API_KEY", "anthropic-key");
-
-    let client = match build_client("claude-3-5-sonnet-20241022") {
-        Some(client) => client,
-        None => return,
-    };
-
-    let mut assistant = message(MessageType::Assistant, "");
-    assistant.tool_calls = Some(vec![function_call(
-        "call-1",
-        "lookup_weather",
-        serde_json::json!({ "location": "NYC" }),
-    )]);
-
-    let tool_result_content = serde_json::json!({ "forecast": "snow" }).to_string();
-    let mut tool_result = message(MessageType::FunctionCallOutput, &tool_result_content);
-    tool_result.tool_call_id = Some("call-1".to_string());
-
-    let chat_history = vec![
-        message(MessageType::User, "What's the weather?"),
-        assistant,
-        tool_result,
-    ];
-
-    let request = client
-        .build_request(
-            "You are a helpful assistant.".to_string(),
-            chat_history,
-            Some(vec![sample_tool("lookup_weather")]),
-            false,
-        )
-        .build()
-        .expect("request should be buildable");
-
-    assert_eq!(
-        request.url().as_str(),
-        "https://api.anthropic.com/v1/messages"
-    );
-    assert_eq!(
-        request
-            .headers()
-            .get("x-api-key")
-            .expect("anthropic auth header present")
-            .to_str()
-            .unwrap(),
-        "anthropic-key"
-    );
-
-    let body = request_body_json(&request);
-
-    assert_eq!(body["system"], "You are a helpful assistant.");
-    assert_eq!(body["model"], "claude-3-5-sonnet-20241022");
-
-    let messages = body["messages"].as_array().expect("messages array");
-    assert_eq!(messages.len(), 3);
-
-    assert_eq!(messages[0]["role"], "user");
-    assert_eq!(messages[0]["content"], "What's the weather?");
-
-    let assistant_content = messages[1]["content"]
-        .as_array()
-        .expect("assistant content");
-    assert_eq!(assistant_content[0]["type"], "tool_use");
-    assert_eq!(assistant_content[0]["name"], "lookup_weather");
-    assert_eq!(
-        assistant_content[0]["input"],
-        serde_json::json!({ "location": "NYC" })
-    );
-
-    let tool_result_content = messages[2]["content"]
-        .as_array()
-        .expect("tool result content");
-    assert_eq!(tool_result_content[0]["type"], "tool_result");
-    assert_eq!(tool_result_content[0]["tool_use_id"], "call-1");
-    assert_eq!(
-        tool_result_content[0]["content"],
-        serde_json::json!({ "forecast": "snow" }).to_string()
-    );
-
-    let tools = body["tools"].as_array().expect("tools array");
-    assert_eq!(tools.len(), 1);
-    assert_eq!(tools[0]["name"], "lookup_weather");
-    assert!(tools[0]["input_schema"].is_object());
+fn anthropic_build_request_maps_tool_choice() {
+    for (tool_choice, expected) in [
+        (ToolChoice::Auto, "auto"),
+        (ToolChoice::Any, "any"),
+        (ToolChoice::None, "none"),
+    ] {
+        let client = with_var("ANTHROPIC_API_KEY", Some("anthropic-key"), || {
+            AnthropicClient::try_with_options(
+                "claude-3-5-haiku-20241022",
+                ClientOptions::default().with_tool_choice(tool_choice),
+            )
+            .expect("known model")
+        });
+
+        let request = client
+            .build_request(
+                "Use tools.".to_string(),
+                vec![message(MessageType::User, "Find it")],
+                Some(&[sample_tool("search").spec()]),
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("anthropic request should be buildable");
+
+        assert_eq!(
+            request_body_json(&request)["tool_choice"],
+            serde_json::json!({ "type": expected })
+        );
+    }
 }
 
 #[test]
 fn anthropic_read_json_response_extracts_text() {
-    let client = match build_client("claude-3-5-sonnet-20241022") {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client("claude-3-5-sonnet-20241022");
 
     let response_json = serde_json::json!({
         "content": [
@@ -137,6 +101,7 @@ fn anthropic_read_json_response_extracts_text() {
     assert_eq!(content, "Response payload");
 }
 
+#[cfg(feature = "mock")]
 #[test]
 fn anthropic_prompt_with_tools_with_status_emits_warning_and_runs_tool() {
     if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
@@ -181,7 +146,8 @@ fn anthropic_prompt_with_tools_with_status_emits_warning_and_runs_tool() {
 
             let options =
                 ClientOptions::for_mock_server(&server).expect("client options for mock server");
-            let client = AnthropicClient::with_options("claude-3-5-sonnet-20241022", options);
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
 
             let (tx, mut rx) = tokio::sync::mpsc::channel(4);
 
@@ -222,6 +188,7 @@ fn anthropic_prompt_with_tools_with_status_emits_warning_and_runs_tool() {
     });
 }
 
+#[cfg(feature = "mock")]
 #[test]
 fn anthropic_prompt_integration_uses_mock_server() {
     if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
@@ -249,7 +216,8 @@ fn anthropic_prompt_integration_uses_mock_server() {
 
             let options =
                 ClientOptions::for_mock_server(&server).expect("client options for mock server");
-            let client = AnthropicClient::with_options("claude-3-5-sonnet-20241022", options);
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
 
             let response = client
                 .prompt(
@@ -286,3 +254,758 @@ fn anthropic_prompt_integration_uses_mock_server() {
         });
     });
 }
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_stream_records_latency_stats() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for latency test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::anthropic_text_stream(["Good ", "day"])
+                    .with_chunk_delay(Duration::from_millis(50)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be polite.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.content, "Good day");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 2);
+
+            // message_start is delayed too, so the first text delta arrives
+            // after two pauses
+            let latency = response.metadata.latency.expect("latency recorded");
+            let ttft = latency.ttft.expect("first token recorded");
+            assert_eq!(latency.deltas, 2);
+            assert!(ttft >= Duration::from_millis(90), "ttft was {:?}", ttft);
+            assert!(latency.total >= Duration::from_millis(180));
+            assert!(latency.total < Duration::from_secs(5));
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_stream_returns_tool_use() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool use stream test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::anthropic_tool_use_stream(
+                    "Let me check.",
+                    "toolu_01",
+                    "get_weather",
+                    ["{\"ci", "ty\": ", "\"Paris\"}"],
+                ),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather in Paris?")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.message_type, MessageType::FunctionCall);
+            assert_eq!(response.content, "Let me check.");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 4);
+            assert_eq!(rx.recv().await.as_deref(), Some("Let me check."));
+
+            let calls = response.tool_calls.expect("tool calls on the reply");
+            assert_eq!(calls.len(), 1);
+            assert_eq!(calls[0].id, "toolu_01");
+            assert_eq!(calls[0].call_type, "function");
+            assert_eq!(calls[0].function.name, "get_weather");
+            assert_eq!(calls[0].function.arguments, "{\"city\": \"Paris\"}");
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_stream_drops_tool_use_cut_off_by_max_tokens() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool use stream test");
+
+        runtime.block_on(async {
+            let event = |json: serde_json::Value| MockSseEvent::data_json(json);
+            let stream = MockSseResponse::new(vec![
+                event(serde_json::json!({
+                    "type": "content_block_start",
+                    "index": 0,
+                    "content_block": { "type": "tool_use", "id": "toolu_01", "name": "get_weather" }
+                })),
+                event(serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": { "type": "input_json_delta", "partial_json": "{\"city\": \"Pa" }
+                })),
+                event(serde_json::json!({
+                    "type": "message_delta",
+                    "delta": { "stop_reason": "max_tokens" },
+                    "usage": { "output_tokens": 2 }
+                })),
+                MockSseEvent::event("message_stop"),
+            ]);
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::Sse(stream),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather in Paris?")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            // Half an input is no call to run
+            assert_eq!(response.message_type, MessageType::Assistant);
+            assert!(response.tool_calls.is_none());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_records_total_latency_without_ttft() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic latency test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for latency test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "content": [{ "type": "text", "text": "hi" }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let response = client
+                .prompt(
+                    "Be polite.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                )
+                .await
+                .expect("prompt returns content");
+
+            let latency = response.metadata.latency.expect("latency recorded");
+            assert!(latency.ttft.is_none());
+            assert_eq!(latency.deltas, 0);
+            assert!(latency.total > Duration::ZERO);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_request_ids_come_from_the_request_id_header() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic request id test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for request id test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/messages",
+                vec![
+                    MockResponse::anthropic_text_stream(["Hello"])
+                        .with_header("request-id", "req_011CKs"),
+                    MockResponse::Json(
+                        MockJsonResponse::new(serde_json::json!({
+                            "type": "error",
+                            "error": { "type": "overloaded_error", "message": "Overloaded" }
+                        }))
+                        .with_status(529),
+                    )
+                    .with_header("request-id", "req_011CKt"),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be polite.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(response.metadata.request_id.as_deref(), Some("req_011CKs"));
+
+            let error = client
+                .prompt(
+                    "Be polite.".to_string(),
+                    vec![message(MessageType::User, "Greet me")],
+                )
+                .await
+                .expect_err("Anthropic is overloaded");
+            assert_eq!(error.request_id(), Some("req_011CKt"));
+            assert!(
+                error.to_string().ends_with("; request-id: req_011CKt"),
+                "{}",
+                error
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_stalled_stream_fails_with_its_partial_content() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for stall test");
+
+        runtime.block_on(async {
+            let stall = Duration::from_secs(3);
+            // message_start and two deltas, then silence
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/messages",
+                vec![
+                    MockResponse::anthropic_text_stream(["Partial ", "reply", " never sent"])
+                        .stall_after(3, stall),
+                    MockResponse::anthropic_text_stream(["Slow ", "but ", "steady"])
+                        .stall_after(3, Duration::from_millis(200)),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_stream_idle_timeout(Some(Duration::from_millis(500)));
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let started = std::time::Instant::now();
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let error = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be polite.".to_string(),
+                    tx,
+                )
+                .await
+                .expect_err("the stream stalls");
+            assert!(started.elapsed() < stall);
+            assert!(
+                matches!(
+                    &error,
+                    WireError::StreamStalled { idle, partial, .. }
+                        if *idle == Duration::from_millis(500) && partial == "Partial reply"
+                ),
+                "{}",
+                error
+            );
+            assert_eq!(rx.recv().await.as_deref(), Some("Partial "));
+            assert_eq!(rx.recv().await.as_deref(), Some("reply"));
+
+            // A pause shorter than the idle timeout is waited out
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be polite.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("the stream picks up again");
+            assert_eq!(response.content, "Slow but steady");
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_stream_stops_at_max_response_bytes() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cap test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::anthropic_text_stream(["abcdef"; 100])
+                    .with_chunk_delay(Duration::from_millis(20)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(256);
+            let response = client
+                .prompt_stream_with_options(
+                    vec![message(MessageType::User, "Ramble")],
+                    "Be verbose.".to_string(),
+                    tx,
+                    &PromptOptions::new().with_max_response_bytes(8),
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.content, "abcdefab");
+            let truncated = response.metadata.truncated.expect("truncation recorded");
+            assert_eq!(truncated.limit, 8);
+            assert_eq!(truncated.original_bytes, 12);
+            assert!(response.metadata.latency.unwrap().total < Duration::from_secs(1));
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_tool_loop_continues_after_max_tokens() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic max_tokens continuation test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for max_tokens test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/messages",
+                vec![
+                    max_tokens_fixture("truncated_tool_use.json"),
+                    max_tokens_fixture("continued_tool_use.json"),
+                    max_tokens_fixture("end_turn.json"),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_max_tokens_behavior(MaxTokensBehavior::Continue {
+                    max_continuations: 1,
+                });
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let history = client
+                .prompt_with_tools(
+                    "Assist kindly.",
+                    vec![message(MessageType::User, "Weather in 10001?")],
+                    vec![sample_tool("lookup_weather")],
+                )
+                .await
+                .expect("tool loop completes");
+
+            assert_eq!(history.len(), 4);
+            assert_eq!(
+                history[1].content,
+                "Let me check the forecast for 10001 before answering."
+            );
+            let calls = history[1].tool_calls.as_ref().expect("tool call kept");
+            assert_eq!(calls.len(), 1);
+            assert_eq!(calls[0].id, "toolu_01Full");
+            assert_eq!(history[2].content, r#"{"zip":"10001"}"#);
+            assert_eq!(history[3].content, "It's sunny in 10001.");
+
+            let requests = server.requests_for("/v1/messages").await;
+            assert_eq!(requests.len(), 3);
+
+            let continuation: serde_json::Value =
+                serde_json::from_slice(&requests[1].body).expect("json body");
+            let messages = continuation["messages"].as_array().unwrap();
+            assert_eq!(messages.len(), 2);
+            let prefill = messages.last().unwrap();
+            assert_eq!(prefill["role"], "assistant");
+            assert_eq!(
+                prefill["content"][0]["text"],
+                "Let me check the forecast for 10001"
+            );
+            assert_eq!(prefill["content"].as_array().unwrap().len(), 1);
+
+            // the prefill is not part of the conversation sent afterwards
+            let after_tool: serde_json::Value =
+                serde_json::from_slice(&requests[2].body).expect("json body");
+            assert_eq!(after_tool["messages"].as_array().unwrap().len(), 3);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_tool_loop_reports_max_tokens_truncation() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic max_tokens error test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for max_tokens test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                max_tokens_fixture("truncated_tool_use.json"),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let err = client
+                .prompt_with_tools(
+                    "Assist kindly.",
+                    vec![message(MessageType::User, "Weather in 10001?")],
+                    vec![sample_tool("lookup_weather")],
+                )
+                .await
+                .expect_err("truncated reply is an error");
+
+            let WireError::TruncatedResponse { partial } = err else {
+                panic!("expected a truncated response, got {}", err);
+            };
+            assert_eq!(partial.content, "Let me check the forecast for 10001 ");
+            assert_eq!(partial.message_type, MessageType::Assistant);
+            assert_eq!(partial.system_prompt, "Assist kindly.");
+            let calls = partial.tool_calls.as_ref().unwrap();
+            assert_eq!(calls[0].function.name, "lookup_weather");
+
+            assert_eq!(server.requests_for("/v1/messages").await.len(), 1);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_prompt_stream_numbers_deltas_across_blocks() {
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for sequence test");
+
+        runtime.block_on(async {
+            let text_delta = |index: usize, text: &str| {
+                MockSseEvent::data_json(serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": index,
+                    "delta": { "type": "text_delta", "text": text }
+                }))
+            };
+            let events = vec![
+                MockSseEvent::event("message_start"),
+                text_delta(0, "Let me "),
+                text_delta(0, "check. "),
+                // tool input deltas carry no text and are not forwarded
+                MockSseEvent::data_json(serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": 1,
+                    "delta": { "type": "input_json_delta", "partial_json": "{\"zip\"" }
+                })),
+                text_delta(2, "It is "),
+                text_delta(2, "sunny"),
+                text_delta(2, "."),
+                MockSseEvent::event("message_stop"),
+            ];
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::Sse(MockSseResponse::new(events)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather?")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            let mut received = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                received.push(delta);
+            }
+
+            let sequence = response.metadata.sequence.expect("sequence recorded");
+            assert_eq!(sequence.final_seq, Some(received.len() as u64 - 1));
+            assert_eq!(
+                sequence.blocks,
+                vec![
+                    ContentBlockSpan {
+                        index: 0,
+                        first_seq: 0,
+                        last_seq: 1,
+                    },
+                    ContentBlockSpan {
+                        index: 2,
+                        first_seq: 2,
+                        last_seq: 4,
+                    },
+                ]
+            );
+
+            // spans are contiguous and cover every delta exactly once
+            let mut next = 0;
+            for block in &sequence.blocks {
+                assert_eq!(block.first_seq, next);
+                assert!(block.last_seq >= block.first_seq);
+                next = block.last_seq + 1;
+            }
+            assert_eq!(next, received.len() as u64);
+            assert_eq!(received[2..].concat(), "It is sunny.");
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_tool_loop_reports_interim_text_before_the_call() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic interim text test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for anthropic tool test");
+
+        runtime.block_on(async {
+            let first = MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "stop_reason": "tool_use",
+                "content": [
+                    { "type": "text", "text": "Let me check the weather." },
+                    {
+                        "type": "tool_use",
+                        "id": "call-1",
+                        "name": "lookup_weather",
+                        "input": { "zip": "10001" }
+                    }
+                ]
+            })));
+            let second = MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "stop_reason": "end_turn",
+                "content": [{ "type": "text", "text": "Snowing." }]
+            })));
+
+            let server =
+                MockLLMServer::start(vec![MockRoute::new("/v1/messages", vec![first, second])])
+                    .await
+                    .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+
+            let result = client
+                .prompt_with_tools_with_status(
+                    tx,
+                    "Assist kindly.",
+                    vec![message(MessageType::User, "Weather please")],
+                    vec![sample_tool("lookup_weather")],
+                )
+                .await
+                .expect("anthropic tool handling succeeds");
+
+            assert_eq!(result[1].content, "Let me check the weather.");
+            assert_eq!(result.last().unwrap().content, "Snowing.");
+
+            let mut statuses = Vec::new();
+            while let Ok(status) = rx.try_recv() {
+                statuses.push(status);
+            }
+            assert_eq!(
+                statuses,
+                vec![
+                    "warn: anthropic tool support is experimental",
+                    "Let me check the weather.",
+                    "calling tool lookup_weather...",
+                ]
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_service_tier_is_sent_and_reported() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping anthropic service tier test");
+        return;
+    }
+
+    with_var("ANTHROPIC_API_KEY", Some("mock-anthropic-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for service tier test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/messages",
+                vec![
+                    fixture("service_tier", "priority.json"),
+                    MockResponse::Sse(MockSseResponse::new(vec![
+                        MockSseEvent::data_json(serde_json::json!({
+                            "type": "message_start",
+                            "message": {
+                                "usage": { "input_tokens": 12, "service_tier": "standard" }
+                            }
+                        })),
+                        MockSseEvent::data_json(serde_json::json!({
+                            "type": "content_block_delta",
+                            "index": 0,
+                            "delta": { "type": "text_delta", "text": "Served." }
+                        })),
+                        MockSseEvent::event("message_stop"),
+                    ])),
+                    max_tokens_fixture("end_turn.json"),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+            let history = || vec![message(MessageType::User, "Hurry.")];
+
+            let response = client
+                .prompt_with_options(
+                    "Be quick.".to_string(),
+                    history(),
+                    &PromptOptions::new().with_service_tier(ServiceTier::Auto),
+                )
+                .await
+                .expect("prompt succeeds");
+            assert_eq!(response.content, "Served fast.");
+            assert_eq!(response.metadata.service_tier.as_deref(), Some("priority"));
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream_with_options(
+                    history(),
+                    "Be quick.".to_string(),
+                    tx,
+                    &PromptOptions::new().with_service_tier(ServiceTier::StandardOnly),
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(response.content, "Served.");
+            assert_eq!(response.metadata.service_tier.as_deref(), Some("standard"));
+
+            // No tier asked for, none sent, and none reported
+            let response = client
+                .prompt("Be quick.".to_string(), history())
+                .await
+                .expect("prompt succeeds");
+            assert_eq!(response.metadata.service_tier, None);
+
+            let recorded = server.requests_for("/v1/messages").await;
+            let tiers: Vec<serde_json::Value> = recorded
+                .iter()
+                .map(|request| {
+                    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                    body["service_tier"].clone()
+                })
+                .collect();
+            assert_eq!(
+                tiers,
+                vec![
+                    serde_json::json!("auto"),
+                    serde_json::json!("standard_only"),
+                    serde_json::Value::Null,
+                ]
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
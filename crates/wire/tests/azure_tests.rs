@@ -0,0 +1,201 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, request_body_json, sample_tool};
+use temp_env::with_vars;
+use wire::api::{OpenAIModel, PromptCore, ToolCapable, API};
+use wire::config::ClientOptions;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+const AZURE_PATH: &str = "/openai/deployments/chat-prod/chat/completions?api-version=2024-10-21";
+
+fn azure_keys<R>(f: impl FnOnce() -> R) -> R {
+    with_vars(
+        [
+            ("AZURE_OPENAI_API_KEY", Some("azure-key")),
+            ("OPENAI_API_KEY", Some("openai-key")),
+        ],
+        f,
+    )
+}
+
+#[test]
+fn azure_requests_use_deployment_path_and_api_key_header() {
+    azure_keys(|| {
+        let options =
+            ClientOptions::for_azure("contoso", "chat-prod", "2024-10-21").expect("azure options");
+        let client = OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+        let request = client
+            .build_request(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+                None,
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("azure request should build");
+
+        assert_eq!(
+            request.url().as_str(),
+            "https://contoso.openai.azure.com/openai/deployments/chat-prod/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            request
+                .url()
+                .query_pairs()
+                .find(|(name, _)| name == "api-version")
+                .map(|(_, value)| value.into_owned()),
+            Some("2024-10-21".to_string())
+        );
+        assert_eq!(
+            request
+                .headers()
+                .get("api-key")
+                .and_then(|value| value.to_str().ok()),
+            Some("azure-key")
+        );
+        assert!(request.headers().get("authorization").is_none());
+        assert_eq!(request_body_json(&request)["messages"][1]["content"], "Hi");
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn azure_prompt_and_stream_reach_the_deployment() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping azure integration test");
+        return;
+    }
+
+    azure_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for azure test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                AZURE_PATH,
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "mock reply" } }]
+                    }))),
+                    MockResponse::openai_text_stream(["Hello", " from", " Azure"]),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_azure_deployment("chat-prod", "2024-10-21");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let reply = client
+                .prompt(
+                    "Stay friendly.".to_string(),
+                    vec![message(MessageType::User, "Ping?")],
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "mock reply");
+            assert_eq!(reply.api, API::OpenAI(OpenAIModel::GPT4oMini));
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(streamed.content, "Hello from Azure");
+
+            let recorded = server.requests_for(AZURE_PATH).await;
+            assert_eq!(recorded.len(), 2);
+            for request in &recorded {
+                assert_eq!(request.path, AZURE_PATH);
+                assert_eq!(
+                    request.headers.get("api-key").map(String::as_str),
+                    Some("azure-key")
+                );
+                assert!(!request.headers.contains_key("authorization"));
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn azure_prompt_with_tools_executes_tool_call_sequence() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping azure tool integration test");
+        return;
+    }
+
+    azure_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for azure tool test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                AZURE_PATH,
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{
+                            "message": {
+                                "content": null,
+                                "tool_calls": [{
+                                    "id": "call-1",
+                                    "type": "function",
+                                    "function": {
+                                        "name": "echo",
+                                        "arguments": "{\"value\":\"hello\"}"
+                                    }
+                                }]
+                            }
+                        }]
+                    }))),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "All done." } }]
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_azure_deployment("chat-prod", "2024-10-21");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let result = client
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Please call the tool")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool-assisted prompt succeeds");
+
+            assert_eq!(result.len(), 4);
+            assert_eq!(result[2].message_type, MessageType::FunctionCallOutput);
+            assert_eq!(result[3].content, "All done.");
+
+            let recorded = server.requests_for(AZURE_PATH).await;
+            assert_eq!(recorded.len(), 2);
+            assert!(recorded.iter().all(|request| {
+                request.headers.get("api-key").map(String::as_str) == Some("azure-key")
+            }));
+
+            server.shutdown().await;
+        });
+    });
+}
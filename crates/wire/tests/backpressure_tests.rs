@@ -0,0 +1,201 @@
+mod common;
+
+use std::time::Duration;
+use wire::api::{PromptCore, WireModel, API};
+use wire::config::{OnFull, PromptOptions, StreamOptions};
+use wire::echo::EchoClient;
+use wire::types::{Message, MessageBuilder, MessageType};
+use wire::warning::WireWarning;
+
+const WORDS: &str = "one two three four five six";
+
+fn history() -> Vec<Message> {
+    vec![MessageBuilder::new(API::Wire(WireModel::Echo), WORDS)
+        .message_type(MessageType::User)
+        .build()]
+}
+
+fn options(on_full: OnFull) -> PromptOptions {
+    PromptOptions::new().with_stream_options(
+        StreamOptions::new()
+            .with_channel_capacity(2)
+            .with_on_full(on_full)
+            .with_stall_warning(None),
+    )
+}
+
+fn drain(rx: &mut tokio::sync::mpsc::Receiver<String>) -> Vec<String> {
+    let mut deltas = Vec::new();
+    while let Ok(delta) = rx.try_recv() {
+        deltas.push(delta);
+    }
+    deltas
+}
+
+#[test]
+fn channel_uses_the_configured_capacity() {
+    let (tx, _rx) = StreamOptions::new().with_channel_capacity(3).channel();
+    assert_eq!(tx.max_capacity(), 3);
+
+    let (tx, _rx) = StreamOptions::default().channel();
+    assert_eq!(
+        tx.max_capacity(),
+        wire::config::DEFAULT_STREAM_CHANNEL_CAPACITY
+    );
+}
+
+#[test]
+fn block_waits_for_a_slow_consumer() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for backpressure test");
+    let client = EchoClient::new();
+
+    runtime.block_on(async {
+        let mut options = options(OnFull::Block);
+        options.stream.stall_warning = Some(Duration::from_millis(10));
+        let (tx, mut rx) = options.stream.channel();
+        let stream = client.prompt_stream_with_options(history(), String::new(), tx, &options);
+        tokio::pin!(stream);
+
+        // Nobody is reading, so the stream stops once the channel is full
+        let stalled = tokio::time::timeout(Duration::from_millis(50), &mut stream).await;
+        assert!(stalled.is_err());
+        assert_eq!(drain(&mut rx), vec!["one ", "two "]);
+
+        let reader = async {
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                deltas.push(delta);
+            }
+            deltas
+        };
+        let (reply, deltas) = tokio::join!(stream, reader);
+
+        let reply = reply.expect("stream completes");
+        assert_eq!(reply.content, WORDS);
+        assert_eq!(deltas, vec!["three ", "four ", "five ", "six"]);
+        assert_eq!(
+            reply.metadata.warnings,
+            vec![WireWarning::ChannelStalled {
+                waited_ms: 10,
+                capacity: 2
+            }]
+        );
+    });
+}
+
+#[test]
+fn fail_errors_when_the_channel_is_full() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for backpressure test");
+    let client = EchoClient::new();
+
+    runtime.block_on(async {
+        let options = options(OnFull::Fail);
+        let (tx, mut rx) = options.stream.channel();
+
+        let err = client
+            .prompt_stream_with_options(history(), String::new(), tx, &options)
+            .await
+            .expect_err("consumer never reads");
+        assert_eq!(
+            err.to_string(),
+            "stream consumer fell behind: channel of capacity 2 is full"
+        );
+        assert_eq!(drain(&mut rx), vec!["one ", "two "]);
+    });
+}
+
+#[test]
+fn drop_oldest_keeps_reading_and_delivers_the_latest_deltas() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for backpressure test");
+    let client = EchoClient::new();
+
+    runtime.block_on(async {
+        let options = options(OnFull::DropOldest);
+        let (tx, mut rx) = options.stream.channel();
+
+        let reader = async {
+            // Start reading only after the whole response has been produced
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push(delta);
+            }
+            deltas
+        };
+        let (reply, deltas) = tokio::join!(
+            client.prompt_stream_with_options(history(), String::new(), tx, &options),
+            reader
+        );
+
+        // The channel held the first two, the backlog the last two
+        assert_eq!(deltas, vec!["one ", "two ", "five ", "six"]);
+
+        let reply = reply.expect("stream completes");
+        assert_eq!(reply.content, WORDS);
+        assert_eq!(reply.metadata.content_bytes, Some(WORDS.len()));
+        assert_eq!(
+            reply.metadata.warnings,
+            vec![WireWarning::StreamEventsDropped { dropped: 2 }]
+        );
+    });
+}
+
+#[cfg(all(feature = "openai", feature = "mock"))]
+#[test]
+fn openai_stream_honors_drop_oldest() {
+    use common::mock_server::{MockLLMServer, MockResponse, MockRoute};
+    use temp_env::with_var;
+    use wire::config::ClientOptions;
+    use wire::openai::OpenAIClient;
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for backpressure test");
+
+        runtime.block_on(async {
+            let chunks: Vec<String> = (0..20).map(|i| format!("{} ", i)).collect();
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(chunks.clone()),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = options(OnFull::DropOldest);
+            let client = OpenAIClient::try_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server).expect("client options"),
+            )
+            .expect("known model");
+            let (tx, mut rx) = options.stream.channel();
+
+            // A consumer that never reads mid-stream does not hold up the response
+            let reply = tokio::time::timeout(
+                Duration::from_secs(5),
+                client.prompt_stream_with_options(
+                    vec![common::message(MessageType::User, "Count")],
+                    String::new(),
+                    tx,
+                    &options,
+                ),
+            );
+            let reader = async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let mut deltas = Vec::new();
+                while let Some(delta) = rx.recv().await {
+                    deltas.push(delta);
+                }
+                deltas
+            };
+            let (reply, deltas) = tokio::join!(reply, reader);
+
+            let reply = reply
+                .expect("stream is not stalled")
+                .expect("stream completes");
+            assert_eq!(reply.content, chunks.concat());
+            assert_eq!(deltas, vec!["0 ", "1 ", "18 ", "19 "]);
+
+            server.shutdown().await;
+        });
+    });
+}
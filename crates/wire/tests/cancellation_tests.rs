@@ -0,0 +1,223 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, sample_tool};
+use temp_env::with_var;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::{CancellationToken, ClientOptions, PromptOptions};
+use wire::echo::{EchoClient, EchoClientConfig};
+use wire::error::WireError;
+use wire::openai::OpenAIClient;
+use wire::retry::RetryPolicy;
+use wire::types::{MessageType, ToolWrapper};
+
+const CHAT: &str = "/v1/chat/completions";
+
+#[test]
+fn cancelled_errors_say_how_much_arrived() {
+    let error = WireError::Cancelled {
+        partial: "Hello".to_string(),
+    };
+    assert_eq!(error.to_string(), "cancelled after 5 bytes");
+    assert!(!RetryPolicy::is_transient(&error));
+}
+
+#[test]
+fn cancelling_an_echo_stream_stops_it_mid_pause() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo cancel test");
+
+    runtime.block_on(async {
+        let token = CancellationToken::new();
+        let client = EchoClient::with_options(
+            ClientOptions::default().with_cancellation(token.child_token()),
+        )
+        .with_config(EchoClientConfig {
+            delay: Duration::from_secs(5),
+            ..Default::default()
+        });
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let stream = client.prompt_stream(
+            vec![message(MessageType::User, "one two three")],
+            String::new(),
+            tx,
+        );
+        let cancel = async {
+            assert_eq!(rx.recv().await.as_deref(), Some("one "));
+            token.cancel();
+        };
+
+        let started = Instant::now();
+        let (result, ()) = tokio::join!(stream, cancel);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        match result {
+            Err(WireError::Cancelled { partial }) => assert_eq!(partial, "one "),
+            other => panic!("expected a cancelled stream, got {:?}", other),
+        }
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn cancelling_a_stream_returns_promptly_with_the_partial_text() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cancel test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                CHAT,
+                MockResponse::openai_text_stream(["Once", " upon", " a", " time"])
+                    .with_chunk_delay(Duration::from_millis(300)),
+            )])
+            .await
+            .expect("mock server starts");
+            let client = OpenAIClient::try_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+            )
+            .expect("known model");
+
+            let token = CancellationToken::new();
+            let options = PromptOptions::new().with_cancellation(token.clone());
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let stream = client.prompt_stream_with_options(
+                vec![message(MessageType::User, "Tell me a story")],
+                "Be brief.".to_string(),
+                tx,
+                &options,
+            );
+            let cancel = async {
+                assert_eq!(rx.recv().await.as_deref(), Some("Once"));
+                token.cancel();
+                Instant::now()
+            };
+
+            let (result, cancelled_at) = tokio::join!(stream, cancel);
+            // The next chunk was still 300ms away
+            assert!(
+                cancelled_at.elapsed() < Duration::from_millis(200),
+                "took {:?} to stop",
+                cancelled_at.elapsed()
+            );
+            match result {
+                Err(WireError::Cancelled { partial }) => assert_eq!(partial, "Once"),
+                other => panic!("expected a cancelled stream, got {:?}", other),
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn a_cancelled_token_sends_nothing() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cancel test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                CHAT,
+                MockResponse::openai_text_stream(["Hello"]),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let token = CancellationToken::new();
+            token.cancel();
+            let client = OpenAIClient::try_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server)
+                    .expect("client options for mock server")
+                    .with_cancellation(token),
+            )
+            .expect("known model");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let result = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Hi")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await;
+            assert!(matches!(result, Err(WireError::Cancelled { partial }) if partial.is_empty()));
+
+            // A prompt's own token takes the client's place
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let reply = client
+                .prompt_stream_with_options(
+                    vec![message(MessageType::User, "Hi")],
+                    "Be brief.".to_string(),
+                    tx,
+                    &PromptOptions::new().with_cancellation(CancellationToken::new()),
+                )
+                .await
+                .expect("stream with a live token completes");
+            assert_eq!(reply.content, "Hello");
+            assert_eq!(server.requests_for(CHAT).await.len(), 1);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn cancelling_a_tool_loop_stops_before_the_next_request() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cancel test");
+
+        runtime.block_on(async {
+            let call = MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "stop", "arguments": "{}" }
+                        }]
+                    }
+                }]
+            })));
+            let server = MockLLMServer::start(vec![MockRoute::new(CHAT, vec![call.clone(), call])])
+                .await
+                .expect("mock server starts");
+
+            let token = CancellationToken::new();
+            let client = OpenAIClient::try_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server)
+                    .expect("client options for mock server")
+                    .with_cancellation(token.clone()),
+            )
+            .expect("known model");
+
+            // The tool itself cancels the loop
+            let mut stop = sample_tool("stop");
+            stop.function = Box::new(ToolWrapper(move |_| {
+                token.cancel();
+                serde_json::json!("stopping")
+            }));
+
+            let result = client
+                .prompt_with_tools(
+                    "Use the tools.",
+                    vec![message(MessageType::User, "Go")],
+                    vec![stop],
+                )
+                .await;
+            assert!(matches!(result, Err(WireError::Cancelled { .. })));
+            assert_eq!(server.requests_for(CHAT).await.len(), 1);
+
+            server.shutdown().await;
+        });
+    });
+}
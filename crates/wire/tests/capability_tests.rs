@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use wire::api::{reply_events, EventStream, PromptCore, ToolCapable, WireModel, API};
+use wire::config::PromptOptions;
+use wire::echo::EchoClient;
+use wire::error::WireError;
+use wire::new_client;
+use wire::orchestrate::cross_check;
+use wire::router::RouterClient;
+use wire::types::{FinishReason, Message, MessageBuilder, MessageType, ToolSpec};
+
+/// A client with only the core capability: it answers every prompt with the
+/// same text, as an HTTP-only provider without tools might.
+struct Canned(&'static str);
+
+#[async_trait::async_trait]
+impl PromptCore for Canned {
+    fn get_auth_token(&self) -> Result<String, WireError> {
+        Ok(String::new())
+    }
+
+    fn new_message(&self, content: String) -> MessageBuilder {
+        MessageBuilder::new(API::Wire(WireModel::Echo), content)
+    }
+
+    fn build_request(
+        &self,
+        _system_prompt: String,
+        _chat_history: Vec<Message>,
+        _tools: Option<&[ToolSpec]>,
+        _stream: bool,
+    ) -> Result<reqwest::RequestBuilder, WireError> {
+        Ok(reqwest::Client::new().post("http://localhost/canned"))
+    }
+
+    async fn prompt_with_options(
+        &self,
+        system_prompt: String,
+        _chat_history: Vec<Message>,
+        _options: &PromptOptions,
+    ) -> Result<Message, WireError> {
+        let mut message = self
+            .new_message(self.0.to_string())
+            .message_type(MessageType::Assistant)
+            .build();
+        message.system_prompt = system_prompt;
+        Ok(message)
+    }
+
+    fn prompt_stream_events_with_options(
+        &self,
+        system_prompt: String,
+        chat_history: Vec<Message>,
+        options: &PromptOptions,
+    ) -> EventStream<'_> {
+        let options = options.clone();
+        reply_events(async move {
+            self.prompt_with_options(system_prompt, chat_history, &options)
+                .await
+        })
+    }
+
+    fn read_json_response(&self, response_json: &serde_json::Value) -> Result<String, WireError> {
+        Ok(response_json.to_string())
+    }
+}
+
+fn question() -> Vec<Message> {
+    vec![
+        MessageBuilder::new(API::Wire(WireModel::Echo), "Which is it?")
+            .message_type(MessageType::User)
+            .build(),
+    ]
+}
+
+#[test]
+fn echo_runs_tools_but_has_no_socket() {
+    let client = new_client("wire:echo").expect("echo model known");
+
+    assert!(client.supports_tools());
+    assert!(client.tools().is_some());
+    assert!(client.raw_transport().is_none());
+}
+
+#[test]
+fn a_router_reports_tools_if_any_route_can_run_them() {
+    let core_only = || Arc::new(Canned("no tools")) as Arc<dyn PromptCore>;
+    let router = RouterClient::new("plain", core_only());
+    assert!(!router.supports_tools());
+    assert!(router.raw_transport().is_none());
+
+    let router = RouterClient::new("plain", core_only()).with_route(
+        "tools",
+        Arc::new(EchoClient::new()),
+        |request| request.has_tools(),
+    );
+    assert!(router.supports_tools());
+}
+
+#[test]
+fn a_tool_loop_routed_to_a_core_only_client_fails() {
+    tokio::runtime::Runtime::new()
+        .expect("runtime for capability test")
+        .block_on(async {
+            let router = RouterClient::new("plain", Arc::new(Canned("no tools"))).with_route(
+                "echo",
+                Arc::new(EchoClient::new()),
+                |request| request.system_prompt == "echo",
+            );
+
+            let err = router
+                .prompt_with_tools("Be brief.", question(), Vec::new())
+                .await
+                .expect_err("the plain route has no tool loop");
+            assert_eq!(
+                err.to_string(),
+                "the client for route \"plain\" cannot run tools"
+            );
+        });
+}
+
+#[cfg(feature = "gemini")]
+#[test]
+fn gemini_reports_tools_over_either_transport() {
+    use wire::config::ClientOptions;
+    use wire::gemini::GeminiClient;
+    use wire::tool_protocol::ToolTransport;
+
+    let native = GeminiClient::try_new("gemini-2.0-flash").expect("known model");
+    assert!(native.supports_tools());
+    assert!(native.raw_transport().is_some());
+
+    let text = GeminiClient::try_with_options(
+        "gemini-2.0-flash",
+        ClientOptions::default().with_tool_transport(ToolTransport::TextProtocol),
+    )
+    .expect("known model");
+    assert!(text.supports_tools());
+}
+
+#[test]
+fn a_core_only_client_reports_no_optional_capabilities() {
+    let client = Canned("forty-two");
+
+    assert!(!client.supports_tools());
+    assert!(client.tools().is_none());
+    assert!(client.raw_transport().is_none());
+}
+
+#[test]
+fn a_core_only_client_gets_the_provided_prompt_methods() {
+    tokio::runtime::Runtime::new()
+        .expect("runtime for capability test")
+        .block_on(async {
+            let client = Canned("forty-two");
+
+            let reply = client
+                .prompt("Be brief.".to_string(), question())
+                .await
+                .expect("prompt succeeds");
+            assert_eq!(reply.content, "forty-two");
+
+            let partial = client
+                .prompt_with_deadline(
+                    "Be brief.".to_string(),
+                    question(),
+                    Instant::now() + Duration::from_secs(5),
+                )
+                .await
+                .expect("deadline prompt succeeds");
+            assert_eq!(partial.finish_reason, FinishReason::Complete);
+            assert_eq!(partial.message.content, "forty-two");
+
+            let result = cross_check(
+                &Canned("yes"),
+                &Canned("no"),
+                &client,
+                "Be brief.".to_string(),
+                question(),
+            )
+            .await
+            .expect("cross check succeeds");
+            assert_eq!(result.answers[0].content, "yes");
+            assert_eq!(result.answers[1].content, "no");
+            assert_eq!(result.verdict.content, "forty-two");
+        });
+}
@@ -0,0 +1,269 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::message;
+use common::mock_server::{
+    ChaosConfig, ChaosFault, MockJsonResponse, MockLLMServer, MockResponse, MockRoute,
+};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+const SOAK_PROMPTS: usize = 50;
+
+const OPENAI_PATH: &str = "/v1/chat/completions";
+const ANTHROPIC_PATH: &str = "/v1/messages";
+const GEMINI_STREAM_PATH: &str =
+    "/v1beta/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key";
+const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+
+fn chaos() -> ChaosConfig {
+    let config = ChaosConfig::from_env();
+    eprintln!(
+        "chaos seed: {} (replay with WIRE_CHAOS_SEED)",
+        config.seed()
+    );
+    config
+}
+
+fn run_with_mock_keys<F>(f: F)
+where
+    F: FnOnce(),
+{
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("GEMINI_API_KEY", Some("mock-gemini-key")),
+        ],
+        f,
+    );
+}
+
+async fn soak_stream<P: PromptCore>(client: &P, server: &MockLLMServer, expected: &str) {
+    for i in 0..SOAK_PROMPTS {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+        let stream = |tx| {
+            client.prompt_stream(
+                vec![message(MessageType::User, "Say hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+        };
+        // a 503 comes before any delta, so a retry streams from the start
+        let mut response = stream(tx.clone()).await;
+        if let Err(WireError::Http { status: 503, .. }) = response {
+            response = stream(tx).await;
+        }
+
+        let response = response.unwrap_or_else(|err| {
+            panic!(
+                "prompt {} failed after retry: {} (faults so far: {:?})",
+                i,
+                err,
+                server.injected_faults()
+            )
+        });
+
+        let mut streamed = String::new();
+        while let Ok(delta) = rx.try_recv() {
+            streamed.push_str(&delta);
+        }
+
+        assert_eq!(
+            response.content,
+            expected,
+            "prompt {} returned the wrong content (faults so far: {:?})",
+            i,
+            server.injected_faults()
+        );
+        assert_eq!(streamed, expected, "prompt {} streamed the wrong deltas", i);
+    }
+}
+
+async fn soak_prompt<P: PromptCore>(client: &P, server: &MockLLMServer, expected: &str) {
+    for i in 0..SOAK_PROMPTS {
+        // a single retry always gets past an injected 503
+        let mut response = client
+            .prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Say hello")],
+            )
+            .await;
+        if response.is_err() {
+            response = client
+                .prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Say hello")],
+                )
+                .await;
+        }
+
+        let response = response.unwrap_or_else(|err| {
+            panic!(
+                "prompt {} failed after retry: {} (faults so far: {:?})",
+                i,
+                err,
+                server.injected_faults()
+            )
+        });
+        assert_eq!(
+            response.content,
+            expected,
+            "prompt {} returned the wrong content (faults so far: {:?})",
+            i,
+            server.injected_faults()
+        );
+    }
+}
+
+#[test]
+fn chaos_soak_openai_prompt_stream() {
+    run_with_mock_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for chaos test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                OPENAI_PATH,
+                MockResponse::openai_text_stream(["Hel", "lo", " there", "!"]),
+            )])
+            .await
+            .expect("mock server starts")
+            .with_chaos(chaos());
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            soak_stream(&client, &server, "Hello there!").await;
+            assert!(!server.injected_faults().is_empty());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn chaos_soak_anthropic_prompt_stream() {
+    run_with_mock_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for chaos test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                ANTHROPIC_PATH,
+                MockResponse::anthropic_text_stream(["Good ", "day ", "to you"]),
+            )])
+            .await
+            .expect("mock server starts")
+            .with_chaos(chaos());
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            soak_stream(&client, &server, "Good day to you").await;
+            assert!(!server.injected_faults().is_empty());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn chaos_soak_gemini_prompt_stream() {
+    run_with_mock_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for chaos test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                GEMINI_STREAM_PATH,
+                MockResponse::gemini_text_stream(["Bonjour", " le", " monde"]),
+            )])
+            .await
+            .expect("mock server starts")
+            .with_chaos(chaos());
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model");
+
+            soak_stream(&client, &server, "Bonjour le monde").await;
+            assert!(!server.injected_faults().is_empty());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn chaos_soak_prompt_all_clients() {
+    run_with_mock_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for chaos test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![
+                MockRoute::single(
+                    OPENAI_PATH,
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "Hello there!" } }],
+                        "usage": { "prompt_tokens": 3, "completion_tokens": 2 }
+                    }))),
+                ),
+                MockRoute::single(
+                    ANTHROPIC_PATH,
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "content": [{ "type": "text", "text": "Good day to you" }]
+                    }))),
+                ),
+                MockRoute::single(
+                    GEMINI_PATH,
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "candidates": [{
+                            "content": { "parts": [{ "text": "Bonjour le monde" }] }
+                        }]
+                    }))),
+                ),
+            ])
+            .await
+            .expect("mock server starts")
+            .with_chaos(chaos());
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+
+            let openai = OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                .expect("known model");
+            soak_prompt(&openai, &server, "Hello there!").await;
+
+            let anthropic =
+                AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options.clone())
+                    .expect("known model");
+            soak_prompt(&anthropic, &server, "Good day to you").await;
+
+            let gemini =
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model");
+            soak_prompt(&gemini, &server, "Bonjour le monde").await;
+
+            assert!(server
+                .injected_faults()
+                .iter()
+                .any(|injected| injected.fault == ChaosFault::ServiceUnavailable));
+
+            server.shutdown().await;
+        });
+    });
+}
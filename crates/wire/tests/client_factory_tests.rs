@@ -1,8 +1,9 @@
-use std::panic;
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
 
 use temp_env::with_var;
-use wire::api::{AnthropicModel, GeminiModel, OpenAIModel, Prompt, API};
+use wire::api::{AnthropicModel, GeminiModel, OpenAIModel, PromptCore, API};
 use wire::config::ClientOptions;
+use wire::error::WireError;
 use wire::types::{Message, MessageBuilder};
 use wire::{new_client, new_client_with_options};
 
@@ -10,33 +11,23 @@ fn simple_message(api: API, content: &str) -> Vec<Message> {
     vec![MessageBuilder::new(api, content).build()]
 }
 
-fn build_client(model: &str) -> Option<Box<dyn Prompt>> {
-    match panic::catch_unwind(|| new_client(model)) {
-        Ok(Ok(client)) => Some(client),
-        Ok(Err(err)) => panic!("unexpected error creating client: {err}"),
-        Err(_) => None,
-    }
+fn build_client(model: &str) -> Box<dyn PromptCore> {
+    new_client(model).expect("known model")
 }
 
-fn build_client_with_options(model: &str, options: ClientOptions) -> Option<Box<dyn Prompt>> {
-    match panic::catch_unwind(|| new_client_with_options(model, options)) {
-        Ok(Ok(client)) => Some(client),
-        Ok(Err(err)) => panic!("unexpected error creating client with options: {err}"),
-        Err(_) => None,
-    }
+fn build_client_with_options(model: &str, options: ClientOptions) -> Box<dyn PromptCore> {
+    new_client_with_options(model, options).expect("known model")
 }
 
 #[test]
 fn new_client_creates_openai_client() {
     with_var("OPENAI_API_KEY", Some("test-openai"), || {
-        let client = match build_client("gpt-4o") {
-            Some(client) => client,
-            None => return,
-        };
+        let client = build_client("gpt-4o");
         let messages = simple_message(API::OpenAI(OpenAIModel::GPT4o), "hello");
 
         let request = client
             .build_request("Be helpful".to_string(), messages, None, false)
+            .expect("request builds")
             .build()
             .expect("openai request should build");
 
@@ -50,14 +41,12 @@ fn new_client_creates_openai_client() {
 #[test]
 fn new_client_creates_anthropic_client() {
     with_var("ANTHROPIC_API_KEY", Some("test-anthropic"), || {
-        let client = match build_client("claude-3-5-sonnet-20241022") {
-            Some(client) => client,
-            None => return,
-        };
+        let client = build_client("claude-3-5-sonnet-20241022");
         let messages = simple_message(API::Anthropic(AnthropicModel::Claude35SonnetNew), "hello");
 
         let request = client
             .build_request("Be kind".to_string(), messages, None, false)
+            .expect("request builds")
             .build()
             .expect("anthropic request should build");
 
@@ -71,14 +60,12 @@ fn new_client_creates_anthropic_client() {
 #[test]
 fn new_client_creates_gemini_client() {
     with_var("GEMINI_API_KEY", Some("test-gemini"), || {
-        let client = match build_client("gemini-2.0-flash") {
-            Some(client) => client,
-            None => return,
-        };
+        let client = build_client("gemini-2.0-flash");
         let messages = simple_message(API::Gemini(GeminiModel::Gemini20Flash), "hello");
 
         let request = client
             .build_request("Be creative".to_string(), messages, None, false)
+            .expect("request builds")
             .build()
             .expect("gemini request should build");
 
@@ -94,14 +81,12 @@ fn new_client_with_options_overrides_base_url() {
     with_var("OPENAI_API_KEY", Some("test-openai"), || {
         let options = ClientOptions::from_base_url("http://localhost:4242")
             .expect("client options from base url");
-        let client = match build_client_with_options("gpt-4o", options) {
-            Some(client) => client,
-            None => return,
-        };
+        let client = build_client_with_options("gpt-4o", options);
         let messages = simple_message(API::OpenAI(OpenAIModel::GPT4o), "override");
 
         let request = client
             .build_request("Use override".to_string(), messages, None, false)
+            .expect("request builds")
             .build()
             .expect("request with options should build");
 
@@ -116,6 +101,7 @@ fn new_client_with_options_overrides_base_url() {
 fn new_client_errors_on_unknown_model() {
     assert!(matches!(
         new_client("unknown"),
-        Err(err) if err.contains("Unknown model")
+        Err(WireError::UnknownModel { model, reason })
+            if model == "unknown" && reason.contains("Unknown model")
     ));
 }
@@ -0,0 +1,114 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+mod common;
+
+use common::message;
+use common::mock_server::{MockLLMServer, MockResponse, MockRoute, TestClock};
+use std::time::Duration;
+use temp_env::with_var;
+use wire::api::PromptCore;
+use wire::clock::Clock;
+use wire::config::ClientOptions;
+use wire::openai::OpenAIClient;
+use wire::types::{FinishReason, MessageType};
+
+/// Wait (in real time) until `clock` has `count` sleeps waiting.
+async fn wait_for_sleeps(clock: &TestClock, count: usize) {
+    for _ in 0..5000 {
+        if clock.pending_sleeps() == count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    panic!("expected {} pending sleeps", count);
+}
+
+#[test]
+fn test_clock_wakes_sleeps_when_advanced() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for clock test");
+
+    runtime.block_on(async {
+        let clock = TestClock::new();
+        let started = clock.now();
+
+        let short = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep(Duration::from_secs(1)).await }
+        });
+        let long = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep(Duration::from_secs(60)).await }
+        });
+        wait_for_sleeps(&clock, 2).await;
+
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(clock.pending_sleeps(), 2);
+
+        clock.advance(Duration::from_millis(1));
+        short.await.expect("short sleep wakes");
+        assert_eq!(clock.pending_sleeps(), 1);
+        assert!(!long.is_finished());
+
+        clock.advance(Duration::from_secs(59));
+        long.await.expect("long sleep wakes");
+        assert_eq!(clock.now() - started, Duration::from_secs(60));
+
+        // Deadlines in the past don't wait
+        clock.sleep_until(started).await;
+        assert_eq!(clock.pending_sleeps(), 0);
+    });
+}
+
+#[test]
+fn deadline_follows_the_injected_clock() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping injected clock deadline test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for clock test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(["slow ", "reply"])
+                    .with_chunk_delay(Duration::from_millis(300)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let clock = TestClock::new();
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_clock(clock.clone());
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let deadline = clock.now() + Duration::from_secs(30);
+            let response = tokio::spawn(async move {
+                client
+                    .prompt_with_deadline(
+                        "Be brief.".to_string(),
+                        vec![message(MessageType::User, "Take your time")],
+                        deadline,
+                    )
+                    .await
+                    .map_err(|err| err.to_string())
+            });
+
+            // Thirty seconds pass without any real waiting
+            wait_for_sleeps(&clock, 1).await;
+            clock.advance(Duration::from_secs(30));
+
+            let response = response
+                .await
+                .expect("deadline task joins")
+                .expect("deadline is not an error");
+            assert_eq!(response.finish_reason, FinishReason::Deadline);
+            assert!("slow reply".starts_with(response.message.content.as_str()));
+
+            server.shutdown().await;
+        });
+    });
+}
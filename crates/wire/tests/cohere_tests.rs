@@ -0,0 +1,317 @@
+#![cfg(feature = "cohere")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{function_call, message, request_body_json, sample_tool};
+use temp_env::with_var;
+use wire::api::{CohereModel, PromptCore, ToolCapable, API};
+use wire::cohere::CohereClient;
+use wire::config::{ClientOptions, ToolChoice};
+use wire::new_client;
+use wire::types::MessageType;
+
+const COHERE_PATH: &str = "/v2/chat";
+
+#[test]
+fn cohere_models_resolve_and_round_trip() {
+    with_var("COHERE_API_KEY", Some("cohere-key"), || {
+        let client = new_client("command-r-08-2024").expect("cohere model resolves");
+        let request = client
+            .build_request(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+                None,
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("cohere request should build");
+
+        assert_eq!(request.url().as_str(), "https://api.cohere.com/v2/chat");
+        assert_eq!(
+            request
+                .headers()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok()),
+            Some("Bearer cohere-key")
+        );
+        let body = request_body_json(&request);
+        assert_eq!(body["model"], "command-r-08-2024");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Hi");
+    });
+
+    let api = API::Cohere(CohereModel::CommandA);
+    let json = serde_json::to_value(&api).expect("api serializes");
+    assert_eq!(
+        json,
+        serde_json::json!({ "provider": "cohere", "model": "command-a-03-2025" })
+    );
+    assert_eq!(
+        serde_json::from_value::<API>(json).expect("api deserializes"),
+        api
+    );
+    let (provider, model) = api.to_strings();
+    assert_eq!(API::from_strings(&provider, &model), Ok(api));
+    assert!(API::from_strings("openai", "command-a-03-2025").is_err());
+}
+
+#[test]
+fn cohere_requests_carry_tool_plans_and_document_outputs() {
+    with_var("COHERE_API_KEY", Some("cohere-key"), || {
+        let options = ClientOptions::default()
+            .with_max_tokens(64)
+            .with_tool_choice(ToolChoice::Any);
+        let client =
+            CohereClient::try_with_options("command-r-plus-08-2024", options).expect("known model");
+
+        let mut call = message(MessageType::FunctionCall, "I will check the weather.");
+        call.tool_calls = Some(vec![function_call(
+            "lookup_weather_1",
+            "lookup_weather",
+            serde_json::json!({ "city": "Paris" }),
+        )]);
+        let mut output = message(MessageType::FunctionCallOutput, "sunny");
+        output.tool_call_id = Some("lookup_weather_1".to_string());
+
+        let tools = [sample_tool("lookup_weather").spec()];
+        let request = client
+            .build_request(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Weather?"), call, output],
+                Some(&tools),
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("cohere request should build");
+        let body = request_body_json(&request);
+
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["tool_choice"], "REQUIRED");
+        assert_eq!(body["tools"][0]["function"]["name"], "lookup_weather");
+        assert_eq!(
+            body["messages"][2],
+            serde_json::json!({
+                "role": "assistant",
+                "tool_plan": "I will check the weather.",
+                "tool_calls": [{
+                    "id": "lookup_weather_1",
+                    "type": "function",
+                    "function": { "name": "lookup_weather", "arguments": "{\"city\":\"Paris\"}" }
+                }]
+            })
+        );
+        assert_eq!(
+            body["messages"][3],
+            serde_json::json!({
+                "role": "tool",
+                "tool_call_id": "lookup_weather_1",
+                "content": [{ "type": "document", "document": { "data": "sunny" } }]
+            })
+        );
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn cohere_prompt_reads_billed_units() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping cohere integration test");
+        return;
+    }
+
+    with_var("COHERE_API_KEY", Some("mock-cohere-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cohere test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                COHERE_PATH,
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "id": "reply-1",
+                    "finish_reason": "COMPLETE",
+                    "message": {
+                        "role": "assistant",
+                        "content": [
+                            { "type": "text", "text": "mock " },
+                            { "type": "text", "text": "reply" }
+                        ]
+                    },
+                    "usage": {
+                        "billed_units": { "input_tokens": 12, "output_tokens": 5 },
+                        "tokens": { "input_tokens": 210, "output_tokens": 5 }
+                    }
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                CohereClient::try_with_options("command-r-08-2024", options).expect("known model");
+
+            let response = client
+                .prompt(
+                    "Stay friendly.".to_string(),
+                    vec![message(MessageType::User, "Ping?")],
+                )
+                .await
+                .expect("prompt returns content");
+
+            assert_eq!(response.content, "mock reply");
+            assert_eq!(response.input_tokens, 12);
+            assert_eq!(response.output_tokens, 5);
+            assert_eq!(response.api, API::Cohere(CohereModel::CommandR));
+
+            let recorded = server.requests_for(COHERE_PATH).await;
+            assert_eq!(recorded.len(), 1);
+            assert_eq!(
+                recorded[0].headers.get("authorization").map(String::as_str),
+                Some("Bearer mock-cohere-key")
+            );
+            let payload: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            assert_eq!(payload["stream"], false);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn cohere_prompt_stream_reads_content_deltas() {
+    with_var("COHERE_API_KEY", Some("mock-cohere-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cohere stream test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                COHERE_PATH,
+                MockResponse::cohere_text_stream(["Bonjour", " le", " monde"]),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                CohereClient::try_with_options("command-a-03-2025", options).expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Say hello in French")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas, ["Bonjour", " le", " monde"]);
+            assert_eq!(response.content, "Bonjour le monde");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 3);
+            assert!(response.metadata.truncated_stream.is_none());
+
+            let recorded = server.requests_for(COHERE_PATH).await;
+            let payload: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            assert_eq!(payload["stream"], true);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn cohere_prompt_with_tools_executes_tool_call_sequence() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping cohere tool integration test");
+        return;
+    }
+
+    with_var("COHERE_API_KEY", Some("mock-cohere-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cohere tool test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                COHERE_PATH,
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "finish_reason": "TOOL_CALL",
+                        "message": {
+                            "role": "assistant",
+                            "tool_plan": "I will call echo.",
+                            "tool_calls": [{
+                                "id": "echo_1",
+                                "type": "function",
+                                "function": { "name": "echo", "arguments": "{\"value\":\"hello\"}" }
+                            }]
+                        },
+                        "usage": { "billed_units": { "input_tokens": 20, "output_tokens": 8 } }
+                    }))),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "finish_reason": "COMPLETE",
+                        "message": {
+                            "role": "assistant",
+                            "content": [{ "type": "text", "text": "All done." }]
+                        }
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                CohereClient::try_with_options("command-r-08-2024", options).expect("known model");
+
+            let result = client
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Please call the tool")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool-assisted prompt succeeds");
+
+            assert_eq!(result.len(), 4);
+            assert_eq!(result[1].message_type, MessageType::FunctionCall);
+            assert_eq!(result[1].content, "I will call echo.");
+            assert_eq!(result[1].output_tokens, 8);
+            let calls = result[1].tool_calls.as_ref().expect("tool calls recorded");
+            assert_eq!(calls[0].id, "echo_1");
+            assert_eq!(calls[0].function.arguments, r#"{"value":"hello"}"#);
+            assert_eq!(result[2].message_type, MessageType::FunctionCallOutput);
+            assert_eq!(result[3].content, "All done.");
+
+            let recorded = server.requests_for(COHERE_PATH).await;
+            assert_eq!(recorded.len(), 2);
+            let first: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            assert_eq!(first["tools"][0]["function"]["name"], "echo");
+            let second: serde_json::Value =
+                serde_json::from_slice(&recorded[1].body).expect("request body parses as json");
+            assert_eq!(second["messages"][2]["tool_plan"], "I will call echo.");
+            assert_eq!(second["messages"][2]["tool_calls"][0]["id"], "echo_1");
+            assert_eq!(second["messages"][3]["role"], "tool");
+            assert_eq!(second["messages"][3]["tool_call_id"], "echo_1");
+            assert_eq!(
+                second["messages"][3]["content"][0]["document"]["data"],
+                r#"{"value":"hello"}"#
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
@@ -0,0 +1,210 @@
+//! Harness for `tests/live_tests.rs`: which providers to call, a sink that
+//! keeps the raw traffic, the invariants checked on it and redacted fixture
+//! recording.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use wire::event_log::{EventSink, FileEventSink, WireEvent};
+
+/// Response fields that identify an account's requests rather than
+/// describing the reply; replaced in fixtures.
+const REDACTED_FIELDS: [&str; 3] = ["id", "system_fingerprint", "responseId"];
+const REDACTED: &str = "[redacted]";
+
+/// A provider the live suite runs against, if its key is set.
+#[derive(Clone, Copy, Debug)]
+pub struct LiveProvider {
+    pub name: &'static str,
+    pub key_var: &'static str,
+    pub model: &'static str,
+    /// OpenAI only reports usage on a stream when asked to, which wire does
+    /// not do.
+    pub reports_stream_usage: bool,
+}
+
+pub const PROVIDERS: [LiveProvider; 3] = [
+    LiveProvider {
+        name: "openai",
+        key_var: "OPENAI_API_KEY",
+        model: "gpt-4o-mini",
+        reports_stream_usage: false,
+    },
+    LiveProvider {
+        name: "anthropic",
+        key_var: "ANTHROPIC_API_KEY",
+        model: "claude-3-5-haiku-20241022",
+        reports_stream_usage: true,
+    },
+    LiveProvider {
+        name: "gemini",
+        key_var: "GEMINI_API_KEY",
+        model: "gemini-2.0-flash",
+        reports_stream_usage: true,
+    },
+];
+
+impl LiveProvider {
+    /// The provider's API key, or `None` (with a note on stderr) when it is
+    /// not set and the provider should be skipped.
+    pub fn key(&self) -> Option<String> {
+        match std::env::var(self.key_var) {
+            Ok(key) if !key.trim().is_empty() => Some(key),
+            _ => {
+                eprintln!(
+                    "skipping live {} tests: {} is not set",
+                    self.name, self.key_var
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Keeps every event in memory.
+#[derive(Default)]
+pub struct Recorder {
+    events: Mutex<Vec<WireEvent>>,
+}
+
+impl Recorder {
+    pub fn events(&self) -> Vec<WireEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl EventSink for Recorder {
+    fn record(&self, event: &WireEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+/// The JSON of every response body and stream event, in order.
+pub fn payloads(events: &[WireEvent]) -> Vec<serde_json::Value> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            WireEvent::Response { body, .. } => Some(body.clone()),
+            WireEvent::StreamData { data } => {
+                let data = data.strip_prefix("data: ").unwrap_or(data);
+                serde_json::from_str(data).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The largest token count any payload reports, whatever the provider calls
+/// it.
+pub fn usage(events: &[WireEvent]) -> u64 {
+    let mut tokens = Vec::new();
+    for payload in payloads(events) {
+        for field in ["total_tokens", "output_tokens", "totalTokenCount"] {
+            collect(&payload, &mut |key, value| {
+                if key == field {
+                    tokens.extend(value.as_u64());
+                }
+            });
+        }
+    }
+
+    tokens.into_iter().max().unwrap_or(0)
+}
+
+/// The last finish or stop reason any payload reports.
+pub fn finish_reason(events: &[WireEvent]) -> Option<String> {
+    let mut reason = None;
+    for payload in payloads(events) {
+        collect(&payload, &mut |key, value| {
+            if matches!(key, "finish_reason" | "stop_reason" | "finishReason") {
+                if let Some(value) = value.as_str() {
+                    reason = Some(value.to_string());
+                }
+            }
+        });
+    }
+
+    reason
+}
+
+/// Call `f` with every key and value in `value`, at any depth.
+fn collect(value: &serde_json::Value, f: &mut impl FnMut(&str, &serde_json::Value)) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                f(key, value);
+                collect(value, f);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect(item, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `event` with `secret` and the `REDACTED_FIELDS` replaced, including
+/// inside stream data.
+pub fn redact(event: &WireEvent, secret: &str) -> WireEvent {
+    let mut value = serde_json::to_value(event).expect("event serializes");
+    redact_value(&mut value, secret);
+    serde_json::from_value(value).expect("redacted event deserializes")
+}
+
+fn redact_value(value: &mut serde_json::Value, secret: &str) {
+    match value {
+        serde_json::Value::String(text) => *text = redact_text(text, secret),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_value(item, secret);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) && value.is_string() {
+                    *value = REDACTED.into();
+                } else {
+                    redact_value(value, secret);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Stream data holds JSON in a string (after `data: ` for SSE), so it is
+/// parsed and redacted like any other payload.
+fn redact_text(text: &str, secret: &str) -> String {
+    let (prefix, payload) = match text.strip_prefix("data: ") {
+        Some(payload) => ("data: ", payload),
+        None => ("", text),
+    };
+
+    if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(payload) {
+        if json.is_object() || json.is_array() {
+            redact_value(&mut json, secret);
+            return format!("{}{}", prefix, json);
+        }
+    }
+
+    text.replace(secret, REDACTED)
+}
+
+/// Write `events`, redacted, to `tests/fixtures/live/<provider>/<case>.jsonl`
+/// as an event log `MockLLMServer::from_event_log` can replay.
+pub fn write_fixture(provider: &str, case: &str, events: &[WireEvent], secret: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/live")
+        .join(provider);
+    std::fs::create_dir_all(&dir).expect("fixture directory is writable");
+
+    let path = dir.join(format!("{}.jsonl", case));
+    let sink = FileEventSink::create(&path).expect("fixture is writable");
+    for event in events {
+        sink.record(&redact(event, secret));
+    }
+
+    path
+}
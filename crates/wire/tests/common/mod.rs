@@ -1,9 +1,16 @@
 #![allow(dead_code)]
 
+#[cfg(feature = "live")]
+pub mod live;
+#[cfg(feature = "mock")]
 pub mod mock_server;
 
-use wire::api::{OpenAIModel, API};
-use wire::types::{Function, FunctionCall, Message, MessageType, Tool, ToolWrapper};
+use std::future::Future;
+use temp_env::with_vars;
+use wire::api::API;
+use wire::types::{
+    Function, FunctionCall, Message, MessageMetadata, MessageType, Tool, ToolWrapper,
+};
 
 pub fn message(message_type: MessageType, content: &str) -> Message {
     Message {
@@ -16,11 +23,69 @@ pub fn message(message_type: MessageType, content: &str) -> Message {
         name: None,
         input_tokens: 0,
         output_tokens: 0,
+        metadata: MessageMetadata::default(),
     }
 }
 
+#[cfg(feature = "openai")]
 fn default_api() -> API {
-    API::OpenAI(OpenAIModel::GPT4o)
+    API::OpenAI(wire::api::OpenAIModel::GPT4o)
+}
+
+#[cfg(not(feature = "openai"))]
+fn default_api() -> API {
+    API::Wire(wire::api::WireModel::Echo)
+}
+
+/// A conversation of one user turn.
+pub fn history() -> Vec<Message> {
+    vec![message(MessageType::User, "Hi")]
+}
+
+/// A conversation in which a tool call has been answered.
+pub fn tool_call_history() -> Vec<Message> {
+    let mut call = message(MessageType::FunctionCall, "");
+    call.tool_calls = Some(vec![function_call(
+        "call-1",
+        "lookup_weather",
+        serde_json::json!({ "zip": "10001", "units": "metric" }),
+    )]);
+
+    let mut output = message(MessageType::FunctionCallOutput, "snow");
+    output.tool_call_id = Some("call-1".to_string());
+    output.name = Some("lookup_weather".to_string());
+
+    vec![message(MessageType::User, "Weather?"), call, output]
+}
+
+/// Run `f` with the OpenAI, Anthropic and Gemini keys set to mock ones.
+pub fn with_mock_keys<F: FnOnce()>(f: F) {
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("GEMINI_API_KEY", Some("mock-gemini-key")),
+        ],
+        f,
+    );
+}
+
+/// Block on `test` with the mock keys set.
+pub fn run_with_mock_keys<F: Future<Output = ()>>(test: F) {
+    with_mock_keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for mock test");
+        runtime.block_on(test);
+    });
+}
+
+/// `run_with_mock_keys`, skipped unless `WIRE_RUN_MOCK_SERVER_TESTS` is set.
+pub fn run_mock_test<F: Future<Output = ()>>(name: &str, test: F) {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping {}", name);
+        return;
+    }
+
+    run_with_mock_keys(test);
 }
 
 pub fn function_call(id: &str, name: &str, arguments: serde_json::Value) -> FunctionCall {
@@ -43,6 +108,7 @@ pub fn sample_tool(name: &str) -> Tool {
             "type": "object",
             "properties": {},
         }),
+        strict: false,
         function: Box::new(ToolWrapper(|args| args)),
     }
 }
@@ -0,0 +1,169 @@
+#![cfg(feature = "compatible")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use std::sync::{Arc, Mutex};
+use temp_env::with_vars;
+use wire::api::{CompatibleModel, PromptCore, API};
+use wire::compatible::OpenAICompatibleClient;
+use wire::config::{ClientOptions, ClientOptionsError};
+use wire::new_client_with_options;
+use wire::types::{Message, MessageType};
+
+const COMPATIBLE_PATH: &str = "/v1/chat/completions";
+
+#[test]
+fn labels_are_kept_when_stored() {
+    let plain = API::Compatible(CompatibleModel::new("my-model"));
+    assert_eq!(API::from_model("custom/my-model"), Ok(plain.clone()));
+
+    for (api, stored) in [
+        (plain, "custom/my-model"),
+        (
+            API::Compatible(
+                CompatibleModel::new("org/fine-tune:v2")
+                    .with_label("litellm")
+                    .unwrap(),
+            ),
+            "litellm/org/fine-tune:v2",
+        ),
+    ] {
+        let (provider, model) = api.to_strings();
+        assert_eq!((provider.as_str(), model.as_str()), ("custom", stored));
+        assert_eq!(API::from_strings(&provider, &model), Ok(api.clone()));
+
+        let json = serde_json::to_value(&api).expect("api serializes");
+        assert_eq!(
+            json,
+            serde_json::json!({ "provider": "custom", "model": stored })
+        );
+        assert_eq!(
+            serde_json::from_value::<API>(json).expect("api deserializes"),
+            api
+        );
+
+        let stored = Message {
+            api: api.clone(),
+            ..message(MessageType::Assistant, "Hello")
+        };
+        let restored: Message =
+            serde_json::from_str(&serde_json::to_string(&stored).expect("message serializes"))
+                .expect("message deserializes");
+        assert_eq!(restored.api, api);
+    }
+
+    assert!(CompatibleModel::new("my-model").with_label("a/b").is_err());
+    assert!(CompatibleModel::new("my-model").with_label("").is_err());
+    for label in ["a/b", ""] {
+        assert!(matches!(
+            ClientOptions::default().with_compatible_label(label),
+            Err(ClientOptionsError::InvalidLabel(rejected)) if rejected == label
+        ));
+    }
+    assert!(API::from_strings("custom", "my-model").is_err());
+    assert!(serde_json::from_value::<API>(
+        serde_json::json!({ "provider": "custom", "model": "no-label" })
+    )
+    .is_err());
+}
+
+#[test]
+fn the_key_comes_from_the_options() {
+    with_vars(
+        [
+            ("OPENAI_COMPATIBLE_API_KEY", Some("default-key")),
+            ("VLLM_KEY", Some("vllm-key")),
+        ],
+        || {
+            let client = OpenAICompatibleClient::new("my-model");
+            assert_eq!(client.get_auth_token().expect("a key"), "default-key");
+
+            let client = OpenAICompatibleClient::with_options(
+                "my-model",
+                ClientOptions::default().with_compatible_key_var("VLLM_KEY"),
+            );
+            assert_eq!(client.get_auth_token().expect("a key"), "vllm-key");
+
+            let client = OpenAICompatibleClient::with_options(
+                "my-model",
+                ClientOptions::default()
+                    .with_compatible_key_var("VLLM_KEY")
+                    .with_compatible_api_key("given-key"),
+            );
+            client.refresh_credentials();
+            assert_eq!(client.get_auth_token().expect("a key"), "given-key");
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn gateway_options_set_the_label_key_and_path() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping compatible integration test");
+        return;
+    }
+
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for compatible test");
+
+    runtime.block_on(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/gateway/chat",
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "choices": [{ "message": { "content": "mock reply" } }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3 }
+            }))),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let metrics = Arc::new(Mutex::new(Vec::new()));
+        let recorded_metrics = metrics.clone();
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_compatible_label("litellm")
+            .expect("a valid label")
+            .with_compatible_api_key("mock-gateway-key")
+            .with_compatible_path("/gateway/chat")
+            .with_metrics_callback(move |metrics| {
+                recorded_metrics.lock().unwrap().push(metrics.api.clone());
+            });
+        let client =
+            new_client_with_options("custom/my-model", options).expect("compatible client");
+        let api = API::Compatible(
+            CompatibleModel::new("my-model")
+                .with_label("litellm")
+                .unwrap(),
+        );
+
+        let reply = client
+            .prompt(
+                "Stay friendly.".to_string(),
+                vec![message(MessageType::User, "Ping?")],
+            )
+            .await
+            .expect("prompt returns content");
+        assert_eq!(reply.content, "mock reply");
+        assert_eq!(reply.api, api);
+
+        let metrics = metrics.lock().unwrap().clone();
+        assert_eq!(metrics, vec![api]);
+
+        let recorded = server.requests_for("/gateway/chat").await;
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(
+            recorded[0].headers.get("authorization").map(String::as_str),
+            Some("Bearer mock-gateway-key")
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+        assert_eq!(body["model"], "my-model");
+        assert!(server.requests_for(COMPATIBLE_PATH).await.is_empty());
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,321 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, run_mock_test};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::compression::{gunzip, unbrotli, BrotliDecoder, GzipDecoder};
+use wire::config::ClientOptions;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/compression")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err))
+}
+
+/// Hands out one byte per read, like a connection that trickles data in.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = *first;
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+#[test]
+fn gunzip_decodes_fixed_huffman_blocks() {
+    let body: serde_json::Value =
+        serde_json::from_slice(&gunzip(&fixture("fixed.json.gz")).expect("fixture decodes"))
+            .expect("decoded body is json");
+
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "Hello from a gzipped body."
+    );
+}
+
+#[test]
+fn gunzip_decodes_dynamic_huffman_blocks() {
+    let decoded = gunzip(&fixture("dynamic.json.gz")).expect("fixture decodes");
+    let items: Vec<serde_json::Value> =
+        serde_json::from_slice(&decoded).expect("decoded body is json");
+
+    assert_eq!(items.len(), 3000);
+    for (index, item) in items.iter().enumerate() {
+        assert_eq!(item["index"], index);
+        assert_eq!(item["text"].as_str().unwrap().split(' ').count(), 8);
+    }
+
+    // Input arriving a byte at a time decodes the same
+    let mut trickled = Vec::new();
+    GzipDecoder::new(Trickle(&fixture("dynamic.json.gz")))
+        .read_to_end(&mut trickled)
+        .expect("trickled fixture decodes");
+    assert_eq!(trickled, decoded);
+}
+
+#[test]
+fn gunzip_rejects_damaged_input() {
+    let body = fixture("fixed.json.gz");
+
+    let err = gunzip(&body[..body.len() - 6]).expect_err("truncated input");
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let mut corrupted = body.clone();
+    let crc = corrupted.len() - 8;
+    corrupted[crc] ^= 0xff;
+    gunzip(&corrupted).expect_err("bad checksum");
+
+    gunzip(b"{\"not\": \"gzip\"}").expect_err("plain input");
+}
+
+#[test]
+fn decoding_stops_at_the_limit() {
+    let body = fixture("dynamic.json.gz");
+    let size = gunzip(&body).expect("fixture decodes").len() as u64;
+
+    let mut decoded = Vec::new();
+    let err = GzipDecoder::with_limit(body.as_slice(), size - 1)
+        .read_to_end(&mut decoded)
+        .expect_err("output over the limit");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!((decoded.len() as u64) < size);
+
+    let mut decoded = Vec::new();
+    GzipDecoder::with_limit(body.as_slice(), size)
+        .read_to_end(&mut decoded)
+        .expect("output at the limit");
+    assert_eq!(decoded.len() as u64, size);
+}
+
+#[test]
+fn unbrotli_decodes_what_gunzip_does() {
+    let expected = gunzip(&fixture("dynamic.json.gz")).expect("gzip fixture decodes");
+    let body = fixture("dynamic.json.br");
+    assert_eq!(unbrotli(&body).expect("brotli fixture decodes"), expected);
+
+    // Input arriving a byte at a time decodes the same
+    let mut trickled = Vec::new();
+    BrotliDecoder::new(Trickle(&body))
+        .read_to_end(&mut trickled)
+        .expect("trickled fixture decodes");
+    assert_eq!(trickled, expected);
+
+    unbrotli(&body[..body.len() / 2]).expect_err("truncated input");
+
+    let size = expected.len() as u64;
+    let mut decoded = Vec::new();
+    let err = BrotliDecoder::with_limit(body.as_slice(), size - 1)
+        .read_to_end(&mut decoded)
+        .expect_err("output over the limit");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn brotli_json_responses_are_decoded() {
+    run_mock_test("brotli json test", async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "choices": [{ "message": { "content": "openai, brotli" } }]
+            })))
+            .brotli(),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let options = ClientOptions::for_mock_server(&server).expect("client options");
+        let reply = OpenAIClient::try_with_options("gpt-4o-mini", options)
+            .expect("known model")
+            .prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hello")],
+            )
+            .await
+            .expect("brotli reply decodes");
+        assert_eq!(reply.content, "openai, brotli");
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn gzipped_json_responses_are_decoded() {
+    run_mock_test("gzipped json test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "openai, compressed" } }]
+                })))
+                .gzipped(),
+            ),
+            MockRoute::single(
+                "/v1/messages",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "content": [{ "type": "text", "text": "anthropic, compressed" }]
+                })))
+                .gzipped(),
+            ),
+            MockRoute::single(
+                GEMINI_PATH,
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "candidates": [{ "content": { "parts": [{ "text": "gemini, compressed" }] } }]
+                })))
+                .gzipped(),
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let options = || ClientOptions::for_mock_server(&server).expect("client options");
+        let clients: Vec<(Box<dyn PromptCore>, &str, &str)> = vec![
+            (
+                Box::new(
+                    OpenAIClient::try_with_options("gpt-4o-mini", options()).expect("known model"),
+                ),
+                "/v1/chat/completions",
+                "openai, compressed",
+            ),
+            (
+                Box::new(
+                    AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options())
+                        .expect("known model"),
+                ),
+                "/v1/messages",
+                "anthropic, compressed",
+            ),
+            (
+                Box::new(
+                    GeminiClient::try_with_options("gemini-2.0-flash", options())
+                        .expect("known model"),
+                ),
+                GEMINI_PATH,
+                "gemini, compressed",
+            ),
+        ];
+
+        for (client, path, expected) in clients {
+            let reply = client
+                .prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hello")],
+                )
+                .await
+                .unwrap_or_else(|err| panic!("{} reply decodes: {}", path, err));
+            assert_eq!(reply.content, expected);
+
+            let requests = server.requests_for(path).await;
+            assert_eq!(
+                requests[0]
+                    .headers
+                    .get("accept-encoding")
+                    .map(String::as_str),
+                Some("gzip, br"),
+                "{}",
+                path
+            );
+        }
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn gzipped_streams_are_decoded_as_events_arrive() {
+    run_mock_test("gzipped stream test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(["Hel", "lo", " there"])
+                    .with_chunk_delay(Duration::from_millis(100))
+                    .gzipped(),
+            ),
+            MockRoute::single(
+                "/v1/messages",
+                MockResponse::anthropic_text_stream(["Bon", "jour"]).gzipped(),
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let options = || ClientOptions::for_mock_server(&server).expect("client options");
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let received = tokio::spawn(async move {
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push((delta, tokio::time::Instant::now()));
+            }
+            deltas
+        });
+        let reply = OpenAIClient::try_with_options("gpt-4o-mini", options())
+            .expect("known model")
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect("openai stream decodes");
+        assert_eq!(reply.content, "Hello there");
+
+        // Each flushed event is decoded as soon as it arrives, not at the end
+        let received = received.await.expect("receiver task");
+        let deltas: Vec<&str> = received.iter().map(|(delta, _)| delta.as_str()).collect();
+        assert_eq!(deltas, vec!["Hel", "lo", " there"]);
+        assert!(received[2].1 - received[0].1 >= Duration::from_millis(150));
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let reply = AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options())
+            .expect("known model")
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect("anthropic stream decodes");
+        assert_eq!(reply.content, "Bonjour");
+
+        for path in ["/v1/chat/completions", "/v1/messages"] {
+            let requests = server.requests_for(path).await;
+            assert_eq!(
+                requests[0]
+                    .headers
+                    .get("accept-encoding")
+                    .map(String::as_str),
+                Some("gzip, br"),
+                "{}",
+                path
+            );
+        }
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,569 @@
+//! Conformance tests: every registered provider runs through every scenario
+//! that has expectations for it, against the mock server.
+//!
+//! A scenario is data: a system prompt, history, tools and options, the
+//! fields each provider's recorded request body must hold (as JSON pointers,
+//! `null` meaning absent), and the reply the parsed response must carry.
+//! Supporting a new provider means adding a `Target` and its expectations to
+//! the scenarios that apply, not a new test file.
+
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{function_call, message, sample_tool};
+use serde_json::{json, Value};
+use temp_env::with_vars;
+use wire::config::{ClientOptions, ToolChoice};
+use wire::new_client_with_options;
+use wire::types::{Message, MessageType};
+
+const KEY: &str = "conformance-key";
+const REPLY: &str = "Conformance reply.";
+
+/// A provider under test: where its client sends, how it authenticates, and
+/// how its API answers with text.
+struct Target {
+    /// `Provider::as_str`, which replies must carry.
+    provider: &'static str,
+    /// The API its requests follow, which the scenarios key their
+    /// expectations by. Providers serving OpenAI's API share OpenAI's.
+    format: &'static str,
+    model: &'static str,
+    /// The model as request bodies name it, for APIs that name it there.
+    sent_as: Option<&'static str>,
+    /// The route the client sends to, query included.
+    path: &'static str,
+    /// A header every request must carry, if the key travels in one.
+    auth: Option<(&'static str, &'static str)>,
+    reply: fn(&str) -> MockResponse,
+}
+
+const BEARER: Option<(&str, &str)> = Some(("authorization", "Bearer conformance-key"));
+
+fn targets() -> Vec<Target> {
+    vec![
+        Target {
+            provider: "openai",
+            format: "openai",
+            model: "gpt-4o-mini",
+            sent_as: Some("gpt-4o-mini"),
+            path: "/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        Target {
+            provider: "anthropic",
+            format: "anthropic",
+            model: "claude-3-5-sonnet-20241022",
+            sent_as: Some("claude-3-5-sonnet-20241022"),
+            path: "/v1/messages",
+            auth: Some(("x-api-key", KEY)),
+            reply: |text| {
+                json_reply(json!({
+                    "stop_reason": "end_turn",
+                    "content": [{ "type": "text", "text": text }]
+                }))
+            },
+        },
+        Target {
+            provider: "gemini",
+            format: "gemini",
+            model: "gemini-2.0-flash",
+            sent_as: None,
+            path: "/v1beta/models/gemini-2.0-flash:generateContent?key=conformance-key",
+            auth: None,
+            reply: |text| {
+                json_reply(json!({
+                    "candidates": [{ "content": { "parts": [{ "text": text }] } }]
+                }))
+            },
+        },
+        #[cfg(feature = "ollama")]
+        Target {
+            provider: "ollama",
+            format: "ollama",
+            model: "ollama/llama3.1:8b",
+            sent_as: Some("llama3.1:8b"),
+            path: "/api/chat",
+            auth: None,
+            reply: |text| {
+                json_reply(json!({
+                    "message": { "role": "assistant", "content": text },
+                    "done": true
+                }))
+            },
+        },
+        #[cfg(feature = "cohere")]
+        Target {
+            provider: "cohere",
+            format: "cohere",
+            model: "command-r-08-2024",
+            sent_as: Some("command-r-08-2024"),
+            path: "/v2/chat",
+            auth: BEARER,
+            reply: |text| {
+                json_reply(json!({
+                    "finish_reason": "COMPLETE",
+                    "message": {
+                        "role": "assistant",
+                        "content": [{ "type": "text", "text": text }]
+                    }
+                }))
+            },
+        },
+        #[cfg(feature = "groq")]
+        Target {
+            provider: "groq",
+            format: "openai",
+            model: "llama-3.3-70b-versatile",
+            sent_as: Some("llama-3.3-70b-versatile"),
+            path: "/openai/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        #[cfg(feature = "openrouter")]
+        Target {
+            provider: "openrouter",
+            format: "openai",
+            model: "openrouter/anthropic/claude-sonnet-4",
+            sent_as: Some("anthropic/claude-sonnet-4"),
+            path: "/api/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        #[cfg(feature = "together")]
+        Target {
+            provider: "together",
+            format: "openai",
+            model: "together/meta-llama/Llama-3.3-70B-Instruct-Turbo",
+            sent_as: Some("meta-llama/Llama-3.3-70B-Instruct-Turbo"),
+            path: "/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        #[cfg(feature = "fireworks")]
+        Target {
+            provider: "fireworks",
+            format: "openai",
+            model: "fireworks/accounts/fireworks/models/llama-v3p1-70b-instruct",
+            sent_as: Some("accounts/fireworks/models/llama-v3p1-70b-instruct"),
+            path: "/inference/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        #[cfg(feature = "perplexity")]
+        Target {
+            provider: "perplexity",
+            format: "openai",
+            model: "sonar",
+            sent_as: Some("sonar"),
+            path: "/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+        #[cfg(feature = "compatible")]
+        Target {
+            provider: "custom",
+            format: "openai",
+            model: "custom/my-model",
+            sent_as: Some("my-model"),
+            path: "/v1/chat/completions",
+            auth: BEARER,
+            reply: openai_reply,
+        },
+    ]
+}
+
+fn openai_reply(text: &str) -> MockResponse {
+    json_reply(json!({ "choices": [{ "message": { "content": text } }] }))
+}
+
+fn json_reply(body: Value) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(body))
+}
+
+struct Scenario {
+    name: &'static str,
+    system: &'static str,
+    history: Vec<Message>,
+    /// Sent through the tool loop when not empty, else through `prompt`.
+    tools: Vec<&'static str>,
+    options: fn(ClientOptions) -> ClientOptions,
+    /// Per format, JSON pointers into the request body and the values found
+    /// there. Targets whose format has no entry skip the scenario.
+    request: Vec<(&'static str, Vec<(&'static str, Value)>)>,
+}
+
+fn scenarios() -> Vec<Scenario> {
+    vec![
+        Scenario {
+            name: "system prompt and plain turns",
+            system: "Follow the safety rules.",
+            history: vec![
+                message(MessageType::User, "Hi there"),
+                message(MessageType::Assistant, "Hello human"),
+                message(MessageType::User, "How are you?"),
+            ],
+            tools: Vec::new(),
+            options: |options| options,
+            request: vec![
+                (
+                    "openai",
+                    vec![
+                        ("/stream", json!(false)),
+                        ("/messages/0/role", json!("system")),
+                        ("/messages/0/content", json!("Follow the safety rules.")),
+                        ("/messages/1/role", json!("user")),
+                        ("/messages/1/content", json!("Hi there")),
+                        ("/messages/2/role", json!("assistant")),
+                        ("/messages/2/content", json!("Hello human")),
+                        ("/messages/3/role", json!("user")),
+                        ("/messages/4", Value::Null),
+                        ("/tools", Value::Null),
+                    ],
+                ),
+                (
+                    "anthropic",
+                    vec![
+                        ("/system", json!("Follow the safety rules.")),
+                        ("/messages/0/role", json!("user")),
+                        ("/messages/0/content", json!("Hi there")),
+                        ("/messages/1/role", json!("assistant")),
+                        ("/messages/1/content/0/type", json!("text")),
+                        ("/messages/1/content/0/text", json!("Hello human")),
+                        ("/messages/2/role", json!("user")),
+                        ("/messages/3", Value::Null),
+                        ("/tools", Value::Null),
+                    ],
+                ),
+                (
+                    "gemini",
+                    vec![
+                        (
+                            "/system_instruction/parts/0/text",
+                            json!("Follow the safety rules."),
+                        ),
+                        ("/contents/0/role", json!("user")),
+                        ("/contents/0/parts/0/text", json!("Hi there")),
+                        ("/contents/1/role", json!("model")),
+                        ("/contents/1/parts/0/text", json!("Hello human")),
+                        ("/contents/2/role", json!("user")),
+                        ("/contents/3", Value::Null),
+                        ("/tools", Value::Null),
+                        ("/toolConfig", Value::Null),
+                    ],
+                ),
+                (
+                    "ollama",
+                    vec![
+                        ("/stream", json!(false)),
+                        ("/messages/0/role", json!("system")),
+                        ("/messages/0/content", json!("Follow the safety rules.")),
+                        ("/messages/1/role", json!("user")),
+                        ("/messages/1/content", json!("Hi there")),
+                        ("/messages/2/role", json!("assistant")),
+                        ("/messages/2/content", json!("Hello human")),
+                        ("/messages/3/role", json!("user")),
+                        ("/messages/4", Value::Null),
+                        ("/tools", Value::Null),
+                    ],
+                ),
+                (
+                    "cohere",
+                    vec![
+                        ("/stream", json!(false)),
+                        ("/messages/0/role", json!("system")),
+                        ("/messages/0/content", json!("Follow the safety rules.")),
+                        ("/messages/1/role", json!("user")),
+                        ("/messages/1/content", json!("Hi there")),
+                        ("/messages/2/role", json!("assistant")),
+                        ("/messages/2/content", json!("Hello human")),
+                        ("/messages/3/role", json!("user")),
+                        ("/messages/4", Value::Null),
+                        ("/tools", Value::Null),
+                    ],
+                ),
+            ],
+        },
+        Scenario {
+            name: "tool call paired with its output",
+            system: "Always explain your reasoning.",
+            history: tool_exchange(r#"{"forecast":"snow"}"#),
+            tools: vec!["lookup_weather"],
+            options: |options| options,
+            request: vec![
+                (
+                    "openai",
+                    vec![
+                        ("/messages/0/role", json!("system")),
+                        ("/messages/1/content", json!("What's the weather?")),
+                        ("/messages/2/role", json!("assistant")),
+                        ("/messages/2/tool_calls/0/id", json!("call-1")),
+                        (
+                            "/messages/2/tool_calls/0/function/name",
+                            json!("lookup_weather"),
+                        ),
+                        ("/messages/3/role", json!("tool")),
+                        ("/messages/3/tool_call_id", json!("call-1")),
+                        ("/messages/3/content", json!(r#"{"forecast":"snow"}"#)),
+                        ("/messages/4", Value::Null),
+                        ("/tools/0/type", json!("function")),
+                        ("/tools/0/function/name", json!("lookup_weather")),
+                        ("/tools/1", Value::Null),
+                    ],
+                ),
+                (
+                    "anthropic",
+                    vec![
+                        ("/system", json!("Always explain your reasoning.")),
+                        ("/messages/0/content", json!("What's the weather?")),
+                        ("/messages/1/role", json!("assistant")),
+                        ("/messages/1/content/0/type", json!("tool_use")),
+                        ("/messages/1/content/0/id", json!("call-1")),
+                        ("/messages/1/content/0/name", json!("lookup_weather")),
+                        ("/messages/1/content/0/input", json!({ "zip": "10001" })),
+                        ("/messages/2/role", json!("user")),
+                        ("/messages/2/content/0/type", json!("tool_result")),
+                        ("/messages/2/content/0/tool_use_id", json!("call-1")),
+                        (
+                            "/messages/2/content/0/content",
+                            json!(r#"{"forecast":"snow"}"#),
+                        ),
+                        ("/messages/3", Value::Null),
+                        ("/tools/0/name", json!("lookup_weather")),
+                        ("/tools/0/input_schema/type", json!("object")),
+                        ("/tools/1", Value::Null),
+                    ],
+                ),
+                (
+                    "gemini",
+                    vec![
+                        ("/contents/1/role", json!("model")),
+                        (
+                            "/contents/1/parts/0/functionCall",
+                            json!({ "name": "lookup_weather", "args": { "zip": "10001" } }),
+                        ),
+                        ("/contents/2/role", json!("user")),
+                        (
+                            "/contents/2/parts/0/functionResponse",
+                            json!({ "name": "lookup_weather", "response": { "forecast": "snow" } }),
+                        ),
+                        (
+                            "/tools/0/functionDeclarations/0/name",
+                            json!("lookup_weather"),
+                        ),
+                    ],
+                ),
+                (
+                    "ollama",
+                    vec![
+                        ("/messages/1/content", json!("What's the weather?")),
+                        ("/messages/2/role", json!("assistant")),
+                        (
+                            "/messages/2/tool_calls/0/function",
+                            json!({ "name": "lookup_weather", "arguments": { "zip": "10001" } }),
+                        ),
+                        ("/messages/3/role", json!("tool")),
+                        ("/messages/3/content", json!(r#"{"forecast":"snow"}"#)),
+                        ("/messages/4", Value::Null),
+                        ("/tools/0/type", json!("function")),
+                        ("/tools/0/function/name", json!("lookup_weather")),
+                        ("/tools/1", Value::Null),
+                    ],
+                ),
+                (
+                    "cohere",
+                    vec![
+                        ("/messages/1/content", json!("What's the weather?")),
+                        ("/messages/2/role", json!("assistant")),
+                        ("/messages/2/tool_calls/0/id", json!("call-1")),
+                        (
+                            "/messages/2/tool_calls/0/function",
+                            json!({ "name": "lookup_weather", "arguments": r#"{"zip":"10001"}"# }),
+                        ),
+                        ("/messages/2/tool_plan", Value::Null),
+                        ("/messages/3/role", json!("tool")),
+                        ("/messages/3/tool_call_id", json!("call-1")),
+                        (
+                            "/messages/3/content/0/document/data",
+                            json!(r#"{"forecast":"snow"}"#),
+                        ),
+                        ("/messages/4", Value::Null),
+                        ("/tools/0/function/name", json!("lookup_weather")),
+                        ("/tools/1", Value::Null),
+                    ],
+                ),
+            ],
+        },
+        Scenario {
+            name: "tool output that isn't a JSON object",
+            system: "Be helpful.",
+            history: tool_exchange("\"snow\""),
+            tools: vec!["lookup_weather"],
+            options: |options| options,
+            request: vec![(
+                "gemini",
+                vec![(
+                    "/contents/2/parts/0/functionResponse",
+                    json!({ "name": "lookup_weather", "response": { "result": "snow" } }),
+                )],
+            )],
+        },
+        Scenario {
+            name: "tool choice any",
+            system: "Use tools.",
+            history: vec![message(MessageType::User, "Find it")],
+            tools: vec!["search"],
+            options: |options| options.with_tool_choice(ToolChoice::Any),
+            request: vec![
+                ("openai", vec![("/tool_choice", json!("required"))]),
+                (
+                    "anthropic",
+                    vec![("/tool_choice", json!({ "type": "any" }))],
+                ),
+                (
+                    "gemini",
+                    vec![("/toolConfig/functionCallingConfig/mode", json!("ANY"))],
+                ),
+                // Ollama has no tool choice; the client warns and sends none
+                ("ollama", vec![("/tool_choice", Value::Null)]),
+                ("cohere", vec![("/tool_choice", json!("REQUIRED"))]),
+            ],
+        },
+    ]
+}
+
+/// A question, a `lookup_weather` call answering it, and the call's output.
+fn tool_exchange(output: &str) -> Vec<Message> {
+    let mut call = message(MessageType::FunctionCall, "");
+    call.tool_calls = Some(vec![function_call(
+        "call-1",
+        "lookup_weather",
+        json!({ "zip": "10001" }),
+    )]);
+    let mut result = message(MessageType::FunctionCallOutput, output);
+    result.tool_call_id = Some("call-1".to_string());
+
+    vec![
+        message(MessageType::User, "What's the weather?"),
+        call,
+        result,
+    ]
+}
+
+async fn run(target: &Target, scenario: &Scenario, expected: &[(&'static str, Value)]) {
+    let case = format!("{} / {}", target.provider, scenario.name);
+    let server = MockLLMServer::start(vec![MockRoute::single(target.path, (target.reply)(REPLY))])
+        .await
+        .expect("mock server starts");
+    let options =
+        (scenario.options)(ClientOptions::for_mock_server(&server).expect("mock server options"));
+    let client = new_client_with_options(target.model, options).expect("target model resolves");
+
+    let reply = if scenario.tools.is_empty() {
+        client
+            .prompt(scenario.system.to_string(), scenario.history.clone())
+            .await
+            .unwrap_or_else(|err| panic!("{}: prompt failed: {}", case, err))
+    } else {
+        let tools = scenario
+            .tools
+            .iter()
+            .map(|name| sample_tool(name))
+            .collect();
+        client
+            .tools()
+            .unwrap_or_else(|| panic!("{}: client runs no tools", case))
+            .prompt_with_tools(scenario.system, scenario.history.clone(), tools)
+            .await
+            .unwrap_or_else(|err| panic!("{}: tool loop failed: {}", case, err))
+            .pop()
+            .expect("the tool loop returns the reply")
+    };
+    assert_eq!(reply.content, REPLY, "{}: reply content", case);
+    assert_eq!(
+        reply.api.provider().as_str(),
+        target.provider,
+        "{}: reply api",
+        case
+    );
+
+    let recorded = server.requests_for(target.path).await;
+    assert_eq!(recorded.len(), 1, "{}: requests sent", case);
+    if let Some((name, value)) = target.auth {
+        assert_eq!(
+            recorded[0].headers.get(name).map(String::as_str),
+            Some(value),
+            "{}: {} header",
+            case,
+            name
+        );
+    }
+
+    let body: Value = serde_json::from_slice(&recorded[0].body).expect("request body is json");
+    if let Some(model) = target.sent_as {
+        assert_eq!(body["model"], model, "{}: model", case);
+    }
+    for (pointer, value) in expected {
+        assert_eq!(
+            body.pointer(pointer).unwrap_or(&Value::Null),
+            value,
+            "{}: {} in {}",
+            case,
+            pointer,
+            serde_json::to_string_pretty(&body).unwrap()
+        );
+    }
+
+    server.shutdown().await;
+}
+
+#[test]
+fn every_target_conforms_to_every_scenario() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping conformance tests");
+        return;
+    }
+
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some(KEY)),
+            ("ANTHROPIC_API_KEY", Some(KEY)),
+            ("GEMINI_API_KEY", Some(KEY)),
+            ("COHERE_API_KEY", Some(KEY)),
+            ("GROQ_API_KEY", Some(KEY)),
+            ("OPENROUTER_API_KEY", Some(KEY)),
+            ("TOGETHER_API_KEY", Some(KEY)),
+            ("FIREWORKS_API_KEY", Some(KEY)),
+            ("PERPLEXITY_API_KEY", Some(KEY)),
+            ("OPENAI_COMPATIBLE_API_KEY", Some(KEY)),
+        ],
+        || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for conformance tests");
+            runtime.block_on(async {
+                let scenarios = scenarios();
+                for target in targets() {
+                    let mut ran = 0;
+                    for scenario in &scenarios {
+                        let expected = scenario
+                            .request
+                            .iter()
+                            .find(|(format, _)| *format == target.format);
+                        if let Some((_, expected)) = expected {
+                            run(&target, scenario, expected).await;
+                            ran += 1;
+                        }
+                    }
+                    assert!(ran > 0, "{} has no scenarios", target.provider);
+                }
+            });
+        },
+    );
+}
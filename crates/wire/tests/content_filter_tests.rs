@@ -0,0 +1,175 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::message;
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::content_filter::{ContentFilter, PiiScrubber};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{Message, MessageType};
+
+const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+const SYSTEM: &str = "Escalations go to oncall@example.com.";
+
+fn history() -> Vec<Message> {
+    vec![
+        message(
+            MessageType::User,
+            "I'm ada@example.com, call me on +14155552671.",
+        ),
+        message(MessageType::Assistant, "Noted."),
+        message(MessageType::User, "My card is 4111 1111 1111 1111."),
+    ]
+}
+
+#[test]
+fn pii_scrubber_replaces_known_shapes() {
+    let scrubber = PiiScrubber::new();
+
+    assert_eq!(
+        scrubber.scrub("Write to a.b+tag@mail.example.co.uk today"),
+        "Write to [EMAIL] today"
+    );
+    assert_eq!(
+        scrubber.scrub("Call +14155552671 or +442071838750."),
+        "Call [PHONE] or [PHONE]."
+    );
+    assert_eq!(
+        scrubber.scrub("Cards 4111-1111-1111-1111 and 378282246310005"),
+        "Cards [CARD] and [CARD]"
+    );
+    // Short numbers, dates and local phone formats are left alone
+    assert_eq!(
+        scrubber.scrub("Order 12345 on 2024-01-31, ext 4155552671"),
+        "Order 12345 on 2024-01-31, ext 4155552671"
+    );
+}
+
+#[test]
+fn filters_see_the_message_type() {
+    let filter = ContentFilter::new(|message_type, text| match message_type {
+        MessageType::User => text.to_uppercase(),
+        _ => text.to_string(),
+    });
+
+    assert_eq!(filter.apply(&MessageType::User, "hi"), "HI");
+    assert_eq!(filter.apply(&MessageType::System, "hi"), "hi");
+}
+
+#[test]
+fn request_bodies_are_scrubbed_but_history_is_not() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping content filter request body test");
+        return;
+    }
+
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("GEMINI_API_KEY", Some("mock-gemini-key")),
+        ],
+        || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for filter test");
+
+            runtime.block_on(async {
+                let server = MockLLMServer::start(vec![
+                    MockRoute::single(
+                        "/v1/chat/completions",
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{ "message": { "content": "ok" } }]
+                        }))),
+                    ),
+                    MockRoute::single(
+                        "/v1/messages",
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "stop_reason": "end_turn",
+                            "content": [{ "type": "text", "text": "ok" }]
+                        }))),
+                    ),
+                    MockRoute::single(
+                        GEMINI_PATH,
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "candidates": [{ "content": { "parts": [{ "text": "ok" }] } }]
+                        }))),
+                    ),
+                ])
+                .await
+                .expect("mock server starts");
+
+                let options = ClientOptions::for_mock_server(&server)
+                    .expect("client options for mock server")
+                    .with_content_filter(PiiScrubber::new().into_filter());
+                let clients: Vec<Box<dyn PromptCore>> = vec![
+                    Box::new(
+                        OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                            .expect("known model"),
+                    ),
+                    Box::new(
+                        AnthropicClient::try_with_options(
+                            "claude-3-5-haiku-20241022",
+                            options.clone(),
+                        )
+                        .expect("known model"),
+                    ),
+                    Box::new(
+                        GeminiClient::try_with_options("gemini-2.0-flash", options)
+                            .expect("known model"),
+                    ),
+                ];
+
+                let local = history();
+                for client in &clients {
+                    let reply = client
+                        .prompt(SYSTEM.to_string(), local.clone())
+                        .await
+                        .expect("prompt succeeds");
+                    assert_eq!(reply.system_prompt, SYSTEM);
+                }
+                let contents = |messages: &[Message]| {
+                    messages
+                        .iter()
+                        .map(|message| message.content.clone())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(contents(&local), contents(&history()));
+
+                for path in ["/v1/chat/completions", "/v1/messages", GEMINI_PATH] {
+                    let requests = server.requests_for(path).await;
+                    assert_eq!(requests.len(), 1, "{}", path);
+                    let body = String::from_utf8(requests[0].body.clone()).expect("utf-8 body");
+
+                    for scrubbed in ["[EMAIL]", "[PHONE]", "[CARD]", "Escalations go to [EMAIL]."] {
+                        assert!(
+                            body.contains(scrubbed),
+                            "{} lacks {}: {}",
+                            path,
+                            scrubbed,
+                            body
+                        );
+                    }
+                    for original in [
+                        "ada@example.com",
+                        "oncall@example.com",
+                        "+14155552671",
+                        "4111 1111",
+                    ] {
+                        assert!(!body.contains(original), "{} leaks {}", path, original);
+                    }
+                }
+
+                server.shutdown().await;
+            });
+        },
+    );
+}
@@ -0,0 +1,401 @@
+//! Contract tests: every request body the clients build is validated against
+//! a JSON Schema of the provider API (see `tests/fixtures/contracts`), across
+//! the option combinations wire supports. The schemas only admit the fields
+//! wire is known to send, so a new field or a malformed value fails here
+//! before it reaches a provider.
+
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+
+mod common;
+
+use common::{function_call, message, raw_request_body, request_body_json, sample_tool};
+use std::path::PathBuf;
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, RawTransport};
+use wire::config::{ClientOptions, ThinkingLevel, ToolChoice};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{Message, MessageType, ToolSpec};
+
+fn contract(name: &str) -> jsonschema::Validator {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/contracts")
+        .join(name);
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err));
+    let schema: serde_json::Value = serde_json::from_str(&contents)
+        .unwrap_or_else(|err| panic!("{} is not valid JSON: {}", path.display(), err));
+
+    jsonschema::validator_for(&schema)
+        .unwrap_or_else(|err| panic!("{} is not a valid schema: {}", path.display(), err))
+}
+
+fn assert_conforms(validator: &jsonschema::Validator, case: &str, body: &serde_json::Value) {
+    let errors: Vec<String> = validator
+        .iter_errors(body)
+        .map(|err| format!("  {}: {}", err.instance_path, err))
+        .collect();
+
+    assert!(
+        errors.is_empty(),
+        "{} violates the contract:\n{}\nbody: {}",
+        case,
+        errors.join("\n"),
+        serde_json::to_string_pretty(body).unwrap()
+    );
+}
+
+fn with_keys<F: FnOnce()>(f: F) {
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("contract-key")),
+            ("ANTHROPIC_API_KEY", Some("contract-key")),
+            ("GEMINI_API_KEY", Some("contract-key")),
+        ],
+        f,
+    );
+}
+
+fn tool_sets() -> Vec<(&'static str, Option<Vec<ToolSpec>>)> {
+    let mut nested = sample_tool("search_records");
+    nested.parameters = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "filters": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": { "field": { "type": "string" } }
+                }
+            }
+        },
+        "required": ["query"]
+    });
+
+    vec![
+        ("no tools", None),
+        (
+            "tools",
+            Some(vec![sample_tool("lookup_weather").spec(), nested.spec()]),
+        ),
+        (
+            "strict tools",
+            Some(vec![
+                sample_tool("lookup_weather").strict(true).spec(),
+                nested.strict(true).spec(),
+            ]),
+        ),
+    ]
+}
+
+fn tool_output(id: &str, content: &str) -> Message {
+    let mut output = message(MessageType::FunctionCallOutput, content);
+    output.tool_call_id = Some(id.to_string());
+    output.name = Some("lookup_weather".to_string());
+    output
+}
+
+/// Conversations shaped like the ones each client's own tool loop produces.
+fn histories(tool_call_type: MessageType) -> Vec<(&'static str, Vec<Message>)> {
+    let mut call = message(tool_call_type, "");
+    call.tool_calls = Some(vec![
+        function_call(
+            "call-1",
+            "lookup_weather",
+            serde_json::json!({ "zip": "10001" }),
+        ),
+        function_call("call-2", "lookup_weather", serde_json::json!({})),
+    ]);
+
+    let mut call_with_text = call.clone();
+    call_with_text.content = "Checking both.".to_string();
+
+    vec![
+        (
+            "single user turn",
+            vec![message(MessageType::User, "Hello")],
+        ),
+        (
+            "multi turn",
+            vec![
+                message(MessageType::User, "Hello"),
+                message(MessageType::Assistant, "Hi! How can I help?"),
+                message(MessageType::User, "Tell me a joke"),
+            ],
+        ),
+        (
+            "tool round trip",
+            vec![
+                message(MessageType::User, "Weather in 10001 and nearby?"),
+                call,
+                tool_output("call-1", "snow"),
+                tool_output("call-2", "{\"temp\": 30}"),
+                message(MessageType::Assistant, "Snowy and cold."),
+                message(MessageType::User, "Thanks"),
+            ],
+        ),
+        (
+            "tool call with text",
+            vec![
+                message(MessageType::User, "Weather?"),
+                call_with_text,
+                tool_output("call-1", "snow"),
+                tool_output("call-2", "sleet"),
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn openai_requests_match_contract() {
+    let validator = contract("openai_chat_completions.schema.json");
+
+    with_keys(|| {
+        let thinking_levels = [
+            None,
+            Some(ThinkingLevel::Minimal),
+            Some(ThinkingLevel::Low),
+            Some(ThinkingLevel::Medium),
+            Some(ThinkingLevel::High),
+        ];
+
+        for model in ["gpt-4o-mini", "gpt-5"] {
+            for (thinking_level, max_tokens) in thinking_levels
+                .into_iter()
+                .flat_map(|level| [(level, None), (level, Some(16))])
+            {
+                let options = ClientOptions {
+                    thinking_level,
+                    max_tokens,
+                    ..Default::default()
+                };
+                let client = OpenAIClient::try_with_options(model, options).expect("known model");
+
+                for (history_name, history) in histories(MessageType::FunctionCall) {
+                    for stream in [false, true] {
+                        for (tools_name, tools) in tool_sets() {
+                            let case = format!(
+                                "{} / {:?} / max_tokens={:?} / {} / stream={} / {}",
+                                model, thinking_level, max_tokens, history_name, stream, tools_name
+                            );
+                            let request = client
+                                .build_request(
+                                    "Be helpful.".to_string(),
+                                    history.clone(),
+                                    tools.as_deref(),
+                                    stream,
+                                )
+                                .expect("request builds")
+                                .build()
+                                .expect("request builds");
+
+                            assert_conforms(&validator, &case, &request_body_json(&request));
+                        }
+                    }
+                }
+
+                let raw = client
+                    .build_request_raw(
+                        "Be helpful.".to_string(),
+                        vec![message(MessageType::User, "Hello")],
+                        true,
+                    )
+                    .expect("request builds");
+                let case = format!("{} / {:?} / {:?} / raw", model, thinking_level, max_tokens);
+                assert_conforms(&validator, &case, &raw_request_body(&raw));
+            }
+        }
+    });
+}
+
+#[test]
+fn anthropic_requests_match_contract() {
+    let validator = contract("anthropic_messages.schema.json");
+
+    with_keys(|| {
+        for model in ["claude-3-5-haiku-20241022", "claude-sonnet-4-20250514"] {
+            for max_tokens in [1, 4096] {
+                let client = AnthropicClient::try_with_options(
+                    model,
+                    ClientOptions::default().with_max_tokens(max_tokens),
+                )
+                .expect("known model");
+
+                for (history_name, history) in histories(MessageType::Assistant) {
+                    for stream in [false, true] {
+                        for (tools_name, tools) in tool_sets() {
+                            let case = format!(
+                                "{} / max_tokens={} / {} / stream={} / {}",
+                                model, max_tokens, history_name, stream, tools_name
+                            );
+                            let request = client
+                                .build_request(
+                                    "Be helpful.".to_string(),
+                                    history.clone(),
+                                    tools.as_deref(),
+                                    stream,
+                                )
+                                .expect("request builds")
+                                .build()
+                                .expect("request builds");
+
+                            assert_conforms(&validator, &case, &request_body_json(&request));
+                        }
+                    }
+
+                    let raw = client
+                        .build_request_raw("Be helpful.".to_string(), history.clone(), true)
+                        .expect("request builds");
+                    let case = format!("{} / {} / raw", model, history_name);
+                    assert_conforms(&validator, &case, &raw_request_body(&raw));
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn gemini_requests_match_contract() {
+    let validator = contract("gemini_generate_content.schema.json");
+
+    with_keys(|| {
+        let clients = [
+            GeminiClient::try_new("gemini-2.0-flash").expect("known model"),
+            GeminiClient::try_with_options(
+                "gemini-2.0-flash",
+                ClientOptions::default().with_max_tokens(16),
+            )
+            .expect("known model"),
+        ];
+
+        let cases = clients.iter().flat_map(|client| {
+            histories(MessageType::FunctionCall)
+                .into_iter()
+                .map(move |history| (client, history))
+        });
+
+        for (client, (history_name, history)) in cases {
+            for stream in [false, true] {
+                for (tools_name, tools) in tool_sets() {
+                    let case = format!(
+                        "max_tokens={:?} / {} / stream={} / {}",
+                        client.options.max_tokens, history_name, stream, tools_name
+                    );
+                    let request = client
+                        .build_request(
+                            "Be helpful.".to_string(),
+                            history.clone(),
+                            tools.as_deref(),
+                            stream,
+                        )
+                        .expect("request builds")
+                        .build()
+                        .expect("request builds");
+
+                    assert_conforms(&validator, &case, &request_body_json(&request));
+                }
+
+                let raw = client
+                    .build_request_raw("Be helpful.".to_string(), history.clone(), stream)
+                    .expect("request builds");
+                let case = format!(
+                    "max_tokens={:?} / {} / stream={} / raw",
+                    client.options.max_tokens, history_name, stream
+                );
+                assert_conforms(&validator, &case, &raw_request_body(&raw));
+            }
+        }
+    });
+}
+
+#[test]
+fn tool_choice_requests_match_contract() {
+    let openai = contract("openai_chat_completions.schema.json");
+    let anthropic = contract("anthropic_messages.schema.json");
+    let gemini = contract("gemini_generate_content.schema.json");
+
+    with_keys(|| {
+        for tool_choice in [ToolChoice::Auto, ToolChoice::Any, ToolChoice::None] {
+            let options = ClientOptions::default().with_tool_choice(tool_choice);
+            let clients: [(&jsonschema::Validator, Box<dyn PromptCore>); 3] = [
+                (
+                    &openai,
+                    Box::new(
+                        OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                            .expect("known model"),
+                    ),
+                ),
+                (
+                    &anthropic,
+                    Box::new(
+                        AnthropicClient::try_with_options(
+                            "claude-3-5-haiku-20241022",
+                            options.clone(),
+                        )
+                        .expect("known model"),
+                    ),
+                ),
+                (
+                    &gemini,
+                    Box::new(
+                        GeminiClient::try_with_options("gemini-2.0-flash", options)
+                            .expect("known model"),
+                    ),
+                ),
+            ];
+
+            for (validator, client) in &clients {
+                for (tools_name, tools) in tool_sets() {
+                    let request = client
+                        .build_request(
+                            "Be helpful.".to_string(),
+                            vec![message(MessageType::User, "Hello")],
+                            tools.as_deref(),
+                            false,
+                        )
+                        .expect("request builds")
+                        .build()
+                        .expect("request builds");
+                    let case = format!("{:?} / {}", tool_choice, tools_name);
+
+                    assert_conforms(validator, &case, &request_body_json(&request));
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn contracts_reject_known_bad_payloads() {
+    let openai = contract("openai_chat_completions.schema.json");
+    let anthropic = contract("anthropic_messages.schema.json");
+
+    // A placeholder participant name on tool-call messages
+    let named_call = serde_json::json!({
+        "model": "gpt-4o-mini",
+        "messages": [{
+            "role": "assistant",
+            "content": "",
+            "name": "idk",
+            "tool_calls": [{
+                "id": "call-1",
+                "type": "function",
+                "function": { "name": "lookup_weather", "arguments": "{}" }
+            }]
+        }]
+    });
+    assert!(!openai.is_valid(&named_call));
+
+    // Unparseable tool arguments forwarded as `null` input
+    let null_input = serde_json::json!({
+        "model": "claude-3-5-haiku-20241022",
+        "max_tokens": 16,
+        "messages": [{
+            "role": "assistant",
+            "content": [{ "type": "tool_use", "id": "t1", "name": "lookup", "input": null }]
+        }]
+    });
+    assert!(!anthropic.is_valid(&null_input));
+}
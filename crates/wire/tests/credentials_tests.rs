@@ -0,0 +1,190 @@
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+
+mod common;
+
+use common::message;
+use std::sync::{Arc, Barrier};
+use temp_env::with_var;
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, RawTransport};
+use wire::credentials::Credentials;
+use wire::error::WireError;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::router::RouterClient;
+use wire::types::MessageType;
+
+/// The key `client`'s next request would carry, wherever its provider puts it.
+fn sent_key(client: &dyn PromptCore) -> String {
+    let request = client
+        .build_request(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "Hello")],
+            None,
+            false,
+        )
+        .expect("key is set")
+        .build()
+        .expect("request builds");
+
+    if let Some(authorization) = request.headers().get("authorization") {
+        let authorization = authorization.to_str().unwrap();
+        return authorization.trim_start_matches("Bearer ").to_string();
+    }
+    if let Some(key) = request.headers().get("x-api-key") {
+        return key.to_str().unwrap().to_string();
+    }
+    request
+        .url()
+        .query_pairs()
+        .find(|(name, _)| name == "key")
+        .map(|(_, key)| key.into_owned())
+        .expect("request carries a key")
+}
+
+/// Run `f` with every provider's key variable set to `key`.
+fn with_vars_set<R>(key: &str, f: impl FnOnce() -> R) -> R {
+    temp_env::with_vars(
+        [
+            ("OPENAI_API_KEY", Some(key)),
+            ("ANTHROPIC_API_KEY", Some(key)),
+            ("GEMINI_API_KEY", Some(key)),
+        ],
+        f,
+    )
+}
+
+#[test]
+fn clients_keep_the_key_they_were_built_with() {
+    let first = with_var("OPENAI_API_KEY", Some("first-key"), || {
+        OpenAIClient::try_new("gpt-4o-mini").expect("known model")
+    });
+
+    // The second client is built, and its key stays in the environment,
+    // while the first one sends requests
+    let barrier = Arc::new(Barrier::new(2));
+    let second = std::thread::spawn({
+        let barrier = barrier.clone();
+        move || {
+            with_var("OPENAI_API_KEY", Some("second-key"), || {
+                let client = OpenAIClient::try_new("gpt-4o-mini").expect("known model");
+                barrier.wait();
+                barrier.wait();
+                client
+            })
+        }
+    });
+
+    barrier.wait();
+    assert_eq!(std::env::var("OPENAI_API_KEY").unwrap(), "second-key");
+    for _ in 0..100 {
+        assert_eq!(sent_key(&first), "first-key");
+    }
+    barrier.wait();
+
+    let second = second.join().expect("second client thread");
+    assert_eq!(sent_key(&second), "second-key");
+    assert_eq!(sent_key(&first), "first-key");
+}
+
+#[test]
+fn refresh_credentials_reads_the_environment_again() {
+    let clients = with_vars_set("old-key", || -> Vec<Arc<dyn PromptCore>> {
+        vec![
+            Arc::new(OpenAIClient::try_new("gpt-4o-mini").expect("known model")),
+            Arc::new(AnthropicClient::try_new("claude-3-5-haiku-20241022").expect("known model")),
+            Arc::new(GeminiClient::try_new("gemini-2.0-flash").expect("known model")),
+        ]
+    });
+    let router = clients.iter().skip(1).fold(
+        RouterClient::new("openai", clients[0].clone()),
+        |router, client| router.with_route("other", client.clone(), |_| false),
+    );
+
+    with_vars_set("new-key", || {
+        for client in &clients {
+            assert_eq!(sent_key(client.as_ref()), "old-key");
+        }
+
+        router.refresh_credentials();
+        for client in &clients {
+            assert_eq!(sent_key(client.as_ref()), "new-key");
+        }
+    });
+}
+
+#[test]
+fn credentials_are_redacted() {
+    let credentials = with_var("OPENAI_API_KEY", Some("sk-very-secret"), || {
+        Credentials::from_env("OPENAI_API_KEY")
+    });
+
+    let debug = format!("{:?}", credentials);
+    assert!(!debug.contains("sk-very-secret"), "{}", debug);
+    assert!(debug.contains("OPENAI_API_KEY"), "{}", debug);
+    assert_eq!(credentials.token().expect("a key"), "sk-very-secret");
+
+    let missing = with_var("OPENAI_API_KEY", None::<&str>, || {
+        Credentials::from_env("OPENAI_API_KEY")
+    });
+    assert!(missing.secret().is_none());
+    assert!(matches!(
+        missing.token(),
+        Err(WireError::AuthMissing { var }) if var == "OPENAI_API_KEY"
+    ));
+}
+
+#[test]
+fn missing_keys_fail_requests_instead_of_panicking() {
+    let clients: Vec<(Box<dyn RawTransport>, &str)> = temp_env::with_vars(
+        [
+            ("OPENAI_API_KEY", None::<&str>),
+            ("ANTHROPIC_API_KEY", None),
+            ("GEMINI_API_KEY", None),
+        ],
+        || {
+            vec![
+                (
+                    Box::new(OpenAIClient::try_new("gpt-4o-mini").expect("known model"))
+                        as Box<dyn RawTransport>,
+                    "OPENAI_API_KEY",
+                ),
+                (
+                    Box::new(
+                        AnthropicClient::try_new("claude-3-5-haiku-20241022").expect("known model"),
+                    ),
+                    "ANTHROPIC_API_KEY",
+                ),
+                (
+                    Box::new(GeminiClient::try_new("gemini-2.0-flash").expect("known model")),
+                    "GEMINI_API_KEY",
+                ),
+            ]
+        },
+    );
+
+    for (client, expected) in clients {
+        let history = || vec![message(MessageType::User, "Hello")];
+        let missing = |result: Result<(), WireError>| matches!(result, Err(WireError::AuthMissing { var }) if var == expected);
+
+        assert!(missing(client.get_auth_token().map(drop)), "{}", expected);
+        assert!(
+            missing(
+                client
+                    .build_request(String::new(), history(), None, false)
+                    .map(drop)
+            ),
+            "{}",
+            expected
+        );
+        assert!(
+            missing(
+                client
+                    .build_request_raw(String::new(), history(), true)
+                    .map(drop)
+            ),
+            "{}",
+            expected
+        );
+    }
+}
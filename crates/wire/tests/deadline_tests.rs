@@ -0,0 +1,123 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockLLMServer, MockResponse, MockRoute};
+use common::{message, run_with_mock_keys};
+use std::time::{Duration, Instant};
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{FinishReason, MessageType};
+
+const GEMINI_STREAM_PATH: &str =
+    "/v1beta/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key";
+
+fn words() -> Vec<String> {
+    (0..20).map(|i| format!("word{} ", i)).collect()
+}
+
+/// A route per provider streaming `words()`, `delay` apart.
+fn routes(delay: Duration) -> Vec<MockRoute> {
+    vec![
+        MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::openai_text_stream(words()).with_chunk_delay(delay),
+        ),
+        MockRoute::single(
+            "/v1/messages",
+            MockResponse::anthropic_text_stream(words()).with_chunk_delay(delay),
+        ),
+        MockRoute::single(
+            GEMINI_STREAM_PATH,
+            MockResponse::gemini_text_stream(words()).with_chunk_delay(delay),
+        ),
+    ]
+}
+
+fn clients(server: &MockLLMServer) -> Vec<Box<dyn PromptCore>> {
+    let options = ClientOptions::for_mock_server(server).expect("client options for mock server");
+
+    vec![
+        Box::new(
+            OpenAIClient::try_with_options("gpt-4o-mini", options.clone()).expect("known model"),
+        ),
+        Box::new(
+            AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                .expect("known model"),
+        ),
+        Box::new(GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model")),
+    ]
+}
+
+#[test]
+fn deadline_returns_partial_content() {
+    run_with_mock_keys(async {
+        // One second per response if read to the end
+        let server = MockLLMServer::start(routes(Duration::from_millis(50)))
+            .await
+            .expect("mock server starts");
+        let full = words().concat();
+
+        for client in clients(&server) {
+            let started = Instant::now();
+            let response = client
+                .prompt_with_deadline(
+                    "Be verbose.".to_string(),
+                    vec![message(MessageType::User, "Count slowly")],
+                    started + Duration::from_millis(300),
+                )
+                .await
+                .expect("deadline is not an error");
+
+            let elapsed = started.elapsed();
+            assert!(elapsed < Duration::from_millis(800), "took {:?}", elapsed);
+            assert!(response.is_partial());
+            assert_eq!(response.finish_reason, FinishReason::Deadline);
+            assert_eq!(response.message.message_type, MessageType::Assistant);
+            assert_eq!(response.message.system_prompt, "Be verbose.");
+
+            let content = &response.message.content;
+            assert!(!content.is_empty(), "nothing arrived before the deadline");
+            assert!(content.len() < full.len());
+            assert!(full.starts_with(content.as_str()), "{:?}", content);
+        }
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn deadline_returns_complete_message_when_in_time() {
+    run_with_mock_keys(async {
+        let server = MockLLMServer::start(routes(Duration::from_millis(1)))
+            .await
+            .expect("mock server starts");
+        let full = words().concat();
+
+        for client in clients(&server) {
+            let response = client
+                .prompt_with_deadline(
+                    "Be verbose.".to_string(),
+                    vec![message(MessageType::User, "Count quickly")],
+                    Instant::now() + Duration::from_secs(5),
+                )
+                .await
+                .expect("stream completes");
+
+            assert!(!response.is_partial());
+            assert_eq!(response.finish_reason, FinishReason::Complete);
+            assert_eq!(response.message.content, full);
+            assert!(response.message.metadata.latency.is_some());
+        }
+
+        server.shutdown().await;
+    });
+}
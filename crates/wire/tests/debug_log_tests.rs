@@ -0,0 +1,252 @@
+#![cfg(feature = "mock")]
+
+mod common;
+
+use common::message;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::debug_log::{DebugLogDir, LogRotation};
+use wire::echo::EchoClient;
+use wire::event_log::{read_event_log, EventSink, WireEvent};
+use wire::mock::TestClock;
+use wire::types::MessageType;
+use wire::warning::WireWarning;
+
+fn log_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wire-debug-log-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn request(index: usize) -> WireEvent {
+    WireEvent::Request {
+        provider: "openai".to_string(),
+        model: "gpt-4o-mini".to_string(),
+        path: "/v1/chat/completions".to_string(),
+        stream: false,
+        body: serde_json::json!({ "index": index }),
+    }
+}
+
+fn response(index: usize) -> WireEvent {
+    WireEvent::Response {
+        status: 200,
+        body: serde_json::json!({ "reply": index }),
+    }
+}
+
+fn reply(index: usize) -> WireEvent {
+    WireEvent::Message {
+        message: message(MessageType::Assistant, &format!("reply {}", index)),
+    }
+}
+
+fn exchange(sink: &DebugLogDir, index: usize) {
+    sink.record(&request(index));
+    sink.record(&response(index));
+    sink.record(&reply(index));
+}
+
+fn names(files: &[PathBuf]) -> Vec<String> {
+    files
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+fn on_disk(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn each_request_gets_its_own_file() {
+    let dir = log_dir("files");
+    let sink = DebugLogDir::open(&dir, LogRotation::default()).expect("log dir opens");
+
+    exchange(&sink, 1);
+    sink.record(&WireEvent::Warning {
+        warning: WireWarning::FieldClamped {
+            field: "max_tokens".to_string(),
+            provider: "openai".to_string(),
+            requested: 1_000_000,
+            sent: 16_384,
+        },
+    });
+    exchange(&sink, 2);
+
+    assert_eq!(
+        on_disk(&dir),
+        ["request-000001.jsonl", "request-000002.jsonl"]
+    );
+    let second = read_event_log(dir.join("request-000002.jsonl")).expect("log reads back");
+    assert_eq!(second.len(), 4);
+    assert!(matches!(second[0], WireEvent::Warning { .. }));
+    assert!(matches!(second[3], WireEvent::Message { .. }));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn oldest_files_go_first_when_over_count_or_size() {
+    let dir = log_dir("count");
+    let sink =
+        DebugLogDir::open(&dir, LogRotation::default().with_max_files(2)).expect("log dir opens");
+    for index in 1..=4 {
+        exchange(&sink, index);
+    }
+    assert_eq!(
+        on_disk(&dir),
+        ["request-000003.jsonl", "request-000004.jsonl"]
+    );
+    assert_eq!(names(&sink.files()), on_disk(&dir));
+
+    let file_bytes = std::fs::metadata(dir.join("request-000004.jsonl"))
+        .unwrap()
+        .len();
+    let dir = log_dir("size");
+    let sink = DebugLogDir::open(
+        &dir,
+        LogRotation::default().with_max_bytes(file_bytes * 3 - 1),
+    )
+    .expect("log dir opens");
+    for index in 1..=4 {
+        exchange(&sink, index);
+    }
+    assert_eq!(
+        on_disk(&dir),
+        ["request-000003.jsonl", "request-000004.jsonl"]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(log_dir("count"));
+}
+
+#[test]
+fn requests_in_flight_are_never_pruned() {
+    let dir = log_dir("in-flight");
+    let sink =
+        DebugLogDir::open(&dir, LogRotation::default().with_max_bytes(1)).expect("log dir opens");
+
+    exchange(&sink, 1);
+    assert!(
+        on_disk(&dir).is_empty(),
+        "a finished request over the limit goes"
+    );
+
+    sink.record(&request(2));
+    sink.record(&response(2));
+    assert_eq!(on_disk(&dir), ["request-000002.jsonl"]);
+    assert_eq!(
+        read_event_log(dir.join("request-000002.jsonl"))
+            .expect("log reads back")
+            .len(),
+        2
+    );
+
+    sink.record(&reply(2));
+    assert!(on_disk(&dir).is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn files_expire_by_the_sink_clock() {
+    let dir = log_dir("age");
+    let clock = TestClock::new();
+    let sink = DebugLogDir::open(
+        &dir,
+        LogRotation::default().with_max_age(Duration::from_secs(60)),
+    )
+    .expect("log dir opens")
+    .with_clock(clock.clone());
+
+    exchange(&sink, 1);
+    clock.advance(Duration::from_secs(30));
+    exchange(&sink, 2);
+    clock.advance(Duration::from_secs(30));
+    sink.record(&request(3));
+    assert_eq!(on_disk(&dir).len(), 3, "nothing is older than a minute yet");
+
+    clock.advance(Duration::from_secs(1));
+    sink.record(&response(3));
+    assert_eq!(
+        on_disk(&dir),
+        ["request-000002.jsonl", "request-000003.jsonl"]
+    );
+
+    // Pruning runs on events; the request in flight outlives its age
+    clock.advance(Duration::from_secs(300));
+    sink.record(&response(3));
+    assert_eq!(on_disk(&dir), ["request-000003.jsonl"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn reopening_keeps_numbering_and_applies_the_limits() {
+    let dir = log_dir("reopen");
+    {
+        let sink = DebugLogDir::open(&dir, LogRotation::default()).expect("log dir opens");
+        for index in 1..=3 {
+            exchange(&sink, index);
+        }
+    }
+    std::fs::write(dir.join("notes.txt"), "not a log").unwrap();
+
+    let sink =
+        DebugLogDir::open(&dir, LogRotation::default().with_max_files(2)).expect("log dir opens");
+    assert_eq!(
+        names(&sink.files()),
+        ["request-000002.jsonl", "request-000003.jsonl"]
+    );
+
+    exchange(&sink, 4);
+    assert_eq!(
+        on_disk(&dir),
+        ["notes.txt", "request-000003.jsonl", "request-000004.jsonl"]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn debug_tail_keeps_the_last_exchanges() {
+    let dir = log_dir("tail");
+    let sink = Arc::new(
+        DebugLogDir::open(&dir, LogRotation::default().with_max_files(1))
+            .expect("log dir opens")
+            .with_tail(2),
+    );
+    let client = EchoClient::with_options(ClientOptions::default().with_event_log(sink.clone()));
+    assert!(client.debug_tail().is_empty());
+
+    for index in 1..=3 {
+        exchange(&sink, index);
+    }
+    sink.record(&request(4));
+
+    let tail = client.debug_tail();
+    assert_eq!(tail.len(), 2);
+    assert!(matches!(
+        &tail[0].request,
+        WireEvent::Request { body, .. } if body["index"] == 3
+    ));
+    assert!(matches!(
+        tail[0].response.as_slice(),
+        [WireEvent::Response { body, .. }] if body["reply"] == 3
+    ));
+    assert!(tail[1].response.is_empty(), "request 4 is still in flight");
+    assert_eq!(tail[1].path, dir.join("request-000004.jsonl"));
+    // The tail outlives the files rotation removed
+    assert!(!tail[0].path.exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
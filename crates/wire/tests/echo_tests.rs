@@ -0,0 +1,275 @@
+mod common;
+
+use std::time::Duration;
+
+#[cfg(feature = "mock")]
+use common::mock_server::TestClock;
+use common::sample_tool;
+use wire::api::{PromptCore, ToolCapable, WireModel, API};
+use wire::config::{ClientOptions, PromptOptions};
+use wire::echo::{EchoClient, EchoClientConfig, InjectedStreamError};
+use wire::error::WireError;
+use wire::new_client;
+use wire::types::{FinishReason, Message, MessageBuilder, MessageType};
+
+fn user(content: &str) -> Message {
+    MessageBuilder::new(API::Wire(WireModel::Echo), content)
+        .message_type(MessageType::User)
+        .build()
+}
+
+#[test]
+fn echo_model_resolves_without_credentials() {
+    let api = API::from_model("wire:echo").expect("echo model known");
+    assert_eq!(api, API::Wire(WireModel::Echo));
+    assert_eq!(
+        api.to_strings(),
+        ("wire".to_string(), "wire:echo".to_string())
+    );
+    assert_eq!(
+        serde_json::to_value(&api).unwrap(),
+        serde_json::json!({ "provider": "wire", "model": "wire:echo" })
+    );
+    assert!(!wire::get_available_models().contains(&api));
+
+    assert!(new_client("wire:echo").is_ok());
+}
+
+#[test]
+fn echo_prompt_repeats_last_user_message() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = new_client("wire:echo").expect("echo client");
+
+    runtime.block_on(async {
+        let history = vec![
+            user("first"),
+            MessageBuilder::new(API::Wire(WireModel::Echo), "ignored")
+                .message_type(MessageType::Assistant)
+                .build(),
+            user("Hello, echo!"),
+        ];
+
+        let reply = client
+            .prompt("Be brief.".to_string(), history.clone())
+            .await
+            .expect("echo replies");
+        assert_eq!(reply.message_type, MessageType::Assistant);
+        assert_eq!(reply.content, "Hello, echo!");
+        assert_eq!(reply.system_prompt, "Be brief.");
+        assert_eq!(reply.api, API::Wire(WireModel::Echo));
+
+        let capped = client
+            .prompt_with_options(
+                "Be brief.".to_string(),
+                history,
+                &PromptOptions::new().with_max_response_bytes(5),
+            )
+            .await
+            .expect("echo replies");
+        assert_eq!(capped.content, "Hello");
+        assert_eq!(capped.metadata.truncated.unwrap().original_bytes, 12);
+    });
+}
+
+#[test]
+fn echo_stream_chunks_by_word() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = EchoClient::new();
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let reply = client
+            .prompt_stream(
+                vec![user("the quick  brown fox")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect("echo streams");
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk);
+        }
+
+        assert_eq!(chunks, vec!["the ", "quick ", " ", "brown ", "fox"]);
+        assert_eq!(reply.content, "the quick  brown fox");
+        assert_eq!(reply.metadata.latency.unwrap().deltas, 5);
+    });
+}
+
+#[test]
+fn echo_scripted_tool_round_trip() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = EchoClient::with_options(ClientOptions::default().with_max_tool_iterations(2));
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let history = client
+            .prompt_with_tools_with_status(
+                tx,
+                "Use tools.",
+                vec![user(
+                    "Checking the weather.\nCALL:lookup_weather:{\"zip\": \"10001\"}",
+                )],
+                vec![sample_tool("lookup_weather")],
+            )
+            .await
+            .expect("tool loop completes");
+
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[1].message_type, MessageType::FunctionCall);
+        let calls = history[1].tool_calls.as_ref().expect("tool calls");
+        assert_eq!(calls[0].id, "echo_0_0");
+        assert_eq!(calls[0].function.name, "lookup_weather");
+        assert_eq!(history[2].message_type, MessageType::FunctionCallOutput);
+        assert_eq!(history[2].tool_call_id.as_deref(), Some("echo_0_0"));
+        assert_eq!(history[2].content, r#"{"zip":"10001"}"#);
+        assert_eq!(history[3].message_type, MessageType::Assistant);
+        assert_eq!(history[3].content, r#"{"zip":"10001"}"#);
+
+        assert_eq!(
+            rx.recv().await.as_deref(),
+            Some("calling tool lookup_weather...")
+        );
+
+        let err = client
+            .prompt_with_tools(
+                "Use tools.",
+                vec![user("CALL:lookup_weather:{not json")],
+                vec![sample_tool("lookup_weather")],
+            )
+            .await
+            .expect_err("bad arguments are rejected");
+        assert!(matches!(
+            err,
+            WireError::ToolExecution { name, reason }
+                if name == "lookup_weather" && reason.starts_with("invalid arguments")
+        ));
+    });
+}
+
+#[test]
+fn echo_deadline_returns_the_complete_echo() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = EchoClient::new();
+
+    let response = runtime
+        .block_on(client.prompt_with_deadline(
+            "Be brief.".to_string(),
+            vec![user("hello there")],
+            std::time::Instant::now() + std::time::Duration::from_secs(5),
+        ))
+        .expect("echo completes");
+
+    assert_eq!(response.finish_reason, FinishReason::Complete);
+    assert_eq!(response.message.content, "hello there");
+}
+
+#[test]
+fn echo_stream_fails_after_the_configured_deltas() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = EchoClient::new().with_config(EchoClientConfig {
+        chunk_chars: Some(3),
+        fail_after: Some(3),
+        ..EchoClientConfig::default()
+    });
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let err = client
+            .prompt_stream(vec![user("héllo, world")], String::new(), tx)
+            .await
+            .expect_err("stream fails partway");
+        let WireError::Io(err) = err else {
+            panic!("expected the injected failure, got {}", err);
+        };
+        assert_eq!(
+            err.get_ref()
+                .and_then(|inner| inner.downcast_ref::<InjectedStreamError>()),
+            Some(&InjectedStreamError { deltas_sent: 3 })
+        );
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, vec!["hél", "lo,", " wo"]);
+
+        let whole = EchoClient::new().with_config(EchoClientConfig {
+            chunk_chars: Some(5),
+            fail_after: Some(3),
+            ..EchoClientConfig::default()
+        });
+        let (tx, _rx) = tokio::sync::mpsc::channel(16);
+        let reply = whole
+            .prompt_stream(vec![user("héllo, world")], String::new(), tx)
+            .await
+            .expect("fewer deltas than fail_after");
+        assert_eq!(reply.content, "héllo, world");
+        assert_eq!(reply.metadata.latency.unwrap().deltas, 3);
+    });
+}
+
+#[test]
+fn echo_stream_stops_when_the_receiver_is_dropped_mid_pause() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let client = EchoClient::new().with_config(EchoClientConfig {
+        delay: Duration::from_secs(3600),
+        ..EchoClientConfig::default()
+    });
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let consumer = tokio::spawn(async move {
+            let first = rx.recv().await;
+            drop(rx);
+            first
+        });
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.prompt_stream(vec![user("one two three")], String::new(), tx),
+        )
+        .await
+        .expect("stream ends without waiting out the pause");
+
+        assert!(result.is_err());
+        assert_eq!(consumer.await.unwrap().as_deref(), Some("one "));
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn echo_stream_pauses_on_the_client_clock() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for echo test");
+    let clock = TestClock::new();
+    let client = EchoClient::with_options(ClientOptions::default().with_clock(clock.clone()))
+        .with_config(EchoClientConfig {
+            delay: Duration::from_secs(1),
+            ..EchoClientConfig::default()
+        });
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let stream = tokio::spawn(async move {
+            client
+                .prompt_stream(vec![user("a b c")], String::new(), tx)
+                .await
+                .map(|reply| reply.content)
+                .map_err(|err| err.to_string())
+        });
+
+        assert_eq!(rx.recv().await.as_deref(), Some("a "));
+        for expected in ["b ", "c"] {
+            while clock.pending_sleeps() == 0 {
+                tokio::task::yield_now().await;
+            }
+            assert!(rx.try_recv().is_err());
+            clock.advance(Duration::from_secs(1));
+            assert_eq!(rx.recv().await.as_deref(), Some(expected));
+        }
+
+        assert_eq!(stream.await.unwrap().as_deref(), Ok("a b c"));
+    });
+}
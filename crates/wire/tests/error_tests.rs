@@ -0,0 +1,686 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use temp_env::{with_var, with_var_unset};
+use wire::api::{OpenAIModel, PromptCore};
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::new_client;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+#[test]
+fn a_missing_key_names_its_variable() {
+    with_var_unset("OPENAI_API_KEY", || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o-mini",
+            ClientOptions::from_base_url("http://127.0.0.1:9").expect("base url parses"),
+        )
+        .expect("known model");
+
+        let error = runtime
+            .block_on(client.prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+            ))
+            .expect_err("there is no key to send");
+        assert!(matches!(
+            &error,
+            WireError::AuthMissing { var } if var == "OPENAI_API_KEY"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "OPENAI_API_KEY environment variable not set"
+        );
+    });
+}
+
+#[test]
+fn unknown_models_keep_the_name_asked_for() {
+    match new_client("gpt-nonexistent") {
+        Err(WireError::UnknownModel { model, .. }) => assert_eq!(model, "gpt-nonexistent"),
+        Err(other) => panic!("expected an unknown model, got {}", other),
+        Ok(_) => panic!("gpt-nonexistent resolved to a client"),
+    }
+}
+
+#[test]
+fn unknown_model_names_are_errors_not_panics() {
+    let unknown = |result: Result<OpenAIClient, WireError>| matches!(result, Err(WireError::UnknownModel { model, .. }) if model == "gpt-4.1-mini");
+    assert!(unknown(OpenAIClient::try_new("gpt-4.1-mini")));
+    assert!(unknown(OpenAIClient::try_new("gpt-4.1-mini".to_string())));
+    assert!(unknown(OpenAIClient::try_with_options(
+        "gpt-4.1-mini",
+        ClientOptions::default()
+    )));
+    assert!(OpenAIModel::try_from("gpt-4.1-mini").is_err());
+
+    let model = OpenAIModel::try_from("gpt-4o-mini").expect("known model");
+    assert!(OpenAIClient::try_new(model).is_ok());
+}
+
+#[test]
+fn errors_still_box() {
+    let boxed: Box<dyn std::error::Error> = WireError::StreamClosed.into();
+    assert!(boxed.downcast_ref::<WireError>().is_some());
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn status_codes_and_missing_fields_are_typed() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping error integration test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    MockResponse::Json(
+                        MockJsonResponse::new(serde_json::json!({
+                            "error": { "message": "Incorrect API key provided" }
+                        }))
+                        .with_status(401),
+                    ),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": []
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+            let ask = || {
+                client.prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                )
+            };
+
+            match ask().await.expect_err("the key is rejected") {
+                WireError::Http {
+                    status,
+                    path,
+                    message,
+                    body,
+                    ..
+                } => {
+                    assert_eq!(status, 401);
+                    assert_eq!(path, "/v1/chat/completions");
+                    assert_eq!(message.as_deref(), Some("Incorrect API key provided"));
+                    assert!(body.contains("Incorrect API key provided"));
+                }
+                other => panic!("expected an HTTP error, got {}", other),
+            }
+
+            assert!(matches!(
+                ask().await.expect_err("the reply has no choices"),
+                WireError::MissingField {
+                    field: "choices[0].message.content"
+                }
+            ));
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn request_ids_reach_messages_and_errors() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping request id integration test");
+        return;
+    }
+
+    let failure = |status: u16, request_id: &str| {
+        MockResponse::Json(
+            MockJsonResponse::new(serde_json::json!({
+                "error": { "message": "The server had an error", "type": "server_error" }
+            }))
+            .with_status(status),
+        )
+        .with_header("x-request-id", request_id)
+    };
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "Hello." } }]
+                    })))
+                    .with_header("x-request-id", "req_prompt"),
+                    failure(500, "req_prompt_failed"),
+                    MockResponse::openai_text_stream(["Hel", "lo."])
+                        .with_header("x-request-id", "req_stream"),
+                    failure(503, "req_stream_failed"),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+            let ask = || {
+                client.prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                )
+            };
+            let stream = || async {
+                let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                client
+                    .prompt_stream(
+                        vec![message(MessageType::User, "Hi")],
+                        "Be brief.".to_string(),
+                        tx,
+                    )
+                    .await
+            };
+
+            let reply = ask().await.expect("the prompt succeeds");
+            assert_eq!(reply.metadata.request_id.as_deref(), Some("req_prompt"));
+
+            let error = ask().await.expect_err("the server fails");
+            assert_eq!(error.request_id(), Some("req_prompt_failed"));
+            assert_eq!(
+                error.to_string(),
+                "HTTP 500 from /v1/chat/completions (server_error): The server had an error; \
+                 request-id: req_prompt_failed"
+            );
+
+            let reply = stream().await.expect("the stream succeeds");
+            assert_eq!(reply.content, "Hello.");
+            assert_eq!(reply.metadata.request_id.as_deref(), Some("req_stream"));
+
+            let error = stream().await.expect_err("the server fails");
+            assert_eq!(error.request_id(), Some("req_stream_failed"));
+            assert!(
+                error
+                    .to_string()
+                    .ends_with("; request-id: req_stream_failed"),
+                "{}",
+                error
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn provider_error_bodies_are_read() {
+    let cases = [
+        (
+            r#"{"error":{"message":"Invalid model","type":"invalid_request_error","code":null}}"#,
+            Some("invalid_request_error"),
+            Some("Invalid model"),
+        ),
+        (
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            Some("overloaded_error"),
+            Some("Overloaded"),
+        ),
+        (
+            r#"{"error":{"code":429,"message":"Quota exceeded","status":"RESOURCE_EXHAUSTED"}}"#,
+            Some("RESOURCE_EXHAUSTED"),
+            Some("Quota exceeded"),
+        ),
+        (
+            r#"{"error":"model 'x' not found"}"#,
+            None,
+            Some("model 'x' not found"),
+        ),
+        (
+            r#"{"message":"invalid api token"}"#,
+            None,
+            Some("invalid api token"),
+        ),
+        ("<html>Bad Gateway</html>", None, None),
+    ];
+
+    for (body, expected_code, expected_message) in cases {
+        match WireError::http(502, "/v1/path", body.to_string()) {
+            WireError::Http { code, message, .. } => {
+                assert_eq!(code.as_deref(), expected_code, "{}", body);
+                assert_eq!(message.as_deref(), expected_message, "{}", body);
+            }
+            other => panic!("expected an HTTP error, got {}", other),
+        }
+    }
+
+    assert_eq!(
+        WireError::http(502, "/v1/path", "<html>Bad Gateway</html>".to_string()).to_string(),
+        "HTTP 502 from /v1/path: <html>Bad Gateway</html>"
+    );
+    assert_eq!(
+        WireError::http(504, "/v1/path", String::new()).to_string(),
+        "HTTP 504 from /v1/path"
+    );
+}
+
+#[test]
+fn errors_say_whether_to_retry() {
+    use std::io::ErrorKind;
+
+    let io = |kind: ErrorKind| WireError::Io(std::io::Error::from(kind));
+    let gemini = |status: &str| WireError::Provider {
+        code: Some(status.to_string()),
+        message: "from the stream".to_string(),
+        request_id: None,
+    };
+    let cases = [
+        (WireError::http(429, "/v1/path", String::new()), true),
+        (WireError::http(500, "/v1/path", String::new()), true),
+        (WireError::http(529, "/v1/path", String::new()), true),
+        (io(ErrorKind::ConnectionReset), true),
+        (io(ErrorKind::ConnectionRefused), true),
+        (gemini("UNAVAILABLE"), true),
+        (gemini("RESOURCE_EXHAUSTED"), true),
+        (WireError::http(400, "/v1/path", String::new()), false),
+        (WireError::http(401, "/v1/path", String::new()), false),
+        (WireError::http(404, "/v1/path", String::new()), false),
+        (gemini("INVALID_ARGUMENT"), false),
+        (io(ErrorKind::InvalidData), false),
+        (
+            WireError::MalformedToolCall {
+                reason: "arguments are not JSON".to_string(),
+            },
+            false,
+        ),
+        (
+            WireError::ToolExecution {
+                name: "add".to_string(),
+                reason: "missing field `b`".to_string(),
+            },
+            false,
+        ),
+        (
+            WireError::AuthMissing {
+                var: "OPENAI_API_KEY".to_string(),
+            },
+            false,
+        ),
+    ];
+
+    for (error, retryable) in cases {
+        assert_eq!(error.is_retryable(), retryable, "{}", error);
+    }
+}
+
+#[cfg(all(feature = "anthropic", feature = "gemini", feature = "mock"))]
+mod statuses {
+    use super::*;
+    use wire::anthropic::AnthropicClient;
+    use wire::gemini::GeminiClient;
+
+    const STATUSES: [u16; 4] = [400, 401, 429, 529];
+    const GEMINI_MODEL: &str = "/v1beta/models/gemini-2.0-flash";
+
+    /// Each provider's error body for `status`, and the code and message
+    /// the error should carry.
+    fn error_body(provider: &str, status: u16) -> (serde_json::Value, &'static str, String) {
+        let message = format!("{} says {}", provider, status);
+        match provider {
+            "anthropic" => (
+                serde_json::json!({
+                    "type": "error",
+                    "error": { "type": "overloaded_error", "message": message }
+                }),
+                "overloaded_error",
+                message,
+            ),
+            "gemini" => (
+                serde_json::json!({
+                    "error": { "code": status, "message": message, "status": "UNAVAILABLE" }
+                }),
+                "UNAVAILABLE",
+                message,
+            ),
+            _ => (
+                serde_json::json!({
+                    "error": { "message": message, "type": "rate_limit_error", "code": null }
+                }),
+                "rate_limit_error",
+                message,
+            ),
+        }
+    }
+
+    /// Two of each status, for a prompt and then a stream.
+    fn failures(provider: &str, times: usize) -> Vec<MockResponse> {
+        STATUSES
+            .iter()
+            .flat_map(|status| {
+                let (body, _, _) = error_body(provider, *status);
+                std::iter::repeat_n(
+                    MockResponse::Json(MockJsonResponse::new(body).with_status(*status)),
+                    times,
+                )
+            })
+            .collect()
+    }
+
+    fn check(result: Result<wire::Message, WireError>, provider: &str, status: u16, path: &str) {
+        let (_, code, message) = error_body(provider, status);
+        match result.expect_err("the provider refused") {
+            WireError::Http {
+                status: got,
+                path: got_path,
+                code: got_code,
+                message: got_message,
+                ..
+            } => {
+                assert_eq!(got, status, "{}", provider);
+                assert_eq!(got_path, path, "{}", provider);
+                assert_eq!(got_code.as_deref(), Some(code), "{}", provider);
+                assert_eq!(got_message, Some(message), "{}", provider);
+            }
+            other => panic!(
+                "{} {}: expected an HTTP error, got {}",
+                provider, status, other
+            ),
+        }
+    }
+
+    async fn ask(client: &dyn PromptCore, stream: bool) -> Result<wire::Message, WireError> {
+        let history = vec![message(MessageType::User, "Hi")];
+        if stream {
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            client
+                .prompt_stream(history, "Be brief.".to_string(), tx)
+                .await
+        } else {
+            client.prompt("Be brief.".to_string(), history).await
+        }
+    }
+
+    #[test]
+    fn error_statuses_fail_prompts_and_streams() {
+        if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+            eprintln!("skipping error status integration test");
+            return;
+        }
+
+        temp_env::with_vars(
+            [
+                ("OPENAI_API_KEY", Some("mock-openai-key")),
+                ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+                ("GEMINI_API_KEY", Some("mock-gemini-key")),
+            ],
+            || {
+                let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+                runtime.block_on(async {
+                    let generate = format!("{}:generateContent", GEMINI_MODEL);
+                    let stream = format!("{}:streamGenerateContent", GEMINI_MODEL);
+                    let server = MockLLMServer::start(vec![
+                        MockRoute::new("/v1/chat/completions", failures("openai", 2)),
+                        MockRoute::new("/v1/messages", failures("anthropic", 2)),
+                        MockRoute::new(
+                            format!("{}?key=mock-gemini-key", generate),
+                            failures("gemini", 1),
+                        ),
+                        MockRoute::new(
+                            format!("{}?key=mock-gemini-key", stream),
+                            failures("gemini", 1),
+                        ),
+                    ])
+                    .await
+                    .expect("mock server starts");
+                    let options = || {
+                        ClientOptions::for_mock_server(&server)
+                            .expect("client options for mock server")
+                    };
+
+                    let openai = OpenAIClient::try_with_options("gpt-4o-mini", options())
+                        .expect("known model");
+                    let anthropic =
+                        AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options())
+                            .expect("known model");
+                    let gemini = GeminiClient::try_with_options("gemini-2.0-flash", options())
+                        .expect("known model");
+
+                    for status in STATUSES {
+                        for streamed in [false, true] {
+                            let path = "/v1/chat/completions";
+                            check(ask(&openai, streamed).await, "openai", status, path);
+                            let path = "/v1/messages";
+                            check(ask(&anthropic, streamed).await, "anthropic", status, path);
+                            let path = if streamed { &stream } else { &generate };
+                            check(ask(&gemini, streamed).await, "gemini", status, path);
+                        }
+                    }
+
+                    server.shutdown().await;
+                });
+            },
+        );
+    }
+
+    #[test]
+    fn statuses_say_whether_to_retry() {
+        if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+            eprintln!("skipping retry classification integration test");
+            return;
+        }
+
+        let cases = [
+            (429, true),
+            (500, true),
+            (529, true),
+            (400, false),
+            (401, false),
+            (404, false),
+        ];
+        let responses = |provider: &str| -> Vec<MockResponse> {
+            cases
+                .iter()
+                .map(|(status, _)| {
+                    let (body, _, _) = error_body(provider, *status);
+                    MockResponse::Json(MockJsonResponse::new(body).with_status(*status))
+                })
+                .collect()
+        };
+
+        temp_env::with_vars(
+            [
+                ("OPENAI_API_KEY", Some("mock-openai-key")),
+                ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+                ("GEMINI_API_KEY", Some("mock-gemini-key")),
+            ],
+            || {
+                let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+                runtime.block_on(async {
+                    let server = MockLLMServer::start(vec![
+                        MockRoute::new("/v1/chat/completions", responses("openai")),
+                        MockRoute::new("/v1/messages", responses("anthropic")),
+                        MockRoute::new(
+                            format!("{}:generateContent?key=mock-gemini-key", GEMINI_MODEL),
+                            responses("gemini"),
+                        ),
+                    ])
+                    .await
+                    .expect("mock server starts");
+                    let options = || {
+                        ClientOptions::for_mock_server(&server)
+                            .expect("client options for mock server")
+                    };
+
+                    let clients: [(&str, Box<dyn PromptCore>); 3] = [
+                        (
+                            "openai",
+                            Box::new(
+                                OpenAIClient::try_with_options("gpt-4o-mini", options())
+                                    .expect("known model"),
+                            ),
+                        ),
+                        (
+                            "anthropic",
+                            Box::new(
+                                AnthropicClient::try_with_options(
+                                    "claude-3-5-sonnet-20241022",
+                                    options(),
+                                )
+                                .expect("known model"),
+                            ),
+                        ),
+                        (
+                            "gemini",
+                            Box::new(
+                                GeminiClient::try_with_options("gemini-2.0-flash", options())
+                                    .expect("known model"),
+                            ),
+                        ),
+                    ];
+
+                    for (status, retryable) in cases {
+                        for (provider, client) in &clients {
+                            let error = ask(client.as_ref(), false)
+                                .await
+                                .expect_err("the provider refused");
+                            assert!(
+                                matches!(error, WireError::Http { status: got, .. } if got == status),
+                                "{}: {}",
+                                provider,
+                                error
+                            );
+                            assert_eq!(error.is_retryable(), retryable, "{}: {}", provider, error);
+                        }
+                    }
+
+                    server.shutdown().await;
+                });
+            },
+        );
+    }
+}
+
+/// A port nothing listens on.
+fn closed_port() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("a free port");
+    listener.local_addr().expect("bound address").port()
+}
+
+/// One of each streaming client the enabled features allow, sending to
+/// `base_url`.
+fn streaming_clients(base_url: &str) -> Vec<(&'static str, Box<dyn PromptCore>)> {
+    let options = || ClientOptions::from_base_url(base_url).expect("base url parses");
+    #[allow(unused_mut)]
+    let mut clients: Vec<(&'static str, Box<dyn PromptCore>)> = vec![(
+        "openai",
+        Box::new(
+            OpenAIClient::try_with_options("gpt-4o-mini", options().with_api_key("unused-key"))
+                .expect("known model"),
+        ),
+    )];
+    #[cfg(feature = "anthropic")]
+    clients.push((
+        "anthropic",
+        Box::new(
+            wire::anthropic::AnthropicClient::try_with_options(
+                "claude-3-5-sonnet-20241022",
+                options(),
+            )
+            .expect("known model"),
+        ),
+    ));
+    #[cfg(feature = "gemini")]
+    clients.push((
+        "gemini",
+        Box::new(
+            wire::gemini::GeminiClient::try_with_options("gemini-2.0-flash", options())
+                .expect("known model"),
+        ),
+    ));
+    clients
+}
+
+#[test]
+fn streams_to_a_closed_port_fail_cleanly() {
+    let port = closed_port();
+
+    temp_env::with_vars(
+        [
+            ("ANTHROPIC_API_KEY", Some("unused-key")),
+            ("GEMINI_API_KEY", Some("unused-key")),
+        ],
+        || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+            for scheme in ["http", "https"] {
+                let base_url = format!("{}://127.0.0.1:{}", scheme, port);
+                for (provider, client) in streaming_clients(&base_url) {
+                    let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                    let error = runtime
+                        .block_on(client.prompt_stream(
+                            vec![message(MessageType::User, "Hi")],
+                            "Be brief.".to_string(),
+                            tx,
+                        ))
+                        .expect_err("nothing is listening");
+                    match error {
+                        WireError::Io(err) => assert_eq!(
+                            err.kind(),
+                            std::io::ErrorKind::ConnectionRefused,
+                            "{} over {}",
+                            provider,
+                            scheme
+                        ),
+                        other => panic!(
+                            "{} over {}: expected a refused connection, got {}",
+                            provider, scheme, other
+                        ),
+                    }
+                }
+            }
+        },
+    );
+}
+
+#[test]
+fn failed_tls_handshakes_are_errors() {
+    // Answers in plain text, which no TLS client accepts
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("a free port");
+    let port = listener.local_addr().expect("bound address").port();
+    let server = std::thread::spawn(move || {
+        use std::io::Write;
+        let (mut stream, _) = listener.accept().expect("the client connects");
+        let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n");
+    });
+
+    let options = ClientOptions::from_base_url(format!("https://127.0.0.1:{}", port))
+        .expect("base url parses")
+        .with_api_key("unused-key");
+    let client = OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for error test");
+    let (tx, _rx) = tokio::sync::mpsc::channel(8);
+    let error = runtime
+        .block_on(client.prompt_stream(
+            vec![message(MessageType::User, "Hi")],
+            "Be brief.".to_string(),
+            tx,
+        ))
+        .expect_err("the handshake fails");
+    assert!(matches!(error, WireError::Io(_)), "{}", error);
+
+    server.join().expect("server thread finishes");
+}
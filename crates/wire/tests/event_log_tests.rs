@@ -0,0 +1,225 @@
+#![cfg(all(feature = "openai", feature = "gemini", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, run_mock_test, sample_tool};
+use std::path::PathBuf;
+use std::sync::Arc;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::ClientOptions;
+use wire::event_log::{read_event_log, FileEventSink, WireEvent};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{Message, MessageType};
+
+const GEMINI_STREAM_PATH: &str = "/v1beta/models/gemini-2.0-flash:streamGenerateContent";
+
+fn log_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "wire-event-log-{}-{}.jsonl",
+        name,
+        std::process::id()
+    ))
+}
+
+fn kind(event: &WireEvent) -> &'static str {
+    match event {
+        WireEvent::Request { .. } => "request",
+        WireEvent::Response { .. } => "response",
+        WireEvent::StreamData { .. } => "stream_data",
+        WireEvent::ToolCall { .. } => "tool_call",
+        WireEvent::ToolResult { .. } => "tool_result",
+        WireEvent::Message { .. } => "message",
+        _ => "other",
+    }
+}
+
+fn as_json(history: &[Message]) -> serde_json::Value {
+    serde_json::to_value(history).expect("history serializes")
+}
+
+fn tool_call_response() -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{
+            "message": {
+                "content": null,
+                "tool_calls": [{
+                    "id": "call-1",
+                    "type": "function",
+                    "function": {
+                        "name": "echo",
+                        "arguments": "{\"value\":\"ping\"}"
+                    }
+                }]
+            }
+        }]
+    })))
+}
+
+async fn run_tool_loop(options: ClientOptions) -> Vec<Message> {
+    OpenAIClient::try_with_options("gpt-4o-mini", options)
+        .expect("known model")
+        .prompt_with_tools(
+            "Follow instructions.",
+            vec![message(MessageType::User, "Call the tool")],
+            vec![sample_tool("echo")],
+        )
+        .await
+        .expect("tool loop completes")
+}
+
+#[test]
+fn tool_loop_replays_from_its_event_log() {
+    run_mock_test("event log tool loop test", async {
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            "/v1/chat/completions",
+            vec![
+                tool_call_response(),
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "The tool said ping." } }]
+                }))),
+            ],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let path = log_path("tool-loop");
+        let sink = FileEventSink::create(&path).expect("log file is writable");
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_event_log(Arc::new(sink));
+        let original = run_tool_loop(options).await;
+        server.shutdown().await;
+
+        let events = read_event_log(&path).expect("log reads back");
+        assert_eq!(
+            events.iter().map(kind).collect::<Vec<_>>(),
+            vec![
+                "request",
+                "response",
+                "message",
+                "tool_call",
+                "tool_result",
+                "request",
+                "response",
+                "message",
+            ]
+        );
+        match &events[4] {
+            WireEvent::ToolResult { id, name, output } => {
+                assert_eq!((id.as_str(), name.as_str()), ("call-1", "echo"));
+                assert_eq!(output, "{\"value\":\"ping\"}");
+            }
+            other => panic!("expected a tool result, got {:?}", other),
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("mock-openai-key"));
+
+        let replay = MockLLMServer::from_event_log(&path)
+            .await
+            .expect("replay server starts");
+        let options = ClientOptions::for_mock_server(&replay).expect("client options");
+        let replayed = run_tool_loop(options).await;
+        assert_eq!(as_json(&replayed), as_json(&original));
+
+        // The replayed client sent what was logged
+        let logged_bodies: Vec<&serde_json::Value> = events
+            .iter()
+            .filter_map(|event| match event {
+                WireEvent::Request { body, .. } => Some(body),
+                _ => None,
+            })
+            .collect();
+        let sent = replay.requests_for("/v1/chat/completions").await;
+        assert_eq!(sent.len(), 2);
+        for (request, logged) in sent.iter().zip(logged_bodies) {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(&body, logged);
+        }
+
+        replay.shutdown().await;
+        let _ = std::fs::remove_file(&path);
+    });
+}
+
+#[test]
+fn streams_replay_from_their_event_log() {
+    run_mock_test("event log stream test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(["Hel", "lo"]),
+            ),
+            MockRoute::single(
+                format!("{}?key=mock-gemini-key", GEMINI_STREAM_PATH),
+                MockResponse::gemini_text_stream(["Bon", "jour"]),
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let path = log_path("streams");
+        let prompt = |options: ClientOptions| async move {
+            let clients: Vec<Box<dyn PromptCore>> = vec![
+                Box::new(
+                    OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                        .expect("known model"),
+                ),
+                Box::new(
+                    GeminiClient::try_with_options("gemini-2.0-flash", options)
+                        .expect("known model"),
+                ),
+            ];
+
+            let mut replies = Vec::new();
+            for client in clients {
+                let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                let reply = client
+                    .prompt_stream(
+                        vec![message(MessageType::User, "Hello")],
+                        "Be brief.".to_string(),
+                        tx,
+                    )
+                    .await
+                    .expect("stream completes");
+                replies.push(reply.content);
+            }
+            replies
+        };
+
+        let sink = FileEventSink::create(&path).expect("log file is writable");
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_event_log(Arc::new(sink));
+        let original = prompt(options).await;
+        assert_eq!(original, vec!["Hello", "Bonjour"]);
+        server.shutdown().await;
+
+        let events = read_event_log(&path).expect("log reads back");
+        let paths: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                WireEvent::Request { path, stream, .. } => {
+                    assert!(*stream);
+                    Some(path.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths, vec!["/v1/chat/completions", GEMINI_STREAM_PATH]);
+        assert!(events.iter().any(
+            |event| matches!(event, WireEvent::StreamData { data } if data == "data: [DONE]")
+        ));
+
+        let replay = MockLLMServer::from_event_log(&path)
+            .await
+            .expect("replay server starts");
+        let options = ClientOptions::for_mock_server(&replay).expect("client options");
+        assert_eq!(prompt(options).await, original);
+
+        replay.shutdown().await;
+        let _ = std::fs::remove_file(&path);
+    });
+}
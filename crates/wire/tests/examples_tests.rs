@@ -0,0 +1,45 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+use std::process::Command;
+
+const EXAMPLES: [&str; 3] = ["stream_chat", "tool_agent", "conversation"];
+
+fn cargo(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        // No key, so the examples pick the mock server on their own
+        .env_remove("OPENAI_API_KEY")
+        .output()
+        .expect("cargo runs")
+}
+
+#[test]
+fn examples_build_and_run_offline() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping examples test");
+        return;
+    }
+
+    let build = cargo(&["build", "--quiet", "--examples"]);
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+
+    for example in EXAMPLES {
+        let run = cargo(&["run", "--quiet", "--example", example]);
+        let stdout = String::from_utf8_lossy(&run.stdout);
+        let stderr = String::from_utf8_lossy(&run.stderr);
+
+        assert!(run.status.success(), "{}: {}", example, stderr);
+        assert!(stderr.contains("offline"), "{}: {}", example, stderr);
+        assert!(
+            stdout.contains("It is sunny in Paris."),
+            "{}: {}",
+            example,
+            stdout
+        );
+    }
+}
@@ -0,0 +1,232 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{history, run_mock_test};
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::{ClientOptions, PromptOptions};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::warning::WireWarning;
+
+const OPENAI_PATH: &str = "/v1/chat/completions";
+const ANTHROPIC_PATH: &str = "/v1/messages";
+const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+const GEMINI_STREAM_PATH: &str =
+    "/v1beta/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key";
+
+fn extra(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    value.as_object().expect("extra body is an object").clone()
+}
+
+async fn bodies(server: &MockLLMServer, path: &str) -> Vec<serde_json::Value> {
+    server
+        .requests_for(path)
+        .await
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).expect("body is json"))
+        .collect()
+}
+
+#[test]
+fn extra_body_is_merged_into_each_request() {
+    run_mock_test("extra body test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                OPENAI_PATH,
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "Seven." } }]
+                }))),
+            ),
+            MockRoute::single(
+                ANTHROPIC_PATH,
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "content": [{ "type": "text", "text": "Seven." }]
+                }))),
+            ),
+            MockRoute::single(
+                GEMINI_PATH,
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "candidates": [{ "content": { "parts": [{ "text": "Seven." }] } }]
+                }))),
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_max_tokens(64);
+
+        let cases: Vec<(Box<dyn PromptCore>, serde_json::Value)> = vec![
+            (
+                Box::new(
+                    OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                        .expect("known model"),
+                ),
+                serde_json::json!({
+                    "prediction": { "type": "content", "content": "Seven." },
+                    "stream": true,
+                }),
+            ),
+            (
+                Box::new(
+                    AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                        .expect("known model"),
+                ),
+                serde_json::json!({
+                    "metadata": { "user_id": "user-1" },
+                    "max_tokens": 1,
+                }),
+            ),
+            (
+                Box::new(
+                    GeminiClient::try_with_options("gemini-2.0-flash", options)
+                        .expect("known model"),
+                ),
+                serde_json::json!({
+                    "generationConfig": { "seed": 7, "maxOutputTokens": 1 },
+                }),
+            ),
+        ];
+
+        for (client, extra_body) in &cases {
+            let with_extras = PromptOptions::new().with_extra_body(extra(extra_body.clone()));
+            let reply = client
+                .prompt_with_options("Be brief.".to_string(), history(), &with_extras)
+                .await
+                .expect("prompt with extras succeeds");
+            // Each sets one field wire sets too
+            assert!(matches!(
+                reply.metadata.warnings.as_slice(),
+                [WireWarning::OptionIgnored { option, .. }] if option.starts_with("extra body field")
+            ));
+            client
+                .prompt("Be brief.".to_string(), history())
+                .await
+                .expect("prompt without extras succeeds");
+        }
+
+        let openai = bodies(&server, OPENAI_PATH).await;
+        assert_eq!(
+            openai[0]["prediction"],
+            serde_json::json!({ "type": "content", "content": "Seven." })
+        );
+        // Fields wire sets itself keep wire's value
+        assert_eq!(openai[0]["stream"], false);
+
+        let anthropic = bodies(&server, ANTHROPIC_PATH).await;
+        assert_eq!(anthropic[0]["metadata"]["user_id"], "user-1");
+        assert_eq!(anthropic[0]["max_tokens"], 64);
+        assert!(anthropic[0].get("prediction").is_none());
+
+        let gemini = bodies(&server, GEMINI_PATH).await;
+        assert_eq!(
+            gemini[0]["generationConfig"],
+            serde_json::json!({ "maxOutputTokens": 64, "seed": 7 })
+        );
+
+        // Extras belong to the prompt they were given to
+        assert!(openai[1].get("prediction").is_none());
+        assert!(anthropic[1].get("metadata").is_none());
+        assert_eq!(
+            gemini[1]["generationConfig"],
+            serde_json::json!({ "maxOutputTokens": 64 })
+        );
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn extra_headers_are_sent_unless_wire_sets_them() {
+    run_mock_test("extra headers test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                OPENAI_PATH,
+                MockResponse::openai_text_stream(["Sev", "en."]),
+            ),
+            MockRoute::single(
+                ANTHROPIC_PATH,
+                MockResponse::anthropic_text_stream(["Sev", "en."]),
+            ),
+            MockRoute::single(
+                GEMINI_STREAM_PATH,
+                MockResponse::gemini_text_stream(["Sev", "en."]),
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+        let options = ClientOptions::for_mock_server(&server).expect("client options");
+        let clients: Vec<Box<dyn PromptCore>> = vec![
+            Box::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model"),
+            ),
+        ];
+
+        let prompt_options = PromptOptions::new()
+            .with_extra_headers([
+                ("anthropic-beta", "prompt-caching-2024-07-31"),
+                ("Authorization", "Bearer stolen"),
+                ("x-api-key", "stolen"),
+                ("x-broken", "line\r\nInjected: yes"),
+            ])
+            .with_extra_body(extra(serde_json::json!({ "user": "user-1" })));
+        for client in &clients {
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let reply = client
+                .prompt_stream_with_options(history(), "Be brief.".to_string(), tx, &prompt_options)
+                .await
+                .expect("stream completes");
+            assert_eq!(reply.content, "Seven.");
+        }
+
+        for path in [OPENAI_PATH, ANTHROPIC_PATH, GEMINI_STREAM_PATH] {
+            let recorded = server.requests_for(path).await;
+            let headers = &recorded[0].headers;
+            assert_eq!(
+                headers.get("anthropic-beta").map(String::as_str),
+                Some("prompt-caching-2024-07-31"),
+                "{}",
+                path
+            );
+            assert!(!headers.contains_key("x-broken"), "{}", path);
+            assert!(!headers.contains_key("injected"), "{}", path);
+            assert!(
+                !headers.values().any(|value| value.contains("stolen")),
+                "{}",
+                path
+            );
+
+            let body: serde_json::Value = serde_json::from_slice(&recorded[0].body).unwrap();
+            assert_eq!(body["user"], "user-1", "{}", path);
+        }
+
+        let openai = server.requests_for(OPENAI_PATH).await;
+        assert_eq!(
+            openai[0].headers.get("authorization").map(String::as_str),
+            Some("Bearer mock-openai-key")
+        );
+        let anthropic = server.requests_for(ANTHROPIC_PATH).await;
+        assert_eq!(
+            anthropic[0].headers.get("x-api-key").map(String::as_str),
+            Some("mock-anthropic-key")
+        );
+
+        server.shutdown().await;
+    });
+}
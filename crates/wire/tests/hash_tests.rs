@@ -0,0 +1,163 @@
+mod common;
+
+use common::{message, sample_tool, tool_call_history};
+use std::collections::HashSet;
+use temp_env::with_var;
+use wire::api::Provider;
+use wire::config::PromptOptions;
+use wire::hash::{request_fingerprint, to_hex};
+use wire::types::{Message, MessageType, ToolSpec};
+
+fn tools() -> Vec<ToolSpec> {
+    vec![sample_tool("lookup_weather").spec()]
+}
+
+fn fingerprint(history: &[Message]) -> [u8; 32] {
+    let tools = tools();
+    request_fingerprint(
+        Provider::OpenAI,
+        "gpt-4o-mini",
+        "Be helpful.",
+        history,
+        Some(&tools),
+        &PromptOptions::default(),
+    )
+}
+
+#[test]
+fn fingerprint_is_pinned() {
+    // Changing this value means changing the canonical form; bump
+    // FINGERPRINT_VERSION in src/hash.rs
+    assert_eq!(
+        to_hex(&fingerprint(&tool_call_history())),
+        "b88789aebf507907c1140981626f2d01dcdb8951ae42789f2c8ea5f6e87903c5"
+    );
+}
+
+#[test]
+fn fingerprint_ignores_credentials_and_bookkeeping() {
+    let base = fingerprint(&tool_call_history());
+
+    let under_other_key = with_var("OPENAI_API_KEY", Some("a-different-key"), || {
+        fingerprint(&tool_call_history())
+    });
+    assert_eq!(under_other_key, base);
+
+    let mut history = tool_call_history();
+    for message in history.iter_mut() {
+        message.input_tokens = 120;
+        message.output_tokens = 40;
+        message.system_prompt = "stale prompt".to_string();
+        message.metadata = Default::default();
+    }
+    assert_eq!(fingerprint(&history), base);
+
+    // Argument key order is not significant
+    history[1].tool_calls.as_mut().unwrap()[0]
+        .function
+        .arguments = r#"{ "units": "metric",  "zip": "10001" }"#.to_string();
+    assert_eq!(fingerprint(&history), base);
+}
+
+#[test]
+fn fingerprint_changes_with_the_request() {
+    let base = fingerprint(&tool_call_history());
+    let tools = tools();
+
+    let mut edited = tool_call_history();
+    edited[2].content = "sleet".to_string();
+    assert_ne!(fingerprint(&edited), base);
+
+    let mut retyped = tool_call_history();
+    retyped[0].message_type = MessageType::Assistant;
+    assert_ne!(fingerprint(&retyped), base);
+
+    let mut longer = tool_call_history();
+    longer.push(message(MessageType::User, "Thanks"));
+    assert_ne!(fingerprint(&longer), base);
+
+    let variants = [
+        request_fingerprint(
+            Provider::Anthropic,
+            "gpt-4o-mini",
+            "Be helpful.",
+            &tool_call_history(),
+            Some(&tools),
+            &PromptOptions::default(),
+        ),
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o",
+            "Be helpful.",
+            &tool_call_history(),
+            Some(&tools),
+            &PromptOptions::default(),
+        ),
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o-mini",
+            "Be brief.",
+            &tool_call_history(),
+            Some(&tools),
+            &PromptOptions::default(),
+        ),
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o-mini",
+            "Be helpful.",
+            &tool_call_history(),
+            None,
+            &PromptOptions::default(),
+        ),
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o-mini",
+            "Be helpful.",
+            &tool_call_history(),
+            Some(&tools),
+            &PromptOptions::new().with_max_response_bytes(64),
+        ),
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o-mini",
+            "Be helpful.",
+            &tool_call_history(),
+            Some(&tools),
+            &PromptOptions::new().with_system_fragments(["Be helpful."]),
+        ),
+    ];
+    for variant in variants {
+        assert_ne!(variant, base);
+    }
+}
+
+#[test]
+fn fingerprints_do_not_collide_on_similar_requests() {
+    let mut seen = HashSet::new();
+
+    for i in 0..500 {
+        let history = vec![message(MessageType::User, &format!("question {}", i))];
+        assert!(seen.insert(fingerprint(&history)));
+    }
+
+    // Moving text across field boundaries must not produce the same input
+    let split = |system: &str, content: &str| {
+        request_fingerprint(
+            Provider::OpenAI,
+            "gpt-4o-mini",
+            system,
+            &[message(MessageType::User, content)],
+            None,
+            &PromptOptions::default(),
+        )
+    };
+    assert_ne!(split("ab", "c"), split("a", "bc"));
+    assert_ne!(split("", "ab"), split("ab", ""));
+
+    let one = vec![message(MessageType::User, "a b")];
+    let two = vec![
+        message(MessageType::User, "a"),
+        message(MessageType::User, "b"),
+    ];
+    assert_ne!(fingerprint(&one), fingerprint(&two));
+}
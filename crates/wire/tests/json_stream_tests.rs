@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use wire::json_stream::{JsonStreamError, JsonStreamEvent, JsonStreamValidator};
+
+fn feed(validator: &mut JsonStreamValidator, deltas: &[&str]) -> Vec<JsonStreamEvent> {
+    deltas
+        .iter()
+        .flat_map(|delta| validator.push(delta).expect("delta is valid"))
+        .collect()
+}
+
+#[test]
+fn strings_and_escapes_split_across_deltas() {
+    let deltas = [
+        "  {\"quote\": \"she said \\",
+        "\"hi\\",
+        "\" and left",
+        " {not a brace}\", \"path\": \"C:\\\\",
+        "\", \"list\": [1, [2",
+        "]]}",
+    ];
+    let mut validator = JsonStreamValidator::new();
+    let events = feed(&mut validator, &deltas);
+
+    let expected = serde_json::json!({
+        "quote": "she said \"hi\" and left {not a brace}",
+        "path": "C:\\",
+        "list": [1, [2]],
+    });
+    assert_eq!(
+        events.last(),
+        Some(&JsonStreamEvent::Complete(expected.clone()))
+    );
+    assert_eq!(validator.finish(), Ok(expected));
+
+    // One prefix per delta, each extending the last
+    let prefixes: Vec<&String> = events
+        .iter()
+        .filter_map(|event| match event {
+            JsonStreamEvent::Prefix(prefix) => Some(prefix),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(prefixes.len(), deltas.len());
+    assert!(prefixes[0].starts_with('{'));
+    for pair in prefixes.windows(2) {
+        assert!(pair[1].starts_with(pair[0].as_str()));
+    }
+}
+
+#[test]
+fn completion_callback_fires_once() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    let mut validator =
+        JsonStreamValidator::new().on_value_complete(move |value| sink.lock().unwrap().push(value));
+
+    feed(&mut validator, &["[{\"a\"", ": 1}", "]", "\n"]);
+    assert!(validator.is_complete());
+    assert_eq!(*seen.lock().unwrap(), vec![serde_json::json!([{ "a": 1 }])]);
+}
+
+#[test]
+fn preamble_fails_fast() {
+    let mut validator = JsonStreamValidator::new();
+
+    let err = validator
+        .push("Sure! Here is the JSON: {")
+        .expect_err("preamble is rejected");
+    assert_eq!(
+        err,
+        JsonStreamError::UnexpectedText {
+            offset: 0,
+            text: "Sure! Here is the JSON: {".to_string(),
+        }
+    );
+
+    // The failure sticks
+    assert_eq!(validator.push("}"), Err(err.clone()));
+    assert_eq!(validator.finish(), Err(err));
+}
+
+#[test]
+fn trailing_text_and_bad_brackets_fail() {
+    let mut validator = JsonStreamValidator::new();
+    feed(&mut validator, &["{\"a\": 1}", "  "]);
+    assert_eq!(
+        validator.push("Hope that helps!"),
+        Err(JsonStreamError::UnexpectedText {
+            offset: 10,
+            text: "Hope that helps!".to_string(),
+        })
+    );
+
+    let mut validator = JsonStreamValidator::new();
+    assert_eq!(
+        validator.push("{\"a\": [1}"),
+        Err(JsonStreamError::MismatchedBracket {
+            offset: 8,
+            found: '}',
+        })
+    );
+
+    let mut validator = JsonStreamValidator::new();
+    assert!(matches!(
+        validator.push("{\"a\": nope}"),
+        Err(JsonStreamError::Invalid(_))
+    ));
+
+    let mut validator = JsonStreamValidator::new();
+    feed(&mut validator, &["{\"a\": \"unterminated"]);
+    assert_eq!(validator.finish(), Err(JsonStreamError::Incomplete));
+}
+
+#[test]
+fn wrapped_sender_forwards_and_stops_on_invalid_text() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for json stream test");
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let (deltas, validated) = JsonStreamValidator::new().wrap_sender(tx);
+        for delta in ["{\"ok\":", " true}"] {
+            deltas.send(delta.to_string()).await.unwrap();
+        }
+        drop(deltas);
+
+        assert_eq!(
+            validated.await.unwrap(),
+            Ok(serde_json::json!({ "ok": true }))
+        );
+        assert_eq!(rx.recv().await.as_deref(), Some("{\"ok\":"));
+        assert_eq!(rx.recv().await.as_deref(), Some(" true}"));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let (deltas, validated) = JsonStreamValidator::new().wrap_sender(tx);
+        deltas.send("Here you go: ".to_string()).await.unwrap();
+
+        assert!(matches!(
+            validated.await.unwrap(),
+            Err(JsonStreamError::UnexpectedText { offset: 0, .. })
+        ));
+        // A client streaming into `deltas` would now get a send error
+        assert!(deltas.send("{}".to_string()).await.is_err());
+        assert!(rx.recv().await.is_none());
+    });
+}
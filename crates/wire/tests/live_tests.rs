@@ -0,0 +1,186 @@
+//! Golden tests against the real provider APIs.
+//!
+//! Every provider whose API key is set gets a plain prompt, a streaming
+//! prompt and a one-tool loop with a small `max_tokens`. Replies are only
+//! checked for invariants (non-empty content, usage reported, a finish
+//! reason), never for what they say. The traffic of each case is written,
+//! redacted, to `tests/fixtures/live/<provider>/<case>.jsonl` and replayed
+//! through `MockLLMServer::from_event_log` to check the mock suite can
+//! serve it.
+//!
+//! Run with `cargo test -p wire --features live -- --ignored`.
+
+#![cfg(feature = "live")]
+
+mod common;
+
+use common::live::{finish_reason, usage, write_fixture, Recorder, PROVIDERS};
+use common::message;
+use common::mock_server::MockLLMServer;
+use std::sync::Arc;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::event_log::WireEvent;
+use wire::new_client_with_options;
+use wire::types::{Message, MessageType, Tool, ToolWrapper};
+
+#[derive(Clone, Copy, Debug)]
+enum Case {
+    Prompt,
+    Stream,
+    ToolLoop,
+}
+
+impl Case {
+    fn name(self) -> &'static str {
+        match self {
+            Case::Prompt => "prompt",
+            Case::Stream => "stream",
+            Case::ToolLoop => "tool_loop",
+        }
+    }
+
+    /// Enough for a short answer; the tool loop also needs room for the
+    /// call's arguments.
+    fn max_tokens(self) -> usize {
+        match self {
+            Case::Prompt | Case::Stream => 32,
+            Case::ToolLoop => 256,
+        }
+    }
+
+    fn options(self) -> ClientOptions {
+        ClientOptions::default().with_max_tokens(self.max_tokens())
+    }
+
+    async fn run(self, client: &dyn PromptCore) -> Result<Message, WireError> {
+        let history = |content: &str| vec![message(MessageType::User, content)];
+
+        match self {
+            Case::Prompt => {
+                client
+                    .prompt(
+                        "Answer in one short sentence.".to_string(),
+                        history("What is the capital of France?"),
+                    )
+                    .await
+            }
+            Case::Stream => {
+                let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+                let drain = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+                let reply = client
+                    .prompt_stream(
+                        history("Count from one to five."),
+                        "Answer in one short sentence.".to_string(),
+                        tx,
+                    )
+                    .await;
+                let _ = drain.await;
+                reply
+            }
+            Case::ToolLoop => {
+                let tool = Tool {
+                    function_type: "function".to_string(),
+                    name: "lookup_number".to_string(),
+                    description: "Returns the number the user is asking about.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {},
+                    }),
+                    strict: false,
+                    function: Box::new(ToolWrapper(|_| serde_json::json!("1729"))),
+                };
+
+                let messages = client
+                    .tools()
+                    .ok_or_else(|| WireError::ToolsUnsupported {
+                        client: "the client".to_string(),
+                    })?
+                    .prompt_with_tools(
+                        "Call lookup_number once, then answer with the number it returns.",
+                        history("Which number am I thinking of?"),
+                        vec![tool],
+                    )
+                    .await?;
+
+                Ok(messages.last().expect("the loop replies").clone())
+            }
+        }
+    }
+}
+
+fn run_live(case: Case) {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for live test");
+
+    for provider in PROVIDERS {
+        let Some(key) = provider.key() else {
+            continue;
+        };
+        let label = format!("{} / {}", provider.name, case.name());
+
+        runtime.block_on(async {
+            let recorder = Arc::new(Recorder::default());
+            let options = case.options().with_event_log(recorder.clone());
+            let client = new_client_with_options(provider.model, options).expect("known model");
+            let reply = case
+                .run(client.as_ref())
+                .await
+                .unwrap_or_else(|err| panic!("{}: {}", label, err));
+            let events = recorder.events();
+
+            assert!(!reply.content.trim().is_empty(), "{}: empty reply", label);
+            assert!(
+                finish_reason(&events).is_some(),
+                "{}: no finish reason",
+                label
+            );
+            if !matches!(case, Case::Stream) || provider.reports_stream_usage {
+                assert!(usage(&events) > 0, "{}: no usage reported", label);
+            }
+            if matches!(case, Case::ToolLoop) {
+                assert!(
+                    events
+                        .iter()
+                        .any(|event| matches!(event, WireEvent::ToolCall { .. })),
+                    "{}: the tool never ran",
+                    label
+                );
+            }
+
+            let fixture = write_fixture(provider.name, case.name(), &events, &key);
+            let replay = MockLLMServer::from_event_log(&fixture)
+                .await
+                .expect("replay server starts");
+            let options = ClientOptions::for_mock_server(&replay)
+                .expect("client options for mock server")
+                .with_max_tokens(case.max_tokens());
+            let client = new_client_with_options(provider.model, options).expect("known model");
+            let replayed = case
+                .run(client.as_ref())
+                .await
+                .unwrap_or_else(|err| panic!("{} replay: {}", label, err));
+            assert_eq!(replayed.content, reply.content, "{}: replay differs", label);
+
+            replay.shutdown().await;
+        });
+    }
+}
+
+#[test]
+#[ignore = "calls the provider APIs; run with --features live -- --ignored"]
+fn live_prompt() {
+    run_live(Case::Prompt);
+}
+
+#[test]
+#[ignore = "calls the provider APIs; run with --features live -- --ignored"]
+fn live_stream() {
+    run_live(Case::Stream);
+}
+
+#[test]
+#[ignore = "calls the provider APIs; run with --features live -- --ignored"]
+fn live_tool_loop() {
+    run_live(Case::ToolLoop);
+}
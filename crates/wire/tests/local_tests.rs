@@ -0,0 +1,194 @@
+#![cfg(feature = "compatible")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, request_body_json};
+use temp_env::{with_var, with_vars_unset};
+use wire::api::{CompatibleModel, PromptCore, API};
+use wire::config::ClientOptions;
+use wire::new_client_with_options;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+const KEY_VARS: [&str; 2] = ["OPENAI_API_KEY", "OPENAI_COMPATIBLE_API_KEY"];
+
+fn local_api(label: &str, name: &str) -> API {
+    API::Compatible(CompatibleModel::new(name).with_label(label).unwrap())
+}
+
+fn request_for(client: &dyn PromptCore) -> reqwest::Request {
+    client
+        .build_request(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "Hi")],
+            None,
+            false,
+        )
+        .expect("request builds")
+        .build()
+        .expect("local request should build")
+}
+
+#[test]
+fn presets_reach_loopback_servers_without_a_key() {
+    with_vars_unset(KEY_VARS, || {
+        for (options, origin, label) in [
+            (ClientOptions::local(5001), "http://127.0.0.1:5001", "local"),
+            (
+                ClientOptions::lm_studio(),
+                "http://127.0.0.1:1234",
+                "lm-studio",
+            ),
+            (
+                ClientOptions::llama_cpp(),
+                "http://127.0.0.1:8080",
+                "llama-cpp",
+            ),
+            (ClientOptions::vllm(), "http://127.0.0.1:8000", "vllm"),
+        ] {
+            assert!(options.disable_proxy);
+
+            let client = new_client_with_options("qwen2.5-7b-instruct", options)
+                .expect("any model resolves locally");
+            let request = request_for(client.as_ref());
+
+            assert_eq!(
+                request.url().as_str(),
+                format!("{}/v1/chat/completions", origin)
+            );
+            assert!(request.headers().get("authorization").is_none());
+            assert_eq!(request_body_json(&request)["model"], "qwen2.5-7b-instruct");
+            assert_eq!(
+                client.new_message("Hi".to_string()).build().api,
+                local_api(label, "qwen2.5-7b-instruct")
+            );
+        }
+    });
+}
+
+#[test]
+fn local_names_are_not_resolved_to_providers() {
+    with_vars_unset(KEY_VARS, || {
+        for (model, sent) in [
+            ("gpt-4o", "gpt-4o"),
+            ("custom/llama3.1:8b", "llama3.1:8b"),
+            (
+                "meta-llama/Llama-3.1-8B-Instruct",
+                "meta-llama/Llama-3.1-8B-Instruct",
+            ),
+        ] {
+            let client = new_client_with_options(model, ClientOptions::lm_studio())
+                .expect("any model resolves locally");
+            assert_eq!(
+                client.new_message("Hi".to_string()).build().api,
+                local_api("lm-studio", sent)
+            );
+            assert_eq!(
+                request_body_json(&request_for(client.as_ref()))["model"],
+                sent
+            );
+        }
+
+        assert!(new_client_with_options("custom/", ClientOptions::lm_studio()).is_err());
+        assert!(new_client_with_options("qwen2.5-7b-instruct", ClientOptions::default()).is_err());
+    });
+}
+
+#[test]
+fn a_given_key_replaces_the_environment_key() {
+    with_vars_unset(KEY_VARS, || {
+        let options = ClientOptions::local(1234).with_api_key("local-secret");
+        let client = new_client_with_options("qwen2.5-7b-instruct", options)
+            .expect("any model resolves locally");
+        assert_eq!(
+            request_for(client.as_ref())
+                .headers()
+                .get("authorization")
+                .and_then(|value| value.to_str().ok()),
+            Some("Bearer local-secret")
+        );
+
+        let options = ClientOptions::from_base_url("http://localhost:8080")
+            .unwrap()
+            .with_api_key("literal");
+        let client = OpenAIClient::try_with_options("gpt-4o", options).expect("known model");
+        assert_eq!(client.get_auth_token().expect("a key"), "literal");
+    });
+
+    with_var("OPENAI_API_KEY", Some("sk-real"), || {
+        let options = ClientOptions::from_base_url("http://localhost:8080")
+            .unwrap()
+            .without_api_key();
+        let client = OpenAIClient::try_with_options("gpt-4o", options).expect("known model");
+        assert!(request_for(&client)
+            .headers()
+            .get("authorization")
+            .is_none());
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn local_client_talks_to_a_mock_server_with_no_environment() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping local integration test");
+        return;
+    }
+
+    with_vars_unset(KEY_VARS, || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for local test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "local reply" } }]
+                    }))),
+                    MockResponse::openai_text_stream(["Hello", " from", " here"]),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::local(server.address().port());
+            let client = new_client_with_options("my-local-gguf", options)
+                .expect("any model resolves locally");
+
+            let reply = client
+                .prompt(
+                    "Stay friendly.".to_string(),
+                    vec![message(MessageType::User, "Ping?")],
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "local reply");
+            assert_eq!(reply.api, local_api("local", "my-local-gguf"));
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(streamed.content, "Hello from here");
+
+            let recorded = server.requests_for("/v1/chat/completions").await;
+            assert_eq!(recorded.len(), 2);
+            for request in &recorded {
+                assert!(!request.headers.contains_key("authorization"));
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("request body parses as json");
+                assert_eq!(body["model"], "my-local-gguf");
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
@@ -1,17 +1,15 @@
+#![cfg(feature = "openai")]
+
 mod common;
 
 use common::sample_tool;
-use std::panic;
-use wire::api::Prompt;
+use wire::api::PromptCore;
 use wire::openai::OpenAIClient;
 use wire::types::MessageType;
 
 #[test]
 fn openai_builder_sets_defaults() {
-    let client = match build_client() {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client();
     let message = client.new_message("hello world".to_string()).build();
 
     assert_eq!(message.message_type, MessageType::User);
@@ -24,10 +22,7 @@ fn openai_builder_sets_defaults() {
 
 #[test]
 fn builder_allows_role_customization() {
-    let client = match build_client() {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client();
     let message = client
         .new_message("system msg".to_string())
         .as_system()
@@ -43,10 +38,7 @@ fn builder_allows_role_customization() {
 
 #[test]
 fn builder_with_tools_returns_bundle() {
-    let client = match build_client() {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client();
     let tool = sample_tool("demo");
 
     let bundle = client
@@ -60,6 +52,6 @@ fn builder_with_tools_returns_bundle() {
     assert_eq!(tools[0].name, "demo");
 }
 
-fn build_client() -> Option<OpenAIClient> {
-    panic::catch_unwind(|| OpenAIClient::new("gpt-4o-mini")).ok()
+fn build_client() -> OpenAIClient {
+    OpenAIClient::try_new("gpt-4o-mini").expect("known model")
 }
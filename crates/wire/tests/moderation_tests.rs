@@ -0,0 +1,210 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, run_mock_test};
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::{ClientOptions, PromptOptions};
+use wire::echo::EchoClient;
+use wire::error::WireError;
+use wire::moderation::{ModerationResult, Moderator, OpenAIModerator};
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+fn moderation_response(flagged: bool) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "id": "modr-1",
+        "model": "omni-moderation-latest",
+        "results": [{
+            "flagged": flagged,
+            "categories": {
+                "harassment": flagged,
+                "violence": flagged,
+                "self-harm": false
+            },
+            "category_scores": {
+                "harassment": 0.9,
+                "violence": 0.8,
+                "self-harm": 0.01
+            }
+        }]
+    })))
+}
+
+fn chat_response() -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{ "message": { "content": "Sure." } }]
+    })))
+}
+
+/// Start a server answering moderation with `flagged` and chat with a fixed
+/// reply, and a client that moderates through it.
+async fn moderated_client(flagged: bool) -> (MockLLMServer, OpenAIClient) {
+    let server = MockLLMServer::start(vec![
+        MockRoute::single("/v1/moderations", moderation_response(flagged)),
+        MockRoute::single("/v1/chat/completions", chat_response()),
+    ])
+    .await
+    .expect("mock server starts");
+
+    let options = ClientOptions::for_mock_server(&server).expect("client options for mock server");
+    let moderator = OpenAIModerator::with_options(options.clone());
+    let client = OpenAIClient::try_with_options("gpt-4o-mini", options.with_moderator(moderator))
+        .expect("known model");
+
+    (server, client)
+}
+
+#[test]
+fn flagged_input_is_not_sent() {
+    run_mock_test("moderation flagged input test", async {
+        let (server, client) = moderated_client(true).await;
+
+        let err = client
+            .prompt(
+                "Be helpful.".to_string(),
+                vec![
+                    message(MessageType::User, "Something awful"),
+                    message(MessageType::User, "and more of it"),
+                ],
+            )
+            .await
+            .expect_err("flagged input is rejected");
+
+        assert!(matches!(
+            &err,
+            WireError::ContentFlagged { categories }
+                if categories == &["harassment", "violence"]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "content flagged by moderation: harassment, violence"
+        );
+
+        let moderations = server.requests_for("/v1/moderations").await;
+        assert_eq!(moderations.len(), 1);
+        let body: serde_json::Value = serde_json::from_slice(&moderations[0].body).unwrap();
+        assert_eq!(body["model"], "omni-moderation-latest");
+        assert_eq!(body["input"], "Something awful\n\nand more of it");
+        assert!(server.requests_for("/v1/chat/completions").await.is_empty());
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn clean_input_reaches_the_model() {
+    run_mock_test("moderation clean input test", async {
+        let (server, client) = moderated_client(false).await;
+
+        let reply = client
+            .prompt(
+                "Be helpful.".to_string(),
+                vec![
+                    message(MessageType::User, "Earlier question"),
+                    message(MessageType::Assistant, "Earlier answer"),
+                    message(MessageType::User, "What is 2 + 2?"),
+                ],
+            )
+            .await
+            .expect("clean input is sent");
+
+        assert_eq!(reply.content, "Sure.");
+
+        // Only the new turn is checked
+        let moderations = server.requests_for("/v1/moderations").await;
+        assert_eq!(moderations.len(), 1);
+        let body: serde_json::Value = serde_json::from_slice(&moderations[0].body).unwrap();
+        assert_eq!(body["input"], "What is 2 + 2?");
+        assert_eq!(server.requests_for("/v1/chat/completions").await.len(), 1);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn bypass_skips_moderation() {
+    run_mock_test("moderation bypass test", async {
+        let (server, client) = moderated_client(true).await;
+
+        let reply = client
+            .prompt_with_options(
+                "Be helpful.".to_string(),
+                vec![message(MessageType::User, "Something awful")],
+                &PromptOptions::new().with_bypass_moderation(true),
+            )
+            .await
+            .expect("bypassed input is sent");
+
+        assert_eq!(reply.content, "Sure.");
+        assert!(server.requests_for("/v1/moderations").await.is_empty());
+        assert_eq!(server.requests_for("/v1/chat/completions").await.len(), 1);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn flagged_input_stops_the_tool_loop() {
+    run_mock_test("moderation tool loop test", async {
+        let (server, client) = moderated_client(true).await;
+
+        let err = client
+            .prompt_with_tools(
+                "Be helpful.",
+                vec![message(MessageType::User, "Something awful")],
+                vec![common::sample_tool("echo")],
+            )
+            .await
+            .expect_err("flagged input is rejected");
+
+        assert!(matches!(err, WireError::ContentFlagged { .. }));
+        assert!(server.requests_for("/v1/chat/completions").await.is_empty());
+
+        server.shutdown().await;
+    });
+}
+
+struct KeywordModerator(&'static str);
+
+#[async_trait::async_trait]
+impl Moderator for KeywordModerator {
+    async fn moderate(&self, input: &str) -> Result<ModerationResult, WireError> {
+        let flagged = input.contains(self.0);
+        Ok(ModerationResult {
+            flagged,
+            categories: if flagged {
+                vec![format!("keyword:{}", self.0)]
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
+
+#[test]
+fn custom_moderators_plug_into_any_client() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for moderation test");
+    let client =
+        EchoClient::with_options(ClientOptions::default().with_moderator(KeywordModerator("boom")));
+
+    let err = runtime
+        .block_on(client.prompt(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "boom")],
+        ))
+        .expect_err("keyword is flagged");
+    assert!(matches!(
+        err,
+        WireError::ContentFlagged { categories } if categories == ["keyword:boom"]
+    ));
+
+    let reply = runtime
+        .block_on(client.prompt(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "hello")],
+        ))
+        .expect("clean input passes");
+    assert_eq!(reply.content, "hello");
+}
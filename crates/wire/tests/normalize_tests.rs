@@ -0,0 +1,423 @@
+#![cfg(all(feature = "openai", feature = "anthropic"))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{function_call, message, sample_tool};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::{AnthropicModel, OpenAIModel, PromptCore, ToolCapable, API};
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::normalize::{normalize_history_for, normalize_history_with, HistoryStrictness};
+use wire::openai::OpenAIClient;
+use wire::types::{Message, MessageType};
+use wire::warning::WireWarning;
+
+const OPENAI_ID: &str = "call_Xk2.9:weather";
+const ANTHROPIC_ID: &str = "toolu_01A09q90qw90lq917835lq9ABCDEFGHIJKLMNOP";
+
+fn anthropic() -> API {
+    API::Anthropic(AnthropicModel::Claude35SonnetNew)
+}
+
+fn openai() -> API {
+    API::OpenAI(OpenAIModel::GPT4o)
+}
+
+fn keys<R>(f: impl FnOnce() -> R) -> R {
+    with_vars(
+        [
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+        ],
+        f,
+    )
+}
+
+/// A user question, one tool call and its output, all tagged `api`.
+fn tool_history(api: &API, id: &str) -> Vec<Message> {
+    let mut call = message(MessageType::FunctionCall, "");
+    call.tool_calls = Some(vec![function_call(
+        id,
+        "lookup_weather",
+        serde_json::json!({ "city": "Paris" }),
+    )]);
+    let mut output = message(MessageType::FunctionCallOutput, "sunny");
+    output.tool_call_id = Some(id.to_string());
+
+    let mut history = vec![
+        message(MessageType::User, "Weather in Paris?"),
+        call,
+        output,
+    ];
+    for message in history.iter_mut() {
+        message.api = api.clone();
+    }
+    history
+}
+
+fn call_id(message: &Message) -> &str {
+    &message.tool_calls.as_ref().expect("tool calls")[0].id
+}
+
+#[test]
+fn anthropic_target_gets_valid_ids_and_matching_outputs() {
+    let history = tool_history(&openai(), OPENAI_ID);
+    let (normalized, _) =
+        normalize_history_for(&anthropic(), &history).expect("history translates");
+
+    assert!(normalized.iter().all(|message| message.api == anthropic()));
+    assert_eq!(call_id(&normalized[1]), "call_Xk2_9_weather");
+    assert_eq!(
+        normalized[2].tool_call_id.as_deref(),
+        Some("call_Xk2_9_weather")
+    );
+    assert_eq!(normalized[2].name.as_deref(), Some("lookup_weather"));
+
+    // The caller's copy is left alone
+    assert_eq!(call_id(&history[1]), OPENAI_ID);
+    assert_eq!(history[1].api, openai());
+}
+
+#[test]
+fn repeated_and_overlong_ids_are_made_unique_for_openai() {
+    // Gemini and Ollama number their calls from one on every turn
+    let mut history = tool_history(&anthropic(), "call-1");
+    history.extend(tool_history(&anthropic(), "call-1"));
+    history.extend(tool_history(&anthropic(), ANTHROPIC_ID));
+
+    let (normalized, _) = normalize_history_for(&openai(), &history).expect("history translates");
+
+    assert_eq!(call_id(&normalized[1]), "call-1");
+    assert_eq!(normalized[2].tool_call_id.as_deref(), Some("call-1"));
+    assert_eq!(call_id(&normalized[4]), "call-1_2");
+    assert_eq!(normalized[5].tool_call_id.as_deref(), Some("call-1_2"));
+
+    let long = call_id(&normalized[7]);
+    assert_eq!(long.len(), 40);
+    assert!(ANTHROPIC_ID.starts_with(long));
+    assert_eq!(normalized[8].tool_call_id.as_deref(), Some(long));
+}
+
+#[test]
+fn strictness_decides_what_happens_to_untranslatable_messages() {
+    let mut orphan = message(MessageType::FunctionCallOutput, "sunny");
+    orphan.tool_call_id = Some("call-9".to_string());
+    let mut history = tool_history(&openai(), OPENAI_ID);
+    history.push(orphan);
+
+    let error = normalize_history_with(&anthropic(), &history, HistoryStrictness::Strict)
+        .expect_err("orphan output is refused");
+    assert!(matches!(
+        error,
+        WireError::UntranslatableHistory {
+            message_index: 3,
+            ..
+        }
+    ));
+    assert!(error.to_string().contains("answers no earlier tool call"));
+
+    let (lenient, warnings) =
+        normalize_history_for(&anthropic(), &history).expect("lenient rewrites it");
+    assert!(matches!(
+        warnings.as_slice(),
+        [WireWarning::HistoryNormalized {
+            message_index: 3,
+            ..
+        }]
+    ));
+    assert_eq!(lenient[3].message_type, MessageType::User);
+    assert_eq!(lenient[3].content, "Output of a tool: sunny");
+    assert!(lenient[3].tool_call_id.is_none());
+
+    let mut scalar = tool_history(&openai(), OPENAI_ID);
+    scalar[1].tool_calls.as_mut().expect("tool calls")[0]
+        .function
+        .arguments = "\"Paris\"".to_string();
+    assert!(normalize_history_with(&anthropic(), &scalar, HistoryStrictness::Strict).is_err());
+    let (lenient, warnings) =
+        normalize_history_for(&anthropic(), &scalar).expect("lenient rewrites it");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        lenient[1].tool_calls.as_ref().expect("tool calls")[0]
+            .function
+            .arguments,
+        "{}"
+    );
+}
+
+#[test]
+fn strict_clients_refuse_before_sending() {
+    let mut orphan = message(MessageType::FunctionCallOutput, "sunny");
+    orphan.tool_call_id = Some("call-9".to_string());
+
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for normalize test");
+        let options = ClientOptions::default().with_history_strictness(HistoryStrictness::Strict);
+        let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+            .expect("known model");
+
+        let error = runtime
+            .block_on(client.prompt("Be brief.".to_string(), vec![orphan]))
+            .expect_err("strict client refuses the history");
+        assert!(matches!(
+            error,
+            WireError::UntranslatableHistory {
+                message_index: 0,
+                ..
+            }
+        ));
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_tool_history_replays_through_anthropic() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping normalize integration test");
+        return;
+    }
+
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for normalize test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/messages",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "content": [{ "type": "text", "text": "It is sunny." }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                .expect("known model");
+
+            let reply = client
+                .prompt("Be brief.".to_string(), tool_history(&openai(), OPENAI_ID))
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "It is sunny.");
+
+            let recorded = server.requests_for("/v1/messages").await;
+            let body: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            let tool_use = body["messages"][1]["content"]
+                .as_array()
+                .expect("assistant content")
+                .iter()
+                .find(|block| block["type"] == "tool_use")
+                .expect("tool_use block");
+            let tool_result = &body["messages"][2]["content"][0];
+
+            assert_eq!(tool_use["id"], "call_Xk2_9_weather");
+            assert_eq!(tool_result["type"], "tool_result");
+            assert_eq!(tool_result["tool_use_id"], tool_use["id"]);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn anthropic_tool_history_replays_through_openai() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping normalize integration test");
+        return;
+    }
+
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for normalize test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "It is sunny." } }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = OpenAIClient::try_with_options("gpt-4o", options).expect("known model");
+
+            let reply = client
+                .prompt(
+                    "Be brief.".to_string(),
+                    tool_history(&anthropic(), ANTHROPIC_ID),
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "It is sunny.");
+            assert_eq!(reply.api, openai());
+
+            let recorded = server.requests_for("/v1/chat/completions").await;
+            let body: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            let id = body["messages"][2]["tool_calls"][0]["id"]
+                .as_str()
+                .expect("tool call id");
+
+            assert_eq!(id.len(), 40);
+            assert_eq!(body["messages"][3]["role"], "tool");
+            assert_eq!(body["messages"][3]["tool_call_id"], id);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn calls_without_ids_get_ids_that_replay_through_anthropic() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping normalize integration test");
+        return;
+    }
+
+    let echo_call = |id: Option<&str>, value: &str| {
+        let mut call = serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": "echo",
+                "arguments": serde_json::json!({ "value": value }).to_string()
+            }
+        });
+        if let Some(id) = id {
+            call["id"] = id.into();
+        }
+        call
+    };
+    let calls = |calls: Vec<serde_json::Value>| {
+        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+            "choices": [{ "message": { "content": null, "tool_calls": calls } }]
+        })))
+    };
+
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for normalize test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![
+                MockRoute::new(
+                    "/v1/chat/completions",
+                    vec![
+                        calls(vec![echo_call(None, "a"), echo_call(Some(""), "b")]),
+                        calls(vec![
+                            echo_call(Some("call_0"), "c"),
+                            echo_call(Some("call_0"), "d"),
+                        ]),
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{ "message": { "content": "All done." } }]
+                        }))),
+                    ],
+                ),
+                MockRoute::single(
+                    "/v1/messages",
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "content": [{ "type": "text", "text": "Still done." }]
+                    }))),
+                ),
+            ])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let openai =
+                OpenAIClient::try_with_options("gpt-4o", options.clone()).expect("known model");
+            let history = openai
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Echo four things")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool loop completes");
+
+            assert_eq!(history.len(), 8);
+            let ids = |message: &Message| -> Vec<String> {
+                message
+                    .tool_calls
+                    .iter()
+                    .flatten()
+                    .map(|call| call.id.clone())
+                    .collect()
+            };
+            assert_eq!(ids(&history[1]), ["call_0_0", "call_0_1"]);
+            assert_eq!(
+                history[1].metadata.synthesized_call_ids,
+                [
+                    ("call_0_0".to_string(), String::new()),
+                    ("call_0_1".to_string(), String::new()),
+                ]
+                .into()
+            );
+            assert_eq!(ids(&history[4]), ["call_0", "call_1_1"]);
+            assert_eq!(
+                history[4].metadata.synthesized_call_ids,
+                [("call_1_1".to_string(), "call_0".to_string())].into()
+            );
+            let outputs: Vec<_> = [2, 3, 5, 6]
+                .iter()
+                .map(|&index| history[index].tool_call_id.clone().unwrap_or_default())
+                .collect();
+            assert_eq!(outputs, ["call_0_0", "call_0_1", "call_0", "call_1_1"]);
+            assert_eq!(history[7].content, "All done.");
+
+            let recorded = server.requests_for("/v1/chat/completions").await;
+            let last = request_messages(&recorded[2].body);
+            assert_eq!(last[6]["tool_call_id"], "call_0");
+            assert_eq!(last[7]["tool_call_id"], "call_1_1");
+
+            let anthropic =
+                AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options)
+                    .expect("known model");
+            let reply = anthropic
+                .prompt("Be brief.".to_string(), history)
+                .await
+                .expect("anthropic takes the history");
+            assert_eq!(reply.content, "Still done.");
+
+            let recorded = server.requests_for("/v1/messages").await;
+            let body: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            let blocks: Vec<&serde_json::Value> = body["messages"]
+                .as_array()
+                .expect("messages")
+                .iter()
+                .filter_map(|message| message["content"].as_array())
+                .flatten()
+                .collect();
+            let tool_uses: Vec<&str> = blocks
+                .iter()
+                .filter(|block| block["type"] == "tool_use")
+                .map(|block| block["id"].as_str().expect("tool_use id"))
+                .collect();
+            let tool_results: Vec<&str> = blocks
+                .iter()
+                .filter(|block| block["type"] == "tool_result")
+                .map(|block| block["tool_use_id"].as_str().expect("tool_use_id"))
+                .collect();
+            assert_eq!(tool_uses, ["call_0_0", "call_0_1", "call_0", "call_1_1"]);
+            assert_eq!(tool_results, tool_uses);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+fn request_messages(body: &[u8]) -> Vec<serde_json::Value> {
+    let body: serde_json::Value = serde_json::from_slice(body).expect("json body");
+    body["messages"].as_array().expect("messages array").clone()
+}
@@ -0,0 +1,336 @@
+#![cfg(feature = "ollama")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{
+    MockJsonLinesResponse, MockJsonResponse, MockLLMServer, MockResponse, MockRoute,
+};
+use common::{function_call, message, request_body_json, sample_tool};
+use wire::api::{OllamaModel, PromptCore, ToolCapable, API};
+use wire::config::ClientOptions;
+use wire::new_client;
+use wire::ollama::OllamaClient;
+use wire::types::MessageType;
+
+const OLLAMA_PATH: &str = "/api/chat";
+
+#[test]
+fn new_client_resolves_prefixed_ollama_models() {
+    let client = new_client("ollama/llama3.1:8b").expect("ollama model resolves");
+    let request = client
+        .build_request(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "Hi")],
+            None,
+            true,
+        )
+        .expect("request builds")
+        .build()
+        .expect("ollama request should build");
+
+    assert_eq!(request.url().as_str(), "http://localhost:11434/api/chat");
+    assert!(request.headers().get("authorization").is_none());
+
+    let body = request_body_json(&request);
+    assert_eq!(body["model"], "llama3.1:8b");
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "Hi");
+
+    // A bare name could be anyone's model
+    assert!(API::from_model("llama3.1:8b").is_err());
+    assert!(API::from_model("ollama/").is_err());
+}
+
+#[test]
+fn ollama_api_round_trips_through_serde_and_strings() {
+    let api = API::Ollama(OllamaModel::new("qwen2.5-coder:7b"));
+
+    let json = serde_json::to_value(&api).expect("api serializes");
+    assert_eq!(
+        json,
+        serde_json::json!({ "provider": "ollama", "model": "qwen2.5-coder:7b" })
+    );
+    assert_eq!(
+        serde_json::from_value::<API>(json).expect("api deserializes"),
+        api
+    );
+
+    let (provider, model) = api.to_strings();
+    assert_eq!(
+        (provider.as_str(), model.as_str()),
+        ("ollama", "qwen2.5-coder:7b")
+    );
+    assert_eq!(API::from_strings(&provider, &model), Ok(api));
+}
+
+#[test]
+fn ollama_requests_carry_tools_history_and_options() {
+    let options = ClientOptions::default().with_max_tokens(64);
+    let client = OllamaClient::with_options("llama3.1:8b", options).with_keep_alive("10m");
+
+    let mut call = message(MessageType::FunctionCall, "");
+    call.tool_calls = Some(vec![function_call(
+        "call-1",
+        "lookup_weather",
+        serde_json::json!({ "city": "Paris" }),
+    )]);
+    let mut output = message(MessageType::FunctionCallOutput, "sunny");
+    output.tool_call_id = Some("call-1".to_string());
+    output.name = Some("lookup_weather".to_string());
+
+    let tools = [sample_tool("lookup_weather").spec()];
+    let request = client
+        .build_request(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "Weather?"), call, output],
+            Some(&tools),
+            false,
+        )
+        .expect("request builds")
+        .build()
+        .expect("ollama request should build");
+    let body = request_body_json(&request);
+
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["keep_alive"], "10m");
+    assert_eq!(body["options"]["num_predict"], 64);
+    assert_eq!(body["tools"][0]["type"], "function");
+    assert_eq!(body["tools"][0]["function"]["name"], "lookup_weather");
+
+    let call = &body["messages"][2];
+    assert_eq!(call["role"], "assistant");
+    assert_eq!(call["tool_calls"][0]["function"]["name"], "lookup_weather");
+    assert_eq!(
+        call["tool_calls"][0]["function"]["arguments"],
+        serde_json::json!({ "city": "Paris" })
+    );
+
+    let output = &body["messages"][3];
+    assert_eq!(output["role"], "tool");
+    assert_eq!(output["tool_name"], "lookup_weather");
+    assert_eq!(output["content"], "sunny");
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn ollama_prompt_reads_token_counts() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping ollama integration test");
+        return;
+    }
+
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for ollama test");
+
+    runtime.block_on(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            OLLAMA_PATH,
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "model": "llama3.1:8b",
+                "message": { "role": "assistant", "content": "mock reply" },
+                "done": true,
+                "prompt_eval_count": 12,
+                "eval_count": 5
+            }))),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let client = OllamaClient::with_options("llama3.1:8b", options);
+
+        let response = client
+            .prompt(
+                "Stay friendly.".to_string(),
+                vec![message(MessageType::User, "Ping?")],
+            )
+            .await
+            .expect("prompt returns content");
+
+        assert_eq!(response.content, "mock reply");
+        assert_eq!(response.input_tokens, 12);
+        assert_eq!(response.output_tokens, 5);
+        assert_eq!(response.api, API::Ollama(OllamaModel::new("llama3.1:8b")));
+
+        let recorded = server.requests_for(OLLAMA_PATH).await;
+        assert_eq!(recorded.len(), 1);
+        let payload: serde_json::Value =
+            serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+        assert_eq!(payload["stream"], false);
+        assert_eq!(payload["messages"][1]["content"], "Ping?");
+
+        server.shutdown().await;
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn ollama_prompt_stream_reads_json_lines() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for ollama stream test");
+
+    runtime.block_on(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            OLLAMA_PATH,
+            MockResponse::ollama_text_stream(["Bonjour", " le", " monde"]),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let client = OllamaClient::with_options("llama3.1:8b", options);
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let response = client
+            .prompt_stream(
+                vec![message(MessageType::User, "Say hello in French")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect("stream completes");
+
+        let mut deltas = Vec::new();
+        while let Some(delta) = rx.recv().await {
+            deltas.push(delta);
+        }
+        assert_eq!(deltas, ["Bonjour", " le", " monde"]);
+        assert_eq!(response.content, "Bonjour le monde");
+        assert_eq!(response.input_tokens, 3);
+        assert_eq!(response.output_tokens, 3);
+        assert!(response.metadata.truncated_stream.is_none());
+
+        let recorded = server.requests_for(OLLAMA_PATH).await;
+        let payload: serde_json::Value =
+            serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+        assert_eq!(payload["stream"], true);
+
+        server.shutdown().await;
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn ollama_stream_error_line_fails_the_prompt() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping ollama stream error test");
+        return;
+    }
+
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for ollama error test");
+
+    runtime.block_on(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            OLLAMA_PATH,
+            MockResponse::JsonLines(MockJsonLinesResponse::new(vec![serde_json::json!({
+                "error": "model \"llama3.1:8b\" not found, try pulling it first"
+            })])),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let client = OllamaClient::with_options("llama3.1:8b", options);
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let error = client
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect_err("error line fails the stream");
+        assert!(
+            error.to_string().contains("try pulling it first"),
+            "{}",
+            error
+        );
+
+        server.shutdown().await;
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn ollama_prompt_with_tools_executes_tool_call_sequence() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping ollama tool integration test");
+        return;
+    }
+
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for ollama tool test");
+
+    runtime.block_on(async {
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            OLLAMA_PATH,
+            vec![
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{
+                            "function": { "name": "echo", "arguments": { "value": "hello" } }
+                        }]
+                    },
+                    "done": true,
+                    "prompt_eval_count": 20,
+                    "eval_count": 8
+                }))),
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "message": { "role": "assistant", "content": "All done." },
+                    "done": true
+                }))),
+            ],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let client = OllamaClient::with_options("llama3.1:8b", options);
+
+        let result = client
+            .prompt_with_tools(
+                "Follow instructions.",
+                vec![message(MessageType::User, "Please call the tool")],
+                vec![sample_tool("echo")],
+            )
+            .await
+            .expect("tool-assisted prompt succeeds");
+
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[1].message_type, MessageType::FunctionCall);
+        assert_eq!(result[1].output_tokens, 8);
+        let calls = result[1].tool_calls.as_ref().expect("tool calls recorded");
+        assert_eq!(calls[0].id, "call_0_0");
+        assert_eq!(
+            result[1].metadata.synthesized_call_ids.get("call_0_0"),
+            Some(&String::new())
+        );
+        assert_eq!(result[2].tool_call_id.as_deref(), Some("call_0_0"));
+        assert_eq!(calls[0].function.arguments, r#"{"value":"hello"}"#);
+        assert_eq!(result[2].message_type, MessageType::FunctionCallOutput);
+        assert_eq!(result[3].content, "All done.");
+
+        let recorded = server.requests_for(OLLAMA_PATH).await;
+        assert_eq!(recorded.len(), 2);
+        let first: serde_json::Value =
+            serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+        assert_eq!(first["tools"][0]["function"]["name"], "echo");
+        let second: serde_json::Value =
+            serde_json::from_slice(&recorded[1].body).expect("request body parses as json");
+        assert_eq!(
+            second["messages"][2]["tool_calls"][0]["function"]["name"],
+            "echo"
+        );
+        assert_eq!(second["messages"][3]["role"], "tool");
+        assert_eq!(second["messages"][3]["tool_name"], "echo");
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,337 @@
+//! The clients built on `OpenAIClient` for other providers differ only in
+//! where they send and whose key they read, so one table drives them all.
+//! Behavior only one provider has is tested in that provider's own file.
+
+#![cfg(any(
+    feature = "groq",
+    feature = "openrouter",
+    feature = "together",
+    feature = "fireworks",
+    feature = "perplexity",
+    feature = "compatible"
+))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, request_body_json, sample_tool};
+use temp_env::with_var;
+use wire::api::API;
+use wire::config::{ClientOptions, ThinkingLevel};
+use wire::new_client_with_options;
+use wire::types::{Message, MessageType};
+
+/// A provider as the tests drive it.
+struct Preset {
+    /// The id `new_client` takes.
+    model: &'static str,
+    /// Other ids naming the same model.
+    aliases: &'static [&'static str],
+    /// Ids that name none of the provider's models.
+    rejected: &'static [&'static str],
+    /// The model as requests name it.
+    sent_as: &'static str,
+    key_var: &'static str,
+    /// Where requests go with default options.
+    url: &'static str,
+    path: &'static str,
+    api: API,
+}
+
+fn presets() -> Vec<Preset> {
+    vec![
+        #[cfg(feature = "groq")]
+        Preset {
+            model: "llama-3.3-70b-versatile",
+            aliases: &[],
+            rejected: &[],
+            sent_as: "llama-3.3-70b-versatile",
+            key_var: "GROQ_API_KEY",
+            url: "https://api.groq.com/openai/v1/chat/completions",
+            path: "/openai/v1/chat/completions",
+            api: API::Groq(wire::api::GroqModel::Llama3370bVersatile),
+        },
+        #[cfg(feature = "openrouter")]
+        Preset {
+            model: "openrouter/anthropic/claude-sonnet-4",
+            aliases: &[],
+            // Without the prefix the id could be anyone's
+            rejected: &["anthropic/claude-sonnet-4", "openrouter/"],
+            sent_as: "anthropic/claude-sonnet-4",
+            key_var: "OPENROUTER_API_KEY",
+            url: "https://openrouter.ai/api/v1/chat/completions",
+            path: "/api/v1/chat/completions",
+            api: API::OpenRouter(wire::api::OpenRouterModel::new("anthropic/claude-sonnet-4")),
+        },
+        #[cfg(feature = "together")]
+        Preset {
+            model: "together/meta-llama/Llama-3.3-70B-Instruct-Turbo",
+            aliases: &[],
+            rejected: &["meta-llama/Llama-3.3-70B-Instruct-Turbo", "together/"],
+            sent_as: "meta-llama/Llama-3.3-70B-Instruct-Turbo",
+            key_var: "TOGETHER_API_KEY",
+            url: "https://api.together.xyz/v1/chat/completions",
+            path: "/v1/chat/completions",
+            api: API::Together(wire::api::TogetherModel::new(
+                "meta-llama/Llama-3.3-70B-Instruct-Turbo",
+            )),
+        },
+        #[cfg(feature = "fireworks")]
+        Preset {
+            model: "fireworks/accounts/fireworks/models/llama-v3p1-70b-instruct",
+            aliases: &["accounts/fireworks/models/llama-v3p1-70b-instruct"],
+            rejected: &[
+                "fireworks/",
+                "fireworks/llama-v3p1-70b-instruct",
+                "fireworks/accounts//models/llama",
+                "fireworks/accounts/fireworks/models/",
+            ],
+            sent_as: "accounts/fireworks/models/llama-v3p1-70b-instruct",
+            key_var: "FIREWORKS_API_KEY",
+            url: "https://api.fireworks.ai/inference/v1/chat/completions",
+            path: "/inference/v1/chat/completions",
+            api: API::Fireworks(wire::api::FireworksModel::new(
+                "accounts/fireworks/models/llama-v3p1-70b-instruct",
+            )),
+        },
+        #[cfg(feature = "perplexity")]
+        Preset {
+            model: "sonar-pro",
+            aliases: &[],
+            rejected: &[],
+            sent_as: "sonar-pro",
+            key_var: "PERPLEXITY_API_KEY",
+            url: "https://api.perplexity.ai/chat/completions",
+            path: "/chat/completions",
+            api: API::Perplexity(wire::api::PerplexityModel::SonarPro),
+        },
+        #[cfg(feature = "compatible")]
+        Preset {
+            model: "custom/my-model",
+            aliases: &[],
+            rejected: &["my-model", "custom/"],
+            sent_as: "my-model",
+            key_var: "OPENAI_COMPATIBLE_API_KEY",
+            url: "https://api.openai.com/v1/chat/completions",
+            path: "/v1/chat/completions",
+            api: API::Compatible(wire::api::CompatibleModel::new("my-model")),
+        },
+    ]
+}
+
+/// The `API`s of the table and of model names the providers that take any id
+/// must keep as given.
+fn stored_apis() -> Vec<API> {
+    let kept = vec![
+        #[cfg(feature = "together")]
+        API::Together(wire::api::TogetherModel::new("someone/fine-tune:v2@latest")),
+        #[cfg(feature = "fireworks")]
+        API::Fireworks(wire::api::FireworksModel::new(
+            "accounts/someone/models/nested/deployment@latest",
+        )),
+        #[cfg(feature = "compatible")]
+        API::Compatible(
+            wire::api::CompatibleModel::new("org/fine-tune:v2")
+                .with_label("litellm")
+                .unwrap(),
+        ),
+    ];
+
+    presets()
+        .into_iter()
+        .map(|preset| preset.api)
+        .chain(kept)
+        .collect()
+}
+
+#[test]
+fn presets_send_to_their_endpoint_with_their_key() {
+    for preset in presets() {
+        with_var(preset.key_var, Some("preset-key"), || {
+            // The thinking level is OpenAI's alone
+            let options = ClientOptions::default().with_thinking_level(ThinkingLevel::High);
+            let client = new_client_with_options(preset.model, options).expect("model resolves");
+            assert_eq!(client.get_auth_token().expect("a key"), "preset-key");
+            assert_eq!(client.new_message("Hi".to_string()).build().api, preset.api);
+
+            let request = client
+                .build_request(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                    None,
+                    false,
+                )
+                .expect("request builds")
+                .build()
+                .expect("request should build");
+
+            assert_eq!(request.url().as_str(), preset.url, "{}", preset.model);
+            assert_eq!(
+                request
+                    .headers()
+                    .get("authorization")
+                    .and_then(|value| value.to_str().ok()),
+                Some("Bearer preset-key")
+            );
+
+            let body = request_body_json(&request);
+            assert_eq!(body["model"], preset.sent_as);
+            assert_eq!(body["messages"][0]["role"], "system");
+            assert_eq!(body["messages"][1]["content"], "Hi");
+            assert!(body.get("reasoning_effort").is_none());
+        });
+
+        assert_eq!(API::from_model(preset.model), Ok(preset.api.clone()));
+        for alias in preset.aliases {
+            assert_eq!(API::from_model(alias), Ok(preset.api.clone()));
+        }
+        for rejected in preset.rejected {
+            assert!(API::from_model(rejected).is_err(), "{}", rejected);
+        }
+    }
+}
+
+#[test]
+fn preset_models_round_trip() {
+    for api in stored_apis() {
+        let (provider, model) = api.to_strings();
+        assert_eq!(API::from_strings(&provider, &model), Ok(api.clone()));
+
+        let json = serde_json::to_value(&api).expect("api serializes");
+        assert_eq!(
+            json,
+            serde_json::json!({ "provider": provider, "model": model })
+        );
+        assert_eq!(
+            serde_json::from_value::<API>(json).expect("api deserializes"),
+            api
+        );
+
+        let stored = Message {
+            api: api.clone(),
+            ..message(MessageType::Assistant, "Hello")
+        };
+        let restored: Message =
+            serde_json::from_str(&serde_json::to_string(&stored).expect("message serializes"))
+                .expect("message deserializes");
+        assert_eq!(restored.api, api);
+    }
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn presets_work_end_to_end() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping OpenAI-compatible integration test");
+        return;
+    }
+
+    for preset in presets() {
+        with_var(preset.key_var, Some("mock-preset-key"), || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for preset test");
+
+            runtime.block_on(async {
+                let server = MockLLMServer::start(vec![MockRoute::new(
+                    preset.path,
+                    vec![
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{ "message": { "content": "mock reply" } }]
+                        }))),
+                        MockResponse::openai_text_stream(["Hello", " from", " the preset"]),
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{
+                                "message": {
+                                    "content": null,
+                                    "tool_calls": [{
+                                        "id": "call-1",
+                                        "type": "function",
+                                        "function": {
+                                            "name": "echo",
+                                            "arguments": "{\"value\":\"hello\"}"
+                                        }
+                                    }]
+                                }
+                            }]
+                        }))),
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{ "message": { "content": "All done." } }]
+                        }))),
+                    ],
+                )])
+                .await
+                .expect("mock server starts");
+
+                let options = ClientOptions::for_mock_server(&server)
+                    .expect("client options for mock server");
+                let client = new_client_with_options(preset.model, options).expect("preset client");
+
+                let reply = client
+                    .prompt(
+                        "Stay friendly.".to_string(),
+                        vec![message(MessageType::User, "Ping?")],
+                    )
+                    .await
+                    .expect("prompt returns content");
+                assert_eq!(reply.content, "mock reply");
+                assert_eq!(reply.api, preset.api);
+
+                let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                let streamed = client
+                    .prompt_stream(
+                        vec![message(MessageType::User, "Greet me")],
+                        "Be brief.".to_string(),
+                        tx,
+                    )
+                    .await
+                    .expect("stream completes");
+                assert_eq!(streamed.content, "Hello from the preset");
+                assert_eq!(streamed.api, preset.api);
+
+                let history = client
+                    .tools()
+                    .expect("presets run tools")
+                    .prompt_with_tools(
+                        "Follow instructions.",
+                        vec![message(MessageType::User, "Please call the tool")],
+                        vec![sample_tool("echo")],
+                    )
+                    .await
+                    .expect("tool-assisted prompt succeeds");
+                assert_eq!(history.len(), 4);
+                assert_eq!(history[1].message_type, MessageType::FunctionCall);
+                assert_eq!(history[2].message_type, MessageType::FunctionCallOutput);
+                assert_eq!(history[3].content, "All done.");
+                assert!(history[1..].iter().all(|m| m.api == preset.api));
+
+                let recorded = server.requests_for(preset.path).await;
+                assert_eq!(recorded.len(), 4, "{}", preset.model);
+                let bodies: Vec<serde_json::Value> = recorded
+                    .iter()
+                    .map(|request| {
+                        assert_eq!(
+                            request.headers.get("authorization").map(String::as_str),
+                            Some("Bearer mock-preset-key")
+                        );
+                        serde_json::from_slice(&request.body).expect("request body parses as json")
+                    })
+                    .collect();
+                for body in &bodies {
+                    assert_eq!(body["model"], preset.sent_as);
+                }
+                assert_eq!(bodies[0]["stream"], false);
+                assert_eq!(bodies[0]["messages"][0]["role"], "system");
+                assert_eq!(bodies[0]["messages"][1]["content"], "Ping?");
+                assert_eq!(bodies[1]["stream"], true);
+                assert_eq!(bodies[2]["tools"][0]["type"], "function");
+                assert_eq!(bodies[2]["tools"][0]["function"]["name"], "echo");
+                assert_eq!(bodies[3]["messages"][2]["tool_calls"][0]["id"], "call-1");
+                assert_eq!(bodies[3]["messages"][3]["role"], "tool");
+                assert_eq!(bodies[3]["messages"][3]["tool_call_id"], "call-1");
+
+                server.shutdown().await;
+            });
+        });
+    }
+}
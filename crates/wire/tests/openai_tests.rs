@@ -1,123 +1,177 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
 mod common;
 
-use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
-use common::{function_call, message, raw_request_body, request_body_json, sample_tool};
-use std::panic;
+#[cfg(feature = "mock")]
+use common::mock_server::{
+    MockJsonResponse, MockLLMServer, MockResponse, MockRoute, MockSseEvent, MockSseResponse,
+};
+use common::{message, raw_request_body, request_body_json, sample_tool};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use temp_env::with_var;
-use wire::api::{OpenAIModel, Prompt};
-use wire::config::{ClientOptions, ThinkingLevel};
+use wire::api::{OpenAIModel, PromptCore, RawTransport, ToolCapable, API};
+use wire::config::{ClientOptions, PromptOptions, ServiceTier, ThinkingLevel, ToolChoice};
+use wire::error::WireError;
 use wire::openai::OpenAIClient;
-use wire::types::MessageType;
+use wire::types::{MessageType, Truncation};
 
-fn build_client<M>(model: M) -> Option<OpenAIClient>
+fn build_client<M>(model: M) -> OpenAIClient
 where
-    M: Into<OpenAIModel>,
+    M: TryInto<OpenAIModel>,
+    M::Error: Into<WireError>,
 {
-    let model = model.into();
-    panic::catch_unwind(|| OpenAIClient::new(model.clone())).ok()
+    OpenAIClient::try_new(model).expect("known model")
 }
 
-fn build_client_with_options<M>(model: M, options: ClientOptions) -> Option<OpenAIClient>
+fn build_client_with_options<M>(model: M, options: ClientOptions) -> OpenAIClient
 where
-    M: Into<OpenAIModel>,
+    M: TryInto<OpenAIModel>,
+    M::Error: Into<WireError>,
 {
-    let model = model.into();
-    panic::catch_unwind(move || OpenAIClient::with_options(model, options)).ok()
+    OpenAIClient::try_with_options(model, options).expect("known model")
 }
 
 #[test]
 fn openai_client_new_accepts_model_str() {
-    let client = match build_client("gpt-5") {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client("gpt-5");
 
-    assert_eq!(client.model, OpenAIModel::GPT5);
+    assert_eq!(client.api, API::OpenAI(OpenAIModel::GPT5));
+}
+
+fn nested_schema_tool(name: &str) -> wire::types::Tool {
+    let mut tool = sample_tool(name);
+    tool.parameters = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string" },
+            "filters": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "field": { "type": "string" },
+                        "value": { "type": "string" }
+                    },
+                    "required": ["field"]
+                }
+            }
+        },
+        "required": ["query"]
+    });
+    tool
 }
 
 #[test]
-fn openai_build_request_includes_system_and_tooling() {
-    std::env::set_var("OPENAI_API_KEY", "openai-key");
-
-    let client = match build_client("gpt-4o-mini") {
-        Some(client) => client,
-        None => return,
-    };
-
-    let mut tool_call_message = message(MessageType::FunctionCall, "");
-    tool_call_message.tool_calls = Some(vec![function_call(
-        "call-1",
-        "lookup_weather",
-        serde_json::json!({ "zip": "10001" }),
-    )]);
-
-    let mut tool_result_message = message(MessageType::FunctionCallOutput, "snow");
-    tool_result_message.tool_call_id = Some("call-1".to_string());
-
-    let chat_history = vec![
-        message(MessageType::User, "What's the weather?"),
-        tool_call_message,
-        tool_result_message,
-    ];
+fn openai_build_request_normalizes_strict_tool_schemas() {
+    let client = with_var("OPENAI_API_KEY", Some("openai-key"), || {
+        build_client("gpt-4o-mini")
+    });
+
+    let strict = nested_schema_tool("search").strict(true);
+    let loose = nested_schema_tool("browse");
 
     let request = client
         .build_request(
-            "Always explain your reasoning.".to_string(),
-            chat_history,
-            Some(vec![sample_tool("lookup_weather")]),
+            "Use tools.".to_string(),
+            vec![message(MessageType::User, "Find it")],
+            Some(&[strict.spec(), loose.spec()]),
             false,
         )
+        .expect("request builds")
         .build()
         .expect("openai request should be buildable");
 
-    assert_eq!(
-        request.url().as_str(),
-        "https://api.openai.com/v1/chat/completions"
-    );
-    assert_eq!(
-        request
-            .headers()
-            .get(reqwest::header::AUTHORIZATION)
-            .expect("auth header present")
-            .to_str()
-            .unwrap(),
-        "Bearer openai-key"
-    );
-
     let body = request_body_json(&request);
+    let tools = body["tools"].as_array().expect("tools array");
 
-    assert_eq!(body["model"], "gpt-4o-mini");
-    assert_eq!(body["stream"], false);
-
-    let messages = body["messages"].as_array().expect("messages array");
-    assert_eq!(messages.len(), 4);
+    assert_eq!(tools[0]["function"]["strict"], true);
+    assert_eq!(
+        tools[0]["function"]["parameters"],
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "filters": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "field": { "type": "string" },
+                            "value": { "type": "string" }
+                        },
+                        "required": ["field", "value"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["query", "filters"],
+            "additionalProperties": false
+        })
+    );
 
-    assert_eq!(messages[0]["role"], "system");
-    assert_eq!(messages[0]["content"], "Always explain your reasoning.");
-    assert_eq!(messages[1]["role"], "user");
-    assert_eq!(messages[1]["content"], "What's the weather?");
+    assert!(tools[1]["function"].get("strict").is_none());
+    assert_eq!(tools[1]["function"]["parameters"], loose.parameters);
+}
 
-    assert_eq!(messages[2]["role"], "assistant");
-    assert!(messages[2]["tool_calls"].is_array());
-    assert_eq!(messages[2]["tool_calls"][0]["id"], "call-1");
+#[cfg(feature = "anthropic")]
+#[test]
+fn strict_flag_only_changes_openai_payloads() {
+    with_var("ANTHROPIC_API_KEY", Some("anthropic-key"), || {
+        let strict = nested_schema_tool("search").strict(true);
+        let client = wire::anthropic::AnthropicClient::try_new("claude-3-5-haiku-20241022")
+            .expect("known model");
+
+        let request = client
+            .build_request(
+                "Use tools.".to_string(),
+                vec![message(MessageType::User, "Find it")],
+                Some(&[strict.spec()]),
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("anthropic request should be buildable");
+
+        let body = request_body_json(&request);
+        assert_eq!(body["tools"][0]["input_schema"], strict.parameters);
+        assert!(body["tools"][0].get("strict").is_none());
+    });
+}
 
-    assert_eq!(messages[3]["role"], "tool");
-    assert_eq!(messages[3]["tool_call_id"], "call-1");
+#[test]
+fn openai_build_request_maps_tool_choice() {
+    for (tool_choice, expected) in [
+        (ToolChoice::Auto, "auto"),
+        (ToolChoice::Any, "required"),
+        (ToolChoice::None, "none"),
+    ] {
+        let options = ClientOptions::default().with_tool_choice(tool_choice);
+        let client = with_var("OPENAI_API_KEY", Some("openai-key"), || {
+            build_client_with_options("gpt-4o-mini", options)
+        });
 
-    let tools = body["tools"].as_array().expect("tools array");
-    assert_eq!(tools.len(), 1);
-    assert_eq!(tools[0]["type"], "function");
-    assert_eq!(tools[0]["function"]["name"], "lookup_weather");
+        let request = client
+            .build_request(
+                "Use tools.".to_string(),
+                vec![message(MessageType::User, "Find it")],
+                Some(&[sample_tool("search").spec()]),
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("openai request should be buildable");
+
+        assert_eq!(request_body_json(&request)["tool_choice"], expected);
+    }
 }
 
 #[test]
 fn openai_build_request_adds_reasoning_effort_for_gpt5() {
-    std::env::set_var("OPENAI_API_KEY", "openai-key");
-
-    let client = match build_client("gpt-5") {
-        Some(client) => client,
-        None => return,
-    };
+    let client = with_var("OPENAI_API_KEY", Some("openai-key"), || {
+        build_client("gpt-5")
+    });
 
     let request = client
         .build_request(
@@ -126,6 +180,7 @@ fn openai_build_request_adds_reasoning_effort_for_gpt5() {
             None,
             false,
         )
+        .expect("request builds")
         .build()
         .expect("gpt-5 request should be buildable");
 
@@ -137,14 +192,11 @@ fn openai_build_request_adds_reasoning_effort_for_gpt5() {
 
 #[test]
 fn openai_client_with_options_overrides_thinking_level_for_gpt5() {
-    std::env::set_var("OPENAI_API_KEY", "openai-key");
-
     let options = ClientOptions::default().with_thinking_level(ThinkingLevel::High);
 
-    let client = match build_client_with_options(OpenAIModel::GPT5, options) {
-        Some(client) => client,
-        None => return,
-    };
+    let client = with_var("OPENAI_API_KEY", Some("openai-key"), || {
+        build_client_with_options(OpenAIModel::GPT5, options)
+    });
 
     let request = client
         .build_request(
@@ -153,6 +205,7 @@ fn openai_client_with_options_overrides_thinking_level_for_gpt5() {
             None,
             false,
         )
+        .expect("request builds")
         .build()
         .expect("gpt-5 request should be buildable");
 
@@ -163,18 +216,17 @@ fn openai_client_with_options_overrides_thinking_level_for_gpt5() {
 
 #[test]
 fn openai_build_request_raw_contains_headers_and_body() {
-    std::env::set_var("OPENAI_API_KEY", "openai-key");
-
-    let client = match build_client(OpenAIModel::GPT4o) {
-        Some(client) => client,
-        None => return,
-    };
+    let client = with_var("OPENAI_API_KEY", Some("openai-key"), || {
+        build_client(OpenAIModel::GPT4o)
+    });
 
-    let raw = client.build_request_raw(
-        "Be concise.".to_string(),
-        vec![message(MessageType::User, "Explain quantum physics")],
-        true,
-    );
+    let raw = client
+        .build_request_raw(
+            "Be concise.".to_string(),
+            vec![message(MessageType::User, "Explain quantum physics")],
+            true,
+        )
+        .expect("request builds");
 
     assert!(raw.contains("Authorization: Bearer openai-key"));
     assert!(raw.contains("Content-Type: application/json"));
@@ -186,10 +238,7 @@ fn openai_build_request_raw_contains_headers_and_body() {
 
 #[test]
 fn openai_read_json_response_extracts_text() {
-    let client = match build_client("gpt-4o-mini") {
-        Some(client) => client,
-        None => return,
-    };
+    let client = build_client("gpt-4o-mini");
 
     let response_json = serde_json::json!({
         "choices": [
@@ -208,6 +257,35 @@ fn openai_read_json_response_extracts_text() {
     assert_eq!(content, "OpenAI reply");
 }
 
+#[test]
+fn openai_process_stream_reads_any_byte_source() {
+    let client = build_client("gpt-4o-mini");
+
+    let response = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\" there\"}}]}\n\n\
+        data: [DONE]\n\n";
+
+    tokio::runtime::Runtime::new()
+        .expect("runtime for process_stream test")
+        .block_on(async {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let content = client
+                .process_stream(Box::new(std::io::Cursor::new(response)), &tx)
+                .await
+                .expect("stream is read");
+            drop(tx);
+
+            assert_eq!(content, "Hello there");
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas, vec!["Hello", " there"]);
+        });
+}
+
+#[cfg(feature = "mock")]
 #[test]
 fn openai_prompt_with_tools_executes_tool_call_sequence() {
     if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
@@ -268,7 +346,8 @@ fn openai_prompt_with_tools_executes_tool_call_sequence() {
 
             let options =
                 ClientOptions::for_mock_server(&server).expect("client options for mock server");
-            let client = OpenAIClient::with_options("gpt-4o-mini", options);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
 
             let history = vec![message(MessageType::User, "Please call the tool")];
 
@@ -312,6 +391,7 @@ fn openai_prompt_with_tools_executes_tool_call_sequence() {
     });
 }
 
+#[cfg(feature = "mock")]
 #[test]
 fn openai_prompt_with_tools_with_status_reports_tool_invocation() {
     if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
@@ -363,7 +443,8 @@ fn openai_prompt_with_tools_with_status_reports_tool_invocation() {
 
             let options =
                 ClientOptions::for_mock_server(&server).expect("client options for mock server");
-            let client = OpenAIClient::with_options("gpt-4o-mini", options);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
 
             let (tx, mut rx) = tokio::sync::mpsc::channel(2);
 
@@ -388,6 +469,7 @@ fn openai_prompt_with_tools_with_status_reports_tool_invocation() {
     });
 }
 
+#[cfg(feature = "mock")]
 #[test]
 fn openai_prompt_integration_uses_mock_server() {
     if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
@@ -420,7 +502,8 @@ fn openai_prompt_integration_uses_mock_server() {
 
             let options =
                 ClientOptions::for_mock_server(&server).expect("client options for mock server");
-            let client = OpenAIClient::with_options("gpt-4o-mini", options);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
 
             let response = client
                 .prompt(
@@ -431,6 +514,8 @@ fn openai_prompt_integration_uses_mock_server() {
                 .expect("prompt returns content");
 
             assert_eq!(response.content, "mock reply");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 2);
 
             let recorded = server.requests_for("/v1/chat/completions").await;
             assert_eq!(recorded.len(), 1);
@@ -447,3 +532,513 @@ fn openai_prompt_integration_uses_mock_server() {
         });
     });
 }
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_stream_records_latency_stats() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for latency test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(["Hel", "lo", "!"])
+                    .with_chunk_delay(Duration::from_millis(50)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let sink = reported.clone();
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_metrics_callback(move |metrics| {
+                    sink.lock().unwrap().push(metrics.clone());
+                });
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Say hello")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.content, "Hello!");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 3);
+
+            let recorded = server.requests_for("/v1/chat/completions").await;
+            let body: serde_json::Value =
+                serde_json::from_str(&recorded[0].body_as_string().expect("request body"))
+                    .expect("request body is JSON");
+            assert_eq!(body["stream_options"]["include_usage"], true);
+
+            let mut deltas = Vec::new();
+            while let Ok(delta) = rx.try_recv() {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas, vec!["Hel", "lo", "!"]);
+
+            let sequence = response
+                .metadata
+                .sequence
+                .as_ref()
+                .expect("sequence recorded");
+            assert_eq!(sequence.final_seq, Some(2));
+            assert_eq!(sequence.blocks.len(), 1);
+
+            let latency = response.metadata.latency.expect("latency recorded");
+            let ttft = latency.ttft.expect("first token recorded");
+            assert_eq!(latency.deltas, 3);
+            assert!(ttft >= Duration::from_millis(40), "ttft was {:?}", ttft);
+            assert!(latency.total >= Duration::from_millis(130));
+            assert!(latency.total >= ttft);
+            assert!(latency.total < Duration::from_secs(5));
+
+            {
+                let reported = reported.lock().unwrap();
+                assert_eq!(reported.len(), 1);
+                assert_eq!(reported[0].latency, latency);
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_stream_returns_tool_calls() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool call stream test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_tool_call_stream(
+                    "call_1",
+                    "get_weather",
+                    ["{\"ci", "ty\":", "\"Paris\"}"],
+                ),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let client = build_client_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+            );
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather in Paris?")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.message_type, MessageType::FunctionCall);
+            assert_eq!(response.content, "");
+            assert_eq!(response.input_tokens, 3);
+            assert_eq!(response.output_tokens, 4);
+
+            let calls = response.tool_calls.expect("tool calls on the reply");
+            assert_eq!(calls.len(), 1);
+            assert_eq!(calls[0].id, "call_1");
+            assert_eq!(calls[0].call_type, "function");
+            assert_eq!(calls[0].function.name, "get_weather");
+            assert_eq!(calls[0].function.arguments, "{\"city\":\"Paris\"}");
+            assert!(response.metadata.synthesized_call_ids.is_empty());
+            assert!(rx.try_recv().is_err());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_stream_pieces_together_interleaved_tool_calls() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool call stream test");
+
+        runtime.block_on(async {
+            let calls = |calls: serde_json::Value| {
+                MockSseEvent::data_json(serde_json::json!({
+                    "choices": [{ "delta": { "tool_calls": calls } }]
+                }))
+            };
+            // The second call leaves out its id, as some compatible servers do
+            let stream = MockSseResponse::new(vec![
+                MockSseEvent::data_json(serde_json::json!({
+                    "choices": [{ "delta": { "content": "Checking both." } }]
+                })),
+                calls(serde_json::json!([{
+                    "index": 0,
+                    "id": "call_a",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{\"city\":" }
+                }])),
+                calls(serde_json::json!([{
+                    "index": 1,
+                    "type": "function",
+                    "function": { "name": "get_time", "arguments": "{\"zone\":" }
+                }])),
+                calls(serde_json::json!([
+                    { "index": 1, "function": { "arguments": "\"CET\"}" } },
+                    { "index": 0, "function": { "arguments": "\"Paris\"}" } }
+                ])),
+            ])
+            .with_done();
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Sse(stream),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let client = build_client_with_options(
+                "gpt-4o-mini",
+                ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+            );
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let response = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather and time in Paris?")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.message_type, MessageType::FunctionCall);
+            assert_eq!(response.content, "Checking both.");
+
+            let calls = response.tool_calls.expect("tool calls on the reply");
+            let summary: Vec<_> = calls
+                .iter()
+                .map(|call| {
+                    (
+                        call.id.as_str(),
+                        call.function.name.as_str(),
+                        call.function.arguments.as_str(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                summary,
+                vec![
+                    ("call_a", "get_weather", "{\"city\":\"Paris\"}"),
+                    ("call_0_1", "get_time", "{\"zone\":\"CET\"}"),
+                ]
+            );
+            assert_eq!(
+                response.metadata.synthesized_call_ids.get("call_0_1"),
+                Some(&String::new())
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_stream_stops_at_max_response_bytes() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cap test");
+
+        runtime.block_on(async {
+            // 200 x 10 bytes, 20ms apart: four seconds if read to the end
+            let chunks: Vec<String> = (0..200).map(|i| format!("chunk-{:04}", i)).collect();
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(chunks)
+                    .with_chunk_delay(Duration::from_millis(20)),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(256);
+            let started = std::time::Instant::now();
+            let response = client
+                .prompt_stream_with_options(
+                    vec![message(MessageType::User, "Talk forever")],
+                    "Be verbose.".to_string(),
+                    tx,
+                    &PromptOptions::new().with_max_response_bytes(25),
+                )
+                .await
+                .expect("stream completes");
+
+            assert!(started.elapsed() < Duration::from_secs(2));
+            assert_eq!(response.content, "chunk-0000chunk-0001chunk");
+            assert_eq!(
+                response.metadata.truncated,
+                Some(Truncation {
+                    limit: 25,
+                    original_bytes: 30,
+                })
+            );
+
+            let mut deltas = Vec::new();
+            while let Ok(delta) = rx.try_recv() {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas, vec!["chunk-0000", "chunk-0001", "chunk"]);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_stream_can_discard_content() {
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for discard test");
+
+        runtime.block_on(async {
+            // 5000 x 20 bytes
+            let chunks: Vec<String> = (0..5000).map(|i| format!("{:>19} ", i)).collect();
+            let expected: String = chunks.concat();
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::openai_text_stream(chunks),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(64);
+            let received = tokio::spawn(async move {
+                let mut content = String::new();
+                while let Some(delta) = rx.recv().await {
+                    content.push_str(&delta);
+                }
+                content
+            });
+
+            let response = client
+                .prompt_stream_with_options(
+                    vec![message(MessageType::User, "Count")],
+                    "Be thorough.".to_string(),
+                    tx,
+                    &PromptOptions::new().with_discard_streamed_content(true),
+                )
+                .await
+                .expect("stream completes");
+
+            assert_eq!(response.message_type, MessageType::Assistant);
+            assert!(response.content.is_empty());
+            assert_eq!(response.metadata.content_bytes, Some(100_000));
+            assert_eq!(response.metadata.truncated, None);
+            assert_eq!(
+                response
+                    .metadata
+                    .sequence
+                    .expect("sequence recorded")
+                    .final_seq,
+                Some(4999)
+            );
+            assert_eq!(
+                response.metadata.latency.expect("latency recorded").deltas,
+                5000
+            );
+
+            // The caller still gets every delta
+            assert_eq!(received.await.expect("receiver task"), expected);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_prompt_truncates_to_max_response_bytes() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping openai response cap test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for cap test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "héllo wörld" } }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let capped = client
+                .prompt_with_options(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Greet")],
+                    &PromptOptions::new().with_max_response_bytes(2),
+                )
+                .await
+                .expect("prompt completes");
+
+            // "é" is two bytes, so only "h" fits
+            assert_eq!(capped.content, "h");
+            assert_eq!(
+                capped.metadata.truncated,
+                Some(Truncation {
+                    limit: 2,
+                    original_bytes: "héllo wörld".len(),
+                })
+            );
+
+            let uncapped = client
+                .prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Greet")],
+                )
+                .await
+                .expect("prompt completes");
+            assert_eq!(uncapped.content, "héllo wörld");
+            assert!(uncapped.metadata.truncated.is_none());
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openai_tool_loop_keeps_and_reports_content_sent_with_tool_calls() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping openai interim text test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for status test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{
+                            "message": {
+                                "content": "Let me echo that.",
+                                "tool_calls": [{
+                                    "id": "call-1",
+                                    "type": "function",
+                                    "function": {
+                                        "name": "echo",
+                                        "arguments": "{\"value\":\"hello\"}"
+                                    }
+                                }]
+                            }
+                        }]
+                    }))),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "All done." } }]
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+
+            let result = client
+                .prompt_with_tools_with_status(
+                    tx,
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Call the tool")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool-assisted prompt succeeds");
+
+            // The text does not end the loop; the call still runs
+            assert_eq!(result.len(), 4);
+            assert_eq!(result[1].message_type, MessageType::FunctionCall);
+            assert_eq!(result[1].content, "Let me echo that.");
+            assert_eq!(result[3].content, "All done.");
+
+            let mut statuses = Vec::new();
+            while let Ok(status) = rx.try_recv() {
+                statuses.push(status);
+            }
+            assert_eq!(statuses, vec!["Let me echo that.", "calling tool echo..."]);
+
+            let requests = server.requests_for("/v1/chat/completions").await;
+            let body: serde_json::Value =
+                serde_json::from_slice(&requests[1].body).expect("json body");
+            assert_eq!(body["messages"][2]["content"], "Let me echo that.");
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn openai_rejects_service_tier() {
+    let client = OpenAIClient::try_new("gpt-4o-mini").expect("known model");
+    let options = PromptOptions::new().with_service_tier(ServiceTier::Auto);
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for service tier test");
+
+    runtime.block_on(async {
+        let error = client
+            .prompt_with_options(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hello")],
+                &options,
+            )
+            .await
+            .expect_err("service tier is rejected");
+        assert!(matches!(
+            error,
+            WireError::UnsupportedOption {
+                option: "service_tier",
+                provider,
+            } if provider == "openai"        ));
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let error = client
+            .prompt_stream_with_options(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+                &options,
+            )
+            .await
+            .expect_err("service tier is rejected");
+        assert_eq!(error.to_string(), "service_tier is not supported by openai");
+    });
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "openrouter")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, request_body_json};
+use temp_env::with_var;
+use wire::api::PromptCore;
+use wire::config::{ClientOptions, PromptOptions, ProviderPreferences};
+use wire::new_client_with_options;
+use wire::openrouter::OpenRouterClient;
+use wire::types::MessageType;
+
+const OPENROUTER_PATH: &str = "/api/v1/chat/completions";
+const MODEL: &str = "openrouter/anthropic/claude-sonnet-4";
+
+fn routed_options(options: ClientOptions) -> ClientOptions {
+    options
+        .with_openrouter_attribution("https://example.com/app", "Example App")
+        .with_openrouter_provider(ProviderPreferences {
+            order: vec!["anthropic".to_string(), "amazon-bedrock".to_string()],
+            allow_fallbacks: Some(false),
+            ..Default::default()
+        })
+}
+
+#[test]
+fn openrouter_requests_carry_attribution_and_provider_preferences() {
+    with_var("OPENROUTER_API_KEY", Some("openrouter-key"), || {
+        let options = routed_options(ClientOptions::default());
+        let client = OpenRouterClient::with_options("anthropic/claude-sonnet-4", options);
+
+        let request = client
+            .build_request(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+                None,
+                false,
+            )
+            .expect("request builds")
+            .build()
+            .expect("openrouter request should build");
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        assert_eq!(
+            header("http-referer").as_deref(),
+            Some("https://example.com/app")
+        );
+        assert_eq!(header("x-title").as_deref(), Some("Example App"));
+        assert_eq!(
+            request_body_json(&request)["provider"],
+            serde_json::json!({
+                "order": ["anthropic", "amazon-bedrock"],
+                "allow_fallbacks": false
+            })
+        );
+
+        let raw = client
+            .raw_transport()
+            .expect("openrouter has a raw transport")
+            .build_request_raw(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+                true,
+            )
+            .expect("request builds");
+        assert!(raw.starts_with("POST /api/v1/chat/completions HTTP/1.1\r\n"));
+        assert!(raw.contains("HTTP-Referer: https://example.com/app\r\n"));
+        assert!(raw.contains("X-Title: Example App\r\n"));
+        assert_eq!(
+            common::raw_request_body(&raw)["provider"]["order"][0],
+            "anthropic"
+        );
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn openrouter_attribution_reaches_every_request() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping openrouter integration test");
+        return;
+    }
+
+    with_var("OPENROUTER_API_KEY", Some("mock-openrouter-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for openrouter test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                OPENROUTER_PATH,
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "mock reply" } }]
+                    }))),
+                    MockResponse::openai_text_stream(["Hello", " from", " OpenRouter"]),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = routed_options(
+                ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+            );
+            let client = new_client_with_options(MODEL, options).expect("openrouter client");
+
+            // The prompt's own header replaces the client's
+            let per_prompt =
+                PromptOptions::default().with_extra_headers([("X-Title", "Nightly Batch")]);
+            let reply = client
+                .prompt_with_options(
+                    "Stay friendly.".to_string(),
+                    vec![message(MessageType::User, "Ping?")],
+                    &per_prompt,
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "mock reply");
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Greet me")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(streamed.content, "Hello from OpenRouter");
+
+            let recorded = server.requests_for(OPENROUTER_PATH).await;
+            assert_eq!(recorded.len(), 2);
+            for request in &recorded {
+                assert_eq!(
+                    request.headers.get("http-referer").map(String::as_str),
+                    Some("https://example.com/app")
+                );
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("request body parses as json");
+                assert_eq!(body["provider"]["allow_fallbacks"], false);
+            }
+            assert_eq!(
+                recorded[0].headers.get("x-title").map(String::as_str),
+                Some("Nightly Batch")
+            );
+            assert_eq!(
+                recorded[1].headers.get("x-title").map(String::as_str),
+                Some("Example App")
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
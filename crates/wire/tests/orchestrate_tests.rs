@@ -0,0 +1,174 @@
+mod common;
+
+use common::message;
+use wire::api::{WireModel, API};
+use wire::new_client;
+use wire::orchestrate::{cross_check_with_options, CrossCheckOptions, Usage};
+use wire::types::{MessageBuilder, MessageType};
+
+#[test]
+fn usage_sums_every_message() {
+    let messages = [
+        MessageBuilder::new(API::Wire(WireModel::Echo), "a")
+            .with_usage(10, 2)
+            .build(),
+        MessageBuilder::new(API::Wire(WireModel::Echo), "b")
+            .with_usage(12, 5)
+            .build(),
+        MessageBuilder::new(API::Wire(WireModel::Echo), "c")
+            .with_usage(40, 7)
+            .build(),
+    ];
+
+    let usage = Usage::of(&messages);
+    assert_eq!(
+        usage,
+        Usage {
+            input_tokens: 62,
+            output_tokens: 14,
+        }
+    );
+    assert_eq!(usage.total_tokens(), 76);
+    assert_eq!(Usage::of([]), Usage::default());
+}
+
+#[test]
+fn judge_template_is_rendered_once() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for cross check test");
+    let echo = new_client("wire:echo").expect("echo client");
+
+    runtime.block_on(async {
+        // The echo judge replies with the rendered template
+        let result = cross_check_with_options(
+            echo.as_ref(),
+            echo.as_ref(),
+            echo.as_ref(),
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "say {second_answer}")],
+            &CrossCheckOptions::new()
+                .with_judge_system_prompt("Judge.")
+                .with_judge_template(
+                    "Q: {question} | A: {first_answer} | B: {second_answer} | {other}",
+                ),
+        )
+        .await
+        .expect("cross check completes");
+
+        assert_eq!(result.answers[0].content, "say {second_answer}");
+        assert_eq!(result.answers[1].content, "say {second_answer}");
+        assert_eq!(
+            result.verdict.content,
+            "Q: say {second_answer} | A: say {second_answer} | B: say {second_answer} | {other}"
+        );
+        assert_eq!(result.verdict.system_prompt, "Judge.");
+    });
+}
+
+#[cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+#[test]
+fn cross_check_sends_both_answers_to_the_judge() {
+    use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+    use temp_env::with_vars;
+    use wire::anthropic::AnthropicClient;
+    use wire::config::ClientOptions;
+    use wire::gemini::GeminiClient;
+    use wire::openai::OpenAIClient;
+    use wire::orchestrate::{cross_check, DEFAULT_JUDGE_SYSTEM_PROMPT};
+
+    const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping cross check test");
+        return;
+    }
+
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("GEMINI_API_KEY", Some("mock-gemini-key")),
+        ],
+        || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for cross check test");
+
+            runtime.block_on(async {
+                let server = MockLLMServer::start(vec![
+                    MockRoute::single(
+                        "/v1/chat/completions",
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "choices": [{ "message": { "content": "It fell in 1989." } }]
+                        }))),
+                    ),
+                    MockRoute::single(
+                        "/v1/messages",
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "content": [{ "type": "text", "text": "On 9 November 1989." }]
+                        }))),
+                    ),
+                    MockRoute::single(
+                        GEMINI_PATH,
+                        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                            "candidates": [{
+                                "content": { "parts": [{ "text": "Both are right; B is more precise." }] }
+                            }]
+                        }))),
+                    ),
+                ])
+                .await
+                .expect("mock server starts");
+
+                let options = || ClientOptions::for_mock_server(&server).expect("client options");
+                let primary = OpenAIClient::try_with_options("gpt-4o-mini", options()).expect("known model");
+                let secondary =
+                    AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options()).expect("known model");
+                let judge = GeminiClient::try_with_options("gemini-2.0-flash", options()).expect("known model");
+
+                let result = cross_check(
+                    &primary,
+                    &secondary,
+                    &judge,
+                    "Answer concisely.".to_string(),
+                    vec![message(MessageType::User, "When did the Berlin Wall fall?")],
+                )
+                .await
+                .expect("cross check completes");
+
+                assert_eq!(result.answers[0].content, "It fell in 1989.");
+                assert_eq!(result.answers[1].content, "On 9 November 1989.");
+                assert_eq!(result.verdict.content, "Both are right; B is more precise.");
+                assert_eq!(
+                    result.usage,
+                    Usage::of(result.answers.iter().chain([&result.verdict]))
+                );
+
+                // Each answering client saw the original prompt
+                for path in ["/v1/chat/completions", "/v1/messages"] {
+                    let requests = server.requests_for(path).await;
+                    assert_eq!(requests.len(), 1, "{}", path);
+                    let body = String::from_utf8_lossy(&requests[0].body);
+                    assert!(body.contains("When did the Berlin Wall fall?"), "{}", body);
+                    assert!(body.contains("Answer concisely."), "{}", body);
+                }
+
+                let requests = server.requests_for(GEMINI_PATH).await;
+                assert_eq!(requests.len(), 1);
+                let body: serde_json::Value =
+                    serde_json::from_slice(&requests[0].body).expect("judge body is json");
+                let judging = body["contents"][0]["parts"][0]["text"]
+                    .as_str()
+                    .expect("judge got one text part");
+                assert!(judging.contains("When did the Berlin Wall fall?"));
+                assert!(judging.contains("Answer A:\nIt fell in 1989."));
+                assert!(judging.contains("Answer B:\nOn 9 November 1989."));
+                assert!(body.to_string().contains(DEFAULT_JUDGE_SYSTEM_PROMPT));
+
+                server.shutdown().await;
+            });
+        },
+    );
+}
@@ -0,0 +1,199 @@
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{history, run_mock_test, with_mock_keys};
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, RawTransport};
+use wire::config::{ClientOptions, PromptOptions};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+
+#[test]
+fn client_paths_apply_to_built_requests() {
+    with_mock_keys(|| {
+        let gemini = GeminiClient::try_new("gemini-2.0-flash")
+            .expect("known model")
+            .with_api_version("v1");
+        let request = gemini
+            .build_request("Be brief.".to_string(), history(), None, false)
+            .expect("request builds")
+            .build()
+            .expect("gemini request builds");
+        assert_eq!(
+            request.url().path(),
+            "/v1/models/gemini-2.0-flash:generateContent"
+        );
+        assert!(gemini
+            .build_request_raw("Be brief.".to_string(), history(), true)
+            .expect("request builds")
+            .starts_with("POST /v1/models/gemini-2.0-flash:streamGenerateContent?key="));
+
+        let openai = OpenAIClient::try_new("gpt-4o-mini")
+            .expect("known model")
+            .with_path("/openai/v1/chat/completions");
+        let request = openai
+            .build_request("Be brief.".to_string(), history(), None, false)
+            .expect("request builds")
+            .build()
+            .expect("openai request builds");
+        assert_eq!(request.url().path(), "/openai/v1/chat/completions");
+        assert!(openai
+            .build_request_raw("Be brief.".to_string(), history(), true)
+            .expect("request builds")
+            .starts_with("POST /openai/v1/chat/completions HTTP/1.1"));
+
+        let anthropic = AnthropicClient::try_new("claude-3-5-haiku-20241022")
+            .expect("known model")
+            .with_path("/anthropic/v1/messages");
+        let request = anthropic
+            .build_request("Be brief.".to_string(), history(), None, false)
+            .expect("request builds")
+            .build()
+            .expect("anthropic request builds");
+        assert_eq!(request.url().path(), "/anthropic/v1/messages");
+        assert!(anthropic
+            .build_request_raw("Be brief.".to_string(), history(), true)
+            .expect("request builds")
+            .starts_with("POST /anthropic/v1/messages HTTP/1.1"));
+    });
+}
+
+/// Send one plain and one streaming prompt with `options`.
+#[cfg(feature = "mock")]
+async fn prompt_and_stream(client: &dyn PromptCore, options: &PromptOptions) {
+    client
+        .prompt_with_options("Be brief.".to_string(), history(), options)
+        .await
+        .expect("prompt succeeds");
+
+    let (tx, _rx) = tokio::sync::mpsc::channel(8);
+    client
+        .prompt_stream_with_options(history(), "Be brief.".to_string(), tx, options)
+        .await
+        .expect("stream succeeds");
+}
+
+#[cfg(feature = "mock")]
+#[cfg(feature = "mock")]
+#[test]
+fn openai_and_anthropic_prompts_use_the_path_override() {
+    run_mock_test("openai/anthropic path override test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::new(
+                "/eu/v1/chat/completions",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "ok" } }]
+                    }))),
+                    MockResponse::openai_text_stream(["o", "k"]),
+                ],
+            ),
+            MockRoute::new(
+                "/eu/v1/messages",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "stop_reason": "end_turn",
+                        "content": [{ "type": "text", "text": "ok" }]
+                    }))),
+                    MockResponse::anthropic_text_stream(["o", "k"]),
+                ],
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let client_options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+
+        let openai = OpenAIClient::try_with_options("gpt-4o-mini", client_options.clone())
+            .expect("known model");
+        prompt_and_stream(
+            &openai,
+            &PromptOptions::new().with_path_override("/eu/v1/chat/completions"),
+        )
+        .await;
+
+        let anthropic =
+            AnthropicClient::try_with_options("claude-3-5-haiku-20241022", client_options)
+                .expect("known model");
+        prompt_and_stream(
+            &anthropic,
+            &PromptOptions::new().with_path_override("/eu/v1/messages"),
+        )
+        .await;
+
+        assert_eq!(
+            server.requests_for("/eu/v1/chat/completions").await.len(),
+            2
+        );
+        assert_eq!(server.requests_for("/eu/v1/messages").await.len(), 2);
+        assert!(server.requests_for("/v1/chat/completions").await.is_empty());
+        assert!(server.requests_for("/v1/messages").await.is_empty());
+
+        server.shutdown().await;
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn gemini_prompts_use_the_api_version_and_path_override() {
+    run_mock_test("gemini path override test", async {
+        let server = MockLLMServer::start(vec![
+            MockRoute::single(
+                "/v1/models/gemini-2.0-flash:generateContent?key=mock-gemini-key",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "candidates": [{ "content": { "parts": [{ "text": "ok" }] } }]
+                }))),
+            ),
+            MockRoute::single(
+                "/v1/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key",
+                MockResponse::gemini_text_stream(["o", "k"]),
+            ),
+            MockRoute::new(
+                "/regional/models/gemini-2.0-flash?key=mock-gemini-key",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "candidates": [{ "content": { "parts": [{ "text": "ok" }] } }]
+                    }))),
+                    MockResponse::gemini_text_stream(["o", "k"]),
+                ],
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let client = GeminiClient::try_with_options(
+            "gemini-2.0-flash",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model")
+        .with_api_version("v1");
+
+        prompt_and_stream(&client, &PromptOptions::new()).await;
+        prompt_and_stream(
+            &client,
+            &PromptOptions::new().with_path_override("/regional/models/gemini-2.0-flash"),
+        )
+        .await;
+
+        for (path, count) in [
+            (
+                "/v1/models/gemini-2.0-flash:generateContent?key=mock-gemini-key",
+                1,
+            ),
+            (
+                "/v1/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key",
+                1,
+            ),
+            ("/regional/models/gemini-2.0-flash?key=mock-gemini-key", 2),
+        ] {
+            assert_eq!(server.requests_for(path).await.len(), count, "{}", path);
+        }
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use wire::payload::{merge, to_canonical_string, to_string, JsonFormat};
+
+#[test]
+fn keys_are_sorted_at_every_level() {
+    let value = serde_json::json!({
+        "b": 1,
+        "a": { "d": [{ "f": 1, "e": "x" }], "c": null },
+    });
+
+    assert_eq!(
+        to_canonical_string(&value),
+        r#"{"a":{"c":null,"d":[{"e":"x","f":1}]},"b":1}"#
+    );
+}
+
+#[test]
+fn pretty_output_keeps_the_canonical_order() {
+    let value = serde_json::json!({ "b": [1, 2], "a": { "d": true, "c": "x" } });
+    let pretty = to_string(&value, JsonFormat::Pretty);
+
+    assert!(pretty.contains('\n'));
+    assert!(pretty.find("\"a\"") < pretty.find("\"b\""));
+    assert!(pretty.find("\"c\"") < pretty.find("\"d\""));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+        value
+    );
+}
+
+#[cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+mod clients {
+    use super::*;
+    use common::{sample_tool, tool_call_history};
+    use temp_env::with_vars;
+    use wire::anthropic::AnthropicClient;
+    use wire::api::PromptCore;
+    use wire::config::ClientOptions;
+    use wire::gemini::GeminiClient;
+    use wire::openai::OpenAIClient;
+    use wire::types::ToolSpec;
+
+    fn clients(options: ClientOptions) -> Vec<Box<dyn PromptCore>> {
+        vec![
+            Box::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model"),
+            ),
+        ]
+    }
+
+    fn body(client: &dyn PromptCore, tools: Option<&[ToolSpec]>) -> Vec<u8> {
+        let request = client
+            .build_request("Be helpful.".to_string(), tool_call_history(), tools, false)
+            .expect("request builds")
+            .build()
+            .expect("request builds");
+
+        request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .expect("request has a body")
+            .to_vec()
+    }
+
+    fn with_keys<F: FnOnce()>(f: F) {
+        with_vars(
+            [
+                ("OPENAI_API_KEY", Some("payload-key")),
+                ("ANTHROPIC_API_KEY", Some("payload-key")),
+                ("GEMINI_API_KEY", Some("payload-key")),
+            ],
+            f,
+        );
+    }
+
+    #[test]
+    fn the_same_request_always_has_the_same_bytes() {
+        with_keys(|| {
+            let tools = vec![sample_tool("lookup_weather").spec()];
+            let first = clients(ClientOptions::default());
+            let second = clients(ClientOptions::default());
+
+            for (a, b) in first.iter().zip(&second) {
+                for tools in [None, Some(tools.as_slice())] {
+                    let bytes = body(a.as_ref(), tools);
+                    assert_eq!(bytes, body(a.as_ref(), tools));
+                    assert_eq!(bytes, body(b.as_ref(), tools));
+
+                    let text = String::from_utf8(bytes).expect("utf-8 body");
+                    let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    assert_eq!(text, to_canonical_string(&value));
+                }
+
+                let raw = |client: &dyn PromptCore| {
+                    client
+                        .raw_transport()
+                        .expect("provider clients have a raw transport")
+                        .build_request_raw("Be helpful.".to_string(), tool_call_history(), true)
+                        .expect("request builds")
+                };
+                assert_eq!(raw(a.as_ref()), raw(b.as_ref()));
+            }
+        });
+    }
+
+    #[test]
+    fn pretty_bodies_parse_to_the_same_request() {
+        with_keys(|| {
+            let compact = clients(ClientOptions::default());
+            let pretty = clients(ClientOptions::default().with_json_format(JsonFormat::Pretty));
+
+            for (compact, pretty) in compact.iter().zip(&pretty) {
+                let compact = body(compact.as_ref(), None);
+                let pretty = body(pretty.as_ref(), None);
+
+                assert!(pretty.contains(&b'\n'));
+                assert_eq!(
+                    serde_json::from_slice::<serde_json::Value>(&pretty).unwrap(),
+                    serde_json::from_slice::<serde_json::Value>(&compact).unwrap()
+                );
+            }
+        });
+    }
+}
+
+#[test]
+fn merge_adds_fields_and_keeps_existing_values() {
+    let mut body = serde_json::json!({
+        "model": "gemini-2.0-flash",
+        "contents": [{ "parts": [{ "text": "Hi" }] }],
+        "generationConfig": { "maxOutputTokens": 64 },
+    });
+    let extra = serde_json::json!({
+        "model": "other",
+        "contents": [],
+        "generationConfig": { "seed": 7, "maxOutputTokens": 1 },
+        "safetySettings": [{ "category": "HARM_CATEGORY_HATE_SPEECH" }],
+    });
+
+    let mut conflicts = merge(&mut body, extra.as_object().unwrap());
+    conflicts.sort();
+
+    assert_eq!(
+        conflicts,
+        vec!["contents", "generationConfig.maxOutputTokens", "model"]
+    );
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "model": "gemini-2.0-flash",
+            "contents": [{ "parts": [{ "text": "Hi" }] }],
+            "generationConfig": { "maxOutputTokens": 64, "seed": 7 },
+            "safetySettings": [{ "category": "HARM_CATEGORY_HATE_SPEECH" }],
+        })
+    );
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "perplexity")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{
+    MockJsonResponse, MockLLMServer, MockResponse, MockRoute, MockSseEvent, MockSseResponse,
+};
+use temp_env::with_var;
+use wire::api::{PerplexityModel, PromptCore, API};
+use wire::config::ClientOptions;
+use wire::perplexity::PerplexityClient;
+use wire::types::{Citations, MessageType, SearchResult};
+
+const PERPLEXITY_PATH: &str = "/chat/completions";
+
+#[cfg(feature = "mock")]
+fn expected_citations() -> Citations {
+    Citations {
+        urls: vec![
+            "https://example.com/paris".to_string(),
+            "https://example.org/weather".to_string(),
+        ],
+        search_results: vec![
+            SearchResult {
+                title: "Paris travel guide".to_string(),
+                url: "https://example.com/paris".to_string(),
+                date: Some("2025-05-01".to_string()),
+            },
+            SearchResult {
+                title: "Weather today".to_string(),
+                url: "https://example.org/weather".to_string(),
+                date: None,
+            },
+        ],
+    }
+}
+
+#[cfg(feature = "mock")]
+fn citation_fields() -> (serde_json::Value, serde_json::Value) {
+    (
+        serde_json::json!(["https://example.com/paris", "https://example.org/weather"]),
+        serde_json::json!([
+            {
+                "title": "Paris travel guide",
+                "url": "https://example.com/paris",
+                "date": "2025-05-01"
+            },
+            { "title": "Weather today", "url": "https://example.org/weather" }
+        ]),
+    )
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn perplexity_citations_survive_prompt_and_stream() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping perplexity integration test");
+        return;
+    }
+
+    with_var("PERPLEXITY_API_KEY", Some("mock-perplexity-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for perplexity test");
+
+        runtime.block_on(async {
+            let (urls, search_results) = citation_fields();
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                PERPLEXITY_PATH,
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "citations": urls,
+                        "search_results": search_results,
+                        "choices": [{ "message": { "content": "Sunny [1][2]." } }]
+                    }))),
+                    MockResponse::Sse(
+                        MockSseResponse::new(vec![
+                            MockSseEvent::data_json(serde_json::json!({
+                                "choices": [{ "delta": { "content": "Sunny" } }]
+                            })),
+                            MockSseEvent::data_json(serde_json::json!({
+                                "choices": [{ "delta": { "content": " [1][2]." } }]
+                            })),
+                            MockSseEvent::data_json(serde_json::json!({
+                                "citations": urls,
+                                "search_results": search_results,
+                                "choices": [{ "delta": {}, "finish_reason": "stop" }]
+                            })),
+                        ])
+                        .with_done(),
+                    ),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "No sources." } }]
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = PerplexityClient::try_with_options("sonar", options).expect("known model");
+
+            let reply = client
+                .prompt(
+                    "Cite your sources.".to_string(),
+                    vec![message(MessageType::User, "Weather in Paris?")],
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "Sunny [1][2].");
+            assert_eq!(reply.api, API::Perplexity(PerplexityModel::Sonar));
+            assert_eq!(reply.metadata.citations, Some(expected_citations()));
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Weather in Paris?")],
+                    "Cite your sources.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("stream completes");
+            assert_eq!(streamed.content, "Sunny [1][2].");
+            assert_eq!(streamed.metadata.citations, Some(expected_citations()));
+
+            let mut deltas = Vec::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push(delta);
+            }
+            assert_eq!(deltas, ["Sunny", " [1][2]."]);
+
+            let uncited = client
+                .prompt(
+                    "Cite your sources.".to_string(),
+                    vec![message(MessageType::User, "Say hi")],
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(uncited.metadata.citations, None);
+
+            let recorded = server.requests_for(PERPLEXITY_PATH).await;
+            assert_eq!(recorded.len(), 3);
+            for request in &recorded {
+                assert_eq!(
+                    request.headers.get("authorization").map(String::as_str),
+                    Some("Bearer mock-perplexity-key")
+                );
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("request body parses as json");
+                assert_eq!(body["model"], "sonar");
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
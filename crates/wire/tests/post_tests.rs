@@ -0,0 +1,240 @@
+mod common;
+
+use wire::api::{WireModel, API};
+use wire::config::PromptOptions;
+use wire::error::WireError;
+use wire::new_client;
+use wire::post::{apply, Post, PostError};
+use wire::types::{Message, MessageBuilder, MessageType};
+
+fn code_block(lang: Option<&str>) -> Post {
+    Post::ExtractFirstCodeBlock {
+        lang: lang.map(str::to_string),
+    }
+}
+
+fn user(content: &str) -> Message {
+    MessageBuilder::new(API::Wire(WireModel::Echo), content)
+        .message_type(MessageType::User)
+        .build()
+}
+
+#[test]
+fn extracts_the_first_fenced_block() {
+    let reply = "Try this:\n\n```\nls -la\n```\n\nor this:\n\n```\nls -l\n```\n";
+    assert_eq!(apply(&[code_block(None)], reply).unwrap(), "ls -la");
+}
+
+#[test]
+fn language_filter_skips_blocks_with_other_tags() {
+    let reply = concat!(
+        "```bash\ncargo new demo\n```\n",
+        "then\n",
+        "~~~ Rust title=\"main.rs\"\nfn main() {\n    println!(\"hi\");\n}\n~~~\n",
+    );
+
+    assert_eq!(
+        apply(&[code_block(Some("rust"))], reply).unwrap(),
+        "fn main() {\n    println!(\"hi\");\n}"
+    );
+    assert_eq!(apply(&[code_block(None)], reply).unwrap(), "cargo new demo");
+    assert_eq!(
+        apply(&[code_block(Some("python"))], reply),
+        Err(PostError::NoCodeBlock {
+            lang: Some("python".to_string())
+        })
+    );
+}
+
+#[test]
+fn untagged_blocks_only_match_without_a_filter() {
+    let reply = "```\nprint('hi')\n```\n";
+    assert_eq!(apply(&[code_block(None)], reply).unwrap(), "print('hi')");
+    assert!(apply(&[code_block(Some("python"))], reply).is_err());
+}
+
+#[test]
+fn nested_fences_stay_inside_the_outer_block() {
+    let reply = concat!(
+        "````markdown\n",
+        "Use a fence:\n",
+        "```rust\n",
+        "let x = 1;\n",
+        "```\n",
+        "````\n",
+        "```rust\nlet y = 2;\n```\n",
+    );
+
+    assert_eq!(
+        apply(&[code_block(Some("markdown"))], reply).unwrap(),
+        "Use a fence:\n```rust\nlet x = 1;\n```"
+    );
+    assert_eq!(
+        apply(&[code_block(Some("rust"))], reply).unwrap(),
+        "let y = 2;"
+    );
+}
+
+#[test]
+fn unclosed_block_runs_to_the_end() {
+    let reply = "```python\ndef f():\n    return 1\n";
+    assert_eq!(
+        apply(&[code_block(Some("python"))], reply).unwrap(),
+        "def f():\n    return 1"
+    );
+}
+
+#[test]
+fn missing_block_is_reported() {
+    let reply = "I can't write that code, but here is `inline` text.";
+    assert_eq!(
+        apply(&[code_block(None)], reply),
+        Err(PostError::NoCodeBlock { lang: None })
+    );
+    assert_eq!(
+        apply(&[code_block(None)], reply).unwrap_err().to_string(),
+        "no code block found"
+    );
+}
+
+#[test]
+fn crlf_line_breaks_are_kept_inside_the_block() {
+    let reply = "```\r\na\r\nb\r\n```\r\n";
+    assert_eq!(apply(&[code_block(None)], reply).unwrap(), "a\r\nb");
+}
+
+#[test]
+fn markdown_to_text_keeps_only_the_text() {
+    let reply = concat!(
+        "# Title #\n",
+        "\n",
+        "Some **bold**, *italic*, ~~gone~~ and `code` with snake_case_names.\n",
+        "> quoted [link](https://example.com) and ![alt text](img.png)\n",
+        "\n",
+        "---\n",
+        "- first\n",
+        "2. second\n",
+        "2 * 3 = 6\n",
+        "```rust\nlet **x** = 1;\n```\n",
+    );
+
+    assert_eq!(
+        apply(&[Post::MarkdownToText], reply).unwrap(),
+        concat!(
+            "Title\n",
+            "\n",
+            "Some bold, italic, gone and code with snake_case_names.\n",
+            "quoted link and alt text\n",
+            "\n",
+            "first\n",
+            "second\n",
+            "2 * 3 = 6\n",
+            "let **x** = 1;",
+        )
+    );
+}
+
+#[test]
+fn regex_capture_picks_a_group() {
+    let reply = "The answer is 42, final.";
+    let step = |pattern: &str, group| Post::RegexCapture {
+        pattern: pattern.to_string(),
+        group,
+    };
+
+    assert_eq!(apply(&[step(r"answer is (\d+)", 1)], reply).unwrap(), "42");
+    assert_eq!(
+        apply(&[step(r"answer is \d+", 0)], reply).unwrap(),
+        "answer is 42"
+    );
+    assert_eq!(
+        apply(&[step(r"total: (\d+)", 1)], reply),
+        Err(PostError::NoMatch {
+            pattern: r"total: (\d+)".to_string(),
+            group: 1
+        })
+    );
+    assert_eq!(
+        apply(&[step(r"answer (is)|(was)", 2)], reply),
+        Err(PostError::NoMatch {
+            pattern: r"answer (is)|(was)".to_string(),
+            group: 2
+        })
+    );
+    assert!(matches!(
+        apply(&[step(r"answer (\d+", 1)], reply),
+        Err(PostError::InvalidPattern { .. })
+    ));
+    assert!(matches!(
+        apply(&[step(r"answer", 1)], reply),
+        Err(PostError::InvalidPattern { .. })
+    ));
+}
+
+#[test]
+fn steps_run_in_order() {
+    let reply = "Result:\n```json\n  {\"value\": 7}  \n```\n";
+    let steps = [
+        code_block(Some("json")),
+        Post::Trim,
+        Post::RegexCapture {
+            pattern: r#""value": (\d+)"#.to_string(),
+            group: 1,
+        },
+    ];
+    assert_eq!(apply(&steps, reply).unwrap(), "7");
+    assert_eq!(apply(&[], reply).unwrap(), reply);
+}
+
+#[test]
+fn prompts_return_post_processed_content() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for post test");
+    let client = new_client("wire:echo").expect("echo client");
+
+    runtime.block_on(async {
+        let options = PromptOptions::new().with_post(vec![code_block(Some("sh")), Post::Trim]);
+        let reply = client
+            .prompt_with_options(
+                String::new(),
+                vec![user("Run:\n```sh\n  make test  \n```")],
+                &options,
+            )
+            .await
+            .expect("block found");
+        assert_eq!(reply.content, "make test");
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(32);
+        let streamed = client
+            .prompt_stream_with_options(
+                vec![user("Run:\n```sh\nmake\n```")],
+                String::new(),
+                tx,
+                &options,
+            )
+            .await
+            .expect("block found");
+        assert_eq!(streamed.content, "make");
+        let mut deltas = String::new();
+        while let Some(delta) = rx.recv().await {
+            deltas.push_str(&delta);
+        }
+        assert_eq!(deltas, "Run:\n```sh\nmake\n```");
+
+        let err = client
+            .prompt_with_options(String::new(), vec![user("No code here.")], &options)
+            .await
+            .expect_err("no block to extract");
+        match err {
+            WireError::PostProcess { error, content } => {
+                assert_eq!(
+                    error,
+                    PostError::NoCodeBlock {
+                        lang: Some("sh".to_string())
+                    }
+                );
+                assert_eq!(content, "No code here.");
+            }
+            other => panic!("expected a post-processing error, got {}", other),
+        }
+    });
+}
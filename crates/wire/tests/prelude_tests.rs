@@ -0,0 +1,121 @@
+//! Uses nothing from wire but `wire::prelude` (and the mock server), so a
+//! getting-started program that needs another import fails to compile here.
+
+use wire::prelude::*;
+
+fn shout(args: serde_json::Value) -> serde_json::Value {
+    serde_json::json!(args["text"].as_str().unwrap_or_default().to_uppercase())
+}
+
+fn shout_tool() -> Tool {
+    let spec = ToolSpec {
+        name: "shout".to_string(),
+        description: "Upper-case some text".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": { "text": { "type": "string" } }
+        }),
+        strict: false,
+    };
+
+    Tool::from_spec(spec, ToolWrapper(shout))
+}
+
+fn user(client: &dyn PromptCore, content: &str) -> Message {
+    client
+        .new_message(content.to_string())
+        .message_type(MessageType::User)
+        .build()
+}
+
+/// How a caller tells wire's own errors from transport failures.
+fn describe(error: &WireError) -> String {
+    match error {
+        WireError::UnsupportedOption { option, .. } => format!("unsupported {}", option),
+        WireError::Transport(_) | WireError::Io(_) => format!("transport: {}", error),
+        other => other.to_string(),
+    }
+}
+
+#[test]
+fn prelude_covers_an_offline_prompt_and_tool_loop() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for prelude test");
+    let api = API::from_model("wire:echo").expect("echo model resolves");
+    assert_eq!(api, API::Wire(WireModel::Echo));
+    assert_eq!(api.provider(), Provider::Wire);
+
+    let options = ClientOptions::default().with_max_tool_iterations(2);
+    let client = new_client_with_options("wire:echo", options).expect("echo client");
+
+    runtime.block_on(async {
+        let reply = client
+            .prompt_with_options(
+                "Be brief.".to_string(),
+                vec![user(client.as_ref(), "hello there")],
+                &PromptOptions::default(),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{}", describe(&err)));
+        assert_eq!(reply.content, "hello there");
+        assert_eq!(reply.message_type, MessageType::Assistant);
+
+        let history = client
+            .tools()
+            .expect("echo runs tools")
+            .prompt_with_tools(
+                "Use tools.",
+                vec![user(client.as_ref(), r#"CALL:shout:{"text": "hi"}"#)],
+                vec![shout_tool()],
+            )
+            .await
+            .expect("tool loop completes");
+        assert_eq!(history.last().map(|m| m.content.as_str()), Some(r#""HI""#));
+    });
+
+    assert!(matches!(
+        new_client("not-a-model"),
+        Err(WireError::UnknownModel { model, .. }) if model == "not-a-model"
+    ));
+}
+
+#[cfg(all(feature = "mock", feature = "openai"))]
+#[test]
+fn prelude_covers_a_mock_provider_prompt() {
+    use wire::mock::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping prelude integration test");
+        return;
+    }
+
+    temp_env::with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for prelude test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "mock reply" } }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = new_client_with_options("gpt-4o-mini", options).expect("openai client");
+
+            let reply = client
+                .prompt(
+                    "Stay friendly.".to_string(),
+                    vec![user(client.as_ref(), "Ping?")],
+                )
+                .await
+                .expect("prompt returns content");
+            assert_eq!(reply.content, "mock reply");
+            assert_eq!(reply.api, API::OpenAI(OpenAIModel::GPT4oMini));
+
+            server.shutdown().await;
+        });
+    });
+}
@@ -0,0 +1,309 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute, TestClock};
+use common::sample_tool;
+use temp_env::with_var;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::ClientOptions;
+use wire::openai::OpenAIClient;
+use wire::rate_limit::RateLimiter;
+use wire::tool_loop::ToolStatus;
+use wire::types::MessageType;
+
+const CHAT: &str = "/v1/chat/completions";
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn requests_are_spaced_once_the_burst_is_spent() {
+    let limiter = RateLimiter::new().with_requests_per_minute(2);
+    let start = Instant::now();
+
+    // A minute's allowance goes at once, then one every 30 seconds
+    let waits: Vec<_> = (0..5).map(|_| limiter.reserve(start, 0)).collect();
+    assert_eq!(waits, [secs(0), secs(0), secs(30), secs(60), secs(90)]);
+
+    // Time that passes pays the queue back
+    assert_eq!(limiter.reserve(start + secs(90), 0), secs(30));
+    assert_eq!(limiter.reserve(start + secs(300), 0), secs(0));
+}
+
+#[test]
+fn tokens_are_budgeted_with_requests() {
+    let limiter = RateLimiter::new()
+        .with_requests_per_minute(100)
+        .with_tokens_per_minute(1000);
+    let start = Instant::now();
+
+    assert_eq!(limiter.reserve(start, 800), secs(0));
+    assert_eq!(limiter.reserve(start, 400), secs(12));
+    // More than a minute's allowance waits no longer than a minute's
+    let limiter = RateLimiter::new().with_tokens_per_minute(1000);
+    assert_eq!(limiter.reserve(start, 1000), secs(0));
+    assert_eq!(limiter.reserve(start, 50_000), secs(60));
+}
+
+#[test]
+fn clones_share_a_budget() {
+    let limiter = RateLimiter::new().with_requests_per_minute(1);
+    let start = Instant::now();
+
+    let first = ClientOptions::default().with_rate_limiter(limiter.clone());
+    let second = first.clone();
+    let reserve = |options: &ClientOptions| {
+        options
+            .rate_limiter
+            .as_ref()
+            .expect("options keep the limiter")
+            .reserve(start, 0)
+    };
+    assert_eq!(reserve(&first), secs(0));
+    assert_eq!(reserve(&second), secs(60));
+    assert_eq!(limiter.reserve(start, 0), secs(120));
+
+    let unlimited = RateLimiter::new();
+    assert!((0..100).all(|_| unlimited.reserve(start, 1_000_000).is_zero()));
+}
+
+#[test]
+fn rate_limited_status_says_how_long() {
+    let status = ToolStatus::RateLimited {
+        wait: Duration::from_millis(1500),
+    };
+    assert_eq!(status.to_string(), "rate limited, waiting 1.5s");
+}
+
+#[cfg(feature = "mock")]
+fn reply(content: &str) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{ "message": { "content": content } }]
+    })))
+}
+
+/// Wait (in real time) until `sleeps` requests are waiting on `clock`.
+#[cfg(feature = "mock")]
+async fn wait_for_sleeps(clock: &TestClock, sleeps: usize) {
+    for _ in 0..5000 {
+        if clock.pending_sleeps() == sleeps {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    panic!("expected {} waiting requests", sleeps);
+}
+
+/// Run `test` against a mock server holding `routes`, with options for it
+/// that use `clock` and `limiter`.
+#[cfg(feature = "mock")]
+fn with_limited_options<F, Fut>(
+    routes: Vec<MockRoute>,
+    clock: TestClock,
+    limiter: RateLimiter,
+    test: F,
+) where
+    F: FnOnce(ClientOptions, Arc<MockLLMServer>) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for rate limit test");
+
+        runtime.block_on(async {
+            let server = Arc::new(
+                MockLLMServer::start(routes)
+                    .await
+                    .expect("mock server starts"),
+            );
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_clock(clock)
+                .with_rate_limiter(limiter);
+
+            test(options, server.clone()).await;
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn concurrent_prompts_queue_instead_of_failing() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping rate limit integration test");
+        return;
+    }
+
+    let clock = TestClock::new();
+    with_limited_options(
+        vec![MockRoute::new(
+            CHAT,
+            (0..5).map(|_| reply("Queued.")).collect(),
+        )],
+        clock.clone(),
+        RateLimiter::new().with_requests_per_minute(2),
+        |options, server| async move {
+            // Two clients from the same options draw on one budget
+            let clients = [
+                Arc::new(
+                    OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                        .expect("known model"),
+                ),
+                Arc::new(
+                    OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model"),
+                ),
+            ];
+            let prompts: Vec<_> = (0..5)
+                .map(|index| {
+                    let client = clients[index % 2].clone();
+                    tokio::spawn(async move {
+                        client
+                            .prompt(
+                                "Be brief.".to_string(),
+                                vec![message(MessageType::User, "Hi")],
+                            )
+                            .await
+                    })
+                })
+                .collect();
+
+            wait_for_sleeps(&clock, 3).await;
+            server
+                .wait_for_requests(CHAT, 2, secs(5))
+                .await
+                .expect("two requests go at once");
+            for sent in 3..=5 {
+                clock.advance(Duration::from_millis(29_999));
+                assert_eq!(server.requests_for(CHAT).await.len(), sent - 1);
+                clock.advance(Duration::from_millis(1));
+                server
+                    .wait_for_requests(CHAT, sent, secs(5))
+                    .await
+                    .expect("the next request goes once its turn comes");
+            }
+
+            for prompt in prompts {
+                let reply = prompt
+                    .await
+                    .expect("prompt task completes")
+                    .expect("queued prompts succeed");
+                assert_eq!(reply.content, "Queued.");
+            }
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn streams_and_tool_turns_wait_their_turn() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping rate limit integration test");
+        return;
+    }
+
+    let tool_call = MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{
+            "message": {
+                "content": null,
+                "tool_calls": [{
+                    "id": "call-1",
+                    "type": "function",
+                    "function": { "name": "lookup_weather", "arguments": "{}" }
+                }]
+            }
+        }]
+    })));
+
+    let clock = TestClock::new();
+    with_limited_options(
+        vec![MockRoute::new(
+            CHAT,
+            vec![
+                MockResponse::openai_text_stream(["Streamed."]),
+                tool_call,
+                reply("Sunny."),
+            ],
+        )],
+        clock.clone(),
+        RateLimiter::new().with_requests_per_minute(1),
+        |options, server| async move {
+            let client = Arc::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model"),
+            );
+
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Hi")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("the first request goes at once");
+            assert_eq!(streamed.content, "Streamed.");
+
+            let (status_tx, mut status_rx) = tokio::sync::mpsc::channel(8);
+            let looped = tokio::spawn({
+                let client = client.clone();
+                async move {
+                    client
+                        .run_tool_loop(
+                            Some(status_tx),
+                            "Use tools.",
+                            vec![message(MessageType::User, "Weather?")],
+                            vec![sample_tool("lookup_weather")],
+                        )
+                        .await
+                }
+            });
+
+            // Each model turn of the loop waits a minute behind the last
+            for sent in 2..=3 {
+                wait_for_sleeps(&clock, 1).await;
+                let mut reported = Vec::new();
+                while let Ok(line) = status_rx.try_recv() {
+                    reported.push(line);
+                }
+                assert_eq!(
+                    reported.last().map(String::as_str),
+                    Some("rate limited, waiting 60.0s")
+                );
+                assert_eq!(server.requests_for(CHAT).await.len(), sent - 1);
+                clock.advance(secs(60));
+                server
+                    .wait_for_requests(CHAT, sent, secs(5))
+                    .await
+                    .expect("the next request goes once its turn comes");
+            }
+
+            let result = looped
+                .await
+                .expect("tool loop task completes")
+                .expect("the tool loop finishes");
+            assert_eq!(
+                result
+                    .messages
+                    .last()
+                    .map(|message| message.content.as_str()),
+                Some("Sunny.")
+            );
+            let waits: Vec<_> = result
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    ToolStatus::RateLimited { wait } => Some(*wait),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(waits, [secs(60), secs(60)]);
+        },
+    );
+}
@@ -0,0 +1,166 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, run_mock_test};
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::openai::OpenAIClient;
+use wire::types::MessageType;
+
+const ENTRY: &str = "/v1/chat/completions";
+const REGION: &str = "/region-b/v1/chat/completions";
+
+/// `ENTRY` answers with `redirect`; `REGION` answers with `response`.
+async fn gateway(redirect: MockResponse, response: MockResponse) -> MockLLMServer {
+    MockLLMServer::start(vec![
+        MockRoute::single(ENTRY, redirect),
+        MockRoute::single(REGION, response),
+    ])
+    .await
+    .expect("mock server starts")
+}
+
+fn client(
+    server: &MockLLMServer,
+    options: impl FnOnce(ClientOptions) -> ClientOptions,
+) -> OpenAIClient {
+    let options =
+        options(ClientOptions::for_mock_server(server).expect("client options for mock server"));
+    OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model")
+}
+
+#[test]
+fn prompt_follows_307_with_body() {
+    run_mock_test("non-streaming redirect test", async {
+        let server = gateway(
+            MockResponse::redirect(307, REGION),
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "choices": [{ "message": { "content": "from region b" } }]
+            }))),
+        )
+        .await;
+
+        let reply = client(&server, |options| options)
+            .prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hello")],
+            )
+            .await
+            .expect("redirect is followed");
+        assert_eq!(reply.content, "from region b");
+
+        let entry = server.requests_for(ENTRY).await;
+        let region = server.requests_for(REGION).await;
+        assert_eq!(region.len(), 1);
+        assert_eq!(region[0].method, "POST");
+        assert_eq!(region[0].body, entry[0].body);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn prompt_stream_follows_307_with_body() {
+    run_mock_test("streaming redirect test", async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            REGION,
+            MockResponse::openai_text_stream(["from ", "region b"]),
+        )])
+        .await
+        .expect("mock server starts");
+        // An absolute Location, as gateways usually send
+        let location = format!("{}{}", server.base_url().trim_end_matches('/'), REGION);
+        let server_with_entry = gateway(
+            MockResponse::redirect(307, location),
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({}))),
+        )
+        .await;
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let reply = client(&server_with_entry, |options| options)
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect("redirect is followed");
+        assert_eq!(reply.content, "from region b");
+
+        let entry = server_with_entry.requests_for(ENTRY).await;
+        let region = server.requests_for(REGION).await;
+        assert_eq!(region.len(), 1);
+        assert_eq!(region[0].body, entry[0].body);
+        assert_eq!(
+            region[0].headers.get("host").map(String::as_str),
+            Some(server.address().to_string().as_str())
+        );
+        // Another origin never sees the API key
+        assert!(entry[0].headers.contains_key("authorization"));
+        assert!(!region[0].headers.contains_key("authorization"));
+
+        server_with_entry.shutdown().await;
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn redirect_limits_apply_to_both_paths() {
+    run_mock_test("redirect limit test", async {
+        let server = gateway(
+            MockResponse::redirect(307, REGION),
+            MockResponse::openai_text_stream(["unreachable"]),
+        )
+        .await;
+        let client = client(&server, |options| options.with_max_redirects(0));
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let err = client
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect_err("redirect limit is enforced");
+        assert!(err.to_string().contains(REGION), "{}", err);
+
+        client
+            .prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hello")],
+            )
+            .await
+            .expect_err("redirect limit is enforced");
+
+        assert!(server.requests_for(REGION).await.is_empty());
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn streaming_refuses_method_changing_redirects() {
+    run_mock_test("streaming 303 test", async {
+        let server = gateway(
+            MockResponse::redirect(303, REGION),
+            MockResponse::openai_text_stream(["unreachable"]),
+        )
+        .await;
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let err = client(&server, |options| options)
+            .prompt_stream(
+                vec![message(MessageType::User, "Hello")],
+                "Be brief.".to_string(),
+                tx,
+            )
+            .await
+            .expect_err("303 is not followed");
+        assert!(err.to_string().contains("HTTP 303"), "{}", err);
+        assert!(err.to_string().contains(REGION), "{}", err);
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,153 @@
+#![cfg(all(feature = "openai", feature = "anthropic"))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports))]
+
+mod common;
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::openai::OpenAIClient;
+use wire::request_size::BodyPart;
+use wire::types::{Message, MessageType};
+
+const LIMIT: usize = 4 * 1024;
+
+/// A short question, a long pasted log, and another short question.
+fn history() -> Vec<Message> {
+    vec![
+        message(MessageType::User, "What went wrong?"),
+        message(MessageType::Assistant, &"log line\n".repeat(1000)),
+        message(MessageType::User, "And how do I fix it?"),
+    ]
+}
+
+/// Nothing listens on the discard port, so a request that got past the check
+/// would fail with a connection error instead.
+fn options() -> ClientOptions {
+    ClientOptions::from_base_url("http://127.0.0.1:9")
+        .unwrap()
+        .with_max_request_bytes(Some(LIMIT))
+}
+
+fn too_large(error: WireError) -> (usize, usize, Vec<BodyPart>) {
+    match error {
+        WireError::RequestTooLarge {
+            bytes,
+            limit,
+            largest,
+        } => (bytes, limit, largest),
+        other => panic!("expected RequestTooLarge, got {}", other),
+    }
+}
+
+#[test]
+fn oversized_requests_fail_before_sending_with_the_largest_parts() {
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("key")),
+            ("ANTHROPIC_API_KEY", Some("key")),
+        ],
+        || {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for request size test");
+            runtime.block_on(async {
+                let openai = OpenAIClient::try_with_options("gpt-4o-mini", options()).expect("known model");
+                let error = openai
+                    .prompt("Be brief.".to_string(), history())
+                    .await
+                    .expect_err("the body is over the limit");
+                let (bytes, limit, largest) = too_large(error);
+                assert!(bytes > LIMIT);
+                assert_eq!(limit, LIMIT);
+                // The system prompt is messages[0]
+                assert_eq!(largest[0].path, "messages[2]");
+                assert!(largest[0].bytes > 9000 && largest[0].bytes < bytes);
+                assert!(largest.len() <= 5);
+                assert!(largest.windows(2).all(|pair| pair[0].bytes >= pair[1].bytes));
+
+                let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                let error = openai
+                    .prompt_stream(history(), "Be brief.".to_string(), tx)
+                    .await
+                    .expect_err("the streamed body is over the limit");
+                let (streamed_bytes, _, streamed_largest) = too_large(error);
+                assert!(streamed_bytes > LIMIT);
+                assert_eq!(streamed_largest[0].path, "messages[2]");
+
+                let anthropic = AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options()).expect("known model");
+                let (tx, _rx) = tokio::sync::mpsc::channel(8);
+                let error = anthropic
+                    .prompt_stream(history(), "Be brief.".to_string(), tx)
+                    .await
+                    .expect_err("the streamed body is over the limit");
+                let (_, _, largest) = too_large(error);
+                // The system prompt is a field of its own
+                assert_eq!(largest[0].path, "messages[1]");
+                let message = WireError::RequestTooLarge {
+                    bytes: 5000,
+                    limit: LIMIT,
+                    largest: largest[..1].to_vec(),
+                }
+                .to_string();
+                assert_eq!(
+                    message,
+                    format!(
+                        "request body of 5000 bytes is over the 4096 byte limit; largest: messages[1] ({} bytes)",
+                        largest[0].bytes
+                    )
+                );
+            });
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn oversized_requests_never_reach_the_server() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping request size integration test");
+        return;
+    }
+
+    with_vars([("OPENAI_API_KEY", Some("mock-openai-key"))], || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for request size test");
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "Restart it." } }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_max_request_bytes(Some(LIMIT));
+
+            let client = OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                .expect("known model");
+            let error = client
+                .prompt("Be brief.".to_string(), history())
+                .await
+                .expect_err("the body is over the limit");
+            too_large(error);
+            assert!(server.requests_for("/v1/chat/completions").await.is_empty());
+
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options.with_max_request_bytes(None))
+                    .expect("known model");
+            let reply = client
+                .prompt("Be brief.".to_string(), history())
+                .await
+                .expect("no limit, so the body is sent");
+            assert_eq!(reply.content, "Restart it.");
+            assert_eq!(server.requests_for("/v1/chat/completions").await.len(), 1);
+
+            server.shutdown().await;
+        });
+    });
+}
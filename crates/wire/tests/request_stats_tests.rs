@@ -0,0 +1,154 @@
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, sample_tool};
+use temp_env::with_var;
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::ClientOptions;
+use wire::metrics::{RequestStats, DEFAULT_TOOL_SCHEMA_WARNING_RATIO};
+use wire::openai::OpenAIClient;
+use wire::types::{MessageType, Tool, ToolSpec};
+use wire::warning::WireWarning;
+
+/// Twenty tools with realistic, moderately sized schemas.
+fn tool_catalog() -> Vec<ToolSpec> {
+    (0..20)
+        .map(|i| ToolSpec {
+            name: format!("tool_{:02}", i),
+            description: format!("Look up record type {} by id and return its fields", i),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "description": "Record identifier" },
+                    "fields": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Fields to include in the response"
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 100 }
+                },
+                "required": ["id"]
+            }),
+            strict: false,
+        })
+        .collect()
+}
+
+fn history() -> Vec<wire::types::Message> {
+    vec![
+        message(MessageType::User, "Find record 42 of type 3."),
+        message(MessageType::Assistant, "Looking it up now."),
+    ]
+}
+
+#[test]
+fn request_stats_split_tool_schemas_from_messages() {
+    with_var("OPENAI_API_KEY", Some("stats-key"), || {
+        let client = OpenAIClient::try_new("gpt-4o-mini").expect("known model");
+        let tools = tool_catalog();
+
+        let without_tools = client.stats("Be brief.", &history(), None);
+        let with_tools = client.stats("Be brief.", &history(), Some(&tools));
+
+        assert_eq!(without_tools.tool_schema_bytes, 0);
+        assert_eq!(with_tools.message_bytes, without_tools.message_bytes);
+        assert_eq!(with_tools.message_count, 2);
+
+        let request = client
+            .build_request("Be brief.".to_string(), history(), Some(&tools), false)
+            .expect("request builds")
+            .build()
+            .expect("request builds");
+        let body_len = request.body().unwrap().as_bytes().unwrap().len();
+        assert_eq!(with_tools.total_bytes(), body_len);
+        assert_eq!(with_tools.estimated_tokens, body_len.div_ceil(4));
+
+        // twenty schemas dwarf a two-message conversation
+        assert!(with_tools.tool_schema_bytes > with_tools.message_bytes * 5);
+        assert!(with_tools.tool_schema_ratio() > DEFAULT_TOOL_SCHEMA_WARNING_RATIO);
+    });
+}
+
+#[test]
+fn request_stats_follow_provider_payloads() {
+    with_var("ANTHROPIC_API_KEY", Some("stats-key"), || {
+        let client = AnthropicClient::try_new("claude-3-5-haiku-20241022").expect("known model");
+        let tools = tool_catalog();
+
+        let stats = client.stats("Be brief.", &history(), Some(&tools));
+        let single = client.stats("Be brief.", &history(), Some(&tools[..1]));
+
+        assert!(stats.tool_schema_bytes > single.tool_schema_bytes * 15);
+        assert_eq!(stats.message_bytes, single.message_bytes);
+    });
+}
+
+#[test]
+fn request_stats_ratio_handles_empty_requests() {
+    let stats = RequestStats::default();
+
+    assert_eq!(stats.total_bytes(), 0);
+    assert_eq!(stats.tool_schema_ratio(), 0.0);
+}
+
+#[test]
+fn tool_loop_warns_about_oversized_tool_schemas() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool schema warning test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for stats test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "Done." } }]
+                }))),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_tool_schema_warning(DEFAULT_TOOL_SCHEMA_WARNING_RATIO);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let tools: Vec<Tool> = tool_catalog()
+                .into_iter()
+                .map(|spec| {
+                    let mut tool = sample_tool(&spec.name);
+                    tool.description = spec.description;
+                    tool.parameters = spec.parameters;
+                    tool
+                })
+                .collect();
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let messages = client
+                .prompt_with_tools_with_status(tx, "Be brief.", history(), tools)
+                .await
+                .expect("tool loop completes");
+            let reply = messages.last().expect("loop returns the reply");
+            assert!(matches!(
+                reply.metadata.warnings.as_slice(),
+                [WireWarning::ToolSchemasLarge { .. }]
+            ));
+
+            let warning = rx.recv().await.expect("warning sent");
+            assert!(
+                warning.starts_with("warn: tool schemas are "),
+                "unexpected status: {}",
+                warning
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
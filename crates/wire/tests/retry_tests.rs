@@ -0,0 +1,528 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute, TestClock};
+use common::sample_tool;
+use temp_env::with_var;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::ClientOptions;
+use wire::error::WireError;
+use wire::openai::OpenAIClient;
+use wire::retry::{parse_retry_after, RetryPolicy};
+use wire::tool_loop::ToolStatus;
+use wire::types::{MessageType, Tool, ToolWrapper};
+
+const CHAT: &str = "/v1/chat/completions";
+
+/// Retries without waiting to speak of.
+fn quick(max_attempts: usize) -> RetryPolicy {
+    RetryPolicy::new(max_attempts)
+        .with_base_delay(Duration::from_millis(1))
+        .with_jitter(0.0)
+}
+
+fn http(status: u16) -> WireError {
+    WireError::http(status, CHAT, String::new())
+}
+
+#[test]
+fn transient_errors_are_retried_by_default() {
+    for status in [408, 429, 500, 502, 503, 504, 529] {
+        assert!(RetryPolicy::is_transient(&http(status)), "{}", status);
+    }
+    for status in [400, 401, 403, 404, 422] {
+        assert!(!RetryPolicy::is_transient(&http(status)), "{}", status);
+    }
+
+    let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+    assert!(RetryPolicy::is_transient(&WireError::Io(reset)));
+    assert!(!RetryPolicy::is_transient(&WireError::StreamClosed));
+
+    let policy = RetryPolicy::default();
+    assert_eq!(policy.max_attempts, 3);
+    assert!(policy.should_retry(&http(503)));
+
+    let policy = policy.with_retry_on(|error| matches!(error, WireError::Http { status: 400, .. }));
+    assert!(policy.should_retry(&http(400)));
+    assert!(!policy.should_retry(&http(503)));
+}
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    let policy = RetryPolicy::new(0)
+        .with_base_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_millis(500))
+        .with_jitter(3.0);
+    assert_eq!(policy.max_attempts, 1);
+    assert_eq!(policy.jitter, 1.0);
+
+    let delays: Vec<_> = (1..=5).map(|retry| policy.backoff(retry)).collect();
+    assert_eq!(delays, [100, 200, 400, 500, 500].map(Duration::from_millis));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(500));
+}
+
+#[test]
+fn providers_say_how_long_to_wait() {
+    let wait = |headers: &[(&str, &str)]| parse_retry_after(headers.iter().copied());
+
+    assert_eq!(wait(&[("Retry-After", "3")]), Some(Duration::from_secs(3)));
+    assert_eq!(
+        wait(&[("retry-after", "0.5")]),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(
+        wait(&[("retry-after", "3"), ("retry-after-ms", "1250")]),
+        Some(Duration::from_millis(1250))
+    );
+    assert_eq!(
+        wait(&[
+            ("x-ratelimit-reset-requests", "1m30s"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ]),
+        Some(Duration::from_secs(360))
+    );
+    assert_eq!(
+        wait(&[("x-ratelimit-reset-tokens", "20ms"), ("retry-after", "2")]),
+        Some(Duration::from_secs(2))
+    );
+
+    // A date is waited for until it comes, not at all once it has passed
+    assert_eq!(
+        wait(&[("Retry-After", "Sun, 06 Nov 1994 08:49:37 GMT")]),
+        Some(Duration::ZERO)
+    );
+    let far = wait(&[("Retry-After", "Fri, 31 Dec 9999 23:59:59 GMT")]).expect("a date parses");
+    assert!(far > Duration::from_secs(3600 * 24 * 365 * 7000));
+
+    assert_eq!(wait(&[("Retry-After", "soon")]), None);
+    assert_eq!(wait(&[("x-ratelimit-reset-tokens", "6 minutes")]), None);
+    assert_eq!(wait(&[("content-type", "application/json")]), None);
+
+    let status = ToolStatus::Retrying {
+        retry: 2,
+        wait: Duration::from_millis(1500),
+        reason: "HTTP 429 from /v1/chat/completions".to_string(),
+    };
+    assert_eq!(
+        status.to_string(),
+        "retry 2 in 1.5s: HTTP 429 from /v1/chat/completions"
+    );
+}
+
+#[cfg(feature = "mock")]
+fn failure(status: u16) -> MockResponse {
+    MockResponse::Json(
+        MockJsonResponse::new(serde_json::json!({
+            "error": { "message": format!("status {}", status), "type": "server_error" }
+        }))
+        .with_status(status),
+    )
+}
+
+#[cfg(feature = "mock")]
+fn rate_limited(retry_after: &str) -> MockResponse {
+    MockResponse::Json(
+        MockJsonResponse::new(serde_json::json!({
+            "error": { "message": "Rate limit reached", "type": "rate_limit_error" }
+        }))
+        .with_status(429)
+        .with_header("Retry-After", retry_after),
+    )
+}
+
+#[cfg(feature = "mock")]
+fn reply(content: &str) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{ "message": { "content": content } }]
+    })))
+}
+
+#[cfg(feature = "mock")]
+fn tool_call() -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{
+            "message": {
+                "content": null,
+                "tool_calls": [{
+                    "id": "call-1",
+                    "type": "function",
+                    "function": { "name": "count", "arguments": "{}" }
+                }]
+            }
+        }]
+    })))
+}
+
+/// Run `test` against a mock server holding `routes`, with a client built
+/// from the server's options passed through `configure`.
+#[cfg(feature = "mock")]
+fn with_mock_client<F, Fut>(
+    routes: Vec<MockRoute>,
+    configure: impl FnOnce(ClientOptions) -> ClientOptions,
+    test: F,
+) where
+    F: FnOnce(OpenAIClient, Arc<MockLLMServer>) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for retry test");
+
+        runtime.block_on(async {
+            let server = Arc::new(
+                MockLLMServer::start(routes)
+                    .await
+                    .expect("mock server starts"),
+            );
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client = OpenAIClient::try_with_options("gpt-4o-mini", configure(options))
+                .expect("known model");
+
+            test(client, server.clone()).await;
+            server.shutdown().await;
+        });
+    });
+}
+
+#[cfg(feature = "mock")]
+async fn ask(client: &OpenAIClient) -> Result<wire::Message, WireError> {
+    client
+        .prompt(
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "Hi")],
+        )
+        .await
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn server_errors_are_retried_until_one_succeeds() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![failure(500), failure(502), reply("Recovered.")],
+        )],
+        |options| options.with_retry_policy(quick(3)),
+        |client, server| async move {
+            let reply = ask(&client).await.expect("the third attempt succeeds");
+            assert_eq!(reply.content, "Recovered.");
+            assert_eq!(server.requests_for(CHAT).await.len(), 3);
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn attempts_are_capped() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    with_mock_client(
+        vec![MockRoute::single(CHAT, failure(503))],
+        |options| options.with_retry_policy(quick(4)),
+        |client, server| async move {
+            let error = ask(&client).await.expect_err("every attempt fails");
+            assert!(matches!(error, WireError::Http { status: 503, .. }));
+            assert_eq!(server.requests_for(CHAT).await.len(), 4);
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn only_retryable_errors_are_retried() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    // Without a policy a request is sent once
+    with_mock_client(
+        vec![MockRoute::new(CHAT, vec![failure(500), reply("Too late.")])],
+        |options| options,
+        |client, server| async move {
+            let error = ask(&client).await.expect_err("no second attempt");
+            assert!(matches!(error, WireError::Http { status: 500, .. }));
+            assert_eq!(server.requests_for(CHAT).await.len(), 1);
+        },
+    );
+
+    // A bad request fails the same way every time
+    with_mock_client(
+        vec![MockRoute::new(CHAT, vec![failure(400), reply("Too late.")])],
+        |options| options.with_retry_policy(quick(3)),
+        |client, server| async move {
+            let error = ask(&client).await.expect_err("a 400 is not retried");
+            assert!(matches!(error, WireError::Http { status: 400, .. }));
+            assert_eq!(server.requests_for(CHAT).await.len(), 1);
+        },
+    );
+
+    // Unless the policy says otherwise
+    with_mock_client(
+        vec![MockRoute::new(CHAT, vec![failure(400), reply("Accepted.")])],
+        |options| {
+            options.with_retry_policy(
+                quick(3).with_retry_on(|error| matches!(error, WireError::Http { .. })),
+            )
+        },
+        |client, server| async move {
+            let reply = ask(&client).await.expect("the predicate retries a 400");
+            assert_eq!(reply.content, "Accepted.");
+            assert_eq!(server.requests_for(CHAT).await.len(), 2);
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn streams_retry_their_connection() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![
+                failure(429),
+                MockResponse::openai_text_stream(["Streamed ", "again."]),
+            ],
+        )],
+        |options| options.with_retry_policy(quick(2)),
+        |client, server| async move {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let reply = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Hi")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("the second connection streams");
+            assert_eq!(reply.content, "Streamed again.");
+
+            let mut deltas = String::new();
+            while let Some(delta) = rx.recv().await {
+                deltas.push_str(&delta);
+            }
+            assert_eq!(deltas, "Streamed again.");
+            assert_eq!(server.requests_for(CHAT).await.len(), 2);
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn tools_that_ran_are_not_run_again() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let tool = {
+        let runs = runs.clone();
+        Tool::from_spec(
+            sample_tool("count").spec(),
+            ToolWrapper(move |_| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                serde_json::json!("counted")
+            }),
+        )
+    };
+
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![
+                tool_call(),
+                failure(500),
+                rate_limited("0.05"),
+                reply("Counted once."),
+            ],
+        )],
+        |options| options.with_retry_policy(quick(3)),
+        |client, server| async move {
+            let result = client
+                .run_tool_loop(
+                    None,
+                    "Use tools.",
+                    vec![message(MessageType::User, "Count")],
+                    vec![tool],
+                )
+                .await
+                .expect("the tool loop recovers");
+            assert_eq!(
+                result
+                    .messages
+                    .last()
+                    .map(|message| message.content.as_str()),
+                Some("Counted once.")
+            );
+            assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+            // Each wait is reported, the second as long as the provider asked
+            let waits: Vec<_> = result
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    ToolStatus::Retrying { retry, wait, .. } => Some((*retry, *wait)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                waits,
+                [
+                    (1, Duration::from_millis(1)),
+                    (2, Duration::from_millis(50))
+                ]
+            );
+
+            // The retries resend the turn carrying the tool's output
+            let requests = server.requests_for(CHAT).await;
+            assert_eq!(requests.len(), 4);
+            assert_eq!(requests[1].body, requests[3].body);
+        },
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn waits_follow_the_client_clock() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    let clock = TestClock::new();
+    let policy = RetryPolicy::new(3)
+        .with_base_delay(Duration::from_secs(1))
+        .with_jitter(0.0);
+
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![failure(500), failure(500), reply("Patient.")],
+        )],
+        {
+            let clock = clock.clone();
+            |options| options.with_clock(clock).with_retry_policy(policy)
+        },
+        |client, server| async move {
+            let client = Arc::new(client);
+            let prompt = tokio::spawn({
+                let client = client.clone();
+                async move { ask(&client).await }
+            });
+
+            wait_for_sleep(&clock).await;
+            assert_eq!(server.requests_for(CHAT).await.len(), 1);
+            clock.advance(Duration::from_secs(1));
+
+            // The second wait is twice the first
+            wait_for_sleep(&clock).await;
+            assert_eq!(server.requests_for(CHAT).await.len(), 2);
+            clock.advance(Duration::from_millis(1999));
+            assert_eq!(clock.pending_sleeps(), 1);
+            clock.advance(Duration::from_millis(1));
+
+            let reply = prompt
+                .await
+                .expect("prompt task completes")
+                .expect("the third attempt succeeds");
+            assert_eq!(reply.content, "Patient.");
+            assert_eq!(server.requests_for(CHAT).await.len(), 3);
+        },
+    );
+}
+
+/// Wait (in real time) until the client is sleeping on `clock`.
+#[cfg(feature = "mock")]
+async fn wait_for_sleep(clock: &TestClock) {
+    for _ in 0..5000 {
+        if clock.pending_sleeps() == 1 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    panic!("the client never slept");
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn rate_limits_wait_as_long_as_asked() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping retry integration test");
+        return;
+    }
+
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![
+                rate_limited("1"),
+                reply("Waited."),
+                rate_limited("1"),
+                MockResponse::openai_text_stream(["Waited ", "too."]),
+            ],
+        )],
+        |options| options.with_retry_policy(quick(2)),
+        |client, server| async move {
+            let started = std::time::Instant::now();
+            let reply = ask(&client).await.expect("the retry succeeds");
+            assert_eq!(reply.content, "Waited.");
+            let elapsed = started.elapsed();
+            assert!(elapsed >= Duration::from_secs(1), "{:?}", elapsed);
+            assert!(elapsed < Duration::from_secs(3), "{:?}", elapsed);
+
+            // Streams read the header off the raw response
+            let started = std::time::Instant::now();
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let reply = client
+                .prompt_stream(
+                    vec![message(MessageType::User, "Hi")],
+                    "Be brief.".to_string(),
+                    tx,
+                )
+                .await
+                .expect("the retried stream succeeds");
+            assert_eq!(reply.content, "Waited too.");
+            assert!(started.elapsed() >= Duration::from_secs(1));
+            assert_eq!(server.requests_for(CHAT).await.len(), 4);
+        },
+    );
+
+    // A wait past the policy's ceiling is cut short
+    with_mock_client(
+        vec![MockRoute::new(
+            CHAT,
+            vec![rate_limited("3600"), reply("Not an hour.")],
+        )],
+        |options| {
+            options.with_retry_policy(quick(2).with_max_retry_after(Duration::from_millis(10)))
+        },
+        |client, _| async move {
+            let started = std::time::Instant::now();
+            let reply = ask(&client).await.expect("the retry succeeds");
+            assert_eq!(reply.content, "Not an hour.");
+            assert!(started.elapsed() < Duration::from_secs(3));
+        },
+    );
+}
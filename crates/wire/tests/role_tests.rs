@@ -0,0 +1,266 @@
+#![cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+
+mod common;
+
+use common::{function_call, message, request_body_json};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::{role_for, PromptCore, Provider};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{Message, MessageType};
+
+const MESSAGE_TYPES: [MessageType; 5] = [
+    MessageType::System,
+    MessageType::User,
+    MessageType::Assistant,
+    MessageType::FunctionCall,
+    MessageType::FunctionCallOutput,
+];
+
+/// One message of every type, in `MESSAGE_TYPES` order.
+fn every_message_type() -> Vec<Message> {
+    MESSAGE_TYPES
+        .iter()
+        .map(|message_type| {
+            let mut m = message(*message_type, "content");
+            match message_type {
+                MessageType::FunctionCall => {
+                    m.tool_calls = Some(vec![function_call(
+                        "call-1",
+                        "lookup_weather",
+                        serde_json::json!({}),
+                    )]);
+                }
+                MessageType::FunctionCallOutput => {
+                    m.tool_call_id = Some("call-1".to_string());
+                    m.name = Some("lookup_weather".to_string());
+                }
+                _ => {}
+            }
+            m
+        })
+        .collect()
+}
+
+fn roles(messages: &serde_json::Value) -> Vec<&str> {
+    messages
+        .as_array()
+        .expect("message array")
+        .iter()
+        .map(|m| m["role"].as_str().expect("role is a string"))
+        .collect()
+}
+
+#[test]
+fn role_for_covers_every_provider_and_message_type() {
+    let expected = [
+        (
+            Provider::OpenAI,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Wire,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Anthropic,
+            ["user", "user", "assistant", "assistant", "user"],
+        ),
+        (Provider::Gemini, ["user", "user", "model", "model", "user"]),
+        (
+            Provider::Groq,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Ollama,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Cohere,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Together,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Fireworks,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Perplexity,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+        (
+            Provider::Compatible,
+            ["system", "user", "assistant", "assistant", "tool"],
+        ),
+    ];
+
+    for (provider, roles) in expected {
+        for (message_type, role) in MESSAGE_TYPES.iter().zip(roles) {
+            assert_eq!(
+                role_for(provider, *message_type),
+                role,
+                "{:?} / {:?}",
+                provider,
+                message_type
+            );
+        }
+    }
+}
+
+#[test]
+fn message_types_round_trip_through_role_strings() {
+    for message_type in MESSAGE_TYPES {
+        let role = message_type.to_string();
+        assert_eq!(role.parse::<MessageType>(), Ok(message_type), "{}", role);
+        assert_eq!(MessageType::try_from(role.as_str()), Ok(message_type));
+    }
+
+    // Provider spellings
+    assert_eq!("developer".parse(), Ok(MessageType::System));
+    assert_eq!("model".parse(), Ok(MessageType::Assistant));
+
+    for role in ["", "Assistant", "critic", " user"] {
+        assert_eq!(
+            role.parse::<MessageType>(),
+            Err(format!("Unknown role: {}", role))
+        );
+    }
+}
+
+#[test]
+fn from_role_reads_tool_calls_on_assistant_turns() {
+    let expected = [
+        ("system", false, MessageType::System),
+        ("system", true, MessageType::System),
+        ("developer", false, MessageType::System),
+        ("user", false, MessageType::User),
+        ("assistant", false, MessageType::Assistant),
+        ("assistant", true, MessageType::FunctionCall),
+        ("model", false, MessageType::Assistant),
+        ("model", true, MessageType::FunctionCall),
+        ("function", false, MessageType::FunctionCall),
+        ("function", true, MessageType::FunctionCall),
+        ("tool", false, MessageType::FunctionCallOutput),
+    ];
+
+    for (role, has_tool_calls, message_type) in expected {
+        assert_eq!(
+            MessageType::from_role(role, has_tool_calls),
+            Ok(message_type),
+            "{} / {}",
+            role,
+            has_tool_calls
+        );
+    }
+
+    assert!(MessageType::from_role("critic", true).is_err());
+}
+
+#[test]
+fn request_builders_use_provider_roles() {
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("role-key")),
+            ("ANTHROPIC_API_KEY", Some("role-key")),
+            ("GEMINI_API_KEY", Some("role-key")),
+        ],
+        || {
+            let build = |client: &dyn PromptCore| {
+                let request = client
+                    .build_request("Be helpful.".to_string(), every_message_type(), None, false)
+                    .expect("request builds")
+                    .build()
+                    .expect("request builds");
+                request_body_json(&request)
+            };
+
+            let openai = build(&OpenAIClient::try_new("gpt-4o-mini").expect("known model"));
+            assert_eq!(
+                roles(&openai["messages"]),
+                vec!["system", "system", "user", "assistant", "assistant", "tool"]
+            );
+
+            let anthropic =
+                build(&AnthropicClient::try_new("claude-3-5-haiku-20241022").expect("known model"));
+            assert_eq!(
+                roles(&anthropic["messages"]),
+                vec!["user", "user", "assistant", "assistant", "user"]
+            );
+            assert_eq!(anthropic["messages"][3]["content"][1]["type"], "tool_use");
+            assert_eq!(
+                anthropic["messages"][4]["content"][0]["type"],
+                "tool_result"
+            );
+
+            let gemini = build(&GeminiClient::try_new("gemini-2.0-flash").expect("known model"));
+            assert_eq!(
+                roles(&gemini["contents"]),
+                vec!["user", "user", "model", "model", "user"]
+            );
+        },
+    );
+}
+
+#[test]
+fn empty_tool_call_content_serializes_per_provider() {
+    with_vars(
+        [
+            ("OPENAI_API_KEY", Some("role-key")),
+            ("ANTHROPIC_API_KEY", Some("role-key")),
+            ("GEMINI_API_KEY", Some("role-key")),
+        ],
+        || {
+            for content in ["", "  \n"] {
+                let mut call = message(MessageType::FunctionCall, content);
+                call.tool_calls = Some(vec![function_call(
+                    "call-1",
+                    "lookup_weather",
+                    serde_json::json!({}),
+                )]);
+                let history = vec![message(MessageType::User, "Weather?"), call];
+
+                let build = |client: &dyn PromptCore| {
+                    let request = client
+                        .build_request("Be helpful.".to_string(), history.clone(), None, false)
+                        .expect("request builds")
+                        .build()
+                        .expect("request builds");
+                    request_body_json(&request)
+                };
+
+                let openai = build(&OpenAIClient::try_new("gpt-4o-mini").expect("known model"));
+                let turn = &openai["messages"][2];
+                assert_eq!(turn["role"], "assistant");
+                assert!(turn["content"].is_null(), "{:?}", content);
+                assert_eq!(turn["tool_calls"][0]["id"], "call-1");
+
+                let anthropic = build(
+                    &AnthropicClient::try_new("claude-3-5-haiku-20241022").expect("known model"),
+                );
+                let blocks = anthropic["messages"][1]["content"]
+                    .as_array()
+                    .expect("content blocks");
+                assert_eq!(blocks.len(), 1, "{:?}", content);
+                assert_eq!(blocks[0]["type"], "tool_use");
+
+                let gemini =
+                    build(&GeminiClient::try_new("gemini-2.0-flash").expect("known model"));
+                assert_eq!(
+                    roles(&gemini["contents"]),
+                    vec!["user", "model"],
+                    "{:?}",
+                    content
+                );
+                let parts = gemini["contents"][1]["parts"]
+                    .as_array()
+                    .expect("content parts");
+                assert_eq!(parts.len(), 1, "{:?}", content);
+                assert_eq!(parts[0]["functionCall"]["name"], "lookup_weather");
+            }
+        },
+    );
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::message;
+use std::sync::Arc;
+use wire::api::PromptCore;
+use wire::echo::EchoClient;
+use wire::router::{RouteRequest, RouterClient, DEFAULT_LONG_REQUEST_TOKENS};
+use wire::types::MessageType;
+
+#[test]
+fn routes_are_checked_in_order() {
+    let echo: Arc<dyn PromptCore> = Arc::new(EchoClient::new());
+    let router = RouterClient::new("fallback", echo.clone())
+        .with_route("first", echo.clone(), |request| {
+            request.system_prompt.contains("first")
+        })
+        .with_route("second", echo, |request| request.chat_history.len() > 1);
+
+    let one = vec![message(MessageType::User, "hi")];
+    let two = vec![
+        message(MessageType::User, "hi"),
+        message(MessageType::User, "again"),
+    ];
+
+    let name = |system_prompt: &str, history| {
+        router
+            .route(&RouteRequest::new(system_prompt, history, &[]))
+            .0
+            .to_string()
+    };
+    assert_eq!(name("first", &one), "first");
+    assert_eq!(name("first", &two), "first");
+    assert_eq!(name("", &two), "second");
+    assert_eq!(name("", &one), "fallback");
+}
+
+#[test]
+fn tiered_routes_long_requests_to_the_expensive_client() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for router test");
+    let router = RouterClient::tiered(Arc::new(EchoClient::new()), Arc::new(EchoClient::new()));
+
+    runtime.block_on(async {
+        let short = vec![message(MessageType::User, "hi")];
+        let reply = router
+            .prompt(String::new(), short)
+            .await
+            .expect("short prompt");
+        assert_eq!(reply.metadata.route.as_deref(), Some("cheap"));
+
+        let text = "word ".repeat(DEFAULT_LONG_REQUEST_TOKENS);
+        let long = vec![message(MessageType::User, &text)];
+        assert!(RouteRequest::new("", &long, &[]).estimated_tokens > DEFAULT_LONG_REQUEST_TOKENS);
+        let reply = router
+            .prompt(String::new(), long)
+            .await
+            .expect("long prompt");
+        assert_eq!(reply.metadata.route.as_deref(), Some("long"));
+    });
+}
+
+#[cfg(all(feature = "openai", feature = "anthropic", feature = "mock"))]
+mod mock_backed {
+    use super::*;
+    use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+    use common::{run_mock_test, sample_tool};
+    use std::sync::Mutex;
+    use wire::anthropic::AnthropicClient;
+    use wire::api::ToolCapable;
+    use wire::config::ClientOptions;
+    use wire::metrics::MetricsCallback;
+    use wire::openai::OpenAIClient;
+
+    const CHEAP_PATH: &str = "/v1/chat/completions";
+    const EXPENSIVE_PATH: &str = "/v1/messages";
+
+    fn cheap_reply() -> MockResponse {
+        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+            "choices": [{ "message": { "content": "cheap" } }]
+        })))
+    }
+
+    fn expensive_reply() -> MockResponse {
+        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+            "stop_reason": "end_turn",
+            "content": [{ "type": "text", "text": "expensive" }]
+        })))
+    }
+
+    fn clients(server: &MockLLMServer) -> (Arc<dyn PromptCore>, Arc<dyn PromptCore>) {
+        let options = || ClientOptions::for_mock_server(server).expect("client options");
+        (
+            Arc::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options()).expect("known model"),
+            ),
+            Arc::new(
+                AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options())
+                    .expect("known model"),
+            ),
+        )
+    }
+
+    #[test]
+    fn routes_by_history_size() {
+        run_mock_test("router history size test", async {
+            let server = MockLLMServer::start(vec![
+                MockRoute::new(CHEAP_PATH, vec![cheap_reply()]),
+                MockRoute::new(EXPENSIVE_PATH, vec![expensive_reply()]),
+            ])
+            .await
+            .expect("mock server starts");
+
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let sink = reported.clone();
+            let (cheap, expensive) = clients(&server);
+            let router = RouterClient::new("cheap", cheap)
+                .with_route("long history", expensive, |request| {
+                    request.chat_history.len() > 2
+                })
+                .with_metrics_callback(MetricsCallback::new(move |metrics| {
+                    sink.lock()
+                        .unwrap()
+                        .push((metrics.route.clone(), metrics.api.clone()));
+                }));
+
+            let short = vec![message(MessageType::User, "hi")];
+            let reply = router.prompt(String::new(), short).await.expect("short");
+            assert_eq!(reply.content, "cheap");
+            assert_eq!(reply.metadata.route.as_deref(), Some("cheap"));
+
+            let long = vec![
+                message(MessageType::User, "one"),
+                message(MessageType::Assistant, "two"),
+                message(MessageType::User, "three"),
+            ];
+            let reply = router.prompt(String::new(), long).await.expect("long");
+            assert_eq!(reply.content, "expensive");
+            assert_eq!(reply.metadata.route.as_deref(), Some("long history"));
+
+            assert_eq!(server.requests_for(CHEAP_PATH).await.len(), 1);
+            assert_eq!(server.requests_for(EXPENSIVE_PATH).await.len(), 1);
+
+            {
+                let reported = reported.lock().unwrap();
+                let routes: Vec<Option<&str>> =
+                    reported.iter().map(|(route, _)| route.as_deref()).collect();
+                assert_eq!(routes, vec![Some("cheap"), Some("long history")]);
+                assert_eq!(reported[1].1.to_strings().0, "anthropic");
+            }
+
+            server.shutdown().await;
+        });
+    }
+
+    #[test]
+    fn tiered_routes_tool_requests_to_the_expensive_client() {
+        run_mock_test("router tools test", async {
+            let server = MockLLMServer::start(vec![
+                MockRoute::new(CHEAP_PATH, vec![cheap_reply()]),
+                MockRoute::new(EXPENSIVE_PATH, vec![expensive_reply()]),
+            ])
+            .await
+            .expect("mock server starts");
+
+            let (cheap, expensive) = clients(&server);
+            let router = RouterClient::tiered(cheap, expensive);
+            let history = vec![message(MessageType::User, "What's the weather?")];
+
+            let messages = router
+                .prompt_with_tools("Assist.", history.clone(), vec![sample_tool("weather")])
+                .await
+                .expect("tool prompt");
+            let reply = messages.last().expect("a reply");
+            assert_eq!(reply.content, "expensive");
+            assert_eq!(reply.metadata.route.as_deref(), Some("tools"));
+            assert_eq!(messages[0].metadata.route, None);
+
+            let reply = router
+                .prompt("Assist.".to_string(), history)
+                .await
+                .expect("plain prompt");
+            assert_eq!(reply.content, "cheap");
+            assert_eq!(reply.metadata.route.as_deref(), Some("cheap"));
+
+            let requests = server.requests_for(EXPENSIVE_PATH).await;
+            assert_eq!(requests.len(), 1);
+            let body: serde_json::Value =
+                serde_json::from_slice(&requests[0].body).expect("json body");
+            assert_eq!(body["tools"][0]["name"], "weather");
+            assert_eq!(server.requests_for(CHEAP_PATH).await.len(), 1);
+
+            server.shutdown().await;
+        });
+    }
+}
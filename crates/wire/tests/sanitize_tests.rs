@@ -0,0 +1,240 @@
+mod common;
+
+use std::borrow::Cow;
+
+use wire::sanitize::{repair_surrogates, sanitize, ControlCharacter, SanitizePolicy};
+
+const NASTY: &str = "\u{0}start\u{1b}[1mbold\u{1b}[0m\ttab\r\nline\rover\u{7}\u{7f}é\u{1f}";
+
+#[test]
+fn strip_drops_controls_but_keeps_newlines_and_tabs() {
+    assert_eq!(
+        sanitize(NASTY, SanitizePolicy::Strip).unwrap(),
+        "start[1mbold[0m\ttab\r\nlineover\u{7f}é"
+    );
+}
+
+#[test]
+fn escape_spells_controls_out() {
+    assert_eq!(
+        sanitize(NASTY, SanitizePolicy::Escape).unwrap(),
+        "\\u0000start\\u001b[1mbold\\u001b[0m\ttab\r\nline\\u000dover\\u0007\u{7f}é\\u001f"
+    );
+}
+
+#[test]
+fn reject_reports_the_first_control() {
+    assert_eq!(
+        sanitize(NASTY, SanitizePolicy::Reject),
+        Err(ControlCharacter {
+            offset: 0,
+            character: '\u{0}'
+        })
+    );
+    assert_eq!(
+        sanitize("ok\r\nfine\u{1b}", SanitizePolicy::Reject)
+            .unwrap_err()
+            .to_string(),
+        "control character U+001B at byte 8"
+    );
+    // A CRLF line break on its own is not a control
+    assert!(matches!(
+        sanitize("one\r\ntwo", SanitizePolicy::Reject),
+        Ok(Cow::Borrowed("one\r\ntwo"))
+    ));
+}
+
+#[test]
+fn clean_text_is_borrowed_under_every_policy() {
+    for policy in [
+        SanitizePolicy::Strip,
+        SanitizePolicy::Escape,
+        SanitizePolicy::Reject,
+    ] {
+        let clean = sanitize("plain\ttext\r\nwith ünïcödé 🦀", policy).unwrap();
+        assert!(matches!(clean, Cow::Borrowed(_)), "{:?}", policy);
+    }
+}
+
+#[test]
+fn unpaired_surrogate_escapes_are_replaced() {
+    let exported = r#"{"a": "cut \ud83e", "b": "\udd80 low", "c": "🦀", "d": "\\ud83e"}"#;
+    assert!(serde_json::from_str::<serde_json::Value>(exported).is_err());
+
+    let repaired = repair_surrogates(exported);
+    let value: serde_json::Value = serde_json::from_str(&repaired).expect("repaired json parses");
+
+    assert_eq!(value["a"], "cut \u{fffd}");
+    assert_eq!(value["b"], "\u{fffd} low");
+    assert_eq!(value["c"], "🦀");
+    // An escaped backslash followed by `u` is text, not an escape
+    assert_eq!(value["d"], "\\ud83e");
+
+    assert!(matches!(
+        repair_surrogates(r#"{"c": "🦀 é"}"#),
+        Cow::Borrowed(_)
+    ));
+}
+
+#[cfg(all(feature = "openai", feature = "anthropic", feature = "gemini"))]
+mod request_bodies {
+    use super::*;
+    use common::{function_call, message, request_body_json};
+    use temp_env::with_vars;
+    use wire::anthropic::AnthropicClient;
+    use wire::api::PromptCore;
+    use wire::config::ClientOptions;
+    use wire::error::WireError;
+    use wire::gemini::GeminiClient;
+    use wire::openai::OpenAIClient;
+    use wire::types::{Message, MessageType};
+
+    fn history() -> Vec<Message> {
+        let mut call = message(MessageType::FunctionCall, "");
+        call.tool_calls = Some(vec![function_call(
+            "call-1",
+            "read_log",
+            serde_json::json!({}),
+        )]);
+        let mut output = message(
+            MessageType::FunctionCallOutput,
+            "\u{1b}[31mERROR\u{1b}[0m\u{0}",
+        );
+        output.tool_call_id = Some("call-1".to_string());
+        output.name = Some("read_log".to_string());
+
+        vec![
+            message(MessageType::User, "What does\r\nthe log say?\u{7}"),
+            call,
+            output,
+        ]
+    }
+
+    fn clients(options: ClientOptions) -> Vec<Box<dyn PromptCore>> {
+        vec![
+            Box::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                    .expect("known model"),
+            ),
+            Box::new(
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model"),
+            ),
+        ]
+    }
+
+    /// Every string in `value`, keys aside.
+    fn strings(value: &serde_json::Value) -> Vec<String> {
+        match value {
+            serde_json::Value::String(text) => vec![text.clone()],
+            serde_json::Value::Array(items) => items.iter().flat_map(strings).collect(),
+            serde_json::Value::Object(map) => map.values().flat_map(strings).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn with_keys(test: impl FnOnce()) {
+        with_vars(
+            [
+                ("OPENAI_API_KEY", Some("key")),
+                ("ANTHROPIC_API_KEY", Some("key")),
+                ("GEMINI_API_KEY", Some("key")),
+            ],
+            test,
+        );
+    }
+
+    #[test]
+    fn request_bodies_carry_sanitized_content() {
+        with_keys(|| {
+            for (policy, expected) in [
+                (
+                    SanitizePolicy::Strip,
+                    ["Be terse.", "What does\r\nthe log say?", "[31mERROR[0m"],
+                ),
+                (
+                    SanitizePolicy::Escape,
+                    [
+                        "Be terse.\\u001b",
+                        "What does\r\nthe log say?\\u0007",
+                        "\\u001b[31mERROR\\u001b[0m\\u0000",
+                    ],
+                ),
+            ] {
+                let options = ClientOptions::default().with_sanitize_policy(policy);
+                for client in clients(options) {
+                    let request = client
+                        .build_request("Be terse.\u{1b}".to_string(), history(), None, false)
+                        .expect("keys are set")
+                        .build()
+                        .expect("request builds");
+                    let body = request_body_json(&request);
+                    let texts = strings(&body);
+
+                    for text in &texts {
+                        assert!(
+                            !text.chars().any(|c| c < ' ' && !"\n\t\r".contains(c)),
+                            "{:?} sent {:?}",
+                            policy,
+                            text
+                        );
+                    }
+                    for wanted in expected {
+                        assert!(
+                            texts.iter().any(|text| text.contains(wanted)),
+                            "{:?} body lacks {:?}: {}",
+                            policy,
+                            wanted,
+                            body
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn reject_fails_before_anything_is_sent() {
+        with_keys(|| {
+            let runtime = tokio::runtime::Runtime::new().expect("runtime for sanitize test");
+
+            runtime.block_on(async {
+                // Nothing listens here; reaching the network would fail differently
+                let options = ClientOptions::from_base_url("http://127.0.0.1:9")
+                    .expect("options")
+                    .with_sanitize_policy(SanitizePolicy::Reject);
+
+                for client in clients(options) {
+                    let err = client
+                        .prompt("Be terse.".to_string(), history())
+                        .await
+                        .expect_err("control characters are rejected");
+                    assert!(matches!(
+                        err,
+                        WireError::ControlCharacter {
+                            message_index: Some(0),
+                            offset: 23,
+                            character: '\u{7}',
+                        }
+                    ));
+                    assert_eq!(
+                        err.to_string(),
+                        "message 0 contains control character U+0007 at byte 23"
+                    );
+
+                    let err = client
+                        .prompt("\u{0}".to_string(), Vec::new())
+                        .await
+                        .expect_err("system prompt is checked too");
+                    assert_eq!(
+                        err.to_string(),
+                        "system prompt contains control character U+0000 at byte 0"
+                    );
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,223 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute, TestClock};
+use common::{message, run_mock_test};
+use std::sync::Arc;
+use std::time::Duration;
+use wire::config::ClientOptions;
+use wire::echo::EchoClient;
+use wire::openai::OpenAIClient;
+use wire::scheduler::{Priority, PromptScheduler, RateLimit, SchedulerOptions};
+use wire::types::MessageType;
+
+const PATH: &str = "/v1/chat/completions";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn start_server() -> MockLLMServer {
+    MockLLMServer::start(vec![MockRoute::single(
+        PATH,
+        MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+            "choices": [{ "message": { "content": "done" } }]
+        }))),
+    )])
+    .await
+    .expect("mock server starts")
+}
+
+/// A scheduler over an OpenAI client that talks to `server` and waits on
+/// `clock`.
+fn scheduler(
+    server: &MockLLMServer,
+    clock: &TestClock,
+    rate_limit: RateLimit,
+    options: SchedulerOptions,
+) -> PromptScheduler {
+    let client_options = ClientOptions::for_mock_server(server)
+        .expect("client options for mock server")
+        .with_clock(clock.clone());
+    let client =
+        OpenAIClient::try_with_options("gpt-4o-mini", client_options).expect("known model");
+
+    PromptScheduler::with_options(Arc::new(client), rate_limit, options)
+}
+
+/// Submit `content` as the only user message and run it in the background.
+fn submit(
+    scheduler: &PromptScheduler,
+    priority: Priority,
+    content: &str,
+) -> tokio::task::JoinHandle<Result<String, String>> {
+    let reply = scheduler.submit(
+        priority,
+        "Be brief.".to_string(),
+        vec![message(MessageType::User, content)],
+    );
+
+    tokio::spawn(async move {
+        reply
+            .await
+            .map(|reply| reply.content)
+            .map_err(|err| err.to_string())
+    })
+}
+
+/// The user message of every request `server` has seen, in arrival order,
+/// once there are `count` of them.
+async fn sent(server: &MockLLMServer, count: usize) -> Vec<String> {
+    server
+        .wait_for_requests(PATH, count, TIMEOUT)
+        .await
+        .expect("requests arrive")
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).expect("request body is json");
+            let messages = body["messages"].as_array().expect("messages array");
+            messages
+                .last()
+                .and_then(|message| message["content"].as_str())
+                .expect("user message content")
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn interactive_prompts_jump_the_queue() {
+    run_mock_test("scheduler priority test", async {
+        let server = start_server().await;
+        let clock = TestClock::new();
+        let scheduler = scheduler(
+            &server,
+            &clock,
+            RateLimit::per_second(1),
+            SchedulerOptions::new(),
+        );
+
+        // The first prompt takes this second's only slot
+        let mut replies = vec![submit(&scheduler, Priority::Background, "background 1")];
+        sent(&server, 1).await;
+
+        for (priority, content) in [
+            (Priority::Background, "background 2"),
+            (Priority::Background, "background 3"),
+            (Priority::Normal, "normal"),
+            (Priority::Interactive, "interactive"),
+        ] {
+            replies.push(submit(&scheduler, priority, content));
+        }
+        assert_eq!(scheduler.waiting(), 4);
+
+        for count in 2..=5 {
+            clock.advance(Duration::from_secs(1));
+            sent(&server, count).await;
+        }
+
+        assert_eq!(
+            sent(&server, 5).await,
+            [
+                "background 1",
+                "interactive",
+                "normal",
+                "background 2",
+                "background 3"
+            ]
+        );
+        for reply in replies {
+            assert_eq!(reply.await.expect("prompt task joins").unwrap(), "done");
+        }
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn aging_lets_waiting_prompts_catch_up() {
+    run_mock_test("scheduler aging test", async {
+        let server = start_server().await;
+        let clock = TestClock::new();
+        let scheduler = scheduler(
+            &server,
+            &clock,
+            RateLimit::per_second(1),
+            SchedulerOptions::new().with_aging(Duration::from_millis(500)),
+        );
+
+        submit(&scheduler, Priority::Interactive, "first");
+        sent(&server, 1).await;
+
+        // By the time a slot opens, the background prompt has waited long
+        // enough to count as interactive and was submitted first
+        submit(&scheduler, Priority::Background, "background");
+        clock.advance(Duration::from_millis(500));
+        submit(&scheduler, Priority::Interactive, "interactive");
+        clock.advance(Duration::from_millis(500));
+        sent(&server, 2).await;
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            sent(&server, 3).await,
+            ["first", "background", "interactive"]
+        );
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn concurrency_limits_apply_per_priority() {
+    run_mock_test("scheduler concurrency test", async {
+        let server = start_server().await;
+        let clock = TestClock::new();
+        let scheduler = scheduler(
+            &server,
+            &clock,
+            RateLimit::per_second(100),
+            SchedulerOptions::new().with_concurrency_limit(Priority::Background, 1),
+        );
+
+        // Started, but never polled: it holds the only background slot
+        let held = scheduler.submit(
+            Priority::Background,
+            "Be brief.".to_string(),
+            vec![message(MessageType::User, "held")],
+        );
+        let queued = submit(&scheduler, Priority::Background, "queued");
+        let normal = submit(&scheduler, Priority::Normal, "normal");
+
+        assert_eq!(normal.await.expect("prompt task joins").unwrap(), "done");
+        assert_eq!(sent(&server, 1).await, ["normal"]);
+        assert_eq!(scheduler.waiting(), 1);
+
+        drop(held);
+        assert_eq!(queued.await.expect("prompt task joins").unwrap(), "done");
+        assert_eq!(sent(&server, 2).await, ["normal", "queued"]);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn dropping_the_scheduler_fails_queued_prompts() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for scheduler test");
+
+    runtime.block_on(async {
+        let clock = TestClock::new();
+        let client = EchoClient::with_options(ClientOptions::default().with_clock(clock));
+        let scheduler = PromptScheduler::new(Arc::new(client), RateLimit::per_minute(1));
+
+        let first = submit(&scheduler, Priority::Normal, "first");
+        assert_eq!(first.await.expect("prompt task joins").unwrap(), "first");
+
+        let second = submit(&scheduler, Priority::Interactive, "second");
+        drop(scheduler);
+
+        let err = second
+            .await
+            .expect("prompt task joins")
+            .expect_err("queued prompt fails");
+        assert!(err.contains("scheduler was dropped"), "{}", err);
+    });
+}
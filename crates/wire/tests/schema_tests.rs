@@ -0,0 +1,187 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "groq",
+    feature = "cohere",
+    feature = "perplexity"
+))]
+
+mod common;
+
+use common::{function_call, message};
+use std::path::PathBuf;
+use wire::api::{get_available_models, AnthropicModel, GeminiModel, OpenAIModel, API};
+use wire::types::{Message, MessageType};
+
+fn fixture(path: &str) -> serde_json::Value {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/schema")
+        .join(path);
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err));
+
+    serde_json::from_str(&contents)
+        .unwrap_or_else(|err| panic!("{} is not valid JSON: {}", path.display(), err))
+}
+
+fn fixture_array(path: &str) -> Vec<serde_json::Value> {
+    match fixture(path) {
+        serde_json::Value::Array(entries) => entries,
+        other => panic!("{} should hold a JSON array, found {}", path, other),
+    }
+}
+
+fn with_api(mut message: Message, api: API, system_prompt: &str) -> Message {
+    message.api = api;
+    message.system_prompt = system_prompt.to_string();
+    message
+}
+
+/// The messages described by `v1/messages.json`.
+fn v1_messages() -> Vec<Message> {
+    let arguments = serde_json::json!({ "location": "NYC" });
+
+    let mut tool_call = message(MessageType::Assistant, "");
+    tool_call.tool_calls = Some(vec![function_call(
+        "call-1",
+        "lookup_weather",
+        arguments.clone(),
+    )]);
+
+    let mut function_call_message = message(MessageType::FunctionCall, &arguments.to_string());
+    function_call_message.tool_call_id = Some("call-1".to_string());
+    function_call_message.name = Some("lookup_weather".to_string());
+
+    let mut tool_output = message(
+        MessageType::FunctionCallOutput,
+        &serde_json::json!({ "forecast": "snow" }).to_string(),
+    );
+    tool_output.tool_call_id = Some("call-1".to_string());
+    tool_output.name = Some("lookup_weather".to_string());
+
+    vec![
+        with_api(
+            message(MessageType::System, "You are a helpful assistant."),
+            API::OpenAI(OpenAIModel::GPT4oMini),
+            "You are a helpful assistant.",
+        ),
+        with_api(
+            message(MessageType::User, "What's the weather in NYC?"),
+            API::Anthropic(AnthropicModel::Claude35SonnetNew),
+            "Be brief.",
+        ),
+        with_api(
+            message(MessageType::Assistant, "Let me check."),
+            API::Gemini(GeminiModel::Gemini20Flash),
+            "",
+        ),
+        with_api(tool_call, API::OpenAI(OpenAIModel::GPT4o), "Be brief."),
+        with_api(function_call_message, API::OpenAI(OpenAIModel::GPT5), ""),
+        with_api(
+            tool_output,
+            API::Anthropic(AnthropicModel::ClaudeSonnet4),
+            "Be brief.",
+        ),
+    ]
+}
+
+#[test]
+fn v1_messages_serialize_to_golden() {
+    let golden = fixture_array("v1/messages.json");
+    let serialized: Vec<serde_json::Value> = v1_messages()
+        .iter()
+        .map(|message| serde_json::to_value(message).expect("message serializes"))
+        .collect();
+
+    assert_eq!(serialized, golden);
+}
+
+#[test]
+fn v1_messages_cover_every_message_type() {
+    let messages = v1_messages();
+
+    for message_type in [
+        MessageType::System,
+        MessageType::User,
+        MessageType::Assistant,
+        MessageType::FunctionCall,
+        MessageType::FunctionCallOutput,
+    ] {
+        assert!(
+            messages
+                .iter()
+                .any(|message| message.message_type == message_type),
+            "{:?} missing from v1/messages.json",
+            message_type
+        );
+    }
+
+    assert!(messages.iter().any(|message| message.tool_calls.is_some()));
+    assert!(messages
+        .iter()
+        .any(|message| message.tool_call_id.is_some()));
+}
+
+#[test]
+fn v1_messages_round_trip() {
+    for golden in fixture_array("v1/messages.json") {
+        let message: Message = serde_json::from_value(golden.clone())
+            .unwrap_or_else(|err| panic!("failed to deserialize {}: {}", golden, err));
+
+        assert_eq!(serde_json::to_value(&message).unwrap(), golden);
+    }
+}
+
+#[test]
+fn v1_apis_serialize_to_golden() {
+    let golden = fixture_array("v1/apis.json");
+    let serialized: Vec<serde_json::Value> = get_available_models()
+        .iter()
+        .map(|api| serde_json::to_value(api).expect("api serializes"))
+        .collect();
+
+    assert_eq!(serialized, golden);
+}
+
+#[test]
+fn v1_apis_round_trip() {
+    let golden = fixture_array("v1/apis.json");
+    let apis: Vec<API> = golden
+        .iter()
+        .map(|value| serde_json::from_value(value.clone()).expect("api deserializes"))
+        .collect();
+
+    assert_eq!(apis, get_available_models());
+}
+
+#[test]
+fn legacy_apis_deserialize_to_current_form() {
+    for entry in fixture_array("legacy/apis.json") {
+        let api: API = serde_json::from_value(entry["legacy"].clone())
+            .unwrap_or_else(|err| panic!("failed to read {}: {}", entry["legacy"], err));
+
+        assert_eq!(serde_json::to_value(&api).unwrap(), entry["current"]);
+    }
+}
+
+#[test]
+fn legacy_messages_deserialize_to_current_form() {
+    for entry in fixture_array("legacy/messages.json") {
+        let message: Message = serde_json::from_value(entry["legacy"].clone())
+            .unwrap_or_else(|err| panic!("failed to read {}: {}", entry["legacy"], err));
+
+        assert_eq!(serde_json::to_value(&message).unwrap(), entry["current"]);
+    }
+}
+
+#[test]
+fn unknown_api_is_rejected() {
+    let err = serde_json::from_value::<API>(serde_json::json!({
+        "provider": "openai",
+        "model": "gpt-2"
+    }))
+    .expect_err("unknown model should fail");
+
+    assert!(err.to_string().contains("provider"));
+}
@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use wire::sentence::SentenceAggregator;
+
+fn aggregate(aggregator: &mut SentenceAggregator, deltas: &[&str]) -> Vec<String> {
+    let mut pieces: Vec<String> = deltas
+        .iter()
+        .flat_map(|delta| aggregator.push(delta))
+        .collect();
+    pieces.extend(aggregator.finish());
+    pieces
+}
+
+struct Deltas(VecDeque<String>);
+
+impl Stream for Deltas {
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<String>> {
+        Poll::Ready(self.0.pop_front())
+    }
+}
+
+#[test]
+fn sentences_split_across_deltas_are_joined() {
+    let deltas = [
+        "The qu",
+        "ick fox",
+        " jumped.",
+        " Then it",
+        " slept! Did it",
+        " dream?",
+    ];
+    let pieces = aggregate(&mut SentenceAggregator::new(), &deltas);
+
+    assert_eq!(
+        pieces,
+        vec!["The quick fox jumped. ", "Then it slept! ", "Did it dream?"]
+    );
+    assert_eq!(pieces.concat(), deltas.concat());
+}
+
+#[test]
+fn period_at_end_of_delta_waits_for_more_text() {
+    let mut aggregator = SentenceAggregator::new();
+
+    assert!(aggregator.push("Pi is 3.").is_empty());
+    assert!(aggregator.push("14 or so.").is_empty());
+    assert_eq!(aggregator.push(" Next"), vec!["Pi is 3.14 or so. "]);
+    assert_eq!(aggregator.finish().as_deref(), Some("Next"));
+    assert_eq!(aggregator.finish(), None);
+}
+
+#[test]
+fn abbreviations_do_not_end_sentences() {
+    let deltas = [
+        "Bring fruit, e.",
+        "g. apples or pears",
+        ", i.e. something ",
+        "sweet. Ask Dr. Smith",
+        " (etc.) first. Done",
+    ];
+    let pieces = aggregate(&mut SentenceAggregator::new(), &deltas);
+
+    assert_eq!(
+        pieces,
+        vec![
+            "Bring fruit, e.g. apples or pears, i.e. something sweet. ",
+            "Ask Dr. Smith (etc.) first. ",
+            "Done",
+        ]
+    );
+
+    let mut custom = SentenceAggregator::new().with_abbreviation("Approx.");
+    let pieces = aggregate(&mut custom, &["It takes APPROX. ", "two hours. Ok"]);
+    assert_eq!(pieces, vec!["It takes APPROX. two hours. ", "Ok"]);
+}
+
+#[test]
+fn newlines_and_custom_boundaries_emit() {
+    let mut aggregator = SentenceAggregator::new();
+    let pieces = aggregate(&mut aggregator, &["- one\n- tw", "o\n\nlast"]);
+    assert_eq!(pieces, vec!["- one\n", "- two\n", "\n", "last"]);
+
+    let mut clauses = SentenceAggregator::new()
+        .with_boundary(r";\s")
+        .expect("valid pattern");
+    let pieces = aggregate(&mut clauses, &["first; sec", "ond;", " third. end"]);
+    assert_eq!(pieces, vec!["first; ", "second; ", "third. ", "end"]);
+
+    assert!(SentenceAggregator::new().with_boundary("(").is_err());
+}
+
+#[test]
+fn wrapped_sender_emits_sentences_and_flushes_on_drop() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for aggregator test");
+
+    runtime.block_on(async {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let deltas = SentenceAggregator::wrap(tx);
+
+        for delta in ["One. Tw", "o. Thr", "ee"] {
+            deltas.send(delta.to_string()).await.unwrap();
+        }
+
+        assert_eq!(rx.recv().await.as_deref(), Some("One. "));
+        assert_eq!(rx.recv().await.as_deref(), Some("Two. "));
+
+        drop(deltas);
+        assert_eq!(rx.recv().await.as_deref(), Some("Three"));
+        assert_eq!(rx.recv().await, None);
+    });
+}
+
+#[test]
+fn stream_adapter_regroups_deltas() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for aggregator test");
+
+    runtime.block_on(async {
+        let deltas = Deltas(
+            ["A sent", "ence. And ", "another.\nTail"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        );
+        let mut sentences = SentenceAggregator::new().stream(deltas);
+
+        let mut pieces = Vec::new();
+        while let Some(piece) =
+            std::future::poll_fn(|cx| Pin::new(&mut sentences).poll_next(cx)).await
+        {
+            pieces.push(piece);
+        }
+
+        assert_eq!(pieces, vec!["A sentence. ", "And another.\n", "Tail"]);
+    });
+}
@@ -0,0 +1,169 @@
+#![cfg(all(feature = "tower", feature = "openai"))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, sample_tool};
+use temp_env::with_var;
+use tower::{Service, ServiceBuilder, ServiceExt};
+use wire::api::PromptCore;
+use wire::config::{ClientOptions, PromptOptions, ServiceTier};
+use wire::error::WireError;
+use wire::openai::OpenAIClient;
+use wire::service::{PromptRequest, PromptResponse};
+use wire::types::MessageType;
+
+fn question() -> PromptRequest {
+    PromptRequest::new("Be brief.", vec![message(MessageType::User, "Hi")])
+}
+
+#[test]
+fn layers_limit_and_time_out_calls() {
+    with_var("OPENAI_API_KEY", Some("test-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for service test");
+
+        runtime.block_on(async {
+            // Connections are queued but never answered
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("listener binds");
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            let options = ClientOptions::from_base_url(&base_url).expect("base url parses");
+            let client: Arc<dyn PromptCore> =
+                Arc::new(OpenAIClient::try_with_options("gpt-4o", options).expect("known model"));
+
+            let mut service = ServiceBuilder::new()
+                .concurrency_limit(1)
+                .timeout(Duration::from_millis(300))
+                .service(client);
+
+            let first = service.ready().await.expect("ready").call(question());
+            let mut second = service.clone();
+            assert!(
+                tokio::time::timeout(Duration::from_millis(100), second.ready())
+                    .await
+                    .is_err(),
+                "the first call holds the only slot"
+            );
+
+            let error = first.await.expect_err("nothing answers");
+            assert!(error.is::<tower::timeout::error::Elapsed>(), "{}", error);
+            tokio::time::timeout(Duration::from_millis(100), second.ready())
+                .await
+                .expect("the slot is free again")
+                .expect("ready");
+        });
+    });
+}
+
+#[test]
+fn wire_errors_survive_the_service() {
+    with_var("OPENAI_API_KEY", Some("test-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for service test");
+        let client = Arc::new(OpenAIClient::try_new("gpt-4o").expect("known model"));
+
+        let request =
+            question().with_options(PromptOptions::default().with_service_tier(ServiceTier::Auto));
+        let error = runtime
+            .block_on(client.oneshot(request))
+            .expect_err("openai takes no service tier");
+        assert!(matches!(
+            error.downcast_ref::<WireError>(),
+            Some(WireError::UnsupportedOption {
+                option: "service_tier",
+                provider,
+            }) if provider == "openai"        ));
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn mock_backed_service_answers_prompts_and_tool_loops() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping service integration test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for service test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "mock reply" } }]
+                    }))),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{
+                            "message": {
+                                "content": null,
+                                "tool_calls": [{
+                                    "id": "call-1",
+                                    "type": "function",
+                                    "function": {
+                                        "name": "echo",
+                                        "arguments": "{\"value\":\"hello\"}"
+                                    }
+                                }]
+                            }
+                        }]
+                    }))),
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "All done." } }]
+                    }))),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let mut service = ServiceBuilder::new()
+                .concurrency_limit(2)
+                .timeout(Duration::from_secs(5))
+                .service(Arc::new(
+                    OpenAIClient::try_with_options("gpt-4o", options).expect("known model"),
+                ));
+
+            let response = service
+                .ready()
+                .await
+                .expect("ready")
+                .call(question())
+                .await
+                .expect("prompt succeeds");
+            match &response {
+                PromptResponse::Reply(reply) => assert_eq!(reply.content, "mock reply"),
+                other => panic!("expected a reply, got {:?}", other),
+            }
+
+            let request = PromptRequest::new(
+                "Follow instructions.",
+                vec![message(MessageType::User, "Please call the tool")],
+            )
+            .with_tools(vec![sample_tool("echo")]);
+            let response = service
+                .ready()
+                .await
+                .expect("ready")
+                .call(request)
+                .await
+                .expect("tool loop succeeds");
+            assert_eq!(
+                response.reply().map(|reply| reply.content.as_str()),
+                Some("All done.")
+            );
+            let history = response.into_messages();
+            assert_eq!(history.len(), 4);
+            assert_eq!(history[2].message_type, MessageType::FunctionCallOutput);
+
+            server.shutdown().await;
+        });
+    });
+}
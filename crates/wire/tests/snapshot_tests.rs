@@ -0,0 +1,220 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{history, run_mock_test, sample_tool};
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::ClientOptions;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::snapshot::replay;
+use wire::types::MessageType;
+
+const GEMINI_PATH: &str = "/v1beta/models/gemini-2.0-flash:generateContent";
+
+async fn start_server() -> MockLLMServer {
+    MockLLMServer::start(vec![
+        MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "choices": [{ "message": { "content": "Seven." } }]
+            }))),
+        ),
+        MockRoute::single(
+            "/v1/messages",
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "content": [{ "type": "text", "text": "Seven." }]
+            }))),
+        ),
+        MockRoute::single(
+            format!("{}?key=mock-gemini-key", GEMINI_PATH),
+            MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                "candidates": [{ "content": { "parts": [{ "text": "Seven." }] } }]
+            }))),
+        ),
+    ])
+    .await
+    .expect("mock server starts")
+}
+
+fn clients(options: ClientOptions) -> Vec<(&'static str, Box<dyn PromptCore>)> {
+    vec![
+        (
+            "/v1/chat/completions",
+            Box::new(
+                OpenAIClient::try_with_options("gpt-4o-mini", options.clone())
+                    .expect("known model"),
+            ),
+        ),
+        (
+            "/v1/messages",
+            Box::new(
+                AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                    .expect("known model"),
+            ),
+        ),
+        (
+            GEMINI_PATH,
+            Box::new(
+                GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model"),
+            ),
+        ),
+    ]
+}
+
+/// The path `server` recorded for `path`, which for Gemini carries the key.
+fn recorded_path(path: &str) -> String {
+    if path == GEMINI_PATH {
+        format!("{}?key=mock-gemini-key", path)
+    } else {
+        path.to_string()
+    }
+}
+
+#[test]
+fn replies_carry_the_request_that_replays_them() {
+    run_mock_test("snapshot replay test", async {
+        let server = start_server().await;
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_request_snapshot(true);
+
+        for (path, client) in clients(options) {
+            let reply = client
+                .prompt("Be brief.".to_string(), history())
+                .await
+                .expect("prompt succeeds");
+            let snapshot = reply
+                .metadata
+                .request_snapshot
+                .expect("reply has a snapshot");
+
+            assert_eq!(snapshot.path, path);
+            assert!(!snapshot.stream);
+            assert!(!snapshot.body.contains("mock-gemini-key"));
+            assert_eq!(
+                snapshot.body_json().unwrap()["contents"].is_array(),
+                path == GEMINI_PATH
+            );
+
+            let replayed = replay(&snapshot, client.as_ref())
+                .await
+                .expect("replay succeeds");
+            assert!(replayed.contains("Seven."), "{}", replayed);
+
+            let sent = server.requests_for(&recorded_path(path)).await;
+            assert_eq!(sent.len(), 2, "{}", path);
+            assert_eq!(sent[0].body, snapshot.body.as_bytes(), "{}", path);
+            assert_eq!(sent[1].body, sent[0].body, "{}", path);
+        }
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn snapshots_are_off_by_default() {
+    run_mock_test("snapshot default test", async {
+        let server = start_server().await;
+        let options = ClientOptions::for_mock_server(&server).expect("client options");
+
+        for (_, client) in clients(options) {
+            let reply = client
+                .prompt("Be brief.".to_string(), history())
+                .await
+                .expect("prompt succeeds");
+            assert!(reply.metadata.request_snapshot.is_none());
+        }
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn streams_and_tool_loops_keep_their_snapshots() {
+    run_mock_test("snapshot stream test", async {
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            "/v1/chat/completions",
+            vec![
+                MockResponse::openai_text_stream(["Sev", "en."]),
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{
+                        "message": {
+                            "content": null,
+                            "tool_calls": [{
+                                "id": "call-1",
+                                "type": "function",
+                                "function": {
+                                    "name": "echo",
+                                    "arguments": "{\"value\":\"ping\"}"
+                                }
+                            }]
+                        }
+                    }]
+                }))),
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": "The tool said ping." } }]
+                }))),
+            ],
+        )])
+        .await
+        .expect("mock server starts");
+        let options = ClientOptions::for_mock_server(&server)
+            .expect("client options for mock server")
+            .with_request_snapshot(true);
+        let client = OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(8);
+        let reply = client
+            .prompt_stream(history(), "Be brief.".to_string(), tx)
+            .await
+            .expect("stream completes");
+        assert_eq!(reply.content, "Seven.");
+        let snapshot = reply
+            .metadata
+            .request_snapshot
+            .expect("stream has a snapshot");
+        assert!(snapshot.stream);
+        assert_eq!(snapshot.body_json().unwrap()["stream"], true);
+
+        let messages = client
+            .prompt_with_tools("Follow instructions.", history(), vec![sample_tool("echo")])
+            .await
+            .expect("tool loop completes");
+
+        // Each reply in the loop has the request that produced it, the second
+        // one including the tool result
+        let sent = server.requests_for("/v1/chat/completions").await;
+        let snapshots: Vec<_> = messages
+            .iter()
+            .filter(|message| {
+                matches!(
+                    message.message_type,
+                    MessageType::Assistant | MessageType::FunctionCall
+                )
+            })
+            .map(|message| {
+                message
+                    .metadata
+                    .request_snapshot
+                    .clone()
+                    .expect("reply has a snapshot")
+            })
+            .collect();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(sent.len(), 3);
+        for (snapshot, request) in snapshots.iter().zip(&sent[1..]) {
+            assert_eq!(snapshot.body.as_bytes(), request.body.as_slice());
+        }
+        assert!(snapshots[1].body.contains("call-1"));
+
+        server.shutdown().await;
+    });
+}
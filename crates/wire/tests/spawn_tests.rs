@@ -0,0 +1,109 @@
+//! Prompts started on one worker thread and finished on another: every
+//! future and error a caller holds has to be `Send`, and errors `Sync` too
+//! so they box into `Box<dyn Error + Send + Sync>`. Most of this is checked
+//! by compiling.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::sample_tool;
+use wire::api::{PromptCore, WireModel, API};
+use wire::error::WireError;
+use wire::types::{Message, MessageBuilder, MessageType, Tool};
+use wire::{new_client, prompt_stream, prompt_with_tools, EchoClient};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+fn assert_send<T: Send>(_: &T) {}
+
+fn user(content: &str) -> Vec<Message> {
+    vec![MessageBuilder::new(API::Wire(WireModel::Echo), content)
+        .message_type(MessageType::User)
+        .build()]
+}
+
+#[test]
+fn results_are_send_and_sync() {
+    assert_send_sync::<WireError>();
+    assert_send_sync::<Message>();
+    assert_send_sync::<Tool>();
+    assert_send_sync::<Box<dyn PromptCore>>();
+}
+
+#[test]
+fn prompts_run_inside_spawned_tasks() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .expect("multi-threaded runtime");
+
+    runtime.block_on(async {
+        let client: Arc<dyn PromptCore> = Arc::new(EchoClient::new());
+
+        let prompt = {
+            let client = client.clone();
+            async move { client.prompt(String::new(), user("spawned")).await }
+        };
+        assert_send(&prompt);
+        let reply = tokio::spawn(prompt)
+            .await
+            .expect("task completes")
+            .expect("echo replies");
+        assert_eq!(reply.content, "spawned");
+
+        let looped = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .tools()
+                    .expect("echo runs tools")
+                    .prompt_with_tools(
+                        "Use tools.",
+                        user("CALL:lookup_weather:{\"zip\":\"10001\"}"),
+                        vec![sample_tool("lookup_weather")],
+                    )
+                    .await
+            }
+        })
+        .await
+        .expect("task completes")
+        .expect("tool loop finishes");
+        assert!(looped.len() > 1);
+
+        // `?` into the error type `tokio::spawn` callers usually have
+        let boxed = tokio::spawn(async move {
+            let client = new_client("wire:echo")?;
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = client
+                .prompt_stream(user("streamed"), String::new(), tx)
+                .await?;
+            Ok::<_, BoxError>(streamed.content)
+        })
+        .await
+        .expect("task completes")
+        .expect("echo streams");
+        assert_eq!(boxed, "streamed");
+
+        let history = user("free");
+        let free = tokio::spawn(async move {
+            let (tx, _rx) = tokio::sync::mpsc::channel(8);
+            let streamed = prompt_stream(API::Wire(WireModel::Echo), "", &history, tx).await?;
+            let tools =
+                prompt_with_tools(API::Wire(WireModel::Echo), "", history, Vec::new()).await?;
+            Ok::<_, WireError>((streamed.content, tools.len()))
+        })
+        .await
+        .expect("task completes")
+        .expect("free functions reply");
+        assert_eq!(free.0, "free");
+
+        let failed = tokio::spawn(async { new_client("not-a-model").map(|_| ()) })
+            .await
+            .expect("task completes");
+        assert!(matches!(failed, Err(WireError::UnknownModel { .. })));
+    });
+}
@@ -0,0 +1,531 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{
+    MockChunkedResponse, MockJsonResponse, MockLLMServer, MockResponse, MockRoute, MockSseEvent,
+    MockSseResponse,
+};
+use common::{history, message, run_with_mock_keys};
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::{ClientOptions, OnFull, PromptOptions, StreamOptions};
+use wire::echo::EchoClient;
+use wire::error::WireError;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{FinishReason, Message, MessageType, StreamEvent, StreamEventKind};
+
+const GEMINI_STREAM_PATH: &str =
+    "/v1beta/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key";
+
+async fn collect_events(client: &dyn PromptCore) -> Vec<Result<StreamEvent, WireError>> {
+    let mut events = client.prompt_stream_events("Be brief.".to_string(), history());
+    let mut collected = Vec::new();
+    while let Some(event) = std::future::poll_fn(|cx| events.as_mut().poll_next(cx)).await {
+        collected.push(event);
+    }
+
+    collected
+}
+
+async fn collect_channel(client: &dyn PromptCore) -> (Vec<String>, Message) {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(64);
+    let reply = client
+        .prompt_stream(history(), "Be brief.".to_string(), tx)
+        .await
+        .expect("channel stream succeeds");
+
+    let mut deltas = Vec::new();
+    while let Some(delta) = rx.recv().await {
+        deltas.push(delta);
+    }
+
+    (deltas, reply)
+}
+
+/// Stream `client` both ways and check the events match the channel.
+async fn assert_same_as_channel(client: &dyn PromptCore) {
+    let (channel_deltas, channel_reply) = collect_channel(client).await;
+    let mut events = collect_events(client).await;
+
+    let Some(Ok(StreamEvent {
+        kind: StreamEventKind::Reply(reply),
+        ..
+    })) = events.pop()
+    else {
+        panic!("event stream should end with the reply");
+    };
+    let deltas: Vec<String> = events
+        .into_iter()
+        .filter_map(|event| match event.map(|event| event.kind) {
+            Ok(StreamEventKind::ContentDelta(delta)) => Some(delta),
+            Ok(_) => None,
+            Err(err) => panic!("expected an event before the reply, got {}", err),
+        })
+        .collect();
+
+    assert_eq!(deltas, channel_deltas);
+    assert_eq!(deltas.concat(), reply.content);
+    assert_eq!(reply.content, channel_reply.content);
+    assert_eq!(reply.message_type, MessageType::Assistant);
+}
+
+/// The events of one stream up to the reply, which must end it.
+async fn events_before_reply(client: &dyn PromptCore) -> Vec<StreamEvent> {
+    let mut events: Vec<StreamEvent> = collect_events(client)
+        .await
+        .into_iter()
+        .map(|event| event.expect("stream succeeds"))
+        .collect();
+
+    let Some(StreamEvent {
+        kind: StreamEventKind::Reply(reply),
+        ..
+    }) = events.pop()
+    else {
+        panic!("event stream should end with the reply");
+    };
+    assert_eq!(reply.content, "Let me check.");
+
+    events
+}
+
+/// What each provider's tool-calling stream below should produce, in the
+/// provider's own order, numbered from 0.
+fn assert_events(events: Vec<StreamEvent>, expected: Vec<StreamEventKind>) {
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, (0..expected.len() as u64).collect::<Vec<_>>());
+
+    // `StreamEventKind` can carry a `Message`, so it has no `PartialEq`
+    let kinds: Vec<StreamEventKind> = events.into_iter().map(|event| event.kind).collect();
+    assert_eq!(format!("{:?}", kinds), format!("{:?}", expected));
+}
+
+fn tool_call_start(index: usize, id: &str) -> StreamEventKind {
+    StreamEventKind::ToolCallStart {
+        index,
+        id: id.to_string(),
+        name: "get_weather".to_string(),
+    }
+}
+
+fn arguments(index: usize, delta: &str) -> StreamEventKind {
+    StreamEventKind::ToolCallArgumentsDelta {
+        index,
+        delta: delta.to_string(),
+    }
+}
+
+#[test]
+fn event_streams_match_the_channel_for_each_provider() {
+    run_with_mock_keys(async {
+        let chunks = ["Hello", " there", ", friend"];
+        let server = MockLLMServer::start(vec![
+            MockRoute::new(
+                "/v1/chat/completions",
+                vec![MockResponse::openai_text_stream(chunks); 2],
+            ),
+            MockRoute::new(
+                "/v1/messages",
+                vec![MockResponse::anthropic_text_stream(chunks); 2],
+            ),
+            MockRoute::new(
+                GEMINI_STREAM_PATH,
+                vec![MockResponse::gemini_text_stream(chunks); 2],
+            ),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let openai =
+            OpenAIClient::try_with_options("gpt-4o-mini", options.clone()).expect("known model");
+        let anthropic =
+            AnthropicClient::try_with_options("claude-3-5-haiku-20241022", options.clone())
+                .expect("known model");
+        let gemini =
+            GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model");
+
+        assert_same_as_channel(&openai).await;
+        assert_same_as_channel(&anthropic).await;
+        assert_same_as_channel(&gemini).await;
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn event_streams_end_with_the_error_of_a_failed_request() {
+    run_with_mock_keys(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::Json(
+                MockJsonResponse::new(serde_json::json!({
+                    "error": { "message": "bad request", "type": "invalid_request_error" }
+                }))
+                .with_status(400),
+            ),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o-mini",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        let events = collect_events(&client).await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            Err(WireError::Http { status: 400, .. })
+        ));
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn openai_streams_report_tool_calls_reasoning_and_usage() {
+    run_with_mock_keys(async {
+        let chunk = |choice: serde_json::Value| {
+            MockSseEvent::data_json(serde_json::json!({ "choices": [choice] }))
+        };
+        let stream = MockResponse::Sse(
+            MockSseResponse::new(vec![
+                chunk(serde_json::json!({ "delta": { "reasoning_content": "Rain?" } })),
+                chunk(serde_json::json!({ "delta": { "content": "Let me check." } })),
+                chunk(serde_json::json!({ "delta": { "tool_calls": [{
+                    "index": 0,
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "" }
+                }] } })),
+                chunk(serde_json::json!({ "delta": { "tool_calls": [{
+                    "index": 0,
+                    "function": { "arguments": "{\"city\":" }
+                }] } })),
+                chunk(serde_json::json!({ "delta": { "tool_calls": [{
+                    "index": 0,
+                    "function": { "arguments": "\"Paris\"}" }
+                }] } })),
+                chunk(serde_json::json!({ "delta": {}, "finish_reason": "tool_calls" })),
+                MockSseEvent::data_json(serde_json::json!({
+                    "choices": [],
+                    "usage": { "prompt_tokens": 12, "completion_tokens": 7 }
+                })),
+            ])
+            .with_done(),
+        );
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            "/v1/chat/completions",
+            vec![stream.clone(), stream],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o-mini",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        assert_events(
+            events_before_reply(&client).await,
+            vec![
+                StreamEventKind::FirstToken,
+                StreamEventKind::Thinking("Rain?".to_string()),
+                StreamEventKind::ContentDelta("Let me check.".to_string()),
+                tool_call_start(0, "call_1"),
+                arguments(0, "{\"city\":"),
+                arguments(0, "\"Paris\"}"),
+                StreamEventKind::Done {
+                    finish_reason: FinishReason::Other("tool_calls".to_string()),
+                },
+                StreamEventKind::Usage {
+                    input: 12,
+                    output: 7,
+                },
+            ],
+        );
+
+        // The channel only ever gets content
+        let (deltas, _) = collect_channel(&client).await;
+        assert_eq!(deltas, ["Let me check."]);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn anthropic_streams_report_tool_use_thinking_and_usage() {
+    run_with_mock_keys(async {
+        let event = |json: serde_json::Value| MockSseEvent::data_json(json);
+        let stream = MockResponse::Sse(MockSseResponse::new(vec![
+            event(serde_json::json!({
+                "type": "message_start",
+                "message": { "usage": { "input_tokens": 12, "output_tokens": 1 } }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_start",
+                "index": 0,
+                "content_block": { "type": "thinking", "thinking": "" }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": { "type": "thinking_delta", "thinking": "Rain?" }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_delta",
+                "index": 1,
+                "delta": { "type": "text_delta", "text": "Let me check." }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_start",
+                "index": 2,
+                "content_block": {
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "get_weather",
+                    "input": {}
+                }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_delta",
+                "index": 2,
+                "delta": { "type": "input_json_delta", "partial_json": "{\"city\":" }
+            })),
+            event(serde_json::json!({
+                "type": "content_block_delta",
+                "index": 2,
+                "delta": { "type": "input_json_delta", "partial_json": "\"Paris\"}" }
+            })),
+            event(serde_json::json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": "tool_use" },
+                "usage": { "output_tokens": 7 }
+            })),
+            MockSseEvent::event("message_stop"),
+        ]));
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            "/v1/messages",
+            vec![stream.clone(), stream],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = AnthropicClient::try_with_options(
+            "claude-3-5-haiku-20241022",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        assert_events(
+            events_before_reply(&client).await,
+            vec![
+                StreamEventKind::Usage {
+                    input: 12,
+                    output: 1,
+                },
+                StreamEventKind::FirstToken,
+                StreamEventKind::Thinking("Rain?".to_string()),
+                StreamEventKind::ContentDelta("Let me check.".to_string()),
+                tool_call_start(2, "toolu_1"),
+                arguments(2, "{\"city\":"),
+                arguments(2, "\"Paris\"}"),
+                StreamEventKind::Usage {
+                    input: 0,
+                    output: 7,
+                },
+                StreamEventKind::Done {
+                    finish_reason: FinishReason::Other("tool_use".to_string()),
+                },
+            ],
+        );
+
+        let (deltas, _) = collect_channel(&client).await;
+        assert_eq!(deltas, ["Let me check."]);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn gemini_streams_report_function_calls_thoughts_and_usage() {
+    run_with_mock_keys(async {
+        let stream = MockResponse::Chunked(MockChunkedResponse::new(vec![
+            serde_json::json!({ "candidates": [{ "content": { "parts": [
+                { "text": "Rain?", "thought": true }
+            ] } }] }),
+            serde_json::json!({ "candidates": [{ "content": { "parts": [
+                { "text": "Let me check." }
+            ] } }] }),
+            serde_json::json!({
+                "candidates": [{
+                    "content": { "parts": [
+                        { "functionCall": { "name": "get_weather", "args": { "city": "Paris" } } }
+                    ] },
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 7 }
+            }),
+        ]));
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            GEMINI_STREAM_PATH,
+            vec![stream.clone(), stream],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = GeminiClient::try_with_options(
+            "gemini-2.0-flash",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        assert_events(
+            events_before_reply(&client).await,
+            vec![
+                StreamEventKind::FirstToken,
+                StreamEventKind::Thinking("Rain?".to_string()),
+                StreamEventKind::ContentDelta("Let me check.".to_string()),
+                tool_call_start(0, ""),
+                arguments(0, "{\"city\":\"Paris\"}"),
+                StreamEventKind::Usage {
+                    input: 12,
+                    output: 7,
+                },
+                StreamEventKind::Done {
+                    finish_reason: FinishReason::Complete,
+                },
+            ],
+        );
+
+        let (deltas, _) = collect_channel(&client).await;
+        assert_eq!(deltas, ["Let me check."]);
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn prompt_options_events_take_the_place_of_the_channel() {
+    run_with_mock_keys(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::openai_text_stream(["Hello", " there"]),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o-mini",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::channel(16);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let reply = client
+            .prompt_stream_with_options(
+                history(),
+                "Be brief.".to_string(),
+                tx,
+                &PromptOptions::new().with_events(events_tx),
+            )
+            .await
+            .expect("stream succeeds");
+
+        assert_eq!(reply.content, "Hello there");
+        assert_eq!(rx.recv().await, None);
+
+        let mut events = Vec::new();
+        while let Some(event) = events_rx.recv().await {
+            events.push(event);
+        }
+        assert_events(
+            events,
+            vec![
+                StreamEventKind::FirstToken,
+                StreamEventKind::ContentDelta("Hello".to_string()),
+                StreamEventKind::ContentDelta(" there".to_string()),
+                StreamEventKind::Usage {
+                    input: 3,
+                    output: 2,
+                },
+            ],
+        );
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn echo_events_are_numbered_and_signal_the_first_token() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for event stream test");
+
+    runtime.block_on(async {
+        let mut events: Vec<StreamEvent> = collect_events(&EchoClient::new())
+            .await
+            .into_iter()
+            .map(|event| event.expect("stream succeeds"))
+            .collect();
+
+        let Some(StreamEvent {
+            kind: StreamEventKind::Reply(reply),
+            seq,
+        }) = events.pop()
+        else {
+            panic!("event stream should end with the reply");
+        };
+        assert_eq!(seq, 2);
+        assert_eq!(reply.content, "Hi");
+        assert_events(
+            events,
+            vec![
+                StreamEventKind::FirstToken,
+                StreamEventKind::ContentDelta("Hi".to_string()),
+            ],
+        );
+    });
+}
+
+#[test]
+fn event_streams_lose_nothing_to_a_slow_consumer() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for event stream test");
+
+    runtime.block_on(async {
+        // Channel settings are for `prompt_stream_with_options` alone
+        let options = PromptOptions::new().with_stream_options(
+            StreamOptions::new()
+                .with_channel_capacity(1)
+                .with_on_full(OnFull::DropOldest),
+        );
+        let client = EchoClient::new();
+        let mut events = client.prompt_stream_events_with_options(
+            String::new(),
+            vec![message(MessageType::User, "one two three four")],
+            &options,
+        );
+
+        let mut deltas = Vec::new();
+        while let Some(event) = std::future::poll_fn(|cx| events.as_mut().poll_next(cx)).await {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            match event.expect("stream succeeds").kind {
+                StreamEventKind::ContentDelta(delta) => deltas.push(delta),
+                StreamEventKind::Reply(reply) => assert!(reply.metadata.warnings.is_empty()),
+                _ => {}
+            }
+        }
+        assert_eq!(deltas, vec!["one ", "two ", "three ", "four"]);
+    });
+}
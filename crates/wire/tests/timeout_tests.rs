@@ -0,0 +1,172 @@
+#![cfg(feature = "openai")]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::message;
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use temp_env::with_var;
+use wire::api::PromptCore;
+use wire::config::ClientOptions;
+use wire::error::{TimeoutPhase, WireError};
+use wire::openai::OpenAIClient;
+use wire::retry::RetryPolicy;
+use wire::types::MessageType;
+
+const CHAT: &str = "/v1/chat/completions";
+
+#[test]
+fn timeouts_say_what_they_waited_for() {
+    let error = WireError::Timeout {
+        phase: TimeoutPhase::Request,
+    };
+    assert_eq!(error.to_string(), "request timed out");
+    assert!(RetryPolicy::is_transient(&error));
+
+    let error = WireError::Timeout {
+        phase: TimeoutPhase::Connect,
+    };
+    assert_eq!(error.to_string(), "connecting timed out");
+
+    // The stream reader's timeouts travel as io errors until `?`
+    let io = std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        WireError::Timeout {
+            phase: TimeoutPhase::Read,
+        },
+    );
+    assert!(matches!(
+        WireError::from(io),
+        WireError::Timeout {
+            phase: TimeoutPhase::Read
+        }
+    ));
+    let io = std::io::Error::from(std::io::ErrorKind::TimedOut);
+    assert!(matches!(WireError::from(io), WireError::Io(_)));
+}
+
+#[test]
+fn stalled_streams_say_how_long_they_waited() {
+    let error = WireError::StreamStalled {
+        idle: Duration::from_secs(90),
+        partial: "Hello, wor".to_string(),
+        request_id: None,
+    };
+    assert_eq!(
+        error.to_string(),
+        "stream stalled: nothing received for 90.0s after 10 bytes"
+    );
+    assert!(RetryPolicy::is_transient(&error));
+    assert_eq!(
+        ClientOptions::default().stream_idle_timeout,
+        Some(wire::config::DEFAULT_STREAM_IDLE_TIMEOUT)
+    );
+}
+
+#[cfg(feature = "mock")]
+fn reply(content: &str) -> MockJsonResponse {
+    MockJsonResponse::new(serde_json::json!({
+        "choices": [{ "message": { "content": content } }]
+    }))
+}
+
+#[cfg(feature = "mock")]
+async fn stream(client: &OpenAIClient) -> Result<wire::Message, WireError> {
+    let (tx, _rx) = tokio::sync::mpsc::channel(64);
+    client
+        .prompt_stream(
+            vec![message(MessageType::User, "Hi")],
+            "Be brief.".to_string(),
+            tx,
+        )
+        .await
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn hung_providers_time_out() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping timeout integration test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for timeout test");
+
+        runtime.block_on(async {
+            let hang = Duration::from_secs(3);
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                CHAT,
+                vec![
+                    MockResponse::Json(reply("Too late.").with_delay(hang)),
+                    MockResponse::Json(reply("In time.")),
+                    // The stream never starts, then stops partway
+                    MockResponse::Json(reply("Too late.").with_delay(hang)),
+                    MockResponse::openai_text_stream(["Stalled ", "stream."])
+                        .with_chunk_delay(hang),
+                    // Slow, but never this slow between two chunks
+                    MockResponse::openai_text_stream([
+                        "A ", "steady ", "stream ", "of ", "chunks.",
+                    ])
+                    .with_chunk_delay(Duration::from_millis(100)),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_connect_timeout(Duration::from_secs(1))
+                .with_request_timeout(Duration::from_millis(400));
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+            let ask = || {
+                client.prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                )
+            };
+
+            let started = Instant::now();
+            let error = ask().await.expect_err("the reply never comes");
+            assert!(
+                matches!(
+                    error,
+                    WireError::Timeout {
+                        phase: TimeoutPhase::Request
+                    }
+                ),
+                "{}",
+                error
+            );
+            assert!(started.elapsed() < hang);
+            assert_eq!(ask().await.expect("a prompt replies").content, "In time.");
+
+            for _ in 0..2 {
+                let started = Instant::now();
+                let error = stream(&client).await.expect_err("the stream stalls");
+                assert!(
+                    matches!(
+                        error,
+                        WireError::Timeout {
+                            phase: TimeoutPhase::Read
+                        }
+                    ),
+                    "{}",
+                    error
+                );
+                assert!(started.elapsed() < hang);
+            }
+
+            let started = Instant::now();
+            let reply = stream(&client).await.expect("every chunk arrives in time");
+            assert_eq!(reply.content, "A steady stream of chunks.");
+            assert!(started.elapsed() > Duration::from_millis(400));
+
+            server.shutdown().await;
+        });
+    });
+}
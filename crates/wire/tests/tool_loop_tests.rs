@@ -0,0 +1,599 @@
+#![cfg(all(feature = "openai", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{function_call, message, sample_tool};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use temp_env::with_var;
+use wire::api::ToolCapable;
+use wire::config::ClientOptions;
+use wire::echo::EchoClient;
+use wire::error::WireError;
+use wire::openai::OpenAIClient;
+use wire::tool_loop::{
+    pending_tool_calls, record_tool_outputs, BudgetNudge, ToolIteration, ToolLoopHooks,
+    ToolOutputPolicy, ToolOutputTruncation, ToolStatus, STATUS_HISTORY,
+};
+use wire::types::{ContextualToolWrapper, Message, MessageType, Tool, ToolContext, ToolWrapper};
+
+const NUDGE: &str = "One more step at most. Answer now.";
+
+fn tool_call_response(id: &str) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{
+            "message": {
+                "content": null,
+                "tool_calls": [{
+                    "id": id,
+                    "type": "function",
+                    "function": {
+                        "name": "echo",
+                        "arguments": serde_json::json!({ "value": id }).to_string()
+                    }
+                }]
+            }
+        }]
+    })))
+}
+
+fn final_response() -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "choices": [{ "message": { "content": "All done." } }]
+    })))
+}
+
+fn request_messages(body: &[u8]) -> Vec<serde_json::Value> {
+    let body: serde_json::Value = serde_json::from_slice(body).expect("json body");
+    body["messages"].as_array().expect("messages array").clone()
+}
+
+/// A conversation persisted mid-loop: the model asked for two tools and
+/// neither output was recorded.
+fn interrupted_history() -> Vec<Message> {
+    let mut call = message(MessageType::FunctionCall, "");
+    call.tool_calls = Some(vec![
+        function_call("call-1", "echo", serde_json::json!({ "value": "first" })),
+        function_call("call-2", "echo", serde_json::json!({ "value": "second" })),
+    ]);
+
+    vec![message(MessageType::User, "Call the tool twice"), call]
+}
+
+#[derive(Clone, Default)]
+struct RecordingHook {
+    seen: Arc<Mutex<Vec<(ToolIteration, usize)>>>,
+}
+
+impl ToolLoopHooks for RecordingHook {
+    fn before_iteration(&self, iteration: &ToolIteration, pending: &mut Vec<Message>) {
+        self.seen.lock().unwrap().push((*iteration, pending.len()));
+    }
+}
+
+#[test]
+fn tool_iteration_reports_remaining_budget() {
+    let first = ToolIteration {
+        index: 0,
+        max_iterations: Some(3),
+    };
+    let last = ToolIteration {
+        index: 2,
+        max_iterations: Some(3),
+    };
+    let unbounded = ToolIteration {
+        index: 7,
+        max_iterations: None,
+    };
+
+    assert_eq!(first.remaining(), Some(2));
+    assert!(!first.is_final());
+    assert_eq!(last.remaining(), Some(0));
+    assert!(last.is_final());
+    assert_eq!(unbounded.remaining(), None);
+    assert!(!unbounded.is_final());
+}
+
+#[test]
+fn budget_nudge_is_sent_on_final_iteration_only() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool loop budget nudge test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    tool_call_response("call-1"),
+                    tool_call_response("call-2"),
+                    final_response(),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let recorder = RecordingHook::default();
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_max_tool_iterations(3)
+                .with_tool_loop_hook(recorder.clone())
+                .with_tool_loop_hook(BudgetNudge::new().with_message(NUDGE));
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let history = client
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Call the tool twice")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool loop completes");
+
+            assert_eq!(history.last().unwrap().content, "All done.");
+            assert!(history.iter().all(|message| message.content != NUDGE));
+
+            {
+                let seen = recorder.seen.lock().unwrap();
+                let indices: Vec<usize> = seen.iter().map(|(it, _)| it.index).collect();
+                assert_eq!(indices, vec![0, 1, 2]);
+                assert_eq!(seen[2].0.max_iterations, Some(3));
+                // user message, then a call and its output per iteration
+                assert_eq!(
+                    seen.iter().map(|(_, len)| *len).collect::<Vec<_>>(),
+                    vec![1, 3, 5]
+                );
+            }
+
+            let requests = server.requests_for("/v1/chat/completions").await;
+            assert_eq!(requests.len(), 3);
+
+            for request in &requests[..2] {
+                let messages = request_messages(&request.body);
+                assert!(messages.iter().all(|message| message["content"] != NUDGE));
+            }
+
+            let last = request_messages(&requests[2].body);
+            let nudge = last.last().expect("final request has messages");
+            assert_eq!(nudge["role"], "user");
+            assert_eq!(nudge["content"], NUDGE);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn tool_loop_stops_at_max_iterations() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool loop iteration limit test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                tool_call_response("call-1"),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_max_tool_iterations(2);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let err = client
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Loop forever")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect_err("loop should hit its budget");
+
+            assert_eq!(err.to_string(), "tool loop exceeded 2 iterations");
+            assert_eq!(server.requests_for("/v1/chat/completions").await.len(), 2);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn pending_tool_calls_finds_unanswered_trailing_calls() {
+    let mut history = interrupted_history();
+    let ids = |calls: Vec<wire::types::FunctionCall>| -> Vec<String> {
+        calls.into_iter().map(|call| call.id).collect()
+    };
+    assert_eq!(ids(pending_tool_calls(&history)), vec!["call-1", "call-2"]);
+
+    let err = record_tool_outputs(
+        &mut history,
+        HashMap::from([("call-9".to_string(), "lost".to_string())]),
+    )
+    .expect_err("unknown call id is rejected");
+    assert!(matches!(&err, WireError::UnknownToolCall { id } if id == "call-9"));
+    assert_eq!(err.to_string(), "no pending tool call with id call-9");
+    assert_eq!(history.len(), 2);
+
+    record_tool_outputs(
+        &mut history,
+        HashMap::from([("call-1".to_string(), "cached".to_string())]),
+    )
+    .expect("pending call id is accepted");
+    let output = history.last().unwrap();
+    assert_eq!(output.message_type, MessageType::FunctionCallOutput);
+    assert_eq!(output.tool_call_id.as_deref(), Some("call-1"));
+    assert_eq!(output.name.as_deref(), Some("echo"));
+    assert_eq!(ids(pending_tool_calls(&history)), vec!["call-2"]);
+
+    history.push(message(MessageType::Assistant, "Done."));
+    assert!(pending_tool_calls(&history).is_empty());
+    assert!(pending_tool_calls(&[message(MessageType::User, "Hi")]).is_empty());
+}
+
+#[test]
+fn tool_loop_resumes_interrupted_history() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool loop resume test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::single(
+                "/v1/chat/completions",
+                final_response(),
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            // The application already ran the second call before stopping
+            let mut history = interrupted_history();
+            record_tool_outputs(
+                &mut history,
+                HashMap::from([("call-2".to_string(), "precomputed".to_string())]),
+            )
+            .expect("call-2 is pending");
+
+            let history = client
+                .prompt_with_tools("Follow instructions.", history, vec![sample_tool("echo")])
+                .await
+                .expect("tool loop resumes");
+
+            assert_eq!(history.len(), 5);
+            assert_eq!(history[3].tool_call_id.as_deref(), Some("call-1"));
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&history[3].content).unwrap(),
+                serde_json::json!({ "value": "first" })
+            );
+            assert_eq!(history.last().unwrap().content, "All done.");
+
+            let requests = server.requests_for("/v1/chat/completions").await;
+            assert_eq!(requests.len(), 1);
+
+            let messages = request_messages(&requests[0].body);
+            let outputs: Vec<(&str, &str)> = messages
+                .iter()
+                .filter(|message| message["role"] == "tool")
+                .map(|message| {
+                    (
+                        message["tool_call_id"].as_str().unwrap(),
+                        message["content"].as_str().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(outputs.len(), 2);
+            assert!(outputs.contains(&("call-2", "precomputed")));
+            assert!(outputs.iter().any(|(id, _)| *id == "call-1"));
+
+            server.shutdown().await;
+        });
+    });
+}
+
+fn conversation_tool() -> Tool {
+    let summarize = |_args: serde_json::Value, context: &ToolContext| {
+        serde_json::json!({
+            "call_id": context.call_id,
+            "tool": context.tool_name,
+            "iteration": context.iteration,
+            "seen": context
+                .history()
+                .iter()
+                .map(|message| message.content.clone())
+                .collect::<Vec<_>>(),
+        })
+    };
+
+    Tool::from_spec(
+        sample_tool("summarize").spec(),
+        ContextualToolWrapper(summarize),
+    )
+}
+
+#[test]
+fn contextual_tools_see_the_conversation() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+    let client = EchoClient::new();
+
+    let history = runtime
+        .block_on(client.prompt_with_tools(
+            "Follow instructions.",
+            vec![
+                message(MessageType::User, "My name is Ada."),
+                message(MessageType::Assistant, "Nice to meet you."),
+                message(MessageType::User, "CALL:summarize:{}"),
+            ],
+            vec![conversation_tool(), sample_tool("echo")],
+        ))
+        .expect("tool loop completes");
+
+    let output: serde_json::Value =
+        serde_json::from_str(&history.last().unwrap().content).expect("tool output is JSON");
+    assert_eq!(
+        output,
+        serde_json::json!({
+            "call_id": "echo_0_0",
+            "tool": "summarize",
+            "iteration": 0,
+            "seen": ["My name is Ada.", "Nice to meet you.", "CALL:summarize:{}", ""],
+        })
+    );
+}
+
+#[test]
+fn contextual_tools_get_an_empty_context_outside_the_loop() {
+    let tool = conversation_tool();
+
+    assert_eq!(
+        tool.function.call(serde_json::json!({}))["seen"],
+        serde_json::json!([])
+    );
+    // Plain tools ignore the context they are handed
+    assert_eq!(
+        sample_tool("echo")
+            .function
+            .call_with_context(serde_json::json!({ "a": 1 }), &ToolContext::default()),
+        serde_json::json!({ "a": 1 })
+    );
+}
+
+#[test]
+fn tool_output_policy_cuts_oversized_output() {
+    let output = format!("{}{}", "a".repeat(3000), "z".repeat(2000));
+    let head = ToolOutputPolicy::new(1024);
+    let tail = head.with_truncation(ToolOutputTruncation::Tail);
+
+    assert_eq!(head.apply("short"), "short");
+    assert_eq!(
+        head.apply(&output),
+        format!("{}...[truncated 3.9KB; 1024 bytes shown]", "a".repeat(1024))
+    );
+    assert_eq!(
+        tail.apply(&output),
+        format!("...[truncated 3.9KB; 1024 bytes shown]{}", "z".repeat(1024))
+    );
+
+    // Cuts never split a character
+    let snowmen = "\u{2603}".repeat(10);
+    assert_eq!(
+        ToolOutputPolicy::new(4).apply(&snowmen),
+        "\u{2603}...[truncated 27 bytes; 3 bytes shown]"
+    );
+
+    let dump = serde_json::json!({
+        "table": "orders",
+        "rows": (0..500).map(|id| serde_json::json!({ "id": id })).collect::<Vec<_>>(),
+    })
+    .to_string();
+    let summary = ToolOutputPolicy::new(256).with_truncation(ToolOutputTruncation::Summary);
+    assert_eq!(
+        summary.apply(&dump),
+        r#"{"rows": [500 items], "table": "orders"}...[truncated 5.3KB; outline shown]"#
+    );
+    // Not JSON, so the head it is
+    assert!(summary
+        .apply(&output)
+        .ends_with("...[truncated 4.6KB; 256 bytes shown]"));
+}
+
+#[test]
+fn oversized_tool_output_is_truncated_for_the_model() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool output policy test");
+        return;
+    }
+
+    // Serialized with its quotes: 1_260_002 bytes
+    let dump = serde_json::json!("x".repeat(1_260_000)).to_string();
+    let tool = Tool::from_spec(
+        sample_tool("echo").spec(),
+        ToolWrapper(|_| serde_json::json!("x".repeat(1_260_000))),
+    );
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![tool_call_response("call-1"), final_response()],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_tool_output_policy(ToolOutputPolicy::new(4096));
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let history = client
+                .prompt_with_tools(
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Dump the table")],
+                    vec![tool],
+                )
+                .await
+                .expect("tool loop completes");
+
+            let shown = format!("{}...[truncated 1.2MB; 4096 bytes shown]", &dump[..4096]);
+            let output = &history[2];
+            assert_eq!(output.message_type, MessageType::FunctionCallOutput);
+            assert_eq!(output.content, shown);
+
+            let invocation = output
+                .metadata
+                .tool_invocation
+                .as_ref()
+                .expect("audit record kept");
+            assert_eq!(invocation.call_id, "call-1");
+            assert_eq!(invocation.name, "echo");
+            assert_eq!(invocation.arguments, r#"{"value":"call-1"}"#);
+            assert_eq!(invocation.output, dump);
+            assert!(invocation.truncated);
+
+            let requests = server.requests_for("/v1/chat/completions").await;
+            let sent = request_messages(&requests[1].body);
+            assert_eq!(sent[3]["role"], "tool");
+            assert_eq!(sent[3]["content"], shown);
+            assert!(requests[1].body.len() < 16 * 1024);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn status_events_are_kept_without_a_receiver() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping tool status history test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+
+        runtime.block_on(async {
+            let interim = || {
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{
+                        "message": {
+                            "content": "Let me echo that.",
+                            "tool_calls": [{
+                                "id": "call-1",
+                                "type": "function",
+                                "function": { "name": "echo", "arguments": "{\"value\":\"hello\"}" }
+                            }]
+                        }
+                    }]
+                })))
+            };
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![interim(), final_response(), interim(), final_response()],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options =
+                ClientOptions::for_mock_server(&server).expect("client options for mock server");
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+            let expected = vec![
+                ToolStatus::Interim("Let me echo that.".to_string()),
+                ToolStatus::Calling {
+                    id: "call-1".to_string(),
+                    name: "echo".to_string(),
+                },
+            ];
+
+            let result = client
+                .run_tool_loop(
+                    None,
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Echo hello")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool loop completes");
+            assert_eq!(result.messages.len(), 4);
+            assert_eq!(result.events, expected);
+            assert_eq!(result.dropped_events, 0);
+
+            // A receiver that is gone loses nothing either
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            drop(rx);
+            let result = client
+                .run_tool_loop(
+                    Some(tx),
+                    "Follow instructions.",
+                    vec![message(MessageType::User, "Echo hello")],
+                    vec![sample_tool("echo")],
+                )
+                .await
+                .expect("tool loop completes");
+            assert_eq!(result.events, expected);
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn status_history_keeps_the_latest_events() {
+    let runtime = tokio::runtime::Runtime::new().expect("runtime for tool loop test");
+    let client = EchoClient::new();
+    let calls = STATUS_HISTORY + 10;
+    let script: Vec<String> = (0..calls)
+        .map(|index| format!("CALL:echo:{{\"value\":{}}}", index))
+        .collect();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(calls);
+    let result = runtime
+        .block_on(client.run_tool_loop(
+            Some(tx),
+            "Use tools.",
+            vec![message(MessageType::User, &script.join("\n"))],
+            vec![sample_tool("echo")],
+        ))
+        .expect("tool loop completes");
+
+    assert_eq!(result.events.len(), STATUS_HISTORY);
+    assert_eq!(result.dropped_events, 10);
+    assert_eq!(
+        result.events[0],
+        ToolStatus::Calling {
+            id: "echo_0_10".to_string(),
+            name: "echo".to_string(),
+        }
+    );
+
+    let mut live = Vec::new();
+    while let Ok(status) = rx.try_recv() {
+        live.push(status);
+    }
+    assert_eq!(live.len(), calls);
+    assert_eq!(
+        live[calls - 1],
+        result.events[STATUS_HISTORY - 1].to_string()
+    );
+}
@@ -0,0 +1,275 @@
+#![cfg(all(feature = "openai", feature = "gemini", feature = "mock"))]
+
+mod common;
+
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{function_call, message, sample_tool};
+use temp_env::with_var;
+use wire::api::ToolCapable;
+use wire::config::ClientOptions;
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::tool_protocol::{TextToolProtocol, ToolTransport};
+use wire::types::{MessageType, Tool, ToolWrapper};
+
+fn add_tool() -> Tool {
+    Tool::from_spec(
+        wire::types::ToolSpec {
+            name: "add".to_string(),
+            description: "Add two integers".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } }
+            }),
+            strict: false,
+        },
+        ToolWrapper(|args: serde_json::Value| {
+            serde_json::json!(args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0))
+        }),
+    )
+}
+
+fn gemini_text(text: &str) -> MockResponse {
+    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+        "candidates": [{ "content": { "parts": [{ "text": text }] } }]
+    })))
+}
+
+#[test]
+fn text_protocol_system_prompt_lists_tools() {
+    let protocol = TextToolProtocol::new();
+    let prompt = protocol.system_prompt("Be brief.", &[add_tool().spec()]);
+
+    assert!(prompt.starts_with("Be brief.\n\n# Tools"));
+    assert!(prompt.contains("- `add`: Add two integers"));
+    assert!(prompt.contains("\"integer\""));
+    assert!(prompt.contains("```tool_call"));
+}
+
+#[test]
+fn text_protocol_parses_multiple_calls() {
+    let protocol = TextToolProtocol::new();
+    let content = "Let me work that out.\n\n\
+        ```tool_call\n{\"name\": \"add\", \"arguments\": {\"a\": 1, \"b\": 2}}\n```\n\
+        ```tool_call\n[{\"name\": \"add\", \"arguments\": \"{\\\"a\\\": 3, \\\"b\\\": 4}\"},\
+        {\"name\": \"lookup_weather\"}]\n```";
+
+    let calls = protocol.parse_tool_calls(content).expect("calls parse");
+
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].id, "call_0");
+    assert_eq!(calls[0].function.name, "add");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&calls[0].function.arguments).unwrap(),
+        serde_json::json!({ "a": 1, "b": 2 })
+    );
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&calls[1].function.arguments).unwrap(),
+        serde_json::json!({ "a": 3, "b": 4 })
+    );
+    assert_eq!(calls[2].id, "call_2");
+    assert_eq!(calls[2].function.arguments, "{}");
+    assert_eq!(protocol.strip_tool_calls(content), "Let me work that out.");
+}
+
+#[test]
+fn text_protocol_repairs_malformed_json() {
+    let protocol = TextToolProtocol::new();
+
+    let trailing_comma =
+        "```tool_call\n{\"name\": \"add\", \"arguments\": {\"a\": 1, \"b\": 2,},}\n```";
+    let unterminated = "```tool_call\n{\"name\": \"add\", \"arguments\": {\"a\": 5, \"b\": 6";
+
+    let calls = protocol
+        .parse_tool_calls(trailing_comma)
+        .expect("trailing comma repaired");
+    assert_eq!(calls[0].function.arguments, r#"{"a":1,"b":2}"#);
+
+    let calls = protocol
+        .parse_tool_calls(unterminated)
+        .expect("unterminated block repaired");
+    assert_eq!(calls[0].function.arguments, r#"{"a":5,"b":6}"#);
+
+    assert!(protocol
+        .parse_tool_calls("```tool_call\nadd(1, 2)\n```")
+        .is_err());
+    assert!(protocol
+        .parse_tool_calls("no tools needed")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn text_protocol_renders_tool_traffic_as_text() {
+    let protocol = TextToolProtocol::new();
+
+    let mut call = message(MessageType::FunctionCall, "Checking.");
+    call.tool_calls = Some(vec![function_call(
+        "call_0_0",
+        "add",
+        serde_json::json!({ "a": 1, "b": 2 }),
+    )]);
+    let mut output = message(MessageType::FunctionCallOutput, "3");
+    output.tool_call_id = Some("call_0_0".to_string());
+    output.name = Some("add".to_string());
+
+    let rendered = protocol.render_history(&[message(MessageType::User, "1 + 2?"), call, output]);
+
+    assert_eq!(rendered[0].content, "1 + 2?");
+    assert_eq!(rendered[1].message_type, MessageType::Assistant);
+    assert!(rendered[1].tool_calls.is_none());
+    assert_eq!(
+        rendered[1].content,
+        "Checking.\n\n```tool_call\n{\"arguments\":{\"a\":1,\"b\":2},\"name\":\"add\"}\n```"
+    );
+    assert_eq!(rendered[2].message_type, MessageType::User);
+    assert_eq!(
+        rendered[2].content,
+        "```tool_result\n{\"name\":\"add\",\"output\":3}\n```"
+    );
+}
+
+#[test]
+fn gemini_text_protocol_tool_loop() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping gemini text protocol test");
+        return;
+    }
+
+    with_var("GEMINI_API_KEY", Some("mock-gemini-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool test");
+
+        runtime.block_on(async {
+            let path = "/v1beta/models/gemini-2.0-flash:generateContent?key=mock-gemini-key";
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                path,
+                vec![
+                    gemini_text(
+                        "```tool_call\n{\"name\": \"add\", \"arguments\": {\"a\": 2, \"b\": 3}}\n```\n\
+                         ```tool_call\n{\"name\": \"add\", \"arguments\": {\"a\": 10, \"b\": 20,}}\n```",
+                    ),
+                    gemini_text("2 + 3 = 5 and 10 + 20 = 30."),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_tool_transport(ToolTransport::TextProtocol);
+            let client = GeminiClient::try_with_options("gemini-2.0-flash", options).expect("known model");
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+            let history = client
+                .prompt_with_tools_with_status(
+                    tx,
+                    "You are a calculator.",
+                    vec![message(MessageType::User, "What are 2 + 3 and 10 + 20?")],
+                    vec![add_tool()],
+                )
+                .await
+                .expect("tool loop completes");
+
+            assert_eq!(history.len(), 5);
+            assert_eq!(history[1].message_type, MessageType::FunctionCall);
+            assert_eq!(history[1].tool_calls.as_ref().unwrap().len(), 2);
+            assert_eq!(history[2].message_type, MessageType::FunctionCallOutput);
+            assert_eq!(history[2].content, "5");
+            assert_eq!(history[2].tool_call_id.as_deref(), Some("call_0_0"));
+            assert_eq!(history[3].content, "30");
+            assert_eq!(history[3].tool_call_id.as_deref(), Some("call_0_1"));
+            assert_eq!(history[4].message_type, MessageType::Assistant);
+            assert_eq!(history[4].content, "2 + 3 = 5 and 10 + 20 = 30.");
+            assert_eq!(history[4].system_prompt, "You are a calculator.");
+
+            assert_eq!(rx.recv().await.as_deref(), Some("calling tool add..."));
+
+            let requests = server.requests_for(path).await;
+            assert_eq!(requests.len(), 2);
+
+            let first: serde_json::Value =
+                serde_json::from_slice(&requests[0].body).expect("json body");
+            let system = first["system_instruction"]["parts"][0]["text"]
+                .as_str()
+                .unwrap();
+            assert!(system.starts_with("You are a calculator.\n\n# Tools"));
+            assert!(first.get("tools").is_none());
+
+            let second: serde_json::Value =
+                serde_json::from_slice(&requests[1].body).expect("json body");
+            let contents = second["contents"].as_array().unwrap();
+            assert_eq!(contents.len(), 4);
+            assert_eq!(contents[1]["role"], "model");
+            assert!(contents[1]["parts"][0]["text"]
+                .as_str()
+                .unwrap()
+                .contains("```tool_call"));
+            assert_eq!(contents[2]["role"], "user");
+            assert_eq!(
+                contents[2]["parts"][0]["text"],
+                "```tool_result\n{\"name\":\"add\",\"output\":5}\n```"
+            );
+
+            server.shutdown().await;
+        });
+    });
+}
+
+#[test]
+fn openai_text_protocol_skips_native_tools() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping openai text protocol test");
+        return;
+    }
+
+    with_var("OPENAI_API_KEY", Some("mock-openai-key"), || {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for tool test");
+
+        runtime.block_on(async {
+            let reply = |content: &str| {
+                MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                    "choices": [{ "message": { "content": content } }]
+                })))
+            };
+            let server = MockLLMServer::start(vec![MockRoute::new(
+                "/v1/chat/completions",
+                vec![
+                    reply("```tool_call\n{\"name\": \"lookup_weather\", \"arguments\": {}}\n```"),
+                    reply("It's snowing."),
+                ],
+            )])
+            .await
+            .expect("mock server starts");
+
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_tool_transport(ToolTransport::TextProtocol);
+            let client =
+                OpenAIClient::try_with_options("gpt-4o-mini", options).expect("known model");
+
+            let history = client
+                .prompt_with_tools(
+                    "Be brief.",
+                    vec![message(MessageType::User, "Weather?")],
+                    vec![sample_tool("lookup_weather")],
+                )
+                .await
+                .expect("tool loop completes");
+
+            assert_eq!(history.last().unwrap().content, "It's snowing.");
+            // sample_tool echoes its arguments back
+            assert_eq!(history[2].content, "{}");
+
+            for request in server.requests_for("/v1/chat/completions").await {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("json body");
+                assert!(body.get("tools").is_none());
+                for message in body["messages"].as_array().unwrap() {
+                    assert_ne!(message["role"], "tool");
+                }
+            }
+
+            server.shutdown().await;
+        });
+    });
+}
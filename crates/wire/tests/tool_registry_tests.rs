@@ -0,0 +1,135 @@
+mod common;
+
+use common::sample_tool;
+use wire::tools::{ToolRegistry, ToolRegistryError};
+use wire::types::{Tool, ToolSpec, ToolWrapper};
+
+const CATALOG: &str = r#"[
+    {
+        "name": "lookup_weather",
+        "description": "Look up the forecast for a city",
+        "parameters": {
+            "type": "object",
+            "properties": { "location": { "type": "string" } }
+        }
+    },
+    {
+        "name": "add",
+        "description": "Add two integers",
+        "parameters": {
+            "type": "object",
+            "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } }
+        }
+    }
+]"#;
+
+fn add(args: serde_json::Value) -> serde_json::Value {
+    serde_json::json!(args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0))
+}
+
+fn forecast(_args: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "forecast": "snow" })
+}
+
+#[test]
+fn tool_spec_round_trips_through_json() {
+    let spec = sample_tool("lookup_weather").spec();
+
+    let json = serde_json::to_string(&spec).expect("spec serializes");
+    let restored: ToolSpec = serde_json::from_str(&json).expect("spec deserializes");
+
+    assert_eq!(restored, spec);
+    assert_eq!(
+        serde_json::to_value(&spec).unwrap(),
+        serde_json::json!({
+            "name": "lookup_weather",
+            "description": "example tool",
+            "parameters": { "type": "object", "properties": {} }
+        })
+    );
+}
+
+#[test]
+fn tool_from_spec_keeps_schema_and_function() {
+    let spec = sample_tool("echo").spec();
+    let tool = Tool::from_spec(spec.clone(), ToolWrapper(add));
+
+    assert_eq!(tool.spec(), spec);
+    assert_eq!(tool.function_type, "function");
+    assert_eq!(
+        tool.function.call(serde_json::json!({ "a": 2, "b": 3 })),
+        serde_json::json!(5)
+    );
+}
+
+#[test]
+fn registry_binds_implementations_loaded_from_json() {
+    let mut registry = ToolRegistry::from_json(CATALOG).expect("catalog parses");
+    assert_eq!(registry.unbound(), vec!["lookup_weather", "add"]);
+
+    registry
+        .bind("lookup_weather", ToolWrapper(forecast))
+        .expect("spec exists");
+    registry.bind("add", ToolWrapper(add)).expect("spec exists");
+
+    let tools = registry.tools().expect("all tools bound");
+    assert_eq!(tools.len(), 2);
+    assert_eq!(tools[1].name, "add");
+    assert_eq!(
+        tools[1]
+            .function
+            .call(serde_json::json!({ "a": 20, "b": 22 })),
+        serde_json::json!(42)
+    );
+    assert_eq!(
+        tools[0].function.call(serde_json::Value::Null),
+        serde_json::json!({ "forecast": "snow" })
+    );
+}
+
+#[test]
+fn registry_rejects_unbound_specs() {
+    let mut registry = ToolRegistry::from_json(CATALOG).expect("catalog parses");
+    registry.bind("add", ToolWrapper(add)).expect("spec exists");
+
+    let err = registry.tools().expect_err("lookup_weather is unbound");
+    assert_eq!(
+        err,
+        ToolRegistryError::Unbound(vec!["lookup_weather".to_string()])
+    );
+}
+
+#[test]
+fn registry_rejects_unknown_and_duplicate_tools() {
+    let mut registry = ToolRegistry::from_json(CATALOG).expect("catalog parses");
+
+    assert_eq!(
+        registry.bind("subtract", ToolWrapper(add)),
+        Err(ToolRegistryError::UnknownTool("subtract".to_string()))
+    );
+    assert_eq!(
+        registry.register(Tool::from_spec(
+            registry.spec("add").unwrap().clone(),
+            ToolWrapper(add)
+        )),
+        Err(ToolRegistryError::DuplicateTool("add".to_string()))
+    );
+    assert!(matches!(
+        ToolRegistry::from_json("{ not json"),
+        Err(ToolRegistryError::Parse(_))
+    ));
+}
+
+#[test]
+fn registry_serializes_specs_only() {
+    let mut registry = ToolRegistry::new();
+    registry
+        .register(sample_tool("lookup_weather"))
+        .expect("first registration");
+
+    let reloaded = ToolRegistry::from_json(&registry.to_json()).expect("catalog reloads");
+
+    assert_eq!(reloaded.specs(), registry.specs());
+    assert_eq!(reloaded.unbound(), vec!["lookup_weather"]);
+    assert!(registry.unbound().is_empty());
+}
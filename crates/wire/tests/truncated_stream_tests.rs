@@ -0,0 +1,191 @@
+#![cfg(all(
+    feature = "openai",
+    feature = "anthropic",
+    feature = "gemini",
+    feature = "mock"
+))]
+
+mod common;
+
+use common::mock_server::{MockLLMServer, MockResponse, MockRoute, MockSseEvent, MockSseResponse};
+use common::{message, run_with_mock_keys};
+use std::time::{Duration, Instant};
+use wire::anthropic::AnthropicClient;
+use wire::api::PromptCore;
+use wire::config::{ClientOptions, PromptOptions};
+use wire::gemini::GeminiClient;
+use wire::openai::OpenAIClient;
+use wire::types::{FinishReason, Message, MessageType, TruncatedStream};
+use wire::warning::WireWarning;
+
+const GEMINI_STREAM_PATH: &str =
+    "/v1beta/models/gemini-2.0-flash:streamGenerateContent?key=mock-gemini-key";
+
+/// An OpenAI stream of text deltas, without the usage chunk so that the
+/// last event carries content.
+fn openai_deltas(chunks: [&str; 3]) -> MockSseResponse {
+    MockSseResponse::new(
+        chunks
+            .into_iter()
+            .map(|text| {
+                MockSseEvent::data_json(serde_json::json!({
+                    "choices": [{ "delta": { "content": text } }]
+                }))
+            })
+            .collect(),
+    )
+}
+
+/// An Anthropic stream of text deltas, without the closing `message_stop`
+/// so that the last event carries JSON.
+fn anthropic_deltas(chunks: [&str; 3]) -> MockSseResponse {
+    MockSseResponse::new(
+        chunks
+            .into_iter()
+            .map(|text| {
+                MockSseEvent::data_json(serde_json::json!({
+                    "type": "content_block_delta",
+                    "delta": { "text": text }
+                }))
+            })
+            .collect(),
+    )
+}
+
+async fn stream(client: &dyn PromptCore, options: &PromptOptions) -> Result<Message, String> {
+    let (tx, _rx) = tokio::sync::mpsc::channel(8);
+    client
+        .prompt_stream_with_options(
+            vec![message(MessageType::User, "Hi")],
+            "Be brief.".to_string(),
+            tx,
+            options,
+        )
+        .await
+        .map_err(|err| err.to_string())
+}
+
+fn assert_recovered(reply: Message, tail: &str) {
+    assert_eq!(reply.content, "Hello world");
+    assert_eq!(
+        reply.metadata.truncated_stream,
+        Some(TruncatedStream {
+            tail: tail.to_string()
+        })
+    );
+    assert_eq!(
+        reply.metadata.finish_reason(),
+        Some(FinishReason::Other("truncated_stream".to_string()))
+    );
+    assert!(matches!(
+        reply.metadata.warnings.as_slice(),
+        [WireWarning::StreamTruncated { tail_bytes, .. }] if *tail_bytes == tail.len()
+    ));
+}
+
+#[test]
+fn sse_streams_keep_content_before_a_cut_final_event() {
+    run_with_mock_keys(async {
+        let cut_openai = MockResponse::Sse(openai_deltas(["Hello", " world", "!"])).cut_short(20);
+        let cut_anthropic =
+            MockResponse::Sse(anthropic_deltas(["Hello", " world", "!"])).cut_short(24);
+        let server = MockLLMServer::start(vec![
+            MockRoute::new("/v1/chat/completions", vec![cut_openai.clone(), cut_openai]),
+            MockRoute::new("/v1/messages", vec![cut_anthropic.clone(), cut_anthropic]),
+        ])
+        .await
+        .expect("mock server starts");
+
+        let client_options =
+            ClientOptions::for_mock_server(&server).expect("client options for mock server");
+        let openai = OpenAIClient::try_with_options("gpt-4o-mini", client_options.clone())
+            .expect("known model");
+        let anthropic =
+            AnthropicClient::try_with_options("claude-3-5-haiku-20241022", client_options)
+                .expect("known model");
+        let strict = PromptOptions::new().with_strict_stream_end(true);
+
+        let reply = stream(&openai, &PromptOptions::new())
+            .await
+            .expect("openai stream recovers");
+        assert_recovered(reply, "{\"choices\":[{\"");
+        stream(&openai, &strict)
+            .await
+            .expect_err("strict openai stream fails");
+
+        let reply = stream(&anthropic, &PromptOptions::new())
+            .await
+            .expect("anthropic stream recovers");
+        assert_recovered(reply, "{\"delta\":{\"text\":\"");
+        stream(&anthropic, &strict)
+            .await
+            .expect_err("strict anthropic stream fails");
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn gemini_keeps_content_before_a_cut_final_chunk() {
+    run_with_mock_keys(async {
+        let cut = MockResponse::gemini_text_stream(["Hello", " world", "!"]).cut_short(12);
+        let server = MockLLMServer::start(vec![MockRoute::new(
+            GEMINI_STREAM_PATH,
+            vec![cut.clone(), cut],
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = GeminiClient::try_with_options(
+            "gemini-2.0-flash",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        let reply = stream(&client, &PromptOptions::new())
+            .await
+            .expect("gemini stream recovers");
+        assert_recovered(reply, ",\r\n{\"candida");
+        stream(&client, &PromptOptions::new().with_strict_stream_end(true))
+            .await
+            .expect_err("strict gemini stream fails");
+
+        server.shutdown().await;
+    });
+}
+
+#[test]
+fn deadline_prompts_report_a_cut_stream() {
+    run_with_mock_keys(async {
+        let server = MockLLMServer::start(vec![MockRoute::single(
+            "/v1/chat/completions",
+            MockResponse::Sse(openai_deltas(["Hello", " world", "!"])).cut_short(20),
+        )])
+        .await
+        .expect("mock server starts");
+
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o-mini",
+            ClientOptions::for_mock_server(&server).expect("client options for mock server"),
+        )
+        .expect("known model");
+
+        let response = client
+            .prompt_with_deadline(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+                Instant::now() + Duration::from_secs(5),
+            )
+            .await
+            .expect("stream recovers");
+
+        assert_eq!(response.message.content, "Hello world");
+        assert_eq!(
+            response.finish_reason,
+            FinishReason::Other("truncated_stream".to_string())
+        );
+        assert!(response.is_partial());
+
+        server.shutdown().await;
+    });
+}
@@ -0,0 +1,243 @@
+#![cfg(all(feature = "openai", feature = "anthropic"))]
+#![cfg_attr(not(feature = "mock"), allow(unused_imports, dead_code))]
+
+mod common;
+
+#[cfg(feature = "mock")]
+use common::mock_server::{MockJsonResponse, MockLLMServer, MockResponse, MockRoute};
+use common::{message, sample_tool};
+use std::sync::{Arc, Mutex};
+use temp_env::with_vars;
+use wire::anthropic::AnthropicClient;
+use wire::api::{PromptCore, ToolCapable};
+use wire::config::{ClientOptions, PromptOptions, ThinkingLevel, ToolChoice};
+use wire::error::WireError;
+use wire::event_log::{EventSink, WireEvent};
+use wire::openai::OpenAIClient;
+use wire::tool_protocol::ToolTransport;
+use wire::types::MessageType;
+use wire::warning::WireWarning;
+
+fn keys<R>(f: impl FnOnce() -> R) -> R {
+    with_vars(
+        [
+            ("ANTHROPIC_API_KEY", Some("mock-anthropic-key")),
+            ("OPENAI_API_KEY", Some("mock-openai-key")),
+        ],
+        f,
+    )
+}
+
+#[derive(Default)]
+struct MemorySink(Mutex<Vec<WireEvent>>);
+
+impl EventSink for MemorySink {
+    fn record(&self, event: &WireEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+fn is_ignored(warning: &WireWarning, option: &str, provider: &str) -> bool {
+    matches!(
+        warning,
+        WireWarning::OptionIgnored { option: o, provider: p, .. } if o == option && p == provider
+    )
+}
+
+#[test]
+fn denied_warnings_fail_before_anything_is_sent() {
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for warning test");
+        // Nothing listens here, so reaching the network would fail differently
+        let options = || {
+            ClientOptions::from_base_url("http://127.0.0.1:9")
+                .expect("base url parses")
+                .deny_warnings()
+        };
+
+        let client = AnthropicClient::try_with_options(
+            "claude-3-5-sonnet-20241022",
+            options().with_thinking_level(ThinkingLevel::High),
+        )
+        .expect("known model");
+        let error = runtime
+            .block_on(client.prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+            ))
+            .expect_err("thinking level is denied");
+        match &error {
+            WireError::Warning { warning } => {
+                assert!(is_ignored(warning, "thinking_level", "anthropic"))
+            }
+            other => panic!("expected a denied warning, got {:?}", other),
+        }
+        assert!(error.to_string().ends_with("(warnings are denied)"));
+
+        let client = AnthropicClient::try_with_options(
+            "claude-3-haiku-20240307",
+            options().with_max_tokens(10_000),
+        )
+        .expect("known model");
+        let error = runtime
+            .block_on(client.prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+            ))
+            .expect_err("clamped max_tokens is denied");
+        match error {
+            WireError::Warning { warning } => assert_eq!(
+                warning,
+                WireWarning::FieldClamped {
+                    field: "max_tokens".to_string(),
+                    provider: "anthropic".to_string(),
+                    requested: 10_000,
+                    sent: 4096,
+                }
+            ),
+            other => panic!("expected a denied warning, got {:?}", other),
+        }
+
+        let client = OpenAIClient::try_with_options(
+            "gpt-4o",
+            options()
+                .with_tool_transport(ToolTransport::TextProtocol)
+                .with_tool_choice(ToolChoice::Any),
+        )
+        .expect("known model");
+        let error = runtime
+            .block_on(client.prompt_with_tools(
+                "Use the tools.",
+                vec![message(MessageType::User, "Hi")],
+                vec![sample_tool("echo")],
+            ))
+            .expect_err("tool choice is denied");
+        match &error {
+            WireError::Warning { warning } => {
+                assert!(is_ignored(warning, "tool_choice", "openai"))
+            }
+            other => panic!("expected a denied warning, got {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn options_that_apply_raise_no_warnings() {
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for warning test");
+        // GPT-5 takes a thinking level, so only the unreachable server fails
+        let options = ClientOptions::from_base_url("http://127.0.0.1:9")
+            .expect("base url parses")
+            .with_thinking_level(ThinkingLevel::High)
+            .deny_warnings();
+        let client = OpenAIClient::try_with_options("gpt-5", options).expect("known model");
+
+        let error = runtime
+            .block_on(client.prompt(
+                "Be brief.".to_string(),
+                vec![message(MessageType::User, "Hi")],
+            ))
+            .expect_err("nothing is listening");
+        assert!(matches!(error, WireError::Transport(_)));
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn warnings_reach_the_reply_and_the_event_log() {
+    if std::env::var("WIRE_RUN_MOCK_SERVER_TESTS").is_err() {
+        eprintln!("skipping warning integration test");
+        return;
+    }
+
+    keys(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("runtime for warning test");
+
+        runtime.block_on(async {
+            let server = MockLLMServer::start(vec![
+                MockRoute::single(
+                    "/v1/messages",
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "content": [{ "type": "text", "text": "Hello." }]
+                    }))),
+                ),
+                MockRoute::single(
+                    "/v1/chat/completions",
+                    MockResponse::Json(MockJsonResponse::new(serde_json::json!({
+                        "choices": [{ "message": { "content": "Hello." } }]
+                    }))),
+                ),
+            ])
+            .await
+            .expect("mock server starts");
+
+            let sink = Arc::new(MemorySink::default());
+            let options = ClientOptions::for_mock_server(&server)
+                .expect("client options for mock server")
+                .with_thinking_level(ThinkingLevel::Low)
+                .with_max_tokens(100_000)
+                .with_event_log(sink.clone());
+
+            let anthropic =
+                AnthropicClient::try_with_options("claude-3-5-sonnet-20241022", options.clone()).expect("known model");
+            let reply = anthropic
+                .prompt(
+                    "Be brief.".to_string(),
+                    vec![message(MessageType::User, "Hi")],
+                )
+                .await
+                .expect("prompt is sent despite the warnings");
+            assert_eq!(reply.metadata.warnings.len(), 2);
+            assert!(is_ignored(&reply.metadata.warnings[0], "thinking_level", "anthropic"));
+            assert_eq!(
+                reply.metadata.warnings[1],
+                WireWarning::FieldClamped {
+                    field: "max_tokens".to_string(),
+                    provider: "anthropic".to_string(),
+                    requested: 100_000,
+                    sent: 8192,
+                }
+            );
+            let recorded = server.requests_for("/v1/messages").await;
+            let body: serde_json::Value =
+                serde_json::from_slice(&recorded[0].body).expect("request body parses as json");
+            assert_eq!(body["max_tokens"], 8192);
+
+            // An orphan tool output is rewritten, and wire's own header kept
+            let mut orphan = message(MessageType::FunctionCallOutput, "sunny");
+            orphan.tool_call_id = Some("call-9".to_string());
+            let openai = OpenAIClient::try_with_options("gpt-4o", options).expect("known model");
+            let reply = openai
+                .prompt_with_options(
+                    "Be brief.".to_string(),
+                    vec![orphan, message(MessageType::User, "Weather?")],
+                    &PromptOptions::default().with_extra_headers([("Authorization", "Bearer x")]),
+                )
+                .await
+                .expect("prompt is sent despite the warnings");
+            let warnings = &reply.metadata.warnings;
+            assert_eq!(warnings.len(), 3);
+            assert!(is_ignored(&warnings[0], "thinking_level", "openai"));
+            assert!(is_ignored(&warnings[1], "extra header `Authorization`", "openai"));
+            assert!(matches!(
+                &warnings[2],
+                WireWarning::HistoryNormalized { message_index: 0, provider, .. } if provider == "openai"
+            ));
+
+            let logged: Vec<WireWarning> = sink
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|event| match event {
+                    WireEvent::Warning { warning } => Some(warning.clone()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(logged.len(), 5);
+            assert_eq!(&logged[2..], warnings.as_slice());
+
+            server.shutdown().await;
+        });
+    });
+}
@@ -0,0 +1,61 @@
+#!/usr/bin/env bash
+# Build, lint and test the wire crate under each supported feature set.
+#
+# Usage: scripts/feature-matrix.sh [extra cargo args, e.g. --offline]
+# Set WIRE_RUN_MOCK_SERVER_TESTS=1 to include the mock server tests. The live
+# tests are only built here; run them with `cargo test -p wire --features live
+# -- --ignored`.
+
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+feature_sets=(
+    ""
+    "openai"
+    "anthropic"
+    "gemini"
+    "mock"
+    "anthropic,mock"
+    "openai,mock"
+    "gemini,mock"
+    "groq"
+    "groq,mock"
+    "ollama"
+    "ollama,mock"
+    "openrouter"
+    "openrouter,mock"
+    "cohere"
+    "cohere,mock"
+    "together"
+    "together,mock"
+    "fireworks"
+    "fireworks,mock"
+    "perplexity"
+    "perplexity,mock"
+    "compatible"
+    "compatible,mock"
+    "tower"
+    "macros"
+    "openai,mock,tower"
+    "openai,anthropic,gemini"
+    "live"
+)
+
+run() {
+    echo "+ $*"
+    "$@"
+}
+
+for features in "${feature_sets[@]}"; do
+    echo "=== features: [${features}]"
+    flags=(-p wire --no-default-features --features "${features}" "$@")
+
+    run cargo build "${flags[@]}"
+    run cargo clippy "${flags[@]}" --all-targets -- -D warnings
+    run cargo test "${flags[@]}" -- --test-threads=1
+done
+
+echo "=== features: default"
+run cargo clippy -p wire --all-targets "$@" -- -D warnings
+run cargo test -p wire "$@" -- --test-threads=1